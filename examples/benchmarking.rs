@@ -1,33 +1,39 @@
 #![allow(dead_code)]
 
-use std::collections::{BTreeMap, HashMap};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
-use serde::Deserialize;
+use clap::Parser;
 use verbum::benchmark;
 use verbum::benchmark::reachability_benchmark_pairs_with_scheduler;
 use verbum::language::expression::load_expressions_from_file;
 use verbum::rewriting::egraph::matching::top_down::TopDownMatcher;
 use verbum::rewriting::egraph::saturation::scheduler::RoundRobinScheduler;
-use verbum::{
-    rewriting::{
-        egraph::{
-            class::simple_math_local_cost::SimpleMathLocalCost,
-            extraction::{SimpleExtractor, children_cost_sum},
-            matching::bottom_up::BottomUpMatcher,
-            saturation::{
-                SaturationConfig, SimpleSaturator, directed_saturator::DirectedSaturator,
-            },
-        },
-        system::TermRewritingSystem,
+use verbum::rewriting::{
+    egraph::{
+        class::simple_math_local_cost::SimpleMathLocalCost,
+        extraction::TableExtractor,
+        matching::bottom_up::BottomUpMatcher,
+        saturation::{SaturationConfig, SimpleSaturator, directed_saturator::DirectedSaturator},
     },
-    utils,
+    system::TermRewritingSystem,
 };
 
-// Helper struct for loading costs from JSON
-#[derive(Deserialize)]
-struct CostsFile {
-    costs: HashMap<String, usize>,
+/// CLI arguments for saturation benchmarks
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Benchmark saturation of simple math expressions", long_about = None)]
+struct Args {
+    /// JSON results of a previous run to compare against
+    #[arg(short = 'b', long)]
+    baseline: Option<PathBuf>,
+
+    /// File to save JSON results of this run to, usable as a baseline later
+    #[arg(short = 'o', long)]
+    output: Option<PathBuf>,
+
+    /// Relative slowdown above which an outcome is reported as a regression
+    #[arg(short = 't', long, default_value_t = 0.1)]
+    threshold: f64,
 }
 
 fn initialize_system() -> TermRewritingSystem {
@@ -39,10 +45,11 @@ fn initialize_system() -> TermRewritingSystem {
 }
 
 fn main() {
+    let args = Args::parse();
     let trs = initialize_system();
     let lang = trs.language();
 
-    // Load expressions and costs
+    // Load expressions, costs are loaded with the system
     let mut base_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
     base_path.push("jsons");
     base_path.push("simple-math");
@@ -50,10 +57,6 @@ fn main() {
     let expr_path = base_path.join("small.json");
     let expressions = load_expressions_from_file(expr_path, lang).unwrap();
 
-    let costs_path = base_path.join("costs.json");
-    let costs_file: CostsFile = utils::json::load_json(costs_path).unwrap();
-    let costs = costs_file.costs;
-
     let config = benchmark::BenchmarkConfig {
         saturation_config: SaturationConfig {
             max_nodes: Some(1000),
@@ -61,38 +64,25 @@ fn main() {
             max_applications: Some(1000),
             ..Default::default()
         },
+        sampling: benchmark::SamplingConfig::default(),
     };
 
-    let extractor = SimpleExtractor::<usize, _, _>::new(
-        |_| 1,
-        |symbol, children_costs| {
-            let symbol_name = lang.get_symbol(symbol.id);
-            let symbol_cost = costs.get(symbol_name).copied().unwrap_or(0);
-            Some(symbol_cost + children_cost_sum(symbol, children_costs)?)
-        },
-    );
+    let extractor = TableExtractor::new(trs.costs().unwrap(), lang);
 
     let simple_saturator = SimpleSaturator::new(Box::new(BottomUpMatcher));
     let simple_outcomes =
-        benchmark::benchmark::<(), _>(&trs, &expressions, &config, &extractor, &simple_saturator);
-
-    let extractor_2 = SimpleExtractor::<usize, _, _>::new(
-        |_| 1,
-        |symbol, children_costs| {
-            let symbol_name = lang.get_symbol(symbol.id);
-            let symbol_cost = costs.get(symbol_name).copied().unwrap_or(0);
-            Some(symbol_cost + children_cost_sum(symbol, children_costs)?)
-        },
-    );
+        benchmark::benchmark::<(), _>(&trs, &expressions, &config, &extractor, &simple_saturator)
+            .unwrap();
 
     let directed_saturator = DirectedSaturator::new(Box::new(BottomUpMatcher));
-    let directed_outcomes = benchmark::benchmark::<SimpleMathLocalCost, SimpleExtractor<usize, _, _>>(
+    let directed_outcomes = benchmark::benchmark::<SimpleMathLocalCost, _>(
         &trs,
         &expressions,
         &config,
-        &extractor_2,
+        &extractor,
         &directed_saturator,
-    );
+    )
+    .unwrap();
 
     let map = BTreeMap::from([
         (String::from("Simple"), simple_outcomes),
@@ -100,17 +90,54 @@ fn main() {
     ]);
 
     use benchmark::{
-        OutcomeFormatter, csv_output::CsvOutputFormatter, pretty_printing::PrettyTableFormatter,
+        CsvOutputFormatter, JsonOutputFormatter, MarkdownTableFormatter, OutcomeFormatter,
+        PrettyTableFormatter, comparison, formatter::PrettyFormatter,
     };
 
     let pretty_formatter = PrettyTableFormatter;
     let pretty_output = pretty_formatter.format_saturator_outcomes(map.clone());
     println!("{pretty_output}");
 
+    for (name, outcomes) in &map {
+        let rules = pretty_formatter.format_rule_outcomes(&benchmark::rule_totals(outcomes));
+        println!("\n--- Rules for Saturator: {name} ---\n\n{rules}");
+    }
+
     let csv_formatter = CsvOutputFormatter;
-    let csv_output = csv_formatter.format_saturator_outcomes(map);
+    let csv_output = csv_formatter.format_saturator_outcomes(map.clone());
     println!("\nCSV Output:\n{csv_output}");
 
+    let markdown_output = MarkdownTableFormatter.format_saturator_outcomes(map.clone());
+    println!("\nMarkdown Output:\n\n{markdown_output}");
+
+    let json_formatter = JsonOutputFormatter::new().with_config(&config).unwrap();
+    let json_output = json_formatter.format_saturator_outcomes(map.clone());
+    println!("\nJSON Output:\n{json_output}");
+
+    if let Some(output) = &args.output {
+        std::fs::write(output, &json_output).unwrap();
+        println!("\nSaved results to {output:?}");
+    }
+
+    if let Some(baseline) = &args.baseline {
+        let baseline = comparison::BenchmarkResults::load(baseline).unwrap();
+        let current = comparison::BenchmarkResults::from_outcomes(&map);
+        let comparison_config = comparison::ComparisonConfig {
+            time_threshold: args.threshold,
+            ..Default::default()
+        };
+        let report = comparison::compare(&baseline, &current, &comparison_config);
+
+        println!("\nComparison with baseline:");
+        println!("{}", PrettyFormatter::format(&report.comparisons));
+        for (saturator, expression) in &report.unmatched {
+            println!("Not present in both runs: {saturator} {expression}");
+        }
+        if report.has_regressions() {
+            println!("\n{} regressions found", report.regressions().count());
+        }
+    }
+
     // Demonstrate reachability benchmarking (current: round-robin scheduler injected locally)
     let reach_pairs = vec![
         (
@@ -132,7 +159,10 @@ fn main() {
         &reach_pairs,
         &reach_cfg,
         &TopDownMatcher,
-        3,
+        &benchmark::SamplingConfig {
+            runs: 3,
+            ..Default::default()
+        },
         |rs| Box::new(RoundRobinScheduler::new(rs.to_vec())),
     );
     println!("\nReachability Outcomes:");
@@ -6,6 +6,7 @@ use verbum::{
         egraph::{
             EGraph,
             class::simple_math_local_cost::SimpleMathLocalCost,
+            drawing::DotConfig,
             matching::bottom_up::BottomUpMatcher,
             saturation::{SaturationConfig, Saturator, SimpleSaturator},
         },
@@ -87,4 +88,15 @@ fn test_saturation_and_dot_output() {
     saturator.saturate(&mut egraph, &rules, &config);
 
     egraph.save_dot(&lang, "output.dot").unwrap();
+    egraph.save_graphml(&lang, "output.graphml").unwrap();
+
+    let before = egraph.clone();
+    let rules = macros::rules!(lang; "(sin $0)" => "(cos $0)");
+    saturator.saturate(&mut egraph, &rules, &config);
+    let config = DotConfig::default()
+        .with_clusters(false)
+        .with_highlighted_classes(egraph.merged_since(&before));
+    egraph
+        .save_dot_with_config(&lang, &config, "output_merged.dot")
+        .unwrap();
 }
@@ -10,18 +10,10 @@
 use clap::Parser;
 use std::error::Error;
 use std::path::PathBuf;
-use std::time::Instant;
-use verbum::benchmark::{
-    RandomGenerationConfig, VariableGenerationConfig,
-    generate_random_expression_by_size_with_variables,
+use std::time::Duration;
+use verbum::rewriting::experiments::{
+    Experiment, HeuristicEvaluation, HeuristicExperimentConfig, run_heuristic_experiment_on,
 };
-use verbum::language::arities::Arities;
-use verbum::language::expression::AnyExpression;
-use verbum::rewriting::heuristic::{AbelianPathHeuristic, Heuristic};
-use verbum::rewriting::random::rewrite_expression;
-use verbum::rewriting::system::TermRewritingSystem;
-use verbum::utils::json::load_json;
-
 /// CLI arguments for heuristic benchmark
 #[derive(Parser, Debug)]
 #[command(author, version, about = "Benchmark heuristic evaluation", long_about = None)]
@@ -41,125 +33,85 @@ struct Args {
     /// Number of random rewrite applications (a)
     #[arg(short = 'a', long)]
     applications: usize,
+
+    /// Maximum line width when printing expressions
+    #[arg(short = 'w', long, default_value_t = 100)]
+    width: usize,
+
+    /// Seed of the random choices, a random one is used and printed if not given
+    #[arg(short = 's', long)]
+    seed: Option<u64>,
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let args = Args::parse();
 
-    // Load TRS and language
-    println!("Loading TRS from {:?}...", args.trs);
-    let trs = TermRewritingSystem::from_directory(&args.trs)?;
-    let lang = trs.language();
-    let rules = trs.rules();
+    let mut config =
+        HeuristicExperimentConfig::new(&args.trs, args.size, args.variables, args.applications);
+    config.seed = args.seed;
 
-    // Load arities
-    let arities_path = args.trs.join("arities.json");
-    println!("Loading arities from {:?}...", arities_path);
-    let arities: Arities = load_json(&arities_path)?;
+    // Load TRS, language and arities
+    println!("Loading TRS from {:?}...", args.trs);
+    let mut experiment = Experiment::new(&config)?;
+    let lang = experiment.trs().language().clone();
+    println!("Using seed {}", experiment.seed());
 
-    // Generate random expression E
+    // Generate E and F, rewrite E into E' and evaluate the heuristic of E'
     println!(
         "Generating random expression of size {} with up to {} variables...",
         args.size, args.variables
     );
-    let mut rng = rand::thread_rng();
-
-    let mut config = RandomGenerationConfig::from_language(lang);
-
-    // Set symbol arities
-    for symbol_id in 0..lang.symbol_count() {
-        if let Some(symbol_arities) = arities.get(symbol_id) {
-            config.symbol_arities[symbol_id] = symbol_arities.to_vec();
-        }
-    }
-
-    // Configure variable generation
-    if args.variables > 0 {
-        config.variable_config = Some(VariableGenerationConfig {
-            variable_range: (0, args.variables - 1),
-            variable_probability: 0.5,
-        });
-    }
-
-    let expr_e =
-        generate_random_expression_by_size_with_variables(lang, args.size, &mut rng, &config)?;
-    let expr_f =
-        generate_random_expression_by_size_with_variables(lang, args.size, &mut rng, &config)?;
-
-    println!("Generated expression E: {}", expr_e.with_language(lang));
-
-    // Apply random rewrites to create E'
     println!("Applying {} random rewrites...", args.applications);
-    let expr_e_prime = rewrite_expression(expr_e.clone(), rules, args.applications, &mut rng);
-
-    println!(
-        "Rewritten expression E': {}",
-        expr_e_prime.with_language(lang)
-    );
+    let result = run_heuristic_experiment_on(&mut experiment)?;
 
-    // Create heuristic for E'
-    println!("\nCreating heuristic for target expression E'...");
-    let heuristic_start = Instant::now();
-    let heuristic = AbelianPathHeuristic::new(&expr_e_prime, &trs, &arities);
-    let heuristic_construction_time = heuristic_start.elapsed();
     println!(
-        "Heuristic construction time: {:.3}ms",
-        heuristic_construction_time.as_secs_f64() * 1000.0
+        "Generated expression E:\n{}",
+        lang.format(&result.pair.from, args.width)
     );
-
-    // Time heuristic evaluation for starting expression E
-    println!("\nEvaluating heuristic for starting expression E...");
-    let eval_start = Instant::now();
-    let distance_e = heuristic.lower_bound_dist(&expr_e);
-    let eval_time_e = eval_start.elapsed();
     println!(
-        "Heuristic h(E): {} (computed in {:.3}ms)",
-        distance_e,
-        eval_time_e.as_secs_f64() * 1000.0
+        "Rewritten expression E':\n{}",
+        lang.format(&result.pair.to, args.width)
     );
-
-    // Time heuristic evaluation for rewritten expression E'
-    println!("\nEvaluating heuristic for rewritten expression E'...");
-    let eval_start = Instant::now();
-    let distance_e_prime = heuristic.lower_bound_dist(&expr_e_prime);
-    let eval_time_e_prime = eval_start.elapsed();
     println!(
-        "Heuristic h(E'): {} (computed in {:.3}ms)",
-        distance_e_prime,
-        eval_time_e_prime.as_secs_f64() * 1000.0
+        "Unrelated expression F:\n{}",
+        lang.format(&result.unrelated, args.width)
     );
 
-    // Time heuristic evaluation for rewritten expression F
-    println!("\nEvaluating heuristic for rewritten expression F (unrelated to E and E')...");
-    let eval_start = Instant::now();
-    let distance_f = heuristic.lower_bound_dist(&expr_f);
-    let eval_time_f = eval_start.elapsed();
     println!(
-        "Heuristic h(F): {} (computed in {:.3}ms)",
-        distance_f,
-        eval_time_f.as_secs_f64() * 1000.0
+        "\nHeuristic construction time for target expression E': {}",
+        millis(result.construction_time)
     );
+    print_evaluation("h(E)", &result.from);
+    print_evaluation("h(E')", &result.to);
+    print_evaluation("h(F)", &result.unrelated_evaluation);
 
     println!("\n=== Summary ===");
     println!("Expression size: {}", args.size);
     println!("Variables: {}", args.variables);
     println!("Rewrite applications: {}", args.applications);
     println!(
-        "Heuristic construction: {:.3}ms",
-        heuristic_construction_time.as_secs_f64() * 1000.0
-    );
-    println!(
-        "h(E) evaluation: {:.3}ms",
-        eval_time_e.as_secs_f64() * 1000.0
+        "Heuristic construction: {}",
+        millis(result.construction_time)
     );
+    println!("h(E) evaluation: {}", millis(result.from.time));
+    println!("h(E') evaluation: {}", millis(result.to.time));
     println!(
-        "h(E') evaluation: {:.3}ms",
-        eval_time_e_prime.as_secs_f64() * 1000.0
+        "h(F) evaluation: {}",
+        millis(result.unrelated_evaluation.time)
     );
+
+    Ok(())
+}
+
+fn print_evaluation(name: &str, evaluation: &HeuristicEvaluation) {
     println!(
-        "h(F) evaluation: {:.3}ms",
-        eval_time_f.as_secs_f64() * 1000.0
+        "Heuristic {}: {} (computed in {})",
+        name,
+        evaluation.distance,
+        millis(evaluation.time)
     );
+}
 
-    Ok(())
+fn millis(duration: Duration) -> String {
+    format!("{:.3}ms", duration.as_secs_f64() * 1000.0)
 }
@@ -1,10 +1,12 @@
-// Example demonstrating the random expression generation features
+// Example demonstrating the random expression generation features.
+// A seed can be given as the first argument to repeat a run.
 
 use verbum::benchmark::{
     LiteralGenerationConfig, RandomGenerationConfig,
     generate_random_expression_by_size_with_config, generate_random_expression_with_config,
 };
 use verbum::language::Language;
+use verbum::utils::rng::seeded_rng;
 
 fn default_math_config(lang: &Language) -> RandomGenerationConfig {
     let mut config = RandomGenerationConfig::from_language(lang);
@@ -22,7 +24,11 @@ fn default_math_config(lang: &Language) -> RandomGenerationConfig {
 
 fn main() {
     let lang = Language::simple_math();
-    let mut rng = rand::thread_rng();
+    let seed = std::env::args()
+        .nth(1)
+        .map(|seed| seed.parse().expect("Seed is a number"));
+    let (mut rng, seed) = seeded_rng(seed);
+    println!("Using seed {}", seed);
     let config = default_math_config(&lang);
 
     println!("=== Depth-Based Generation ===");
@@ -2,12 +2,20 @@
 //!
 //! This example shows how to use the random rewriter to perform
 //! destructive term rewriting on expressions.
+//! A seed can be given as the first argument to repeat a run.
 
 use verbum::language::Language;
 use verbum::rewriting::random::rewrite;
 use verbum::rewriting::rule::Rule;
+use verbum::utils::rng::seeded_rng;
 
 fn main() {
+    let seed = std::env::args()
+        .nth(1)
+        .map(|seed| seed.parse().expect("Seed is a number"));
+    let (mut rng, seed) = seeded_rng(seed);
+    println!("Using seed {}", seed);
+
     // Create a simple math language
     let lang = Language::simple_math();
 
@@ -30,20 +38,20 @@ fn main() {
     println!("Original expression: {}", expr);
 
     // Apply random rewrites
-    let rewritten = rewrite(expr.clone(), &rules, 5);
+    let rewritten = rewrite(expr.clone(), &rules, 5, &mut rng);
     println!("After 5 random rewrites: {}", rewritten);
 
     // Try another example with multiplication
     let expr2 = lang.parse_no_vars("(* 2 (+ 3 4))").unwrap();
     println!("\nOriginal expression: {}", expr2);
 
-    let rewritten2 = rewrite(expr2, &rules, 10);
+    let rewritten2 = rewrite(expr2, &rules, 10, &mut rng);
     println!("After 10 random rewrites: {}", rewritten2);
 
     // Example with nested operations
     let expr3 = lang.parse_no_vars("(+ 0 (+ 0 (+ 0 5)))").unwrap();
     println!("\nOriginal expression: {}", expr3);
 
-    let rewritten3 = rewrite(expr3, &rules, 3);
+    let rewritten3 = rewrite(expr3, &rules, 3, &mut rng);
     println!("After 3 random rewrites: {}", rewritten3);
 }
@@ -0,0 +1,514 @@
+//! Benchmark campaigns described by configuration files.
+//!
+//! A campaign is a JSON file listing term rewriting systems, expressions to saturate,
+//! saturators to compare, limits and outputs, for example
+//!
+//! ```json
+//! {
+//!   "systems": ["simple-math", "arithmetic"],
+//!   "expressions": [
+//!     { "file": "simple-math/small.json" },
+//!     { "random": { "count": 20, "size": 9, "seed": 1 } }
+//!   ],
+//!   "saturators": ["simple", "directed", { "fair_class": { "per_class": 2 } }],
+//!   "matcher": "bottom_up",
+//!   "saturation": { "max_nodes": 10000, "time_limit": { "secs": 1, "nanos": 0 } },
+//!   "sampling": { "runs": 5 },
+//!   "outputs": [{ "format": "markdown", "path": "results.md" }, { "format": "pretty" }]
+//! }
+//! ```
+//!
+//! Every saturator is benchmarked on all expressions in every system, see [`Campaign::run`].
+//! Relative paths are resolved against the directory of the campaign file.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::io::Write;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use clap::ValueEnum;
+use rand::{SeedableRng, rngs::StdRng};
+use serde::Deserialize;
+
+use crate::language::{
+    arities::Arities,
+    expression::{VarFreeExpression, load_expressions_from_file},
+};
+use crate::rewriting::egraph::{
+    EGraph,
+    class::{local_cost::LocalCost, size_local_cost::SizeLocalCost},
+    extraction::TableExtractor,
+    matching::{Matcher, bottom_up::BottomUpMatcher, top_down::TopDownMatcher},
+    saturation::{
+        SaturationConfig, SaturationReport, Saturator, SimpleSaturator,
+        directed_saturator::DirectedSaturator,
+        scheduled_saturator::ScheduledSaturator,
+        scheduler::{FairClassScheduler, RoundRobinScheduler, Scheduler},
+    },
+};
+use crate::rewriting::{rule::Rule, system::TermRewritingSystem};
+use crate::utils::json::load_json;
+
+use super::{
+    BenchmarkConfig, BenchmarkError, CsvOutputFormatter, JsonOutputFormatter,
+    MarkdownTableFormatter, Outcome, OutcomeFormatter, PrettyTableFormatter,
+    RandomGenerationConfig, SamplingConfig, benchmark,
+    generate_random_expression_by_size_with_config,
+};
+
+/// Outcomes of a campaign, by names of systems and then names of saturators.
+pub type CampaignOutcomes = BTreeMap<String, BTreeMap<String, Vec<Outcome>>>;
+
+/// A matrix of benchmarks, usually loaded with [`Campaign::load`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct Campaign {
+    /// Directories of term rewriting systems, see [`TermRewritingSystem::from_directory`]
+    pub systems: Vec<PathBuf>,
+    /// Expressions benchmarked in every system
+    pub expressions: Vec<ExpressionSource>,
+    /// Saturators to compare
+    pub saturators: Vec<SaturatorKind>,
+    /// Matcher used by all saturators
+    #[serde(default)]
+    pub matcher: MatcherKind,
+    /// Limits of every saturation
+    #[serde(default)]
+    pub saturation: SaturationConfig,
+    /// How the saturations are timed
+    #[serde(default)]
+    pub sampling: SamplingConfig,
+    /// Where the outcomes are written, they are printed in the pretty format if empty
+    #[serde(default)]
+    pub outputs: Vec<CampaignOutput>,
+}
+
+/// Expressions benchmarked in a campaign.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpressionSource {
+    /// JSON file with a list of `expressions`, see [`load_expressions_from_file`]
+    File(PathBuf),
+    /// `count` random expressions of size `size`, generated from `seed` with the arities
+    /// given in `arities.json` in the directory of the system
+    Random {
+        count: usize,
+        size: usize,
+        seed: u64,
+    },
+}
+
+/// Saturators which can be compared in a campaign, where all of them use [`SizeLocalCost`],
+/// or on the command line, where they are given by their names, see [`SaturatorKind::name`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SaturatorKind {
+    /// [`SimpleSaturator`]
+    Simple,
+    /// [`DirectedSaturator`]
+    Directed,
+    /// Saturation with a [`RoundRobinScheduler`]
+    RoundRobin,
+    /// Saturation with a [`FairClassScheduler`]
+    FairClass { per_class: NonZeroUsize },
+}
+
+impl SaturatorKind {
+    /// Name of the saturator in the outcomes.
+    pub fn name(&self) -> String {
+        match self {
+            SaturatorKind::Simple => String::from("simple"),
+            SaturatorKind::Directed => String::from("directed"),
+            SaturatorKind::RoundRobin => String::from("round_robin"),
+            SaturatorKind::FairClass { per_class } => format!("fair_class({per_class})"),
+        }
+    }
+
+    /// Returns a new saturator of this kind finding matches with `matcher`.
+    /// The directed saturator orders applications by the local costs of `LC`.
+    pub fn saturator<LC: LocalCost + 'static>(
+        &self,
+        matcher: MatcherKind,
+    ) -> Box<dyn Saturator<LC>> {
+        match *self {
+            SaturatorKind::Simple => Box::new(SimpleSaturator::new(matcher.matcher())),
+            SaturatorKind::Directed => Box::new(DirectedSaturator::new(matcher.matcher())),
+            kind => Box::new(SchedulerSaturator { kind, matcher }),
+        }
+    }
+}
+
+impl fmt::Display for SaturatorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for SaturatorKind {
+    type Err = UnknownSaturatorError;
+
+    /// Parses a saturator from its name, see [`SaturatorKind::name`].
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "simple" => Ok(SaturatorKind::Simple),
+            "directed" => Ok(SaturatorKind::Directed),
+            "round_robin" => Ok(SaturatorKind::RoundRobin),
+            _ => name
+                .strip_prefix("fair_class(")
+                .and_then(|rest| rest.strip_suffix(')'))
+                .and_then(|per_class| per_class.parse().ok())
+                .map(|per_class| SaturatorKind::FairClass { per_class })
+                .ok_or_else(|| UnknownSaturatorError(name.to_string())),
+        }
+    }
+}
+
+/// Error of parsing a [`SaturatorKind`] from a name which is not a name of any saturator.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownSaturatorError(pub String);
+
+impl fmt::Display for UnknownSaturatorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Unknown saturator `{}`, expected one of simple, directed, round_robin \
+             or fair_class(N) with positive N",
+            self.0
+        )
+    }
+}
+
+impl Error for UnknownSaturatorError {}
+
+/// Runs a [`ScheduledSaturator`] with a new scheduler for every saturation.
+struct SchedulerSaturator {
+    kind: SaturatorKind,
+    matcher: MatcherKind,
+}
+
+impl<LC: LocalCost + 'static> Saturator<LC> for SchedulerSaturator {
+    fn saturate(
+        &self,
+        egraph: &mut EGraph<LC>,
+        rules: &[Rule],
+        config: &SaturationConfig,
+    ) -> SaturationReport {
+        let scheduler: Box<dyn Scheduler<LC>> = match self.kind {
+            SaturatorKind::FairClass { per_class } => {
+                Box::new(FairClassScheduler::new(rules.to_vec(), per_class))
+            }
+            _ => Box::new(RoundRobinScheduler::new(rules.to_vec())),
+        };
+        ScheduledSaturator::new(scheduler).run(egraph, config, &*self.matcher.matcher())
+    }
+}
+
+/// Matchers which can be used in a campaign or on the command line.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum MatcherKind {
+    /// Matches patterns from their roots down to their leaves
+    TopDown,
+    /// Assigns classes to variables and looks patterns up from their leaves
+    #[default]
+    BottomUp,
+}
+
+impl MatcherKind {
+    /// Returns a matcher of this kind.
+    pub fn matcher(self) -> Box<dyn Matcher> {
+        match self {
+            MatcherKind::TopDown => Box::new(TopDownMatcher),
+            MatcherKind::BottomUp => Box::new(BottomUpMatcher),
+        }
+    }
+}
+
+/// Formats of benchmark outcomes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    /// Tables for terminals
+    Pretty,
+    /// Comma-separated values
+    Csv,
+    /// Markdown tables
+    Markdown,
+    /// JSON including the configuration of the benchmarks
+    Json,
+}
+
+impl OutputFormat {
+    /// Returns a formatter of outcomes in this format of benchmarks run with `config`.
+    pub fn formatter(
+        self,
+        config: &BenchmarkConfig,
+    ) -> serde_json::Result<Box<dyn OutcomeFormatter>> {
+        Ok(match self {
+            OutputFormat::Pretty => Box::new(PrettyTableFormatter),
+            OutputFormat::Csv => Box::new(CsvOutputFormatter),
+            OutputFormat::Markdown => Box::new(MarkdownTableFormatter),
+            OutputFormat::Json => Box::new(JsonOutputFormatter::new().with_config(config)?),
+        })
+    }
+}
+
+/// An output of a campaign.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct CampaignOutput {
+    /// Format of the outcomes
+    pub format: OutputFormat,
+    /// File to write the outcomes to, they are printed if there is none
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+}
+
+impl Campaign {
+    /// Loads a campaign from a JSON file, resolving relative paths against its directory.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let mut campaign: Campaign = load_json(path)?;
+        let base = path.parent().unwrap_or(Path::new(""));
+
+        for system in &mut campaign.systems {
+            *system = base.join(&*system);
+        }
+        for source in &mut campaign.expressions {
+            if let ExpressionSource::File(file) = source {
+                *file = base.join(&*file);
+            }
+        }
+        for output in &mut campaign.outputs {
+            if let Some(path) = &mut output.path {
+                *path = base.join(&*path);
+            }
+        }
+
+        Ok(campaign)
+    }
+
+    /// Configuration of the benchmarks of the campaign.
+    pub fn benchmark_config(&self) -> BenchmarkConfig {
+        BenchmarkConfig {
+            saturation_config: self.saturation.clone(),
+            sampling: self.sampling.clone(),
+        }
+    }
+
+    /// Benchmarks every saturator on the expressions of every system.
+    ///
+    /// Systems are named by their directories. Expressions are extracted using the costs
+    /// of the system, or by their size if it has none.
+    pub fn run(&self) -> Result<CampaignOutcomes, Box<dyn Error>> {
+        let config = self.benchmark_config();
+        let mut outcomes = CampaignOutcomes::new();
+
+        for system in &self.systems {
+            let trs = TermRewritingSystem::from_directory(system)?;
+            let expressions = self.load_expressions(system, &trs)?;
+            let extractor = TableExtractor::new(&trs.costs_or_sizes(), trs.language());
+
+            let system_outcomes = self
+                .saturators
+                .iter()
+                .map(|kind| {
+                    let saturator = kind.saturator::<SizeLocalCost>(self.matcher);
+                    let outcomes = benchmark(&trs, &expressions, &config, &extractor, &*saturator)?;
+                    Ok((kind.name(), outcomes))
+                })
+                .collect::<Result<_, BenchmarkError>>()?;
+            outcomes.insert(system_name(system), system_outcomes);
+        }
+
+        Ok(outcomes)
+    }
+
+    /// Writes `outcomes` of the campaign to its outputs, the ones without a path to `out`.
+    ///
+    /// The outcomes of all systems are formatted together, with the saturators
+    /// named `system: saturator`.
+    pub fn write_outputs(
+        &self,
+        outcomes: &CampaignOutcomes,
+        out: &mut dyn Write,
+    ) -> Result<(), Box<dyn Error>> {
+        let flattened: BTreeMap<_, _> = outcomes
+            .iter()
+            .flat_map(|(system, saturators)| {
+                saturators.iter().map(move |(saturator, outcomes)| {
+                    (format!("{system}: {saturator}"), outcomes.clone())
+                })
+            })
+            .collect();
+        let default_output = [CampaignOutput {
+            format: OutputFormat::Pretty,
+            path: None,
+        }];
+        let outputs = if self.outputs.is_empty() {
+            &default_output[..]
+        } else {
+            &self.outputs
+        };
+
+        for output in outputs {
+            let formatted = output
+                .format
+                .formatter(&self.benchmark_config())?
+                .format_saturator_outcomes(flattened.clone());
+
+            match &output.path {
+                Some(path) => std::fs::write(path, formatted)?,
+                None => writeln!(out, "{formatted}")?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn load_expressions(
+        &self,
+        system: &Path,
+        trs: &TermRewritingSystem,
+    ) -> Result<Vec<VarFreeExpression>, Box<dyn Error>> {
+        let mut expressions = Vec::new();
+
+        for source in &self.expressions {
+            match source {
+                ExpressionSource::File(file) => {
+                    expressions.extend(load_expressions_from_file(file, trs.language())?);
+                }
+                ExpressionSource::Random { count, size, seed } => {
+                    let arities: Arities = load_json(system.join("arities.json"))?;
+                    let config = arities.map.iter().fold(
+                        RandomGenerationConfig::from_language(trs.language()),
+                        |config, (&symbol_id, arities)| {
+                            config.with_symbol_arities(symbol_id, arities.clone())
+                        },
+                    );
+                    let mut rng = StdRng::seed_from_u64(*seed);
+                    for _ in 0..*count {
+                        expressions.push(generate_random_expression_by_size_with_config(
+                            trs.language(),
+                            *size,
+                            &mut rng,
+                            &config,
+                        )?);
+                    }
+                }
+            }
+        }
+
+        Ok(expressions)
+    }
+}
+
+fn system_name(system: &Path) -> String {
+    system
+        .file_name()
+        .unwrap_or(system.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+    use std::path::Path;
+
+    use super::{Campaign, ExpressionSource, MatcherKind, SaturatorKind};
+
+    #[test]
+    fn loads_and_runs_campaign() {
+        let jsons = Path::new(env!("CARGO_MANIFEST_DIR")).join("jsons");
+        let path = std::env::temp_dir().join(format!("campaign-{}.json", std::process::id()));
+        std::fs::write(
+            &path,
+            serde_json::json!({
+                "systems": [jsons.join("simple-math")],
+                "expressions": [
+                    { "file": jsons.join("simple-math/small.json") },
+                    { "random": { "count": 3, "size": 5, "seed": 7 } }
+                ],
+                "saturators": ["simple", "round_robin", { "fair_class": { "per_class": 1 } }],
+                "saturation": { "max_applications": 20 },
+                "sampling": { "warm_up_runs": 0, "runs": 1 }
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let campaign = Campaign::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(campaign.matcher, MatcherKind::BottomUp);
+        assert_eq!(
+            campaign.saturators[2],
+            SaturatorKind::FairClass {
+                per_class: NonZeroUsize::MIN
+            }
+        );
+        assert!(
+            serde_json::from_value::<SaturatorKind>(
+                serde_json::json!({ "fair_class": { "per_class": 0 } })
+            )
+            .is_err()
+        );
+        assert!(matches!(
+            campaign.expressions[1],
+            ExpressionSource::Random { seed: 7, .. }
+        ));
+
+        let outcomes = campaign.run().unwrap();
+        let saturators = &outcomes["simple-math"];
+        assert_eq!(
+            saturators.keys().collect::<Vec<_>>(),
+            ["fair_class(1)", "round_robin", "simple"]
+        );
+        let small = crate::utils::json::load_json::<serde_json::Value, _>(
+            jsons.join("simple-math/small.json"),
+        )
+        .unwrap();
+        let file_count = small["expressions"].as_array().unwrap().len();
+        assert!(
+            saturators
+                .values()
+                .all(|outcomes| outcomes.len() == file_count + 3)
+        );
+
+        // Expressions are generated deterministically
+        let rerun = campaign.run().unwrap();
+        let originals = |outcomes: &super::CampaignOutcomes| {
+            outcomes["simple-math"]["simple"]
+                .iter()
+                .map(|outcome| outcome.original_expression.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(originals(&outcomes), originals(&rerun));
+
+        let mut out = Vec::new();
+        campaign.write_outputs(&outcomes, &mut out).unwrap();
+        assert!(
+            String::from_utf8(out)
+                .unwrap()
+                .contains("simple-math: fair_class(1)")
+        );
+    }
+
+    #[test]
+    fn parses_saturator_names() {
+        let kinds = [
+            SaturatorKind::Simple,
+            SaturatorKind::Directed,
+            SaturatorKind::RoundRobin,
+            SaturatorKind::FairClass {
+                per_class: NonZeroUsize::new(3).unwrap(),
+            },
+        ];
+        for kind in kinds {
+            assert_eq!(kind.name().parse::<SaturatorKind>(), Ok(kind));
+        }
+        assert!("fair_class(0)".parse::<SaturatorKind>().is_err());
+        assert!("Simple".parse::<SaturatorKind>().is_err());
+    }
+}
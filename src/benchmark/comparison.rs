@@ -0,0 +1,321 @@
+//! Comparison of benchmark results with a baseline.
+//!
+//! Results of a previous run are loaded from a file written with
+//! [`JsonOutputFormatter`](super::JsonOutputFormatter) and compared outcome by outcome
+//! with the current ones, flagging slowdowns and growth of e-graphs beyond a threshold.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::Path;
+use tabled::{Table, Tabled};
+
+use super::Outcome;
+use super::formatter::Formattable;
+use crate::rewriting::egraph::saturation::SaturationStopReason;
+
+/// Outcome of a single saturation as stored in benchmark results.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedOutcome {
+    #[serde(rename = "Original Expression")]
+    pub expression: String,
+    #[serde(rename = "Time (ns)")]
+    pub time_ns: u128,
+    #[serde(rename = "Stop Reason")]
+    pub stop_reason: String,
+    #[serde(rename = "Nodes")]
+    pub nodes: usize,
+    #[serde(rename = "Classes")]
+    pub classes: usize,
+}
+
+impl From<&Outcome> for RecordedOutcome {
+    fn from(outcome: &Outcome) -> Self {
+        Self {
+            expression: outcome.original_expression.to_string(),
+            time_ns: outcome.time.as_nanos(),
+            stop_reason: format!("{:?}", outcome.stop_reason),
+            nodes: outcome.nodes,
+            classes: outcome.classes,
+        }
+    }
+}
+
+/// Outcomes of a benchmark run, grouped by saturator name.
+/// Outcomes which were not grouped are stored under an empty name.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BenchmarkResults {
+    pub saturators: BTreeMap<String, Vec<RecordedOutcome>>,
+}
+
+/// Layout of files written by [`JsonOutputFormatter`](super::JsonOutputFormatter).
+#[derive(Deserialize)]
+struct ResultsFile {
+    #[serde(default)]
+    outcomes: Option<Vec<RecordedOutcome>>,
+    #[serde(default)]
+    saturators: Option<BTreeMap<String, Vec<RecordedOutcome>>>,
+}
+
+impl BenchmarkResults {
+    /// Records outcomes of the current run.
+    pub fn from_outcomes(outcomes_map: &BTreeMap<String, Vec<Outcome>>) -> Self {
+        Self {
+            saturators: outcomes_map
+                .iter()
+                .map(|(name, outcomes)| (name.clone(), outcomes.iter().map(Into::into).collect()))
+                .collect(),
+        }
+    }
+
+    /// Parses results written by [`JsonOutputFormatter`](super::JsonOutputFormatter).
+    pub fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+        let file: ResultsFile = serde_json::from_str(json)?;
+        let mut saturators = file.saturators.unwrap_or_default();
+        if let Some(outcomes) = file.outcomes {
+            saturators.insert(String::new(), outcomes);
+        }
+        Ok(Self { saturators })
+    }
+
+    /// Loads results written by [`JsonOutputFormatter`](super::JsonOutputFormatter) from a file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+}
+
+/// Thresholds above which changes are considered regressions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ComparisonConfig {
+    /// Maximum allowed relative increase of time, e.g. `0.1` for 10%
+    pub time_threshold: f64,
+    /// Maximum allowed relative increase of the number of nodes
+    pub node_threshold: f64,
+}
+
+impl Default for ComparisonConfig {
+    fn default() -> Self {
+        Self {
+            time_threshold: 0.1,
+            node_threshold: 0.0,
+        }
+    }
+}
+
+fn format_change(change: &f64) -> String {
+    format!("{:+.1}%", change * 100.0)
+}
+
+fn format_regression(regression: &bool) -> String {
+    String::from(if *regression { "REGRESSION" } else { "" })
+}
+
+/// Difference between an outcome of the baseline and the current run.
+#[derive(Clone, Debug, Tabled, Serialize)]
+pub struct OutcomeComparison {
+    #[tabled(rename = "Saturator")]
+    #[serde(rename = "Saturator")]
+    pub saturator: String,
+    #[tabled(rename = "Expression")]
+    #[serde(rename = "Expression")]
+    pub expression: String,
+    /// Relative change of time
+    #[tabled(rename = "Time", display_with = "format_change")]
+    #[serde(rename = "Time Change")]
+    pub time_change: f64,
+    /// Relative change of the number of nodes
+    #[tabled(rename = "Nodes", display_with = "format_change")]
+    #[serde(rename = "Node Change")]
+    pub node_change: f64,
+    #[tabled(rename = "Baseline Stop Reason")]
+    #[serde(rename = "Baseline Stop Reason")]
+    pub baseline_stop_reason: String,
+    #[tabled(rename = "Stop Reason")]
+    #[serde(rename = "Stop Reason")]
+    pub stop_reason: String,
+    #[tabled(rename = "Regression", display_with = "format_regression")]
+    #[serde(rename = "Regression")]
+    pub regression: bool,
+}
+
+impl Formattable for OutcomeComparison {
+    fn calculate_averages(_items: &[Self]) -> Option<Table> {
+        None
+    }
+}
+
+/// Result of [`compare`].
+#[derive(Clone, Debug, Default)]
+pub struct ComparisonReport {
+    /// Comparisons of outcomes present in both runs
+    pub comparisons: Vec<OutcomeComparison>,
+    /// Saturators and expressions present in only one of the runs
+    pub unmatched: Vec<(String, String)>,
+}
+
+impl ComparisonReport {
+    /// Returns comparisons flagged as regressions.
+    pub fn regressions(&self) -> impl Iterator<Item = &OutcomeComparison> {
+        self.comparisons.iter().filter(|c| c.regression)
+    }
+
+    /// Returns `true` if any outcome regressed.
+    pub fn has_regressions(&self) -> bool {
+        self.regressions().next().is_some()
+    }
+}
+
+fn relative_change(baseline: f64, current: f64) -> f64 {
+    if baseline == 0.0 {
+        if current == 0.0 { 0.0 } else { f64::INFINITY }
+    } else {
+        (current - baseline) / baseline
+    }
+}
+
+/// Compares outcomes of `current` with outcomes of `baseline` for the same saturators
+/// and expressions.
+///
+/// An outcome is a regression if its time or number of nodes grew by more than
+/// the thresholds of `config`, or if it no longer saturates.
+pub fn compare(
+    baseline: &BenchmarkResults,
+    current: &BenchmarkResults,
+    config: &ComparisonConfig,
+) -> ComparisonReport {
+    let saturated = format!("{:?}", SaturationStopReason::Saturated);
+    let mut report = ComparisonReport::default();
+
+    for (saturator, outcomes) in &current.saturators {
+        let baseline_outcomes = baseline.saturators.get(saturator);
+        for outcome in outcomes {
+            let Some(baseline_outcome) = baseline_outcomes
+                .and_then(|b| b.iter().find(|b| b.expression == outcome.expression))
+            else {
+                report
+                    .unmatched
+                    .push((saturator.clone(), outcome.expression.clone()));
+                continue;
+            };
+
+            let time_change =
+                relative_change(baseline_outcome.time_ns as f64, outcome.time_ns as f64);
+            let node_change = relative_change(baseline_outcome.nodes as f64, outcome.nodes as f64);
+            let lost_saturation =
+                baseline_outcome.stop_reason == saturated && outcome.stop_reason != saturated;
+
+            report.comparisons.push(OutcomeComparison {
+                saturator: saturator.clone(),
+                expression: outcome.expression.clone(),
+                time_change,
+                node_change,
+                baseline_stop_reason: baseline_outcome.stop_reason.clone(),
+                stop_reason: outcome.stop_reason.clone(),
+                regression: time_change > config.time_threshold
+                    || node_change > config.node_threshold
+                    || lost_saturation,
+            });
+        }
+    }
+
+    for (saturator, outcomes) in &baseline.saturators {
+        let current_outcomes = current.saturators.get(saturator);
+        for outcome in outcomes {
+            if !current_outcomes
+                .is_some_and(|c| c.iter().any(|c| c.expression == outcome.expression))
+            {
+                report
+                    .unmatched
+                    .push((saturator.clone(), outcome.expression.clone()));
+            }
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{BenchmarkResults, ComparisonConfig, RecordedOutcome, compare};
+
+    fn outcome(
+        expression: &str,
+        time_ns: u128,
+        nodes: usize,
+        stop_reason: &str,
+    ) -> RecordedOutcome {
+        RecordedOutcome {
+            expression: String::from(expression),
+            time_ns,
+            stop_reason: String::from(stop_reason),
+            nodes,
+            classes: nodes,
+        }
+    }
+
+    #[test]
+    fn load_formatter_output() {
+        let json = r#"{
+            "config": {},
+            "outcomes": [{
+                "Original Expression": "(+ 1 0)",
+                "Extracted Expression": "1",
+                "Time (ns)": 1000,
+                "Stop Reason": "Saturated",
+                "Nodes": 4,
+                "Classes": 4,
+                "Min Cost": 1,
+                "Top Rule": "",
+                "Rules": []
+            }]
+        }"#;
+        let results = BenchmarkResults::from_json(json).unwrap();
+        assert_eq!(
+            results.saturators[""],
+            [outcome("(+ 1 0)", 1000, 4, "Saturated")]
+        );
+    }
+
+    #[test]
+    fn detects_regressions() {
+        let baseline = BenchmarkResults {
+            saturators: BTreeMap::from([(
+                String::from("Simple"),
+                vec![
+                    outcome("a", 1000, 10, "Saturated"),
+                    outcome("b", 1000, 10, "Saturated"),
+                    outcome("c", 1000, 10, "Saturated"),
+                    outcome("d", 1000, 10, "Saturated"),
+                ],
+            )]),
+        };
+        let current = BenchmarkResults {
+            saturators: BTreeMap::from([(
+                String::from("Simple"),
+                vec![
+                    outcome("a", 1050, 10, "Saturated"),
+                    outcome("b", 2000, 10, "Saturated"),
+                    outcome("c", 500, 10, "MaxNodes"),
+                    outcome("e", 1000, 10, "Saturated"),
+                ],
+            )]),
+        };
+
+        let report = compare(&baseline, &current, &ComparisonConfig::default());
+        let regressions: Vec<_> = report
+            .regressions()
+            .map(|c| c.expression.as_str())
+            .collect();
+        assert_eq!(regressions, ["b", "c"]);
+        assert!((report.comparisons[1].time_change - 1.0).abs() < 1e-9);
+        assert_eq!(
+            report.unmatched,
+            [
+                (String::from("Simple"), String::from("e")),
+                (String::from("Simple"), String::from("d"))
+            ]
+        );
+    }
+}
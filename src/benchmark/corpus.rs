@@ -0,0 +1,284 @@
+//! Corpora of benchmarked expressions.
+//!
+//! A corpus is a JSON file with a list of `expressions`, in the format read by
+//! [`load_expressions_from_file`](crate::language::expression::load_expressions_from_file),
+//! which may also contain variables. [`Corpus`] reports statistics of such files,
+//! removes expressions differing only in names of variables and splits corpora
+//! into training and evaluation subsets.
+
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+use rand::{SeedableRng, rngs::StdRng, seq::SliceRandom};
+use serde::{Deserialize, Serialize};
+
+use crate::language::{
+    Language,
+    expression::{AnyExpression, Expression},
+    symbol::Symbol,
+};
+use crate::utils::json::{load_json, save_json};
+
+/// Format of corpus files.
+#[derive(Serialize, Deserialize)]
+struct CorpusFile {
+    expressions: Vec<String>,
+}
+
+/// A list of expressions of a single language.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Corpus {
+    /// Expressions of the corpus, in order
+    pub expressions: Vec<Expression>,
+}
+
+/// Statistics of a [`Corpus`], see [`Corpus::statistics`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CorpusStatistics {
+    /// Number of expressions
+    pub expressions: usize,
+    /// Numbers of expressions by their sizes, i.e. numbers of subexpressions
+    pub sizes: BTreeMap<usize, usize>,
+    /// Numbers of expressions by their depths, a leaf has depth 1
+    pub depths: BTreeMap<usize, usize>,
+    /// Numbers of occurrences of symbols in all expressions, by their names
+    pub symbol_frequencies: BTreeMap<String, usize>,
+    /// Number of occurrences of literals in all expressions
+    pub literals: usize,
+    /// Numbers of expressions by the number of distinct variables they contain
+    pub distinct_variables: BTreeMap<usize, usize>,
+    /// Number of occurrences of variables in all expressions
+    pub variable_occurrences: usize,
+}
+
+impl Corpus {
+    /// Creates a corpus of `expressions`.
+    pub fn new(expressions: Vec<Expression>) -> Self {
+        Self { expressions }
+    }
+
+    /// Loads a corpus file, parsing its expressions in `language`.
+    pub fn load<P: AsRef<Path>>(path: P, language: &Language) -> Result<Self, Box<dyn Error>> {
+        let file: CorpusFile = load_json(path)?;
+        let expressions = file
+            .expressions
+            .iter()
+            .map(|expression| {
+                language
+                    .parse(expression)
+                    .map_err(|e| format!("Failed to parse expression '{expression}': {e}").into())
+            })
+            .collect::<Result<_, Box<dyn Error>>>()?;
+        Ok(Self { expressions })
+    }
+
+    /// Saves the corpus in the format read by [`Corpus::load`].
+    pub fn save<P: AsRef<Path>>(&self, path: P, language: &Language) -> Result<(), Box<dyn Error>> {
+        let file = CorpusFile {
+            expressions: self
+                .expressions
+                .iter()
+                .map(|expression| expression.with_language(language).to_string())
+                .collect(),
+        };
+        save_json(&file, path)
+    }
+
+    /// Returns the number of expressions.
+    pub fn len(&self) -> usize {
+        self.expressions.len()
+    }
+
+    /// `true` if the corpus has no expressions.
+    pub fn is_empty(&self) -> bool {
+        self.expressions.is_empty()
+    }
+
+    /// Computes statistics of the expressions, with symbols named after `language`.
+    pub fn statistics(&self, language: &Language) -> CorpusStatistics {
+        let mut statistics = CorpusStatistics {
+            expressions: self.len(),
+            ..Default::default()
+        };
+
+        for expression in &self.expressions {
+            let mut size = 0;
+            let mut depth = 0;
+            let mut variables = HashSet::new();
+            let mut pending = vec![(expression, 1)];
+            while let Some((current, current_depth)) = pending.pop() {
+                size += 1;
+                depth = depth.max(current_depth);
+                match current {
+                    Expression::Literal(_) => statistics.literals += 1,
+                    Expression::Variable(variable_id) => {
+                        statistics.variable_occurrences += 1;
+                        variables.insert(*variable_id);
+                    }
+                    Expression::Symbol(Symbol { id, children }) => {
+                        *statistics
+                            .symbol_frequencies
+                            .entry(language.get_symbol(*id).to_owned())
+                            .or_default() += 1;
+                        pending.extend(children.iter().map(|child| (child, current_depth + 1)));
+                    }
+                }
+            }
+
+            *statistics.sizes.entry(size).or_default() += 1;
+            *statistics.depths.entry(depth).or_default() += 1;
+            *statistics
+                .distinct_variables
+                .entry(variables.len())
+                .or_default() += 1;
+        }
+
+        statistics
+    }
+
+    /// Removes expressions equal to an earlier one up to a renaming of variables,
+    /// keeping the order of the remaining ones. Returns the number of removed expressions.
+    pub fn deduplicate(&mut self) -> usize {
+        let before = self.len();
+        let mut seen = HashSet::new();
+        self.expressions.retain(|expression| {
+            let mut canonical = expression.clone();
+            canonical.canonicalize_variables();
+            seen.insert(canonical)
+        });
+        before - self.len()
+    }
+
+    /// Randomly splits the corpus into a training and an evaluation subset, with
+    /// `eval_fraction` of the expressions, rounded, in the latter.
+    /// The same `seed` always gives the same split.
+    pub fn split(&self, eval_fraction: f64, seed: u64) -> (Corpus, Corpus) {
+        let mut expressions = self.expressions.clone();
+        expressions.shuffle(&mut StdRng::seed_from_u64(seed));
+        let eval_size = (eval_fraction.clamp(0.0, 1.0) * self.len() as f64).round() as usize;
+        let eval = expressions.split_off(self.len() - eval_size);
+        (Corpus::new(expressions), Corpus::new(eval))
+    }
+}
+
+impl fmt::Display for CorpusStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn histogram(
+            f: &mut fmt::Formatter<'_>,
+            name: &str,
+            counts: &BTreeMap<impl fmt::Display, usize>,
+        ) -> fmt::Result {
+            writeln!(f, "{name}:")?;
+            for (key, count) in counts {
+                writeln!(f, "  {key}: {count}")?;
+            }
+            Ok(())
+        }
+
+        writeln!(f, "Expressions: {}", self.expressions)?;
+        histogram(f, "Sizes", &self.sizes)?;
+        histogram(f, "Depths", &self.depths)?;
+        histogram(f, "Symbols", &self.symbol_frequencies)?;
+        writeln!(f, "Literals: {}", self.literals)?;
+        histogram(f, "Distinct variables", &self.distinct_variables)?;
+        write!(f, "Variable occurrences: {}", self.variable_occurrences)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::language::{Language, expression::AnyExpression};
+
+    use super::Corpus;
+
+    fn corpus(lang: &Language, expressions: &[&str]) -> Corpus {
+        Corpus::new(
+            expressions
+                .iter()
+                .map(|expression| lang.parse(expression).unwrap())
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn statistics() {
+        let lang = Language::simple_math();
+        let corpus = corpus(&lang, &["(+ 1 (* $0 $0))", "(sin $1)", "2"]);
+        let statistics = corpus.statistics(&lang);
+
+        assert_eq!(statistics.expressions, 3);
+        assert_eq!(statistics.sizes, BTreeMap::from([(1, 1), (2, 1), (5, 1)]));
+        assert_eq!(statistics.depths, BTreeMap::from([(1, 1), (2, 1), (3, 1)]));
+        assert_eq!(
+            statistics.symbol_frequencies,
+            BTreeMap::from([
+                (String::from("*"), 1),
+                (String::from("+"), 1),
+                (String::from("sin"), 1)
+            ])
+        );
+        assert_eq!(statistics.literals, 2);
+        assert_eq!(
+            statistics.distinct_variables,
+            BTreeMap::from([(0, 1), (1, 2)])
+        );
+        assert_eq!(statistics.variable_occurrences, 3);
+    }
+
+    #[test]
+    fn deduplicates_alpha_equivalent_expressions() {
+        let lang = Language::simple_math();
+        let mut corpus = corpus(
+            &lang,
+            &[
+                "(+ $0 $1)",
+                "(+ $1 $0)",
+                "(+ $2 $2)",
+                "(+ $0 $0)",
+                "(+ $0 1)",
+            ],
+        );
+
+        assert_eq!(corpus.deduplicate(), 2);
+        assert_eq!(
+            corpus,
+            self::corpus(&lang, &["(+ $0 $1)", "(+ $2 $2)", "(+ $0 1)"])
+        );
+    }
+
+    #[test]
+    fn splits_reproducibly() {
+        let lang = Language::simple_math();
+        let corpus = corpus(&lang, &["1", "2", "3", "4", "5", "6", "7", "8", "9", "10"]);
+
+        let (train, eval) = corpus.split(0.3, 7);
+        assert_eq!((train.len(), eval.len()), (7, 3));
+        assert_eq!(corpus.split(0.3, 7), (train.clone(), eval.clone()));
+
+        let mut joined = train.expressions;
+        joined.extend(eval.expressions);
+        joined.sort_by_key(|expression| expression.with_language(&lang).to_string());
+        let mut expected = corpus.expressions.clone();
+        expected.sort_by_key(|expression| expression.with_language(&lang).to_string());
+        assert_eq!(joined, expected);
+    }
+
+    #[test]
+    fn loads_standard_corpus() {
+        let lang = Language::simple_math();
+        let path =
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("jsons/simple-math/small.json");
+        let corpus = Corpus::load(&path, &lang).unwrap();
+        assert_eq!(corpus.len(), 7);
+
+        let saved = std::env::temp_dir().join(format!("corpus-{}.json", std::process::id()));
+        corpus.save(&saved, &lang).unwrap();
+        let reloaded = Corpus::load(&saved, &lang);
+        std::fs::remove_file(&saved).unwrap();
+        assert_eq!(reloaded.unwrap(), corpus);
+    }
+}
@@ -0,0 +1,242 @@
+//! Consistency checks between saturators.
+//!
+//! Without limits, [`SimpleSaturator`] and [`DirectedSaturator`] apply rules in different
+//! orders but should reach the same fixed point, i.e. e-graphs representing the same
+//! equivalences with the same numbers of nodes and classes. [`cross_check`] saturates
+//! every expression with both and compares the results with [`EGraph::entails`].
+//! If an e-graph has an equivalence missing from the other one, the applications of its
+//! saturator are replayed to find the first one deriving an equivalence the other saturator
+//! never derived.
+//!
+//! The check is only meaningful if both saturations stop with
+//! [`SaturationStopReason::Saturated`], so it should be run under unlimited budgets
+//! on small inputs.
+
+use std::cell::RefCell;
+
+use crate::{
+    language::expression::VarFreeExpression,
+    rewriting::{
+        egraph::{
+            Analysis, DynEGraph, EGraph,
+            class::local_cost::LocalCost,
+            matching::{EGraphMatch, Matcher},
+            saturation::{
+                ApplicationFilter, RuleReport, SaturationConfig, SaturationReport,
+                SaturationStopReason, Saturator, SimpleSaturator,
+                directed_saturator::DirectedSaturator,
+            },
+        },
+        rule::Rule,
+        system::TermRewritingSystem,
+    },
+};
+
+/// Rule application deriving an equivalence which the other saturator did not derive.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    /// Name of the saturator which applied the rule
+    pub saturator: &'static str,
+    /// Index of the application among the ones considered by the saturator, starting at 0
+    pub application: usize,
+    /// Label of the applied rule
+    pub rule: String,
+}
+
+/// Results of saturating an expression with both saturators.
+#[derive(Clone, Debug)]
+pub struct CrossCheckOutcome {
+    /// Saturated expression
+    pub expression: VarFreeExpression,
+    /// Report of the saturation with the simple saturator
+    pub simple: SaturationReport,
+    /// Report of the saturation with the directed saturator
+    pub directed: SaturationReport,
+    /// First diverging application, `None` if both e-graphs entail each other
+    pub divergence: Option<Divergence>,
+}
+
+impl CrossCheckOutcome {
+    /// `true` if both saturations reached a fixed point, so that their results are comparable.
+    pub fn is_conclusive(&self) -> bool {
+        self.simple.stop_reason == Some(SaturationStopReason::Saturated)
+            && self.directed.stop_reason == Some(SaturationStopReason::Saturated)
+    }
+
+    /// `true` if both e-graphs represent the same equivalences and have the same numbers
+    /// of nodes and classes.
+    pub fn is_consistent(&self) -> bool {
+        self.divergence.is_none()
+            && self.simple.node_count == self.directed.node_count
+            && self.simple.class_count == self.directed.class_count
+    }
+}
+
+/// Saturates every expression with a [`SimpleSaturator`] and a [`DirectedSaturator`]
+/// using matchers created by `matcher`, comparing the saturated e-graphs.
+///
+/// Divergences are only searched for if both saturations are conclusive,
+/// see [`CrossCheckOutcome::is_conclusive`].
+pub fn cross_check<LC: LocalCost + 'static>(
+    trs: &TermRewritingSystem,
+    expressions: &[VarFreeExpression],
+    config: &SaturationConfig,
+    matcher: impl Fn() -> Box<dyn Matcher>,
+) -> Vec<CrossCheckOutcome> {
+    expressions
+        .iter()
+        .map(|expression| {
+            let simple_log = ApplicationLog::default();
+            let mut simple_egraph = trs.egraph::<LC>();
+            simple_egraph.add_expression(expression.clone());
+            let simple = SimpleSaturator::new(matcher())
+                .with_filter(&simple_log)
+                .saturate(&mut simple_egraph, trs.rules(), config);
+
+            let directed_log = ApplicationLog::default();
+            let mut directed_egraph = trs.egraph::<LC>();
+            directed_egraph.add_expression(expression.clone());
+            let directed = DirectedSaturator::new(matcher())
+                .with_filter(&directed_log)
+                .saturate(&mut directed_egraph, trs.rules(), config);
+
+            let mut outcome = CrossCheckOutcome {
+                expression: expression.clone(),
+                simple,
+                directed,
+                divergence: None,
+            };
+            if outcome.is_conclusive() {
+                outcome.divergence = simple_log
+                    .first_divergence(expression, &directed_egraph)
+                    .map(|(application, rule)| (SIMPLE, application, rule))
+                    .or_else(|| {
+                        directed_log
+                            .first_divergence(expression, &simple_egraph)
+                            .map(|(application, rule)| (DIRECTED, application, rule))
+                    })
+                    .map(|(saturator, application, rule)| Divergence {
+                        saturator,
+                        application,
+                        rule: rule.label(trs.language()),
+                    });
+            }
+            outcome
+        })
+        .collect()
+}
+
+const SIMPLE: &str = "Simple";
+const DIRECTED: &str = "Directed";
+
+/// A filter accepting all applications and logging them in the order they are considered.
+#[derive(Default)]
+struct ApplicationLog {
+    applications: RefCell<Vec<(Rule, EGraphMatch)>>,
+}
+
+impl<A: Analysis> ApplicationFilter<A> for &ApplicationLog {
+    fn accept(&self, _egraph: &EGraph<A>, matching: &EGraphMatch, rule: &Rule) -> bool {
+        self.applications
+            .borrow_mut()
+            .push((rule.clone(), matching.clone()));
+        true
+    }
+}
+
+impl ApplicationLog {
+    /// Replays the logged applications on the e-graph of `expression`, returning the index
+    /// of the first one after which the e-graph has an equivalence not entailed by `other`,
+    /// together with its rule.
+    ///
+    /// Replaying reproduces the IDs of classes in the logged matches, as e-graphs assign IDs
+    /// in the order of additions and restore congruence after every merge.
+    fn first_divergence<LC: LocalCost>(
+        &self,
+        expression: &VarFreeExpression,
+        other: &EGraph<LC>,
+    ) -> Option<(usize, Rule)> {
+        let mut egraph = EGraph::<LC>::from_expression(expression.clone());
+        self.applications
+            .borrow()
+            .iter()
+            .enumerate()
+            .find(|(_, (rule, matching))| {
+                rule.apply_match(&mut egraph, matching, &(), &mut RuleReport::default())
+                    && !other.entails(&egraph)
+            })
+            .map(|(index, (rule, _))| (index, rule.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        language::Language,
+        macros::rules,
+        rewriting::{
+            egraph::{
+                EGraph,
+                class::size_local_cost::SizeLocalCost,
+                matching::bottom_up::BottomUpMatcher,
+                saturation::{SaturationConfig, Saturator, SimpleSaturator},
+            },
+            system::TermRewritingSystem,
+        },
+    };
+
+    use super::{ApplicationLog, cross_check};
+
+    #[test]
+    fn saturators_reach_same_egraphs() {
+        let lang = Language::simple_math();
+        let rules = rules!(lang;
+            "(* $0 2)" => "(<< $0 1)",
+            "(* $0 1)" => "$0",
+            "(/ (* $0 $1) $2)" => "(* $0 (/ $1 $2))",
+            "(/ $0 $0)" => "1",
+        );
+        let trs = TermRewritingSystem::new(lang.clone(), rules);
+        let expressions = vec![
+            lang.parse_no_vars("(/ (* (sin 5) 2) 2)").unwrap(),
+            lang.parse_no_vars("(* (* 3 1) 2)").unwrap(),
+        ];
+
+        let outcomes =
+            cross_check::<SizeLocalCost>(&trs, &expressions, &SaturationConfig::default(), || {
+                Box::new(BottomUpMatcher)
+            });
+        assert_eq!(outcomes.len(), 2);
+        for outcome in outcomes {
+            assert!(outcome.is_conclusive());
+            assert!(outcome.is_consistent(), "{outcome:?}");
+        }
+    }
+
+    #[test]
+    fn finds_first_diverging_application() {
+        let lang = Language::simple_math();
+        let rules = rules!(lang;
+            "(* $0 1)" => "$0",
+            "(+ $0 0)" => "$0",
+        );
+        let expression = lang.parse_no_vars("(+ (* 3 1) 0)").unwrap();
+
+        let log = ApplicationLog::default();
+        let mut egraph = EGraph::<SizeLocalCost>::from_expression(expression.clone());
+        SimpleSaturator::new(Box::new(BottomUpMatcher))
+            .with_filter(&log)
+            .saturate(&mut egraph, &rules, &SaturationConfig::default());
+        assert_eq!(log.first_divergence(&expression, &egraph), None);
+
+        // An e-graph saturated only with the second rule misses the first equivalence
+        let mut partial = EGraph::<SizeLocalCost>::from_expression(expression.clone());
+        SimpleSaturator::new(Box::new(BottomUpMatcher)).saturate(
+            &mut partial,
+            &rules[1..],
+            &SaturationConfig::default(),
+        );
+        let (_, rule) = log.first_divergence(&expression, &partial).unwrap();
+        assert_eq!(rule.label(&lang), "(* $0 1) => $0");
+    }
+}
@@ -1,5 +1,5 @@
 use super::formatter::CsvFormatter;
-use super::{Outcome, OutcomeFormatter};
+use super::{BatchOutcome, Outcome, OutcomeFormatter, ReachabilityOutcome, RuleOutcome};
 use std::collections::BTreeMap;
 
 pub struct CsvOutputFormatter;
@@ -13,4 +13,16 @@ impl OutcomeFormatter for CsvOutputFormatter {
         CsvFormatter::format_grouped(outcomes_map)
             .unwrap_or_else(|e| format!("Error formatting CSV: {}", e))
     }
+
+    fn format_rule_outcomes(&self, rules: &[RuleOutcome]) -> String {
+        CsvFormatter::format(rules).unwrap_or_else(|e| format!("Error formatting CSV: {}", e))
+    }
+
+    fn format_reachability_outcomes(&self, outcomes: &[ReachabilityOutcome]) -> String {
+        CsvFormatter::format(outcomes).unwrap_or_else(|e| format!("Error formatting CSV: {}", e))
+    }
+
+    fn format_batch_outcomes(&self, outcomes: &[BatchOutcome]) -> String {
+        CsvFormatter::format(outcomes).unwrap_or_else(|e| format!("Error formatting CSV: {}", e))
+    }
 }
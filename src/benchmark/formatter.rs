@@ -1,7 +1,8 @@
 //! Generic formatting module for benchmark outcomes.
 //!
 //! This module provides a unified approach to formatting benchmark results
-//! using the `tabled` library for pretty-printed tables and the `csv` crate for CSV output.
+//! using the `tabled` library for pretty-printed and Markdown tables, the `csv` crate for CSV output
+//! and `serde_json` for JSON output.
 //!
 //! The `Formattable` trait enables any benchmark outcome type to be formatted
 //! consistently without code duplication.
@@ -13,8 +14,8 @@ use tabled::{Table, Tabled, settings::Style};
 /// A generic trait for formatting benchmark outcomes.
 /// This trait is implemented for both Outcome and ReachabilityOutcome types.
 pub trait Formattable: Tabled + serde::Serialize {
-    /// Calculate averages for numeric fields and return as a map
-    fn calculate_averages(items: &[Self]) -> Option<String>
+    /// Calculate averages for numeric fields and return them as an unstyled single-row table
+    fn calculate_averages(items: &[Self]) -> Option<Table>
     where
         Self: Sized;
 }
@@ -37,9 +38,10 @@ impl PrettyFormatter {
         buffer.push_str(&table.to_string());
 
         // Add averages if available
-        if let Some(avg_str) = T::calculate_averages(items) {
+        if let Some(mut avg_table) = T::calculate_averages(items) {
+            avg_table.with(Style::rounded());
             buffer.push('\n');
-            buffer.push_str(&avg_str);
+            buffer.push_str(&avg_table.to_string());
         }
 
         buffer
@@ -59,6 +61,45 @@ impl PrettyFormatter {
     }
 }
 
+/// Markdown table formatter using the tabled library
+pub struct MarkdownFormatter;
+
+impl MarkdownFormatter {
+    /// Format a collection of formattable items as a Markdown table with averages
+    pub fn format<T: Formattable>(items: &[T]) -> String {
+        if items.is_empty() {
+            return String::new();
+        }
+
+        let mut table = Table::new(items);
+        table.with(Style::markdown());
+        let mut buffer = table.to_string();
+        buffer.push('\n');
+
+        if let Some(mut avg_table) = T::calculate_averages(items) {
+            avg_table.with(Style::markdown());
+            buffer.push_str("\n**Averages**\n\n");
+            buffer.push_str(&avg_table.to_string());
+            buffer.push('\n');
+        }
+
+        buffer
+    }
+
+    /// Format outcomes grouped by saturator name, each under its own heading
+    pub fn format_grouped<T: Formattable>(outcomes_map: BTreeMap<String, Vec<T>>) -> String {
+        let mut buffer = String::new();
+
+        for (name, outcomes) in outcomes_map {
+            buffer.push_str(&format!("### Saturator: {}\n\n", name));
+            buffer.push_str(&Self::format(&outcomes));
+            buffer.push('\n');
+        }
+
+        buffer
+    }
+}
+
 /// CSV formatter using the csv crate
 pub struct CsvFormatter;
 
@@ -0,0 +1,149 @@
+use super::{BatchOutcome, Outcome, OutcomeFormatter, ReachabilityOutcome, RuleOutcome};
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+use std::collections::BTreeMap;
+
+/// Formats outcomes as JSON documents, which can be loaded by other tools.
+///
+/// Every document is an object with the outcomes and, if one was given,
+/// the configuration used to obtain them under the `config` key.
+/// Saturation outcomes contain full statistics of every rule under the `Rules` key,
+/// and all outcomes contain full statistics of their times under the `Statistics` key.
+#[derive(Default)]
+pub struct JsonOutputFormatter {
+    config: Option<Value>,
+}
+
+impl JsonOutputFormatter {
+    /// Creates a formatter whose documents contain no configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Includes `config` in the output. Fails if `config` cannot be represented as JSON.
+    pub fn with_config<C: Serialize>(mut self, config: &C) -> serde_json::Result<Self> {
+        self.config = Some(serde_json::to_value(config)?);
+        Ok(self)
+    }
+
+    fn document(&self, key: &str, value: Value) -> String {
+        let mut document = Map::new();
+        if let Some(config) = &self.config {
+            document.insert(String::from("config"), config.clone());
+        }
+        document.insert(String::from(key), value);
+        serde_json::to_string_pretty(&document)
+            .unwrap_or_else(|e| format!("Error formatting JSON: {}", e))
+    }
+}
+
+fn outcome_value(outcome: &Outcome) -> Value {
+    let mut value = json!(outcome);
+    value["Rules"] = json!(outcome.rules);
+    value["Statistics"] = json!(outcome.statistics);
+    value
+}
+
+fn reachability_outcome_value(outcome: &ReachabilityOutcome) -> Value {
+    let mut value = json!(outcome);
+    value["Statistics"] = json!(outcome.statistics);
+    value
+}
+
+fn outcomes_value(outcomes: &[Outcome]) -> Value {
+    Value::Array(outcomes.iter().map(outcome_value).collect())
+}
+
+impl OutcomeFormatter for JsonOutputFormatter {
+    fn format_outcomes(&self, outcomes: &[Outcome]) -> String {
+        self.document("outcomes", outcomes_value(outcomes))
+    }
+
+    fn format_saturator_outcomes(&self, outcomes_map: BTreeMap<String, Vec<Outcome>>) -> String {
+        let saturators = outcomes_map
+            .iter()
+            .map(|(name, outcomes)| (name.clone(), outcomes_value(outcomes)))
+            .collect();
+        self.document("saturators", Value::Object(saturators))
+    }
+
+    fn format_rule_outcomes(&self, rules: &[RuleOutcome]) -> String {
+        self.document("rules", json!(rules))
+    }
+
+    fn format_reachability_outcomes(&self, outcomes: &[ReachabilityOutcome]) -> String {
+        let outcomes = outcomes.iter().map(reachability_outcome_value).collect();
+        self.document("outcomes", Value::Array(outcomes))
+    }
+
+    fn format_batch_outcomes(&self, outcomes: &[BatchOutcome]) -> String {
+        let outcomes = outcomes
+            .iter()
+            .map(|outcome| {
+                let mut value = json!(outcome);
+                value["Statistics"] = json!(outcome.statistics);
+                value
+            })
+            .collect();
+        self.document("batches", Value::Array(outcomes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::JsonOutputFormatter;
+    use crate::{
+        benchmark::{BenchmarkConfig, Outcome, OutcomeFormatter, RuleOutcome, Statistics},
+        language::Language,
+        rewriting::egraph::saturation::{SaturationConfig, SaturationStopReason},
+    };
+
+    #[test]
+    fn outcomes_with_config() {
+        let lang = Language::simple_math();
+        let expression = lang.parse_no_vars("(+ 1 0)").unwrap();
+        let outcome = Outcome {
+            original_expression: expression.clone(),
+            extracted_expression: lang.parse_no_vars("1").unwrap(),
+            time: Duration::from_micros(5),
+            statistics: Statistics {
+                samples: 10,
+                median: Duration::from_micros(4),
+                ..Default::default()
+            },
+            stop_reason: SaturationStopReason::Saturated,
+            nodes: 4,
+            classes: 3,
+            min_cost: 1,
+            rules: vec![RuleOutcome {
+                rule: String::from("zero"),
+                matches: 1,
+                applications: 1,
+                unions: 1,
+                match_time: Duration::from_micros(2),
+                apply_time: Duration::from_micros(1),
+            }],
+            metrics: Default::default(),
+        };
+        let config = BenchmarkConfig {
+            saturation_config: SaturationConfig {
+                max_nodes: Some(100),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let formatter = JsonOutputFormatter::new().with_config(&config).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&formatter.format_outcomes(&[outcome])).unwrap();
+
+        assert_eq!(json["config"]["saturation_config"]["max_nodes"], 100);
+        assert_eq!(json["outcomes"][0]["Time (ns)"], 5000);
+        assert_eq!(json["outcomes"][0]["Rules"][0]["Rule"], "zero");
+        assert_eq!(json["outcomes"][0]["Rules"][0]["Match Time (ns)"], 2000);
+        assert_eq!(json["outcomes"][0]["Statistics"]["Median (ns)"], 4000);
+        assert_eq!(json["config"]["sampling"]["runs"], 10);
+    }
+}
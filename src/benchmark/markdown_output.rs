@@ -0,0 +1,63 @@
+use super::formatter::MarkdownFormatter;
+use super::{BatchOutcome, Outcome, OutcomeFormatter, ReachabilityOutcome, RuleOutcome};
+use std::collections::BTreeMap;
+
+/// Formats outcomes as Markdown tables, e.g. for pasting into issues.
+pub struct MarkdownTableFormatter;
+
+impl OutcomeFormatter for MarkdownTableFormatter {
+    fn format_outcomes(&self, outcomes: &[Outcome]) -> String {
+        MarkdownFormatter::format(outcomes)
+    }
+
+    fn format_saturator_outcomes(&self, outcomes_map: BTreeMap<String, Vec<Outcome>>) -> String {
+        MarkdownFormatter::format_grouped(outcomes_map)
+    }
+
+    fn format_rule_outcomes(&self, rules: &[RuleOutcome]) -> String {
+        MarkdownFormatter::format(rules)
+    }
+
+    fn format_reachability_outcomes(&self, outcomes: &[ReachabilityOutcome]) -> String {
+        MarkdownFormatter::format(outcomes)
+    }
+
+    fn format_batch_outcomes(&self, outcomes: &[BatchOutcome]) -> String {
+        MarkdownFormatter::format(outcomes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::MarkdownTableFormatter;
+    use crate::{
+        benchmark::{OutcomeFormatter, ReachabilityOutcome},
+        language::Language,
+        rewriting::reachability::ReachabilityStopReason,
+    };
+
+    #[test]
+    fn reachability_table() {
+        let lang = Language::simple_math();
+        let outcome = ReachabilityOutcome {
+            expr_a: lang.parse_no_vars("(+ 1 0)").unwrap(),
+            goals: vec![lang.parse_no_vars("1").unwrap()],
+            reached_goal: None,
+            time: Duration::from_millis(1),
+            statistics: Default::default(),
+            stop_reason: ReachabilityStopReason::SaturatedNoUnification,
+            applications: 2,
+            nodes: 4,
+            classes: 3,
+        };
+
+        let table = MarkdownTableFormatter.format_reachability_outcomes(&[outcome]);
+        let mut lines = table.lines();
+        assert!(lines.next().unwrap().starts_with("| Expr A "));
+        assert!(lines.next().unwrap().starts_with("|---"));
+        assert!(table.contains("| (+ 1 0) "));
+        assert!(table.contains("**Averages**"));
+    }
+}
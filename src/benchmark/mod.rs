@@ -3,27 +3,55 @@
 //! This module provides tools for benchmarking the performance of term rewriting
 //! systems, including:
 //! - Saturation benchmarks
+//! - Statistics of repeated runs, with warm-up, outlier rejection and confidence intervals
 //! - Reachability analysis
-//! - Result formatting (CSV, pretty tables)
+//! - Result formatting (CSV, pretty tables, Markdown, JSON), including per-rule statistics
+//! - Comparison with results of previous runs
 //! - Random expression generation
+//! - Statistics, deduplication and splitting of expression corpora
+//! - Consistency checks of saturators, which should reach the same fixed points
+//! - Campaigns running a matrix of benchmarks described by a configuration file
 
+pub mod campaign;
+pub mod comparison;
+pub mod corpus;
+pub mod cross_check;
 pub mod csv_output;
 pub mod formatter;
+pub mod json_output;
+pub mod markdown_output;
 pub mod pretty_printing;
 pub mod random_generation;
 pub mod reachability;
 pub mod saturation;
+pub mod statistics;
 
-pub use saturation::{BenchmarkConfig, Outcome, OutcomeFormatter, benchmark};
+pub use saturation::{
+    BatchOutcome, BenchmarkConfig, BenchmarkError, Outcome, OutcomeFormatter, RuleOutcome,
+    benchmark, benchmark_batch, rule_totals,
+};
+
+pub use csv_output::CsvOutputFormatter;
+pub use json_output::JsonOutputFormatter;
+pub use markdown_output::MarkdownTableFormatter;
+pub use pretty_printing::PrettyTableFormatter;
+
+pub use statistics::{SamplingConfig, Statistics};
+
+pub use corpus::{Corpus, CorpusStatistics};
+
+pub use cross_check::{CrossCheckOutcome, Divergence, cross_check};
 
 pub use reachability::{
     ReachabilityOutcome,
     benchmark_pairs_with_scheduler as reachability_benchmark_pairs_with_scheduler,
+    benchmark_sets_with_prefilter as reachability_benchmark_sets_with_prefilter,
+    benchmark_sets_with_scheduler as reachability_benchmark_sets_with_scheduler,
 };
 
 pub use random_generation::{
-    GenerationError, LiteralGenerationConfig, RandomGenerationConfig, VariableGenerationConfig,
-    generate_random_expression_by_size_with_config,
+    GenerationError, LiteralGenerationConfig, RandomGenerationConfig, ScrambledPair,
+    VariableGenerationConfig, generate_random_expression_by_size_with_config,
     generate_random_expression_by_size_with_variables, generate_random_expression_with_config,
-    generate_random_expression_with_variables,
+    generate_random_expression_with_variables, generate_scrambled_pair,
 };
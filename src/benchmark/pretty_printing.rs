@@ -1,5 +1,5 @@
 use super::formatter::PrettyFormatter;
-use super::{Outcome, OutcomeFormatter, ReachabilityOutcome};
+use super::{BatchOutcome, Outcome, OutcomeFormatter, ReachabilityOutcome, RuleOutcome};
 use std::collections::BTreeMap;
 
 pub struct PrettyTableFormatter;
@@ -12,11 +12,16 @@ impl OutcomeFormatter for PrettyTableFormatter {
     fn format_saturator_outcomes(&self, outcomes_map: BTreeMap<String, Vec<Outcome>>) -> String {
         PrettyFormatter::format_grouped(outcomes_map)
     }
-}
 
-impl PrettyTableFormatter {
-    /// Format reachability outcomes as a pretty table
-    pub fn format_reachability_outcomes(&self, outcomes: &[ReachabilityOutcome]) -> String {
+    fn format_rule_outcomes(&self, rules: &[RuleOutcome]) -> String {
+        PrettyFormatter::format(rules)
+    }
+
+    fn format_reachability_outcomes(&self, outcomes: &[ReachabilityOutcome]) -> String {
+        PrettyFormatter::format(outcomes)
+    }
+
+    fn format_batch_outcomes(&self, outcomes: &[BatchOutcome]) -> String {
         PrettyFormatter::format(outcomes)
     }
 }
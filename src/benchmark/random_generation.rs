@@ -1,9 +1,11 @@
 //! Random expression generation for testing and benchmarking.
 //!
 //! This module provides utilities for generating random expressions,
-//! useful for benchmarking term rewriting systems.
+//! useful for benchmarking term rewriting systems, and pairs of expressions
+//! rewritable into each other within a known number of steps.
 
 use rand::Rng;
+use std::collections::HashMap;
 use std::fmt;
 
 use crate::language::{
@@ -11,6 +13,10 @@ use crate::language::{
     expression::{Expression, Literal, VarFreeExpression, VariableId},
     symbol::Symbol,
 };
+use crate::rewriting::{
+    direct::{apply_rewrite_at_position_expr, find_all_rewrite_positions_expr},
+    rule::Rule,
+};
 
 /// Error type for random expression generation.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -427,11 +433,102 @@ fn distribute_size(total: usize, num_children: usize, rng: &mut impl Rng) -> Vec
     sizes
 }
 
+/// A pair of expressions with a known bound on the number of rewrites between them,
+/// see [`generate_scrambled_pair`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScrambledPair {
+    /// Expression which can be rewritten to `goal`
+    pub scrambled: VarFreeExpression,
+    /// Randomly generated expression
+    pub goal: VarFreeExpression,
+    /// Number of inverted rules applied to `goal`, `scrambled` can be rewritten
+    /// to `goal` in at most that many steps
+    pub steps: usize,
+}
+
+/// Generates a random goal expression of size `goal_size` and scrambles it by applying
+/// up to `steps` randomly chosen inverted `rules` at random positions.
+///
+/// Every inverted application can be undone by applying the original rule, so `scrambled`
+/// rewrites to `goal` in at most [`ScrambledPair::steps`] steps, which is lower than `steps`
+/// if no inverted rule applies at some point. Variables occurring only in the left-hand side
+/// of a rule are instantiated with random literals, generated according to `config`.
+///
+/// # Errors
+///
+/// Returns `GenerationError` if the goal expression cannot be generated,
+/// see [`generate_random_expression_by_size_with_config`].
+pub fn generate_scrambled_pair(
+    language: &Language,
+    goal_size: usize,
+    steps: usize,
+    rules: &[Rule],
+    rng: &mut impl Rng,
+    config: &RandomGenerationConfig,
+) -> Result<ScrambledPair, GenerationError> {
+    let goal = generate_random_expression_by_size_with_config(language, goal_size, rng, config)?;
+    let inverted: Vec<_> = rules.iter().map(Rule::reversed).collect();
+
+    let mut scrambled = goal.clone();
+    let mut applied = 0;
+    while applied < steps {
+        let expression = scrambled.to_expression();
+        let positions = find_all_rewrite_positions_expr(&expression, &inverted);
+        if positions.is_empty() {
+            break;
+        }
+        let position = &positions[rng.gen_range(0..positions.len())];
+        let rewritten = apply_rewrite_at_position_expr(expression, &inverted, position);
+        scrambled = instantiate_unbound_variables(rewritten, rng, &config.literal_config);
+        applied += 1;
+    }
+
+    Ok(ScrambledPair {
+        scrambled,
+        goal,
+        steps: applied,
+    })
+}
+
+/// Replaces every variable in `expression` with a random literal, the same for all of its occurrences.
+fn instantiate_unbound_variables(
+    expression: Expression,
+    rng: &mut impl Rng,
+    config: &LiteralGenerationConfig,
+) -> VarFreeExpression {
+    fn instantiate(
+        expression: Expression,
+        literals: &mut HashMap<VariableId, Literal>,
+        rng: &mut impl Rng,
+        config: &LiteralGenerationConfig,
+    ) -> VarFreeExpression {
+        match expression {
+            Expression::Variable(variable_id) => VarFreeExpression::Literal(
+                literals
+                    .entry(variable_id)
+                    .or_insert_with(|| generate_random_literal(rng, config))
+                    .clone(),
+            ),
+            Expression::Symbol(Symbol { id, children }) => VarFreeExpression::Symbol(Symbol {
+                id,
+                children: children
+                    .into_iter()
+                    .map(|child| instantiate(child, literals, rng, config))
+                    .collect(),
+            }),
+            Expression::Literal(literal) => VarFreeExpression::Literal(literal),
+        }
+    }
+
+    instantiate(expression, &mut HashMap::new(), rng, config)
+}
+
 #[cfg(test)]
 mod tests {
     use super::{
         LiteralGenerationConfig, RandomGenerationConfig,
         generate_random_expression_by_size_with_config, generate_random_expression_with_config,
+        generate_scrambled_pair,
     };
     use crate::language::{
         Language,
@@ -762,4 +859,52 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn scrambled_pairs_are_reachable() {
+        use crate::language::expression::AnyExpression;
+        use crate::rewriting::direct::{apply_rewrite_at_position, find_all_rewrite_positions};
+        use crate::rewriting::rule::Rule;
+        use rand::{SeedableRng, rngs::StdRng};
+        use std::collections::HashSet;
+
+        let lang = Language::simple_math();
+        let config = default_math_config(&lang);
+        let rules = vec![
+            Rule::from_strings("(+ $0 0)", "$0", &lang),
+            Rule::from_strings("(* $0 1)", "$0", &lang),
+            Rule::from_strings("(* $0 $1)", "(* $1 $0)", &lang),
+            Rule::from_strings("(* $0 0)", "0", &lang),
+        ];
+        let mut rng = StdRng::seed_from_u64(3);
+
+        for _ in 0..10 {
+            let pair = generate_scrambled_pair(&lang, 3, 3, &rules, &mut rng, &config).unwrap();
+            assert_eq!(pair.steps, 3);
+
+            // Breadth-first search from the scrambled expression
+            let mut reached = HashSet::from([pair.scrambled.clone()]);
+            let mut frontier = vec![pair.scrambled.clone()];
+            for _ in 0..pair.steps {
+                frontier = frontier
+                    .into_iter()
+                    .flat_map(|expression| {
+                        find_all_rewrite_positions(&expression, &rules)
+                            .iter()
+                            .map(|position| {
+                                apply_rewrite_at_position(expression.clone(), &rules, position)
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                    .filter(|expression| reached.insert(expression.clone()))
+                    .collect();
+            }
+            assert!(
+                reached.contains(&pair.goal),
+                "{} is not reachable from {}",
+                pair.goal.with_language(&lang),
+                pair.scrambled.with_language(&lang)
+            );
+        }
+    }
 }
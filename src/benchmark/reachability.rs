@@ -1,21 +1,36 @@
 use serde::Serialize;
 use std::hint::black_box;
 use std::time::{Duration, Instant};
-use tabled::Tabled;
+use tabled::{Table, Tabled};
 
 use super::formatter::{Formattable, format_duration};
+use super::saturation::serialize_statistics;
+use super::statistics::{SamplingConfig, Statistics};
 use crate::language::expression::VarFreeExpression;
+use crate::rewriting::abelian::AbelianPrefilter;
 use crate::rewriting::egraph::matching::Matcher;
 use crate::rewriting::egraph::saturation::SaturationConfig;
 use crate::rewriting::egraph::saturation::scheduler::Scheduler;
 use crate::rewriting::egraph::{Analysis, DynEGraph};
-use crate::rewriting::reachability::{ReachabilityStopReason, terms_reachable};
+use crate::rewriting::reachability::{ReachabilityStopReason, terms_reachable_any};
 use crate::rewriting::rule::Rule;
 
 fn format_reachability_stop_reason(reason: &ReachabilityStopReason) -> String {
     format!("{:?}", reason)
 }
 
+fn format_goals(goals: &[VarFreeExpression]) -> String {
+    goals
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn format_reached_goal(goal: &Option<usize>) -> String {
+    goal.map(|goal| goal.to_string()).unwrap_or_default()
+}
+
 fn serialize_duration<S>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: serde::Serializer,
@@ -33,6 +48,13 @@ where
     serializer.serialize_str(&format!("{:?}", reason))
 }
 
+fn serialize_goals<S>(goals: &[VarFreeExpression], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    serializer.serialize_str(&format_goals(goals))
+}
+
 fn serialize_expr<S>(expr: &VarFreeExpression, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: serde::Serializer,
@@ -45,12 +67,21 @@ pub struct ReachabilityOutcome {
     #[tabled(rename = "Expr A")]
     #[serde(rename = "Expr A", serialize_with = "serialize_expr")]
     pub expr_a: VarFreeExpression,
-    #[tabled(rename = "Expr B")]
-    #[serde(rename = "Expr B", serialize_with = "serialize_expr")]
-    pub expr_b: VarFreeExpression,
+    /// Expressions to be reached from `expr_a`, a single one when benchmarking pairs
+    #[tabled(rename = "Goals", display_with = "format_goals")]
+    #[serde(rename = "Goals", serialize_with = "serialize_goals")]
+    pub goals: Vec<VarFreeExpression>,
+    /// Index of the goal reached from `expr_a`, if any
+    #[tabled(rename = "Reached Goal", display_with = "format_reached_goal")]
+    #[serde(rename = "Reached Goal")]
+    pub reached_goal: Option<usize>,
+    /// Mean time of the sampled runs
     #[tabled(rename = "Time", display_with = "format_duration")]
     #[serde(rename = "Time (ns)", serialize_with = "serialize_duration")]
     pub time: Duration,
+    #[tabled(rename = "Time Statistics")]
+    #[serde(rename = "Time Statistics", serialize_with = "serialize_statistics")]
+    pub statistics: Statistics,
     #[tabled(
         rename = "Stop Reason",
         display_with = "format_reachability_stop_reason"
@@ -80,14 +111,29 @@ pub fn run_single_with_scheduler<A: Analysis, F>(
     matcher: &dyn Matcher,
     build_scheduler: F,
 ) -> ReachabilityOutcome
+where
+    F: Clone + FnOnce(&[Rule]) -> Box<dyn Scheduler<A>>,
+{
+    run_single_to_set_with_scheduler(rules, expr_a, vec![expr_b], cfg, matcher, build_scheduler)
+}
+
+/// Run a single attempt to reach any of `goals` from `expr_a` with a custom scheduler factory.
+pub fn run_single_to_set_with_scheduler<A: Analysis, F>(
+    rules: &[Rule],
+    expr_a: VarFreeExpression,
+    goals: Vec<VarFreeExpression>,
+    cfg: &SaturationConfig,
+    matcher: &dyn Matcher,
+    build_scheduler: F,
+) -> ReachabilityOutcome
 where
     F: Clone + FnOnce(&[Rule]) -> Box<dyn Scheduler<A>>,
 {
     let start = Instant::now();
-    let res = terms_reachable(
+    let res = terms_reachable_any(
         rules,
         expr_a.clone(),
-        expr_b.clone(),
+        goals.clone(),
         cfg,
         matcher,
         build_scheduler.clone(),
@@ -98,8 +144,10 @@ where
 
     ReachabilityOutcome {
         expr_a,
-        expr_b,
+        goals,
+        reached_goal: res.goal,
         time,
+        statistics: Statistics::default(),
         stop_reason: res.reason,
         applications: res.applications,
         nodes,
@@ -108,47 +156,129 @@ where
 }
 
 /// Generic benchmarking over pairs using a custom scheduler factory closure.
+/// Times of the runs sampled according to `sampling` are summarized in [`Statistics`].
 pub fn benchmark_pairs_with_scheduler<A: Analysis, F>(
     rules: &[Rule],
     pairs: &[(VarFreeExpression, VarFreeExpression)],
     cfg: &SaturationConfig,
     matcher: &dyn Matcher,
-    runs: usize,
+    sampling: &SamplingConfig,
     build_scheduler: F,
 ) -> Vec<ReachabilityOutcome>
 where
     F: Clone + FnOnce(&[Rule]) -> Box<dyn Scheduler<A>>,
 {
-    let mut out = Vec::with_capacity(pairs.len());
-    for (a, b) in pairs {
-        let mut collected = Vec::with_capacity(runs + 1);
-        for _ in 0..(runs + 1) {
-            collected.push(black_box(run_single_with_scheduler::<A, F>(
+    let sets: Vec<_> = pairs
+        .iter()
+        .map(|(a, b)| (a.clone(), vec![b.clone()]))
+        .collect();
+    benchmark_sets_with_scheduler(rules, &sets, cfg, matcher, sampling, build_scheduler)
+}
+
+/// Same as [`benchmark_pairs_with_scheduler`], but every expression is paired with a set
+/// of goals, e.g. to check whether it reduces to any normal form in a list.
+pub fn benchmark_sets_with_scheduler<A: Analysis, F>(
+    rules: &[Rule],
+    sets: &[(VarFreeExpression, Vec<VarFreeExpression>)],
+    cfg: &SaturationConfig,
+    matcher: &dyn Matcher,
+    sampling: &SamplingConfig,
+    build_scheduler: F,
+) -> Vec<ReachabilityOutcome>
+where
+    F: Clone + FnOnce(&[Rule]) -> Box<dyn Scheduler<A>>,
+{
+    let mut out = Vec::with_capacity(sets.len());
+    for (a, goals) in sets {
+        let mut collected = sampling.sample(|| {
+            black_box(run_single_to_set_with_scheduler::<A, F>(
                 black_box(rules),
                 black_box(a.clone()),
-                black_box(b.clone()),
+                black_box(goals.clone()),
                 black_box(cfg),
                 black_box(matcher),
                 black_box(build_scheduler.clone()),
-            )));
-        }
-        let mut avg = collected.remove(0); // warm-up discard
+            ))
+        });
+        let times: Vec<_> = collected.iter().map(|c| c.time).collect();
+        let runs = collected.len();
+        let mut avg = collected.remove(0);
         for c in collected {
             assert_eq!(avg.stop_reason, c.stop_reason);
+            assert_eq!(avg.reached_goal, c.reached_goal);
             assert_eq!(avg.nodes, c.nodes);
             assert_eq!(avg.classes, c.classes);
-            avg.time += c.time;
             avg.applications += c.applications;
         }
-        avg.time /= runs as u32;
+        avg.statistics = Statistics::from_samples(&times, sampling);
+        avg.time = avg.statistics.mean;
         avg.applications /= runs;
         out.push(avg);
     }
     out
 }
 
+/// Same as [`benchmark_sets_with_scheduler`], but goals which `prefilter` proves
+/// never to unify with their expression are skipped before any saturation.
+/// Expressions with all goals pruned are not saturated at all and stop with
+/// [`ReachabilityStopReason::Pruned`], with the time of the check as their time.
+pub fn benchmark_sets_with_prefilter<A: Analysis, F>(
+    rules: &[Rule],
+    sets: &[(VarFreeExpression, Vec<VarFreeExpression>)],
+    cfg: &SaturationConfig,
+    matcher: &dyn Matcher,
+    sampling: &SamplingConfig,
+    prefilter: &AbelianPrefilter,
+    build_scheduler: F,
+) -> Vec<ReachabilityOutcome>
+where
+    F: Clone + FnOnce(&[Rule]) -> Box<dyn Scheduler<A>>,
+{
+    let mut out = Vec::with_capacity(sets.len());
+    for (a, goals) in sets {
+        let start = Instant::now();
+        let admissible: Vec<_> = (0..goals.len())
+            .filter(|&index| prefilter.may_unify(a, &goals[index]))
+            .collect();
+        let time = start.elapsed();
+
+        if admissible.is_empty() {
+            out.push(ReachabilityOutcome {
+                expr_a: a.clone(),
+                goals: goals.clone(),
+                reached_goal: None,
+                time,
+                statistics: Statistics::from_samples(&[time], sampling),
+                stop_reason: ReachabilityStopReason::Pruned,
+                applications: 0,
+                nodes: 0,
+                classes: 0,
+            });
+            continue;
+        }
+
+        let kept = admissible
+            .iter()
+            .map(|&index| goals[index].clone())
+            .collect();
+        let mut outcome = benchmark_sets_with_scheduler(
+            rules,
+            &[(a.clone(), kept)],
+            cfg,
+            matcher,
+            sampling,
+            build_scheduler.clone(),
+        )
+        .remove(0);
+        outcome.goals = goals.clone();
+        outcome.reached_goal = outcome.reached_goal.map(|index| admissible[index]);
+        out.push(outcome);
+    }
+    out
+}
+
 impl Formattable for ReachabilityOutcome {
-    fn calculate_averages(items: &[Self]) -> Option<String> {
+    fn calculate_averages(items: &[Self]) -> Option<Table> {
         if items.is_empty() {
             return None;
         }
@@ -171,17 +301,20 @@ impl Formattable for ReachabilityOutcome {
         let avg_nodes = total_nodes / num_outcomes;
         let avg_classes = total_classes / num_outcomes;
 
-        // Create a formatted average row
-        use tabled::{Table, settings::Style};
+        // Create an average row
 
         #[derive(Tabled)]
         struct AverageRow {
             #[tabled(rename = "Expr A")]
             label: String,
-            #[tabled(rename = "Expr B")]
+            #[tabled(rename = "Goals")]
             empty1: String,
+            #[tabled(rename = "Reached Goal")]
+            reached: String,
             #[tabled(rename = "Time")]
             time: String,
+            #[tabled(rename = "Time Statistics")]
+            statistics: String,
             #[tabled(rename = "Stop Reason")]
             empty2: String,
             #[tabled(rename = "Applications(avg)")]
@@ -195,23 +328,23 @@ impl Formattable for ReachabilityOutcome {
         let avg_row = AverageRow {
             label: "AVERAGE".to_string(),
             empty1: String::new(),
+            reached: String::new(),
             time: format!("{:?}", avg_time),
+            statistics: String::new(),
             empty2: String::new(),
             applications: avg_apps,
             nodes: avg_nodes,
             classes: avg_classes,
         };
 
-        let mut table = Table::new(vec![avg_row]);
-        table.with(Style::rounded());
-        Some(table.to_string())
+        Some(Table::new(vec![avg_row]))
     }
 }
 
 #[cfg(test)]
 mod tests {
     /// Benchmark reachability for multiple expression pairs.
-    /// Performs `runs + 1` executions (dropping the first as warm-up) and averages time/applications.
+    /// Performs `runs + 1` executions (dropping the first as warm-up).
     pub fn benchmark_pairs<A: Analysis>(
         rules: &[Rule],
         pairs: &[(VarFreeExpression, VarFreeExpression)],
@@ -219,7 +352,11 @@ mod tests {
         matcher: &dyn Matcher,
         runs: usize,
     ) -> Vec<ReachabilityOutcome> {
-        benchmark_pairs_with_scheduler::<(), _>(rules, pairs, cfg, matcher, runs, |rs| {
+        let sampling = SamplingConfig {
+            runs,
+            ..Default::default()
+        };
+        benchmark_pairs_with_scheduler::<(), _>(rules, pairs, cfg, matcher, &sampling, |rs| {
             Box::new(RoundRobinScheduler::new(rs.to_vec()))
         })
     }
@@ -305,4 +442,64 @@ mod tests {
             other => panic!("expected limit stop, got {other:?}"),
         }
     }
+
+    #[test]
+    fn reports_reached_goal_of_set() {
+        let lang = Language::simple_math();
+        let rules = rules!(lang; "1" => "2", "2" => "3");
+        let a = lang.parse_no_vars("1").unwrap();
+        let goals = vec![
+            lang.parse_no_vars("4").unwrap(),
+            lang.parse_no_vars("3").unwrap(),
+        ];
+        let cfg = SaturationConfig::default();
+        let sampling = SamplingConfig {
+            runs: 2,
+            ..Default::default()
+        };
+        let outcomes = benchmark_sets_with_scheduler::<(), _>(
+            &rules,
+            &[(a, goals)],
+            &cfg,
+            &TopDownMatcher,
+            &sampling,
+            |rs| Box::new(RoundRobinScheduler::new(rs.to_vec())),
+        );
+        assert_eq!(outcomes.len(), 1);
+        assert!(matches!(
+            outcomes[0].stop_reason,
+            ReachabilityStopReason::ReachedCommonForm { .. }
+        ));
+        assert_eq!(outcomes[0].reached_goal, Some(1));
+    }
+
+    #[test]
+    fn prefilter_prunes_unreachable_goals() {
+        let lang = Language::simple_math();
+        let rules = rules!(lang; "(sin (sin $0))" => "$0");
+        let prefilter = AbelianPrefilter::new(&rules, &lang).unwrap();
+        let parse = |expr| lang.parse_no_vars(expr).unwrap();
+        let sets = [
+            (parse("(sin 1)"), vec![parse("1"), parse("2")]),
+            (parse("(sin (sin 1))"), vec![parse("(sin 1)"), parse("1")]),
+        ];
+        let sampling = SamplingConfig {
+            runs: 1,
+            ..Default::default()
+        };
+
+        let outcomes = benchmark_sets_with_prefilter::<(), _>(
+            &rules,
+            &sets,
+            &SaturationConfig::default(),
+            &TopDownMatcher,
+            &sampling,
+            &prefilter,
+            |rs| Box::new(RoundRobinScheduler::new(rs.to_vec())),
+        );
+        assert_eq!(outcomes[0].stop_reason, ReachabilityStopReason::Pruned);
+        assert_eq!(outcomes[0].nodes, 0);
+        assert_eq!(outcomes[1].goals.len(), 2);
+        assert_eq!(outcomes[1].reached_goal, Some(1));
+    }
 }
@@ -2,21 +2,32 @@
 //!
 //! This module provides benchmarking functionality for measuring the performance
 //! of equality saturation on various expressions and configurations.
+//! Besides saturating e-graphs of expressions one at a time, [`benchmark_batch`]
+//! saturates a single e-graph of all expressions, measuring the amortized win
+//! of sharing their subexpressions and rule matches.
 
 use serde::Serialize;
 use std::collections::BTreeMap;
+use std::fmt;
 use std::hint::black_box;
 use std::time::{Duration, Instant};
-use tabled::Tabled;
+use tabled::{Table, Tabled};
 
 use super::formatter::{Formattable, format_duration};
+use super::reachability::ReachabilityOutcome;
+use super::statistics::{SamplingConfig, Statistics};
 use crate::{
     language::expression::VarFreeExpression,
+    metrics::EGraphMetrics,
     rewriting::{
         egraph::{
             Analysis, DynEGraph, EGraph,
             extraction::Extractor,
-            saturation::{SaturationConfig, SaturationStopReason, Saturator},
+            matching::{Matcher, bottom_up::BottomUpMatcher, top_down::TopDownMatcher},
+            saturation::{
+                RuleReport, SaturationConfig, SaturationReport, SaturationStopReason, Saturator,
+                SimpleSaturator,
+            },
         },
         system::TermRewritingSystem,
     },
@@ -47,8 +58,34 @@ where
     serializer.serialize_str(&expr.to_string())
 }
 
-/// Number of runs (after warm-up) used for averaging.
-pub const RUN_COUNT: usize = 10;
+pub(super) fn serialize_statistics<S>(
+    statistics: &Statistics,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    serializer.collect_str(statistics)
+}
+
+fn format_top_rule(rules: &[RuleOutcome]) -> String {
+    let total: Duration = rules.iter().map(RuleOutcome::time).sum();
+    match rules.iter().max_by_key(|rule| rule.time()) {
+        Some(rule) if !total.is_zero() => format!(
+            "{} ({:.0}%)",
+            rule.rule,
+            100.0 * rule.time().as_secs_f64() / total.as_secs_f64()
+        ),
+        _ => String::new(),
+    }
+}
+
+fn serialize_top_rule<S>(rules: &[RuleOutcome], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    serializer.serialize_str(&format_top_rule(rules))
+}
 
 /// Trait for formatting benchmark outcomes.
 pub trait OutcomeFormatter {
@@ -56,6 +93,81 @@ pub trait OutcomeFormatter {
     fn format_outcomes(&self, outcomes: &[Outcome]) -> String;
     /// Formats a map of saturator names to their outcomes.
     fn format_saturator_outcomes(&self, outcomes_map: BTreeMap<String, Vec<Outcome>>) -> String;
+    /// Formats statistics of rules, e.g. the ones returned by [`rule_totals`].
+    fn format_rule_outcomes(&self, rules: &[RuleOutcome]) -> String;
+    /// Formats a list of reachability outcomes.
+    fn format_reachability_outcomes(&self, outcomes: &[ReachabilityOutcome]) -> String;
+    /// Formats a list of batch outcomes, see [`benchmark_batch`].
+    fn format_batch_outcomes(&self, outcomes: &[BatchOutcome]) -> String;
+}
+
+/// Statistics of a single rule gathered during a benchmarked saturation.
+#[derive(Clone, Debug, Tabled, Serialize)]
+pub struct RuleOutcome {
+    #[tabled(rename = "Rule")]
+    #[serde(rename = "Rule")]
+    pub rule: String,
+    #[tabled(rename = "Matches")]
+    #[serde(rename = "Matches")]
+    pub matches: usize,
+    #[tabled(rename = "Applications")]
+    #[serde(rename = "Applications")]
+    pub applications: usize,
+    #[tabled(rename = "Unions")]
+    #[serde(rename = "Unions")]
+    pub unions: usize,
+    #[tabled(rename = "Match Time", display_with = "format_duration")]
+    #[serde(rename = "Match Time (ns)", serialize_with = "serialize_duration")]
+    pub match_time: Duration,
+    #[tabled(rename = "Apply Time", display_with = "format_duration")]
+    #[serde(rename = "Apply Time (ns)", serialize_with = "serialize_duration")]
+    pub apply_time: Duration,
+}
+
+impl RuleOutcome {
+    fn new(rule: String, report: &RuleReport) -> Self {
+        Self {
+            rule,
+            matches: report.matches,
+            applications: report.applications,
+            unions: report.unions,
+            match_time: report.match_time,
+            apply_time: report.apply_time,
+        }
+    }
+
+    /// Total time spent on the rule.
+    pub fn time(&self) -> Duration {
+        self.match_time + self.apply_time
+    }
+}
+
+impl Formattable for RuleOutcome {
+    fn calculate_averages(_items: &[Self]) -> Option<Table> {
+        None
+    }
+}
+
+/// Sums statistics of rules over `outcomes`, which have to come from the same system.
+/// Rules are sorted by the total time spent on them, in descending order.
+pub fn rule_totals(outcomes: &[Outcome]) -> Vec<RuleOutcome> {
+    let mut totals: Vec<RuleOutcome> = Vec::new();
+    for outcome in outcomes {
+        for (index, rule) in outcome.rules.iter().enumerate() {
+            match totals.get_mut(index) {
+                Some(total) => {
+                    total.matches += rule.matches;
+                    total.applications += rule.applications;
+                    total.unions += rule.unions;
+                    total.match_time += rule.match_time;
+                    total.apply_time += rule.apply_time;
+                }
+                None => totals.push(rule.clone()),
+            }
+        }
+    }
+    totals.sort_by_key(|rule| std::cmp::Reverse(rule.time()));
+    totals
 }
 
 #[derive(Clone, Debug, Tabled, Serialize)]
@@ -66,9 +178,13 @@ pub struct Outcome {
     #[tabled(rename = "Extracted Expression")]
     #[serde(rename = "Extracted Expression", serialize_with = "serialize_expr")]
     pub extracted_expression: VarFreeExpression,
+    /// Mean time of the sampled runs
     #[tabled(rename = "Time", display_with = "format_duration")]
     #[serde(rename = "Time (ns)", serialize_with = "serialize_duration")]
     pub time: Duration,
+    #[tabled(rename = "Time Statistics")]
+    #[serde(rename = "Time Statistics", serialize_with = "serialize_statistics")]
+    pub statistics: Statistics,
     #[tabled(rename = "Stop Reason", display_with = "format_stop_reason")]
     #[serde(rename = "Stop Reason", serialize_with = "serialize_stop_reason")]
     pub stop_reason: SaturationStopReason,
@@ -81,6 +197,14 @@ pub struct Outcome {
     #[tabled(rename = "Min Cost")]
     #[serde(rename = "Min Cost")]
     pub min_cost: usize,
+    /// Statistics of the rules of the system, in the order of the rules
+    #[tabled(rename = "Top Rule", display_with = "format_top_rule")]
+    #[serde(rename = "Top Rule", serialize_with = "serialize_top_rule")]
+    pub rules: Vec<RuleOutcome>,
+    /// Work done by the e-graph of the first run, only recorded with the `metrics` feature
+    #[tabled(skip)]
+    #[serde(skip)]
+    pub metrics: EGraphMetrics,
 }
 
 impl PartialEq for Outcome {
@@ -96,9 +220,34 @@ impl PartialEq for Outcome {
 
 impl Eq for Outcome {}
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize)]
 pub struct BenchmarkConfig {
     pub saturation_config: SaturationConfig,
+    pub sampling: SamplingConfig,
+}
+
+/// Errors of saturation benchmarks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BenchmarkError {
+    /// A saturator returned without setting the stop reason of its report
+    MissingStopReason,
+}
+
+impl fmt::Display for BenchmarkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BenchmarkError::MissingStopReason => {
+                write!(f, "Saturator returned without a stop reason")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BenchmarkError {}
+
+/// Returns the reason why the saturation of `report` stopped.
+fn stop_reason(report: &SaturationReport) -> Result<SaturationStopReason, BenchmarkError> {
+    report.stop_reason.ok_or(BenchmarkError::MissingStopReason)
 }
 
 fn run_single_benchmark<A, E>(
@@ -107,15 +256,16 @@ fn run_single_benchmark<A, E>(
     config: &BenchmarkConfig,
     extractor: &E,
     saturator: &dyn Saturator<A>,
-) -> Outcome
+) -> Result<Outcome, BenchmarkError>
 where
     A: Analysis,
     E: Extractor<Cost = usize>,
 {
-    let (mut egraph, class_id) = EGraph::<A>::from_expression_with_id(expression.clone());
+    let mut egraph = trs.egraph::<A>();
+    let class_id = egraph.add_expression(expression.clone());
 
     let start_time = Instant::now();
-    let stop_reason = saturator.saturate(&mut egraph, trs.rules(), &config.saturation_config);
+    let report = saturator.saturate(&mut egraph, trs.rules(), &config.saturation_config);
     let time = start_time.elapsed();
 
     let extraction_result = extractor.extract(&egraph, class_id);
@@ -128,15 +278,28 @@ where
         None => 0, // Default cost if no extraction or error
     };
 
-    Outcome {
+    let rules = trs
+        .rules()
+        .iter()
+        .enumerate()
+        .map(|(index, rule)| {
+            let rule_report = report.rules.get(index).cloned().unwrap_or_default();
+            RuleOutcome::new(rule.label(trs.language()), &rule_report)
+        })
+        .collect();
+
+    Ok(Outcome {
         original_expression: expression,
         extracted_expression,
         time,
-        stop_reason,
+        statistics: Statistics::default(),
+        stop_reason: stop_reason(&report)?,
         nodes: egraph.actual_node_count(),
         classes: egraph.class_count(),
         min_cost,
-    }
+        rules,
+        metrics: egraph.metrics().clone(),
+    })
 }
 
 pub fn benchmark<A, E>(
@@ -145,7 +308,7 @@ pub fn benchmark<A, E>(
     config: &BenchmarkConfig,
     extractor: &E,
     saturator: &dyn Saturator<A>,
-) -> Vec<Outcome>
+) -> Result<Vec<Outcome>, BenchmarkError>
 where
     A: Analysis,
     E: Extractor<Cost = usize>,
@@ -153,8 +316,21 @@ where
     let mut averaged_outcomes = Vec::with_capacity(expressions.len());
 
     for expression in expressions {
-        let mut expression_outcomes =
-            benchmark_multiple_times(trs, config, extractor, saturator, expression.clone());
+        let mut expression_outcomes = config
+            .sampling
+            .sample(|| {
+                black_box(run_single_benchmark(
+                    black_box(trs),
+                    black_box(expression.clone()),
+                    black_box(config),
+                    black_box(extractor),
+                    black_box(saturator),
+                ))
+            })
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?;
+        let times: Vec<_> = expression_outcomes.iter().map(|o| o.time).collect();
+        let run_count = expression_outcomes.len() as u32;
         let mut averaged_outcome = expression_outcomes.remove(0);
 
         for (i, outcome) in expression_outcomes.into_iter().enumerate() {
@@ -162,19 +338,27 @@ where
                 averaged_outcome,
                 outcome,
                 "Outcome mismatch in run {} for expression {:?}. Expected {:?}, got {:?}",
-                i + 1, // +1 because we removed the first run
+                i + 1,
                 averaged_outcome.original_expression,
                 averaged_outcome,
                 outcome
             );
-            averaged_outcome.time += outcome.time;
+            for (total, rule) in averaged_outcome.rules.iter_mut().zip(&outcome.rules) {
+                total.match_time += rule.match_time;
+                total.apply_time += rule.apply_time;
+            }
         }
 
-        averaged_outcome.time /= RUN_COUNT as u32;
+        averaged_outcome.statistics = Statistics::from_samples(&times, &config.sampling);
+        averaged_outcome.time = averaged_outcome.statistics.mean;
+        for rule in &mut averaged_outcome.rules {
+            rule.match_time /= run_count;
+            rule.apply_time /= run_count;
+        }
         averaged_outcomes.push(averaged_outcome);
     }
 
-    averaged_outcomes
+    Ok(averaged_outcomes)
 }
 
 pub fn benchmark_saturators<A, E>(
@@ -183,7 +367,7 @@ pub fn benchmark_saturators<A, E>(
     config: &BenchmarkConfig,
     extractor: &E,
     saturators: BTreeMap<String, Box<dyn Saturator<A>>>,
-) -> BTreeMap<String, Vec<Outcome>>
+) -> Result<BTreeMap<String, Vec<Outcome>>, BenchmarkError>
 where
     A: Analysis,
     E: Extractor<Cost = usize>,
@@ -191,45 +375,192 @@ where
     saturators
         .into_iter()
         .map(|(name, saturator)| {
-            (
-                name,
-                benchmark(trs, expressions, config, extractor, saturator.as_ref()),
-            )
+            let outcomes = benchmark(trs, expressions, config, extractor, saturator.as_ref())?;
+            Ok((name, outcomes))
         })
         .collect()
 }
 
-/// Benchmarks saturation of `expression` `RUN_COUNT + 1` times,
-/// rejecting the first outcome as cache warm-up
-fn benchmark_multiple_times<A, E>(
+/// Benchmarks [`SimpleSaturator`]s finding matches with a [`BottomUpMatcher`]
+/// and a [`TopDownMatcher`], whose outcomes are named `bottom_up` and `top_down`.
+///
+/// Both matchers find the same matches, but in different orders, so the outcomes
+/// of saturations which are not stopped early differ only in times.
+pub fn benchmark_matchers<A, E>(
     trs: &TermRewritingSystem,
+    expressions: &[VarFreeExpression],
     config: &BenchmarkConfig,
     extractor: &E,
-    saturator: &dyn Saturator<A>,
-    expression: VarFreeExpression,
-) -> Vec<Outcome>
+) -> Result<BTreeMap<String, Vec<Outcome>>, BenchmarkError>
 where
     A: Analysis,
     E: Extractor<Cost = usize>,
 {
-    let mut expression_outcomes: Vec<Outcome> = Vec::with_capacity(RUN_COUNT + 1);
-    for _ in 0..(RUN_COUNT + 1) {
-        expression_outcomes.push(black_box(run_single_benchmark(
-            black_box(trs),
-            black_box(expression.clone()),
-            black_box(config),
-            black_box(extractor),
-            black_box(saturator),
-        )));
+    let matchers: [(&str, Box<dyn Matcher>); 2] = [
+        ("bottom_up", Box::new(BottomUpMatcher)),
+        ("top_down", Box::new(TopDownMatcher)),
+    ];
+    let saturators = matchers
+        .into_iter()
+        .map(|(name, matcher)| {
+            let saturator: Box<dyn Saturator<A>> = Box::new(SimpleSaturator::new(matcher));
+            (String::from(name), saturator)
+        })
+        .collect();
+
+    benchmark_saturators(trs, expressions, config, extractor, saturators)
+}
+
+fn format_speedup(speedup: &f64) -> String {
+    format!("{speedup:.2}x")
+}
+
+/// Outcome of saturating a single e-graph of all expressions of a benchmark,
+/// compared with saturating their e-graphs one at a time.
+///
+/// Limits of the saturation apply to the whole e-graph, so unless both saturations
+/// saturate, the batch one may stop earlier.
+#[derive(Clone, Debug, Tabled, Serialize)]
+pub struct BatchOutcome {
+    #[tabled(rename = "Saturator")]
+    #[serde(rename = "Saturator")]
+    pub saturator: String,
+    #[tabled(rename = "Expressions")]
+    #[serde(rename = "Expressions")]
+    pub expressions: usize,
+    /// Mean time of the sampled batch saturations
+    #[tabled(rename = "Batch Time", display_with = "format_duration")]
+    #[serde(rename = "Batch Time (ns)", serialize_with = "serialize_duration")]
+    pub batch_time: Duration,
+    #[tabled(rename = "Batch Time Statistics")]
+    #[serde(
+        rename = "Batch Time Statistics",
+        serialize_with = "serialize_statistics"
+    )]
+    pub statistics: Statistics,
+    /// Sum of the mean times of saturations of single expressions
+    #[tabled(rename = "Individual Time", display_with = "format_duration")]
+    #[serde(rename = "Individual Time (ns)", serialize_with = "serialize_duration")]
+    pub individual_time: Duration,
+    /// `individual_time` divided by `batch_time`
+    #[tabled(rename = "Speedup", display_with = "format_speedup")]
+    #[serde(rename = "Speedup")]
+    pub speedup: f64,
+    #[tabled(rename = "Stop Reason", display_with = "format_stop_reason")]
+    #[serde(rename = "Stop Reason", serialize_with = "serialize_stop_reason")]
+    pub stop_reason: SaturationStopReason,
+    #[tabled(rename = "Nodes")]
+    #[serde(rename = "Nodes")]
+    pub nodes: usize,
+    /// Sum of the numbers of nodes of e-graphs of single expressions
+    #[tabled(rename = "Individual Nodes")]
+    #[serde(rename = "Individual Nodes")]
+    pub individual_nodes: usize,
+    /// Sum of the costs of expressions extracted from the batch e-graph,
+    /// `None` if any of them could not be extracted
+    #[tabled(rename = "Min Cost", display_with = "format_min_cost")]
+    #[serde(rename = "Min Cost")]
+    pub min_cost: Option<usize>,
+    /// Sum of the costs of expressions extracted from e-graphs of single expressions
+    #[tabled(rename = "Individual Min Cost")]
+    #[serde(rename = "Individual Min Cost")]
+    pub individual_min_cost: usize,
+}
+
+fn format_min_cost(cost: &Option<usize>) -> String {
+    cost.map(|cost| cost.to_string()).unwrap_or_default()
+}
+
+impl Formattable for BatchOutcome {
+    fn calculate_averages(_items: &[Self]) -> Option<Table> {
+        None
     }
+}
+
+/// Saturates an e-graph of all `expressions` at once and extracts their cheapest equivalents
+/// (see [`EGraph::from_expressions`]), returning the saturation time with the report
+/// and the e-graph, and the total cost of the extracted expressions, if all of them were extracted.
+fn run_batch<A, E>(
+    trs: &TermRewritingSystem,
+    expressions: &[VarFreeExpression],
+    config: &BenchmarkConfig,
+    extractor: &E,
+    saturator: &dyn Saturator<A>,
+) -> (Duration, SaturationReport, EGraph<A>, Option<usize>)
+where
+    A: Analysis,
+    E: Extractor<Cost = usize>,
+{
+    let mut egraph = trs.egraph::<A>();
+    let roots: Vec<_> = expressions
+        .iter()
+        .map(|expression| egraph.add_expression(expression.clone()))
+        .collect();
+
+    let start_time = Instant::now();
+    let report = saturator.saturate(&mut egraph, trs.rules(), &config.saturation_config);
+    let time = start_time.elapsed();
+
+    let min_cost = extractor
+        .extract_roots(&egraph, &roots)
+        .into_iter()
+        .map(|result| result.map(|result| *result.cost()))
+        .sum();
+
+    (time, report, egraph, min_cost)
+}
 
-    expression_outcomes.remove(0);
-    // Cache warm-up
-    expression_outcomes
+/// Benchmarks every saturator of `saturators` both on single expressions, as [`benchmark`],
+/// and on a single e-graph of all `expressions`, see [`BatchOutcome`].
+pub fn benchmark_batch<A, E>(
+    trs: &TermRewritingSystem,
+    expressions: &[VarFreeExpression],
+    config: &BenchmarkConfig,
+    extractor: &E,
+    saturators: BTreeMap<String, Box<dyn Saturator<A>>>,
+) -> Result<Vec<BatchOutcome>, BenchmarkError>
+where
+    A: Analysis,
+    E: Extractor<Cost = usize>,
+{
+    saturators
+        .into_iter()
+        .map(|(name, saturator)| {
+            let individual = benchmark(trs, expressions, config, extractor, saturator.as_ref())?;
+
+            let mut runs = config.sampling.sample(|| {
+                black_box(run_batch(
+                    black_box(trs),
+                    black_box(expressions),
+                    black_box(config),
+                    black_box(extractor),
+                    black_box(saturator.as_ref()),
+                ))
+            });
+            let times: Vec<_> = runs.iter().map(|(time, ..)| *time).collect();
+            let statistics = Statistics::from_samples(&times, &config.sampling);
+            let (_, report, egraph, min_cost) = runs.remove(0);
+
+            let individual_time: Duration = individual.iter().map(|outcome| outcome.time).sum();
+            Ok(BatchOutcome {
+                saturator: name,
+                expressions: expressions.len(),
+                batch_time: statistics.mean,
+                individual_time,
+                speedup: individual_time.as_secs_f64() / statistics.mean.as_secs_f64(),
+                statistics,
+                stop_reason: stop_reason(&report)?,
+                nodes: egraph.actual_node_count(),
+                individual_nodes: individual.iter().map(|outcome| outcome.nodes).sum(),
+                min_cost,
+                individual_min_cost: individual.iter().map(|outcome| outcome.min_cost).sum(),
+            })
+        })
+        .collect()
 }
 
 impl Formattable for Outcome {
-    fn calculate_averages(items: &[Self]) -> Option<String> {
+    fn calculate_averages(items: &[Self]) -> Option<Table> {
         if items.is_empty() {
             return None;
         }
@@ -252,8 +583,7 @@ impl Formattable for Outcome {
         let avg_classes = num_classes_sum / num_outcomes;
         let avg_min_cost = min_cost_sum / num_outcomes;
 
-        // Create a formatted average row
-        use tabled::{Table, settings::Style};
+        // Create an average row
 
         #[derive(Tabled)]
         struct AverageRow {
@@ -263,6 +593,8 @@ impl Formattable for Outcome {
             empty1: String,
             #[tabled(rename = "Time")]
             time: String,
+            #[tabled(rename = "Time Statistics")]
+            statistics: String,
             #[tabled(rename = "Stop Reason")]
             empty2: String,
             #[tabled(rename = "Nodes")]
@@ -271,20 +603,187 @@ impl Formattable for Outcome {
             classes: u64,
             #[tabled(rename = "Min Cost")]
             min_cost: u64,
+            #[tabled(rename = "Top Rule")]
+            top_rule: String,
         }
 
         let avg_row = AverageRow {
             label: "AVERAGE".to_string(),
             empty1: String::new(),
             time: format!("{:?}", avg_total_time),
+            statistics: String::new(),
             empty2: String::new(),
             nodes: avg_num_nodes,
             classes: avg_classes,
             min_cost: avg_min_cost,
+            top_rule: format_top_rule(&rule_totals(items)),
+        };
+
+        Some(Table::new(vec![avg_row]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use std::collections::BTreeMap;
+
+    use super::{BenchmarkConfig, benchmark, benchmark_batch, benchmark_matchers, rule_totals};
+    use crate::{
+        benchmark::{
+            RandomGenerationConfig, SamplingConfig, generate_random_expression_by_size_with_config,
+        },
+        language::{Language, arities::Arities},
+        macros::rules,
+        rewriting::{
+            egraph::{
+                extraction::{SimpleExtractor, children_cost_sum},
+                matching::bottom_up::BottomUpMatcher,
+                saturation::{SaturationConfig, SaturationStopReason, Saturator, SimpleSaturator},
+            },
+            system::TermRewritingSystem,
+        },
+        utils::json::load_json,
+    };
+
+    #[test]
+    fn rule_statistics() {
+        let lang = Language::simple_math();
+        let rules = rules!(lang;
+            "identity": "(* $0 1)" => "$0",
+            "(+ $0 0)" => "$0",
+        );
+        let expressions = vec![lang.parse_no_vars("(* (+ 2 0) 1)").unwrap()];
+        let trs = TermRewritingSystem::new(lang, rules);
+        let extractor = SimpleExtractor::<usize, _, _>::new(
+            |_| 1,
+            |symbol, costs| Some(1 + children_cost_sum(symbol, costs)?),
+        );
+        let saturator = SimpleSaturator::new(Box::new(BottomUpMatcher));
+
+        let outcomes = benchmark::<(), _>(
+            &trs,
+            &expressions,
+            &BenchmarkConfig::default(),
+            &extractor,
+            &saturator,
+        )
+        .unwrap();
+
+        let rules = &outcomes[0].rules;
+        assert_eq!(rules[0].rule, "identity");
+        assert_eq!(rules[0].applications, 1);
+        assert_eq!(rules[0].unions, 1);
+        assert_eq!(rules[1].rule, "(+ $0 0) => $0");
+        assert_eq!(rules[1].applications, 1);
+
+        let mut totals: Vec<_> = rule_totals(&outcomes)
+            .into_iter()
+            .map(|rule| rule.rule)
+            .collect();
+        totals.sort();
+        assert_eq!(totals, ["(+ $0 0) => $0", "identity"]);
+    }
+
+    #[test]
+    fn batch_shares_subexpressions() {
+        let lang = Language::simple_math();
+        let rules = rules!(lang;
+            "(* $0 1)" => "$0",
+            "(+ $0 0)" => "$0",
+        );
+        let expressions = vec![
+            lang.parse_no_vars("(* (+ 2 0) 1)").unwrap(),
+            lang.parse_no_vars("(+ 2 0)").unwrap(),
+        ];
+        let trs = TermRewritingSystem::new(lang, rules);
+        let extractor = SimpleExtractor::<usize, _, _>::new(
+            |_| 1,
+            |symbol, costs| Some(1 + children_cost_sum(symbol, costs)?),
+        );
+        let config = BenchmarkConfig {
+            sampling: SamplingConfig {
+                warm_up_runs: 0,
+                runs: 2,
+                ..Default::default()
+            },
+            ..Default::default()
         };
+        let saturator: Box<dyn Saturator<()>> =
+            Box::new(SimpleSaturator::new(Box::new(BottomUpMatcher)));
+
+        let outcomes = benchmark_batch(
+            &trs,
+            &expressions,
+            &config,
+            &extractor,
+            BTreeMap::from([(String::from("simple"), saturator)]),
+        )
+        .unwrap();
+
+        let outcome = &outcomes[0];
+        assert_eq!(outcome.saturator, "simple");
+        assert_eq!(outcome.expressions, 2);
+        assert_eq!(outcome.statistics.samples + outcome.statistics.outliers, 2);
+        assert_eq!(outcome.stop_reason, SaturationStopReason::Saturated);
+        // Both expressions are equivalent to 2
+        assert_eq!(outcome.min_cost, Some(2));
+        assert_eq!(outcome.individual_min_cost, 2);
+        assert!(outcome.nodes < outcome.individual_nodes);
+    }
 
-        let mut table = Table::new(vec![avg_row]);
-        table.with(Style::rounded());
-        Some(table.to_string())
+    #[test]
+    fn matchers_agree_on_standard_systems() {
+        let jsons = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("jsons");
+        let extractor = SimpleExtractor::<usize, _, _>::new(
+            |_| 1,
+            |symbol, costs| Some(1 + children_cost_sum(symbol, costs)?),
+        );
+        let config = BenchmarkConfig {
+            saturation_config: SaturationConfig {
+                max_nodes: Some(300),
+                ..Default::default()
+            },
+            sampling: SamplingConfig {
+                warm_up_runs: 0,
+                runs: 1,
+                ..Default::default()
+            },
+        };
+
+        for system in ["algebra", "arithmetic", "logic", "simple-math"] {
+            let trs = TermRewritingSystem::from_directory(jsons.join(system)).unwrap();
+            let arities: Arities = load_json(jsons.join(system).join("arities.json")).unwrap();
+            let generation = arities.map.iter().fold(
+                RandomGenerationConfig::from_language(trs.language()),
+                |config, (&symbol_id, arities)| {
+                    config.with_symbol_arities(symbol_id, arities.clone())
+                },
+            );
+            let mut rng = StdRng::seed_from_u64(3);
+            let expressions: Vec<_> = (0..3)
+                .map(|_| {
+                    generate_random_expression_by_size_with_config(
+                        trs.language(),
+                        5,
+                        &mut rng,
+                        &generation,
+                    )
+                    .unwrap()
+                })
+                .collect();
+
+            let outcomes =
+                benchmark_matchers::<(), _>(&trs, &expressions, &config, &extractor).unwrap();
+            for (bottom_up, top_down) in outcomes["bottom_up"].iter().zip(&outcomes["top_down"]) {
+                if bottom_up.stop_reason == SaturationStopReason::Saturated {
+                    assert_eq!(top_down.stop_reason, SaturationStopReason::Saturated);
+                    assert_eq!(bottom_up.nodes, top_down.nodes);
+                    assert_eq!(bottom_up.classes, top_down.classes);
+                    assert_eq!(bottom_up.min_cost, top_down.min_cost);
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,288 @@
+//! Statistics of repeated benchmark runs.
+//!
+//! Every benchmarked computation is first run a number of times to warm up caches,
+//! after which its timings are sampled. Samples lying outside of Tukey's fences are
+//! rejected as outliers, and the remaining ones are summarized by their median, mean,
+//! standard deviation and extremes, together with a bootstrapped confidence interval
+//! of the mean.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+
+use crate::utils::rng::seeded_rng;
+
+/// How timings of a benchmarked computation are sampled and summarized.
+///
+/// Missing fields are deserialized as their defaults.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SamplingConfig {
+    /// Number of runs performed and discarded before sampling
+    pub warm_up_runs: usize,
+    /// Number of sampled runs, has to be positive
+    pub runs: usize,
+    /// Samples further than `outlier_fence` interquartile ranges from the quartiles are rejected,
+    /// `None` keeps all samples, has to be non-negative
+    pub outlier_fence: Option<f64>,
+    /// Confidence level of [`Statistics::confidence_interval`] between 0 and 1, e.g. `0.95`
+    pub confidence_level: f64,
+    /// Number of bootstrap resamples used to estimate the confidence interval
+    pub resamples: usize,
+    /// Seed of the bootstrap resampling, `None` uses a random one
+    pub seed: Option<u64>,
+}
+
+impl Default for SamplingConfig {
+    fn default() -> Self {
+        Self {
+            warm_up_runs: 1,
+            runs: 10,
+            outlier_fence: Some(1.5),
+            confidence_level: 0.95,
+            resamples: 1000,
+            seed: None,
+        }
+    }
+}
+
+impl SamplingConfig {
+    /// Runs `f` `warm_up_runs + runs` times, returning the results of the sampled runs.
+    pub fn sample<T>(&self, mut f: impl FnMut() -> T) -> Vec<T> {
+        assert!(self.runs > 0, "At least one run has to be sampled");
+        for _ in 0..self.warm_up_runs {
+            f();
+        }
+        (0..self.runs).map(|_| f()).collect()
+    }
+}
+
+/// Summary of sampled timings.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Statistics {
+    /// Number of samples which were not rejected as outliers
+    #[serde(rename = "Samples")]
+    pub samples: usize,
+    /// Number of samples rejected as outliers
+    #[serde(rename = "Outliers")]
+    pub outliers: usize,
+    /// Mean of the samples
+    #[serde(rename = "Mean (ns)", serialize_with = "serialize_duration")]
+    pub mean: Duration,
+    /// Median of the samples
+    #[serde(rename = "Median (ns)", serialize_with = "serialize_duration")]
+    pub median: Duration,
+    /// Sample standard deviation, zero for a single sample
+    #[serde(rename = "Std Dev (ns)", serialize_with = "serialize_duration")]
+    pub std_dev: Duration,
+    /// Shortest sample
+    #[serde(rename = "Min (ns)", serialize_with = "serialize_duration")]
+    pub min: Duration,
+    /// Longest sample
+    #[serde(rename = "Max (ns)", serialize_with = "serialize_duration")]
+    pub max: Duration,
+    /// Bounds of the confidence interval of the mean
+    #[serde(
+        rename = "Confidence Interval (ns)",
+        serialize_with = "serialize_interval"
+    )]
+    pub confidence_interval: (Duration, Duration),
+}
+
+fn serialize_duration<S>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    serializer.serialize_u128(duration.as_nanos())
+}
+
+fn serialize_interval<S>(interval: &(Duration, Duration), serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    use serde::ser::SerializeTuple;
+    let mut tuple = serializer.serialize_tuple(2)?;
+    tuple.serialize_element(&interval.0.as_nanos())?;
+    tuple.serialize_element(&interval.1.as_nanos())?;
+    tuple.end()
+}
+
+impl Statistics {
+    /// Computes statistics of `samples`, rejecting outliers as configured by `config`.
+    /// Statistics of samples which would all be rejected are computed from all of them.
+    /// Panics if the fence of `config` is negative or its confidence level is not between 0 and 1.
+    pub fn from_samples(samples: &[Duration], config: &SamplingConfig) -> Self {
+        assert!(
+            config.outlier_fence.is_none_or(|fence| fence >= 0.0),
+            "The outlier fence has to be non-negative"
+        );
+        assert!(
+            config.confidence_level > 0.0 && config.confidence_level < 1.0,
+            "The confidence level has to be between 0 and 1"
+        );
+        if samples.is_empty() {
+            return Self::default();
+        }
+
+        let mut sorted: Vec<f64> = samples.iter().map(Duration::as_secs_f64).collect();
+        sorted.sort_by(f64::total_cmp);
+
+        if let Some(fence) = config.outlier_fence {
+            let q1 = quantile(&sorted, 0.25);
+            let q3 = quantile(&sorted, 0.75);
+            let iqr = q3 - q1;
+            let kept: Vec<_> = sorted
+                .iter()
+                .copied()
+                .filter(|&x| x >= q1 - fence * iqr && x <= q3 + fence * iqr)
+                .collect();
+            if !kept.is_empty() {
+                sorted = kept;
+            }
+        }
+
+        let n = sorted.len() as f64;
+        let mean = sorted.iter().sum::<f64>() / n;
+        let variance = if sorted.len() > 1 {
+            sorted.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0)
+        } else {
+            0.0
+        };
+
+        Self {
+            samples: sorted.len(),
+            outliers: samples.len() - sorted.len(),
+            mean: Duration::from_secs_f64(mean),
+            median: Duration::from_secs_f64(quantile(&sorted, 0.5)),
+            std_dev: Duration::from_secs_f64(variance.sqrt()),
+            min: Duration::from_secs_f64(sorted[0]),
+            max: Duration::from_secs_f64(sorted[sorted.len() - 1]),
+            confidence_interval: bootstrap_interval(&sorted, config),
+        }
+    }
+}
+
+impl fmt::Display for Statistics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} ± {:?} [{:?}, {:?}]",
+            self.median, self.std_dev, self.confidence_interval.0, self.confidence_interval.1
+        )?;
+        if self.outliers > 0 {
+            write!(f, " ({} outliers)", self.outliers)?;
+        }
+        Ok(())
+    }
+}
+
+/// Linearly interpolated quantile of sorted, non-empty `values`.
+fn quantile(values: &[f64], q: f64) -> f64 {
+    let position = q * (values.len() - 1) as f64;
+    let lower = position.floor() as usize;
+    let upper = position.ceil() as usize;
+    values[lower] + (values[upper] - values[lower]) * (position - lower as f64)
+}
+
+/// Percentile bootstrap confidence interval of the mean of `values`.
+fn bootstrap_interval(values: &[f64], config: &SamplingConfig) -> (Duration, Duration) {
+    let (mut rng, _) = seeded_rng(config.seed);
+    let mut means: Vec<f64> = (0..config.resamples.max(1))
+        .map(|_| {
+            (0..values.len())
+                .map(|_| values[rng.gen_range(0..values.len())])
+                .sum::<f64>()
+                / values.len() as f64
+        })
+        .collect();
+    means.sort_by(f64::total_cmp);
+
+    let alpha = (1.0 - config.confidence_level) / 2.0;
+    (
+        Duration::from_secs_f64(quantile(&means, alpha)),
+        Duration::from_secs_f64(quantile(&means, 1.0 - alpha)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{SamplingConfig, Statistics};
+
+    #[test]
+    fn rejects_outliers() {
+        let mut samples: Vec<_> = (10..20).map(Duration::from_micros).collect();
+        samples.push(Duration::from_millis(10));
+        let config = SamplingConfig::default();
+
+        let statistics = Statistics::from_samples(&samples, &config);
+        assert_eq!(statistics.samples, 10);
+        assert_eq!(statistics.outliers, 1);
+        assert_eq!(statistics.min, Duration::from_micros(10));
+        assert_eq!(statistics.max, Duration::from_micros(19));
+        assert_eq!(statistics.median.as_nanos(), 14500);
+        assert_eq!(statistics.mean.as_nanos(), 14500);
+        assert!(statistics.confidence_interval.0 <= statistics.mean);
+        assert!(statistics.mean <= statistics.confidence_interval.1);
+        assert!(statistics.confidence_interval.0 >= statistics.min);
+
+        let statistics = Statistics::from_samples(
+            &samples,
+            &SamplingConfig {
+                outlier_fence: None,
+                ..config
+            },
+        );
+        assert_eq!(statistics.outliers, 0);
+        assert_eq!(statistics.max, Duration::from_millis(10));
+
+        let seeded = SamplingConfig {
+            seed: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(
+            Statistics::from_samples(&samples, &seeded),
+            Statistics::from_samples(&samples, &seeded)
+        );
+    }
+
+    #[test]
+    fn keeps_samples_all_rejected_by_fence() {
+        let samples = [Duration::from_micros(10), Duration::from_micros(20)];
+        let config = SamplingConfig {
+            outlier_fence: Some(0.0),
+            ..Default::default()
+        };
+
+        let statistics = Statistics::from_samples(&samples, &config);
+        assert_eq!(statistics.samples, 2);
+        assert_eq!(statistics.min, Duration::from_micros(10));
+    }
+
+    #[test]
+    #[should_panic(expected = "non-negative")]
+    fn rejects_negative_fence() {
+        let config = SamplingConfig {
+            outlier_fence: Some(-1.0),
+            ..Default::default()
+        };
+        Statistics::from_samples(&[Duration::from_micros(10)], &config);
+    }
+
+    #[test]
+    fn warms_up_before_sampling() {
+        let mut count = 0;
+        let config = SamplingConfig {
+            warm_up_runs: 2,
+            runs: 3,
+            ..Default::default()
+        };
+        let samples = config.sample(|| {
+            count += 1;
+            count
+        });
+        assert_eq!(samples, [3, 4, 5]);
+    }
+}
@@ -0,0 +1,85 @@
+//! Binary for generating datasets of rewrite distances labelled with heuristic values.
+//!
+//! This binary:
+//! 1. Loads a term rewriting system (TRS) and language from JSON
+//! 2. Generates random expressions E of specified size with variables
+//! 3. Applies random rewrites to every E creating E'
+//! 4. Finds the shortest rewrite distance from E to E' by breadth-first search up to a bound
+//! 5. Evaluates every implemented heuristic on the pair
+//! 6. Outputs one JSON record per pair, one per line, see
+//!    `verbum::rewriting::linear_heuristic::DistanceRecord`
+
+use clap::Parser;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use verbum::rewriting::experiments::{Experiment, HeuristicExperimentConfig};
+
+/// CLI arguments for dataset generation
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Generate expression pairs labelled with rewrite distances and heuristic values", long_about = None)]
+struct Args {
+    /// Path to directory containing TRS JSON files (language.json, trs.json, and arities.json)
+    #[arg(short = 't', long)]
+    trs: PathBuf,
+
+    /// Expression size (n)
+    #[arg(short = 'n', long)]
+    size: usize,
+
+    /// Variable count (v) - maximum variable ID will be v-1
+    #[arg(short = 'v', long)]
+    variables: usize,
+
+    /// Number of random rewrite applications (a)
+    #[arg(short = 'a', long)]
+    applications: usize,
+
+    /// Number of records to generate
+    #[arg(short = 'c', long)]
+    count: usize,
+
+    /// Maximum distance searched for, larger distances are recorded as unknown
+    #[arg(short = 'd', long, default_value_t = 4)]
+    max_distance: usize,
+
+    /// Maximum number of expressions visited by a single search
+    #[arg(long, default_value_t = 100_000)]
+    max_visited: usize,
+
+    /// Output JSONL file path
+    #[arg(short = 'o', long)]
+    output: PathBuf,
+
+    /// Seed of the random choices, a random one is used and printed if not given
+    #[arg(short = 's', long)]
+    seed: Option<u64>,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+
+    let mut config =
+        HeuristicExperimentConfig::new(&args.trs, args.size, args.variables, args.applications);
+    config.seed = args.seed;
+
+    println!("Loading TRS from {:?}...", args.trs);
+    let mut experiment = Experiment::new(&config)?;
+    println!("Using seed {}", experiment.seed());
+
+    let mut output = BufWriter::new(File::create(&args.output)?);
+    let mut found = 0;
+    for index in 0..args.count {
+        let record = experiment.distance_record(args.max_distance, args.max_visited)?;
+        found += record.distance.is_some() as usize;
+        writeln!(output, "{}", serde_json::to_string(&record)?)?;
+        println!("Record {}: distance {:?}", index, record.distance);
+    }
+    output.flush()?;
+
+    println!("Saved {} records to {:?}", args.count, args.output);
+    println!("Distances found for {} of them", found);
+
+    Ok(())
+}
@@ -15,18 +15,10 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::error::Error;
 use std::path::PathBuf;
-use verbum::benchmark::{
-    RandomGenerationConfig, VariableGenerationConfig,
-    generate_random_expression_by_size_with_variables,
-};
-use verbum::language::arities::Arities;
 use verbum::language::expression::{AnyExpression, Expression, VariableId};
-use verbum::rewriting::random::rewrite_expression;
-use verbum::rewriting::strings::{
-    expression_to_abelian_vector, expression_to_paths, rules_to_abelian_matrix, to_string_language,
-};
-use verbum::rewriting::system::TermRewritingSystem;
-use verbum::utils::json::{load_json, save_json};
+use verbum::rewriting::experiments::{Experiment, HeuristicExperimentConfig, RewritePair};
+use verbum::rewriting::strings::{StringRewritingSystem, expression_to_abelian_vector};
+use verbum::utils::json::save_json;
 
 /// CLI arguments for path expression generation
 #[derive(Parser, Debug)]
@@ -51,6 +43,14 @@ struct Args {
     /// Output JSON file path
     #[arg(short = 'o', long)]
     output: PathBuf,
+
+    /// Maximum line width when printing expressions
+    #[arg(short = 'w', long, default_value_t = 100)]
+    width: usize,
+
+    /// Seed of the random choices, a random one is used and printed if not given
+    #[arg(short = 's', long)]
+    seed: Option<u64>,
 }
 
 /// Output structure for JSON serialization
@@ -84,69 +84,41 @@ struct OutputData {
 fn main() -> Result<(), Box<dyn Error>> {
     let args = Args::parse();
 
-    // Step 1: Load TRS and language
+    // Step 1: Load TRS, language and arities from the same directory
     println!("Loading TRS from {:?}...", args.trs);
-    let trs = TermRewritingSystem::from_directory(&args.trs)?;
-    let lang = trs.language();
-    let rules = trs.rules();
-
-    // Step 2: Load arities from the same directory
-    let arities_path = args.trs.join("arities.json");
-    println!("Loading arities from {:?}...", arities_path);
-    let arities: Arities = load_json(&arities_path)?;
-
-    // Step 3: Generate random expression E with size n and up to v variables
+    let mut config =
+        HeuristicExperimentConfig::new(&args.trs, args.size, args.variables, args.applications);
+    config.seed = args.seed;
+    let mut experiment = Experiment::new(&config)?;
+    println!("Using seed {}", experiment.seed());
+
+    // Steps 2-4: Generate random expression E with size n and up to v variables
+    // and apply random rewrites to E creating E'
     println!(
         "Generating random expression of size {} with up to {} variables...",
         args.size, args.variables
     );
-    let mut rng = rand::thread_rng();
-
-    // Create generation config
-    let mut config = RandomGenerationConfig::from_language(lang);
-
-    // Set symbol arities from the loaded arities
-    for symbol_id in 0..lang.symbol_count() {
-        if let Some(symbol_arities) = arities.get(symbol_id) {
-            config.symbol_arities[symbol_id] = symbol_arities.to_vec();
-        }
-    }
-
-    // Configure variable generation
-    if args.variables > 0 {
-        config.variable_config = Some(VariableGenerationConfig {
-            variable_range: (0, args.variables - 1),
-            variable_probability: 0.5,
-        });
-    }
-
-    let expr_e =
-        generate_random_expression_by_size_with_variables(lang, args.size, &mut rng, &config)?;
-
-    println!("Generated expression E: {}", expr_e.with_language(lang));
-
-    // Step 4: Apply a random rewrites to E creating E'
     println!("Applying {} random rewrites...", args.applications);
-    let expr_e_prime = rewrite_expression(expr_e.clone(), rules, args.applications, &mut rng);
+    let RewritePair {
+        from: expr_e,
+        to: expr_e_prime,
+    } = experiment.random_pair()?;
+    let lang = &experiment.trs().language().clone();
 
     println!(
-        "Rewritten expression E': {}",
-        expr_e_prime.with_language(lang)
+        "Generated expression E:\n{}",
+        lang.format(&expr_e, args.width)
+    );
+    println!(
+        "Rewritten expression E':\n{}",
+        lang.format(&expr_e_prime, args.width)
     );
 
     // Step 5: Create abelianized stringified matrix A for trs
     println!("Creating abelianized matrix for TRS...");
-    let string_lang = to_string_language(lang, &arities);
-
-    // Convert all rules to induced string rules
-    let mut all_induced_rules = Vec::new();
-    for rule in rules {
-        let induced =
-            verbum::rewriting::strings::rule_to_induced_rules(rule, lang, &string_lang, &arities);
-        all_induced_rules.extend(induced);
-    }
-
-    let matrix_a = rules_to_abelian_matrix(&all_induced_rules, &string_lang);
+    let srs = StringRewritingSystem::from_trs(experiment.trs(), experiment.arities());
+    let string_lang = srs.string_language();
+    let matrix_a = srs.abelian_matrix();
 
     // Display string language symbols
     println!("\nString language symbols:");
@@ -170,14 +142,14 @@ fn main() -> Result<(), Box<dyn Error>> {
     }
 
     let k = *common_vars
-        .choose(&mut rng)
+        .choose(experiment.rng())
         .ok_or("Failed to choose a random variable")?;
 
     println!("Chosen variable k: ${}", k);
 
     // Step 7: Extract all paths from root to leaves which are variable k
     println!("Extracting paths to variable ${} in E...", k);
-    let all_paths_e = expression_to_paths(&expr_e, lang, &string_lang, &arities);
+    let all_paths_e = srs.expression_to_paths(&expr_e);
     let paths_to_k_e: Vec<_> = all_paths_e
         .iter()
         .filter(|path| {
@@ -187,7 +159,7 @@ fn main() -> Result<(), Box<dyn Error>> {
         .collect();
 
     println!("Extracting paths to variable ${} in E'...", k);
-    let all_paths_e_prime = expression_to_paths(&expr_e_prime, lang, &string_lang, &arities);
+    let all_paths_e_prime = srs.expression_to_paths(&expr_e_prime);
     let paths_to_k_e_prime: Vec<_> = all_paths_e_prime
         .iter()
         .filter(|path| {
@@ -204,7 +176,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     let p_a: Vec<Vec<i32>> = paths_to_k_e
         .iter()
         .map(|path| {
-            let vec = expression_to_abelian_vector(path, &string_lang);
+            let vec = expression_to_abelian_vector(path, string_lang);
             vec.as_slice().to_vec()
         })
         .collect();
@@ -212,7 +184,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     let p_a_prime: Vec<Vec<i32>> = paths_to_k_e_prime
         .iter()
         .map(|path| {
-            let vec = expression_to_abelian_vector(path, &string_lang);
+            let vec = expression_to_abelian_vector(path, string_lang);
             vec.as_slice().to_vec()
         })
         .collect();
@@ -0,0 +1,268 @@
+//! Strongly connected components, topological sorting and cycles of [`Graph`]s.
+
+use std::collections::VecDeque;
+
+use crate::graph::{Graph, VertexId};
+
+/// The graph of strongly connected components of a [`Graph`], see [`Graph::condensation`].
+#[derive(PartialEq, Debug)]
+pub struct Condensation {
+    /// The components, each a vertex of `graph`
+    pub components: Vec<Vec<VertexId>>,
+    /// Index of the component of every vertex of the condensed graph
+    pub component_of: Vec<usize>,
+    /// Acyclic graph with an edge between two components if there is an edge between their vertices
+    pub graph: Graph,
+}
+
+impl Condensation {
+    /// Returns the components in a topological order of `graph`, so that every edge
+    /// leads from a component to a later one.
+    pub fn topological_components(&self) -> Vec<&[VertexId]> {
+        self.graph
+            .topological_sort()
+            .expect("The condensation is acyclic")
+            .into_iter()
+            .map(|component| self.components[component].as_slice())
+            .collect()
+    }
+}
+
+impl Graph {
+    /// Returns the strongly connected components of the graph, found by Tarjan's algorithm.
+    ///
+    /// The components are in a reverse topological order: edges between different components
+    /// lead from later components to earlier ones. Vertices of every component are sorted.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<VertexId>> {
+        const UNVISITED: usize = usize::MAX;
+
+        let count = self.num_vertices();
+        let mut index = vec![UNVISITED; count];
+        let mut low_link = vec![0; count];
+        let mut on_stack = vec![false; count];
+        let mut stack = Vec::new();
+        let mut components = Vec::new();
+        let mut next_index = 0;
+
+        for root in 0..count {
+            if index[root] != UNVISITED {
+                continue;
+            }
+
+            // Vertices on the current path with the number of out-neighbors already visited
+            let mut path = vec![(root, 0)];
+            index[root] = next_index;
+            low_link[root] = next_index;
+            next_index += 1;
+            stack.push(root);
+            on_stack[root] = true;
+
+            while let Some((vertex, visited)) = path.last_mut() {
+                let vertex = *vertex;
+                if let Some(&neighbor) = self.out_neighbors(vertex).get(*visited) {
+                    *visited += 1;
+                    if index[neighbor] == UNVISITED {
+                        index[neighbor] = next_index;
+                        low_link[neighbor] = next_index;
+                        next_index += 1;
+                        stack.push(neighbor);
+                        on_stack[neighbor] = true;
+                        path.push((neighbor, 0));
+                    } else if on_stack[neighbor] {
+                        low_link[vertex] = low_link[vertex].min(index[neighbor]);
+                    }
+                    continue;
+                }
+
+                path.pop();
+                if let Some(&(parent, _)) = path.last() {
+                    low_link[parent] = low_link[parent].min(low_link[vertex]);
+                }
+                if low_link[vertex] == index[vertex] {
+                    let mut component = Vec::new();
+                    loop {
+                        let member = stack.pop().expect("The root is on the stack");
+                        on_stack[member] = false;
+                        component.push(member);
+                        if member == vertex {
+                            break;
+                        }
+                    }
+                    component.sort_unstable();
+                    components.push(component);
+                }
+            }
+        }
+
+        components
+    }
+
+    /// Returns the condensation of the graph, in which every strongly connected component
+    /// is contracted to a single vertex. Components are numbered as in
+    /// [`Graph::strongly_connected_components`].
+    pub fn condensation(&self) -> Condensation {
+        let components = self.strongly_connected_components();
+        let mut component_of = vec![0; self.num_vertices()];
+        for (component, vertices) in components.iter().enumerate() {
+            for &vertex in vertices {
+                component_of[vertex] = component;
+            }
+        }
+
+        let mut graph = Graph::new();
+        for _ in &components {
+            graph.add_vertex();
+        }
+        for (component, vertices) in components.iter().enumerate() {
+            let mut successors: Vec<_> = vertices
+                .iter()
+                .flat_map(|&vertex| self.out_neighbors(vertex))
+                .map(|&neighbor| component_of[neighbor])
+                .filter(|&successor| successor != component)
+                .collect();
+            successors.sort_unstable();
+            successors.dedup();
+            for successor in successors {
+                graph.add_edge(component, successor);
+            }
+        }
+
+        Condensation {
+            components,
+            component_of,
+            graph,
+        }
+    }
+
+    /// Returns the vertices sorted so that every edge leads from a vertex to a later one,
+    /// or `None` if the graph has a cycle. Ties are broken by the smaller index.
+    pub fn topological_sort(&self) -> Option<Vec<VertexId>> {
+        let mut in_degrees: Vec<_> = (0..self.num_vertices())
+            .map(|vertex| self.in_neighbors(vertex).len())
+            .collect();
+        let mut ready: std::collections::BinaryHeap<_> = (0..self.num_vertices())
+            .filter(|&vertex| in_degrees[vertex] == 0)
+            .map(std::cmp::Reverse)
+            .collect();
+
+        let mut order = Vec::with_capacity(self.num_vertices());
+        while let Some(std::cmp::Reverse(vertex)) = ready.pop() {
+            order.push(vertex);
+            for &neighbor in self.out_neighbors(vertex) {
+                in_degrees[neighbor] -= 1;
+                if in_degrees[neighbor] == 0 {
+                    ready.push(std::cmp::Reverse(neighbor));
+                }
+            }
+        }
+
+        (order.len() == self.num_vertices()).then_some(order)
+    }
+
+    /// `true` if the graph has a cycle, including a self-loop.
+    pub fn has_cycle(&self) -> bool {
+        self.topological_sort().is_none()
+    }
+
+    /// Returns the sorted vertices lying on some cycle, i.e. in strongly connected components
+    /// with more than one vertex or with a self-loop.
+    pub fn cyclic_vertices(&self) -> Vec<VertexId> {
+        let mut vertices: Vec<_> = self
+            .strongly_connected_components()
+            .into_iter()
+            .filter(|component| {
+                component.len() > 1 || self.out_neighbors(component[0]).contains(&component[0])
+            })
+            .flatten()
+            .collect();
+        vertices.sort_unstable();
+        vertices
+    }
+
+    /// Returns a shortest cycle through `vertex` as the list of its vertices starting with `vertex`,
+    /// or `None` if `vertex` does not lie on a cycle.
+    pub fn shortest_cycle_through(&self, vertex: VertexId) -> Option<Vec<VertexId>> {
+        let mut parents = vec![None; self.num_vertices()];
+        let mut queue = VecDeque::from([vertex]);
+
+        while let Some(current) = queue.pop_front() {
+            for &neighbor in self.out_neighbors(current) {
+                if neighbor == vertex {
+                    let mut cycle = vec![current];
+                    while let Some(parent) = parents[*cycle.last().unwrap()] {
+                        cycle.push(parent);
+                    }
+                    if *cycle.last().unwrap() != vertex {
+                        cycle.push(vertex);
+                    }
+                    cycle.reverse();
+                    return Some(cycle);
+                }
+                if parents[neighbor].is_none() && neighbor != vertex {
+                    parents[neighbor] = Some(current);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::graph::Graph;
+
+    fn graph(vertices: usize, edges: &[(usize, usize)]) -> Graph {
+        let mut graph = Graph::new();
+        for _ in 0..vertices {
+            graph.add_vertex();
+        }
+        for &(from, to) in edges {
+            graph.add_edge(from, to);
+        }
+        graph
+    }
+
+    #[test]
+    fn strongly_connected_components() {
+        // 0 <-> 1 -> 2 -> 3 -> 2, 4 -> 4, 5
+        let graph = graph(6, &[(0, 1), (1, 0), (1, 2), (2, 3), (3, 2), (4, 4)]);
+        let components = graph.strongly_connected_components();
+        assert_eq!(components.len(), 4);
+        let position = |vertex| {
+            components
+                .iter()
+                .position(|component| component.contains(&vertex))
+                .unwrap()
+        };
+        assert_eq!(components[position(0)], [0, 1]);
+        assert_eq!(components[position(2)], [2, 3]);
+        assert!(position(2) < position(0));
+
+        let condensation = graph.condensation();
+        assert_eq!(condensation.component_of[3], position(3));
+        assert_eq!(condensation.graph.out_neighbors(position(1)), [position(2)]);
+        let order = condensation.topological_components();
+        let index = |vertex| order.iter().position(|c| c.contains(&vertex)).unwrap();
+        assert!(index(0) < index(2));
+
+        assert!(graph.has_cycle());
+        assert_eq!(graph.cyclic_vertices(), [0, 1, 2, 3, 4]);
+        assert_eq!(graph.shortest_cycle_through(1), Some(vec![1, 0]));
+        assert_eq!(graph.shortest_cycle_through(4), Some(vec![4]));
+        assert_eq!(graph.shortest_cycle_through(5), None);
+    }
+
+    #[test]
+    fn topological_sort() {
+        let dag = graph(4, &[(2, 0), (0, 1), (2, 3), (3, 1)]);
+        assert_eq!(dag.topological_sort(), Some(vec![2, 0, 3, 1]));
+        assert!(!dag.has_cycle());
+        assert!(dag.cyclic_vertices().is_empty());
+
+        let cycle = graph(3, &[(0, 1), (1, 2), (2, 0)]);
+        assert_eq!(cycle.topological_sort(), None);
+        assert_eq!(cycle.shortest_cycle_through(2), Some(vec![2, 0, 1]));
+    }
+}
@@ -1,6 +1,9 @@
 //! A graph that associates data with each vertex.
 
-use crate::graph::{Graph, VertexId};
+use crate::graph::{
+    Graph, VertexId,
+    graphml::{AttributeType, GraphMlWriter},
+};
 
 /// A graph that associates data with each vertex.
 #[derive(PartialEq, Debug)]
@@ -95,6 +98,24 @@ impl<T> DataGraph<T> {
         dot.push('}');
         dot
     }
+
+    /// Returns a string in GraphML format representing the graph.
+    /// The data of each vertex is stored in the `label` attribute using the `Display` trait.
+    pub fn graphml(&self) -> String
+    where
+        T: std::fmt::Display,
+    {
+        let mut writer = GraphMlWriter::new(&[("label", AttributeType::String)], &[]);
+        for (i, data) in self.data.iter().enumerate() {
+            writer.vertex(i, &[("label", data)]);
+        }
+        for i in 0..self.graph.num_vertices() {
+            for &neighbor in self.graph.out_neighbors(i) {
+                writer.edge(i, neighbor, &[]);
+            }
+        }
+        writer.finish()
+    }
 }
 
 #[cfg(test)]
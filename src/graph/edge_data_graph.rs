@@ -2,6 +2,7 @@
 
 use crate::graph::VertexId;
 use crate::graph::data_graph::DataGraph;
+use crate::graph::graphml::{AttributeType, GraphMlWriter};
 use std::collections::HashMap;
 
 /// A graph that associates data with both vertices and edges.
@@ -126,6 +127,26 @@ impl<V, E> EdgeDataGraph<V, E> {
         dot.push('}');
         dot
     }
+
+    /// Returns a string in GraphML format representing the graph.
+    /// The data of each vertex and edge is stored in the `label` attribute using the `Display` trait.
+    pub fn graphml(&self) -> String
+    where
+        V: std::fmt::Display,
+        E: std::fmt::Display,
+    {
+        let mut writer = GraphMlWriter::new(
+            &[("label", AttributeType::String)],
+            &[("label", AttributeType::String)],
+        );
+        for (i, data) in self.data_graph.data.iter().enumerate() {
+            writer.vertex(i, &[("label", data)]);
+        }
+        for (&(from, to), data) in &self.edge_data {
+            writer.edge(from, to, &[("label", data)]);
+        }
+        writer.finish()
+    }
 }
 
 #[cfg(test)]
@@ -188,4 +209,16 @@ mod tests {
         let in_data: Vec<_> = graph.in_neighbor_data(v0).collect();
         assert_eq!(in_data, vec![(&30, &"v2->v0")]);
     }
+
+    #[test]
+    fn test_edge_data_graph_graphml() {
+        let mut graph = EdgeDataGraph::new();
+        let v0 = graph.add_vertex("a");
+        let v1 = graph.add_vertex("b");
+        graph.add_edge(v0, v1, "a->b");
+
+        let graphml = graph.graphml();
+        assert!(graphml.contains(r#"<data key="node_label">b</data>"#));
+        assert!(graphml.contains(r#"<data key="edge_label">a-&gt;b</data>"#));
+    }
 }
@@ -0,0 +1,174 @@
+//! Writing graphs in the GraphML format.
+//!
+//! GraphML is understood by Gephi, Cytoscape and yEd, which can lay out graphs
+//! far larger than what is practical to render with `dot`.
+
+use std::collections::HashSet;
+use std::fmt::{Display, Write};
+
+/// Type of values of a GraphML attribute.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttributeType {
+    String,
+    Int,
+    Double,
+}
+
+impl AttributeType {
+    fn name(self) -> &'static str {
+        match self {
+            AttributeType::String => "string",
+            AttributeType::Int => "int",
+            AttributeType::Double => "double",
+        }
+    }
+}
+
+/// Incrementally builds a GraphML document describing a single directed graph.
+///
+/// Attributes have to be declared up front, and can then be given to vertices and edges by name.
+/// Vertices are identified by the `Display` representation of their IDs. Edges are written
+/// when the document is finished, and only if both of their endpoints were added as vertices.
+pub struct GraphMlWriter {
+    out: String,
+    vertices: HashSet<String>,
+    /// Source, target and the element of every edge
+    edges: Vec<(String, String, String)>,
+}
+
+impl GraphMlWriter {
+    /// Starts a document with the given vertex and edge attributes.
+    pub fn new(
+        vertex_attributes: &[(&str, AttributeType)],
+        edge_attributes: &[(&str, AttributeType)],
+    ) -> Self {
+        let mut out = String::new();
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+        writeln!(
+            out,
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+        )
+        .unwrap();
+
+        for (domain, attributes) in [("node", vertex_attributes), ("edge", edge_attributes)] {
+            for (name, attribute_type) in attributes {
+                let name = escape(name);
+                writeln!(
+                    out,
+                    r#"  <key id="{domain}_{name}" for="{domain}" attr.name="{name}" attr.type="{}"/>"#,
+                    attribute_type.name()
+                )
+                .unwrap();
+            }
+        }
+
+        writeln!(out, r#"  <graph id="G" edgedefault="directed">"#).unwrap();
+        Self {
+            out,
+            vertices: HashSet::new(),
+            edges: Vec::new(),
+        }
+    }
+
+    /// Adds a vertex with values of some of the declared vertex attributes.
+    pub fn vertex(&mut self, id: impl Display, data: &[(&str, &dyn Display)]) {
+        let id = escape(&id.to_string());
+        let element = element("node", &format!(r#"id="{id}""#), data);
+        self.out.push_str(&element);
+        self.vertices.insert(id);
+    }
+
+    /// Adds a directed edge with values of some of the declared edge attributes.
+    /// The edge is left out of the document unless both of its endpoints are added as vertices.
+    pub fn edge(&mut self, from: impl Display, to: impl Display, data: &[(&str, &dyn Display)]) {
+        let from = escape(&from.to_string());
+        let to = escape(&to.to_string());
+        let element = element("edge", &format!(r#"source="{from}" target="{to}""#), data);
+        self.edges.push((from, to, element));
+    }
+
+    /// Closes the document and returns it.
+    pub fn finish(mut self) -> String {
+        for (from, to, element) in &self.edges {
+            if self.vertices.contains(from) && self.vertices.contains(to) {
+                self.out.push_str(element);
+            }
+        }
+        writeln!(self.out, "  </graph>").unwrap();
+        writeln!(self.out, "</graphml>").unwrap();
+        self.out
+    }
+}
+
+/// Writes a vertex or edge element with its data.
+fn element(domain: &str, attributes: &str, data: &[(&str, &dyn Display)]) -> String {
+    let mut out = String::new();
+    if data.is_empty() {
+        writeln!(out, "    <{domain} {attributes}/>").unwrap();
+        return out;
+    }
+
+    writeln!(out, "    <{domain} {attributes}>").unwrap();
+    for (name, value) in data {
+        writeln!(
+            out,
+            r#"      <data key="{domain}_{}">{}</data>"#,
+            escape(name),
+            escape(&value.to_string())
+        )
+        .unwrap();
+    }
+    writeln!(out, "    </{domain}>").unwrap();
+    out
+}
+
+/// Escapes characters which cannot appear verbatim in XML text or attribute values.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape(r#"<a & "b">"#), "&lt;a &amp; &quot;b&quot;&gt;");
+    }
+
+    #[test]
+    fn test_writer() {
+        let mut writer = GraphMlWriter::new(
+            &[("label", AttributeType::String)],
+            &[("weight", AttributeType::Int)],
+        );
+        writer.vertex(0, &[("label", &"<<")]);
+        writer.vertex(1, &[]);
+        writer.edge(0, 1, &[("weight", &3)]);
+        writer.edge(1, 2, &[]);
+        let graphml = writer.finish();
+
+        assert!(
+            graphml.contains(
+                r#"<key id="node_label" for="node" attr.name="label" attr.type="string"/>"#
+            )
+        );
+        assert!(graphml.contains(r#"<data key="node_label">&lt;&lt;</data>"#));
+        assert!(graphml.contains(r#"<node id="1"/>"#));
+        assert!(graphml.contains(r#"<edge source="0" target="1">"#));
+        assert!(graphml.contains(r#"<data key="edge_weight">3</data>"#));
+        assert!(!graphml.contains(r#"target="2""#));
+        assert!(graphml.ends_with("</graph>\n</graphml>\n"));
+    }
+}
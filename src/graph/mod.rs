@@ -1,4 +1,8 @@
 //! A module for representing directed graphs.
+//!
+//! Besides the representations, it provides strongly connected components,
+//! topological sorting and cycle detection, see [`components`], as well as
+//! reachability and shortest paths, see [`paths`].
 
 pub type VertexId = usize;
 
@@ -77,13 +81,34 @@ impl Graph {
         dot.push('}');
         dot
     }
+
+    /// Returns a string in GraphML format representing the graph.
+    pub fn graphml(&self) -> String {
+        let mut writer = GraphMlWriter::new(&[], &[]);
+        for i in 0..self.num_vertices() {
+            writer.vertex(i, &[]);
+        }
+        for i in 0..self.num_vertices() {
+            for &neighbor in self.out_neighbors(i) {
+                writer.edge(i, neighbor, &[]);
+            }
+        }
+        writer.finish()
+    }
 }
 
+pub mod components;
 pub mod data_graph;
 pub mod edge_data_graph;
+pub mod graphml;
+pub mod paths;
 
+pub use self::components::Condensation;
 pub use self::data_graph::DataGraph;
 pub use self::edge_data_graph::EdgeDataGraph;
+pub use self::paths::ShortestPaths;
+
+use self::graphml::GraphMlWriter;
 
 #[cfg(test)]
 mod tests {
@@ -116,4 +141,15 @@ mod tests {
         assert_eq!(graph.out_neighbors(v1), &[] as &[VertexId]);
         assert_eq!(graph.in_neighbors(v0), &[] as &[VertexId]);
     }
+
+    #[test]
+    fn test_graph_graphml() {
+        let mut graph = Graph::new();
+        let v0 = graph.add_vertex();
+        let v1 = graph.add_vertex();
+        graph.add_edge(v0, v1);
+        let graphml = graph.graphml();
+        assert_eq!(graphml.matches("<node ").count(), 2);
+        assert!(graphml.contains(r#"<edge source="0" target="1"/>"#));
+    }
 }
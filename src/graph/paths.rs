@@ -0,0 +1,175 @@
+//! Reachability and shortest paths in [`Graph`]s and weighted [`EdgeDataGraph`]s.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+use std::ops::Add;
+
+use crate::graph::{EdgeDataGraph, Graph, VertexId};
+
+/// Shortest paths from a single source vertex, see [`Graph::bfs`] and [`EdgeDataGraph::dijkstra`].
+#[derive(PartialEq, Debug)]
+pub struct ShortestPaths<W> {
+    /// Vertex the paths start at
+    pub source: VertexId,
+    /// Distance of every vertex from the source, `None` if unreachable
+    pub distances: Vec<Option<W>>,
+    /// Predecessor of every reachable vertex other than the source on a shortest path
+    pub parents: Vec<Option<VertexId>>,
+}
+
+impl<W: Copy> ShortestPaths<W> {
+    /// Returns the distance of `target` from the source, `None` if unreachable.
+    pub fn distance(&self, target: VertexId) -> Option<W> {
+        self.distances.get(target).copied().flatten()
+    }
+
+    /// Returns the vertices of a shortest path from the source to `target`, including both,
+    /// or `None` if `target` is unreachable.
+    pub fn path_to(&self, target: VertexId) -> Option<Vec<VertexId>> {
+        self.distance(target)?;
+        let mut path = vec![target];
+        while let Some(parent) = self.parents[*path.last().unwrap()] {
+            path.push(parent);
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    /// Returns the sorted vertices reachable from the source, including the source.
+    pub fn reachable(&self) -> Vec<VertexId> {
+        (0..self.distances.len())
+            .filter(|&vertex| self.distances[vertex].is_some())
+            .collect()
+    }
+}
+
+impl Graph {
+    /// Finds shortest paths from `source` in terms of the numbers of edges by breadth-first search.
+    pub fn bfs(&self, source: VertexId) -> ShortestPaths<usize> {
+        let mut distances = vec![None; self.num_vertices()];
+        let mut parents = vec![None; self.num_vertices()];
+        distances[source] = Some(0);
+        let mut queue = VecDeque::from([(source, 0)]);
+
+        while let Some((vertex, distance)) = queue.pop_front() {
+            for &neighbor in self.out_neighbors(vertex) {
+                if distances[neighbor].is_none() {
+                    distances[neighbor] = Some(distance + 1);
+                    parents[neighbor] = Some(vertex);
+                    queue.push_back((neighbor, distance + 1));
+                }
+            }
+        }
+
+        ShortestPaths {
+            source,
+            distances,
+            parents,
+        }
+    }
+
+    /// `true` if there is a path from `from` to `to`.
+    pub fn is_reachable(&self, from: VertexId, to: VertexId) -> bool {
+        self.bfs(from).distance(to).is_some()
+    }
+}
+
+impl<V, E> EdgeDataGraph<V, E> {
+    /// Finds shortest paths from `source` by Dijkstra's algorithm, with the length of every edge
+    /// given by `weight` of its data. Weights must not be negative, that is less than `W::default()`.
+    pub fn dijkstra<W, F>(&self, source: VertexId, weight: F) -> ShortestPaths<W>
+    where
+        W: Copy + Ord + Default + Add<Output = W>,
+        F: Fn(&E) -> W,
+    {
+        let num_vertices = self.data_graph.graph.num_vertices();
+        let mut distances = vec![None; num_vertices];
+        let mut parents = vec![None; num_vertices];
+        distances[source] = Some(W::default());
+        let mut queue = BinaryHeap::from([Reverse((W::default(), source))]);
+
+        while let Some(Reverse((distance, vertex))) = queue.pop() {
+            if distances[vertex].is_some_and(|known| known < distance) {
+                continue;
+            }
+
+            for (neighbor, data) in self.out_neighbors_with_data(vertex) {
+                let next_distance = distance + weight(data);
+                if distances[neighbor].is_none_or(|known| next_distance < known) {
+                    distances[neighbor] = Some(next_distance);
+                    parents[neighbor] = Some(vertex);
+                    queue.push(Reverse((next_distance, neighbor)));
+                }
+            }
+        }
+
+        ShortestPaths {
+            source,
+            distances,
+            parents,
+        }
+    }
+
+    /// Returns the length and the vertices of a shortest path from `from` to `to`,
+    /// with edge lengths as in [`EdgeDataGraph::dijkstra`], or `None` if there is no path.
+    pub fn shortest_path<W, F>(
+        &self,
+        from: VertexId,
+        to: VertexId,
+        weight: F,
+    ) -> Option<(W, Vec<VertexId>)>
+    where
+        W: Copy + Ord + Default + Add<Output = W>,
+        F: Fn(&E) -> W,
+    {
+        let paths = self.dijkstra(from, weight);
+        Some((paths.distance(to)?, paths.path_to(to)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::graph::{EdgeDataGraph, Graph};
+
+    #[test]
+    fn test_graph_bfs() {
+        let mut graph = Graph::new();
+        for _ in 0..5 {
+            graph.add_vertex();
+        }
+        graph.add_edge(0, 1);
+        graph.add_edge(1, 2);
+        graph.add_edge(0, 2);
+        graph.add_edge(2, 3);
+
+        let paths = graph.bfs(0);
+        assert_eq!(paths.distances, [Some(0), Some(1), Some(1), Some(2), None]);
+        assert_eq!(paths.path_to(3), Some(vec![0, 2, 3]));
+        assert_eq!(paths.path_to(4), None);
+        assert_eq!(paths.reachable(), [0, 1, 2, 3]);
+        assert!(graph.is_reachable(1, 3));
+        assert!(!graph.is_reachable(3, 0));
+    }
+
+    #[test]
+    fn test_edge_data_graph_dijkstra() {
+        let mut graph = EdgeDataGraph::new();
+        let a = graph.add_vertex("a");
+        let b = graph.add_vertex("b");
+        let c = graph.add_vertex("c");
+        let d = graph.add_vertex("d");
+        graph.add_edge(a, b, 1u32);
+        graph.add_edge(b, c, 1);
+        graph.add_edge(a, c, 5);
+        graph.add_edge(c, d, 2);
+
+        let paths = graph.dijkstra(a, |&weight| weight);
+        assert_eq!(paths.distances, [Some(0), Some(1), Some(2), Some(4)]);
+        assert_eq!(
+            graph.shortest_path(a, d, |&w| w),
+            Some((4, vec![a, b, c, d]))
+        );
+        assert_eq!(graph.shortest_path(a, d, |_| 1), Some((2, vec![a, c, d])));
+        assert_eq!(graph.shortest_path(d, a, |&w| w), None);
+    }
+}
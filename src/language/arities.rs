@@ -79,6 +79,38 @@ impl Arities {
         self.map.get(&symbol_id).and_then(|v| v.first().copied())
     }
 
+    /// Gets the largest arity of a symbol.
+    ///
+    /// Variadic symbols, e.g. `+` of [`Language::simple_math`](super::Language::simple_math)
+    /// which is parsed with any number of children, may have several arities.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol_id` - The ID of the symbol
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(usize)` if the symbol has at least one arity defined, `None` otherwise
+    #[must_use]
+    pub fn get_max(&self, symbol_id: SymbolId) -> Option<usize> {
+        self.map
+            .get(&symbol_id)
+            .and_then(|v| v.iter().max().copied())
+    }
+
+    /// Adds an arity of a symbol, keeping its arities sorted and without duplicates.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol_id` - The ID of the symbol
+    /// * `arity` - The arity to allow
+    pub fn add(&mut self, symbol_id: SymbolId, arity: usize) {
+        let arities = self.map.entry(symbol_id).or_default();
+        if let Err(index) = arities.binary_search(&arity) {
+            arities.insert(index, arity);
+        }
+    }
+
     /// Checks if a symbol has a specific arity.
     ///
     /// # Arguments
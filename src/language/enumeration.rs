@@ -0,0 +1,190 @@
+//! Exhaustive enumeration of ground terms.
+//!
+//! [`enumerate_ground_terms`] lists every ground term of a language up to a size bound,
+//! which allows checking properties of rewriting systems, e.g. confluence or results of
+//! saturation, on all small terms instead of random samples.
+
+use std::vec;
+
+use itertools::Itertools;
+
+use super::{
+    Language,
+    arities::Arities,
+    expression::VarFreeExpression,
+    symbol::{Symbol, SymbolId},
+};
+
+/// Returns all ground terms of `language` with at most `max_size` symbols, built from symbols
+/// with arities declared in `arities`.
+///
+/// Terms are produced in a canonical order: by size, then by the ID of the root symbol,
+/// then by its arity, then by the sizes of the children from the left and finally by the
+/// children themselves in this order. Constants of the language missing from `arities` are
+/// nullary, while other symbols missing from it accept any number of children
+/// and are skipped, as are literals, so that the number of terms stays finite.
+///
+/// Terms of each size are built from the smaller ones, which are kept until the iterator
+/// is dropped, so enumerating large sizes takes a lot of memory.
+pub fn enumerate_ground_terms(
+    language: &Language,
+    arities: &Arities,
+    max_size: usize,
+) -> impl Iterator<Item = VarFreeExpression> + use<> {
+    let signature = (0..language.symbol_count())
+        .flat_map(|id| {
+            let allowed = match arities.get(id) {
+                Some(allowed) => allowed.iter().copied().sorted().dedup().collect(),
+                None if language.is_constant(id) => vec![0],
+                None => Vec::new(),
+            };
+            allowed.into_iter().map(move |arity| (id, arity))
+        })
+        .collect();
+
+    GroundTerms {
+        signature,
+        max_size,
+        by_size: vec![Vec::new()],
+        current: Vec::new().into_iter(),
+    }
+}
+
+/// Iterator of [`enumerate_ground_terms`], producing the terms of one size at a time.
+struct GroundTerms {
+    /// Symbols with their arities, in the order of the enumeration
+    signature: Vec<(SymbolId, usize)>,
+    max_size: usize,
+    /// Terms of every size already reached, indexed by their sizes
+    by_size: Vec<Vec<VarFreeExpression>>,
+    current: vec::IntoIter<VarFreeExpression>,
+}
+
+impl GroundTerms {
+    fn terms_of_size(&self, size: usize) -> Vec<VarFreeExpression> {
+        let mut terms = Vec::new();
+        for &(id, arity) in &self.signature {
+            if arity == 0 {
+                if size == 1 {
+                    terms.push(VarFreeExpression::Symbol(Symbol {
+                        id,
+                        children: Vec::new(),
+                    }));
+                }
+                continue;
+            }
+
+            for sizes in compositions(size - 1, arity) {
+                terms.extend(
+                    sizes
+                        .iter()
+                        .map(|&size| self.by_size[size].iter().cloned())
+                        .multi_cartesian_product()
+                        .map(|children| VarFreeExpression::Symbol(Symbol { id, children })),
+                );
+            }
+        }
+        terms
+    }
+}
+
+impl Iterator for GroundTerms {
+    type Item = VarFreeExpression;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(term) = self.current.next() {
+                return Some(term);
+            }
+
+            let size = self.by_size.len();
+            if size > self.max_size {
+                return None;
+            }
+            let terms = self.terms_of_size(size);
+            self.current = terms.clone().into_iter();
+            self.by_size.push(terms);
+        }
+    }
+}
+
+/// Returns the ways of writing `total` as an ordered sum of `parts` positive numbers,
+/// in the lexicographic order.
+fn compositions(total: usize, parts: usize) -> Vec<Vec<usize>> {
+    if parts == 0 {
+        return if total == 0 {
+            vec![Vec::new()]
+        } else {
+            Vec::new()
+        };
+    }
+    if total < parts {
+        return Vec::new();
+    }
+
+    (1..=total - (parts - 1))
+        .flat_map(|first| {
+            compositions(total - first, parts - 1)
+                .into_iter()
+                .map(move |mut rest| {
+                    rest.insert(0, first);
+                    rest
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::language::{Language, arities::Arities, expression::AnyExpression};
+
+    use super::{compositions, enumerate_ground_terms};
+
+    fn language() -> (Language, Arities) {
+        let language = Language::default()
+            .add_symbol("f")
+            .add_symbol("g")
+            .add_symbol("h")
+            .add_constant("a");
+        let mut arities = Arities::new();
+        arities.set(0, vec![1]);
+        arities.set(1, vec![2]);
+        (language, arities)
+    }
+
+    #[test]
+    fn enumerates_terms_in_canonical_order() {
+        let (language, arities) = language();
+        let terms = enumerate_ground_terms(&language, &arities, 4)
+            .map(|term| term.with_language(&language).to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            terms,
+            [
+                "a",
+                "(f a)",
+                "(f (f a))",
+                "(g a a)",
+                "(f (f (f a)))",
+                "(f (g a a))",
+                "(g a (f a))",
+                "(g (f a) a)",
+            ]
+        );
+        assert_eq!(enumerate_ground_terms(&language, &arities, 0).count(), 0);
+    }
+
+    #[test]
+    fn counts_binary_trees() {
+        let language = Language::default().add_symbol("g").add_constant("a");
+        let mut arities = Arities::new();
+        arities.set(0, vec![2]);
+        // Catalan numbers for the sizes 1, 3, 5, 7 and 9
+        assert_eq!(
+            enumerate_ground_terms(&language, &arities, 9).count(),
+            1 + 1 + 2 + 5 + 14
+        );
+        assert_eq!(compositions(4, 2), [[1, 3], [2, 2], [3, 1]]);
+        assert!(compositions(1, 2).is_empty());
+    }
+}
@@ -0,0 +1,325 @@
+//! Evaluation of expressions.
+//!
+//! An [`Interpreter`] gives meaning to the symbols of a language as functions over [`Literal`]s,
+//! which lets expressions be evaluated with [`Expression::evaluate`] and
+//! [`VarFreeExpression::evaluate`]. [`FunctionInterpreter`] maps symbols to host functions,
+//! with standard implementations for [`Language::simple_math`] and the logic language
+//! of `jsons/logic`.
+
+use std::{collections::HashMap, error::Error, fmt};
+
+use itertools::Itertools;
+
+use super::{
+    Language,
+    expression::{Expression, Literal, VarFreeExpression, VariableId},
+    symbol::SymbolId,
+};
+
+/// Error of evaluation of an expression.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EvaluationError {
+    /// A variable without a value in the bindings
+    UnboundVariable(VariableId),
+    /// A symbol which the interpreter does not know
+    UninterpretedSymbol(SymbolId),
+    /// A symbol applied to arguments for which it is undefined, e.g. division by zero
+    InvalidArguments {
+        symbol: SymbolId,
+        arguments: Vec<Literal>,
+    },
+}
+
+impl fmt::Display for EvaluationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvaluationError::UnboundVariable(id) => {
+                write!(
+                    f,
+                    "Variable {} is not bound",
+                    Expression::variable_name(*id)
+                )
+            }
+            EvaluationError::UninterpretedSymbol(symbol) => {
+                write!(f, "Symbol {symbol} is not interpreted")
+            }
+            EvaluationError::InvalidArguments { symbol, arguments } => {
+                write!(
+                    f,
+                    "Symbol {symbol} is undefined for arguments ({})",
+                    arguments.iter().join(" ")
+                )
+            }
+        }
+    }
+}
+
+impl Error for EvaluationError {}
+
+/// Interpretation of the symbols of a language as functions over literals.
+pub trait Interpreter {
+    /// Returns the value of `symbol` applied to `arguments`.
+    fn apply(&self, symbol: SymbolId, arguments: &[Literal]) -> Result<Literal, EvaluationError>;
+}
+
+/// A host function giving the value of a symbol, `None` if it is undefined for the arguments.
+pub type Function = Box<dyn Fn(&[Literal]) -> Option<Literal>>;
+
+/// An [`Interpreter`] mapping symbols to host functions.
+#[derive(Default)]
+pub struct FunctionInterpreter {
+    functions: HashMap<SymbolId, Function>,
+}
+
+impl FunctionInterpreter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Interprets `symbol` as `function`.
+    pub fn with_function(
+        mut self,
+        symbol: SymbolId,
+        function: impl Fn(&[Literal]) -> Option<Literal> + 'static,
+    ) -> Self {
+        self.functions.insert(symbol, Box::new(function));
+        self
+    }
+
+    /// Interprets the symbol called `name` in `language` as `function`,
+    /// if `language` contains such a symbol.
+    pub fn with_named_function(
+        self,
+        language: &Language,
+        name: &str,
+        function: impl Fn(&[Literal]) -> Option<Literal> + 'static,
+    ) -> Self {
+        match language.try_get_id(name) {
+            Some(symbol) => self.with_function(symbol, function),
+            None => self,
+        }
+    }
+
+    /// Interpreter of the symbols of [`Language::simple_math`] present in `language`.
+    ///
+    /// Arithmetic is exact on integers and rationals, overflowing operations are undefined.
+    /// `<<` and `>>` multiply and divide integers by powers of two, rounding down,
+    /// and `sin` and `cos` return floats.
+    pub fn simple_math(language: &Language) -> Self {
+        Self::new()
+            .with_named_function(language, "+", binary(Literal::checked_add))
+            .with_named_function(language, "-", binary(Literal::checked_sub))
+            .with_named_function(language, "*", binary(Literal::checked_mul))
+            .with_named_function(language, "/", binary(Literal::checked_div))
+            .with_named_function(language, "<<", binary(|a, b| shift(a, b, i64::checked_mul)))
+            .with_named_function(
+                language,
+                ">>",
+                binary(|a, b| shift(a, b, i64::checked_div_euclid)),
+            )
+            .with_named_function(
+                language,
+                "sin",
+                unary(|a| Some(Literal::float(a.to_f64()?.sin()))),
+            )
+            .with_named_function(
+                language,
+                "cos",
+                unary(|a| Some(Literal::float(a.to_f64()?.cos()))),
+            )
+    }
+
+    /// Interpreter of the logic language, with `0` and `1` standing for false and true,
+    /// as do the constants `false` and `true` if the language has them.
+    pub fn logic(language: &Language) -> Self {
+        Self::new()
+            .with_named_function(language, "false", |_| Some(boolean(false)))
+            .with_named_function(language, "true", |_| Some(boolean(true)))
+            .with_named_function(language, "not", unary(|a| Some(boolean(!truth(a)?))))
+            .with_named_function(language, "and", logic_binary(|a, b| a && b))
+            .with_named_function(language, "or", logic_binary(|a, b| a || b))
+            .with_named_function(language, "xor", logic_binary(|a, b| a != b))
+            .with_named_function(language, "imp", logic_binary(|a, b| !a || b))
+            .with_named_function(language, "iff", logic_binary(|a, b| a == b))
+    }
+}
+
+impl fmt::Debug for FunctionInterpreter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.functions.keys()).finish()
+    }
+}
+
+impl Interpreter for FunctionInterpreter {
+    fn apply(&self, symbol: SymbolId, arguments: &[Literal]) -> Result<Literal, EvaluationError> {
+        let function = self
+            .functions
+            .get(&symbol)
+            .ok_or(EvaluationError::UninterpretedSymbol(symbol))?;
+        function(arguments).ok_or_else(|| EvaluationError::InvalidArguments {
+            symbol,
+            arguments: arguments.to_vec(),
+        })
+    }
+}
+
+fn unary(f: impl Fn(&Literal) -> Option<Literal>) -> impl Fn(&[Literal]) -> Option<Literal> {
+    move |arguments| match arguments {
+        [a] => f(a),
+        _ => None,
+    }
+}
+
+fn binary(
+    f: impl Fn(&Literal, &Literal) -> Option<Literal>,
+) -> impl Fn(&[Literal]) -> Option<Literal> {
+    move |arguments| match arguments {
+        [a, b] => f(a, b),
+        _ => None,
+    }
+}
+
+fn logic_binary(f: impl Fn(bool, bool) -> bool) -> impl Fn(&[Literal]) -> Option<Literal> {
+    binary(move |a, b| Some(boolean(f(truth(a)?, truth(b)?))))
+}
+
+fn truth(literal: &Literal) -> Option<bool> {
+    match literal {
+        Literal::Int(0) | Literal::UInt(0) => Some(false),
+        Literal::Int(1) | Literal::UInt(1) => Some(true),
+        _ => None,
+    }
+}
+
+fn boolean(value: bool) -> Literal {
+    Literal::Int(value as i64)
+}
+
+/// Applies `op` to the integer `a` and `2^b`.
+fn shift(a: &Literal, b: &Literal, op: impl Fn(i64, i64) -> Option<i64>) -> Option<Literal> {
+    let integer = |literal: &Literal| match literal {
+        Literal::Int(value) => Some(*value),
+        Literal::UInt(value) => i64::try_from(*value).ok(),
+        _ => None,
+    };
+    let power = 1i64.checked_shl(u32::try_from(integer(b)?).ok()?)?;
+    if power < 0 {
+        return None;
+    }
+    Some(Literal::Int(op(integer(a)?, power)?))
+}
+
+impl Expression {
+    /// Evaluates the expression with `interpreter`, substituting values of variables from `bindings`.
+    pub fn evaluate(
+        &self,
+        interpreter: &dyn Interpreter,
+        bindings: &HashMap<VariableId, Literal>,
+    ) -> Result<Literal, EvaluationError> {
+        match self {
+            Expression::Literal(literal) => Ok(literal.clone()),
+            Expression::Variable(id) => bindings
+                .get(id)
+                .cloned()
+                .ok_or(EvaluationError::UnboundVariable(*id)),
+            Expression::Symbol(symbol) => {
+                let arguments = symbol
+                    .children
+                    .iter()
+                    .map(|child| child.evaluate(interpreter, bindings))
+                    .collect::<Result<Vec<_>, _>>()?;
+                interpreter.apply(symbol.id, &arguments)
+            }
+        }
+    }
+}
+
+impl VarFreeExpression {
+    /// Evaluates the expression with `interpreter`.
+    pub fn evaluate(&self, interpreter: &dyn Interpreter) -> Result<Literal, EvaluationError> {
+        match self {
+            VarFreeExpression::Literal(literal) => Ok(literal.clone()),
+            VarFreeExpression::Symbol(symbol) => {
+                let arguments = symbol
+                    .children
+                    .iter()
+                    .map(|child| child.evaluate(interpreter))
+                    .collect::<Result<Vec<_>, _>>()?;
+                interpreter.apply(symbol.id, &arguments)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{EvaluationError, FunctionInterpreter};
+    use crate::language::{Language, expression::Literal};
+
+    #[test]
+    fn simple_math() {
+        let lang = Language::simple_math();
+        let interpreter = FunctionInterpreter::simple_math(&lang);
+        let evaluate = |expression: &str| {
+            lang.parse_no_vars(expression)
+                .unwrap()
+                .evaluate(&interpreter)
+        };
+
+        assert_eq!(evaluate("(+ (* 2 3) (<< 1 4))"), Ok(Literal::Int(22)));
+        assert_eq!(evaluate("(>> -7 1)"), Ok(Literal::Int(-4)));
+        assert_eq!(evaluate("(/ 1 2)"), Ok(Literal::Rational(1, 2)));
+        assert_eq!(evaluate("(cos 0)"), Ok(Literal::float(1.0)));
+        assert!(matches!(
+            evaluate("(+ 1 (/ 1 0))"),
+            Err(EvaluationError::InvalidArguments { .. })
+        ));
+    }
+
+    #[test]
+    fn logic_with_bindings() {
+        let lang = Language::default()
+            .add_symbol("and")
+            .add_symbol("or")
+            .add_symbol("not")
+            .add_symbol("xor")
+            .add_symbol("imp")
+            .add_symbol("iff")
+            .add_constant("true")
+            .add_constant("false");
+        let interpreter = FunctionInterpreter::logic(&lang);
+        let expression = lang.parse("(iff (imp $0 $1) (or (not $0) $1))").unwrap();
+
+        for a in 0..2 {
+            for b in 0..2 {
+                let bindings = HashMap::from([(0, Literal::Int(a)), (1, Literal::Int(b))]);
+                assert_eq!(
+                    expression.evaluate(&interpreter, &bindings),
+                    Ok(Literal::Int(1))
+                );
+            }
+        }
+
+        assert_eq!(
+            expression.evaluate(&interpreter, &HashMap::new()),
+            Err(EvaluationError::UnboundVariable(0))
+        );
+        assert_eq!(
+            lang.parse_no_vars("(and 1 2)")
+                .unwrap()
+                .evaluate(&interpreter),
+            Err(EvaluationError::InvalidArguments {
+                symbol: lang.get_id("and"),
+                arguments: vec![Literal::Int(1), Literal::Int(2)]
+            })
+        );
+        assert_eq!(
+            lang.parse_no_vars("(xor true (and 1 false))")
+                .unwrap()
+                .evaluate(&interpreter),
+            Ok(Literal::Int(1))
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use super::{Expression, Path, VarFreeExpression, path::SubexpressionPathIterator};
-use crate::language::Language;
+use crate::language::{Language, symbol::SymbolId};
 use std::borrow::Cow;
 
 pub trait AnyExpression: Clone + PartialEq + Eq + 'static {
@@ -12,6 +12,9 @@ pub trait AnyExpression: Clone + PartialEq + Eq + 'static {
 
     fn children(&self) -> Option<Vec<&Self>>;
 
+    /// Returns the ID of the root symbol, or `None` if the expression is not a symbol.
+    fn symbol_id(&self) -> Option<SymbolId>;
+
     fn subexpression<'e>(&'e self, path: Path) -> Option<&'e Self> {
         if let Some(head) = path.head() {
             self.children()
@@ -81,10 +84,7 @@ impl<'e, 'l> std::fmt::Display for LangExpression<'e, 'l, Expression> {
         match self.expression.as_ref() {
             Expression::Variable(id) => write!(f, "{}", Expression::variable_name(*id)),
             Expression::Symbol(symbol) => symbol.fmt(f, self.language),
-            Expression::Literal(literal) => match literal {
-                super::Literal::UInt(uint) => write!(f, "{uint}u"),
-                super::Literal::Int(int) => write!(f, "{int}"),
-            },
+            Expression::Literal(literal) => write!(f, "{literal}"),
         }
     }
 }
@@ -93,10 +93,7 @@ impl<'e, 'l> std::fmt::Display for LangExpression<'e, 'l, VarFreeExpression> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self.expression.as_ref() {
             VarFreeExpression::Symbol(symbol) => symbol.fmt(f, self.language),
-            VarFreeExpression::Literal(literal) => match literal {
-                super::Literal::UInt(uint) => write!(f, "{uint}u"),
-                super::Literal::Int(int) => write!(f, "{int}"),
-            },
+            VarFreeExpression::Literal(literal) => write!(f, "{literal}"),
         }
     }
 }
@@ -0,0 +1,261 @@
+//! Variables bound by binder symbols.
+//!
+//! A symbol declared a binder with [`Language::declare_binder`](crate::language::Language::declare_binder),
+//! e.g. `lam` or `forall`, binds the variable which is its first child in its remaining
+//! children. In `(lam $0 (f $0 $1))` the variable `$0` is bound and `$1` is free, so that
+//! substituting `$0` for `$1` must rename the bound variable first, giving
+//! `(lam $2 (f $2 $0))` instead of capturing it. Binders are given as sets of symbol IDs,
+//! see [`Language::binders`](crate::language::Language::binders).
+
+use std::collections::{BTreeSet, HashSet};
+
+use crate::language::symbol::SymbolId;
+
+use super::{Expression, VariableId};
+
+impl Expression {
+    /// Returns the variable bound by `self` if it is an application of one of `binders`
+    /// whose first child is a variable.
+    pub fn bound_variable(&self, binders: &BTreeSet<SymbolId>) -> Option<VariableId> {
+        let Expression::Symbol(symbol) = self else {
+            return None;
+        };
+        match symbol.children.first() {
+            Some(Expression::Variable(id)) if binders.contains(&symbol.id) => Some(*id),
+            _ => None,
+        }
+    }
+
+    /// Returns the variables occurring in `self` outside of the scopes of binders binding them.
+    pub fn free_variables(&self, binders: &BTreeSet<SymbolId>) -> HashSet<VariableId> {
+        let mut free = HashSet::new();
+        self.collect_free_variables(binders, &mut Vec::new(), &mut free);
+        free
+    }
+
+    fn collect_free_variables(
+        &self,
+        binders: &BTreeSet<SymbolId>,
+        bound: &mut Vec<VariableId>,
+        free: &mut HashSet<VariableId>,
+    ) {
+        match self {
+            Expression::Variable(id) => {
+                if !bound.contains(id) {
+                    free.insert(*id);
+                }
+            }
+            Expression::Symbol(symbol) => {
+                let binding = self.bound_variable(binders);
+                let scope = if binding.is_some() { 1 } else { 0 };
+                bound.extend(binding);
+                for child in &symbol.children[scope..] {
+                    child.collect_free_variables(binders, bound, free);
+                }
+                if binding.is_some() {
+                    bound.pop();
+                }
+            }
+            Expression::Literal(_) => {}
+        }
+    }
+
+    /// Replaces the free occurrences of `variable_id` with `expression`. Variables bound in `self`
+    /// which would capture free variables of `expression` are renamed to fresh ones first,
+    /// with IDs larger than those of all variables of `self` and `expression`.
+    pub fn substitute_avoiding_capture(
+        &mut self,
+        variable_id: VariableId,
+        expression: &Expression,
+        binders: &BTreeSet<SymbolId>,
+    ) {
+        let mut next_fresh = [self.max_variable_id(), expression.max_variable_id()]
+            .into_iter()
+            .flatten()
+            .fold(variable_id, VariableId::max)
+            + 1;
+        let free = expression.free_variables(binders);
+        self.substitute_free(variable_id, expression, &free, binders, &mut next_fresh);
+    }
+
+    fn substitute_free(
+        &mut self,
+        variable_id: VariableId,
+        expression: &Expression,
+        free: &HashSet<VariableId>,
+        binders: &BTreeSet<SymbolId>,
+        next_fresh: &mut VariableId,
+    ) {
+        let binding = self.bound_variable(binders);
+        match self {
+            Expression::Variable(id) => {
+                if *id == variable_id {
+                    *self = expression.clone();
+                }
+            }
+            Expression::Symbol(symbol) => match binding {
+                None => {
+                    for child in &mut symbol.children {
+                        child.substitute_free(variable_id, expression, free, binders, next_fresh);
+                    }
+                }
+                // Shadowed by the binder
+                Some(bound) if bound == variable_id => {}
+                Some(bound) => {
+                    let body = &mut symbol.children[1..];
+                    if !body
+                        .iter()
+                        .any(|child| child.free_variables(binders).contains(&variable_id))
+                    {
+                        return;
+                    }
+
+                    if free.contains(&bound) {
+                        let fresh = *next_fresh;
+                        *next_fresh += 1;
+                        let renamed = Expression::Variable(fresh);
+                        let renamed_free = HashSet::from([fresh]);
+                        for child in body.iter_mut() {
+                            child.substitute_free(
+                                bound,
+                                &renamed,
+                                &renamed_free,
+                                binders,
+                                next_fresh,
+                            );
+                        }
+                        symbol.children[0] = renamed;
+                    }
+                    for child in &mut symbol.children[1..] {
+                        child.substitute_free(variable_id, expression, free, binders, next_fresh);
+                    }
+                }
+            },
+            Expression::Literal(_) => {}
+        }
+    }
+
+    /// Checks if `self` and `other` are equal up to renaming of variables bound by `binders`.
+    /// Free variables have to be equal.
+    pub fn alpha_eq_binding(&self, other: &Expression, binders: &BTreeSet<SymbolId>) -> bool {
+        self.alpha_eq_in_scope(other, binders, &mut Vec::new())
+    }
+
+    /// `bound` holds the pairs of variables bound by the enclosing binders, innermost last.
+    fn alpha_eq_in_scope(
+        &self,
+        other: &Expression,
+        binders: &BTreeSet<SymbolId>,
+        bound: &mut Vec<(VariableId, VariableId)>,
+    ) -> bool {
+        match (self, other) {
+            (Expression::Variable(left), Expression::Variable(right)) => {
+                match bound
+                    .iter()
+                    .rev()
+                    .find(|(bound_left, bound_right)| bound_left == left || bound_right == right)
+                {
+                    Some((bound_left, bound_right)) => bound_left == left && bound_right == right,
+                    None => left == right,
+                }
+            }
+            (Expression::Symbol(left), Expression::Symbol(right)) => {
+                if !left.same_shape_as(right) {
+                    return false;
+                }
+                match (self.bound_variable(binders), other.bound_variable(binders)) {
+                    (Some(bound_left), Some(bound_right)) => {
+                        bound.push((bound_left, bound_right));
+                        let equal = left.children[1..]
+                            .iter()
+                            .zip(&right.children[1..])
+                            .all(|(left, right)| left.alpha_eq_in_scope(right, binders, bound));
+                        bound.pop();
+                        equal
+                    }
+                    (None, None) => left
+                        .children
+                        .iter()
+                        .zip(&right.children)
+                        .all(|(left, right)| left.alpha_eq_in_scope(right, binders, bound)),
+                    _ => false,
+                }
+            }
+            (Expression::Literal(left), Expression::Literal(right)) => left == right,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::language::Language;
+
+    fn lambda() -> Language {
+        Language::default()
+            .add_symbol("lam")
+            .add_symbol("app")
+            .add_symbol("f")
+            .declare_binder("lam")
+    }
+
+    #[test]
+    fn free_variables() {
+        let lang = lambda();
+        let expression = lang.parse("(app (lam $0 (f $0 $1)) $0)").unwrap();
+        assert_eq!(
+            expression.free_variables(lang.binders()),
+            HashSet::from([0, 1])
+        );
+        assert_eq!(
+            lang.parse("(lam $0 (lam $1 (f $0 $1)))")
+                .unwrap()
+                .free_variables(lang.binders()),
+            HashSet::new()
+        );
+        assert_eq!(expression.bound_variable(lang.binders()), None);
+    }
+
+    #[test]
+    fn substitution_avoids_capture() {
+        let lang = lambda();
+        let mut expression = lang.parse("(app (lam $0 (f $0 $1)) $1)").unwrap();
+        expression.substitute_avoiding_capture(1, &lang.parse("$0").unwrap(), lang.binders());
+        assert_eq!(
+            expression,
+            lang.parse("(app (lam $2 (f $2 $0)) $0)").unwrap()
+        );
+
+        // Bound occurrences are not substituted
+        let mut expression = lang.parse("(f $0 (lam $0 $0))").unwrap();
+        expression.substitute_avoiding_capture(0, &lang.parse("(f $1)").unwrap(), lang.binders());
+        assert_eq!(expression, lang.parse("(f (f $1) (lam $0 $0))").unwrap());
+
+        // Without binders the variable is captured
+        let mut expression = lang.parse("(lam $0 $1)").unwrap();
+        expression.substitute(1, &lang.parse("$0").unwrap());
+        assert_eq!(expression, lang.parse("(lam $0 $0)").unwrap());
+    }
+
+    #[test]
+    fn alpha_equivalence_of_bound_variables() {
+        let lang = lambda();
+        let binders = lang.binders();
+        let parse = |expression| lang.parse(expression).unwrap();
+        assert!(
+            parse("(lam $0 (f $0 $2))").alpha_eq_binding(&parse("(lam $1 (f $1 $2))"), binders)
+        );
+        assert!(
+            !parse("(lam $0 (f $0 $2))").alpha_eq_binding(&parse("(lam $1 (f $1 $3))"), binders)
+        );
+        assert!(
+            !parse("(lam $0 (f $0 $1))").alpha_eq_binding(&parse("(lam $1 (f $1 $1))"), binders)
+        );
+        assert!(
+            parse("(lam $0 (lam $1 (f $0 $1)))")
+                .alpha_eq_binding(&parse("(lam $1 (lam $0 (f $1 $0)))"), binders)
+        );
+    }
+}
@@ -1,10 +1,59 @@
 //! Literal value representations.
 //!
 //! This module provides the [`Literal`] enum for representing constant values
-//! in expressions, such as integers.
+//! in expressions, such as integers, floating point numbers, rationals and strings.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
 
 use serde::{Deserialize, Serialize};
 
+/// A 64-bit floating point number with a total order.
+///
+/// Equality, ordering and hashing follow [`f64::total_cmp`], so `NaN` is equal to itself
+/// and `0.0` differs from `-0.0`. This allows floats to be used as literals in e-graphs.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct OrderedFloat(pub f64);
+
+impl PartialEq for OrderedFloat {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for OrderedFloat {}
+
+impl PartialOrd for OrderedFloat {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OrderedFloat {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl Hash for OrderedFloat {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state);
+    }
+}
+
+impl fmt::Display for OrderedFloat {
+    /// Always prints a decimal point, so that the result is parsed back as a float.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let string = self.0.to_string();
+        if self.0.is_finite() && !string.contains('.') {
+            write!(f, "{string}.0")
+        } else {
+            write!(f, "{string}")
+        }
+    }
+}
+
 /// A literal constant value in an expression.
 ///
 /// Represents concrete values like integers that appear in expressions.
@@ -14,4 +63,371 @@ pub enum Literal {
     UInt(u64),
     /// A signed 64-bit integer
     Int(i64),
+    /// A 64-bit floating point number
+    Float(OrderedFloat),
+    /// A rational number given by its numerator and denominator.
+    /// Constructed by [`Literal::rational`], it is always in lowest terms with a positive denominator.
+    Rational(i64, u64),
+    /// A string, e.g. an identifier or a field name
+    Str(String),
+}
+
+/// The kind of a [`Literal`], i.e. its variant without the value.
+#[derive(Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+pub enum LiteralKind {
+    /// An unsigned 64-bit integer
+    UInt,
+    /// A signed 64-bit integer
+    Int,
+    /// A 64-bit floating point number
+    Float,
+    /// A rational number
+    Rational,
+    /// A string
+    Str,
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+
+    a
+}
+
+impl Literal {
+    /// Returns the kind of the literal.
+    pub fn kind(&self) -> LiteralKind {
+        match self {
+            Literal::UInt(_) => LiteralKind::UInt,
+            Literal::Int(_) => LiteralKind::Int,
+            Literal::Float(_) => LiteralKind::Float,
+            Literal::Rational(_, _) => LiteralKind::Rational,
+            Literal::Str(_) => LiteralKind::Str,
+        }
+    }
+
+    /// Creates a float literal.
+    pub fn float(value: f64) -> Self {
+        Literal::Float(OrderedFloat(value))
+    }
+
+    /// Creates a rational literal in lowest terms.
+    /// Returns `None` if `denominator` is zero.
+    pub fn rational(numerator: i64, denominator: u64) -> Option<Self> {
+        if denominator == 0 {
+            return None;
+        }
+
+        // In `i128`, as the divisor of `i64::MIN` can be 2^63
+        let divisor = gcd(numerator.unsigned_abs(), denominator).max(1);
+        Some(Literal::Rational(
+            (numerator as i128 / divisor as i128).try_into().ok()?,
+            denominator / divisor,
+        ))
+    }
+
+    /// Creates a string literal.
+    pub fn string(value: &str) -> Self {
+        Literal::Str(String::from(value))
+    }
+
+    /// Returns the value of the literal as a float, or `None` if it is not numeric.
+    pub fn to_f64(&self) -> Option<f64> {
+        match self {
+            Literal::UInt(value) => Some(*value as f64),
+            Literal::Int(value) => Some(*value as f64),
+            Literal::Float(value) => Some(value.0),
+            Literal::Rational(numerator, denominator) => {
+                Some(*numerator as f64 / *denominator as f64)
+            }
+            Literal::Str(_) => None,
+        }
+    }
+
+    /// `true` unless the literal is a string.
+    pub fn is_numeric(&self) -> bool {
+        !matches!(self, Literal::Str(_))
+    }
+
+    /// Compares the values of two numeric literals, `None` if one of them is a string.
+    /// Unlike [`Ord`], which orders literals of equal value by their kind,
+    /// it finds e.g. `1`, `1u` and `1.0` equal.
+    pub fn cmp_value(&self, other: &Literal) -> Option<Ordering> {
+        if !self.is_numeric() || !other.is_numeric() {
+            return None;
+        }
+
+        Some(match (self.to_fraction(), other.to_fraction()) {
+            (Some((n_1, d_1)), Some((n_2, d_2))) => (n_1 * d_2).cmp(&(n_2 * d_1)),
+            _ => OrderedFloat(self.to_f64()?).cmp(&OrderedFloat(other.to_f64()?)),
+        })
+    }
+
+    /// Returns the value of the literal as an exact fraction, or `None` for floats and strings.
+    fn to_fraction(&self) -> Option<(i128, i128)> {
+        match self {
+            Literal::UInt(value) => Some((*value as i128, 1)),
+            Literal::Int(value) => Some((*value as i128, 1)),
+            Literal::Float(_) | Literal::Str(_) => None,
+            Literal::Rational(numerator, denominator) => {
+                Some((*numerator as i128, *denominator as i128))
+            }
+        }
+    }
+
+    /// Creates the simplest literal representing `numerator / denominator`:
+    /// an integer if possible, a rational otherwise.
+    fn from_fraction(numerator: i128, denominator: i128) -> Option<Self> {
+        if denominator == 0 {
+            return None;
+        }
+
+        let (numerator, denominator) = if denominator < 0 {
+            (-numerator, -denominator)
+        } else {
+            (numerator, denominator)
+        };
+
+        let divisor = gcd(
+            numerator.unsigned_abs().try_into().ok()?,
+            denominator.unsigned_abs().try_into().ok()?,
+        )
+        .max(1) as i128;
+        let (numerator, denominator) = (numerator / divisor, denominator / divisor);
+
+        if denominator == 1 {
+            Some(Literal::Int(numerator.try_into().ok()?))
+        } else {
+            Some(Literal::Rational(
+                numerator.try_into().ok()?,
+                denominator.try_into().ok()?,
+            ))
+        }
+    }
+
+    /// Folds a binary arithmetic operation on two literals.
+    /// Integers and rationals are combined exactly, falling back to floats if any operand is one.
+    /// Results of operations on two unsigned integers stay unsigned if they are non-negative
+    /// integers fitting in `u64`. Strings are never folded.
+    fn fold(
+        &self,
+        other: &Literal,
+        exact: impl FnOnce((i128, i128), (i128, i128)) -> Option<(i128, i128)>,
+        float: impl FnOnce(f64, f64) -> f64,
+    ) -> Option<Literal> {
+        match (self.to_fraction(), other.to_fraction()) {
+            (Some(left), Some(right)) => {
+                let (numerator, denominator) = exact(left, right)?;
+                if let (Literal::UInt(_), Literal::UInt(_)) = (self, other)
+                    && denominator != 0
+                    && numerator % denominator == 0
+                    && let Ok(value) = u64::try_from(numerator / denominator)
+                {
+                    return Some(Literal::UInt(value));
+                }
+                Literal::from_fraction(numerator, denominator)
+            }
+            _ => Some(Literal::float(float(self.to_f64()?, other.to_f64()?))),
+        }
+    }
+
+    /// Constant folding of addition. Returns `None` on overflow.
+    pub fn checked_add(&self, other: &Literal) -> Option<Literal> {
+        self.fold(
+            other,
+            |(n_1, d_1), (n_2, d_2)| {
+                Some((
+                    n_1.checked_mul(d_2)?.checked_add(n_2.checked_mul(d_1)?)?,
+                    d_1.checked_mul(d_2)?,
+                ))
+            },
+            |a, b| a + b,
+        )
+    }
+
+    /// Constant folding of subtraction. Returns `None` on overflow.
+    pub fn checked_sub(&self, other: &Literal) -> Option<Literal> {
+        self.fold(
+            other,
+            |(n_1, d_1), (n_2, d_2)| {
+                Some((
+                    n_1.checked_mul(d_2)?.checked_sub(n_2.checked_mul(d_1)?)?,
+                    d_1.checked_mul(d_2)?,
+                ))
+            },
+            |a, b| a - b,
+        )
+    }
+
+    /// Constant folding of multiplication. Returns `None` on overflow.
+    pub fn checked_mul(&self, other: &Literal) -> Option<Literal> {
+        self.fold(
+            other,
+            |(n_1, d_1), (n_2, d_2)| Some((n_1.checked_mul(n_2)?, d_1.checked_mul(d_2)?)),
+            |a, b| a * b,
+        )
+    }
+
+    /// Constant folding of exact division. Returns `None` on overflow or division of
+    /// non-float literals by zero.
+    pub fn checked_div(&self, other: &Literal) -> Option<Literal> {
+        self.fold(
+            other,
+            |(n_1, d_1), (n_2, d_2)| Some((n_1.checked_mul(d_2)?, d_1.checked_mul(n_2)?)),
+            |a, b| a / b,
+        )
+    }
+}
+
+impl PartialOrd for Literal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Literal {
+    /// Orders literals by their numeric value, and literals of equal value by their kind.
+    /// Integers and rationals are compared exactly. Strings are greater than all numbers
+    /// and are ordered lexicographically.
+    fn cmp(&self, other: &Self) -> Ordering {
+        let value_ordering = match (self, other) {
+            (Literal::Str(left), Literal::Str(right)) => left.cmp(right),
+            (Literal::Str(_), _) | (_, Literal::Str(_)) => self.kind().cmp(&other.kind()),
+            _ => self.cmp_value(other).unwrap(),
+        };
+
+        value_ordering
+            .then_with(|| self.kind().cmp(&other.kind()))
+            .then_with(|| match (self, other) {
+                (Literal::Float(left), Literal::Float(right)) => left.cmp(right),
+                _ => Ordering::Equal,
+            })
+    }
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Literal::UInt(uint) => write!(f, "{uint}u"),
+            Literal::Int(int) => write!(f, "{int}"),
+            Literal::Float(float) => write!(f, "{float}"),
+            Literal::Rational(numerator, denominator) => write!(f, "{numerator}/{denominator}"),
+            Literal::Str(string) => {
+                write!(f, "\"")?;
+                for character in string.chars() {
+                    match character {
+                        '"' => write!(f, "\\\"")?,
+                        '\\' => write!(f, "\\\\")?,
+                        '\n' => write!(f, "\\n")?,
+                        _ => write!(f, "{character}")?,
+                    }
+                }
+                write!(f, "\"")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Literal;
+
+    #[test]
+    fn rational_lowest_terms() {
+        assert_eq!(Literal::rational(6, 8), Some(Literal::Rational(3, 4)));
+        assert_eq!(Literal::rational(-6, 8), Some(Literal::Rational(-3, 4)));
+        assert_eq!(Literal::rational(0, 5), Some(Literal::Rational(0, 1)));
+        assert_eq!(Literal::rational(1, 0), None);
+        assert_eq!(
+            Literal::rational(i64::MIN, 1 << 63),
+            Some(Literal::Rational(-1, 1))
+        );
+        assert_eq!(
+            Literal::rational(i64::MIN, 1),
+            Some(Literal::Rational(i64::MIN, 1))
+        );
+    }
+
+    #[test]
+    fn float_equality() {
+        assert_eq!(Literal::float(1.5), Literal::float(1.5));
+        assert_eq!(Literal::float(f64::NAN), Literal::float(f64::NAN));
+        assert_ne!(Literal::float(0.0), Literal::float(-0.0));
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Literal::float(1.0).to_string(), "1.0");
+        assert_eq!(Literal::float(-2.25).to_string(), "-2.25");
+        assert_eq!(Literal::Rational(-3, 4).to_string(), "-3/4");
+        assert_eq!(
+            Literal::string("say \"hi\"\n").to_string(),
+            r#""say \"hi\"\n""#
+        );
+    }
+
+    #[test]
+    fn ordering() {
+        assert!(Literal::Int(-1) < Literal::UInt(0));
+        assert!(Literal::rational(1, 3).unwrap() < Literal::float(0.5));
+        assert!(Literal::float(0.5) < Literal::Int(1));
+        assert!(Literal::UInt(1) < Literal::Int(1));
+        assert!(Literal::Int(100) < Literal::string("a"));
+        assert!(Literal::string("a") < Literal::string("b"));
+    }
+
+    #[test]
+    fn constant_folding() {
+        let half = Literal::rational(1, 2).unwrap();
+        let third = Literal::rational(1, 3).unwrap();
+
+        assert_eq!(half.checked_add(&third), Literal::rational(5, 6));
+        assert_eq!(half.checked_add(&half), Some(Literal::Int(1)));
+        assert_eq!(
+            Literal::Int(3).checked_div(&Literal::Int(6)),
+            Literal::rational(1, 2)
+        );
+        assert_eq!(Literal::Int(3).checked_div(&Literal::Int(0)), None);
+        assert_eq!(
+            Literal::UInt(2).checked_mul(&Literal::float(1.5)),
+            Some(Literal::float(3.0))
+        );
+        assert_eq!(
+            third.checked_sub(&Literal::Int(1)),
+            Literal::rational(-2, 3)
+        );
+        assert_eq!(Literal::Int(i64::MAX).checked_add(&Literal::Int(1)), None);
+        assert_eq!(Literal::string("a").checked_add(&Literal::Int(1)), None);
+    }
+
+    #[test]
+    fn unsigned_folding() {
+        assert_eq!(
+            Literal::UInt(5).checked_add(&Literal::UInt(3)),
+            Some(Literal::UInt(8))
+        );
+        assert_eq!(
+            Literal::UInt(u64::MAX).checked_add(&Literal::UInt(0)),
+            Some(Literal::UInt(u64::MAX))
+        );
+        assert_eq!(
+            Literal::UInt(6).checked_div(&Literal::UInt(3)),
+            Some(Literal::UInt(2))
+        );
+        assert_eq!(
+            Literal::UInt(3).checked_div(&Literal::UInt(6)),
+            Literal::rational(1, 2)
+        );
+        // Negative results and mixed operands are signed
+        assert_eq!(
+            Literal::UInt(3).checked_sub(&Literal::UInt(5)),
+            Some(Literal::Int(-2))
+        );
+        assert_eq!(
+            Literal::UInt(5).checked_add(&Literal::Int(3)),
+            Some(Literal::Int(8))
+        );
+        assert_eq!(Literal::UInt(u64::MAX).checked_add(&Literal::UInt(1)), None);
+    }
 }
@@ -3,20 +3,23 @@
 //! This module provides various expression types used throughout the system.
 
 pub mod any;
+pub mod binding;
 pub mod literal;
 pub mod mixed;
 pub mod multi;
 pub mod path;
+pub mod pretty;
 pub mod var_free;
 
 pub use any::{AnyExpression, LangExpression};
-pub use literal::Literal;
+pub use literal::{Literal, LiteralKind, OrderedFloat};
 pub use mixed::MixedExpression;
 pub use path::{OwnedPath, Path};
+pub use pretty::PrettyConfig;
 pub use var_free::VarFreeExpression;
 
 use crate::language::Language;
-use crate::language::symbol::Symbol;
+use crate::language::symbol::{Symbol, SymbolId, SymbolIdRemap};
 use crate::rewriting::egraph::matching::EGraphMatch;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
@@ -55,21 +58,40 @@ impl Expression {
 
     /// Returns `None` if `self` contains variables, or a corresponding `VarFreeExpression` if it does not
     pub fn without_variables(&self) -> Option<VarFreeExpression> {
-        match self {
-            Expression::Variable(_) => None,
-            Expression::Symbol(Symbol { id, children }) => {
-                let mut children_no_vars = Vec::with_capacity(children.len());
-
-                for child in children {
-                    children_no_vars.push(child.without_variables()?);
+        // Symbols on the path to `current` with their remaining and converted children,
+        // kept on the heap so that deep expressions do not overflow the stack
+        let mut pending = Vec::new();
+        let mut current = self;
+        loop {
+            let mut converted = match current {
+                Expression::Variable(_) => return None,
+                Expression::Literal(literal) => VarFreeExpression::Literal(literal.clone()),
+                Expression::Symbol(Symbol { id, children }) => {
+                    let mut remaining = children.iter();
+                    if let Some(first) = remaining.next() {
+                        pending.push((*id, remaining, Vec::with_capacity(children.len())));
+                        current = first;
+                        continue;
+                    }
+                    VarFreeExpression::Symbol(Symbol {
+                        id: *id,
+                        children: Vec::new(),
+                    })
                 }
-
-                Some(VarFreeExpression::Symbol(Symbol {
-                    id: *id,
-                    children: children_no_vars,
-                }))
+            };
+
+            loop {
+                let Some((_, remaining, converted_children)) = pending.last_mut() else {
+                    return Some(converted);
+                };
+                converted_children.push(converted);
+                if let Some(next) = remaining.next() {
+                    current = next;
+                    break;
+                }
+                let (id, _, children) = pending.pop().unwrap();
+                converted = VarFreeExpression::Symbol(Symbol { id, children });
             }
-            Expression::Literal(literal) => Some(VarFreeExpression::Literal(literal.clone())),
         }
     }
 
@@ -150,30 +172,42 @@ impl Expression {
 
     /// Finds all variables in the expression together with their paths
     pub fn find_all_variables(&self) -> HashMap<VariableId, Vec<OwnedPath>> {
-        let mut vars = HashMap::new();
-        self.find_all_variables_impl(&mut OwnedPath::default(), &mut vars);
-        vars
-    }
-
-    fn find_all_variables_impl(
-        &self,
-        current_path: &mut OwnedPath,
-        vars: &mut HashMap<VariableId, Vec<OwnedPath>>,
-    ) {
-        match self {
-            Expression::Variable(variable_id) => {
-                vars.entry(*variable_id)
-                    .or_default()
-                    .push(current_path.clone());
+        let mut vars: HashMap<_, Vec<_>> = HashMap::new();
+        let mut current_path = OwnedPath::default();
+        // Remaining children of the symbols on `current_path`
+        let mut pending = Vec::new();
+        let mut current = self;
+        loop {
+            match current {
+                Expression::Variable(variable_id) => {
+                    vars.entry(*variable_id)
+                        .or_default()
+                        .push(current_path.clone());
+                }
+                Expression::Symbol(symbol) => {
+                    let mut remaining = symbol.children.iter().enumerate();
+                    if let Some((i, first)) = remaining.next() {
+                        current_path.push(i);
+                        pending.push(remaining);
+                        current = first;
+                        continue;
+                    }
+                }
+                Expression::Literal(_) => {}
             }
-            Expression::Symbol(symbol) => {
-                for (i, child) in symbol.children.iter().enumerate() {
+
+            loop {
+                let Some(remaining) = pending.last_mut() else {
+                    return vars;
+                };
+                current_path.pop();
+                if let Some((i, next)) = remaining.next() {
                     current_path.push(i);
-                    child.find_all_variables_impl(current_path, vars);
-                    current_path.pop();
+                    current = next;
+                    break;
                 }
+                pending.pop();
             }
-            Expression::Literal(_) => {}
         }
     }
 
@@ -195,6 +229,92 @@ impl Expression {
         }
     }
 
+    /// Renames every variable found in `renaming` to the corresponding variable,
+    /// all at once, so that e.g. `$0` and `$1` can be swapped. Other variables are kept.
+    pub fn rename_variables(&mut self, renaming: &HashMap<VariableId, VariableId>) {
+        match self {
+            Expression::Variable(id) => {
+                if let Some(new_id) = renaming.get(id) {
+                    *id = *new_id;
+                }
+            }
+            Expression::Symbol(symbol) => {
+                for child in &mut symbol.children {
+                    child.rename_variables(renaming);
+                }
+            }
+            Expression::Literal(_) => {}
+        }
+    }
+
+    /// Replaces the IDs of all symbols according to `remap`,
+    /// e.g. to move the expression into a language merged with [`Language::merge`].
+    pub fn remap_symbols(&mut self, remap: &SymbolIdRemap) {
+        if let Expression::Symbol(symbol) = self {
+            symbol.remap_id(remap);
+            for child in &mut symbol.children {
+                child.remap_symbols(remap);
+            }
+        }
+    }
+
+    /// Extends `renaming` with the variables of `self` missing in it, renamed to consecutive IDs
+    /// following the ones already in `renaming` in the order of their first occurrences.
+    /// Calling it for several expressions gives a canonical renaming shared by all of them,
+    /// e.g. both sides of a rule.
+    pub fn extend_canonical_renaming(&self, renaming: &mut HashMap<VariableId, VariableId>) {
+        let mut pending = vec![self];
+        while let Some(current) = pending.pop() {
+            match current {
+                Expression::Variable(id) => {
+                    let next = renaming.len();
+                    renaming.entry(*id).or_insert(next);
+                }
+                Expression::Symbol(symbol) => pending.extend(symbol.children.iter().rev()),
+                Expression::Literal(_) => {}
+            }
+        }
+    }
+
+    /// Renames variables to `$0`, `$1`, ... in the order of their first occurrences,
+    /// so that alpha-equivalent expressions become equal.
+    pub fn canonicalize_variables(&mut self) {
+        let mut renaming = HashMap::new();
+        self.extend_canonical_renaming(&mut renaming);
+        self.rename_variables(&renaming);
+    }
+
+    /// Checks if `self` and `other` are equal up to a one-to-one renaming of variables.
+    pub fn alpha_eq(&self, other: &Expression) -> bool {
+        let mut left_to_right = HashMap::new();
+        let mut right_to_left = HashMap::new();
+        let mut pending = vec![(self, other)];
+        while let Some(current) = pending.pop() {
+            match current {
+                (Expression::Variable(left), Expression::Variable(right)) => {
+                    if *left_to_right.entry(*left).or_insert(*right) != *right
+                        || *right_to_left.entry(*right).or_insert(*left) != *left
+                    {
+                        return false;
+                    }
+                }
+                (Expression::Symbol(left), Expression::Symbol(right)) => {
+                    if left.id != right.id || left.children.len() != right.children.len() {
+                        return false;
+                    }
+                    pending.extend(left.children.iter().zip(&right.children));
+                }
+                (Expression::Literal(left), Expression::Literal(right)) => {
+                    if left != right {
+                        return false;
+                    }
+                }
+                _ => return false,
+            }
+        }
+        true
+    }
+
     /// Replaces all occurences of `variable_id` with `expression`
     pub fn substitute(&mut self, variable_id: VariableId, expression: &Expression) {
         match self {
@@ -251,6 +371,13 @@ impl AnyExpression for Expression {
             _ => None,
         }
     }
+
+    fn symbol_id(&self) -> Option<SymbolId> {
+        match self {
+            Expression::Symbol(symbol) => Some(symbol.id),
+            _ => None,
+        }
+    }
 }
 
 /// Helper struct for loading expressions from JSON
@@ -295,8 +422,9 @@ pub fn load_expressions_from_file<P: AsRef<std::path::Path>>(
 #[cfg(test)]
 mod tests {
     use super::Expression;
-    use crate::language::Language;
+    use crate::language::{Language, symbol::Symbol};
     use serde_json;
+    use std::collections::HashMap;
 
     #[test]
     fn test_expression_serialization() {
@@ -325,4 +453,56 @@ mod tests {
                 .contains_variable(0)
         );
     }
+
+    #[test]
+    fn variable_renaming() {
+        let lang = Language::simple_math();
+        let mut expr = lang.parse("(+ $3 (* $0 $3))").unwrap();
+
+        let mut swapped = expr.clone();
+        swapped.rename_variables(&HashMap::from([(0, 3), (3, 0)]));
+        assert_eq!(swapped, lang.parse("(+ $0 (* $3 $0))").unwrap());
+
+        expr.canonicalize_variables();
+        assert_eq!(expr, lang.parse("(+ $0 (* $1 $0))").unwrap());
+
+        let mut renaming = HashMap::new();
+        lang.parse("(* $5 $2)")
+            .unwrap()
+            .extend_canonical_renaming(&mut renaming);
+        lang.parse("(+ $2 $7)")
+            .unwrap()
+            .extend_canonical_renaming(&mut renaming);
+        assert_eq!(renaming, HashMap::from([(5, 0), (2, 1), (7, 2)]));
+    }
+
+    #[test]
+    fn alpha_equivalence() {
+        let lang = Language::simple_math();
+        let expr = lang.parse("(+ $0 (* $1 $0))").unwrap();
+
+        assert!(expr.alpha_eq(&lang.parse("(+ $4 (* $2 $4))").unwrap()));
+        assert!(expr.alpha_eq(&lang.parse("(+ $1 (* $0 $1))").unwrap()));
+        assert!(!expr.alpha_eq(&lang.parse("(+ $0 (* $0 $0))").unwrap()));
+        assert!(!expr.alpha_eq(&lang.parse("(+ $0 (* $1 $2))").unwrap()));
+        assert!(!expr.alpha_eq(&lang.parse("(+ $0 (* 1 $0))").unwrap()));
+    }
+
+    #[test]
+    fn deep_expression_traversals() {
+        let lang = Language::simple_math();
+        let depth = 10_000;
+        let mut expr = Expression::Variable(0);
+        for _ in 0..depth {
+            expr = Expression::Symbol(Symbol {
+                id: lang.get_id("sin"),
+                children: vec![expr],
+            });
+        }
+
+        let vars = expr.find_all_variables();
+        assert_eq!(vars[&0].len(), 1);
+        assert_eq!(vars[&0][0].0, vec![0; depth]);
+        assert!(expr.without_variables().is_none());
+    }
 }
@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 
 use super::AnyExpression;
 
-#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub struct OwnedPath(pub Vec<usize>);
 
 impl OwnedPath {
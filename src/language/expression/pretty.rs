@@ -0,0 +1,147 @@
+//! Pretty printing of expressions.
+//!
+//! Expressions which fit within the configured width are printed on a single line,
+//! just like with [`std::fmt::Display`]. Longer expressions are broken up so that every
+//! child of a symbol is placed on its own, indented line.
+
+use super::{AnyExpression, LangExpression};
+use crate::language::Language;
+use std::fmt::Display;
+
+/// Configuration of the pretty printer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PrettyConfig {
+    /// Maximum number of characters in a line, including indentation.
+    /// Leaves longer than this are never broken.
+    pub width: usize,
+    /// Number of spaces by which children of a broken up symbol are indented
+    pub indent: usize,
+}
+
+impl PrettyConfig {
+    /// Creates a configuration with the given maximum width and the default indentation.
+    pub fn new(width: usize) -> Self {
+        Self {
+            width,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the number of spaces used for a single level of indentation.
+    pub fn with_indent(mut self, indent: usize) -> Self {
+        self.indent = indent;
+        self
+    }
+}
+
+impl Default for PrettyConfig {
+    fn default() -> Self {
+        Self {
+            width: 80,
+            indent: 2,
+        }
+    }
+}
+
+impl<'e, 'l, E: AnyExpression> LangExpression<'e, 'l, E>
+where
+    for<'a, 'b> LangExpression<'a, 'b, E>: Display,
+{
+    /// Formats the expression so that no line is longer than `config.width`, if possible.
+    pub fn pretty(&self, config: &PrettyConfig) -> String {
+        let mut out = String::new();
+        write_pretty(self.expression.as_ref(), self.language, config, 0, &mut out);
+        out
+    }
+}
+
+/// Appends `expression` to `out`, assuming the current line is already indented by `indent`.
+fn write_pretty<E: AnyExpression>(
+    expression: &E,
+    language: &Language,
+    config: &PrettyConfig,
+    indent: usize,
+    out: &mut String,
+) where
+    for<'a, 'b> LangExpression<'a, 'b, E>: Display,
+{
+    let flat = expression.with_language(language).to_string();
+    let (Some(id), Some(children)) = (expression.symbol_id(), expression.children()) else {
+        out.push_str(&flat);
+        return;
+    };
+
+    if indent + flat.chars().count() <= config.width || children.is_empty() {
+        out.push_str(&flat);
+        return;
+    }
+
+    let child_indent = indent + config.indent;
+    out.push('(');
+    out.push_str(language.get_symbol(id));
+    for child in children {
+        out.push('\n');
+        out.push_str(&" ".repeat(child_indent));
+        write_pretty(child, language, config, child_indent, out);
+    }
+    out.push(')');
+}
+
+impl Language {
+    /// Formats `expression` in this language with lines at most `width` characters long, if possible.
+    pub fn format<E: AnyExpression>(&self, expression: &E, width: usize) -> String
+    where
+        for<'a, 'b> LangExpression<'a, 'b, E>: Display,
+    {
+        expression
+            .with_language(self)
+            .pretty(&PrettyConfig::new(width))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PrettyConfig;
+    use crate::language::{Language, expression::AnyExpression};
+
+    #[test]
+    fn short_expression_on_one_line() {
+        let lang = Language::simple_math();
+        let expr = lang.parse("(+ $0 (sin 1))").unwrap();
+        assert_eq!(lang.format(&expr, 80), "(+ $0 (sin 1))");
+    }
+
+    #[test]
+    fn long_expression_wrapped() {
+        let lang = Language::simple_math();
+        let expr = lang
+            .parse_no_vars("(+ (* 1 2) (sin (- 300 400 500)))")
+            .unwrap();
+        assert_eq!(
+            lang.format(&expr, 20),
+            "(+\n  (* 1 2)\n  (sin\n    (- 300 400 500)))"
+        );
+    }
+
+    #[test]
+    fn custom_indent() {
+        let lang = Language::simple_math();
+        let expr = lang.parse("(+ $0 $1)").unwrap();
+        let config = PrettyConfig::new(5).with_indent(4);
+        assert_eq!(
+            expr.with_language(&lang).pretty(&config),
+            "(+\n    $0\n    $1)"
+        );
+    }
+
+    #[test]
+    fn wrapped_expression_parses_back() {
+        let lang = Language::simple_math();
+        let expr = lang
+            .parse("(* (+ $0 (sin (cos 1.5))) (- $1 (/ 2 3/4)) (cos \"long string\"))")
+            .unwrap();
+        let formatted = lang.format(&expr, 12);
+        assert!(formatted.lines().count() > 1);
+        assert_eq!(lang.parse(&formatted).unwrap(), expr);
+    }
+}
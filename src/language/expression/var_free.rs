@@ -5,14 +5,14 @@
 
 use super::{AnyExpression, Expression, Literal};
 use crate::language::Language;
-use crate::language::symbol::Symbol;
+use crate::language::symbol::{Symbol, SymbolId, SymbolIdRemap};
 use serde::{Deserialize, Serialize};
 
 /// An expression which does not contain variables.
 ///
 /// Unlike [`Expression`], this type guarantees the absence of variables.
 /// These are ground terms consisting only of literals and symbols with concrete children.
-#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[derive(Clone, Hash, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum VarFreeExpression {
     /// A literal constant value
     Literal(Literal),
@@ -43,6 +43,16 @@ impl VarFreeExpression {
         }
     }
 
+    /// Replaces the IDs of all symbols according to `remap`, see [`Expression::remap_symbols`].
+    pub fn remap_symbols(&mut self, remap: &SymbolIdRemap) {
+        if let VarFreeExpression::Symbol(symbol) = self {
+            symbol.remap_id(remap);
+            for child in &mut symbol.children {
+                child.remap_symbols(remap);
+            }
+        }
+    }
+
     /// Applies a transformation function at a specific path in the expression tree.
     ///
     /// Uses `subexpression` to find the target, avoiding manual recursion.
@@ -147,6 +157,13 @@ impl AnyExpression for VarFreeExpression {
             _ => None,
         }
     }
+
+    fn symbol_id(&self) -> Option<SymbolId> {
+        match self {
+            VarFreeExpression::Symbol(symbol) => Some(symbol.id),
+            _ => None,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -162,4 +179,15 @@ mod tests {
         let deserialized: VarFreeExpression = serde_json::from_str(&serialized).unwrap();
         assert_eq!(expr, deserialized);
     }
+
+    #[test]
+    fn test_var_free_expression_serialization_with_strings() {
+        let lang = crate::language::Language::simple_math();
+        let expr = lang
+            .parse_no_vars(r#"(+ "field" (* 1.5 "a \"b\""))"#)
+            .unwrap();
+        let serialized = serde_json::to_string(&expr).unwrap();
+        let deserialized: VarFreeExpression = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(expr, deserialized);
+    }
 }
@@ -0,0 +1,554 @@
+//! Infix expression parsing.
+//!
+//! This module provides an alternative to the s-expression syntax for languages modelling
+//! arithmetic, e.g. `$0 * 2 + sin($1)`. Operators are resolved with a [`PrecedenceTable`],
+//! and the result is the same [`Expression`] that the s-expression parser produces
+//! for the equivalent input.
+//!
+//! Besides infix and prefix operators, the syntax consists of:
+//! * function calls such as `sin(x)` or `f(x, y)`, where the name is a symbol of the language,
+//! * variables, written either as `$0`, `$1`, ... or as identifiers which are not symbols,
+//! * literals in the s-expression syntax, except for rationals, since `3/4` is a division,
+//! * parentheses for grouping.
+
+use super::{
+    Language,
+    expression::{Expression, Literal, VariableId},
+    parsing::{LanguageParser, ParseError, Rule},
+    symbol::Symbol,
+};
+use pest::Parser;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How operators of the same precedence group.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Associativity {
+    /// `a - b - c` is `(a - b) - c`
+    Left,
+    /// `a ^ b ^ c` is `a ^ (b ^ c)`
+    Right,
+}
+
+/// A binary operator written between its operands.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InfixOperator {
+    /// Name of the symbol the operator stands for
+    pub symbol: String,
+    /// Operators with higher precedence bind more tightly
+    pub precedence: u32,
+    pub associativity: Associativity,
+}
+
+/// A unary operator written before its operand.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrefixOperator {
+    /// Name of the symbol the operator stands for
+    pub symbol: String,
+    /// Infix operators with lower precedence than this are not part of the operand
+    pub precedence: u32,
+}
+
+/// Operator tokens accepted by the infix parser, together with their precedences.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrecedenceTable {
+    #[serde(default)]
+    infix: HashMap<String, InfixOperator>,
+    #[serde(default)]
+    prefix: HashMap<String, PrefixOperator>,
+}
+
+impl PrecedenceTable {
+    /// Creates a table without any operators.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a table with the usual arithmetic operators, from the loosest binding:
+    /// `<<` and `>>`, `+` and `-`, `*` and `/`, prefix `-`, and right-associative `^`.
+    /// Each token stands for the symbol of the same name.
+    pub fn arithmetic() -> Self {
+        Self::new()
+            .with_infix("<<", "<<", 1, Associativity::Left)
+            .with_infix(">>", ">>", 1, Associativity::Left)
+            .with_infix("+", "+", 2, Associativity::Left)
+            .with_infix("-", "-", 2, Associativity::Left)
+            .with_infix("*", "*", 3, Associativity::Left)
+            .with_infix("/", "/", 3, Associativity::Left)
+            .with_prefix("-", "-", 4)
+            .with_infix("^", "^", 5, Associativity::Right)
+    }
+
+    /// Adds a binary operator.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The operator as written in the input
+    /// * `symbol` - Name of the symbol the operator stands for
+    /// * `precedence` - Operators with higher precedence bind more tightly
+    /// * `associativity` - How operators of the same precedence group
+    pub fn with_infix(
+        mut self,
+        token: &str,
+        symbol: &str,
+        precedence: u32,
+        associativity: Associativity,
+    ) -> Self {
+        self.infix.insert(
+            String::from(token),
+            InfixOperator {
+                symbol: String::from(symbol),
+                precedence,
+                associativity,
+            },
+        );
+        self
+    }
+
+    /// Adds a unary operator.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The operator as written in the input
+    /// * `symbol` - Name of the symbol the operator stands for
+    /// * `precedence` - Infix operators with lower precedence than this are not part of the operand
+    pub fn with_prefix(mut self, token: &str, symbol: &str, precedence: u32) -> Self {
+        self.prefix.insert(
+            String::from(token),
+            PrefixOperator {
+                symbol: String::from(symbol),
+                precedence,
+            },
+        );
+        self
+    }
+
+    /// Returns a table with the operators of both tables.
+    /// Where both tables define the same token, the operator of `self` is kept.
+    pub fn merge(&self, other: &PrecedenceTable) -> PrecedenceTable {
+        let mut merged = other.clone();
+        merged.infix.extend(self.infix.clone());
+        merged.prefix.extend(self.prefix.clone());
+        merged
+    }
+}
+
+/// Finds the longest token in `operators` with which `input` starts.
+/// Tokens ending with an alphanumeric character must not be followed by one.
+fn longest_match<'t, O>(
+    operators: &'t HashMap<String, O>,
+    input: &str,
+) -> Option<(&'t String, &'t O)> {
+    operators
+        .iter()
+        .filter(|(token, _)| {
+            input.starts_with(token.as_str())
+                && !(token.ends_with(is_identifier_char)
+                    && input[token.len()..].starts_with(is_identifier_char))
+        })
+        .max_by_key(|(token, _)| token.len())
+}
+
+fn is_identifier_char(character: char) -> bool {
+    character.is_alphanumeric() || character == '_'
+}
+
+/// Recursive descent parser using precedence climbing for operators.
+struct InfixParser<'p> {
+    language: &'p Language,
+    table: &'p PrecedenceTable,
+    input: &'p str,
+    position: usize,
+    variables: &'p mut HashMap<String, VariableId>,
+    /// ID given to the next new named variable, above all explicit and assigned IDs
+    next_fresh: VariableId,
+}
+
+impl InfixParser<'_> {
+    fn rest(&self) -> &str {
+        &self.input[self.position..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    fn unexpected(&self) -> ParseError {
+        if self.position == self.input.len() {
+            ParseError::UnexpectedEnd
+        } else {
+            ParseError::UnexpectedInput {
+                position: self.position,
+            }
+        }
+    }
+
+    /// Consumes `expected` if the input continues with it, after optional whitespace.
+    fn eat(&mut self, expected: char) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(expected) {
+            self.position += expected.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ParseError> {
+        if self.eat(expected) {
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> &str {
+        let start = self.position;
+        let length = self
+            .rest()
+            .find(|character| !predicate(character))
+            .unwrap_or(self.rest().len());
+        self.position += length;
+        &self.input[start..self.position]
+    }
+
+    fn symbol(&self, name: &str, children: Vec<Expression>) -> Result<Expression, ParseError> {
+        let id = self
+            .language
+            .try_get_id(name)
+            .ok_or_else(|| ParseError::UnknownSymbol(String::from(name)))?;
+        self.language.check_arity(id, children.len())?;
+        Ok(Expression::Symbol(Symbol { id, children }))
+    }
+
+    /// Parses an expression containing only infix operators of at least `min_precedence`.
+    fn parse_expression(&mut self, min_precedence: u32) -> Result<Expression, ParseError> {
+        let mut left = self.parse_prefix()?;
+
+        loop {
+            self.skip_whitespace();
+            let Some((token, operator)) = longest_match(&self.table.infix, self.rest()) else {
+                break;
+            };
+            if operator.precedence < min_precedence {
+                break;
+            }
+
+            self.position += token.len();
+            let right_precedence = match operator.associativity {
+                Associativity::Left => operator.precedence + 1,
+                Associativity::Right => operator.precedence,
+            };
+            let right = self.parse_expression(right_precedence)?;
+            left = self.symbol(&operator.symbol, vec![left, right])?;
+        }
+
+        Ok(left)
+    }
+
+    /// Parses a primary expression preceded by any number of prefix operators.
+    /// A `-` directly before a number produces a negative literal, as in the s-expression syntax.
+    fn parse_prefix(&mut self) -> Result<Expression, ParseError> {
+        self.skip_whitespace();
+        let Some((token, operator)) = longest_match(&self.table.prefix, self.rest()) else {
+            return self.parse_primary();
+        };
+
+        self.position += token.len();
+        let operand = self.parse_expression(operator.precedence)?;
+        let negated = match &operand {
+            Expression::Literal(Literal::Int(value)) if token == "-" => {
+                value.checked_neg().map(Literal::Int)
+            }
+            Expression::Literal(Literal::Float(value)) if token == "-" => {
+                Some(Literal::float(-value.0))
+            }
+            _ => None,
+        };
+
+        match negated {
+            Some(literal) => Ok(Expression::Literal(literal)),
+            None => self.symbol(&operator.symbol, vec![operand]),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Expression, ParseError> {
+        self.skip_whitespace();
+        let Some(next) = self.rest().chars().next() else {
+            return Err(ParseError::UnexpectedEnd);
+        };
+
+        if next == '(' {
+            self.position += 1;
+            let expression = self.parse_expression(0)?;
+            self.expect(')')?;
+            Ok(expression)
+        } else if next == '$' {
+            self.position += 1;
+            let start = self.position;
+            self.take_while(|character| character.is_ascii_digit())
+                .parse()
+                .map(Expression::Variable)
+                .map_err(|_| ParseError::UnexpectedInput { position: start })
+        } else if next.is_ascii_digit() || next == '"' {
+            self.parse_literal()
+        } else if is_identifier_char(next) {
+            let name = self.take_while(is_identifier_char).to_owned();
+            if self.eat('(') {
+                let mut children = Vec::new();
+                if !self.eat(')') {
+                    loop {
+                        children.push(self.parse_expression(0)?);
+                        if self.eat(')') {
+                            break;
+                        }
+                        self.expect(',')?;
+                    }
+                }
+                self.symbol(&name, children)
+            } else if self.language.try_get_id(&name).is_some() {
+                self.symbol(&name, Vec::new())
+            } else {
+                let id = *self.variables.entry(name).or_insert_with(|| {
+                    self.next_fresh += 1;
+                    self.next_fresh - 1
+                });
+                Ok(Expression::Variable(id))
+            }
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    /// Parses a literal with the grammar of the s-expression parser.
+    fn parse_literal(&mut self) -> Result<Expression, ParseError> {
+        for rule in [
+            Rule::string,
+            Rule::float,
+            Rule::unsigned_integer,
+            Rule::integer,
+        ] {
+            let input = self.input;
+            if let Ok(mut pairs) = LanguageParser::parse(rule, &input[self.position..]) {
+                let pair = pairs.next().unwrap();
+                self.position += pair.as_str().len();
+                return self.language.parse_expression(pair);
+            }
+        }
+
+        Err(self.unexpected())
+    }
+}
+
+impl Language {
+    /// Attaches a precedence table used by [`Language::parse_infix`].
+    pub fn with_precedence_table(mut self, table: PrecedenceTable) -> Self {
+        self.precedence_table = Some(table);
+        self
+    }
+
+    /// Returns the attached precedence table, if any.
+    pub fn precedence_table(&self) -> Option<&PrecedenceTable> {
+        self.precedence_table.as_ref()
+    }
+
+    /// Parses an expression written in infix notation, e.g. `a * 2 + sin(x)`.
+    ///
+    /// Operators are taken from the precedence table of the language, or from
+    /// [`PrecedenceTable::arithmetic`] if it has none. Identifiers which are not symbols
+    /// of the language are variables, numbered in the order of their first occurrence
+    /// starting above the largest ID of variables written as `$N`.
+    ///
+    /// # Arguments
+    ///
+    /// * `string` - The infix representation of the expression
+    ///
+    /// # Returns
+    ///
+    /// Returns an `Expression` on success, or an error if parsing fails.
+    pub fn parse_infix(&self, string: &str) -> anyhow::Result<Expression> {
+        self.parse_infix_with_variables(string, &mut HashMap::new())
+    }
+
+    /// Parses an expression written in infix notation, like [`Language::parse_infix`],
+    /// resolving named variables with `variables`. Names missing from `variables` are added
+    /// with IDs above all IDs in `variables` and in `string`, so that several expressions,
+    /// e.g. both sides of a rule, can share their variables.
+    ///
+    /// # Arguments
+    ///
+    /// * `string` - The infix representation of the expression
+    /// * `variables` - IDs of named variables
+    ///
+    /// # Returns
+    ///
+    /// Returns an `Expression` on success, or an error if parsing fails.
+    pub fn parse_infix_with_variables(
+        &self,
+        string: &str,
+        variables: &mut HashMap<String, VariableId>,
+    ) -> anyhow::Result<Expression> {
+        let arithmetic;
+        let table = match &self.precedence_table {
+            Some(table) => table,
+            None => {
+                arithmetic = PrecedenceTable::arithmetic();
+                &arithmetic
+            }
+        };
+
+        // Explicit `$N` variables may appear after named ones, so they are found up front
+        let explicit = string.split('$').skip(1).filter_map(|rest| {
+            let digits = rest
+                .find(|character: char| !character.is_ascii_digit())
+                .unwrap_or(rest.len());
+            rest[..digits].parse::<VariableId>().ok()
+        });
+        let next_fresh = explicit
+            .chain(variables.values().copied())
+            .map(|id| id + 1)
+            .fold(0, VariableId::max);
+
+        let mut parser = InfixParser {
+            language: self,
+            table,
+            input: string,
+            position: 0,
+            variables,
+            next_fresh,
+        };
+
+        let expression = parser.parse_expression(0)?;
+        parser.skip_whitespace();
+        if parser.position != string.len() {
+            return Err(parser.unexpected().into());
+        }
+
+        Ok(expression)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Associativity, PrecedenceTable};
+    use crate::language::{Language, arities::Arities, parsing::ParseError};
+
+    fn assert_same(infix: &str, sexpr: &str) {
+        let lang = Language::simple_math();
+        assert_eq!(
+            lang.parse_infix(infix).unwrap(),
+            lang.parse(sexpr).unwrap(),
+            "{infix}"
+        );
+    }
+
+    #[test]
+    fn precedence_and_associativity() {
+        assert_same("$0 * 2 + sin($1)", "(+ (* $0 2) (sin $1))");
+        assert_same("1 + 2 * 3", "(+ 1 (* 2 3))");
+        assert_same("(1 + 2) * 3", "(* (+ 1 2) 3)");
+        assert_same("1 - 2 - 3", "(- (- 1 2) 3)");
+        assert_same("1 / 2 / 3", "(/ (/ 1 2) 3)");
+        assert_same("1 << 2 + 3", "(<< 1 (+ 2 3))");
+    }
+
+    #[test]
+    fn named_variables() {
+        assert_same("a * 2 + sin(x)", "(+ (* $0 2) (sin $1))");
+        assert_same("x * x + y", "(+ (* $0 $0) $1)");
+        assert_same("x * $0", "(* $1 $0)");
+        assert_same("x + $2 * y", "(+ $3 (* $2 $4))");
+    }
+
+    #[test]
+    fn negation_and_literals() {
+        assert_same("-3", "-3");
+        assert_same("-1.5 * 2u", "(* -1.5 2u)");
+        assert_same("- $0", "(- $0)");
+        assert_same("2 * -sin(1)", "(* 2 (- (sin 1)))");
+        assert_same("3/4", "(/ 3 4)");
+        assert_same("cos(\"x\")", "(cos \"x\")");
+    }
+
+    #[test]
+    fn right_associative_operator() {
+        let lang = Language::simple_math().add_symbol("^");
+        assert_eq!(
+            lang.parse_infix("2 ^ 3 ^ x").unwrap(),
+            lang.parse("(^ 2 (^ 3 $0))").unwrap()
+        );
+        assert_eq!(
+            lang.parse_infix("(-2) ^ 3").unwrap(),
+            lang.parse("(^ -2 3)").unwrap()
+        );
+        assert_eq!(
+            lang.parse_infix("-x ^ 2").unwrap(),
+            lang.parse("(- (^ $0 2))").unwrap()
+        );
+    }
+
+    #[test]
+    fn custom_table() {
+        let lang = Language::simple_math()
+            .add_symbol("and")
+            .add_symbol("or")
+            .add_symbol("not")
+            .with_precedence_table(
+                PrecedenceTable::new()
+                    .with_infix("||", "or", 1, Associativity::Left)
+                    .with_infix("and", "and", 2, Associativity::Left)
+                    .with_prefix("!", "not", 3),
+            );
+
+        assert_eq!(
+            lang.parse_infix("!a || b and candy").unwrap(),
+            lang.parse("(or (not $0) (and $1 $2))").unwrap()
+        );
+        assert!(lang.parse_infix("a + b").is_err());
+    }
+
+    #[test]
+    fn shared_variables() {
+        let lang = Language::simple_math();
+        let mut variables = Default::default();
+        let from = lang
+            .parse_infix_with_variables("a * b", &mut variables)
+            .unwrap();
+        let to = lang
+            .parse_infix_with_variables("b * a", &mut variables)
+            .unwrap();
+        assert_eq!(from, lang.parse("(* $0 $1)").unwrap());
+        assert_eq!(to, lang.parse("(* $1 $0)").unwrap());
+    }
+
+    #[test]
+    fn errors() {
+        let lang = Language::simple_math();
+        let error = |string| {
+            lang.parse_infix(string)
+                .unwrap_err()
+                .downcast::<ParseError>()
+                .unwrap()
+        };
+
+        assert_eq!(error("1 +"), ParseError::UnexpectedEnd);
+        assert_eq!(error("(1 + 2"), ParseError::UnexpectedEnd);
+        assert_eq!(error("1 + 2)"), ParseError::UnexpectedInput { position: 5 });
+        assert_eq!(error("1 2"), ParseError::UnexpectedInput { position: 2 });
+        assert_eq!(error("1 % 2"), ParseError::UnexpectedInput { position: 2 });
+        assert_eq!(error("1 ^ 2"), ParseError::UnknownSymbol(String::from("^")));
+        assert_eq!(error("f(1)"), ParseError::UnknownSymbol(String::from("f")));
+
+        let mut arities = Arities::default();
+        arities.set(lang.get_id("sin"), vec![1]);
+        let lang = lang.with_arities(arities);
+        assert!(matches!(
+            lang.parse_infix("sin(1, 2)")
+                .unwrap_err()
+                .downcast::<ParseError>()
+                .unwrap(),
+            ParseError::ArityMismatch { got: 2, .. }
+        ));
+    }
+}
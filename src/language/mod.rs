@@ -13,26 +13,58 @@
 //!     .add_symbol("*");
 //! ```
 
+use std::collections::BTreeSet;
+
+use arities::Arities;
+use infix::PrecedenceTable;
 use serde::{Deserialize, Serialize};
-use symbol::SymbolId;
+use sorts::Sorts;
+use symbol::{SymbolId, SymbolIdRemap};
 
 pub mod arities;
+pub mod enumeration;
+pub mod eval;
 pub mod expression;
+pub mod infix;
 pub mod parsing;
+pub mod sorts;
 pub mod symbol;
 pub mod topology;
 
+pub use enumeration::enumerate_ground_terms;
+
 /// A symbolic language definition.
 ///
 /// The `Language` struct represents a collection of symbols that can be used to
 /// build expressions. Symbols are identified by their unique IDs, which are
 /// assigned based on the order they are added to the language. Symbols do not
-/// have a fixed arity and can have any number of children.
+/// have a fixed arity and can have any number of children, unless the language
+/// carries an [`Arities`] table, in which case parsing rejects symbols with
+/// undeclared numbers of children. A language may also be many-sorted, see [`Sorts`].
+/// Expressions can be written in the infix notation described by a [`PrecedenceTable`].
+/// Symbols can be declared associative and commutative, which lets
+/// [`AcMatcher`](crate::rewriting::egraph::matching::ac::AcMatcher) match modulo these laws,
+/// and flattened, in which case their nested applications are written as single n-ary ones.
+/// Nullary symbols added as constants, e.g. `pi` or `true`, are written without parentheses.
+/// Symbols declared as binders, e.g. `lam` or `forall`, bind the variable which is their first
+/// child in their remaining children, see [`expression::binding`].
 #[derive(Default, Clone, Serialize, Deserialize, PartialEq, Debug)]
 pub struct Language {
-    // Runtime constraints specifying number of inputs/outputs?
     symbols: Vec<String>,
-    // constraints: Vec<Vec<Constraint>>
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    arities: Option<Arities>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sorts: Option<Sorts>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    precedence_table: Option<PrecedenceTable>,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    ac_symbols: BTreeSet<SymbolId>,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    flattened_symbols: BTreeSet<SymbolId>,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    constants: BTreeSet<SymbolId>,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    binders: BTreeSet<SymbolId>,
 }
 
 impl Language {
@@ -50,6 +82,168 @@ impl Language {
         self
     }
 
+    /// Adds a new symbol to the language as a constant, e.g. `pi` or `true`.
+    ///
+    /// Constants are nullary symbols, which are written without parentheses,
+    /// e.g. `(and p true)`, and are distinct from both variables and literals.
+    /// They can still be written as `(true)`, which is the same expression.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the constant, starting with a letter and consisting of letters,
+    ///   digits and underscores
+    ///
+    /// # Returns
+    ///
+    /// Returns the language with the new constant added
+    pub fn add_constant(mut self, name: &str) -> Self {
+        self.constants.insert(self.symbols.len());
+        self.add_symbol(name)
+    }
+
+    /// `true` if the symbol with ID `id` is a constant.
+    pub fn is_constant(&self, id: SymbolId) -> bool {
+        self.constants.contains(&id)
+    }
+
+    /// Returns the IDs of the constants of the language.
+    pub fn constants(&self) -> &BTreeSet<SymbolId> {
+        &self.constants
+    }
+
+    /// Declares the allowed arities of symbols.
+    /// Symbols missing from `arities` still accept any number of children.
+    ///
+    /// # Arguments
+    ///
+    /// * `arities` - The arities table, indexed by symbol IDs of this language
+    ///
+    /// # Returns
+    ///
+    /// Returns the language with the arities attached
+    pub fn with_arities(mut self, arities: Arities) -> Self {
+        self.arities = Some(arities);
+        self
+    }
+
+    /// Returns the declared arities of symbols, if any.
+    pub fn arities(&self) -> Option<&Arities> {
+        self.arities.as_ref()
+    }
+
+    /// Declares sorts of symbols and literals, making the language many-sorted.
+    ///
+    /// # Arguments
+    ///
+    /// * `sorts` - The sort table, indexed by symbol IDs of this language
+    ///
+    /// # Returns
+    ///
+    /// Returns the language with the sorts attached
+    pub fn with_sorts(mut self, sorts: Sorts) -> Self {
+        self.sorts = Some(sorts);
+        self
+    }
+
+    /// Returns the declared sorts of symbols and literals, if any.
+    pub fn sorts(&self) -> Option<&Sorts> {
+        self.sorts.as_ref()
+    }
+
+    /// Declares a symbol to be associative and commutative.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the symbol
+    ///
+    /// # Returns
+    ///
+    /// Returns the language with the symbol declared
+    ///
+    /// # Panics
+    ///
+    /// Panics if the symbol is not present in the language
+    pub fn declare_ac(mut self, name: &str) -> Self {
+        let id = self.get_id(name);
+        self.ac_symbols.insert(id);
+        self
+    }
+
+    /// `true` if the symbol with ID `id` is declared associative and commutative.
+    pub fn is_ac(&self, id: SymbolId) -> bool {
+        self.ac_symbols.contains(&id)
+    }
+
+    /// Returns the IDs of the symbols declared associative and commutative.
+    pub fn ac_symbols(&self) -> &BTreeSet<SymbolId> {
+        &self.ac_symbols
+    }
+
+    /// Declares a symbol to be variadic and associative, so that its nested applications
+    /// such as `(+ a (+ b c))` are flattened into `(+ a b c)` when added to e-graphs,
+    /// see [`EGraph::with_flattened_symbols`](crate::rewriting::egraph::EGraph::with_flattened_symbols).
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the symbol
+    ///
+    /// # Returns
+    ///
+    /// Returns the language with the symbol declared
+    ///
+    /// # Panics
+    ///
+    /// Panics if the symbol is not present in the language
+    pub fn declare_flattened(mut self, name: &str) -> Self {
+        let id = self.get_id(name);
+        self.flattened_symbols.insert(id);
+        self
+    }
+
+    /// `true` if the symbol with ID `id` is declared flattened.
+    pub fn is_flattened(&self, id: SymbolId) -> bool {
+        self.flattened_symbols.contains(&id)
+    }
+
+    /// Returns the IDs of the symbols declared flattened.
+    pub fn flattened_symbols(&self) -> &BTreeSet<SymbolId> {
+        &self.flattened_symbols
+    }
+
+    /// Declares a symbol to be a binder, e.g. `lam` or `forall`, so that the variable which is
+    /// its first child is bound in its remaining children, e.g. `$0` in `(lam $0 (f $0))`.
+    ///
+    /// Bound variables are renamed to avoid capture by
+    /// [`Expression::substitute_avoiding_capture`](expression::Expression::substitute_avoiding_capture)
+    /// and respected by matching and unification which are given the binders.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the symbol
+    ///
+    /// # Returns
+    ///
+    /// Returns the language with the symbol declared
+    ///
+    /// # Panics
+    ///
+    /// Panics if the symbol is not present in the language
+    pub fn declare_binder(mut self, name: &str) -> Self {
+        let id = self.get_id(name);
+        self.binders.insert(id);
+        self
+    }
+
+    /// `true` if the symbol with ID `id` is declared a binder.
+    pub fn is_binder(&self, id: SymbolId) -> bool {
+        self.binders.contains(&id)
+    }
+
+    /// Returns the IDs of the symbols declared binders.
+    pub fn binders(&self) -> &BTreeSet<SymbolId> {
+        &self.binders
+    }
+
     /// Gets the name of a symbol by its ID.
     ///
     /// # Arguments
@@ -117,6 +311,97 @@ impl Language {
     pub fn symbol_count(&self) -> usize {
         self.symbols.len()
     }
+
+    /// Combines two languages, e.g. of rule sets loaded from different files.
+    ///
+    /// Symbols of `self` keep their IDs, while symbols of `other` missing in `self` are
+    /// appended to them. Symbols with the same name are the same symbol of the merged language.
+    /// Expressions of `other` are moved into the merged language with
+    /// [`Expression::remap_symbols`](expression::Expression::remap_symbols) and the returned
+    /// remapping of the IDs of `other`.
+    ///
+    /// Declarations of both languages are combined. A shared symbol is restricted to the union
+    /// of its arities only if both languages restrict it, and keeps the sort signature of `self`
+    /// if it has one in both. Operators and literal sorts of `self` win over those of `other`.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The language to merge into `self`
+    ///
+    /// # Returns
+    ///
+    /// Returns the merged language and the remapping of symbol IDs of `other` into it
+    pub fn merge(&self, other: &Language) -> (Language, SymbolIdRemap) {
+        let mut merged = self.clone();
+        let mut ids = Vec::with_capacity(other.symbols.len());
+        for name in &other.symbols {
+            let id = merged.try_get_id(name).unwrap_or_else(|| {
+                merged.symbols.push(name.clone());
+                merged.symbols.len() - 1
+            });
+            ids.push(id);
+        }
+        let remap = SymbolIdRemap::new(ids);
+        let is_shared = |id: SymbolId| remap.get(id) < self.symbols.len();
+
+        if let Some(other_arities) = &other.arities {
+            let arities = merged.arities.get_or_insert_default();
+            for (&id, allowed) in &other_arities.map {
+                if !is_shared(id) {
+                    arities.set(remap.get(id), allowed.clone());
+                } else if let Some(own) = arities.map.get_mut(&remap.get(id)) {
+                    own.extend(allowed);
+                    own.sort();
+                    own.dedup();
+                }
+            }
+            // Shared symbols `other` does not restrict accept any number of children
+            for id in (0..other.symbols.len()).filter(|&id| is_shared(id)) {
+                if other_arities.get(id).is_none() {
+                    arities.map.remove(&remap.get(id));
+                }
+            }
+        } else if let Some(arities) = &mut merged.arities {
+            for id in 0..other.symbols.len() {
+                arities.map.remove(&remap.get(id));
+            }
+        }
+
+        if let Some(other_sorts) = &other.sorts {
+            let sorts = merged.sorts.get_or_insert_default();
+            for (&id, signature) in &other_sorts.signatures {
+                sorts
+                    .signatures
+                    .entry(remap.get(id))
+                    .or_insert_with(|| signature.clone());
+            }
+            for (&kind, sort) in &other_sorts.literal_sorts {
+                sorts
+                    .literal_sorts
+                    .entry(kind)
+                    .or_insert_with(|| sort.clone());
+            }
+        }
+
+        merged.precedence_table = match (&self.precedence_table, &other.precedence_table) {
+            (Some(own), Some(other_table)) => Some(own.merge(other_table)),
+            (own, other_table) => own.clone().or_else(|| other_table.clone()),
+        };
+        merged
+            .ac_symbols
+            .extend(other.ac_symbols.iter().map(|&id| remap.get(id)));
+        merged
+            .flattened_symbols
+            .extend(other.flattened_symbols.iter().map(|&id| remap.get(id)));
+        merged
+            .constants
+            .extend(other.constants.iter().map(|&id| remap.get(id)));
+        merged
+            .binders
+            .extend(other.binders.iter().map(|&id| remap.get(id)));
+
+        (merged, remap)
+    }
 }
 
 #[cfg(test)]
@@ -140,4 +425,97 @@ mod tests {
 
         assert_eq!(lang.symbols, deserialized.symbols);
     }
+
+    #[test]
+    fn test_language_serialization_with_arities() {
+        let mut arities = crate::language::arities::Arities::new();
+        arities.set(0, vec![2]);
+        let lang = Language::simple_math().with_arities(arities);
+        let serialized = serde_json::to_string(&lang).unwrap();
+        let deserialized: Language = serde_json::from_str(&serialized).unwrap();
+
+        assert_eq!(lang, deserialized);
+    }
+
+    #[test]
+    fn merge() {
+        use super::{
+            arities::Arities,
+            sorts::{Sorts, SymbolSignature},
+        };
+
+        let mut own_arities = Arities::new();
+        own_arities.set(0, vec![2]);
+        own_arities.set(1, vec![1]);
+        let own = Language::default()
+            .add_symbol("+")
+            .add_symbol("neg")
+            .with_arities(own_arities)
+            .declare_ac("+");
+
+        let mut other_arities = Arities::new();
+        other_arities.set(0, vec![1]);
+        other_arities.set(1, vec![2]);
+        other_arities.set(2, vec![0]);
+        let other = Language::default()
+            .add_symbol("not")
+            .add_symbol("+")
+            .add_symbol("true")
+            .with_arities(other_arities)
+            .with_sorts(Sorts::new().with_signature(0, SymbolSignature::new(&["bool"], "bool")))
+            .declare_flattened("+")
+            .add_symbol("forall")
+            .declare_binder("forall");
+
+        let (merged, remap) = own.merge(&other);
+        assert_eq!(merged.symbol_count(), 5);
+        assert_eq!(
+            (0..4).map(|id| remap.get(id)).collect::<Vec<_>>(),
+            [2, 0, 3, 4]
+        );
+        assert!(!remap.is_identity());
+        assert!(merged.is_ac(0) && merged.is_flattened(0));
+        assert!(merged.is_binder(4) && !merged.is_binder(0));
+
+        let arities = merged.arities().unwrap();
+        assert_eq!(arities.get(0), Some(&[2][..]));
+        assert_eq!(arities.get(1), Some(&[1][..]));
+        assert_eq!(arities.get(2), Some(&[1][..]));
+        assert_eq!(
+            merged.sorts().unwrap().symbol_sort(2),
+            Some(&String::from("bool"))
+        );
+
+        let mut expression = other.parse("(not (+ (true) $0))").unwrap();
+        expression.remap_symbols(&remap);
+        assert_eq!(expression, merged.parse("(not (+ (true) $0))").unwrap());
+        let mut ground = other.parse_no_vars("(+ 1 (true))").unwrap();
+        ground.remap_symbols(&remap);
+        assert_eq!(ground, merged.parse_no_vars("(+ 1 (true))").unwrap());
+
+        let (same, identity) = own.merge(&own);
+        assert_eq!(same, own);
+        assert!(identity.is_identity());
+    }
+
+    #[test]
+    fn ac_symbols() {
+        let lang = Language::simple_math()
+            .declare_ac("+")
+            .declare_ac("*")
+            .declare_flattened("+");
+        assert!(lang.is_ac(lang.get_id("+")));
+        assert!(!lang.is_ac(lang.get_id("-")));
+        assert!(lang.is_flattened(lang.get_id("+")));
+        assert!(!lang.is_flattened(lang.get_id("*")));
+
+        let serialized = serde_json::to_string(&lang).unwrap();
+        let deserialized: Language = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(lang, deserialized);
+        assert!(
+            !serde_json::to_string(&Language::simple_math())
+                .unwrap()
+                .contains("ac_symbols")
+        );
+    }
 }
@@ -5,42 +5,202 @@
 
 use super::{
     Language,
-    expression::{Expression, Literal, VarFreeExpression},
-    symbol::Symbol,
+    expression::{Expression, Literal, VarFreeExpression, VariableId},
+    symbol::{Symbol, SymbolId},
+};
+use pest::{
+    Parser,
+    iterators::{Pair, Pairs},
 };
-use pest::{Parser, iterators::Pair};
 use pest_derive::Parser;
+use std::collections::BTreeSet;
+use std::fmt;
 
 #[derive(Parser)]
 #[grammar = "language/grammar.pest"]
-struct LanguageParser;
+pub(super) struct LanguageParser;
+
+/// Errors detected while building an expression from a syntactically correct string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// A symbol has a number of children not declared in the arities of the language
+    ArityMismatch {
+        /// Name of the symbol
+        symbol: String,
+        /// Declared arities of the symbol
+        expected: Vec<usize>,
+        /// Number of children found
+        got: usize,
+    },
+    /// A rational literal has zero as its denominator
+    ZeroDenominator,
+    /// Input could not be parsed at the given byte offset
+    UnexpectedInput {
+        /// Byte offset of the unexpected input
+        position: usize,
+    },
+    /// Input ended in the middle of an expression
+    UnexpectedEnd,
+    /// A symbol used by infix input does not exist in the language
+    UnknownSymbol(String),
+    /// A name written without parentheses is not a constant of the language
+    UnknownConstant(String),
+}
 
-impl Language {
-    /// Parses an expression from a parsed syntax tree node.
-    fn parse_expression(&self, pair: Pair<Rule>) -> Expression {
-        match pair.as_rule() {
-            Rule::standalone_expression | Rule::expression => {
-                self.parse_expression(pair.into_inner().next().unwrap())
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::ArityMismatch {
+                symbol,
+                expected,
+                got,
+            } => write!(
+                f,
+                "Symbol {symbol} expects {expected:?} children, but got {got}"
+            ),
+            ParseError::ZeroDenominator => write!(f, "Rational literal with zero denominator"),
+            ParseError::UnexpectedInput { position } => {
+                write!(f, "Unexpected input at position {position}")
             }
-            Rule::variable => {
-                Expression::Variable(pair.into_inner().next().unwrap().as_str().parse().unwrap())
+            ParseError::UnexpectedEnd => write!(f, "Unexpected end of input"),
+            ParseError::UnknownSymbol(name) => write!(f, "Unknown symbol {name}"),
+            ParseError::UnknownConstant(name) => write!(f, "Unknown constant {name}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Strips the quotes of a string literal and resolves its escape sequences.
+fn unescape(quoted: &str) -> String {
+    let mut string = String::new();
+    let mut characters = quoted[1..quoted.len() - 1].chars();
+    while let Some(character) = characters.next() {
+        if character == '\\' {
+            match characters.next() {
+                Some('n') => string.push('\n'),
+                Some(escaped) => string.push(escaped),
+                None => unreachable!(),
             }
-            Rule::symbol_call => {
-                let mut inner = pair.into_inner();
-                let id = self.get_id(inner.next().unwrap().as_str());
-                let children = inner.map(|e| self.parse_expression(e)).collect();
+        } else {
+            string.push(character);
+        }
+    }
+    string
+}
 
-                Expression::Symbol(Symbol { id, children })
+impl Language {
+    /// Parses an expression from a parsed syntax tree node.
+    pub(super) fn parse_expression(&self, pair: Pair<Rule>) -> Result<Expression, ParseError> {
+        Ok(match pair.as_rule() {
+            Rule::standalone_expression => self.parse_tokens(pair.into_inner())?,
+            Rule::variable | Rule::literal_variable => {
+                Expression::Variable(pair.into_inner().next().unwrap().as_str().parse().unwrap())
             }
-            Rule::literal => self.parse_expression(pair.into_inner().next().unwrap()),
+            Rule::literal => self.parse_expression(pair.into_inner().next().unwrap())?,
             Rule::integer => Expression::Literal(Literal::Int(pair.as_str().parse().unwrap())),
             Rule::unsigned_integer => Expression::Literal(Literal::UInt(
                 pair.as_str().strip_suffix("u").unwrap().parse().unwrap(),
             )),
-            Rule::symbol_char | Rule::WHITESPACE | Rule::EOI | Rule::symbol_name | Rule::number => {
-                unreachable!()
+            Rule::float => Expression::Literal(Literal::float(pair.as_str().parse().unwrap())),
+            Rule::rational => {
+                let (numerator, denominator) = pair.as_str().split_once('/').unwrap();
+                let literal =
+                    Literal::rational(numerator.parse().unwrap(), denominator.parse().unwrap())
+                        .ok_or(ParseError::ZeroDenominator)?;
+                Expression::Literal(literal)
+            }
+            Rule::string => Expression::Literal(Literal::Str(unescape(pair.as_str()))),
+            Rule::constant => {
+                let name = pair.as_str();
+                let id = self
+                    .try_get_id(name)
+                    .filter(|&id| self.is_constant(id))
+                    .ok_or_else(|| ParseError::UnknownConstant(name.to_owned()))?;
+                self.check_arity(id, 0)?;
+                Expression::Symbol(Symbol {
+                    id,
+                    children: Vec::new(),
+                })
+            }
+            Rule::open
+            | Rule::close
+            | Rule::token
+            | Rule::symbol_char
+            | Rule::WHITESPACE
+            | Rule::EOI
+            | Rule::symbol_name
+            | Rule::number
+            | Rule::escape => unreachable!(),
+        })
+    }
+
+    /// Builds an expression from a flat sequence of tokens.
+    ///
+    /// The grammar does not nest symbol calls, as both pest and a recursive descent over
+    /// its syntax tree would limit the depth of expressions. Unclosed symbol calls
+    /// are kept on an explicit stack instead.
+    fn parse_tokens(&self, tokens: Pairs<Rule>) -> Result<Expression, ParseError> {
+        // Unclosed symbol calls together with their children parsed so far
+        let mut open: Vec<(SymbolId, Vec<Expression>)> = Vec::new();
+        let mut result = None;
+
+        for token in tokens {
+            let rule = token.as_rule();
+            if rule == Rule::EOI {
+                break;
+            }
+            if result.is_some() {
+                return Err(ParseError::UnexpectedInput {
+                    position: token.as_span().start(),
+                });
+            }
+
+            let expression = match rule {
+                Rule::open => {
+                    let id = self.get_id(token.into_inner().next().unwrap().as_str());
+                    open.push((id, Vec::new()));
+                    continue;
+                }
+                Rule::close => {
+                    let Some((id, children)) = open.pop() else {
+                        return Err(ParseError::UnexpectedInput {
+                            position: token.as_span().start(),
+                        });
+                    };
+                    self.check_arity(id, children.len())?;
+                    Expression::Symbol(Symbol { id, children })
+                }
+                _ => self.parse_expression(token)?,
+            };
+
+            match open.last_mut() {
+                Some((_, children)) => children.push(expression),
+                None => result = Some(expression),
             }
         }
+
+        result.ok_or(ParseError::UnexpectedEnd)
+    }
+
+    /// Checks `child_count` against the declared arities of `id`, if there are any.
+    /// Constants accept no children.
+    pub(super) fn check_arity(&self, id: SymbolId, child_count: usize) -> Result<(), ParseError> {
+        if self.is_constant(id) && child_count != 0 {
+            return Err(ParseError::ArityMismatch {
+                symbol: self.get_symbol(id).to_owned(),
+                expected: vec![0],
+                got: child_count,
+            });
+        }
+        match self.arities().and_then(|arities| arities.get(id)) {
+            Some(expected) if !expected.contains(&child_count) => Err(ParseError::ArityMismatch {
+                symbol: self.get_symbol(id).to_owned(),
+                expected: expected.to_vec(),
+                got: child_count,
+            }),
+            _ => Ok(()),
+        }
     }
 
     /// Parses a string into an expression.
@@ -51,13 +211,43 @@ impl Language {
     ///
     /// # Returns
     ///
-    /// Returns an `Expression` on success, or an error if parsing fails
+    /// Returns an `Expression` on success, or an error if parsing fails.
+    /// If the language declares arities, a [`ParseError::ArityMismatch`] is returned
+    /// for symbols with a wrong number of children.
     pub fn parse(&self, string: &str) -> anyhow::Result<Expression> {
         let expr = LanguageParser::parse(Rule::standalone_expression, string)?
             .next()
             .unwrap();
 
-        Ok(self.parse_expression(expr))
+        Ok(self.parse_expression(expr)?)
+    }
+
+    /// Parses a pattern of a rule, in which variables may also be written as `#0`, `#1`, ...
+    /// to only match numeric literals, see [`Guard`](crate::rewriting::guard::Guard).
+    ///
+    /// # Arguments
+    ///
+    /// * `string` - The string representation of the pattern
+    ///
+    /// # Returns
+    ///
+    /// Returns the pattern, in which `#n` is the variable `$n`, together with the IDs
+    /// of variables written as `#n`, or an error if parsing fails
+    pub fn parse_pattern(
+        &self,
+        string: &str,
+    ) -> anyhow::Result<(Expression, BTreeSet<VariableId>)> {
+        let expr = LanguageParser::parse(Rule::standalone_expression, string)?
+            .next()
+            .unwrap();
+        let literal_variables = expr
+            .clone()
+            .into_inner()
+            .filter(|token| token.as_rule() == Rule::literal_variable)
+            .map(|token| token.into_inner().next().unwrap().as_str().parse().unwrap())
+            .collect();
+
+        Ok((self.parse_expression(expr)?, literal_variables))
     }
 
     /// Parses a string into a variable-free expression.
@@ -81,9 +271,11 @@ impl Language {
 
 #[cfg(test)]
 mod tests {
+    use super::ParseError;
     use crate::language::{
         Language,
-        expression::{Expression, Literal},
+        arities::Arities,
+        expression::{AnyExpression, Expression, Literal},
     };
 
     #[test]
@@ -186,4 +378,145 @@ mod tests {
             Expression::Literal(Literal::Int(128))
         ));
     }
+
+    fn lang_with_arities() -> Language {
+        let lang = Language::simple_math();
+        let mut arities = Arities::new();
+        arities.set(lang.get_id("+"), vec![2]);
+        arities.set(lang.get_id("-"), vec![1, 2]);
+        lang.with_arities(arities)
+    }
+
+    #[test]
+    fn parse_with_arities() {
+        let lang = lang_with_arities();
+
+        assert!(lang.parse("(+ $0 (- 1))").is_ok());
+        assert!(lang.parse_no_vars("(- (+ 1 2) 3)").is_ok());
+        // Symbols without declared arities accept any number of children
+        assert!(lang.parse("(* 1 2 3)").is_ok());
+    }
+
+    #[test]
+    fn parse_arity_mismatch() {
+        let lang = lang_with_arities();
+
+        let error = lang.parse("(- (+ 1 2 3))").unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<ParseError>(),
+            Some(&ParseError::ArityMismatch {
+                symbol: String::from("+"),
+                expected: vec![2],
+                got: 3,
+            })
+        );
+
+        assert!(lang.parse_no_vars("(- 1 2 3)").is_err());
+    }
+
+    #[test]
+    fn parse_floats_and_rationals() {
+        let lang = Language::simple_math();
+
+        let expr = lang.parse("(+ 1.5 -0.25 3/4 -6/8 2)").unwrap();
+        let children = expr.expect_symbol("+", &lang);
+
+        assert_eq!(children[0], Expression::Literal(Literal::float(1.5)));
+        assert_eq!(children[1], Expression::Literal(Literal::float(-0.25)));
+        assert_eq!(children[2], Expression::Literal(Literal::Rational(3, 4)));
+        assert_eq!(children[3], Expression::Literal(Literal::Rational(-3, 4)));
+        assert_eq!(children[4], Expression::Literal(Literal::Int(2)));
+
+        let error = lang.parse("1/0").unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<ParseError>(),
+            Some(&ParseError::ZeroDenominator)
+        );
+    }
+
+    #[test]
+    fn parse_strings() {
+        let lang = Language::simple_math();
+
+        let expr = lang.parse(r#"(+ "x" "" "a b" "say \"hi\"\\\n")"#).unwrap();
+        let children = expr.expect_symbol("+", &lang);
+
+        assert_eq!(children[0], Expression::Literal(Literal::string("x")));
+        assert_eq!(children[1], Expression::Literal(Literal::string("")));
+        assert_eq!(children[2], Expression::Literal(Literal::string("a b")));
+        assert_eq!(
+            children[3],
+            Expression::Literal(Literal::string("say \"hi\"\\\n"))
+        );
+
+        assert!(lang.parse(r#""unterminated"#).is_err());
+        assert!(lang.parse(r#""bad \t escape""#).is_err());
+    }
+
+    #[test]
+    fn display_round_trip() {
+        let lang = Language::simple_math();
+        let string = r#"(/ 1.0 (+ -3/4 2.5 7u "a \"b\""))"#;
+        let expr = lang.parse(string).unwrap();
+        assert_eq!(expr.with_language(&lang).to_string(), string);
+    }
+
+    #[test]
+    fn parse_constants() {
+        let lang = Language::simple_math().add_constant("pi").add_symbol("e");
+        let pi = lang.get_id("pi");
+
+        let expr = lang.parse("(* 2 pi $0)").unwrap();
+        let children = expr.expect_symbol("*", &lang);
+        assert_eq!(children[1], lang.parse("(pi)").unwrap());
+        assert_eq!(children[1].symbol_id(), Some(pi));
+        assert_ne!(children[1], lang.parse("2").unwrap());
+        assert_eq!(expr.with_language(&lang).to_string(), "(* 2 pi $0)");
+
+        let error = lang.parse("(+ e 1)").unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<ParseError>(),
+            Some(&ParseError::UnknownConstant(String::from("e")))
+        );
+        let error = lang.parse("(pi 1)").unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<ParseError>(),
+            Some(&ParseError::ArityMismatch {
+                symbol: String::from("pi"),
+                expected: vec![0],
+                got: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_deep_expression() {
+        let lang = Language::simple_math();
+        let depth = 10_000;
+        let string = format!("{}$0{}", "(sin ".repeat(depth), ")".repeat(depth));
+
+        let mut expr = &lang.parse(&string).unwrap();
+        for _ in 0..depth {
+            expr = &expr.expect_symbol("sin", &lang)[0];
+        }
+        assert_eq!(expr.expect_variable(), 0);
+    }
+
+    #[test]
+    fn parse_unbalanced_parentheses() {
+        let lang = Language::simple_math();
+
+        let error = lang.parse("(+ 1 (sin 2)").unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<ParseError>(),
+            Some(&ParseError::UnexpectedEnd)
+        );
+        let error = lang.parse("(+ 1 2))").unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<ParseError>(),
+            Some(&ParseError::UnexpectedInput { position: 7 })
+        );
+        assert!(lang.parse("1 2").is_err());
+        assert!(lang.parse("").is_err());
+    }
 }
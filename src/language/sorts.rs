@@ -0,0 +1,276 @@
+//! Sorts (types) of symbols for many-sorted languages.
+//!
+//! This module provides the [`Sorts`] table which assigns argument sorts and a result
+//! sort to symbols and sorts to literal kinds. Expressions whose children do not have
+//! the sorts expected by their parents are rejected. Symbols and literal kinds without
+//! a declared sort are untyped and compatible with every sort.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::expression::{Expression, LiteralKind, VariableId};
+use super::symbol::SymbolId;
+
+/// Name of a sort
+pub type Sort = String;
+
+/// Sorts of the arguments and of the result of a symbol.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SymbolSignature {
+    /// Sorts of the children, in order
+    pub arguments: Vec<Sort>,
+    /// Sort of the expression with the symbol at its root
+    pub result: Sort,
+}
+
+impl SymbolSignature {
+    /// Creates a new signature.
+    pub fn new(arguments: &[&str], result: &str) -> Self {
+        Self {
+            arguments: arguments.iter().map(|sort| String::from(*sort)).collect(),
+            result: String::from(result),
+        }
+    }
+}
+
+/// A sort error found while checking an expression.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SortError {
+    /// An expression of sort `found` is used where `expected` is required
+    Mismatch { expected: Sort, found: Sort },
+    /// A variable is used with two different sorts
+    VariableConflict {
+        variable: VariableId,
+        first: Sort,
+        second: Sort,
+    },
+    /// A sorted symbol has a different number of children than its signature declares
+    ArgumentCount {
+        symbol: SymbolId,
+        expected: usize,
+        got: usize,
+    },
+}
+
+impl fmt::Display for SortError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SortError::Mismatch { expected, found } => {
+                write!(
+                    f,
+                    "Expected an expression of sort {expected}, found {found}"
+                )
+            }
+            SortError::VariableConflict {
+                variable,
+                first,
+                second,
+            } => write!(
+                f,
+                "Variable {} used with sorts {first} and {second}",
+                Expression::variable_name(*variable)
+            ),
+            SortError::ArgumentCount {
+                symbol,
+                expected,
+                got,
+            } => write!(
+                f,
+                "Symbol with ID {symbol} expects {expected} children, but got {got}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SortError {}
+
+/// Sort declarations of a language.
+#[derive(Default, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sorts {
+    /// Signatures of sorted symbols
+    pub signatures: HashMap<SymbolId, SymbolSignature>,
+    /// Sorts of literals of a given kind
+    pub literal_sorts: HashMap<LiteralKind, Sort>,
+}
+
+/// Sorts assigned to variables while checking expressions.
+pub type VariableSorts = HashMap<VariableId, Sort>;
+
+impl Sorts {
+    /// Creates an empty sort table, in which everything is untyped.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declares the signature of `symbol`.
+    pub fn with_signature(mut self, symbol: SymbolId, signature: SymbolSignature) -> Self {
+        self.signatures.insert(symbol, signature);
+        self
+    }
+
+    /// Declares the sort of literals of kind `kind`.
+    pub fn with_literal_sort(mut self, kind: LiteralKind, sort: &str) -> Self {
+        self.literal_sorts.insert(kind, String::from(sort));
+        self
+    }
+
+    /// Returns the result sort of `symbol`, or `None` if it is untyped.
+    pub fn symbol_sort(&self, symbol: SymbolId) -> Option<&Sort> {
+        self.signatures
+            .get(&symbol)
+            .map(|signature| &signature.result)
+    }
+
+    /// Returns the sort of literals of kind `kind`, or `None` if they are untyped.
+    pub fn literal_sort(&self, kind: LiteralKind) -> Option<&Sort> {
+        self.literal_sorts.get(&kind)
+    }
+
+    /// Checks that `expression` is well-sorted.
+    ///
+    /// # Returns
+    ///
+    /// Returns the sort of `expression` (`None` if it cannot be determined) or the first sort error
+    pub fn check(&self, expression: &Expression) -> Result<Option<Sort>, SortError> {
+        self.check_with(expression, None, &mut VariableSorts::new())
+    }
+
+    /// Checks that `expression` is well-sorted and has sort `expected`, if given.
+    /// Sorts of variables are looked up in and added to `variables`, so that
+    /// several expressions sharing variables (e.g. sides of a rule) can be checked together.
+    pub fn check_with(
+        &self,
+        expression: &Expression,
+        expected: Option<&Sort>,
+        variables: &mut VariableSorts,
+    ) -> Result<Option<Sort>, SortError> {
+        let found = match expression {
+            Expression::Variable(variable) => {
+                if let Some(expected) = expected {
+                    match variables.get(variable) {
+                        Some(first) if first != expected => {
+                            return Err(SortError::VariableConflict {
+                                variable: *variable,
+                                first: first.clone(),
+                                second: expected.clone(),
+                            });
+                        }
+                        Some(_) => {}
+                        None => {
+                            variables.insert(*variable, expected.clone());
+                        }
+                    }
+                }
+
+                return Ok(variables.get(variable).cloned());
+            }
+            Expression::Literal(literal) => self.literal_sort(literal.kind()).cloned(),
+            Expression::Symbol(symbol) => match self.signatures.get(&symbol.id) {
+                Some(signature) => {
+                    if signature.arguments.len() != symbol.children.len() {
+                        return Err(SortError::ArgumentCount {
+                            symbol: symbol.id,
+                            expected: signature.arguments.len(),
+                            got: symbol.children.len(),
+                        });
+                    }
+
+                    for (child, sort) in symbol.children.iter().zip(&signature.arguments) {
+                        self.check_with(child, Some(sort), variables)?;
+                    }
+
+                    Some(signature.result.clone())
+                }
+                None => {
+                    for child in &symbol.children {
+                        self.check_with(child, None, variables)?;
+                    }
+
+                    None
+                }
+            },
+        };
+
+        if let (Some(expected), Some(found)) = (expected, &found)
+            && expected != found
+        {
+            return Err(SortError::Mismatch {
+                expected: expected.clone(),
+                found: found.clone(),
+            });
+        }
+
+        Ok(found)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SortError, Sorts, SymbolSignature};
+    use crate::language::{Language, expression::LiteralKind};
+
+    fn lang() -> Language {
+        let lang = Language::default()
+            .add_symbol("+")
+            .add_symbol("<")
+            .add_symbol("and")
+            .add_symbol("if");
+        let sorts = Sorts::new()
+            .with_signature(
+                lang.get_id("+"),
+                SymbolSignature::new(&["int", "int"], "int"),
+            )
+            .with_signature(
+                lang.get_id("<"),
+                SymbolSignature::new(&["int", "int"], "bool"),
+            )
+            .with_signature(
+                lang.get_id("and"),
+                SymbolSignature::new(&["bool", "bool"], "bool"),
+            )
+            .with_literal_sort(LiteralKind::Int, "int");
+        lang.with_sorts(sorts)
+    }
+
+    #[test]
+    fn well_sorted() {
+        let lang = lang();
+        let sorts = lang.sorts().unwrap();
+
+        let expression = lang.parse("(and (< $0 1) (< 2 (+ $0 $1)))").unwrap();
+        assert_eq!(sorts.check(&expression), Ok(Some(String::from("bool"))));
+
+        // Untyped symbols accept and produce anything
+        let expression = lang.parse("(if (< 1 2) 3 (and $0 $1))").unwrap();
+        assert_eq!(sorts.check(&expression), Ok(None));
+    }
+
+    #[test]
+    fn ill_sorted() {
+        let lang = lang();
+        let sorts = lang.sorts().unwrap();
+
+        let expression = lang.parse("(+ 1 (< 2 3))").unwrap();
+        assert_eq!(
+            sorts.check(&expression),
+            Err(SortError::Mismatch {
+                expected: String::from("int"),
+                found: String::from("bool"),
+            })
+        );
+
+        let expression = lang.parse("(and $0 (< $0 1))").unwrap();
+        assert!(matches!(
+            sorts.check(&expression),
+            Err(SortError::VariableConflict { variable: 0, .. })
+        ));
+
+        let expression = lang.parse("(+ 1)").unwrap();
+        assert!(matches!(
+            sorts.check(&expression),
+            Err(SortError::ArgumentCount { .. })
+        ));
+    }
+}
@@ -11,6 +11,34 @@ use serde::{Deserialize, Serialize};
 
 pub type SymbolId = usize;
 
+/// Mapping of the symbol IDs of one language to the IDs of the same symbols in another one,
+/// e.g. in the result of [`Language::merge`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SymbolIdRemap {
+    ids: Vec<SymbolId>,
+}
+
+impl SymbolIdRemap {
+    /// Creates a remapping sending the symbol with ID `i` to `ids[i]`.
+    pub fn new(ids: Vec<SymbolId>) -> Self {
+        Self { ids }
+    }
+
+    /// Returns the new ID of the symbol with ID `id`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` is not a symbol of the remapped language
+    pub fn get(&self, id: SymbolId) -> SymbolId {
+        self.ids[id]
+    }
+
+    /// `true` if every symbol keeps its ID.
+    pub fn is_identity(&self) -> bool {
+        self.ids.iter().enumerate().all(|(old, &new)| old == new)
+    }
+}
+
 /// A symbol with `id` as its ID and children of type `E`.
 ///
 /// Represents a function symbol or operator in an expression tree.
@@ -28,6 +56,11 @@ pub struct Symbol<E> {
 }
 
 impl<E> Symbol<E> {
+    /// Replaces the ID of this symbol, but not of its children, according to `remap`.
+    pub fn remap_id(&mut self, remap: &SymbolIdRemap) {
+        self.id = remap.get(self.id);
+    }
+
     /// Checks if `self` and `other` have the same shape,
     /// i.e. the same symbol id and the same number of children.
     pub fn same_shape_as<EO>(&self, other: &Symbol<EO>) -> bool {
@@ -60,6 +93,9 @@ where
         f: &mut std::fmt::Formatter<'_>,
         language: &'l Language,
     ) -> std::fmt::Result {
+        if self.children.is_empty() && language.is_constant(self.id) {
+            return write!(f, "{}", language.get_symbol(self.id));
+        }
         write!(f, "({}", language.get_symbol(self.id))?;
         for child in &self.children {
             write!(f, " {}", child.with_language(language))?;
@@ -1,3 +1,15 @@
+//! Term rewriting with e-graphs.
+//!
+//! The library is organized as follows:
+//! - [`language`]: Languages, expressions and their parsing
+//! - [`rewriting`]: Rules, term rewriting systems, e-graphs, equality saturation and reachability
+//! - [`benchmark`]: Benchmarks of saturation and reachability, and random expression generation
+//! - [`graph`]: Directed graphs used by the analyses
+//! - [`utils`]: JSON and SMT-LIB helpers and seedable random number generation
+//! - [`metrics`]: Counters of the work done by e-graphs and ILP solvers, behind the `metrics` feature
+//!
+//! The binaries in `src/bin` and the examples use only this public API.
+
 pub mod benchmark;
 pub mod compact;
 pub mod data_union_find;
@@ -7,6 +19,7 @@ pub mod graph;
 pub mod index_selector;
 pub mod language;
 pub mod macros;
+pub mod metrics;
 pub mod rewriting;
 pub mod seen;
 pub mod union_find;
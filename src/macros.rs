@@ -11,6 +11,8 @@
 ///
 /// - `rules!(lang; "pattern" => "replacement", ...)` - Creates unidirectional rules
 /// - `rules!(lang; "pattern" <=> "replacement", ...)` - Creates bidirectional rules
+/// - `rules!(lang; "name": "pattern" => "replacement", ...)` - Creates named rules,
+///   also with `<=>`, in which case the reversed rule is named `name-rev`
 ///
 /// # Examples
 ///
@@ -20,7 +22,7 @@
 /// let lang = Language::simple_math();
 /// let rules = rules!(lang;
 ///     "(* $0 1)" => "$0",
-///     "(+ $0 0)" => "$0"
+///     "comm-add": "(+ $0 $1)" <=> "(+ $1 $0)"
 /// );
 /// ```
 #[macro_export]
@@ -28,20 +30,37 @@ macro_rules! rules {
     ($lang:expr; ) => {
         Vec::<$crate::rewriting::rule::Rule>::new()
     };
-    ($lang:expr; $from:expr => $to:expr $(, $($rest:tt)*)? ) => {{
+    ($lang:expr; $name:literal : $from:tt <=> $to:tt $(, $($rest:tt)*)? ) => {{
+        let rule = $crate::rewriting::rule::Rule::from_strings($from, $to, &$lang).with_name($name);
+        let reversed = rule.reversed();
         #[allow(unused_mut)]
-        let mut v = vec![$crate::rewriting::rule::Rule::from_strings($from, $to, &$lang)];
+        let mut v = vec![rule, reversed];
+        $( v.extend($crate::macros::rules!($lang; $($rest)*)); )?
+        v
+    }};
+    ($lang:expr; $name:literal : $from:tt => $to:tt $(, $($rest:tt)*)? ) => {{
+        #[allow(unused_mut)]
+        let mut v = vec![
+            $crate::rewriting::rule::Rule::from_strings($from, $to, &$lang).with_name($name)
+        ];
         $( v.extend($crate::macros::rules!($lang; $($rest)*)); )?
         v
     }};
     ($lang:expr; $from:tt <=> $to:tt $(, $($rest:tt)*)? ) => {{
+        #[allow(unused_mut)]
         let mut v = vec![
-            $crate::rewriting::rule::Rule::from_strings($from, $to, &$lang)
-            $crate::rewriting::rule::Rule::from_strings($to, $from, &$lang)
+            $crate::rewriting::rule::Rule::from_strings($from, $to, &$lang),
+            $crate::rewriting::rule::Rule::from_strings($to, $from, &$lang),
         ];
         $( v.extend($crate::macros::rules!($lang; $($rest)*)); )?
         v
     }};
+    ($lang:expr; $from:expr => $to:expr $(, $($rest:tt)*)? ) => {{
+        #[allow(unused_mut)]
+        let mut v = vec![$crate::rewriting::rule::Rule::from_strings($from, $to, &$lang)];
+        $( v.extend($crate::macros::rules!($lang; $($rest)*)); )?
+        v
+    }};
 }
 
 /// Creates a complete term rewriting system with symbols and rules.
@@ -1,3 +1,658 @@
-fn main() {
-    println!("This is a clean main function.");
+//! Command line interface of verbum.
+//!
+//! Every subcommand loads a term rewriting system from a directory with `language.json`
+//! and `trs.json` (see [`TermRewritingSystem::from_directory`]) and runs one of the analyses
+//! of the library on it. Saturation limits are given as flags or in a JSON file
+//! with a [`SaturationConfig`], in which case the flags override the file.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Duration;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use verbum::benchmark::{
+    self, BenchmarkConfig, Corpus, SamplingConfig,
+    campaign::{Campaign, MatcherKind, OutputFormat, SaturatorKind},
+};
+use verbum::language::arities::Arities;
+use verbum::language::expression::{AnyExpression, VarFreeExpression, load_expressions_from_file};
+use verbum::metrics::{self, EGraphMetrics};
+use verbum::rewriting::egraph::{
+    DynEGraph, EGraph,
+    class::{size_local_cost::SizeLocalCost, table_local_cost::TableLocalCost},
+    extraction::{Extractor, TableExtractor},
+    matching::Matcher,
+    saturation::{SaturationConfig, Saturator, SimpleSaturator, scheduler::GoalDirectedScheduler},
+};
+use verbum::rewriting::heuristic::AbelianPathHeuristic;
+use verbum::rewriting::ilp::{IlpBackend, IlpConfig, TimeoutPolicy};
+use verbum::rewriting::reachability::{
+    ReachabilityResult, terms_reachable, terms_reachable_round_robin,
+};
+use verbum::rewriting::system::TermRewritingSystem;
+use verbum::utils::json::load_json;
+
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
+/// CLI arguments of verbum
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Term rewriting with e-graphs", long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Saturate e-graphs of expressions and extract their cheapest equivalents
+    Saturate {
+        #[command(flatten)]
+        input: Input,
+
+        /// Saturator to use: simple, directed, round_robin or fair_class(N)
+        #[arg(short = 's', long, default_value_t = SaturatorKind::Simple)]
+        saturator: SaturatorKind,
+
+        #[command(flatten)]
+        saturation: SaturationArgs,
+    },
+    /// Benchmark saturators on expressions
+    Benchmark {
+        #[command(flatten)]
+        input: Input,
+
+        /// Saturators to compare: simple, directed, round_robin or fair_class(N)
+        #[arg(short = 's', long, num_args = 1.., default_values_t = [SaturatorKind::Simple, SaturatorKind::Directed])]
+        saturators: Vec<SaturatorKind>,
+
+        /// Number of sampled runs of every saturation
+        #[arg(short = 'r', long, default_value_t = 1)]
+        runs: usize,
+
+        /// Format of the results
+        #[arg(short = 'f', long, value_enum, default_value_t = OutputFormat::Pretty)]
+        format: OutputFormat,
+
+        /// File to save the results to instead of printing them
+        #[arg(short = 'o', long)]
+        output: Option<PathBuf>,
+
+        /// Compare saturating all expressions in a single e-graph with saturating them
+        /// one at a time, instead of reporting every expression
+        #[arg(long)]
+        batch: bool,
+
+        /// Check that the simple and the directed saturator saturate every expression
+        /// to the same e-graph instead of benchmarking, which needs unlimited budgets
+        #[arg(long, conflicts_with = "batch")]
+        cross_check: bool,
+
+        #[command(flatten)]
+        saturation: SaturationArgs,
+    },
+    /// Run a benchmark campaign described by a JSON file, see `verbum::benchmark::campaign`
+    Campaign {
+        /// Campaign file
+        file: PathBuf,
+    },
+    /// Check whether two expressions can be rewritten into a common form
+    Reachability {
+        #[command(flatten)]
+        pair: Pair,
+
+        #[command(flatten)]
+        saturation: SaturationArgs,
+    },
+    /// Rewrite an expression towards another one best-first, guided by the abelianized path
+    /// heuristic, which needs `arities.json` in the system directory
+    Astar {
+        #[command(flatten)]
+        pair: Pair,
+
+        #[command(flatten)]
+        saturation: SaturationArgs,
+
+        /// Maximum time to solve a single ILP of the heuristic, in seconds
+        #[arg(long)]
+        ilp_time_limit: Option<f64>,
+
+        /// Treat ILPs exceeding the time limit as infeasible
+        /// instead of bounding them by their linear relaxations
+        #[arg(long)]
+        ilp_no_bound: bool,
+
+        /// Solver of the ILPs of the heuristic
+        #[arg(long, value_enum, default_value_t = IlpBackendKind::Default)]
+        ilp_backend: IlpBackendKind,
+    },
+    /// Print statistics of a corpus of expressions, optionally deduplicating it
+    /// and splitting it into training and evaluation subsets
+    Corpus {
+        #[command(flatten)]
+        input: Input,
+
+        /// Remove expressions equal to earlier ones up to renaming of variables
+        #[arg(long)]
+        dedup: bool,
+
+        /// Fraction of the expressions to put in the evaluation subset
+        #[arg(long, requires_all = ["train", "eval"])]
+        split: Option<f64>,
+
+        /// Seed of the split
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+
+        /// File to save the training subset to
+        #[arg(long)]
+        train: Option<PathBuf>,
+
+        /// File to save the evaluation subset to
+        #[arg(long)]
+        eval: Option<PathBuf>,
+    },
+    /// Saturate the e-graph of an expression and save it in DOT format
+    Dot {
+        /// Path to directory containing TRS JSON files
+        #[arg(short = 't', long)]
+        trs: PathBuf,
+
+        /// Expression to saturate
+        #[arg(short = 'e', long)]
+        expr: String,
+
+        /// File to save the e-graph to
+        #[arg(short = 'o', long)]
+        output: PathBuf,
+
+        /// Save the e-graph in GraphML format instead
+        #[arg(long)]
+        graphml: bool,
+
+        #[command(flatten)]
+        saturation: SaturationArgs,
+    },
+}
+
+/// A rewriting system and expressions to process
+#[derive(Args, Debug)]
+struct Input {
+    /// Path to directory containing TRS JSON files
+    #[arg(short = 't', long)]
+    trs: PathBuf,
+
+    /// JSON file with a list of `expressions`
+    #[arg(short = 'e', long)]
+    expr: PathBuf,
+}
+
+/// A rewriting system with a source and a goal expression
+#[derive(Args, Debug)]
+struct Pair {
+    /// Path to directory containing TRS JSON files
+    #[arg(short = 't', long)]
+    trs: PathBuf,
+
+    /// Source expression
+    #[arg(long)]
+    from: String,
+
+    /// Goal expression
+    #[arg(long)]
+    to: String,
+}
+
+#[derive(Args, Debug)]
+struct SaturationArgs {
+    /// JSON file with a saturation configuration, overridden by the flags
+    #[arg(short = 'c', long)]
+    config: Option<PathBuf>,
+
+    /// Maximum number of nodes in the e-graph
+    #[arg(long)]
+    max_nodes: Option<usize>,
+
+    /// Maximum number of classes in the e-graph
+    #[arg(long)]
+    max_classes: Option<usize>,
+
+    /// Maximum number of rule applications
+    #[arg(long)]
+    max_applications: Option<usize>,
+
+    /// Maximum number of iterations
+    #[arg(long)]
+    max_iterations: Option<usize>,
+
+    /// Maximum number of rule applications in a single iteration
+    #[arg(long)]
+    max_applications_per_iteration: Option<usize>,
+
+    /// Maximum time to spend saturating, in seconds
+    #[arg(long)]
+    time_limit: Option<f64>,
+
+    /// Maximum depth of terms created by rule applications
+    #[arg(long)]
+    max_term_depth: Option<usize>,
+
+    /// Maximum size of terms created by rule applications
+    #[arg(long)]
+    max_term_size: Option<usize>,
+
+    /// Remove rules which cannot help reaching the goal before saturating
+    #[arg(long)]
+    preprocess: bool,
+
+    /// Matching algorithm
+    #[arg(short = 'm', long, value_enum, default_value_t = MatcherKind::BottomUp)]
+    matcher: MatcherKind,
+}
+
+impl SaturationArgs {
+    fn config(&self) -> CliResult<SaturationConfig> {
+        let mut config: SaturationConfig = match &self.config {
+            Some(path) => load_json(path)?,
+            None => SaturationConfig::default(),
+        };
+
+        config.max_nodes = self.max_nodes.or(config.max_nodes);
+        config.max_classes = self.max_classes.or(config.max_classes);
+        config.max_applications = self.max_applications.or(config.max_applications);
+        config.max_iterations = self.max_iterations.or(config.max_iterations);
+        config.max_applications_per_iteration = self
+            .max_applications_per_iteration
+            .or(config.max_applications_per_iteration);
+        config.time_limit = self
+            .time_limit
+            .map(Duration::from_secs_f64)
+            .or(config.time_limit);
+        config.max_term_depth = self.max_term_depth.or(config.max_term_depth);
+        config.max_term_size = self.max_term_size.or(config.max_term_size);
+        config.preprocess |= self.preprocess;
+
+        Ok(config)
+    }
+
+    fn matcher(&self) -> Box<dyn Matcher> {
+        self.matcher.matcher()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum IlpBackendKind {
+    /// The default solver of `good_lp`
+    Default,
+    #[cfg(feature = "cbc")]
+    Cbc,
+    #[cfg(feature = "microlp")]
+    Microlp,
+}
+
+impl IlpBackendKind {
+    fn backend(self) -> IlpBackend {
+        match self {
+            IlpBackendKind::Default => IlpBackend::Default,
+            #[cfg(feature = "cbc")]
+            IlpBackendKind::Cbc => IlpBackend::Cbc,
+            #[cfg(feature = "microlp")]
+            IlpBackendKind::Microlp => IlpBackend::MicroLp,
+        }
+    }
+}
+
+fn main() -> CliResult<()> {
+    match Cli::parse().command {
+        Command::Saturate {
+            input,
+            saturator,
+            saturation,
+        } => saturate(&input, saturator, &saturation),
+        Command::Benchmark {
+            input,
+            cross_check: true,
+            saturation,
+            ..
+        } => cross_check(&input, &saturation),
+        Command::Benchmark {
+            input,
+            saturators,
+            runs,
+            format,
+            output,
+            batch,
+            saturation,
+            ..
+        } => run_benchmark(
+            &input,
+            &saturators,
+            runs,
+            format,
+            output.as_deref(),
+            batch,
+            &saturation,
+        ),
+        Command::Campaign { file } => {
+            let campaign = Campaign::load(file)?;
+            campaign.write_outputs(&campaign.run()?, &mut std::io::stdout())
+        }
+        Command::Reachability { pair, saturation } => reachability(&pair, &saturation),
+        Command::Astar {
+            pair,
+            saturation,
+            ilp_time_limit,
+            ilp_no_bound,
+            ilp_backend,
+        } => {
+            let ilp_config = IlpConfig {
+                backend: ilp_backend.backend(),
+                time_limit: ilp_time_limit.map(Duration::from_secs_f64),
+                on_timeout: if ilp_no_bound {
+                    TimeoutPolicy::Infinite
+                } else {
+                    TimeoutPolicy::BestBound
+                },
+                ..Default::default()
+            };
+            astar(&pair, &saturation, ilp_config)
+        }
+        Command::Corpus {
+            input,
+            dedup,
+            split,
+            seed,
+            train,
+            eval,
+        } => corpus(
+            &input,
+            dedup,
+            split,
+            seed,
+            train.as_deref(),
+            eval.as_deref(),
+        ),
+        Command::Dot {
+            trs,
+            expr,
+            output,
+            graphml,
+            saturation,
+        } => dot(&trs, &expr, &output, graphml, &saturation),
+    }
+}
+
+/// Loads the system from `dir_path`, printing its warnings to the standard error output.
+fn load_trs(dir_path: &Path) -> CliResult<TermRewritingSystem> {
+    let (trs, diagnostics) = TermRewritingSystem::from_directory_with_diagnostics(dir_path)?;
+    for diagnostic in diagnostics {
+        eprintln!("Warning: {diagnostic}");
+    }
+    Ok(trs)
+}
+
+/// Extractor using the costs of `trs`, or the sizes of expressions if it has none.
+fn extractor(trs: &TermRewritingSystem) -> TableExtractor {
+    TableExtractor::new(&trs.costs_or_sizes(), trs.language())
+}
+
+fn saturate(input: &Input, saturator: SaturatorKind, saturation: &SaturationArgs) -> CliResult<()> {
+    let trs = load_trs(&input.trs)?;
+    let lang = trs.language();
+    let expressions = load_expressions_from_file(&input.expr, lang)?;
+    let config = saturation.config()?;
+    let saturator = saturator.saturator::<TableLocalCost>(saturation.matcher);
+    let extractor = extractor(&trs);
+
+    for expression in expressions {
+        println!("{}", expression.with_language(lang));
+        let mut egraph = trs.egraph::<TableLocalCost>();
+        let class_id = egraph.add_expression(expression);
+        let report = saturator.saturate(&mut egraph, trs.rules(), &config);
+        println!(
+            "  stopped: {:?} after {} iterations and {} applications, {} nodes in {} classes",
+            report.stop_reason,
+            report.iterations,
+            report.applications,
+            report.node_count,
+            report.class_count,
+        );
+        if let Some(result) = extractor.extract(&egraph, class_id) {
+            println!(
+                "  cheapest: {} (cost {})",
+                result.winner().with_language(lang),
+                result.cost()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn run_benchmark(
+    input: &Input,
+    saturators: &[SaturatorKind],
+    runs: usize,
+    format: OutputFormat,
+    output: Option<&Path>,
+    batch: bool,
+    saturation: &SaturationArgs,
+) -> CliResult<()> {
+    let trs = load_trs(&input.trs)?;
+    let expressions = load_expressions_from_file(&input.expr, trs.language())?;
+    let config = BenchmarkConfig {
+        saturation_config: saturation.config()?,
+        sampling: SamplingConfig {
+            runs,
+            ..Default::default()
+        },
+    };
+    let extractor = extractor(&trs);
+    let formatter = format.formatter(&config)?;
+
+    let formatted = if batch {
+        let saturators = saturators
+            .iter()
+            .map(|&kind| {
+                (
+                    kind.name(),
+                    kind.saturator::<TableLocalCost>(saturation.matcher),
+                )
+            })
+            .collect();
+        let outcomes =
+            benchmark::benchmark_batch(&trs, &expressions, &config, &extractor, saturators)?;
+        formatter.format_batch_outcomes(&outcomes)
+    } else {
+        let outcomes: BTreeMap<_, _> = saturators
+            .iter()
+            .map(|&kind| {
+                let saturator = kind.saturator::<TableLocalCost>(saturation.matcher);
+                let outcomes =
+                    benchmark::benchmark(&trs, &expressions, &config, &extractor, &*saturator)?;
+                Ok((kind.name(), outcomes))
+            })
+            .collect::<CliResult<_>>()?;
+        if metrics::ENABLED {
+            for (name, outcomes) in &outcomes {
+                let total = EGraphMetrics::default();
+                outcomes
+                    .iter()
+                    .for_each(|outcome| total.absorb(&outcome.metrics));
+                eprintln!("Metrics of {name}:\n{total}");
+            }
+        }
+        formatter.format_saturator_outcomes(outcomes)
+    };
+    if metrics::ENABLED {
+        eprintln!("ILP solves: {}", metrics::ILP_SOLVES);
+    }
+
+    match output {
+        Some(path) => std::fs::write(path, formatted)?,
+        None => println!("{formatted}"),
+    }
+
+    Ok(())
+}
+
+/// Saturates every expression with both saturators, reporting whether they reach the same
+/// e-graph, and fails if any expression diverged.
+fn cross_check(input: &Input, saturation: &SaturationArgs) -> CliResult<()> {
+    let trs = load_trs(&input.trs)?;
+    let lang = trs.language();
+    let expressions = load_expressions_from_file(&input.expr, lang)?;
+    let outcomes =
+        benchmark::cross_check::<TableLocalCost>(&trs, &expressions, &saturation.config()?, || {
+            saturation.matcher()
+        });
+
+    let mut diverged = 0;
+    for outcome in &outcomes {
+        println!("{}", outcome.expression.with_language(lang));
+        for (name, report) in [("simple", &outcome.simple), ("directed", &outcome.directed)] {
+            println!(
+                "  {name}: {:?}, {} nodes in {} classes",
+                report.stop_reason, report.node_count, report.class_count
+            );
+        }
+        if !outcome.is_conclusive() {
+            println!("  inconclusive: a saturation did not reach a fixed point");
+        } else if let Some(divergence) = &outcome.divergence {
+            diverged += 1;
+            println!(
+                "  diverged: application {} of {}, {}, derived an equivalence missing from the other",
+                divergence.application, divergence.saturator, divergence.rule
+            );
+        } else if !outcome.is_consistent() {
+            diverged += 1;
+            println!("  diverged: same equivalences, but different numbers of nodes or classes");
+        } else {
+            println!("  consistent");
+        }
+    }
+
+    if diverged > 0 {
+        return Err(format!("{diverged} of {} expressions diverged", outcomes.len()).into());
+    }
+    Ok(())
+}
+
+/// Loads the system of `pair` and parses its expressions.
+fn load_pair(
+    pair: &Pair,
+) -> CliResult<(TermRewritingSystem, VarFreeExpression, VarFreeExpression)> {
+    let trs = load_trs(&pair.trs)?;
+    let from = trs.language().parse_no_vars(&pair.from)?;
+    let to = trs.language().parse_no_vars(&pair.to)?;
+    Ok((trs, from, to))
+}
+
+fn print_reachability<A: verbum::rewriting::egraph::Analysis>(result: &ReachabilityResult<A>) {
+    println!("Result: {:?}", result.reason);
+    println!("Applications: {}", result.applications);
+    println!("Pruned rules: {}", result.pruned_rules);
+    println!("Duration: {:?}", result.duration);
+}
+
+fn reachability(pair: &Pair, saturation: &SaturationArgs) -> CliResult<()> {
+    let (trs, from, to) = load_pair(pair)?;
+    let result: ReachabilityResult<()> = terms_reachable_round_robin(
+        trs.rules(),
+        from,
+        to,
+        &saturation.config()?,
+        &*saturation.matcher(),
+    );
+    print_reachability(&result);
+    Ok(())
+}
+
+fn astar(pair: &Pair, saturation: &SaturationArgs, ilp_config: IlpConfig) -> CliResult<()> {
+    let (trs, from, to) = load_pair(pair)?;
+    let arities: Arities = load_json(pair.trs.join("arities.json"))?;
+    let heuristic = Rc::new(
+        AbelianPathHeuristic::new(&to.to_expression(), &trs, &arities).with_ilp_config(ilp_config),
+    );
+
+    let result = terms_reachable(
+        trs.rules(),
+        from,
+        to,
+        &saturation.config()?,
+        &*saturation.matcher(),
+        |rules| {
+            Box::new(GoalDirectedScheduler::<SizeLocalCost>::new(
+                rules.to_vec(),
+                heuristic.clone(),
+            ))
+        },
+    );
+    print_reachability(&result);
+
+    let statistics = heuristic.ilp_statistics();
+    println!(
+        "ILPs: {} optimal, {} infeasible, {} timed out in {:?}",
+        statistics.optimal, statistics.infeasible, statistics.timed_out, statistics.total_time
+    );
+    Ok(())
+}
+
+fn corpus(
+    input: &Input,
+    dedup: bool,
+    split: Option<f64>,
+    seed: u64,
+    train: Option<&Path>,
+    eval: Option<&Path>,
+) -> CliResult<()> {
+    let trs = load_trs(&input.trs)?;
+    let lang = trs.language();
+    let mut corpus = Corpus::load(&input.expr, lang)?;
+    if dedup {
+        println!("Removed {} duplicates", corpus.deduplicate());
+    }
+    println!("{}", corpus.statistics(lang));
+
+    if let Some(fraction) = split
+        && let (Some(train), Some(eval)) = (train, eval)
+    {
+        let (train_corpus, eval_corpus) = corpus.split(fraction, seed);
+        train_corpus.save(train, lang)?;
+        eval_corpus.save(eval, lang)?;
+        println!(
+            "Saved {} training and {} evaluation expressions",
+            train_corpus.len(),
+            eval_corpus.len()
+        );
+    }
+
+    Ok(())
+}
+
+fn dot(
+    trs: &Path,
+    expr: &str,
+    output: &Path,
+    graphml: bool,
+    saturation: &SaturationArgs,
+) -> CliResult<()> {
+    let trs = load_trs(trs)?;
+    let lang = trs.language();
+    let mut egraph = EGraph::<()>::from_expression(lang.parse_no_vars(expr)?);
+    SimpleSaturator::new(saturation.matcher()).saturate(
+        &mut egraph,
+        trs.rules(),
+        &saturation.config()?,
+    );
+
+    if graphml {
+        egraph.save_graphml(lang, output)?;
+    } else {
+        egraph.save_dot(lang, output)?;
+    }
+    println!("Saved e-graph to {output:?}");
+
+    Ok(())
 }
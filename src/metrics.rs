@@ -0,0 +1,241 @@
+//! Counters and timers of the work done by e-graphs and ILP solvers.
+//!
+//! Every [`EGraph`](crate::rewriting::egraph::EGraph) keeps [`EGraphMetrics`], see
+//! [`DynEGraph::metrics`](crate::rewriting::egraph::DynEGraph::metrics), and solves of ILP
+//! problems are counted in [`ILP_SOLVES`]. Values are only recorded with the `metrics` feature
+//! enabled, otherwise recording compiles to nothing and all values stay zero, so that
+//! the instrumented hot paths cost nothing in regular builds.
+
+use std::{
+    fmt,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use serde::{Serialize, Serializer, ser::SerializeStruct};
+
+/// `true` if the crate is built with the `metrics` feature and values are recorded.
+pub const ENABLED: bool = cfg!(feature = "metrics");
+
+/// Solves of ILP problems of all heuristics, including relaxations.
+pub static ILP_SOLVES: Timer = Timer::new();
+
+/// A counter of events, which can be incremented through shared references.
+#[derive(Debug, Default)]
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    /// Creates a counter starting at zero.
+    pub const fn new() -> Self {
+        Self(AtomicU64::new(0))
+    }
+
+    /// Counts a single event.
+    #[inline]
+    pub fn increment(&self) {
+        self.add(1);
+    }
+
+    /// Counts `count` events.
+    #[inline]
+    pub fn add(&self, count: u64) {
+        if ENABLED {
+            self.0.fetch_add(count, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the number of counted events.
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Sets the counter back to zero.
+    pub fn reset(&self) {
+        self.0.store(0, Ordering::Relaxed);
+    }
+}
+
+impl Clone for Counter {
+    fn clone(&self) -> Self {
+        Self(AtomicU64::new(self.get()))
+    }
+}
+
+impl Serialize for Counter {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.get())
+    }
+}
+
+/// A counter of events together with the total time they took.
+#[derive(Clone, Debug, Default)]
+pub struct Timer {
+    count: Counter,
+    nanos: Counter,
+}
+
+impl Timer {
+    /// Creates a timer without events.
+    pub const fn new() -> Self {
+        Self {
+            count: Counter::new(),
+            nanos: Counter::new(),
+        }
+    }
+
+    /// Runs `f`, recording it as an event with its duration.
+    #[inline]
+    pub fn time<T>(&self, f: impl FnOnce() -> T) -> T {
+        if !ENABLED {
+            return f();
+        }
+        let start = Instant::now();
+        let result = f();
+        self.record(start.elapsed());
+        result
+    }
+
+    /// Records an event which took `duration`.
+    #[inline]
+    pub fn record(&self, duration: Duration) {
+        self.count.increment();
+        self.nanos.add(duration.as_nanos() as u64);
+    }
+
+    /// Returns the number of recorded events.
+    pub fn count(&self) -> u64 {
+        self.count.get()
+    }
+
+    /// Returns the total time of the recorded events.
+    pub fn total(&self) -> Duration {
+        Duration::from_nanos(self.nanos.get())
+    }
+
+    /// Adds the events of `other` to this timer.
+    pub fn absorb(&self, other: &Timer) {
+        self.count.add(other.count.get());
+        self.nanos.add(other.nanos.get());
+    }
+
+    /// Forgets all recorded events.
+    pub fn reset(&self) {
+        self.count.reset();
+        self.nanos.reset();
+    }
+}
+
+impl Serialize for Timer {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Timer", 2)?;
+        state.serialize_field("count", &self.count())?;
+        state.serialize_field("time_ns", &self.nanos.get())?;
+        state.end()
+    }
+}
+
+impl fmt::Display for Timer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} in {:?}", self.count(), self.total())
+    }
+}
+
+/// Work done by an e-graph and by matching patterns in it.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct EGraphMetrics {
+    /// Lookups of nodes in the hashcons
+    pub node_lookups: Counter,
+    /// Replacements of children of nodes with their canonical classes
+    pub canonicalizations: Counter,
+    /// Subpatterns tried against classes while searching for matches of rules
+    pub match_attempts: Counter,
+    /// Matches of rules found, before checking their guards
+    pub matches: Counter,
+    /// Merges of distinct classes
+    pub unions: Counter,
+    /// Rebuilds of classes after merges, restoring congruence of their parents
+    pub class_rebuilds: Counter,
+    /// Full passes rebuilding the hashcons
+    pub hashcons_rebuilds: Timer,
+}
+
+impl EGraphMetrics {
+    /// Adds the values of `other` to these metrics.
+    pub fn absorb(&self, other: &EGraphMetrics) {
+        self.node_lookups.add(other.node_lookups.get());
+        self.canonicalizations.add(other.canonicalizations.get());
+        self.match_attempts.add(other.match_attempts.get());
+        self.matches.add(other.matches.get());
+        self.unions.add(other.unions.get());
+        self.class_rebuilds.add(other.class_rebuilds.get());
+        self.hashcons_rebuilds.absorb(&other.hashcons_rebuilds);
+    }
+
+    /// Sets all values back to zero.
+    pub fn reset(&self) {
+        self.node_lookups.reset();
+        self.canonicalizations.reset();
+        self.match_attempts.reset();
+        self.matches.reset();
+        self.unions.reset();
+        self.class_rebuilds.reset();
+        self.hashcons_rebuilds.reset();
+    }
+}
+
+impl fmt::Display for EGraphMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "node lookups: {}", self.node_lookups.get())?;
+        writeln!(f, "canonicalizations: {}", self.canonicalizations.get())?;
+        writeln!(f, "match attempts: {}", self.match_attempts.get())?;
+        writeln!(f, "matches: {}", self.matches.get())?;
+        writeln!(f, "unions: {}", self.unions.get())?;
+        writeln!(f, "class rebuilds: {}", self.class_rebuilds.get())?;
+        write!(f, "hashcons rebuilds: {}", self.hashcons_rebuilds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        language::Language,
+        macros::rules,
+        rewriting::egraph::{DynEGraph, EGraph, matching::top_down::TopDownMatcher},
+    };
+
+    use super::{EGraphMetrics, ENABLED};
+
+    #[test]
+    fn counts_work_of_egraphs() {
+        let lang = Language::simple_math();
+        let rules = rules!(lang; "(* $0 1)" => "$0");
+        let mut egraph =
+            EGraph::<()>::from_expression(lang.parse_no_vars("(+ (* 2 1) 3)").unwrap());
+        assert_eq!(rules[0].apply(&mut egraph, &TopDownMatcher), 1);
+
+        let metrics = egraph.metrics();
+        let counts = [
+            metrics.node_lookups.get(),
+            metrics.canonicalizations.get(),
+            metrics.match_attempts.get(),
+            metrics.matches.get(),
+            metrics.unions.get(),
+            metrics.class_rebuilds.get(),
+            metrics.hashcons_rebuilds.count(),
+        ];
+        if ENABLED {
+            assert!(counts.iter().all(|&count| count > 0));
+            assert_eq!(metrics.matches.get(), 1);
+            assert_eq!(metrics.unions.get(), 1);
+        } else {
+            assert_eq!(counts, [0; 7]);
+        }
+
+        let total = EGraphMetrics::default();
+        total.absorb(metrics);
+        total.absorb(metrics);
+        assert_eq!(total.unions.get(), 2 * metrics.unions.get());
+        total.reset();
+        assert_eq!(total.node_lookups.get(), 0);
+    }
+}
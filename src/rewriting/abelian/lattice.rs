@@ -0,0 +1,209 @@
+//! Exact membership in integer lattices via the Smith normal form.
+//!
+//! Every integer matrix `A` can be brought to the diagonal form `D = U A V` with unimodular
+//! `U` and `V`, the Smith normal form. Then `A x = d` has an integer solution if and only if
+//! `D y = U d` does, i.e. if every entry of `U d` is divisible by the corresponding diagonal
+//! entry of `D`, and the entries beyond the rank are zero. Once `U` and `D` are computed
+//! for the abelianized matrix of a system, checking whether a difference vector is
+//! an integer combination of the columns takes a single matrix-vector product, which is
+//! much cheaper than solving an ILP. As the check ignores nonnegativity, it decides
+//! [`convertible`](super::convertible) exactly, while for [`reachable`](super::reachable)
+//! and ILP heuristics it is only a quick necessary condition.
+
+use nalgebra::DMatrix;
+
+use crate::rewriting::strings::SparseAbelianVector;
+
+/// The lattice of integer combinations of the columns of a matrix.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Lattice {
+    /// Rows of the unimodular row transformation `U`
+    transform: Vec<Vec<i64>>,
+    /// Nonzero diagonal entries of the Smith normal form, each dividing the next one
+    invariant_factors: Vec<i64>,
+}
+
+impl Lattice {
+    /// Computes the Smith normal form of `matrix`.
+    pub fn new(matrix: &DMatrix<i32>) -> Self {
+        let (rows, columns) = matrix.shape();
+        let mut work: Vec<Vec<i64>> = (0..rows)
+            .map(|row| {
+                (0..columns)
+                    .map(|column| matrix[(row, column)] as i64)
+                    .collect()
+            })
+            .collect();
+        let mut transform: Vec<Vec<i64>> = (0..rows)
+            .map(|row| (0..rows).map(|column| (row == column) as i64).collect())
+            .collect();
+        let mut invariant_factors = Vec::new();
+
+        for t in 0..rows.min(columns) {
+            let Some((pivot_row, pivot_column)) = smallest_entry(&work, t) else {
+                break;
+            };
+            work.swap(t, pivot_row);
+            transform.swap(t, pivot_row);
+            swap_columns(&mut work, t, pivot_column);
+
+            loop {
+                // Reduce the column, then the row of the pivot by it, moving a smaller remainder
+                // to the pivot position whenever one appears
+                for row in t + 1..rows {
+                    let quotient = work[row][t] / work[t][t];
+                    add_row_multiple(&mut work, row, t, -quotient);
+                    add_row_multiple(&mut transform, row, t, -quotient);
+                }
+                if let Some(row) = smallest_nonzero(t + 1..rows, |row| work[row][t]) {
+                    work.swap(t, row);
+                    transform.swap(t, row);
+                    continue;
+                }
+
+                for column in t + 1..columns {
+                    let quotient = work[t][column] / work[t][t];
+                    for row in &mut work {
+                        row[column] -= quotient * row[t];
+                    }
+                }
+                if let Some(column) = smallest_nonzero(t + 1..columns, |column| work[t][column]) {
+                    swap_columns(&mut work, t, column);
+                    continue;
+                }
+
+                // The pivot must divide all remaining entries, otherwise adding a row
+                // with a non-divisible entry makes the next reduction find a smaller remainder
+                let pivot = work[t][t];
+                let non_divisible = (t + 1..rows)
+                    .find(|&row| work[row][t + 1..].iter().any(|&entry| entry % pivot != 0));
+                match non_divisible {
+                    Some(row) => {
+                        add_row_multiple(&mut work, t, row, 1);
+                        add_row_multiple(&mut transform, t, row, 1);
+                    }
+                    None => break,
+                }
+            }
+
+            if work[t][t] < 0 {
+                work[t].iter_mut().for_each(|entry| *entry = -*entry);
+                transform[t].iter_mut().for_each(|entry| *entry = -*entry);
+            }
+            invariant_factors.push(work[t][t]);
+        }
+
+        Self {
+            transform,
+            invariant_factors,
+        }
+    }
+
+    /// Returns the rank of the matrix.
+    pub fn rank(&self) -> usize {
+        self.invariant_factors.len()
+    }
+
+    /// Returns the nonzero diagonal entries of the Smith normal form of the matrix.
+    pub fn invariant_factors(&self) -> &[i64] {
+        &self.invariant_factors
+    }
+
+    /// `true` if `vector` is an integer combination of the columns of the matrix.
+    pub fn contains(&self, vector: &SparseAbelianVector) -> bool {
+        let dimension = self.transform.len();
+        if vector
+            .iter()
+            .any(|(symbol_id, count)| symbol_id >= dimension && count != 0)
+        {
+            return false;
+        }
+
+        self.transform
+            .iter()
+            .enumerate()
+            .all(|(row, coefficients)| {
+                let entry: i64 = vector
+                    .iter()
+                    .filter(|&(symbol_id, _)| symbol_id < dimension)
+                    .map(|(symbol_id, count)| coefficients[symbol_id] * count as i64)
+                    .sum();
+                match self.invariant_factors.get(row) {
+                    Some(factor) => entry % factor == 0,
+                    None => entry == 0,
+                }
+            })
+    }
+}
+
+/// Returns the position of a nonzero entry with the smallest absolute value
+/// in rows and columns from `t` on.
+fn smallest_entry(work: &[Vec<i64>], t: usize) -> Option<(usize, usize)> {
+    (t..work.len())
+        .flat_map(|row| (t..work[row].len()).map(move |column| (row, column)))
+        .filter(|&(row, column)| work[row][column] != 0)
+        .min_by_key(|&(row, column)| work[row][column].abs())
+}
+
+/// Returns the index in `indices` of the nonzero `entry` with the smallest absolute value.
+fn smallest_nonzero(
+    indices: std::ops::Range<usize>,
+    entry: impl Fn(usize) -> i64,
+) -> Option<usize> {
+    indices
+        .filter(|&index| entry(index) != 0)
+        .min_by_key(|&index| entry(index).abs())
+}
+
+/// Adds `multiple` times the row `source` to the row `target`.
+fn add_row_multiple(matrix: &mut [Vec<i64>], target: usize, source: usize, multiple: i64) {
+    if multiple == 0 {
+        return;
+    }
+    for column in 0..matrix[target].len() {
+        matrix[target][column] += multiple * matrix[source][column];
+    }
+}
+
+fn swap_columns(matrix: &mut [Vec<i64>], a: usize, b: usize) {
+    for row in matrix {
+        row.swap(a, b);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{DMatrix, DVector};
+
+    use super::Lattice;
+    use crate::rewriting::strings::SparseAbelianVector;
+
+    fn vector(entries: &[i32]) -> SparseAbelianVector {
+        SparseAbelianVector::from_dense(&DVector::from_row_slice(entries))
+    }
+
+    #[test]
+    fn computes_invariant_factors() {
+        let matrix = DMatrix::from_row_slice(3, 3, &[2, 4, 4, -6, 6, 12, 10, -4, -16]);
+        let lattice = Lattice::new(&matrix);
+        assert_eq!(lattice.invariant_factors(), [2, 6, 12]);
+        assert_eq!(lattice.rank(), 3);
+
+        let singular = DMatrix::from_row_slice(2, 2, &[1, 2, 2, 4]);
+        assert_eq!(Lattice::new(&singular).invariant_factors(), [1]);
+    }
+
+    #[test]
+    fn decides_lattice_membership() {
+        // Columns (2, 0, 0) and (1, 1, 0)
+        let matrix = DMatrix::from_row_slice(3, 2, &[2, 1, 0, 1, 0, 0]);
+        let lattice = Lattice::new(&matrix);
+
+        assert!(lattice.contains(&vector(&[3, 1, 0])));
+        assert!(lattice.contains(&vector(&[-1, 1, 0])));
+        assert!(!lattice.contains(&vector(&[1, 0, 0])));
+        assert!(!lattice.contains(&vector(&[0, 0, 1])));
+        assert!(!lattice.contains(&vector(&[0, 0, 0, 1])));
+        assert!(lattice.contains(&vector(&[0, 0, 0, 0])));
+    }
+}
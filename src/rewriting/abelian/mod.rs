@@ -0,0 +1,268 @@
+//! Abelianized reachability.
+//!
+//! Forgetting the order of symbols, a rule application changes the counts of symbols
+//! of an expression by the difference between the abelianized vectors of the sides of the rule,
+//! i.e. by a column of the abelianized matrix (see [`rules_to_abelian_matrix`]),
+//! as long as the rule is linear. If `b` is reachable from `a`, then `b - a` is therefore
+//! a nonnegative integer combination of the columns, and if `a` and `b` are equivalent,
+//! it is any integer combination of them. These necessary conditions are decided here
+//! and can be used to prove unreachability without any rewriting.
+//! Integer combinations are decided exactly by the Smith normal form, see [`lattice`],
+//! which also serves as a quick check before solving ILPs for nonnegative ones.
+
+pub mod lattice;
+
+pub use lattice::Lattice;
+
+use good_lp::{SolverModel, constraint, default_solver};
+use nalgebra::DMatrix;
+
+use crate::language::{Language, expression::VarFreeExpression};
+use crate::rewriting::{
+    ilp::{create_ilp_feasibility_problem, create_lp_feasibility_problem},
+    rule::Rule,
+    strings::{SparseAbelianVector, rules_to_abelian_matrix},
+};
+
+/// `true` if `b_vec - a_vec` is in the integer cone generated by columns of `matrix`,
+/// i.e. if it is a combination of the columns with nonnegative integer coefficients.
+///
+/// If `b_vec` is reachable from `a_vec` with linear rules whose abelianized matrix
+/// is `matrix`, the result is `true`, so `false` proves that it is unreachable.
+pub fn reachable(
+    a_vec: &SparseAbelianVector,
+    b_vec: &SparseAbelianVector,
+    matrix: &DMatrix<i32>,
+) -> bool {
+    let diff = b_vec - a_vec;
+    if diff.is_zero() {
+        return true;
+    }
+
+    // A count which must grow (shrink) needs a column which grows (shrinks) it
+    let sign_infeasible = diff.iter().any(|(symbol_id, count)| {
+        symbol_id >= matrix.nrows()
+            || matrix
+                .row(symbol_id)
+                .iter()
+                .all(|&coefficient| coefficient.signum() != count.signum())
+    });
+    if sign_infeasible {
+        return false;
+    }
+
+    Lattice::new(matrix).contains(&diff) && feasible(&diff, matrix)
+}
+
+/// `true` if `b_vec - a_vec` is in the integer lattice generated by columns of `matrix`,
+/// i.e. if it is a combination of the columns with any integer coefficients.
+///
+/// If `a_vec` and `b_vec` are equivalent with respect to linear rules whose abelianized matrix
+/// is `matrix`, e.g. if they are unified by equality saturation, the result is `true`,
+/// so `false` proves that they are not equivalent.
+pub fn convertible(
+    a_vec: &SparseAbelianVector,
+    b_vec: &SparseAbelianVector,
+    matrix: &DMatrix<i32>,
+) -> bool {
+    let diff = b_vec - a_vec;
+    if diff.is_zero() {
+        return true;
+    }
+
+    Lattice::new(matrix).contains(&diff)
+}
+
+fn feasible(diff: &SparseAbelianVector, matrix: &DMatrix<i32>) -> bool {
+    if matrix.ncols() == 0 {
+        return false;
+    }
+
+    let (model, _) = create_ilp_feasibility_problem(
+        matrix,
+        &diff.to_dense(matrix.nrows()),
+        true,
+        default_solver,
+    );
+    model.solve().is_ok()
+}
+
+/// Returns indices of rules which may be applied when rewriting `source` into any of `goals`
+/// according to the abelianized relaxation, i.e. rules with a nonzero coefficient
+/// in some combination of columns equal to the difference between a goal and `source`.
+///
+/// Equality saturation rewrites the goals too, so, like in [`convertible`], coefficients
+/// of both signs are allowed. Combinations with real coefficients are considered,
+/// which is cheaper than solving integer problems and may only keep more rules than necessary.
+/// The relaxation is sound only for linear rules, so all rules are returned
+/// if some of them are not linear.
+pub fn supporting_rules(
+    rules: &[Rule],
+    source: &VarFreeExpression,
+    goals: &[VarFreeExpression],
+) -> Vec<usize> {
+    if !rules.iter().all(Rule::is_linear) {
+        return (0..rules.len()).collect();
+    }
+
+    let columns: Vec<_> = rules
+        .iter()
+        .map(|rule| {
+            &SparseAbelianVector::from_expression(rule.to())
+                - &SparseAbelianVector::from_expression(rule.from())
+        })
+        .collect();
+    let source = SparseAbelianVector::from_expression(&source.to_expression());
+    let diffs: Vec<_> = goals
+        .iter()
+        .map(|goal| &SparseAbelianVector::from_expression(&goal.to_expression()) - &source)
+        .collect();
+
+    let dimension = columns
+        .iter()
+        .chain(&diffs)
+        .flat_map(|vector| vector.iter().map(|(symbol_id, _)| symbol_id + 1))
+        .max()
+        .unwrap_or(0);
+    let matrix = DMatrix::from_fn(dimension, columns.len(), |row, column| {
+        columns[column].get(row)
+    });
+
+    (0..rules.len())
+        .filter(|&column| diffs.iter().any(|diff| supports(diff, &matrix, column)))
+        .collect()
+}
+
+/// `true` if some real solution of `matrix x = diff` has `x[column] >= 1` or `x[column] <= -1`,
+/// which holds if an integer solution has a nonzero `x[column]`.
+fn supports(diff: &SparseAbelianVector, matrix: &DMatrix<i32>, column: usize) -> bool {
+    let diff = diff.to_dense(matrix.nrows());
+    let feasible_with = |bound: f64| {
+        let (model, x) = create_lp_feasibility_problem(matrix, &diff, false, default_solver);
+        let constraint = if bound > 0.0 {
+            constraint!(x[column] >= bound)
+        } else {
+            constraint!(x[column] <= bound)
+        };
+        model.with(constraint).solve().is_ok()
+    };
+    feasible_with(1.0) || feasible_with(-1.0)
+}
+
+/// Prunes pairs of expressions which cannot be unified by equality saturation
+/// with given rules, using [`convertible`] on the abelianized vectors of the expressions.
+///
+/// The abelianization of whole expressions is sound only for linear rules, i.e. rules
+/// in which every variable occurs equally many times on both sides.
+#[derive(Clone, Debug)]
+pub struct AbelianPrefilter {
+    lattice: Lattice,
+}
+
+impl AbelianPrefilter {
+    /// Creates a prefilter for `rules` over `language`, or returns `None` if some rule
+    /// is not linear.
+    pub fn new(rules: &[Rule], language: &Language) -> Option<Self> {
+        rules.iter().all(Rule::is_linear).then(|| Self {
+            lattice: Lattice::new(&rules_to_abelian_matrix(rules, language)),
+        })
+    }
+
+    /// `false` if `a` and `b` are provably never unified.
+    pub fn may_unify(&self, a: &VarFreeExpression, b: &VarFreeExpression) -> bool {
+        let diff = &SparseAbelianVector::from_expression(&b.to_expression())
+            - &SparseAbelianVector::from_expression(&a.to_expression());
+        self.lattice.contains(&diff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AbelianPrefilter, convertible, reachable, supporting_rules};
+    use crate::{
+        language::Language,
+        macros::rules,
+        rewriting::strings::{SparseAbelianVector, rules_to_abelian_matrix},
+    };
+
+    #[test]
+    fn sign_and_divisibility_prove_unreachability() {
+        let lang = Language::simple_math();
+        // + => *, and (sin (sin x)) => x
+        let rules = rules!(lang;
+            "(+ $0 $1)" => "(* $0 $1)",
+            "(sin (sin $0))" => "$0",
+        );
+        let matrix = rules_to_abelian_matrix(&rules, &lang);
+        let vector = |expr| SparseAbelianVector::from_expression(&lang.parse(expr).unwrap());
+
+        let sum = vector("(+ 1 2)");
+        let product = vector("(* 1 2)");
+        assert!(reachable(&sum, &sum, &matrix));
+        // * can never be turned back into +
+        assert!(!reachable(&product, &sum, &matrix));
+        // sin can only be created or removed in pairs
+        assert!(!convertible(&vector("(sin 1)"), &vector("1"), &matrix));
+        // cos is not affected by any rule
+        assert!(!convertible(&vector("(cos 1)"), &vector("1"), &matrix));
+    }
+
+    #[test]
+    fn prefilter_requires_linear_rules() {
+        let lang = Language::simple_math();
+        assert!(AbelianPrefilter::new(&rules!(lang; "(* $0 2)" => "(+ $0 $0)"), &lang).is_none());
+
+        let prefilter =
+            AbelianPrefilter::new(&rules!(lang; "(sin (sin $0))" => "$0"), &lang).unwrap();
+        let parse = |expr| lang.parse_no_vars(expr).unwrap();
+        assert!(prefilter.may_unify(&parse("(sin (sin 1))"), &parse("1")));
+        assert!(!prefilter.may_unify(&parse("(sin 1)"), &parse("1")));
+    }
+
+    #[test]
+    fn finds_rules_supporting_reachability() {
+        let lang = Language::simple_math();
+        let rules = rules!(lang;
+            "(+ $0 $1)" => "(* $0 $1)",
+            "(sin $0)" => "(cos $0)",
+            "(+ $0 $1)" => "(+ $1 $0)",
+            "(* $0 $1)" => "(<< $0 $1)",
+        );
+        let parse = |expr| lang.parse_no_vars(expr).unwrap();
+
+        // Commutativity never changes the counts, so it may always be used
+        assert_eq!(
+            supporting_rules(&rules, &parse("(+ 1 2)"), &[parse("(* 2 1)")]),
+            [0, 2]
+        );
+        assert_eq!(
+            supporting_rules(
+                &rules,
+                &parse("(+ 1 (sin 2))"),
+                &[parse("(<< 1 (sin 2))"), parse("(+ 1 (cos 2))")]
+            ),
+            [0, 1, 2, 3]
+        );
+        // The goal can be rewritten into the source, so the same rules may be used
+        assert_eq!(
+            supporting_rules(&rules, &parse("(* 1 2)"), &[parse("(+ 1 2)")]),
+            [0, 2]
+        );
+    }
+
+    #[test]
+    fn keeps_rules_rewriting_goals() {
+        let lang = Language::simple_math();
+        let rules = rules!(lang;
+            "(+ $0 $1)" => "(* $0 $1)",
+            "(<< $0 $1)" => "(* $0 $1)",
+        );
+        let parse = |expr| lang.parse_no_vars(expr).unwrap();
+
+        // Both sides are rewritten into (* 1 2), which needs the second rule backwards
+        assert_eq!(
+            supporting_rules(&rules, &parse("(+ 1 2)"), &[parse("(<< 1 2)")]),
+            [0, 1]
+        );
+    }
+}
@@ -7,7 +7,10 @@
 //! Variables in expressions are treated as distinct from pattern variables in rules,
 //! allowing rules to be applied to expressions containing variables.
 
+use std::collections::BTreeSet;
+
 use crate::language::expression::{Expression, VarFreeExpression};
+use crate::language::symbol::SymbolId;
 use crate::rewriting::rule::Rule;
 
 // Re-export ExpressionMatch for convenience
@@ -167,6 +170,20 @@ pub struct RewritePosition {
 pub fn find_all_rewrite_positions_expr(
     expression: &Expression,
     rules: &[Rule],
+) -> Vec<RewritePosition> {
+    find_all_rewrite_positions_with_binders(expression, rules, &BTreeSet::new())
+}
+
+/// Same as [`find_all_rewrite_positions_expr`], respecting the scopes of variables bound by
+/// `binders`, see [`Language::binders`](crate::language::Language::binders).
+///
+/// Rules match up to renaming of bound variables, see
+/// [`Expression::try_match_expression_with_binders`], and are not applicable where
+/// a bound variable would escape the scope of its binder.
+pub fn find_all_rewrite_positions_with_binders(
+    expression: &Expression,
+    rules: &[Rule],
+    binders: &BTreeSet<SymbolId>,
 ) -> Vec<RewritePosition> {
     use crate::language::expression::AnyExpression;
 
@@ -178,13 +195,10 @@ pub fn find_all_rewrite_positions_expr(
                 rules
                     .iter()
                     .enumerate()
-                    .filter_map(move |(rule_index, rule)| {
-                        Expression::try_match_expression(rule.from(), subexpr).map(|_| {
-                            RewritePosition {
-                                path: path.clone(),
-                                rule_index,
-                            }
-                        })
+                    .filter(move |(_, rule)| applies(rule, subexpr, binders))
+                    .map(move |(rule_index, _)| RewritePosition {
+                        path: path.clone(),
+                        rule_index,
                     }),
             )
         })
@@ -210,21 +224,56 @@ pub fn apply_rewrite_at_position_expr(
     expression: Expression,
     rules: &[Rule],
     position: &RewritePosition,
+) -> Expression {
+    apply_rewrite_at_position_with_binders(expression, rules, position, &BTreeSet::new())
+}
+
+/// Same as [`apply_rewrite_at_position_expr`], respecting the scopes of variables bound by
+/// `binders`, for positions found by [`find_all_rewrite_positions_with_binders`].
+pub fn apply_rewrite_at_position_with_binders(
+    expression: Expression,
+    rules: &[Rule],
+    position: &RewritePosition,
+    binders: &BTreeSet<SymbolId>,
 ) -> Expression {
     expression.apply_at_path(&position.path, |subexpr| {
-        let rule = &rules[position.rule_index];
-
-        // Try to match the rule at this position
-        if let Some(matching) = Expression::try_match_expression(rule.from(), subexpr) {
-            // Instantiate the right-hand side with the matched variables
-            Expression::instantiate_expression(rule.to(), &matching)
-        } else {
-            // This shouldn't happen if find_all_rewrite_positions_expr is correct
-            subexpr.clone()
-        }
+        // The subexpression is kept if the rule does not apply, which shouldn't happen
+        // for positions found by find_all_rewrite_positions_with_binders
+        rewrite_subexpression(&rules[position.rule_index], subexpr, binders)
+            .unwrap_or_else(|| subexpr.clone())
     })
 }
 
+/// Checks if `rule` can rewrite `expression` at its root.
+fn applies(rule: &Rule, expression: &Expression, binders: &BTreeSet<SymbolId>) -> bool {
+    if binders.is_empty() {
+        Expression::try_match_expression(rule.from(), expression)
+            .is_some_and(|matching| rule.admits_expression_match(&matching))
+    } else {
+        rewrite_subexpression(rule, expression, binders).is_some()
+    }
+}
+
+/// Rewrites `expression` at its root with `rule`, if it matches.
+fn rewrite_subexpression(
+    rule: &Rule,
+    expression: &Expression,
+    binders: &BTreeSet<SymbolId>,
+) -> Option<Expression> {
+    if binders.is_empty() {
+        let matching = Expression::try_match_expression(rule.from(), expression)?;
+        return rule
+            .admits_expression_match(&matching)
+            .then(|| Expression::instantiate_expression(rule.to(), &matching));
+    }
+
+    let matching = Expression::try_match_expression_with_binders(rule.from(), expression, binders)?;
+    if !rule.admits_expression_match(&matching) {
+        return None;
+    }
+    Expression::instantiate_expression_with_binders(rule.to(), &matching, binders)
+}
+
 /// Finds all positions in a variable-free expression where any rule can be applied.
 ///
 /// Converts to Expression, finds positions, and returns them.
@@ -439,4 +488,29 @@ mod tests {
         // Should find 2 positions: at root and at nested (+)
         assert_eq!(positions.len(), 2);
     }
+
+    #[test]
+    fn test_rewrite_positions_with_binders() {
+        let lang = Language::default()
+            .add_symbol("lam")
+            .add_symbol("app")
+            .add_symbol("f")
+            .declare_binder("lam");
+        let binders = lang.binders();
+        let rules = vec![
+            Rule::from_strings("(app $0 $0)", "$0", &lang),
+            Rule::from_strings("(lam $0 $1)", "$1", &lang),
+        ];
+        let expr = lang.parse("(app (lam $1 (f $1)) (lam $2 (f $2)))").unwrap();
+
+        // Only the alpha-equivalent arguments match, the bound variables cannot escape
+        assert!(find_all_rewrite_positions_expr(&expr, &rules[..1]).is_empty());
+        let positions = find_all_rewrite_positions_with_binders(&expr, &rules, binders);
+        assert_eq!(positions.len(), 1);
+        assert_eq!(positions[0].rule_index, 0);
+
+        let rewritten =
+            apply_rewrite_at_position_with_binders(expr, &rules, &positions[0], binders);
+        assert_eq!(rewritten, lang.parse("(lam $1 (f $1))").unwrap());
+    }
 }
@@ -0,0 +1,408 @@
+//! Rewrite rules with computed right-hand sides.
+//!
+//! This module provides the [`DynamicRule`] struct, whose right-hand side is built by a callback
+//! instead of being instantiated from a pattern, and the [`RuleLike`] enum covering both kinds
+//! of rules, which is accepted by
+//! [`Saturator::saturate_dynamic`](super::egraph::saturation::Saturator::saturate_dynamic).
+
+use std::{fmt, rc::Rc, time::Instant};
+
+use crate::{
+    language::{
+        Language,
+        expression::{AnyExpression, Expression},
+    },
+    seen::Seen,
+};
+
+use super::{
+    egraph::{
+        Analysis, ClassId, DynEGraph, EGraph,
+        matching::{EGraphMatch, Matcher},
+        saturation::{ApplicationFilter, report::RuleReport},
+    },
+    rule::Rule,
+};
+
+/// Callback building the right-hand side of a [`DynamicRule`] at a match.
+pub type Applier = dyn Fn(&EGraphMatch, &mut dyn DynEGraph) -> Seen<ClassId>;
+
+/// A rewrite rule whose right-hand side is computed by a callback.
+///
+/// When the `from` pattern matches, the callback adds the right-hand side to the e-graph
+/// and returns its class, which is merged with the matched class. This allows rewrites
+/// which cannot be written as patterns, e.g. folding `(+ 1 2)` into the literal `3`.
+#[derive(Clone)]
+pub struct DynamicRule {
+    from: Expression,
+    applier: Rc<Applier>,
+    name: Option<String>,
+}
+
+impl DynamicRule {
+    /// Creates a rule from a pattern and a callback building the right-hand side.
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - The pattern to match (left-hand side)
+    /// * `applier` - Adds the right-hand side at a match, returning its class
+    pub fn new(
+        from: Expression,
+        applier: impl Fn(&EGraphMatch, &mut dyn DynEGraph) -> Seen<ClassId> + 'static,
+    ) -> Self {
+        Self {
+            from,
+            applier: Rc::new(applier),
+            name: None,
+        }
+    }
+
+    /// Creates a rule from a string pattern, see [`DynamicRule::new`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pattern cannot be parsed
+    pub fn from_string(
+        from: &str,
+        applier: impl Fn(&EGraphMatch, &mut dyn DynEGraph) -> Seen<ClassId> + 'static,
+        language: &Language,
+    ) -> Self {
+        Self::new(language.parse(from).unwrap(), applier)
+    }
+
+    /// Names the rule.
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = Some(String::from(name));
+        self
+    }
+
+    /// Returns the name of the rule, if it has one.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the pattern to match (left-hand side).
+    pub fn from(&self) -> &Expression {
+        &self.from
+    }
+
+    /// Returns the name of the rule or, for unnamed rules, its pattern as `from => ...`.
+    pub fn label(&self, language: &Language) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => format!("{} => ...", self.from.with_language(language)),
+        }
+    }
+
+    /// Returns the number of positions at which the rule changed the e-graph.
+    /// Applications which would add new nodes to frozen classes are skipped,
+    /// see [`EGraph::freeze_class`].
+    pub fn apply<A: Analysis>(
+        &self,
+        egraph: &mut EGraph<A>,
+        matcher: &(impl Matcher + ?Sized),
+    ) -> usize {
+        self.apply_reported(egraph, matcher, &mut RuleReport::default())
+    }
+
+    /// Same as [`DynamicRule::apply`], but also adds the numbers of matches and applications
+    /// to `report`.
+    pub fn apply_reported<A: Analysis>(
+        &self,
+        egraph: &mut EGraph<A>,
+        matcher: &(impl Matcher + ?Sized),
+        report: &mut RuleReport,
+    ) -> usize {
+        self.apply_filtered(egraph, matcher, &(), report)
+    }
+
+    /// Same as [`DynamicRule::apply_reported`], but skips applications rejected by `filter`,
+    /// see [`ApplicationFilter::accept_dynamic`].
+    pub fn apply_filtered<A: Analysis>(
+        &self,
+        egraph: &mut EGraph<A>,
+        matcher: &(impl Matcher + ?Sized),
+        filter: &dyn ApplicationFilter<A>,
+        report: &mut RuleReport,
+    ) -> usize {
+        let start = Instant::now();
+        let matches = matcher.try_match(egraph, &self.from);
+        report.matches += matches.len();
+        report.match_time += start.elapsed();
+
+        matches
+            .iter()
+            .filter(|matching| self.apply_match(egraph, matching, filter, report))
+            .count()
+    }
+
+    /// Builds the right-hand side at `matching` and merges it with the matched class,
+    /// unless the application is rejected by `filter` or it adds new nodes to a frozen class,
+    /// in which case the right-hand side is removed again.
+    /// Returns `true` if the e-graph has changed, in which case the application is added to `report`.
+    pub fn apply_match<A: Analysis>(
+        &self,
+        egraph: &mut EGraph<A>,
+        matching: &EGraphMatch,
+        filter: &dyn ApplicationFilter<A>,
+        report: &mut RuleReport,
+    ) -> bool {
+        let start = Instant::now();
+        let snapshot = egraph.snapshot();
+        let node_count = egraph.total_node_count();
+        let added = (self.applier)(matching, egraph);
+        let grows_frozen =
+            egraph.is_frozen(matching.root()) && egraph.total_node_count() > node_count;
+        if grows_frozen || !filter.accept_dynamic(egraph, matching, self, &added) {
+            egraph.rollback(snapshot);
+            report.apply_time += start.elapsed();
+            return false;
+        }
+        egraph.commit(snapshot);
+
+        let merged = egraph
+            .merge_classes(matching.root(), *added.as_ref().any())
+            .new()
+            .is_some();
+        if merged {
+            report.unions += 1;
+        }
+        let applied = merged || added.new().is_some();
+        if applied {
+            report.applications += 1;
+        }
+        report.apply_time += start.elapsed();
+        applied
+    }
+}
+
+impl fmt::Debug for DynamicRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynamicRule")
+            .field("from", &self.from)
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Either a [`Rule`] or a [`DynamicRule`].
+#[derive(Clone, Debug)]
+pub enum RuleLike {
+    Static(Rule),
+    Dynamic(DynamicRule),
+}
+
+impl RuleLike {
+    /// Returns the pattern to match (left-hand side).
+    pub fn pattern(&self) -> &Expression {
+        match self {
+            RuleLike::Static(rule) => rule.from(),
+            RuleLike::Dynamic(rule) => rule.from(),
+        }
+    }
+
+    /// Returns the name of the rule, if it has one.
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            RuleLike::Static(rule) => rule.name(),
+            RuleLike::Dynamic(rule) => rule.name(),
+        }
+    }
+
+    /// Returns the name or the patterns of the rule, see [`Rule::label`].
+    pub fn label(&self, language: &Language) -> String {
+        match self {
+            RuleLike::Static(rule) => rule.label(language),
+            RuleLike::Dynamic(rule) => rule.label(language),
+        }
+    }
+
+    /// Returns the rule if it is static.
+    pub fn as_static(&self) -> Option<&Rule> {
+        match self {
+            RuleLike::Static(rule) => Some(rule),
+            RuleLike::Dynamic(_) => None,
+        }
+    }
+
+    /// Applies the rule at all of its matches, see [`Rule::apply_filtered`]
+    /// and [`DynamicRule::apply_filtered`].
+    pub fn apply_filtered<A: Analysis>(
+        &self,
+        egraph: &mut EGraph<A>,
+        matcher: &(impl Matcher + ?Sized),
+        filter: &dyn ApplicationFilter<A>,
+        report: &mut RuleReport,
+    ) -> usize {
+        match self {
+            RuleLike::Static(rule) => rule.apply_filtered(egraph, matcher, filter, report),
+            RuleLike::Dynamic(rule) => rule.apply_filtered(egraph, matcher, filter, report),
+        }
+    }
+}
+
+impl From<Rule> for RuleLike {
+    fn from(rule: Rule) -> Self {
+        RuleLike::Static(rule)
+    }
+}
+
+impl From<DynamicRule> for RuleLike {
+    fn from(rule: DynamicRule) -> Self {
+        RuleLike::Dynamic(rule)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        language::{
+            Language,
+            expression::{Literal, VarFreeExpression},
+        },
+        rewriting::{
+            egraph::{
+                ClassId, DynEGraph, EGraph, Node,
+                class::{simple_math_local_cost::SimpleMathLocalCost, term_size::TermSize},
+                matching::{EGraphMatch, bottom_up::BottomUpMatcher},
+                saturation::{
+                    RuleReport, SaturationConfig, SaturationStopReason, Saturator, SimpleSaturator,
+                    TermBounds, UnsupportedDynamicRules, directed_saturator::DirectedSaturator,
+                },
+            },
+            rule::Rule,
+        },
+        seen::Seen,
+    };
+
+    use super::{DynamicRule, RuleLike};
+
+    fn integer(egraph: &dyn DynEGraph, class_id: ClassId) -> Option<i64> {
+        egraph
+            .dyn_class(class_id)
+            .iter_nodes()
+            .find_map(|&node_id| match egraph.node(node_id) {
+                Node::Literal(Literal::Int(value)) => Some(*value),
+                _ => None,
+            })
+    }
+
+    fn fold_sum(lang: &Language) -> DynamicRule {
+        DynamicRule::from_string(
+            "(+ $0 $1)",
+            |matching: &EGraphMatch, egraph: &mut dyn DynEGraph| {
+                let (Some(left), Some(right)) = (
+                    integer(egraph, matching.class_variable(0)),
+                    integer(egraph, matching.class_variable(1)),
+                ) else {
+                    return Seen::Old(matching.root());
+                };
+                let sum = Literal::Int(left + right);
+                match egraph.find_literal(sum.clone()) {
+                    Some(class_id) => Seen::Old(class_id),
+                    None => {
+                        let node_id = egraph.add_expression(VarFreeExpression::Literal(sum));
+                        Seen::New(egraph.containing_class(node_id))
+                    }
+                }
+            },
+            lang,
+        )
+        .with_name("fold-sum")
+    }
+
+    #[test]
+    fn folds_constants() {
+        let lang = Language::simple_math();
+        let mut egraph =
+            EGraph::<()>::from_expression(lang.parse_no_vars("(+ 1 (+ 2 3))").unwrap());
+        let root = egraph.canonical_class(egraph.class_count() - 1);
+
+        // The inner sum is folded before the outer one is built
+        assert_eq!(fold_sum(&lang).apply(&mut egraph, &BottomUpMatcher), 2);
+        assert_eq!(fold_sum(&lang).apply(&mut egraph, &BottomUpMatcher), 0);
+        assert_eq!(integer(&egraph, root), Some(6));
+    }
+
+    #[test]
+    fn saturates_with_static_and_dynamic_rules() {
+        let lang = Language::simple_math();
+        let mut egraph =
+            EGraph::<()>::from_expression(lang.parse_no_vars("(* (+ 1 2) (sin 4))").unwrap());
+        let rules = [
+            RuleLike::from(Rule::from_strings("(* $0 $1)", "(* $1 $0)", &lang)),
+            RuleLike::from(fold_sum(&lang)),
+        ];
+
+        let report = SimpleSaturator::new(Box::new(BottomUpMatcher))
+            .saturate_dynamic(&mut egraph, &rules, &SaturationConfig::default())
+            .unwrap();
+        assert_eq!(report.stop_reason, Some(SaturationStopReason::Saturated));
+        assert_eq!(report.rules.len(), 2);
+        assert_eq!(report.rules[1].applications, 1);
+
+        let folded = egraph.add_expression(lang.parse_no_vars("(* (sin 4) 3)").unwrap());
+        let product = egraph.add_expression(lang.parse_no_vars("(* (+ 1 2) (sin 4))").unwrap());
+        assert_eq!(
+            egraph.canonical_class(folded),
+            egraph.canonical_class(product)
+        );
+    }
+
+    #[test]
+    fn respects_frozen_classes_and_filters() {
+        let lang = Language::simple_math();
+        let mut egraph =
+            EGraph::<TermSize>::from_expression(lang.parse_no_vars("(+ 1 2)").unwrap());
+        let root = egraph.canonical_class(egraph.class_count() - 1);
+        let node_count = egraph.total_node_count();
+
+        egraph.freeze_class(root);
+        assert_eq!(fold_sum(&lang).apply(&mut egraph, &BottomUpMatcher), 0);
+        egraph.unfreeze_class(root);
+        let bounds = TermBounds {
+            max_size: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(
+            fold_sum(&lang).apply_filtered(
+                &mut egraph,
+                &BottomUpMatcher,
+                &bounds,
+                &mut RuleReport::default()
+            ),
+            0
+        );
+        // Rejected right-hand sides are removed again
+        assert_eq!(egraph.total_node_count(), node_count);
+        assert_eq!(fold_sum(&lang).apply(&mut egraph, &BottomUpMatcher), 1);
+    }
+
+    #[test]
+    fn other_saturators_reject_dynamic_rules() {
+        let lang = Language::simple_math();
+        let mut egraph =
+            EGraph::<SimpleMathLocalCost>::from_expression(lang.parse_no_vars("(* 1 2)").unwrap());
+        let commutativity = RuleLike::from(Rule::from_strings("(* $0 $1)", "(* $1 $0)", &lang));
+        let saturator = DirectedSaturator::new(Box::new(BottomUpMatcher));
+
+        let report = saturator
+            .saturate_dynamic(
+                &mut egraph,
+                std::slice::from_ref(&commutativity),
+                &SaturationConfig::default(),
+            )
+            .unwrap();
+        assert_eq!(report.rules[0].applications, 1);
+        assert_eq!(
+            saturator
+                .saturate_dynamic(
+                    &mut egraph,
+                    &[commutativity, RuleLike::from(fold_sum(&lang))],
+                    &SaturationConfig::default(),
+                )
+                .unwrap_err(),
+            UnsupportedDynamicRules
+        );
+    }
+}
@@ -3,7 +3,9 @@
 //! This module provides the [`Analysis`] trait for computing and maintaining
 //! metadata about equivalence classes in an e-graph.
 
-use super::{EGraph, NodeId};
+use crate::rewriting::egraph::{ClassId, DynEGraph, Node};
+
+use super::{EGraph, NodeId, table_local_cost::SymbolCosts, term_size::TermSize};
 
 /// Trait for computing analysis data on e-graph classes.
 ///
@@ -16,7 +18,7 @@ pub trait Analysis: Sized + Clone + Default {
     ///
     /// * `egraph` - The e-graph containing the node
     /// * `node_id` - The ID of the node
-    fn make(egraph: &EGraph<Self>, node_id: NodeId) -> Self;
+    fn make(egraph: &dyn AnalysisGraph<Self>, node_id: NodeId) -> Self;
 
     /// Creates analysis data by merging data from two other classes.
     ///
@@ -30,6 +32,28 @@ pub trait Analysis: Sized + Clone + Default {
     /// Returns the merged analysis data
     fn merge(a: Self, b: Self) -> Self;
 
+    /// Modifies the e-graph based on the analysis data of a class.
+    ///
+    /// Called after a class is created and after two classes are merged, including merges
+    /// performed while rebuilding. This can be used e.g. to add a literal node to a class whose
+    /// analysis found it to be constant. Changes made by this method trigger further calls,
+    /// so it should stop changing the e-graph once its changes are already present.
+    ///
+    /// The analysis of the e-graph can be a composite of this one, see [`composite_analysis`],
+    /// so the data of the class is read with `data`.
+    ///
+    /// # Arguments
+    ///
+    /// * `egraph` - The e-graph containing the class
+    /// * `class_id` - The ID of the class, which is canonical when this method is called
+    /// * `data` - Returns the data of this analysis from the data of the analysis of `egraph`
+    fn modify<G: Analysis>(
+        _egraph: &mut EGraph<G>,
+        _class_id: ClassId,
+        _data: impl Fn(&G) -> &Self,
+    ) {
+    }
+
     /// Converts the analysis data to a string for display purposes.
     ///
     /// # Returns
@@ -38,10 +62,263 @@ pub trait Analysis: Sized + Clone + Default {
     fn to_string(&self) -> Option<String> {
         None
     }
+
+    /// Returns the size and depth of the smallest terms represented by the class,
+    /// if the analysis tracks them.
+    ///
+    /// Bounds on sizes and depths of terms created during saturation, see
+    /// [`TermBounds`](crate::rewriting::egraph::saturation::TermBounds),
+    /// are only enforced for analyses which do.
+    fn term_size(&self) -> Option<TermSize> {
+        None
+    }
+
+    /// Returns a lower bound on the cost of terms extractable from the class,
+    /// if the analysis tracks one.
+    ///
+    /// Bounds on costs of terms created during saturation, see
+    /// [`TermBounds::max_cost_increase`](crate::rewriting::egraph::saturation::TermBounds::max_cost_increase),
+    /// are only enforced for analyses which do.
+    fn cost_lower_bound(&self) -> Option<usize> {
+        None
+    }
+
+    /// Returns `true` if the data of parents of a class should be remade after its data
+    /// changed from `previous` to `self` by a merge.
+    ///
+    /// By default data is not propagated, so data depending on the data of children
+    /// can be stale after their classes are merged. Analyses which should stay exact,
+    /// e.g. [`CostLowerBound`](super::cost_lower_bound::CostLowerBound), return `true`
+    /// when the data changed. Data of parents is then remade by merging the data made
+    /// for each of their nodes, which is propagated further while it keeps changing.
+    fn propagates(&self, _previous: &Self) -> bool {
+        false
+    }
+}
+
+/// Read access to an e-graph needed to make analysis data.
+///
+/// Implemented by [`EGraph`] and by [`Projection`], which presents the e-graph
+/// to a component of a composite analysis as if only that component was computed.
+pub trait AnalysisGraph<A> {
+    /// Returns the node with id `node_id`.
+    fn node(&self, node_id: NodeId) -> &Node;
+
+    /// Returns the analysis data of the class with id `class_id`.
+    fn analysis(&self, class_id: ClassId) -> &A;
+
+    /// Returns the costs of symbols of the e-graph, see [`EGraph::with_costs`].
+    fn costs(&self) -> Option<&SymbolCosts>;
+}
+
+impl<A: Analysis> AnalysisGraph<A> for EGraph<A> {
+    fn node(&self, node_id: NodeId) -> &Node {
+        DynEGraph::node(self, node_id)
+    }
+
+    fn analysis(&self, class_id: ClassId) -> &A {
+        self.class(class_id).analysis()
+    }
+
+    fn costs(&self) -> Option<&SymbolCosts> {
+        EGraph::costs(self)
+    }
+}
+
+/// An e-graph with analysis `P` seen through analysis `A` being a part of `P`.
+pub struct Projection<'a, P, A> {
+    egraph: &'a dyn AnalysisGraph<P>,
+    project: fn(&P) -> &A,
+}
+
+impl<'a, P, A> Projection<'a, P, A> {
+    /// Creates a projection of `egraph` whose analysis data is `project` of the data of `egraph`.
+    pub fn new(egraph: &'a dyn AnalysisGraph<P>, project: fn(&P) -> &A) -> Self {
+        Self { egraph, project }
+    }
+}
+
+impl<P, A> AnalysisGraph<A> for Projection<'_, P, A> {
+    fn node(&self, node_id: NodeId) -> &Node {
+        self.egraph.node(node_id)
+    }
+
+    fn analysis(&self, class_id: ClassId) -> &A {
+        (self.project)(self.egraph.analysis(class_id))
+    }
+
+    fn costs(&self) -> Option<&SymbolCosts> {
+        self.egraph.costs()
+    }
 }
 
 /// Unit analysis - no metadata is computed.
 impl Analysis for () {
-    fn make(_egraph: &EGraph<Self>, _node_id: NodeId) -> Self {}
+    fn make(_egraph: &dyn AnalysisGraph<Self>, _node_id: NodeId) -> Self {}
     fn merge(_a: Self, _b: Self) -> Self {}
 }
+
+/// Implements [`Analysis`] for a composite of other analyses, computing all of them.
+///
+/// Data of the composite is displayed as the data of its parts which can be displayed.
+/// Its term sizes and cost bounds are those of the first part which tracks them,
+/// and its data is propagated if the data of any part is.
+/// It is used for tuples, and can be used for structs with the same fields, e.g.
+///
+/// ```
+/// use verbum::composite_analysis;
+/// use verbum::rewriting::egraph::class::{
+///     literal_count::LiteralCountAnalysis, term_size::TermSize,
+/// };
+///
+/// #[derive(Clone, Debug, Default)]
+/// struct Combined {
+///     literals: LiteralCountAnalysis,
+///     size: TermSize,
+/// }
+///
+/// composite_analysis!(Combined { literals: LiteralCountAnalysis, size: TermSize });
+/// ```
+#[macro_export]
+macro_rules! composite_analysis {
+    ($composite:ident { $( $field:tt : $part:ty ),+ $(,)? }) => {
+        $crate::composite_analysis!(@impl [] $composite { $( $field: $part ),+ });
+    };
+    (@impl [$( $param:ident ),*] $composite:ty { $( $field:tt : $part:ty ),+ }) => {
+        impl<$( $param: $crate::rewriting::egraph::Analysis + 'static ),*>
+            $crate::rewriting::egraph::Analysis for $composite
+        {
+            fn make(
+                egraph: &dyn $crate::rewriting::egraph::class::analysis::AnalysisGraph<Self>,
+                node_id: $crate::rewriting::egraph::NodeId,
+            ) -> Self {
+                // Fields are assigned one by one so that tuples can be composites too
+                let mut composite = Self::default();
+                $(
+                    composite.$field = <$part as $crate::rewriting::egraph::Analysis>::make(
+                        &$crate::rewriting::egraph::class::analysis::Projection::new(
+                            egraph,
+                            |composite: &Self| &composite.$field,
+                        ),
+                        node_id,
+                    );
+                )+
+                composite
+            }
+
+            fn merge(a: Self, b: Self) -> Self {
+                let mut composite = Self::default();
+                $(
+                    composite.$field =
+                        <$part as $crate::rewriting::egraph::Analysis>::merge(a.$field, b.$field);
+                )+
+                composite
+            }
+
+            fn modify<G: $crate::rewriting::egraph::Analysis>(
+                egraph: &mut $crate::rewriting::egraph::EGraph<G>,
+                class_id: $crate::rewriting::egraph::ClassId,
+                data: impl Fn(&G) -> &Self,
+            ) {
+                $(
+                    let class_id = $crate::rewriting::egraph::DynEGraph::canonical_class(
+                        egraph,
+                        class_id,
+                    );
+                    <$part as $crate::rewriting::egraph::Analysis>::modify(
+                        egraph,
+                        class_id,
+                        |analysis: &G| &data(analysis).$field,
+                    );
+                )+
+            }
+
+            fn to_string(&self) -> Option<String> {
+                let parts = [
+                    $( $crate::rewriting::egraph::Analysis::to_string(&self.$field) ),+
+                ];
+                let parts: Vec<_> = parts.into_iter().flatten().collect();
+                (!parts.is_empty()).then(|| parts.join(", "))
+            }
+
+            fn term_size(&self) -> Option<$crate::rewriting::egraph::class::term_size::TermSize> {
+                None $( .or_else(|| self.$field.term_size()) )+
+            }
+
+            fn cost_lower_bound(&self) -> Option<usize> {
+                None $( .or_else(|| self.$field.cost_lower_bound()) )+
+            }
+
+            fn propagates(&self, previous: &Self) -> bool {
+                false $( || self.$field.propagates(&previous.$field) )+
+            }
+        }
+    };
+}
+
+pub use composite_analysis;
+
+composite_analysis!(@impl [A, B] (A, B) { 0: A, 1: B });
+composite_analysis!(@impl [A, B, C] (A, B, C) { 0: A, 1: B, 2: C });
+composite_analysis!(@impl [A, B, C, D] (A, B, C, D) { 0: A, 1: B, 2: C, 3: D });
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        language::Language,
+        rewriting::egraph::{
+            Analysis, DynEGraph, EGraph,
+            class::{
+                literal_count::LiteralCountAnalysis, simple_math_local_cost::SimpleMathLocalCost,
+                term_size::TermSize,
+            },
+        },
+    };
+
+    fn analyses<A: Analysis>(expression: &str, equal: (&str, &str)) -> Vec<A> {
+        let lang = Language::simple_math();
+        let mut egraph = EGraph::<A>::from_expression(lang.parse_no_vars(expression).unwrap());
+        let left = egraph.add_expression(lang.parse_no_vars(equal.0).unwrap());
+        let right = egraph.add_expression(lang.parse_no_vars(equal.1).unwrap());
+        egraph.merge_classes(left, right);
+
+        let mut classes = egraph.iter_classes().map(|(&id, _)| id).collect::<Vec<_>>();
+        classes.sort();
+        classes
+            .into_iter()
+            .map(|id| egraph.class(id).analysis().clone())
+            .collect()
+    }
+
+    #[test]
+    fn tuple_computes_all_parts() {
+        let expression = "(* (+ 1 2) (- 3 (* 4 5)))";
+        let equal = ("(* 4 5)", "20");
+        let composite =
+            analyses::<(LiteralCountAnalysis, TermSize, SimpleMathLocalCost)>(expression, equal);
+
+        let counts = analyses::<LiteralCountAnalysis>(expression, equal);
+        let sizes = analyses::<TermSize>(expression, equal);
+        let costs = analyses::<SimpleMathLocalCost>(expression, equal);
+        assert_eq!(composite.len(), counts.len());
+        for (i, (count, size, cost)) in composite.into_iter().enumerate() {
+            assert_eq!(count, counts[i]);
+            assert_eq!(size, sizes[i]);
+            assert!(cost == costs[i]);
+        }
+    }
+
+    #[test]
+    fn tuple_display_and_term_size() {
+        let lang = Language::simple_math();
+        let egraph = EGraph::<((), TermSize, SimpleMathLocalCost)>::from_expression(
+            lang.parse_no_vars("(+ 1 2)").unwrap(),
+        );
+        let root = egraph.canonical_class(egraph.iter_classes().count() - 1);
+        let analysis = egraph.class(root).analysis();
+
+        assert_eq!(analysis.term_size(), Some(TermSize { size: 3, depth: 2 }));
+        let display = Analysis::to_string(analysis).unwrap();
+        assert!(display.starts_with("size 3, depth 2, "));
+    }
+}
@@ -0,0 +1,183 @@
+//! Lower bounds on costs of terms extractable from classes.
+//!
+//! [`CostLowerBound`] tracks, for every class, the cost of its cheapest term according to
+//! the [`SymbolCosts`] of the e-graph, see [`EGraph::with_costs`]. Unlike the data of a [`LocalCost`] analysis, the bounds are
+//! propagated to parents when classes merge, see
+//! [`Analysis::propagates`](super::Analysis::propagates), so they never
+//! exceed the cost of any term the e-graph represents. Saturators can use them to skip
+//! applications creating terms much more expensive than the class they are added to,
+//! see [`TermBounds::max_cost_increase`](crate::rewriting::egraph::saturation::TermBounds::max_cost_increase).
+//!
+//! [`EGraph::with_costs`]: crate::rewriting::egraph::EGraph::with_costs
+
+use std::{
+    iter::Sum,
+    ops::{Add, Sub},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::language::{expression::Literal, symbol::SymbolId};
+
+use super::{
+    local_cost::LocalCost,
+    table_local_cost::{SymbolCosts, TableLocalCost},
+};
+
+/// Cost of the cheapest term of a class, a lower bound on the cost of any term extractable
+/// from it with the costs of the e-graph.
+///
+/// Costs of symbols and literals are those of [`TableLocalCost`]. Being a [`LocalCost`], it can
+/// also direct a [`DirectedSaturator`](crate::rewriting::egraph::saturation::directed_saturator::DirectedSaturator).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct CostLowerBound(i32);
+
+impl CostLowerBound {
+    /// Returns the bound as a number.
+    pub fn value(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl Sum for CostLowerBound {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        Self(iter.map(|x| x.0).sum())
+    }
+}
+
+impl Add for CostLowerBound {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self(self.0 + other.0)
+    }
+}
+
+impl Sub for CostLowerBound {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self(self.0 - other.0)
+    }
+}
+
+impl LocalCost for CostLowerBound {
+    fn symbol_cost(symbol_id: SymbolId, costs: Option<&SymbolCosts>) -> Self {
+        Self(TableLocalCost::symbol_cost(symbol_id, costs).value())
+    }
+
+    fn literal_cost(literal: &Literal, costs: Option<&SymbolCosts>) -> Self {
+        Self(TableLocalCost::literal_cost(literal, costs).value())
+    }
+
+    fn to_string(&self) -> String {
+        format!("Cost bound: {}", self.0)
+    }
+
+    fn lower_bound(&self) -> Option<usize> {
+        Some(self.value())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use crate::language::{Language, expression::Literal};
+    use crate::macros::rules;
+    use crate::rewriting::egraph::{
+        Analysis, DynEGraph, EGraph,
+        class::{table_local_cost::SymbolCosts, term_size::TermSize},
+        extraction::CostTable,
+        matching::bottom_up::BottomUpMatcher,
+        saturation::{
+            SaturationConfig, SaturationStopReason, Saturator, SimpleSaturator,
+            directed_saturator::DirectedSaturator,
+        },
+    };
+
+    use super::CostLowerBound;
+
+    #[test]
+    fn bounds_are_propagated_to_parents() {
+        let lang = Language::simple_math();
+        let (mut egraph, root) = EGraph::<CostLowerBound>::from_expression_with_id(
+            lang.parse_no_vars("(sin (+ (* 3 1) 0))").unwrap(),
+        );
+        assert_eq!(egraph.class(root).analysis().value(), 6);
+
+        let product = egraph.add_expression(lang.parse_no_vars("(* 3 1)").unwrap());
+        let three = egraph.find_literal(Literal::Int(3)).unwrap();
+        egraph.merge_classes(product, three);
+        assert_eq!(egraph.class(root).analysis().value(), 4);
+    }
+
+    #[test]
+    fn composites_remake_all_parts() {
+        let lang = Language::simple_math();
+        let (mut egraph, root) = EGraph::<(TermSize, CostLowerBound)>::from_expression_with_id(
+            lang.parse_no_vars("(sin (+ (* 3 1) 0))").unwrap(),
+        );
+        let product = egraph.add_expression(lang.parse_no_vars("(* 3 1)").unwrap());
+        let three = egraph.find_literal(Literal::Int(3)).unwrap();
+        egraph.merge_classes(product, three);
+
+        let analysis = egraph.class(root).analysis();
+        assert_eq!(analysis.cost_lower_bound(), Some(4));
+        assert_eq!(analysis.term_size().unwrap().size, 4);
+    }
+
+    #[test]
+    fn bounds_follow_egraph_costs() {
+        let lang = Language::simple_math();
+        let table = CostTable {
+            literal_cost: 2,
+            per_symbol_cost: HashMap::from([(String::from("*"), 5), (String::from("<<"), 1)]),
+            default: 3,
+        };
+        let rules = rules!(lang; "(* $0 2)" => "(<< $0 1)");
+        let mut egraph = EGraph::<CostLowerBound>::default()
+            .with_costs(Arc::new(SymbolCosts::new(&table, &lang)));
+        let root = egraph.add_expression(lang.parse_no_vars("(+ (* 3 2) 1)").unwrap());
+        assert_eq!(egraph.class(root).analysis().value(), 3 + 5 + 2 + 2 + 2);
+
+        SimpleSaturator::new(Box::new(BottomUpMatcher)).saturate(
+            &mut egraph,
+            &rules,
+            &SaturationConfig::default(),
+        );
+        assert_eq!(egraph.class(root).analysis().value(), 3 + 1 + 2 + 2 + 2);
+    }
+
+    #[test]
+    fn directed_saturation_skips_expensive_terms() {
+        let lang = Language::simple_math();
+        let rules = rules!(lang;
+            "(* $0 1)" => "(* (* $0 1) 1)",
+            "(* $0 2)" => "(<< $0 1)",
+        );
+        let saturate = |max_cost_increase| {
+            let mut egraph = EGraph::<CostLowerBound>::from_expression(
+                lang.parse_no_vars("(* (* 3 1) 2)").unwrap(),
+            );
+            let config = SaturationConfig {
+                max_cost_increase,
+                ..Default::default()
+            };
+            let report = DirectedSaturator::new(Box::new(BottomUpMatcher)).saturate(
+                &mut egraph,
+                &rules,
+                &config,
+            );
+            (report.stop_reason, egraph.actual_node_count())
+        };
+
+        // (* (* 3 1) 1) is added to the class of (* 3 1), which costs less
+        assert_eq!(saturate(None), (Some(SaturationStopReason::Saturated), 7));
+        // Only (<< (* 3 1) 1) costs no more than the class it is added to
+        assert_eq!(
+            saturate(Some(0)),
+            (Some(SaturationStopReason::Saturated), 6)
+        );
+    }
+}
@@ -1,9 +1,9 @@
-use crate::rewriting::egraph::DynEGraph;
+use serde::{Deserialize, Serialize};
 
-use super::{Analysis, EGraph};
+use super::{Analysis, analysis::AnalysisGraph};
 
 /// A simple analysis class used only for testing
-#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct LiteralCountAnalysis {
     count: usize,
 }
@@ -15,7 +15,7 @@ impl LiteralCountAnalysis {
 }
 
 impl Analysis for LiteralCountAnalysis {
-    fn make(egraph: &EGraph<Self>, node_id: super::NodeId) -> Self {
+    fn make(egraph: &dyn AnalysisGraph<Self>, node_id: super::NodeId) -> Self {
         let node = egraph.node(node_id);
         Self {
             count: if node.try_as_symbol().is_none() { 1 } else { 0 },
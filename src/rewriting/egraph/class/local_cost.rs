@@ -8,48 +8,58 @@ use crate::{
         expression::{Expression, Literal},
         symbol::SymbolId,
     },
-    rewriting::egraph::{DynEGraph, Node},
+    rewriting::egraph::Node,
 };
 
-use super::{Analysis, EGraph, NodeId};
+use super::{Analysis, NodeId, analysis::AnalysisGraph, table_local_cost::SymbolCosts};
 
+/// Analysis whose data is the cost of the cheapest term of a class, a sum of costs of its
+/// symbols and literals. Costs can depend on the costs of the e-graph,
+/// see [`EGraph::with_costs`](crate::rewriting::egraph::EGraph::with_costs).
 pub trait LocalCost:
     Default + Clone + Ord + Sum + Add<Output = Self> + Sub<Output = Self> + Ord
 {
-    fn symbol_cost(symbol_id: SymbolId) -> Self;
-    fn literal_cost(literal: &Literal) -> Self;
+    fn symbol_cost(symbol_id: SymbolId, costs: Option<&SymbolCosts>) -> Self;
+    fn literal_cost(literal: &Literal, costs: Option<&SymbolCosts>) -> Self;
 
-    fn expression_cost(expression: &Expression) -> Self {
+    fn expression_cost(expression: &Expression, costs: Option<&SymbolCosts>) -> Self {
         match expression {
-            Expression::Literal(literal) => Self::literal_cost(literal),
+            Expression::Literal(literal) => Self::literal_cost(literal, costs),
             Expression::Symbol(symbol) => {
                 symbol
                     .children
                     .iter()
-                    .map(|child| Self::expression_cost(child))
+                    .map(|child| Self::expression_cost(child, costs))
                     .sum::<Self>()
-                    + Self::symbol_cost(symbol.id)
+                    + Self::symbol_cost(symbol.id, costs)
             }
             Expression::Variable(_) => Self::default(),
         }
     }
 
     fn to_string(&self) -> String;
+
+    /// Returns the cost as a lower bound on the cost of terms extractable from a class,
+    /// if the cost is kept exact when classes of children merge, see [`Analysis::propagates`].
+    /// By default costs are not propagated and can be stale.
+    fn lower_bound(&self) -> Option<usize> {
+        None
+    }
 }
 
 impl<LC> Analysis for LC
 where
     LC: LocalCost,
 {
-    fn make(egraph: &EGraph<Self>, node_id: NodeId) -> Self {
+    fn make(egraph: &dyn AnalysisGraph<Self>, node_id: NodeId) -> Self {
         match egraph.node(node_id) {
-            Node::Literal(literal) => Self::literal_cost(literal),
+            Node::Literal(literal) => Self::literal_cost(literal, egraph.costs()),
             Node::Symbol(symbol) => {
-                Self::symbol_cost(symbol.id)
+                Self::symbol_cost(symbol.id, egraph.costs())
                     + egraph
                         .node(node_id)
                         .iter_children()
-                        .map(|child_id| egraph.class(*child_id).analysis())
+                        .map(|child_id| egraph.analysis(*child_id))
                         .cloned()
                         .sum::<Self>()
             }
@@ -63,4 +73,12 @@ where
     fn to_string(&self) -> Option<String> {
         Some(self.to_string())
     }
+
+    fn cost_lower_bound(&self) -> Option<usize> {
+        self.lower_bound()
+    }
+
+    fn propagates(&self, previous: &Self) -> bool {
+        self.lower_bound().is_some() && self != previous
+    }
 }
@@ -4,14 +4,19 @@
 //! in an e-graph, along with analysis computation mechanisms.
 
 pub mod analysis;
+pub mod cost_lower_bound;
 pub mod literal_count;
 pub mod local_cost;
 pub mod simple_math_local_cost;
+pub mod size_local_cost;
+pub mod table_local_cost;
+pub mod term_size;
 
-use std::collections::HashSet;
 use std::collections::hash_set;
 
-use super::{Analysis, EGraph, NodeId};
+use serde::{Deserialize, Serialize};
+
+use super::{Analysis, EGraph, FixedHashSet, NodeId};
 
 /// An equivalence class in an e-graph.
 ///
@@ -21,10 +26,10 @@ use super::{Analysis, EGraph, NodeId};
 /// # Type Parameters
 ///
 /// * `A` - The analysis type for computing metadata about the class
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct Class<A: Analysis> {
-    nodes_ids: HashSet<NodeId>,
-    parents_ids: HashSet<NodeId>,
+    nodes_ids: FixedHashSet<NodeId>,
+    parents_ids: FixedHashSet<NodeId>,
     analysis: A,
 }
 
@@ -37,8 +42,8 @@ impl<A: Analysis> Class<A> {
     /// * `node_id` - The ID of the node to create a class for
     pub fn from_node(egraph: &EGraph<A>, node_id: NodeId) -> Self {
         Self {
-            nodes_ids: HashSet::from([node_id]),
-            parents_ids: HashSet::new(),
+            nodes_ids: FixedHashSet::from_iter([node_id]),
+            parents_ids: FixedHashSet::default(),
             analysis: A::make(egraph, node_id),
         }
     }
@@ -58,6 +63,10 @@ impl<A: Analysis> Class<A> {
     pub fn analysis(&self) -> &A {
         &self.analysis
     }
+
+    pub(super) fn set_analysis(&mut self, analysis: A) {
+        self.analysis = analysis;
+    }
 }
 
 /// Trait for dynamically accessing class data.
@@ -66,16 +75,16 @@ pub trait DynClass {
     fn iter_nodes(&self) -> hash_set::Iter<'_, usize>;
 
     /// Returns a reference to the set of node IDs.
-    fn nodes_ids(&self) -> &HashSet<NodeId>;
+    fn nodes_ids(&self) -> &FixedHashSet<NodeId>;
 
     /// Returns a mutable reference to the set of node IDs.
-    fn nodes_ids_mut(&mut self) -> &mut HashSet<NodeId>;
+    fn nodes_ids_mut(&mut self) -> &mut FixedHashSet<NodeId>;
 
     /// Returns a reference to the set of parent node IDs.
-    fn parents_ids(&self) -> &HashSet<NodeId>;
+    fn parents_ids(&self) -> &FixedHashSet<NodeId>;
 
     /// Returns a mutable reference to the set of parent node IDs.
-    fn parents_ids_mut(&mut self) -> &mut HashSet<NodeId>;
+    fn parents_ids_mut(&mut self) -> &mut FixedHashSet<NodeId>;
 }
 
 impl<A: Analysis> DynClass for Class<A> {
@@ -83,19 +92,19 @@ impl<A: Analysis> DynClass for Class<A> {
         self.nodes_ids.iter()
     }
 
-    fn nodes_ids(&self) -> &HashSet<NodeId> {
+    fn nodes_ids(&self) -> &FixedHashSet<NodeId> {
         &self.nodes_ids
     }
 
-    fn nodes_ids_mut(&mut self) -> &mut HashSet<NodeId> {
+    fn nodes_ids_mut(&mut self) -> &mut FixedHashSet<NodeId> {
         &mut self.nodes_ids
     }
 
-    fn parents_ids(&self) -> &HashSet<NodeId> {
+    fn parents_ids(&self) -> &FixedHashSet<NodeId> {
         &self.parents_ids
     }
 
-    fn parents_ids_mut(&mut self) -> &mut HashSet<NodeId> {
+    fn parents_ids_mut(&mut self) -> &mut FixedHashSet<NodeId> {
         &mut self.parents_ids
     }
 }
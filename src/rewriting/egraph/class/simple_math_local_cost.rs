@@ -3,11 +3,13 @@ use std::{
     ops::{Add, Sub},
 };
 
+use serde::{Deserialize, Serialize};
+
 use crate::language::{Language, expression::Literal, symbol::SymbolId};
 
-use super::local_cost::LocalCost;
+use super::{local_cost::LocalCost, table_local_cost::SymbolCosts};
 
-#[derive(Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Default, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct SimpleMathLocalCost(i32);
 
 impl Sum for SimpleMathLocalCost {
@@ -33,7 +35,7 @@ impl Sub for SimpleMathLocalCost {
 }
 
 impl LocalCost for SimpleMathLocalCost {
-    fn symbol_cost(symbol_id: SymbolId) -> Self {
+    fn symbol_cost(symbol_id: SymbolId, _: Option<&SymbolCosts>) -> Self {
         let lang = Language::simple_math();
         Self(match lang.get_symbol(symbol_id) {
             "+" => 1,
@@ -46,7 +48,7 @@ impl LocalCost for SimpleMathLocalCost {
         })
     }
 
-    fn literal_cost(_: &Literal) -> Self {
+    fn literal_cost(_: &Literal, _: Option<&SymbolCosts>) -> Self {
         Self(1)
     }
 
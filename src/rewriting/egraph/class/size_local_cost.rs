@@ -0,0 +1,74 @@
+use std::{
+    iter::Sum,
+    ops::{Add, Sub},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::language::{expression::Literal, symbol::SymbolId};
+
+use super::{local_cost::LocalCost, table_local_cost::SymbolCosts};
+
+/// Local cost of one per symbol and literal, i.e. the size of the cheapest term of a class.
+///
+/// Unlike [`SimpleMathLocalCost`](super::simple_math_local_cost::SimpleMathLocalCost),
+/// it does not depend on the language.
+#[derive(Default, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct SizeLocalCost(i32);
+
+impl Sum for SizeLocalCost {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        Self(iter.map(|x| x.0).sum())
+    }
+}
+
+impl Add for SizeLocalCost {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self(self.0 + other.0)
+    }
+}
+
+impl Sub for SizeLocalCost {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self(self.0 - other.0)
+    }
+}
+
+impl LocalCost for SizeLocalCost {
+    fn symbol_cost(_: SymbolId, _: Option<&SymbolCosts>) -> Self {
+        Self(1)
+    }
+
+    fn literal_cost(_: &Literal, _: Option<&SymbolCosts>) -> Self {
+        Self(1)
+    }
+
+    fn to_string(&self) -> String {
+        format!("Size: {}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::language::{Language, expression::Literal};
+    use crate::rewriting::egraph::{DynEGraph, EGraph};
+
+    use super::SizeLocalCost;
+
+    #[test]
+    fn cost_is_size_of_cheapest_term() {
+        let lang = Language::simple_math();
+        let (mut egraph, root) = EGraph::<SizeLocalCost>::from_expression_with_id(
+            lang.parse_no_vars("(* (+ 1 2) 3)").unwrap(),
+        );
+        assert_eq!(egraph.class(root).analysis().0, 5);
+
+        let three = egraph.find_literal(Literal::Int(3)).unwrap();
+        egraph.merge_classes(three, root);
+        assert_eq!(egraph.class(root).analysis().0, 1);
+    }
+}
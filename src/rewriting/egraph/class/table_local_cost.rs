@@ -0,0 +1,182 @@
+//! Local costs given by a [`CostTable`].
+//!
+//! Costs of a [`LocalCost`] only depend on IDs of symbols, so [`TableLocalCost`] looks them up
+//! in the [`SymbolCosts`] of the e-graph, see [`EGraph::with_costs`]. This lets
+//! the [`DirectedSaturator`](crate::rewriting::egraph::saturation::directed_saturator::DirectedSaturator)
+//! use the costs of any system loaded from JSON, see
+//! [`TermRewritingSystem::costs`](crate::rewriting::system::TermRewritingSystem::costs).
+//!
+//! [`EGraph::with_costs`]: crate::rewriting::egraph::EGraph::with_costs
+
+use std::{
+    iter::Sum,
+    ops::{Add, Sub},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    language::{Language, expression::Literal, symbol::SymbolId},
+    rewriting::egraph::extraction::CostTable,
+};
+
+use super::local_cost::LocalCost;
+
+/// Costs of a [`CostTable`] indexed by symbol IDs of a language.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SymbolCosts {
+    literal_cost: i32,
+    symbol_costs: Vec<i32>,
+    default: i32,
+}
+
+impl SymbolCosts {
+    /// Looks up the costs of `table` for all symbols of `language`.
+    /// Symbols with IDs outside of `language` cost the default of `table`.
+    pub fn new(table: &CostTable, language: &Language) -> Self {
+        Self {
+            literal_cost: table.literal_cost as i32,
+            symbol_costs: (0..language.symbol_count())
+                .map(|id| table.symbol_cost(language.get_symbol(id)) as i32)
+                .collect(),
+            default: table.default as i32,
+        }
+    }
+
+    /// Returns the cost of the symbol with ID `symbol_id`.
+    pub fn symbol_cost(&self, symbol_id: SymbolId) -> i32 {
+        self.symbol_costs
+            .get(symbol_id)
+            .copied()
+            .unwrap_or(self.default)
+    }
+
+    /// Returns the cost of every literal.
+    pub fn literal_cost(&self) -> i32 {
+        self.literal_cost
+    }
+}
+
+/// Local cost given by the [`SymbolCosts`] of the e-graph,
+/// i.e. the cost of the cheapest term of a class according to its table.
+///
+/// In e-graphs without costs every symbol and literal costs one, as in
+/// [`SizeLocalCost`](super::size_local_cost::SizeLocalCost).
+#[derive(Default, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct TableLocalCost(i32);
+
+impl TableLocalCost {
+    /// Returns the cost as a number.
+    pub fn value(&self) -> i32 {
+        self.0
+    }
+}
+
+impl Sum for TableLocalCost {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        Self(iter.map(|x| x.0).sum())
+    }
+}
+
+impl Add for TableLocalCost {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self(self.0 + other.0)
+    }
+}
+
+impl Sub for TableLocalCost {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self(self.0 - other.0)
+    }
+}
+
+impl LocalCost for TableLocalCost {
+    fn symbol_cost(symbol_id: SymbolId, costs: Option<&SymbolCosts>) -> Self {
+        Self(costs.map_or(1, |costs| costs.symbol_cost(symbol_id)))
+    }
+
+    fn literal_cost(_: &Literal, costs: Option<&SymbolCosts>) -> Self {
+        Self(costs.map_or(1, SymbolCosts::literal_cost))
+    }
+
+    fn to_string(&self) -> String {
+        format!("Cost: {}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use crate::language::{Language, expression::Literal};
+    use crate::macros::rules;
+    use crate::rewriting::egraph::{
+        DynEGraph, EGraph,
+        extraction::CostTable,
+        matching::bottom_up::BottomUpMatcher,
+        saturation::{SaturationConfig, Saturator, directed_saturator::DirectedSaturator},
+    };
+
+    use super::{SymbolCosts, TableLocalCost};
+
+    fn costs(lang: &Language) -> Arc<SymbolCosts> {
+        let table = CostTable {
+            literal_cost: 2,
+            per_symbol_cost: HashMap::from([(String::from("*"), 4), (String::from("<<"), 1)]),
+            default: 3,
+        };
+        Arc::new(SymbolCosts::new(&table, lang))
+    }
+
+    #[test]
+    fn costs_are_looked_up_in_egraph_costs() {
+        let lang = Language::simple_math();
+        let expression = lang.parse_no_vars("(+ (* 3 2) 1)").unwrap();
+
+        let (egraph, root) = EGraph::<TableLocalCost>::from_expression_with_id(expression.clone());
+        assert_eq!(egraph.class(root).analysis().value(), 5);
+
+        let mut egraph = EGraph::<TableLocalCost>::default().with_costs(costs(&lang));
+        let root = egraph.add_expression(expression);
+        assert_eq!(egraph.class(root).analysis().value(), 3 + 4 + 2 + 2 + 2);
+
+        let one = egraph.find_literal(Literal::Int(1)).unwrap();
+        egraph.merge_classes(one, root);
+        assert_eq!(egraph.class(root).analysis().value(), 2);
+
+        // Other e-graphs are not affected
+        let (egraph, root) = EGraph::<TableLocalCost>::from_expression_with_id(
+            lang.parse_no_vars("(* 3 2)").unwrap(),
+        );
+        assert_eq!(egraph.class(root).analysis().value(), 3);
+    }
+
+    #[test]
+    fn directs_saturation_by_table_costs() {
+        let lang = Language::simple_math();
+        let rules = rules!(lang;
+            "(* $0 2)" => "(<< $0 1)",
+            "(* $0 1)" => "$0",
+        );
+        let mut egraph = EGraph::<TableLocalCost>::default().with_costs(costs(&lang));
+        let root = egraph.add_expression(lang.parse_no_vars("(* (* 5 1) 2)").unwrap());
+
+        DirectedSaturator::new(Box::new(BottomUpMatcher)).saturate(
+            &mut egraph,
+            &rules,
+            &SaturationConfig::default(),
+        );
+        // (<< 5 1)
+        assert_eq!(
+            egraph
+                .class(egraph.canonical_class(root))
+                .analysis()
+                .value(),
+            1 + 2 + 2
+        );
+    }
+}
@@ -0,0 +1,92 @@
+//! Sizes and depths of the smallest terms represented by classes.
+
+use serde::{Deserialize, Serialize};
+
+use crate::rewriting::egraph::Node;
+
+use super::{Analysis, NodeId, analysis::AnalysisGraph};
+
+/// Size (number of nodes) and depth of the terms represented by a class.
+///
+/// As an analysis, both are minimal over the represented terms,
+/// though they may be attained by different terms.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TermSize {
+    pub size: usize,
+    pub depth: usize,
+}
+
+impl TermSize {
+    /// Size and depth of a leaf.
+    pub fn leaf() -> Self {
+        Self { size: 1, depth: 1 }
+    }
+
+    /// Size and depth of a node with children of sizes and depths `children`.
+    pub fn node(children: impl IntoIterator<Item = TermSize>) -> Self {
+        children.into_iter().fold(Self::leaf(), |term, child| Self {
+            size: term.size + child.size,
+            depth: term.depth.max(child.depth + 1),
+        })
+    }
+}
+
+impl Analysis for TermSize {
+    fn make(egraph: &dyn AnalysisGraph<Self>, node_id: NodeId) -> Self {
+        match egraph.node(node_id) {
+            Node::Literal(_) => Self::leaf(),
+            Node::Symbol(symbol) => {
+                Self::node(symbol.children.iter().map(|&child| *egraph.analysis(child)))
+            }
+        }
+    }
+
+    fn merge(a: Self, b: Self) -> Self {
+        Self {
+            size: a.size.min(b.size),
+            depth: a.depth.min(b.depth),
+        }
+    }
+
+    fn to_string(&self) -> Option<String> {
+        Some(format!("size {}, depth {}", self.size, self.depth))
+    }
+
+    fn term_size(&self) -> Option<TermSize> {
+        Some(*self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TermSize;
+    use crate::{
+        language::Language,
+        rewriting::egraph::{DynEGraph, EGraph},
+    };
+
+    #[test]
+    fn minimal_over_merged_classes() {
+        let lang = Language::simple_math();
+        let mut egraph = EGraph::<TermSize>::default();
+        let deep = egraph.add_expression(lang.parse_no_vars("(+ (* 1 2) (* 3 4))").unwrap());
+        assert_eq!(
+            *egraph.class(deep).analysis(),
+            TermSize { size: 7, depth: 3 }
+        );
+
+        let shallow = egraph.add_expression(lang.parse_no_vars("(sin (sin (sin 0)))").unwrap());
+        egraph.merge_classes(deep, shallow);
+        assert_eq!(
+            *egraph.class(deep).analysis(),
+            TermSize { size: 4, depth: 3 }
+        );
+
+        let small = egraph.add_expression(lang.parse_no_vars("(+ 0 0)").unwrap());
+        egraph.merge_classes(deep, small);
+        assert_eq!(
+            *egraph.class(deep).analysis(),
+            TermSize { size: 3, depth: 2 }
+        );
+    }
+}
@@ -0,0 +1,220 @@
+//! Compaction of e-graphs.
+//!
+//! Nodes removed from classes as duplicates after merges stay in the node map, and their IDs
+//! stay in the parent sets of classes and in the union-find, so the memory used by an e-graph
+//! grows with all nodes ever added rather than with the live ones, see
+//! [`total_node_count`](super::DynEGraph::total_node_count) and
+//! [`actual_node_count`](super::DynEGraph::actual_node_count).
+//! [`EGraph::compact`] drops such nodes and renumbers the live ones, so that long saturations
+//! can periodically shrink their e-graphs.
+
+use std::fmt;
+
+use itertools::Itertools;
+
+use crate::union_find::UnionFind;
+
+use super::{Analysis, ClassId, EGraph, FixedHashSet, NodeId, class::DynClass};
+
+/// Mapping of IDs of an e-graph from before [`EGraph::compact`] to the ones after it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CompactionRemap {
+    /// New IDs of nodes, indexed by the old ones, `None` for dropped nodes
+    nodes: Vec<Option<NodeId>>,
+    /// New canonical IDs of classes, indexed by the old ones
+    classes: Vec<ClassId>,
+}
+
+impl CompactionRemap {
+    /// Returns the new ID of the node with old ID `node_id`, or `None` if it was dropped
+    /// or did not exist.
+    pub fn node(&self, node_id: NodeId) -> Option<NodeId> {
+        self.nodes.get(node_id).copied().flatten()
+    }
+
+    /// Returns the new canonical ID of the class with old ID `class_id`, which does not
+    /// have to be canonical, or `None` if it did not exist.
+    pub fn class(&self, class_id: ClassId) -> Option<ClassId> {
+        self.classes.get(class_id).copied()
+    }
+}
+
+/// Reason why [`EGraph::compact`] failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompactionError {
+    /// The e-graph has this many open snapshots, whose records refer to the old IDs
+    OpenSnapshots(usize),
+}
+
+impl fmt::Display for CompactionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompactionError::OpenSnapshots(count) => write!(
+                f,
+                "E-graphs with open snapshots cannot be compacted, {count} are open"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CompactionError {}
+
+impl<A: Analysis> EGraph<A> {
+    /// Drops nodes which are no longer in any class, renumbers the remaining nodes and classes
+    /// in the order of their IDs and shrinks the containers of the e-graph.
+    ///
+    /// Canonical classes keep their nodes, parents, analysis data, tags and frozen status.
+    /// As the union-find only keeps the live nodes, non-canonical class IDs can only be
+    /// translated with the returned remap.
+    ///
+    /// Returns [`CompactionError::OpenSnapshots`] without changing the e-graph if there are
+    /// open snapshots.
+    pub fn compact(&mut self) -> Result<CompactionRemap, CompactionError> {
+        if self.open_snapshots() > 0 {
+            return Err(CompactionError::OpenSnapshots(self.open_snapshots()));
+        }
+
+        let old_size = self.union_find.size();
+        let live: Vec<NodeId> = self
+            .classes
+            .values()
+            .flat_map(|class| class.iter_nodes().copied())
+            .sorted()
+            .collect();
+
+        let mut nodes = vec![None; old_size];
+        for (new_id, &old_id) in live.iter().enumerate() {
+            nodes[old_id] = Some(new_id);
+        }
+        // Every class is represented by its live node with the smallest ID
+        let mut roots = vec![0; old_size];
+        for (&class_id, class) in &self.classes {
+            if let Some(root) = class
+                .iter_nodes()
+                .filter_map(|&node_id| nodes[node_id])
+                .min()
+            {
+                roots[class_id] = root;
+            }
+        }
+        let classes: Vec<ClassId> = (0..old_size)
+            .map(|id| roots[self.union_find.find(id)])
+            .collect();
+
+        let mut union_find = UnionFind::with_size(live.len());
+        for (node_id, &old_id) in live.iter().enumerate() {
+            let root = classes[old_id];
+            if node_id != root {
+                union_find.union(node_id, root);
+            }
+        }
+
+        let remap_nodes = |ids: &FixedHashSet<NodeId>| -> FixedHashSet<NodeId> {
+            ids.iter().filter_map(|&node_id| nodes[node_id]).collect()
+        };
+        self.classes = std::mem::take(&mut self.classes)
+            .into_iter()
+            .map(|(class_id, mut class)| {
+                *class.nodes_ids_mut() = remap_nodes(class.nodes_ids());
+                *class.parents_ids_mut() = remap_nodes(class.parents_ids());
+                (roots[class_id], class)
+            })
+            .collect();
+        self.nodes = live
+            .iter()
+            .enumerate()
+            .filter_map(|(node_id, old_id)| {
+                let mut node = self.nodes.remove(old_id)?;
+                for child in node.iter_mut_children() {
+                    *child = classes[*child];
+                }
+                Some((node_id, node))
+            })
+            .collect();
+        self.frozen = self
+            .frozen
+            .iter()
+            .map(|&class_id| classes[class_id])
+            .collect();
+        self.tags = std::mem::take(&mut self.tags)
+            .into_iter()
+            .map(|(class_id, tags)| (classes[class_id], tags))
+            .collect();
+        self.union_find = union_find;
+        self.rebuild_hashcons();
+
+        Ok(CompactionRemap { nodes, classes })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CompactionError;
+    use crate::{
+        language::{Language, expression::Literal},
+        rewriting::egraph::{DynEGraph, EGraph, class::term_size::TermSize},
+    };
+
+    #[test]
+    fn drops_orphaned_nodes() {
+        let lang = Language::simple_math();
+        let mut egraph = EGraph::<TermSize>::default();
+        let root = egraph.add_expression(lang.parse_no_vars("(+ (sin 1) (sin 2))").unwrap());
+        let sin_1 = egraph.add_expression(lang.parse_no_vars("(sin 1)").unwrap());
+        let one = egraph.find_literal(Literal::Int(1)).unwrap();
+        let two = egraph.find_literal(Literal::Int(2)).unwrap();
+        egraph.tag_class(one, "one");
+        egraph.freeze_class(root);
+        egraph.merge_classes(one, two);
+        assert!(egraph.total_node_count() > egraph.actual_node_count());
+
+        let before = egraph.clone();
+        let remap = egraph.compact().unwrap();
+        assert_eq!(egraph.total_node_count(), egraph.actual_node_count());
+        assert_eq!(egraph.actual_node_count(), before.actual_node_count());
+        assert_eq!(egraph.class_count(), before.class_count());
+        assert!(egraph.entails(&before) && before.entails(&egraph));
+
+        let new_root = remap.class(root).unwrap();
+        assert_eq!(
+            remap.class(sin_1),
+            remap.class(before.canonical_class(sin_1))
+        );
+        assert_eq!(remap.class(one), egraph.find_literal(Literal::Int(2)));
+        assert!(egraph.has_tag(remap.class(two).unwrap(), "one"));
+        assert!(egraph.is_frozen(new_root));
+        assert_eq!(
+            egraph.class(new_root).analysis().size,
+            before.class(root).analysis().size
+        );
+        for class_id in 0..before.total_node_count() {
+            if let Some(node_id) = remap.node(class_id) {
+                assert_eq!(egraph.node(node_id), &{
+                    let mut node = before.node(class_id).clone();
+                    node.iter_mut_children()
+                        .for_each(|child| *child = remap.class(*child).unwrap());
+                    node
+                });
+            }
+        }
+
+        let new = egraph.add_expression(lang.parse_no_vars("(sin 3)").unwrap());
+        assert_eq!(new, egraph.total_node_count() - 1);
+    }
+
+    #[test]
+    fn refuses_open_snapshots() {
+        let lang = Language::simple_math();
+        let mut egraph = EGraph::<TermSize>::default();
+        egraph.add_expression(lang.parse_no_vars("(sin 1)").unwrap());
+        let snapshot = egraph.snapshot();
+        egraph.add_expression(lang.parse_no_vars("(sin 2)").unwrap());
+        let before = egraph.clone();
+
+        assert_eq!(egraph.compact(), Err(CompactionError::OpenSnapshots(1)));
+        assert!(egraph.entails(&before) && before.entails(&egraph));
+
+        egraph.commit(snapshot);
+        assert!(egraph.compact().is_ok());
+    }
+}
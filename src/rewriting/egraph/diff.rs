@@ -0,0 +1,368 @@
+//! Differences between e-graphs.
+//!
+//! E-graphs built by different saturators from the same expression have different IDs
+//! even when they represent the same terms, so they are compared up to renaming of classes.
+//! A class of one e-graph is related to a class of the other one if both represent
+//! a common term, and a node has a counterpart if the other e-graph has a node with
+//! the same symbol or literal whose children are related to its own.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+use itertools::Itertools;
+
+use crate::language::{Language, expression::Literal, symbol::SymbolId};
+
+use super::{Analysis, ClassId, DynEGraph, EGraph, Node, NodeId};
+
+const LEFT_COLOR: &str = "lightcoral";
+const RIGHT_COLOR: &str = "lightgreen";
+const MERGED_COLOR: &str = "lightgoldenrod";
+
+/// Differences between two e-graphs, see [`diff`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EGraphDiff {
+    /// Nodes of the left e-graph without counterparts in the right one
+    pub nodes_only_in_left: Vec<NodeId>,
+    /// Nodes of the right e-graph without counterparts in the left one
+    pub nodes_only_in_right: Vec<NodeId>,
+    /// Classes of the left e-graph representing no term of the right one
+    pub classes_only_in_left: Vec<ClassId>,
+    /// Classes of the right e-graph representing no term of the left one
+    pub classes_only_in_right: Vec<ClassId>,
+    /// Classes of the left e-graph together with the several classes of the right one
+    /// they are related to, which are merged only in the left e-graph
+    pub merged_only_in_left: Vec<(ClassId, Vec<ClassId>)>,
+    /// Classes of the right e-graph together with the several classes of the left one
+    /// they are related to, which are merged only in the right e-graph
+    pub merged_only_in_right: Vec<(ClassId, Vec<ClassId>)>,
+}
+
+impl EGraphDiff {
+    /// `true` if the e-graphs are equal up to renaming of classes.
+    pub fn is_empty(&self) -> bool {
+        self.nodes_only_in_left.is_empty()
+            && self.nodes_only_in_right.is_empty()
+            && self.classes_only_in_left.is_empty()
+            && self.classes_only_in_right.is_empty()
+            && self.merged_only_in_left.is_empty()
+            && self.merged_only_in_right.is_empty()
+    }
+
+    /// Renders both e-graphs side by side in the DOT format, coloring nodes and classes
+    /// present in only one of them and classes merged in only one of them.
+    /// `left` and `right` should be the e-graphs the differences were computed for.
+    pub fn dot<A: Analysis, B: Analysis>(
+        &self,
+        left: &EGraph<A>,
+        right: &EGraph<B>,
+        language: &Language,
+    ) -> String {
+        let mut out = String::new();
+        writeln!(&mut out, "digraph diff {{").unwrap();
+        writeln!(&mut out, "  compound=true;").unwrap();
+        writeln!(
+            &mut out,
+            "  node [shape=\"box\", style=filled, fillcolor=white];"
+        )
+        .unwrap();
+
+        let merged = |merged: &[(ClassId, Vec<ClassId>)]| -> HashSet<ClassId> {
+            merged.iter().map(|(class_id, _)| *class_id).collect()
+        };
+        write_side(
+            &mut out,
+            left,
+            language,
+            Side {
+                name: "left",
+                only_color: LEFT_COLOR,
+                only_nodes: self.nodes_only_in_left.iter().copied().collect(),
+                only_classes: self.classes_only_in_left.iter().copied().collect(),
+                merged_classes: merged(&self.merged_only_in_left),
+            },
+        );
+        write_side(
+            &mut out,
+            right,
+            language,
+            Side {
+                name: "right",
+                only_color: RIGHT_COLOR,
+                only_nodes: self.nodes_only_in_right.iter().copied().collect(),
+                only_classes: self.classes_only_in_right.iter().copied().collect(),
+                merged_classes: merged(&self.merged_only_in_right),
+            },
+        );
+
+        writeln!(&mut out, "}}").unwrap();
+        out
+    }
+}
+
+/// Compares `left` and `right` up to renaming of classes, see the [module](self) documentation.
+/// Both e-graphs should use the same language.
+pub fn diff<A: Analysis, B: Analysis>(left: &EGraph<A>, right: &EGraph<B>) -> EGraphDiff {
+    let related = related_classes(left, right);
+    let right_related: HashSet<_> = related.iter().map(|&(l, r)| (r, l)).collect();
+
+    EGraphDiff {
+        nodes_only_in_left: unmatched_nodes(left, right, &related),
+        nodes_only_in_right: unmatched_nodes(right, left, &right_related),
+        classes_only_in_left: unrelated_classes(left, &related),
+        classes_only_in_right: unrelated_classes(right, &right_related),
+        merged_only_in_left: merged_classes(&related),
+        merged_only_in_right: merged_classes(&right_related),
+    }
+}
+
+/// Symbol or literal of a node, nodes can only correspond to nodes with the same head.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Head<'a> {
+    Literal(&'a Literal),
+    Symbol(SymbolId, usize),
+}
+
+fn head(node: &Node) -> Head<'_> {
+    match node {
+        Node::Literal(literal) => Head::Literal(literal),
+        Node::Symbol(symbol) => Head::Symbol(symbol.id, symbol.children.len()),
+    }
+}
+
+/// Canonical IDs of all classes of `egraph` with their nodes, sorted by the IDs.
+fn sorted_classes(egraph: &dyn DynEGraph) -> Vec<(ClassId, Vec<NodeId>)> {
+    egraph
+        .dyn_classes()
+        .into_iter()
+        .map(|(&class_id, class)| (class_id, class.iter_nodes().copied().sorted().collect()))
+        .sorted()
+        .collect()
+}
+
+/// Nodes of `egraph` grouped by their heads.
+fn nodes_by_head(egraph: &dyn DynEGraph) -> HashMap<Head<'_>, Vec<NodeId>> {
+    let mut by_head: HashMap<_, Vec<_>> = HashMap::new();
+    for (_, nodes) in sorted_classes(egraph) {
+        for node_id in nodes {
+            by_head
+                .entry(head(egraph.node(node_id)))
+                .or_default()
+                .push(node_id);
+        }
+    }
+    by_head
+}
+
+/// `true` if the nodes have related children, assuming that they have the same head.
+fn children_related(
+    left: &dyn DynEGraph,
+    left_node: NodeId,
+    right: &dyn DynEGraph,
+    right_node: NodeId,
+    related: &HashSet<(ClassId, ClassId)>,
+) -> bool {
+    left.node(left_node)
+        .iter_children()
+        .zip(right.node(right_node).iter_children())
+        .all(|(&l, &r)| related.contains(&(left.canonical_class(l), right.canonical_class(r))))
+}
+
+/// Pairs of canonical IDs of classes of `left` and `right` which represent a common term.
+fn related_classes(left: &dyn DynEGraph, right: &dyn DynEGraph) -> HashSet<(ClassId, ClassId)> {
+    let left_classes = sorted_classes(left);
+    let right_by_head = nodes_by_head(right);
+    let mut related = HashSet::new();
+
+    // Terms of height `n` are found in the `n`-th iteration
+    loop {
+        let mut changed = false;
+        for (left_class, left_nodes) in &left_classes {
+            for &left_node in left_nodes {
+                let Some(candidates) = right_by_head.get(&head(left.node(left_node))) else {
+                    continue;
+                };
+                for &right_node in candidates {
+                    let pair = (*left_class, right.containing_class(right_node));
+                    if !related.contains(&pair)
+                        && children_related(left, left_node, right, right_node, &related)
+                    {
+                        related.insert(pair);
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        if !changed {
+            return related;
+        }
+    }
+}
+
+/// Nodes of `egraph` without counterparts in `other`, given the related classes.
+fn unmatched_nodes(
+    egraph: &dyn DynEGraph,
+    other: &dyn DynEGraph,
+    related: &HashSet<(ClassId, ClassId)>,
+) -> Vec<NodeId> {
+    let other_by_head = nodes_by_head(other);
+    sorted_classes(egraph)
+        .into_iter()
+        .flat_map(|(_, nodes)| nodes)
+        .filter(|&node_id| {
+            let candidates = other_by_head.get(&head(egraph.node(node_id)));
+            !candidates.is_some_and(|candidates| {
+                candidates.iter().any(|&other_node| {
+                    children_related(egraph, node_id, other, other_node, related)
+                })
+            })
+        })
+        .collect()
+}
+
+fn unrelated_classes(
+    egraph: &dyn DynEGraph,
+    related: &HashSet<(ClassId, ClassId)>,
+) -> Vec<ClassId> {
+    let with_relatives: HashSet<_> = related.iter().map(|&(class_id, _)| class_id).collect();
+    sorted_classes(egraph)
+        .into_iter()
+        .map(|(class_id, _)| class_id)
+        .filter(|class_id| !with_relatives.contains(class_id))
+        .collect()
+}
+
+fn merged_classes(related: &HashSet<(ClassId, ClassId)>) -> Vec<(ClassId, Vec<ClassId>)> {
+    related
+        .iter()
+        .copied()
+        .into_group_map()
+        .into_iter()
+        .filter(|(_, relatives)| relatives.len() > 1)
+        .map(|(class_id, relatives)| (class_id, relatives.into_iter().sorted().collect()))
+        .sorted()
+        .collect()
+}
+
+/// One of the compared e-graphs with its differences, drawn as a cluster by [`write_side`].
+struct Side {
+    name: &'static str,
+    only_color: &'static str,
+    only_nodes: HashSet<NodeId>,
+    only_classes: HashSet<ClassId>,
+    merged_classes: HashSet<ClassId>,
+}
+
+fn write_side<A: Analysis>(out: &mut String, egraph: &EGraph<A>, language: &Language, side: Side) {
+    let Side { name, .. } = side;
+    writeln!(out, "  subgraph cluster_{name} {{").unwrap();
+    writeln!(out, "    label = \"{name}\";").unwrap();
+
+    let classes = sorted_classes(egraph);
+    for (class_id, nodes) in &classes {
+        writeln!(out, "    subgraph cluster_{name}_{class_id} {{").unwrap();
+        writeln!(out, "      label = \"Class {class_id}\";").unwrap();
+        if side.only_classes.contains(class_id) {
+            writeln!(out, "      style = filled;").unwrap();
+            writeln!(out, "      fillcolor = {:?};", side.only_color).unwrap();
+        } else if side.merged_classes.contains(class_id) {
+            writeln!(out, "      style = filled;").unwrap();
+            writeln!(out, "      fillcolor = {MERGED_COLOR:?};").unwrap();
+        }
+        for node_id in nodes {
+            let label = EGraph::<A>::node_label(egraph.node(*node_id), language);
+            let color = if side.only_nodes.contains(node_id) {
+                format!(", fillcolor={:?}", side.only_color)
+            } else {
+                String::new()
+            };
+            writeln!(out, "      {name}_{node_id} [label=\"{label}\"{color}];").unwrap();
+        }
+        writeln!(out, "    }}").unwrap();
+    }
+    writeln!(out, "  }}").unwrap();
+
+    let representatives: HashMap<_, _> = classes
+        .iter()
+        .map(|(class_id, nodes)| (*class_id, nodes[0]))
+        .collect();
+    for (_, nodes) in &classes {
+        for node_id in nodes {
+            for (index, child) in egraph.node(*node_id).iter_children().enumerate() {
+                let child = egraph.canonical_class(*child);
+                writeln!(
+                    out,
+                    "  {name}_{node_id} -> {name}_{} [lhead=cluster_{name}_{child}, taillabel=\"{index}\"];",
+                    representatives[&child]
+                )
+                .unwrap();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        language::{Language, expression::Literal},
+        rewriting::egraph::{DynEGraph, EGraph},
+    };
+
+    use super::diff;
+
+    #[test]
+    fn equal_up_to_renaming() {
+        let lang = Language::simple_math();
+        let mut left = EGraph::<()>::default();
+        left.add_expression(lang.parse_no_vars("(+ 1 2)").unwrap());
+        left.add_expression(lang.parse_no_vars("(* 3 4)").unwrap());
+        let mut right = EGraph::<()>::default();
+        right.add_expression(lang.parse_no_vars("(* 3 4)").unwrap());
+        right.add_expression(lang.parse_no_vars("(+ 1 2)").unwrap());
+
+        let difference = diff(&left, &right);
+        assert!(difference.is_empty(), "{difference:?}");
+        assert!(
+            difference
+                .dot(&left, &right, &lang)
+                .starts_with("digraph diff {")
+        );
+    }
+
+    #[test]
+    fn finds_missing_nodes_and_merges() {
+        let lang = Language::simple_math();
+        let (mut left, sum) =
+            EGraph::<()>::from_expression_with_id(lang.parse_no_vars("(+ 1 2)").unwrap());
+        let mut right = left.clone();
+        let product = left.add_expression(lang.parse_no_vars("(* 1 2)").unwrap());
+        left.merge_classes(sum, product);
+        let sine = right.add_expression(lang.parse_no_vars("(sin 1)").unwrap());
+
+        let difference = diff(&left, &right);
+        let one = right.find_literal(Literal::Int(1)).unwrap();
+        assert_eq!(difference.nodes_only_in_left, [product]);
+        assert_eq!(difference.nodes_only_in_right, [sine]);
+        assert!(difference.classes_only_in_left.is_empty());
+        assert_eq!(
+            difference.classes_only_in_right,
+            [right.containing_class(sine)]
+        );
+        assert!(difference.merged_only_in_left.is_empty());
+        assert!(difference.merged_only_in_right.is_empty());
+
+        // Classes merged only in `right`
+        let two = right.find_literal(Literal::Int(2)).unwrap();
+        let merged = right.merge_classes(one, two).any();
+        let difference = diff(&left, &right);
+        assert_eq!(difference.merged_only_in_right.len(), 1);
+        assert_eq!(difference.merged_only_in_right[0].0, merged);
+        assert_eq!(difference.merged_only_in_right[0].1.len(), 2);
+
+        let dot = difference.dot(&left, &right, &lang);
+        assert!(dot.contains(super::RIGHT_COLOR) && dot.contains(super::MERGED_COLOR));
+    }
+}
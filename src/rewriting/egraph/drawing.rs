@@ -1,20 +1,105 @@
-use std::{collections::HashMap, fmt::Write, fs::File, path::Path};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt::{Display, Write},
+    fs::File,
+    path::Path,
+};
 
-use crate::language::Language;
+use itertools::Itertools;
 
-use super::{Analysis, ClassId, EGraph, Node, NodeId, class::DynClass};
+use crate::{
+    graph::graphml::{AttributeType, GraphMlWriter},
+    language::Language,
+    utils::json,
+};
+
+use super::{Analysis, Class, ClassId, EGraph, Node, NodeId, class::DynClass};
 
 // This file contains only debugging code for drawing egraphs using `dot`.
 // It was written by ChatGPT, as I don't know this language.
 // Have fun debugging if it turns out it doesn't work.
+//
+// Classes and nodes are written in the order of their IDs, so that the output is deterministic
+// and consecutive frames of a growing e-graph keep similar layouts, see `FrameDump`.
 
 const NODE_SHAPE: &str = "box";
+const CLASS_SHAPE: &str = "ellipse";
 const GRAPH_SPLINES: &str = "true";
 const GRAPH_NODESEP: f32 = 1.0;
 const GRAPH_RANKSEP: f32 = 1.2;
+const HIGHLIGHT_COLOR: &str = "lightsalmon";
+
+/// Options controlling how [`EGraph::dot_with_config`] renders an e-graph.
+#[derive(Clone, Debug)]
+pub struct DotConfig {
+    /// Draw every class as a cluster containing its nodes, instead of as a separate vertex
+    /// with edges to its nodes
+    pub cluster_classes: bool,
+    /// Label edges with the index of the child they lead to
+    pub edge_labels: bool,
+    /// Annotate classes with their analysis data, see [`Analysis::to_string`]
+    pub show_analysis: bool,
+    /// Costs of the best expressions of classes, displayed next to the class IDs
+    pub costs: HashMap<ClassId, String>,
+    /// Classes drawn in a different color, e.g. the ones merged during the last iteration
+    pub highlighted_classes: HashSet<ClassId>,
+}
+
+impl Default for DotConfig {
+    fn default() -> Self {
+        Self {
+            cluster_classes: true,
+            edge_labels: true,
+            show_analysis: true,
+            costs: HashMap::new(),
+            highlighted_classes: HashSet::new(),
+        }
+    }
+}
+
+impl DotConfig {
+    /// Sets whether classes are drawn as clusters.
+    pub fn with_clusters(mut self, cluster_classes: bool) -> Self {
+        self.cluster_classes = cluster_classes;
+        self
+    }
+
+    /// Sets whether edges are labeled with child indices.
+    pub fn with_edge_labels(mut self, edge_labels: bool) -> Self {
+        self.edge_labels = edge_labels;
+        self
+    }
+
+    /// Sets whether classes are annotated with their analysis data.
+    pub fn with_analysis(mut self, show_analysis: bool) -> Self {
+        self.show_analysis = show_analysis;
+        self
+    }
+
+    /// Annotates classes with costs, e.g. the ones from
+    /// [`SimpleExtractor::class_costs`](super::extraction::SimpleExtractor::class_costs).
+    pub fn with_costs<C: Display>(mut self, costs: &HashMap<ClassId, C>) -> Self {
+        self.costs = costs
+            .iter()
+            .map(|(&class_id, cost)| (class_id, cost.to_string()))
+            .collect();
+        self
+    }
+
+    /// Highlights the given classes, e.g. the ones returned by [`EGraph::merged_since`].
+    pub fn with_highlighted_classes(mut self, classes: impl IntoIterator<Item = ClassId>) -> Self {
+        self.highlighted_classes = classes.into_iter().collect();
+        self
+    }
+}
 
 impl<A: Analysis> EGraph<A> {
     pub fn dot(&self, language: &Language) -> String {
+        self.dot_with_config(language, &DotConfig::default())
+    }
+
+    pub fn dot_with_config(&self, language: &Language, config: &DotConfig) -> String {
         let mut out = String::new();
         writeln!(&mut out, "digraph egraph {{").unwrap();
         writeln!(&mut out, "  compound=true;").unwrap();
@@ -25,32 +110,67 @@ impl<A: Analysis> EGraph<A> {
         )
         .unwrap();
 
-        let class_repr = self.write_clusters(&mut out, language);
-        self.write_edges(&mut out, &class_repr);
+        if config.cluster_classes {
+            let class_repr = self.write_clusters(&mut out, language, config);
+            self.write_edges(&mut out, &class_repr, config);
+        } else {
+            self.write_class_vertices(&mut out, language, config);
+        }
 
         writeln!(&mut out, "}}\n").unwrap();
         out
     }
 
-    fn write_clusters(&self, out: &mut String, language: &Language) -> HashMap<ClassId, NodeId> {
+    fn class_label(&self, class_id: ClassId, config: &DotConfig) -> String {
+        let mut label = format!("Class {class_id:?}");
+        if config.show_analysis
+            && let Some(analysis_str) = self.classes[&class_id].analysis().to_string()
+        {
+            write!(label, " ({analysis_str})").unwrap();
+        }
+        if let Some(cost) = config.costs.get(&class_id) {
+            write!(label, "\\ncost: {cost}").unwrap();
+        }
+        label
+    }
+
+    pub(super) fn node_label(node: &Node, language: &Language) -> String {
+        match node {
+            Node::Literal(lit) => lit.to_string().replace('\\', "\\\\").replace('"', "\\\""),
+            Node::Symbol(sym) => language.get_symbol(sym.id).to_string(),
+        }
+    }
+
+    fn edge_attributes(index: usize, config: &DotConfig) -> String {
+        if config.edge_labels {
+            format!("taillabel=\"{index}\"")
+        } else {
+            String::new()
+        }
+    }
+
+    fn write_clusters(
+        &self,
+        out: &mut String,
+        language: &Language,
+        config: &DotConfig,
+    ) -> HashMap<ClassId, NodeId> {
         let mut class_repr = HashMap::new();
 
-        for (class_id, class) in &self.classes {
+        for (class_id, class) in self.sorted_classes() {
             writeln!(out, "  subgraph cluster_{class_id:?} {{").unwrap();
-            let mut label = format!("Class {class_id:?}");
-            if let Some(analysis_str) = class.analysis().to_string() {
-                write!(label, " ({analysis_str})").unwrap();
-            }
+            let label = self.class_label(*class_id, config);
             writeln!(out, "    label = \"{label}\";").unwrap();
+            if config.highlighted_classes.contains(class_id) {
+                writeln!(out, "    style = filled;").unwrap();
+                writeln!(out, "    fillcolor = {HIGHLIGHT_COLOR:?};").unwrap();
+            }
 
-            for node_id in class.nodes_ids() {
+            for node_id in class.nodes_ids().iter().sorted() {
                 class_repr.entry(*class_id).or_insert(*node_id);
 
                 if let Some(node) = self.nodes.get(node_id) {
-                    let label = match node {
-                        Node::Literal(lit) => format!("{lit:?}"),
-                        Node::Symbol(sym) => language.get_symbol(sym.id).to_string(),
-                    };
+                    let label = Self::node_label(node, language);
                     writeln!(out, "    {node_id:?} [label=\"{label}\"];").unwrap();
                 }
             }
@@ -60,16 +180,23 @@ impl<A: Analysis> EGraph<A> {
         class_repr
     }
 
-    fn write_edges(&self, out: &mut String, class_repr: &HashMap<ClassId, NodeId>) {
-        for class in self.classes.values() {
-            for node_id in class.nodes_ids() {
+    fn write_edges(
+        &self,
+        out: &mut String,
+        class_repr: &HashMap<ClassId, NodeId>,
+        config: &DotConfig,
+    ) {
+        for (_, class) in self.sorted_classes() {
+            for node_id in class.nodes_ids().iter().sorted() {
                 if let Some(Node::Symbol(sym)) = self.nodes.get(node_id) {
                     for (i, child_class) in sym.children.iter().enumerate() {
                         let canonical = self.union_find.find(*child_class);
                         if let Some(target_node) = class_repr.get(&canonical) {
+                            let attributes = Self::edge_attributes(i, config);
+                            let separator = if attributes.is_empty() { "" } else { ", " };
                             writeln!(
                                 out,
-                                "  {node_id:?} -> {target_node:?} [lhead=cluster_{canonical:?}, taillabel=\"{i}\"];"
+                                "  {node_id:?} -> {target_node:?} [lhead=cluster_{canonical:?}{separator}{attributes}];"
                             )
                             .unwrap();
                         }
@@ -79,9 +206,293 @@ impl<A: Analysis> EGraph<A> {
         }
     }
 
+    fn write_class_vertices(&self, out: &mut String, language: &Language, config: &DotConfig) {
+        for (class_id, class) in self.sorted_classes() {
+            let label = self.class_label(*class_id, config);
+            let fill = if config.highlighted_classes.contains(class_id) {
+                format!(", style=filled, fillcolor={HIGHLIGHT_COLOR:?}")
+            } else {
+                String::new()
+            };
+            writeln!(
+                out,
+                "  c{class_id:?} [shape={CLASS_SHAPE:?}, label=\"{label}\"{fill}];"
+            )
+            .unwrap();
+
+            for node_id in class.nodes_ids().iter().sorted() {
+                let Some(node) = self.nodes.get(node_id) else {
+                    continue;
+                };
+                let label = Self::node_label(node, language);
+                writeln!(out, "  {node_id:?} [label=\"{label}\"];").unwrap();
+                writeln!(out, "  c{class_id:?} -> {node_id:?} [style=dashed];").unwrap();
+
+                for (i, child_class) in node.iter_children().enumerate() {
+                    let canonical = self.union_find.find(*child_class);
+                    let attributes = Self::edge_attributes(i, config);
+                    writeln!(out, "  {node_id:?} -> c{canonical:?} [{attributes}];").unwrap();
+                }
+            }
+        }
+    }
+
+    fn sorted_classes(&self) -> impl Iterator<Item = (&ClassId, &Class<A>)> {
+        self.classes
+            .iter()
+            .sorted_by_key(|(class_id, _)| **class_id)
+    }
+
     pub fn save_dot<P: AsRef<Path>>(&self, language: &Language, path: P) -> std::io::Result<()> {
-        let dot_content = self.dot(language);
+        self.save_dot_with_config(language, &DotConfig::default(), path)
+    }
+
+    pub fn save_dot_with_config<P: AsRef<Path>>(
+        &self,
+        language: &Language,
+        config: &DotConfig,
+        path: P,
+    ) -> std::io::Result<()> {
+        let dot_content = self.dot_with_config(language, config);
         let mut file = File::create(path)?;
         std::io::Write::write_all(&mut file, dot_content.as_bytes())
     }
+
+    /// Returns the e-graph in GraphML format, e.g. for laying it out with Gephi.
+    ///
+    /// Both classes (`c<id>`) and nodes (`n<id>`) become vertices, distinguished by the `kind`
+    /// attribute. Every class has `member` edges to its nodes and every node has `child` edges
+    /// to the classes of its children.
+    pub fn graphml(&self, language: &Language) -> String {
+        let mut writer = GraphMlWriter::new(
+            &[
+                ("label", AttributeType::String),
+                ("kind", AttributeType::String),
+                ("class", AttributeType::Int),
+                ("analysis", AttributeType::String),
+            ],
+            &[
+                ("kind", AttributeType::String),
+                ("index", AttributeType::Int),
+            ],
+        );
+
+        for (class_id, class) in &self.classes {
+            let label = format!("Class {class_id}");
+            let mut data: Vec<(&str, &dyn Display)> =
+                vec![("label", &label), ("kind", &"class"), ("class", class_id)];
+            let analysis = class.analysis().to_string();
+            if let Some(analysis) = &analysis {
+                data.push(("analysis", analysis));
+            }
+            writer.vertex(format_args!("c{class_id}"), &data);
+
+            for node_id in class.nodes_ids() {
+                let Some(node) = self.nodes.get(node_id) else {
+                    continue;
+                };
+                let label = match node {
+                    Node::Literal(lit) => lit.to_string(),
+                    Node::Symbol(sym) => language.get_symbol(sym.id).to_string(),
+                };
+                writer.vertex(
+                    format_args!("n{node_id}"),
+                    &[("label", &label), ("kind", &"node"), ("class", class_id)],
+                );
+                writer.edge(
+                    format_args!("c{class_id}"),
+                    format_args!("n{node_id}"),
+                    &[("kind", &"member")],
+                );
+            }
+        }
+
+        for (node_id, node) in &self.nodes {
+            for (i, child_class) in node.iter_children().enumerate() {
+                let canonical = self.union_find.find(*child_class);
+                writer.edge(
+                    format_args!("n{node_id}"),
+                    format_args!("c{canonical}"),
+                    &[("kind", &"child"), ("index", &i)],
+                );
+            }
+        }
+
+        writer.finish()
+    }
+
+    pub fn save_graphml<P: AsRef<Path>>(
+        &self,
+        language: &Language,
+        path: P,
+    ) -> std::io::Result<()> {
+        let mut file = File::create(path)?;
+        std::io::Write::write_all(&mut file, self.graphml(language).as_bytes())
+    }
+
+    /// Returns the e-graph in the JSON format of the `egraph-serialize` crate,
+    /// e.g. for drawing it with `egraph-visualizer`.
+    ///
+    /// Every node is keyed by its ID and points to its class. As children in this format
+    /// are nodes rather than classes, every child class is represented by its node
+    /// with the smallest ID.
+    pub fn serialized_json(&self, language: &Language) -> serde_json::Value {
+        let mut nodes = serde_json::Map::new();
+        let mut class_data = serde_json::Map::new();
+
+        for (class_id, class) in self.sorted_classes() {
+            let data = match class.analysis().to_string() {
+                Some(analysis) => serde_json::json!({ "type": analysis }),
+                None => serde_json::json!({}),
+            };
+            class_data.insert(class_id.to_string(), data);
+
+            for node_id in class.nodes_ids().iter().sorted() {
+                let Some(node) = self.nodes.get(node_id) else {
+                    continue;
+                };
+                let op = match node {
+                    Node::Literal(lit) => lit.to_string(),
+                    Node::Symbol(sym) => language.get_symbol(sym.id).to_string(),
+                };
+                let children: Vec<_> = node
+                    .iter_children()
+                    .filter_map(|child_class| {
+                        let canonical = self.union_find.find(*child_class);
+                        self.classes[&canonical].nodes_ids().iter().min()
+                    })
+                    .map(|child| child.to_string())
+                    .collect();
+                nodes.insert(
+                    node_id.to_string(),
+                    serde_json::json!({
+                        "op": op,
+                        "children": children,
+                        "eclass": class_id.to_string(),
+                        "cost": 1.0,
+                    }),
+                );
+            }
+        }
+
+        serde_json::json!({
+            "nodes": nodes,
+            "root_eclasses": [],
+            "class_data": class_data,
+        })
+    }
+
+    /// Saves the e-graph in the JSON format of [`EGraph::serialized_json`].
+    pub fn save_serialized_json<P: AsRef<Path>>(
+        &self,
+        language: &Language,
+        path: P,
+    ) -> Result<(), Box<dyn Error>> {
+        json::save_json(&self.serialized_json(language), path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        language::{Language, expression::Literal},
+        rewriting::egraph::{DynEGraph, EGraph, extraction::SimpleExtractor},
+    };
+
+    use super::DotConfig;
+
+    fn egraph(lang: &Language) -> EGraph<()> {
+        EGraph::from_expression(lang.parse_no_vars("(+ 1 (sin 2))").unwrap())
+    }
+
+    #[test]
+    fn default_config_clusters_classes() {
+        let lang = Language::simple_math();
+        let dot = egraph(&lang).dot(&lang);
+        assert_eq!(dot.matches("subgraph cluster_").count(), 4);
+        assert!(dot.contains("taillabel=\"1\""));
+    }
+
+    #[test]
+    fn unclustered_without_edge_labels() {
+        let lang = Language::simple_math();
+        let config = DotConfig::default()
+            .with_clusters(false)
+            .with_edge_labels(false);
+        let dot = egraph(&lang).dot_with_config(&lang, &config);
+        assert!(!dot.contains("subgraph"));
+        assert!(!dot.contains("taillabel"));
+        assert_eq!(dot.matches("[style=dashed]").count(), 4);
+    }
+
+    #[test]
+    fn costs_and_highlights() {
+        let lang = Language::simple_math();
+        let mut egraph = egraph(&lang);
+        let before = egraph.clone();
+        let one = egraph.find_literal(Literal::Int(1));
+        let two = egraph.find_literal(Literal::Int(2));
+        egraph.merge_classes(one.unwrap(), two.unwrap());
+
+        let extractor = SimpleExtractor::<usize, _, _>::new(|_| 1, |_, _| Some(1));
+        let config = DotConfig::default()
+            .with_costs(&extractor.class_costs(&egraph))
+            .with_highlighted_classes(egraph.merged_since(&before));
+        let dot = egraph.dot_with_config(&lang, &config);
+
+        assert_eq!(dot.matches("\\ncost: 1").count(), 3);
+        assert_eq!(dot.matches("fillcolor").count(), 1);
+    }
+
+    #[test]
+    fn serialized_json_points_children_to_nodes() {
+        let lang = Language::simple_math();
+        let mut egraph = egraph(&lang);
+        let one = egraph.find_literal(Literal::Int(1)).unwrap();
+        let two = egraph.find_literal(Literal::Int(2)).unwrap();
+        egraph.merge_classes(one, two);
+
+        let json = egraph.serialized_json(&lang);
+        let nodes = json["nodes"].as_object().unwrap();
+        assert_eq!(nodes.len(), 4);
+        assert_eq!(json["class_data"].as_object().unwrap().len(), 3);
+
+        let (_, sin) = nodes.iter().find(|(_, node)| node["op"] == "sin").unwrap();
+        let child = &nodes[sin["children"][0].as_str().unwrap()];
+        assert_eq!(child["op"], "1");
+        assert_eq!(
+            child["eclass"],
+            egraph.canonical_class(two).to_string().as_str()
+        );
+    }
+
+    #[test]
+    fn graphml_vertices_and_edges() {
+        let lang = Language::simple_math();
+        let graphml = egraph(&lang).graphml(&lang);
+        assert_eq!(
+            graphml
+                .matches("<data key=\"node_kind\">class</data>")
+                .count(),
+            4
+        );
+        assert_eq!(
+            graphml
+                .matches("<data key=\"node_kind\">node</data>")
+                .count(),
+            4
+        );
+        assert_eq!(
+            graphml
+                .matches("<data key=\"edge_kind\">member</data>")
+                .count(),
+            4
+        );
+        assert_eq!(
+            graphml
+                .matches("<data key=\"edge_kind\">child</data>")
+                .count(),
+            3
+        );
+    }
 }
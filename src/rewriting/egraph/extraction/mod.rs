@@ -0,0 +1,656 @@
+//! Expression extraction from e-graphs.
+//!
+//! This module provides algorithms for extracting concrete expressions from
+//! e-graphs, typically selecting the "best" representative according to some
+//! cost function, or the one closest to a target expression with [`TargetedExtractor`].
+//! Costs can also be loaded from JSON as a [`CostTable`] and used with [`TableExtractor`].
+//! Extraction from huge e-graphs can be given a time limit, see [`SimpleExtractor::extract_anytime`],
+//! or restricted to the classes reachable from the extracted class, see [`Extractor::extract_cone`].
+
+use std::{
+    collections::{HashMap, HashSet},
+    iter::Sum,
+    marker::PhantomData,
+    time::{Duration, Instant},
+};
+
+use crate::language::{
+    expression::{Literal, VarFreeExpression},
+    symbol::Symbol,
+};
+
+use super::{ClassId, DynEGraph, Node, NodeId};
+
+mod table;
+mod targeted;
+
+pub use table::{CostTable, TableExtractor};
+pub use targeted::TargetedExtractor;
+
+/// The result of extracting an expression from an e-graph.
+///
+/// Contains both the extracted expression and its associated cost.
+#[derive(Clone, Debug)]
+pub struct ExtractionResult<C> {
+    winner: VarFreeExpression,
+    cost: C,
+}
+
+impl<C> ExtractionResult<C> {
+    /// Returns a reference to the extracted expression.
+    pub fn winner(&self) -> &VarFreeExpression {
+        &self.winner
+    }
+
+    /// Returns a reference to the cost of the extracted expression.
+    pub fn cost(&self) -> &C {
+        &self.cost
+    }
+
+    /// Consumes the result and returns the cost value.
+    pub fn cost_value(self) -> C {
+        self.cost
+    }
+}
+
+/// The result of an extraction with a time limit, see [`SimpleExtractor::extract_anytime`].
+#[derive(Clone, Debug)]
+pub struct AnytimeExtraction<C> {
+    /// Cheapest expression known when the extraction stopped,
+    /// `None` if no expression of the class had been costed yet
+    pub result: Option<ExtractionResult<C>>,
+    /// `true` if the costs reached their fixed point, so `result` is the cheapest expression
+    pub converged: bool,
+}
+
+/// Trait for extracting expressions from e-graphs.
+pub trait Extractor {
+    /// The type used to represent costs
+    type Cost;
+
+    /// Finds the cheapest expression represented by `egraph` that's represented by class with id `equivalent`.
+    ///
+    /// # Arguments
+    ///
+    /// * `egraph` - The e-graph to extract from
+    /// * `equivalent` - The class ID to extract an expression from
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(result)` with the cheapest expression, or `None` if extraction fails
+    fn extract(
+        &self,
+        egraph: &dyn DynEGraph,
+        equivalent: ClassId,
+    ) -> Option<ExtractionResult<Self::Cost>>;
+
+    /// Same as [`Extractor::extract`], but considers only the classes with canonical IDs
+    /// in `classes`, which should contain all classes reachable from `equivalent`,
+    /// e.g. the ones returned by [`reachable_classes`].
+    ///
+    /// Extractors which cannot restrict their work extract from the whole e-graph.
+    fn extract_within(
+        &self,
+        egraph: &dyn DynEGraph,
+        equivalent: ClassId,
+        classes: &HashSet<ClassId>,
+    ) -> Option<ExtractionResult<Self::Cost>> {
+        let _ = classes;
+        self.extract(egraph, equivalent)
+    }
+
+    /// Same as [`Extractor::extract`], but computes costs only in the cone of `equivalent`,
+    /// i.e. in the classes reachable from it, which is faster for small classes of big e-graphs.
+    fn extract_cone(
+        &self,
+        egraph: &dyn DynEGraph,
+        equivalent: ClassId,
+    ) -> Option<ExtractionResult<Self::Cost>> {
+        self.extract_within(egraph, equivalent, &reachable_classes(egraph, equivalent))
+    }
+
+    /// Same as [`Extractor::extract`] for each of `roots`, e.g. the classes of expressions
+    /// saturated together, see [`EGraph::from_expressions`](super::EGraph::from_expressions).
+    ///
+    /// Extractors computing costs of all classes at once do it only once for all roots.
+    fn extract_roots(
+        &self,
+        egraph: &dyn DynEGraph,
+        roots: &[ClassId],
+    ) -> Vec<Option<ExtractionResult<Self::Cost>>> {
+        roots
+            .iter()
+            .map(|&root| self.extract(egraph, root))
+            .collect()
+    }
+}
+
+/// Returns canonical IDs of the class `root` and all classes reachable from it through children.
+pub fn reachable_classes(egraph: &dyn DynEGraph, root: ClassId) -> HashSet<ClassId> {
+    let mut reachable = HashSet::new();
+    let mut stack = vec![egraph.canonical_class(root)];
+    while let Some(class_id) = stack.pop() {
+        if !reachable.insert(class_id) {
+            continue;
+        }
+        for &node_id in egraph.dyn_class(class_id).iter_nodes() {
+            stack.extend(
+                egraph
+                    .node(node_id)
+                    .iter_children()
+                    .map(|&child| egraph.canonical_class(child)),
+            );
+        }
+    }
+    reachable
+}
+
+/// Canonical IDs of all classes of `egraph`, in the order of iteration over them.
+fn all_classes(egraph: &dyn DynEGraph) -> Vec<ClassId> {
+    egraph
+        .dyn_classes()
+        .into_iter()
+        .map(|(&class_id, _)| class_id)
+        .collect()
+}
+
+/// Canonical IDs of `classes` in a deterministic order.
+fn sorted_classes(classes: &HashSet<ClassId>) -> Vec<ClassId> {
+    let mut classes: Vec<_> = classes.iter().copied().collect();
+    classes.sort_unstable();
+    classes
+}
+
+trait_set::trait_set! {
+    pub trait SimpleSymbolCost<C> = Fn(&Symbol<ClassId>, &HashMap<ClassId, C>) -> Option<C>;
+    pub trait SimpleLiteralCost<C> = Fn(&Literal) -> C;
+}
+
+/// A simple extractor that finds the cheapest expression using cost functions.
+///
+/// # Type Parameters
+///
+/// * `C` - The cost type (must be ordered)
+/// * `SC` - The function type for computing symbol costs
+/// * `LC` - The function type for computing literal costs
+pub struct SimpleExtractor<C, SC, LC>
+where
+    C: Ord + PartialEq + Clone,
+    LC: SimpleLiteralCost<C>,
+    SC: SimpleSymbolCost<C>,
+{
+    literal_cost: LC,
+    symbol_cost: SC,
+    cost: PhantomData<C>,
+}
+
+impl<C, SC, LC> SimpleExtractor<C, SC, LC>
+where
+    C: Ord + PartialEq + Clone,
+    LC: SimpleLiteralCost<C>,
+    SC: SimpleSymbolCost<C>,
+{
+    pub fn new(literal_cost: LC, symbol_cost: SC) -> Self {
+        Self {
+            literal_cost,
+            symbol_cost,
+            cost: PhantomData,
+        }
+    }
+
+    fn node_cost(&self, node: &Node, costs: &HashMap<ClassId, C>) -> Option<C> {
+        match node {
+            Node::Literal(literal) => Some((self.literal_cost)(literal)),
+            Node::Symbol(symbol) => (self.symbol_cost)(symbol, costs),
+        }
+    }
+
+    /// Returns the cost of the cheapest expression represented by every class of `egraph`.
+    /// Classes for which no cost could be determined are missing from the result.
+    pub fn class_costs(&self, egraph: &dyn DynEGraph) -> HashMap<ClassId, C> {
+        self.calculate_costs(egraph).1
+    }
+
+    /// Extracts the cheapest expressions of all classes `class_ids`, computing costs only once.
+    /// Classes for which no cost could be determined are missing from the result.
+    pub fn extract_classes(
+        &self,
+        egraph: &dyn DynEGraph,
+        class_ids: impl IntoIterator<Item = ClassId>,
+    ) -> HashMap<ClassId, ExtractionResult<C>> {
+        let (cheapest_nodes, class_costs) = self.calculate_costs(egraph);
+        class_ids
+            .into_iter()
+            .filter_map(|class_id| {
+                let class_id = egraph.canonical_class(class_id);
+                let cost = class_costs.get(&class_id)?.clone();
+                Some((
+                    class_id,
+                    ExtractionResult {
+                        winner: extract_expression(egraph, &cheapest_nodes, class_id),
+                        cost,
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    /// Same as [`Extractor::extract`], but stops computing costs once `time_limit` has passed,
+    /// returning the cheapest expression known at that point.
+    ///
+    /// Costs of classes only decrease while they are computed, so the expression is valid
+    /// but may not be the cheapest one unless [`AnytimeExtraction::converged`] is `true`.
+    /// Costs have to be larger than the costs of children for the intermediate choices
+    /// to form an expression.
+    pub fn extract_anytime(
+        &self,
+        egraph: &dyn DynEGraph,
+        equivalent: ClassId,
+        time_limit: Duration,
+    ) -> AnytimeExtraction<C> {
+        let equivalent = egraph.canonical_class(equivalent);
+        let deadline = Instant::now() + time_limit;
+        let (cheapest_nodes, class_costs, converged) =
+            self.calculate_costs_until(egraph, &all_classes(egraph), Some(deadline));
+
+        AnytimeExtraction {
+            result: class_costs.get(&equivalent).map(|cost| ExtractionResult {
+                winner: extract_expression(egraph, &cheapest_nodes, equivalent),
+                cost: cost.clone(),
+            }),
+            converged,
+        }
+    }
+
+    fn calculate_costs(
+        &self,
+        egraph: &dyn DynEGraph,
+    ) -> (HashMap<ClassId, NodeId>, HashMap<ClassId, C>) {
+        let (cheapest_nodes, class_costs, _) =
+            self.calculate_costs_until(egraph, &all_classes(egraph), None);
+        (cheapest_nodes, class_costs)
+    }
+
+    /// Iterates costs of `classes` to their fixed point, or until `deadline` passes.
+    /// Classes missing from `classes` have no cost. The last element of the result is `true`
+    /// if the fixed point was reached.
+    fn calculate_costs_until(
+        &self,
+        egraph: &dyn DynEGraph,
+        classes: &[ClassId],
+        deadline: Option<Instant>,
+    ) -> (HashMap<ClassId, NodeId>, HashMap<ClassId, C>, bool) {
+        let mut work_remaining = true;
+        let mut class_costs = HashMap::new();
+        let mut node_costs = HashMap::new();
+        let mut cheapest_nodes = HashMap::new();
+
+        while work_remaining {
+            work_remaining = false;
+
+            for &class_id in classes {
+                if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    return (cheapest_nodes, class_costs, false);
+                }
+
+                let class = egraph.dyn_class(class_id);
+                for &node_id in class.iter_nodes() {
+                    let node = egraph.node(node_id);
+                    if let Some(node_cost) = self.node_cost(node, &class_costs) {
+                        if let Some(old_cost) = node_costs.get(&node_id)
+                            && *old_cost == node_cost
+                        {
+                            continue;
+                        }
+
+                        node_costs.insert(node_id, node_cost);
+                        work_remaining = true;
+                    }
+                }
+
+                if let Some((min_node_id, min_node_cost)) = class
+                    .iter_nodes()
+                    .filter_map(|node_id| Some((*node_id, node_costs.get(node_id)?)))
+                    .min_by_key(|x| x.1)
+                {
+                    class_costs.insert(class_id, min_node_cost.clone());
+                    cheapest_nodes.insert(class_id, min_node_id);
+                }
+            }
+        }
+
+        (cheapest_nodes, class_costs, true)
+    }
+}
+
+/// Builds the expression of `class_id` made of `chosen_nodes`.
+fn extract_expression(
+    egraph: &dyn DynEGraph,
+    chosen_nodes: &HashMap<ClassId, NodeId>,
+    class_id: ClassId,
+) -> VarFreeExpression {
+    match egraph.node(*chosen_nodes.get(&class_id).expect(concat!(
+        "Found a class for which cost could not be determined.",
+        "Have you defined correct costs for all symbols in the language?"
+    ))) {
+        Node::Literal(literal) => VarFreeExpression::Literal(literal.clone()),
+        Node::Symbol(symbol) => VarFreeExpression::Symbol(Symbol {
+            id: symbol.id,
+            children: symbol
+                .children
+                .iter()
+                .map(|&child_id| extract_expression(egraph, chosen_nodes, child_id))
+                .collect(),
+        }),
+    }
+}
+
+pub fn children_cost_sum<C>(symbol: &Symbol<ClassId>, costs: &HashMap<ClassId, C>) -> Option<C>
+where
+    C: Ord + PartialEq + Clone + Sum,
+{
+    symbol
+        .children
+        .iter()
+        .map(|child_id| costs.get(child_id).cloned())
+        .sum::<Option<C>>()
+}
+
+impl<C, SC, LC> Extractor for SimpleExtractor<C, SC, LC>
+where
+    C: Ord + PartialEq + Clone,
+    LC: SimpleLiteralCost<C>,
+    SC: SimpleSymbolCost<C>,
+{
+    type Cost = C;
+
+    fn extract(
+        &self,
+        egraph: &dyn DynEGraph,
+        equivalent: ClassId,
+    ) -> Option<ExtractionResult<Self::Cost>> {
+        let equivalent = egraph.canonical_class(equivalent);
+        let (cheapest_nodes, class_costs) = self.calculate_costs(egraph);
+
+        Some(ExtractionResult {
+            winner: extract_expression(egraph, &cheapest_nodes, equivalent),
+            cost: class_costs.get(&equivalent)?.clone(),
+        })
+    }
+
+    fn extract_within(
+        &self,
+        egraph: &dyn DynEGraph,
+        equivalent: ClassId,
+        classes: &HashSet<ClassId>,
+    ) -> Option<ExtractionResult<Self::Cost>> {
+        let equivalent = egraph.canonical_class(equivalent);
+        let (cheapest_nodes, class_costs, _) =
+            self.calculate_costs_until(egraph, &sorted_classes(classes), None);
+
+        Some(ExtractionResult {
+            cost: class_costs.get(&equivalent)?.clone(),
+            winner: extract_expression(egraph, &cheapest_nodes, equivalent),
+        })
+    }
+
+    fn extract_roots(
+        &self,
+        egraph: &dyn DynEGraph,
+        roots: &[ClassId],
+    ) -> Vec<Option<ExtractionResult<Self::Cost>>> {
+        let results = self.extract_classes(egraph, roots.iter().copied());
+        roots
+            .iter()
+            .map(|&root| results.get(&egraph.canonical_class(root)).cloned())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        language::Language,
+        rewriting::{
+            egraph::{
+                DynEGraph, EGraph,
+                matching::bottom_up::BottomUpMatcher,
+                saturation::{SaturationConfig, Saturator, SimpleSaturator},
+            },
+            rule::Rule,
+        },
+    };
+
+    use std::{collections::HashSet, time::Duration};
+
+    use super::{Extractor, SimpleExtractor, children_cost_sum, reachable_classes};
+
+    #[test]
+    fn literal_cost() {
+        let lang = Language::simple_math();
+        let mut egraph = EGraph::<()>::default();
+
+        let expr_1 = lang.parse_no_vars("4u").unwrap();
+        let expr_2 = lang.parse_no_vars("8u").unwrap();
+
+        let node_1_id = egraph.add_expression(expr_1.clone());
+        let class_1_id = egraph.containing_class(node_1_id);
+        let node_2_id = egraph.add_expression(expr_2);
+        let class_2_id = egraph.containing_class(node_2_id);
+
+        let merged_id = egraph.merge_classes(class_1_id, class_2_id).new().unwrap();
+
+        let extractor = SimpleExtractor::<usize, _, _>::new(
+            |literal| match literal {
+                crate::language::expression::Literal::UInt(x) => *x as usize,
+                _ => 0,
+            },
+            |_, _| Some(0),
+        );
+
+        let extraction_result = extractor.extract(&egraph, merged_id).unwrap();
+
+        assert_eq!(extraction_result.winner, expr_1);
+        assert_eq!(extraction_result.cost, 4);
+    }
+
+    #[test]
+    fn symbol_cost() {
+        let lang = Language::simple_math();
+        let mut egraph = EGraph::<()>::default();
+
+        let expr_1 = lang.parse_no_vars("(+ 2u 3u)").unwrap();
+        let expr_2 = lang.parse_no_vars("(* 1u 2u)").unwrap();
+
+        let node_1_id = egraph.add_expression(expr_1.clone());
+        let class_1_id = egraph.containing_class(node_1_id);
+        let node_2_id = egraph.add_expression(expr_2);
+        let class_2_id = egraph.containing_class(node_2_id);
+
+        let merged_id = egraph.merge_classes(class_1_id, class_2_id).new().unwrap();
+
+        let extractor = SimpleExtractor::<usize, _, _>::new(
+            |literal| match literal {
+                crate::language::expression::Literal::UInt(x) => *x as usize,
+                _ => 0,
+            },
+            |symbol, costs| {
+                Some(match lang.get_symbol(symbol.id) {
+                    "+" => 1usize + children_cost_sum(symbol, costs)?,
+                    "*" => 1usize + 2usize * children_cost_sum(symbol, costs)?,
+                    _ => None?,
+                })
+            },
+        );
+
+        let extraction_result = extractor.extract(&egraph, merged_id).unwrap();
+
+        assert_eq!(extraction_result.winner, expr_1);
+        assert_eq!(extraction_result.cost, 6);
+    }
+
+    #[test]
+    fn saturated_cost() {
+        let lang = Language::simple_math();
+        let rules = vec![
+            Rule::from_strings("(* $0 2)", "(<< $0 1)", &lang),
+            Rule::from_strings("(* $0 1)", "$0", &lang),
+            Rule::from_strings("(/ (* $0 $1) $2)", "(* $0 (/ $1 $2))", &lang),
+            Rule::from_strings("(/ $0 $0)", "1", &lang),
+        ];
+
+        let mut egraph =
+            EGraph::<()>::from_expression(lang.parse_no_vars("(/ (* (sin 5) 2) 2)").unwrap());
+        let top_class_id = egraph.containing_class(egraph.find_symbols(lang.get_id("/"))[0]);
+
+        let saturator = SimpleSaturator::new(Box::new(BottomUpMatcher));
+        let _ = saturator.saturate(&mut egraph, &rules, &SaturationConfig::default());
+
+        let extractor = SimpleExtractor::<usize, _, _>::new(
+            |_| 1,
+            |symbol, costs| {
+                Some(match lang.get_symbol(symbol.id) {
+                    "/" => 8usize + children_cost_sum(symbol, costs)?,
+                    "*" => 4usize + children_cost_sum(symbol, costs)?,
+                    "<<" => 2usize + children_cost_sum(symbol, costs)?,
+                    "sin" => 2usize + children_cost_sum(symbol, costs)?,
+                    _ => None?,
+                })
+            },
+        );
+
+        let extraction_result = extractor.extract(&egraph, top_class_id).unwrap();
+
+        assert_eq!(
+            extraction_result.winner,
+            lang.parse_no_vars("(sin 5)").unwrap()
+        );
+        assert_eq!(extraction_result.cost, 3);
+    }
+
+    #[test]
+    fn extraction_of_many_roots() {
+        let lang = Language::simple_math();
+        let rules = vec![
+            Rule::from_strings("(* $0 2)", "(<< $0 1)", &lang),
+            Rule::from_strings("(* 1 $0)", "$0", &lang),
+        ];
+        let (mut egraph, roots) = EGraph::<()>::from_expressions(
+            ["(* 3 2)", "(+ (* 3 2) 1)", "(* 1 (* 3 2))"]
+                .map(|expression| lang.parse_no_vars(expression).unwrap())
+                .to_vec(),
+        );
+        SimpleSaturator::new(Box::new(BottomUpMatcher)).saturate(
+            &mut egraph,
+            &rules,
+            &SaturationConfig::default(),
+        );
+        let extractor = SimpleExtractor::<usize, _, _>::new(
+            |_| 1,
+            |symbol, costs| {
+                Some(match lang.get_symbol(symbol.id) {
+                    "*" => 4usize + children_cost_sum(symbol, costs)?,
+                    _ => 1usize + children_cost_sum(symbol, costs)?,
+                })
+            },
+        );
+
+        let results = extractor.extract_roots(&egraph, &roots);
+        let winners: Vec<_> = results
+            .iter()
+            .map(|result| result.as_ref().unwrap().winner.clone())
+            .collect();
+        assert_eq!(
+            winners,
+            ["(<< 3 1)", "(+ (<< 3 1) 1)", "(<< 3 1)"]
+                .map(|expression| lang.parse_no_vars(expression).unwrap())
+        );
+        for (result, &root) in results.iter().zip(&roots) {
+            assert_eq!(
+                result.as_ref().unwrap().cost,
+                extractor.extract(&egraph, root).unwrap().cost
+            );
+        }
+    }
+
+    #[test]
+    fn extraction_within_time_limit() {
+        let lang = Language::simple_math();
+        let rules = vec![
+            Rule::from_strings("(* $0 2)", "(<< $0 1)", &lang),
+            Rule::from_strings("(+ $0 $1)", "(+ $1 $0)", &lang),
+        ];
+        let (mut egraph, root) =
+            EGraph::<()>::from_expression_with_id(lang.parse_no_vars("(+ (* 3 2) 1)").unwrap());
+        SimpleSaturator::new(Box::new(BottomUpMatcher)).saturate(
+            &mut egraph,
+            &rules,
+            &SaturationConfig::default(),
+        );
+        let extractor = SimpleExtractor::<usize, _, _>::new(
+            |_| 1,
+            |symbol, costs| {
+                Some(match lang.get_symbol(symbol.id) {
+                    "*" => 4usize + children_cost_sum(symbol, costs)?,
+                    _ => 1usize + children_cost_sum(symbol, costs)?,
+                })
+            },
+        );
+
+        let expired = extractor.extract_anytime(&egraph, root, Duration::ZERO);
+        assert!(!expired.converged);
+        assert!(expired.result.is_none());
+
+        let anytime = extractor.extract_anytime(&egraph, root, Duration::from_secs(60));
+        let full = extractor.extract(&egraph, root).unwrap();
+        assert!(anytime.converged);
+        let result = anytime.result.unwrap();
+        assert_eq!(result.winner, full.winner);
+        assert_eq!(result.cost, 5);
+    }
+
+    #[test]
+    fn extraction_in_cone() {
+        let lang = Language::simple_math();
+        let mut egraph = EGraph::<()>::default();
+        let root = egraph.add_expression(lang.parse_no_vars("(+ (* 3 2) 1)").unwrap());
+        let root = egraph.containing_class(root);
+        egraph.add_expression(lang.parse_no_vars("(sin (cos 4))").unwrap());
+        let shifted = egraph.add_expression(lang.parse_no_vars("(<< 3 1)").unwrap());
+        let product = egraph.find_symbols(lang.get_id("*"))[0];
+        egraph.merge_classes(
+            egraph.containing_class(product),
+            egraph.containing_class(shifted),
+        );
+        let extractor = SimpleExtractor::<usize, _, _>::new(
+            |_| 1,
+            |symbol, costs| {
+                Some(match lang.get_symbol(symbol.id) {
+                    "*" => 4usize + children_cost_sum(symbol, costs)?,
+                    _ => 1usize + children_cost_sum(symbol, costs)?,
+                })
+            },
+        );
+
+        // +, the merged class and the literals 3, 2 and 1
+        let cone = reachable_classes(&egraph, root);
+        assert_eq!(cone.len(), 5);
+        assert!(cone.contains(&egraph.containing_class(shifted)));
+
+        let full = extractor.extract(&egraph, root).unwrap();
+        let restricted = extractor.extract_cone(&egraph, root).unwrap();
+        assert_eq!(
+            restricted.winner,
+            lang.parse_no_vars("(+ (<< 3 1) 1)").unwrap()
+        );
+        assert_eq!(restricted.winner, full.winner);
+        assert_eq!(restricted.cost, full.cost);
+
+        let without_root: HashSet<_> = cone.iter().copied().filter(|&id| id != root).collect();
+        assert!(
+            extractor
+                .extract_within(&egraph, root, &without_root)
+                .is_none()
+        );
+    }
+}
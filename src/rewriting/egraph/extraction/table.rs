@@ -0,0 +1,195 @@
+//! Extraction with costs given by tables.
+//!
+//! A [`CostTable`] assigns costs to symbols by their names, so it can be loaded from JSON,
+//! e.g. from the `costs` section of a rewriting system, see
+//! [`TermRewritingSystem::costs`](crate::rewriting::system::TermRewritingSystem::costs).
+//! The cost of an expression is the sum of costs of all its literals and symbols.
+
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::language::{Language, expression::Literal, symbol::Symbol};
+
+use super::{
+    super::{ClassId, DynEGraph},
+    AnytimeExtraction, ExtractionResult, Extractor, SimpleExtractor, SimpleLiteralCost,
+    SimpleSymbolCost, children_cost_sum,
+};
+
+fn default_literal_cost() -> usize {
+    1
+}
+
+/// Costs of literals and symbols, the latter given by their names.
+///
+/// Files like `jsons/simple-math/costs.json`, with only a `costs` map, are also accepted.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CostTable {
+    /// Cost of every literal
+    #[serde(default = "default_literal_cost")]
+    pub literal_cost: usize,
+    /// Costs of symbols
+    #[serde(default, alias = "costs")]
+    pub per_symbol_cost: HashMap<String, usize>,
+    /// Cost of symbols missing from `per_symbol_cost`
+    #[serde(default)]
+    pub default: usize,
+}
+
+impl Default for CostTable {
+    fn default() -> Self {
+        Self {
+            literal_cost: default_literal_cost(),
+            per_symbol_cost: HashMap::new(),
+            default: 0,
+        }
+    }
+}
+
+impl CostTable {
+    /// Returns the cost of the symbol called `name`.
+    pub fn symbol_cost(&self, name: &str) -> usize {
+        self.per_symbol_cost
+            .get(name)
+            .copied()
+            .unwrap_or(self.default)
+    }
+
+    /// Returns names in `per_symbol_cost` which are not symbols of `language`.
+    pub fn unknown_symbols<'t>(&'t self, language: &Language) -> Vec<&'t str> {
+        let mut unknown: Vec<_> = self
+            .per_symbol_cost
+            .keys()
+            .filter(|name| language.try_get_id(name).is_none())
+            .map(String::as_str)
+            .collect();
+        unknown.sort();
+        unknown
+    }
+}
+
+/// An extractor finding the cheapest expressions according to a [`CostTable`].
+#[derive(Clone, Debug)]
+pub struct TableExtractor {
+    literal_cost: usize,
+    /// Costs indexed by symbol IDs
+    symbol_costs: Vec<usize>,
+}
+
+impl TableExtractor {
+    /// Creates an extractor for expressions of `language`.
+    /// Names in `table` which are not symbols of `language` are ignored.
+    pub fn new(table: &CostTable, language: &Language) -> Self {
+        Self {
+            literal_cost: table.literal_cost,
+            symbol_costs: (0..language.symbol_count())
+                .map(|id| table.symbol_cost(language.get_symbol(id)))
+                .collect(),
+        }
+    }
+
+    fn simple_extractor(
+        &self,
+    ) -> SimpleExtractor<usize, impl SimpleSymbolCost<usize> + '_, impl SimpleLiteralCost<usize>>
+    {
+        let literal_cost = self.literal_cost;
+        SimpleExtractor::new(
+            move |_: &Literal| literal_cost,
+            |symbol: &Symbol<ClassId>, costs: &HashMap<ClassId, usize>| {
+                Some(self.symbol_costs.get(symbol.id)? + children_cost_sum(symbol, costs)?)
+            },
+        )
+    }
+
+    /// Returns the cost of the cheapest expression represented by every class of `egraph`.
+    pub fn class_costs(&self, egraph: &dyn DynEGraph) -> HashMap<ClassId, usize> {
+        self.simple_extractor().class_costs(egraph)
+    }
+
+    /// Extracts the cheapest expression of class `equivalent` known after `time_limit`,
+    /// see [`SimpleExtractor::extract_anytime`].
+    pub fn extract_anytime(
+        &self,
+        egraph: &dyn DynEGraph,
+        equivalent: ClassId,
+        time_limit: Duration,
+    ) -> AnytimeExtraction<usize> {
+        self.simple_extractor()
+            .extract_anytime(egraph, equivalent, time_limit)
+    }
+}
+
+impl Extractor for TableExtractor {
+    type Cost = usize;
+
+    fn extract(
+        &self,
+        egraph: &dyn DynEGraph,
+        equivalent: ClassId,
+    ) -> Option<ExtractionResult<usize>> {
+        self.simple_extractor().extract(egraph, equivalent)
+    }
+
+    fn extract_within(
+        &self,
+        egraph: &dyn DynEGraph,
+        equivalent: ClassId,
+        classes: &HashSet<ClassId>,
+    ) -> Option<ExtractionResult<usize>> {
+        self.simple_extractor()
+            .extract_within(egraph, equivalent, classes)
+    }
+
+    fn extract_roots(
+        &self,
+        egraph: &dyn DynEGraph,
+        roots: &[ClassId],
+    ) -> Vec<Option<ExtractionResult<usize>>> {
+        self.simple_extractor().extract_roots(egraph, roots)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CostTable, TableExtractor};
+    use crate::{
+        language::Language,
+        macros::rules,
+        rewriting::egraph::{
+            DynEGraph, EGraph,
+            extraction::Extractor,
+            matching::bottom_up::BottomUpMatcher,
+            saturation::{SaturationConfig, Saturator, SimpleSaturator},
+        },
+    };
+
+    #[test]
+    fn extracts_with_loaded_costs() {
+        let table: CostTable =
+            serde_json::from_str(r#"{ "costs": { "*": 4, "<<": 2, "foo": 1 }, "default": 3 }"#)
+                .unwrap();
+        let lang = Language::simple_math();
+        assert_eq!(table.literal_cost, 1);
+        assert_eq!(table.symbol_cost("sin"), 3);
+        assert_eq!(table.unknown_symbols(&lang), ["foo"]);
+
+        let mut egraph = EGraph::<()>::default();
+        let root = egraph.add_expression(lang.parse_no_vars("(sin (* 5 2))").unwrap());
+        SimpleSaturator::new(Box::new(BottomUpMatcher)).saturate(
+            &mut egraph,
+            &rules!(lang; "(* $0 2)" => "(<< $0 1)"),
+            &SaturationConfig::default(),
+        );
+
+        let result = TableExtractor::new(&table, &lang)
+            .extract(&egraph, egraph.containing_class(root))
+            .unwrap();
+        assert_eq!(
+            result.winner(),
+            &lang.parse_no_vars("(sin (<< 5 1))").unwrap()
+        );
+        assert_eq!(*result.cost(), 3 + 2 + 1 + 1);
+    }
+}
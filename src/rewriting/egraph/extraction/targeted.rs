@@ -0,0 +1,309 @@
+//! Extraction of expressions resembling a target expression.
+//!
+//! Instead of the cheapest representative of a class, [`TargetedExtractor`] extracts the one
+//! closest to a given target in the top-down tree edit distance, in which the roots of compared
+//! trees are always matched and children are aligned like in the Levenshtein distance.
+//! Relabeling a node costs 1, while inserting or deleting a subtree costs its size.
+//! Subtrees of the extracted expression without counterparts in the target are
+//! the smallest expressions of their classes.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::language::{
+    expression::{Literal, VarFreeExpression},
+    symbol::{Symbol, SymbolId},
+};
+
+use super::{
+    super::{ClassId, DynEGraph, Node, NodeId},
+    ExtractionResult, Extractor, SimpleExtractor, all_classes, children_cost_sum,
+    extract_expression, sorted_classes,
+};
+
+const INFINITY: usize = usize::MAX;
+
+#[derive(PartialEq)]
+enum Label<'t> {
+    Literal(&'t Literal),
+    Symbol(SymbolId),
+}
+
+impl<'t> Label<'t> {
+    fn of_node(node: &'t Node) -> Self {
+        match node {
+            Node::Literal(literal) => Label::Literal(literal),
+            Node::Symbol(symbol) => Label::Symbol(symbol.id),
+        }
+    }
+}
+
+/// A subexpression of the target, with children given by their indices in the flattened target.
+struct TargetNode<'t> {
+    label: Label<'t>,
+    children: Vec<usize>,
+    size: usize,
+}
+
+fn flatten<'t>(expression: &'t VarFreeExpression, nodes: &mut Vec<TargetNode<'t>>) -> usize {
+    let index = nodes.len();
+    let (label, children) = match expression {
+        VarFreeExpression::Literal(literal) => (Label::Literal(literal), &[][..]),
+        VarFreeExpression::Symbol(symbol) => (Label::Symbol(symbol.id), &symbol.children[..]),
+    };
+    nodes.push(TargetNode {
+        label,
+        children: Vec::new(),
+        size: 1,
+    });
+
+    let children: Vec<_> = children.iter().map(|child| flatten(child, nodes)).collect();
+    nodes[index].size += children
+        .iter()
+        .map(|&child| nodes[child].size)
+        .sum::<usize>();
+    nodes[index].children = children;
+    index
+}
+
+/// Extracts the representative of a class closest to a target expression.
+///
+/// The cost of an extracted expression is its distance from the target.
+#[derive(Clone, Debug)]
+pub struct TargetedExtractor {
+    target: VarFreeExpression,
+}
+
+impl TargetedExtractor {
+    pub fn new(target: VarFreeExpression) -> Self {
+        Self { target }
+    }
+}
+
+impl Extractor for TargetedExtractor {
+    type Cost = usize;
+
+    fn extract(
+        &self,
+        egraph: &dyn DynEGraph,
+        equivalent: ClassId,
+    ) -> Option<ExtractionResult<usize>> {
+        self.extract_from(egraph, equivalent, &all_classes(egraph))
+    }
+
+    fn extract_within(
+        &self,
+        egraph: &dyn DynEGraph,
+        equivalent: ClassId,
+        classes: &HashSet<ClassId>,
+    ) -> Option<ExtractionResult<usize>> {
+        self.extract_from(egraph, equivalent, &sorted_classes(classes))
+    }
+}
+
+impl TargetedExtractor {
+    /// Extracts the expression of `equivalent` closest to the target, considering only `classes`.
+    fn extract_from(
+        &self,
+        egraph: &dyn DynEGraph,
+        equivalent: ClassId,
+        classes: &[ClassId],
+    ) -> Option<ExtractionResult<usize>> {
+        let size_extractor = SimpleExtractor::<usize, _, _>::new(
+            |_| 1,
+            |symbol, costs| Some(1 + children_cost_sum(symbol, costs)?),
+        );
+        let (smallest_nodes, sizes, _) =
+            size_extractor.calculate_costs_until(egraph, classes, None);
+
+        let mut target = Vec::new();
+        flatten(&self.target, &mut target);
+
+        let mut search = Search {
+            egraph,
+            target,
+            sizes,
+            smallest_nodes,
+            closest_nodes: HashMap::new(),
+        };
+
+        let equivalent = egraph.canonical_class(equivalent);
+        let cost = search.distance(equivalent, 0);
+        if cost == INFINITY {
+            return None;
+        }
+
+        Some(ExtractionResult {
+            winner: search.build(equivalent, 0),
+            cost,
+        })
+    }
+}
+
+struct Search<'a, 't> {
+    egraph: &'a dyn DynEGraph,
+    target: Vec<TargetNode<'t>>,
+    sizes: HashMap<ClassId, usize>,
+    smallest_nodes: HashMap<ClassId, NodeId>,
+    /// Distances of classes from target subexpressions, with the nodes achieving them
+    closest_nodes: HashMap<(ClassId, usize), (usize, NodeId)>,
+}
+
+impl Search<'_, '_> {
+    fn size(&self, class_id: ClassId) -> usize {
+        self.sizes.get(&class_id).copied().unwrap_or(INFINITY)
+    }
+
+    fn children(&self, node_id: NodeId) -> Vec<ClassId> {
+        self.egraph
+            .node(node_id)
+            .iter_children()
+            .map(|&child| self.egraph.canonical_class(child))
+            .collect()
+    }
+
+    /// Distance of the closest expression of class `class_id` from the target subexpression `t`.
+    fn distance(&mut self, class_id: ClassId, t: usize) -> usize {
+        if let Some(&(distance, _)) = self.closest_nodes.get(&(class_id, t)) {
+            return distance;
+        }
+
+        let mut closest = (INFINITY, None);
+        let node_ids: Vec<_> = self.egraph.nodes(class_id).iter().copied().collect();
+        for node_id in node_ids {
+            let relabel =
+                usize::from(Label::of_node(self.egraph.node(node_id)) != self.target[t].label);
+            let children = self.children(node_id);
+            let alignment = self.alignment(&children, t);
+            let distance =
+                relabel.saturating_add(alignment[children.len()][self.target[t].children.len()]);
+            if distance < closest.0 {
+                closest = (distance, Some(node_id));
+            }
+        }
+
+        if let (distance, Some(node_id)) = closest {
+            self.closest_nodes
+                .insert((class_id, t), (distance, node_id));
+        }
+        closest.0
+    }
+
+    /// Table of distances between prefixes of `children` and of the children of target subexpression `t`.
+    fn alignment(&mut self, children: &[ClassId], t: usize) -> Vec<Vec<usize>> {
+        let target_children = self.target[t].children.clone();
+        let mut table = vec![vec![0usize; target_children.len() + 1]; children.len() + 1];
+
+        for j in 1..=target_children.len() {
+            table[0][j] = table[0][j - 1].saturating_add(self.target[target_children[j - 1]].size);
+        }
+        for i in 1..=children.len() {
+            table[i][0] = table[i - 1][0].saturating_add(self.size(children[i - 1]));
+            for j in 1..=target_children.len() {
+                let delete = table[i - 1][j].saturating_add(self.size(children[i - 1]));
+                let insert =
+                    table[i][j - 1].saturating_add(self.target[target_children[j - 1]].size);
+                let substitute = table[i - 1][j - 1]
+                    .saturating_add(self.distance(children[i - 1], target_children[j - 1]));
+                table[i][j] = delete.min(insert).min(substitute);
+            }
+        }
+
+        table
+    }
+
+    /// Builds the closest expression of class `class_id` to the target subexpression `t`.
+    fn build(&mut self, class_id: ClassId, t: usize) -> VarFreeExpression {
+        let (_, node_id) = self.closest_nodes[&(class_id, t)];
+        let symbol = match self.egraph.node(node_id) {
+            Node::Literal(literal) => return VarFreeExpression::Literal(literal.clone()),
+            Node::Symbol(symbol) => symbol.id,
+        };
+
+        let children = self.children(node_id);
+        let target_children = self.target[t].children.clone();
+        let table = self.alignment(&children, t);
+
+        let mut built = Vec::with_capacity(children.len());
+        let (mut i, mut j) = (children.len(), target_children.len());
+        while i > 0 {
+            if j > 0
+                && table[i][j]
+                    == table[i - 1][j - 1]
+                        .saturating_add(self.distance(children[i - 1], target_children[j - 1]))
+            {
+                built.push(self.build(children[i - 1], target_children[j - 1]));
+                i -= 1;
+                j -= 1;
+            } else if j > 0
+                && table[i][j]
+                    == table[i][j - 1].saturating_add(self.target[target_children[j - 1]].size)
+            {
+                j -= 1;
+            } else {
+                built.push(extract_expression(
+                    self.egraph,
+                    &self.smallest_nodes,
+                    children[i - 1],
+                ));
+                i -= 1;
+            }
+        }
+        built.reverse();
+
+        VarFreeExpression::Symbol(Symbol {
+            id: symbol,
+            children: built,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TargetedExtractor;
+    use crate::{
+        language::Language,
+        macros::rules,
+        rewriting::egraph::{
+            DynEGraph, EGraph,
+            extraction::Extractor,
+            matching::bottom_up::BottomUpMatcher,
+            saturation::{SaturationConfig, Saturator, SimpleSaturator},
+        },
+    };
+
+    #[test]
+    fn extracts_closest_to_target() {
+        let lang = Language::simple_math().add_symbol("a").add_symbol("b");
+        let rules = rules!(lang;
+            "(+ $0 $1)" => "(+ $1 $0)",
+            "(* $0 2)" => "(<< $0 1)",
+        );
+        let mut egraph = EGraph::<()>::default();
+        let root = egraph.add_expression(lang.parse_no_vars("(* (+ (a) (b)) 2)").unwrap());
+        SimpleSaturator::new(Box::new(BottomUpMatcher)).saturate(
+            &mut egraph,
+            &rules,
+            &SaturationConfig::default(),
+        );
+        let root = egraph.containing_class(root);
+
+        let target = lang.parse_no_vars("(<< (+ (b) (a)) 1)").unwrap();
+        let result = TargetedExtractor::new(target.clone())
+            .extract(&egraph, root)
+            .unwrap();
+        assert_eq!(result.winner(), &target);
+        assert_eq!(*result.cost(), 0);
+
+        let result = TargetedExtractor::new(lang.parse_no_vars("(<< (+ (b) (a) 7) 5)").unwrap())
+            .extract(&egraph, root)
+            .unwrap();
+        assert_eq!(result.winner(), &target);
+        assert_eq!(*result.cost(), 2);
+
+        let result = TargetedExtractor::new(lang.parse_no_vars("(* (b))").unwrap())
+            .extract(&egraph, root)
+            .unwrap();
+        assert_eq!(result.winner().expect_symbol("*", &lang).len(), 2);
+        assert_eq!(*result.cost(), 4);
+    }
+}
@@ -0,0 +1,505 @@
+//! Matching modulo associativity and commutativity.
+//!
+//! Applications of associative and commutative symbols are flattened, both in patterns
+//! and in the e-graph, and their operands are matched in any order. Explicit rules
+//! reordering and regrouping such applications do not have to be applied to find their matches.
+//! Applications of symbols which are only flattened, see [`Language::declare_flattened`],
+//! are flattened in the same way, but their operands are matched in order.
+
+use std::collections::{HashMap, HashSet};
+
+use itertools::Itertools;
+
+use crate::{
+    language::{
+        Language,
+        expression::{Expression, VariableId},
+        symbol::{Symbol, SymbolId},
+    },
+    rewriting::egraph::{ClassId, DynEGraph, NodeId},
+};
+
+use super::{EGraphMatch, MatchCounters, Matcher};
+
+type Substitutions = HashMap<VariableId, ClassId>;
+
+/// Matcher treating symbols declared with [`Language::declare_ac`] as associative
+/// and commutative, and symbols declared with [`Language::declare_flattened`] as associative.
+///
+/// A pattern application of such a symbol matches a class if some of its nodes, with nested
+/// applications of the symbol in the classes of their children flattened, has operands
+/// matching the flattened operands of the pattern in some order. Variables are only matched
+/// against the resulting operands, so e.g. `(+ $0 $1)` matches `(+ 1 (+ 2 3))` with `$1`
+/// being the class of `(+ 2 3)`, but not with `$0` being the class of `(+ 1 2)`,
+/// unless that class is an operand of another node of the same class.
+/// Other symbols are matched like by [`TopDownMatcher`](super::top_down::TopDownMatcher).
+pub struct AcMatcher {
+    ac_symbols: HashSet<SymbolId>,
+    flattened_symbols: HashSet<SymbolId>,
+}
+
+impl AcMatcher {
+    /// Creates a matcher for the associative and commutative symbols
+    /// and the flattened symbols of `language`.
+    pub fn new(language: &Language) -> Self {
+        Self::from_symbols(language.ac_symbols().iter().copied())
+            .with_flattened_symbols(language.flattened_symbols().iter().copied())
+    }
+
+    /// Creates a matcher treating the symbols with IDs `ac_symbols` as associative and commutative.
+    pub fn from_symbols(ac_symbols: impl IntoIterator<Item = SymbolId>) -> Self {
+        Self {
+            ac_symbols: ac_symbols.into_iter().collect(),
+            flattened_symbols: HashSet::new(),
+        }
+    }
+
+    /// Makes the matcher treat the symbols with IDs `symbols` as associative.
+    pub fn with_flattened_symbols(mut self, symbols: impl IntoIterator<Item = SymbolId>) -> Self {
+        self.flattened_symbols = symbols.into_iter().collect();
+        self
+    }
+
+    /// Extends `bound` in all ways matching `expression` in the class with ID `class_id`.
+    fn try_match_at_class(
+        &self,
+        egraph: &dyn DynEGraph,
+        class_id: ClassId,
+        expression: &Expression,
+        bound: &Substitutions,
+        counters: &mut MatchCounters,
+    ) -> Vec<Substitutions> {
+        counters.attempts += 1;
+        match expression {
+            Expression::Literal(literal) => {
+                if egraph.class_contains_literal(class_id, literal) {
+                    vec![bound.clone()]
+                } else {
+                    Vec::new()
+                }
+            }
+            Expression::Symbol(symbol)
+                if self.ac_symbols.contains(&symbol.id)
+                    || self.flattened_symbols.contains(&symbol.id) =>
+            {
+                self.try_match_flattened_at_class(egraph, class_id, symbol, bound, counters)
+            }
+            Expression::Symbol(symbol) => {
+                let mut matches = Vec::new();
+                for &node_id in egraph.dyn_class(class_id).iter_nodes() {
+                    matches.extend(
+                        self.try_match_symbol_at_node(egraph, node_id, symbol, bound, counters),
+                    );
+                }
+                matches
+            }
+            Expression::Variable(variable_id) => match bound.get(variable_id) {
+                Some(&bound_class_id) if bound_class_id == class_id => vec![bound.clone()],
+                Some(_) => {
+                    counters.rejected_bindings += 1;
+                    Vec::new()
+                }
+                None => {
+                    let mut substitutions = bound.clone();
+                    substitutions.insert(*variable_id, class_id);
+                    vec![substitutions]
+                }
+            },
+        }
+    }
+
+    fn try_match_symbol_at_node(
+        &self,
+        egraph: &dyn DynEGraph,
+        node_id: NodeId,
+        symbol: &Symbol<Expression>,
+        bound: &Substitutions,
+        counters: &mut MatchCounters,
+    ) -> Vec<Substitutions> {
+        let Some(node_symbol) = egraph.node(node_id).try_as_symbol() else {
+            return Vec::new();
+        };
+        if !node_symbol.same_shape_as(symbol) {
+            return Vec::new();
+        }
+
+        let operands = symbol.children.iter().collect_vec();
+        self.try_match_in_order(egraph, &operands, &node_symbol.children, bound, counters)
+    }
+
+    fn try_match_flattened_at_class(
+        &self,
+        egraph: &dyn DynEGraph,
+        class_id: ClassId,
+        symbol: &Symbol<Expression>,
+        bound: &Substitutions,
+        counters: &mut MatchCounters,
+    ) -> Vec<Substitutions> {
+        let mut operands = Vec::new();
+        flatten_pattern(symbol, &mut operands);
+        let commutative = self.ac_symbols.contains(&symbol.id);
+
+        let mut matches = Vec::new();
+        for &node_id in egraph.dyn_class(class_id).iter_nodes() {
+            for flattened in self.flattened_operands(
+                egraph,
+                node_id,
+                symbol.id,
+                operands.len(),
+                commutative,
+                &mut vec![class_id],
+            ) {
+                if flattened.len() != operands.len() {
+                    continue;
+                }
+                if commutative {
+                    matches.extend(
+                        self.try_match_operands(egraph, &operands, &flattened, bound, counters),
+                    );
+                } else {
+                    matches.extend(
+                        self.try_match_in_order(egraph, &operands, &flattened, bound, counters),
+                    );
+                }
+            }
+        }
+
+        let mut seen = HashSet::new();
+        matches.retain(|substitutions| seen.insert(substitutions_key(substitutions)));
+        matches
+    }
+
+    /// Lists the operands of the node with ID `node_id` applying the symbol with ID `symbol_id`,
+    /// with nested applications of the symbol flattened in all possible ways.
+    /// Lists longer than `max_len` are skipped, and classes on `visiting` are not flattened,
+    /// which avoids cycles. Operands of commutative symbols are sorted.
+    fn flattened_operands(
+        &self,
+        egraph: &dyn DynEGraph,
+        node_id: NodeId,
+        symbol_id: SymbolId,
+        max_len: usize,
+        commutative: bool,
+        visiting: &mut Vec<ClassId>,
+    ) -> Vec<Vec<ClassId>> {
+        let Some(node_symbol) = egraph.node(node_id).try_as_symbol() else {
+            return Vec::new();
+        };
+        if node_symbol.id != symbol_id {
+            return Vec::new();
+        }
+
+        let mut flattenings: Vec<Vec<ClassId>> = vec![Vec::new()];
+        for &child_id in &node_symbol.children {
+            let child_id = egraph.canonical_class(child_id);
+            let mut child_flattenings = vec![vec![child_id]];
+            if !visiting.contains(&child_id) {
+                visiting.push(child_id);
+                for &child_node_id in egraph.dyn_class(child_id).iter_nodes() {
+                    child_flattenings.extend(self.flattened_operands(
+                        egraph,
+                        child_node_id,
+                        symbol_id,
+                        max_len,
+                        commutative,
+                        visiting,
+                    ));
+                }
+                visiting.pop();
+            }
+
+            flattenings = flattenings
+                .iter()
+                .cartesian_product(&child_flattenings)
+                .map(|(operands, child_operands)| [&operands[..], child_operands].concat())
+                .filter(|operands| operands.len() <= max_len)
+                .collect();
+        }
+
+        if commutative {
+            for operands in &mut flattenings {
+                operands.sort();
+            }
+        }
+        flattenings.sort();
+        flattenings.dedup();
+        flattenings
+    }
+
+    /// Extends `bound` in all ways matching `operands` against `classes` in any order.
+    fn try_match_operands(
+        &self,
+        egraph: &dyn DynEGraph,
+        operands: &[&Expression],
+        classes: &[ClassId],
+        bound: &Substitutions,
+        counters: &mut MatchCounters,
+    ) -> Vec<Substitutions> {
+        let Some((operand, rest)) = operands.split_first() else {
+            return vec![bound.clone()];
+        };
+
+        let mut matches = Vec::new();
+        for (index, &class_id) in classes.iter().enumerate() {
+            // Equal classes give equal matches
+            if classes[..index].contains(&class_id) {
+                continue;
+            }
+            let operand_matches =
+                self.try_match_at_class(egraph, class_id, operand, bound, counters);
+            if operand_matches.is_empty() {
+                continue;
+            }
+
+            let mut remaining = classes.to_vec();
+            remaining.remove(index);
+            for substitutions in operand_matches {
+                matches.extend(self.try_match_operands(
+                    egraph,
+                    rest,
+                    &remaining,
+                    &substitutions,
+                    counters,
+                ));
+            }
+        }
+
+        matches
+    }
+
+    /// Extends `bound` in all ways matching `operands` against `classes` in order.
+    fn try_match_in_order(
+        &self,
+        egraph: &dyn DynEGraph,
+        operands: &[&Expression],
+        classes: &[ClassId],
+        bound: &Substitutions,
+        counters: &mut MatchCounters,
+    ) -> Vec<Substitutions> {
+        let mut matches = vec![bound.clone()];
+        for (operand, &class_id) in operands.iter().zip(classes) {
+            matches = matches
+                .iter()
+                .flat_map(|substitutions| {
+                    self.try_match_at_class(egraph, class_id, operand, substitutions, counters)
+                })
+                .collect();
+        }
+        matches
+    }
+}
+
+impl Matcher for AcMatcher {
+    fn try_match(&self, egraph: &dyn DynEGraph, expression: &Expression) -> Vec<EGraphMatch> {
+        self.try_match_counted(egraph, expression, &mut MatchCounters::default())
+    }
+
+    fn try_match_counted(
+        &self,
+        egraph: &dyn DynEGraph,
+        expression: &Expression,
+        counters: &mut MatchCounters,
+    ) -> Vec<EGraphMatch> {
+        let mut matches = Vec::new();
+        for (&class_id, _) in egraph.dyn_classes() {
+            let found =
+                self.try_match_at_class(egraph, class_id, expression, &HashMap::new(), counters);
+            matches.extend(found.into_iter().map(|substitutions| EGraphMatch {
+                root: class_id,
+                substitutions,
+            }));
+        }
+        counters.matches += matches.len();
+        matches
+    }
+
+    fn try_match_at(
+        &self,
+        egraph: &dyn DynEGraph,
+        expression: &Expression,
+        class_id: ClassId,
+    ) -> Vec<EGraphMatch> {
+        self.try_match_at_class(
+            egraph,
+            class_id,
+            expression,
+            &HashMap::new(),
+            &mut MatchCounters::default(),
+        )
+        .into_iter()
+        .map(|substitutions| EGraphMatch {
+            root: class_id,
+            substitutions,
+        })
+        .collect()
+    }
+}
+
+/// Collects the operands of `symbol`, flattening its nested applications.
+fn flatten_pattern<'a>(symbol: &'a Symbol<Expression>, operands: &mut Vec<&'a Expression>) {
+    for child in &symbol.children {
+        match child {
+            Expression::Symbol(child_symbol) if child_symbol.id == symbol.id => {
+                flatten_pattern(child_symbol, operands)
+            }
+            _ => operands.push(child),
+        }
+    }
+}
+
+fn substitutions_key(substitutions: &Substitutions) -> Vec<(VariableId, ClassId)> {
+    substitutions
+        .iter()
+        .map(|(&variable_id, &class_id)| (variable_id, class_id))
+        .sorted()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        language::{Language, expression::Literal},
+        rewriting::{
+            egraph::{
+                DynEGraph, EGraph,
+                matching::{MatchCounters, Matcher, top_down::TopDownMatcher},
+                saturation::{SaturationConfig, Saturator, SimpleSaturator},
+            },
+            rule::Rule,
+        },
+    };
+
+    use super::AcMatcher;
+
+    fn language() -> Language {
+        Language::simple_math().declare_ac("+").declare_ac("*")
+    }
+
+    #[test]
+    fn standard_matches() {
+        super::super::tests::find_literal::<AcMatcher, ()>(AcMatcher::new(&language()));
+        super::super::tests::find_symbol::<AcMatcher, ()>(AcMatcher::new(&language()));
+        super::super::tests::not_find_symbol::<AcMatcher, ()>(AcMatcher::new(&language()));
+        super::super::tests::match_with_repeated_variables::<AcMatcher, ()>(AcMatcher::new(
+            &language(),
+        ));
+        super::super::tests::match_with_repeated_variables_fail::<AcMatcher, ()>(AcMatcher::new(
+            &language(),
+        ));
+    }
+
+    #[test]
+    fn matches_at_class() {
+        let lang = language();
+        let matcher = AcMatcher::new(&lang);
+        let (egraph, root) =
+            EGraph::<()>::from_expression_with_id(lang.parse_no_vars("(+ (sin 5) 1)").unwrap());
+        let pattern = lang.parse("(+ 1 $0)").unwrap();
+
+        let matches = matcher.try_match_at(&egraph, &pattern, root);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].root(), root);
+        let one = egraph.find_literal(Literal::Int(1)).unwrap();
+        assert!(matcher.try_match_at(&egraph, &pattern, one).is_empty());
+    }
+
+    #[test]
+    fn matches_modulo_commutativity() {
+        let lang = language();
+        let egraph = EGraph::<()>::from_expression(lang.parse_no_vars("(+ (sin 5) 1)").unwrap());
+        let pattern = lang.parse("(+ 1 $0)").unwrap();
+        let sin = egraph.containing_class(egraph.find_symbols(lang.get_id("sin"))[0]);
+
+        assert!(TopDownMatcher.try_match(&egraph, &pattern).is_empty());
+        let matches = AcMatcher::new(&lang).try_match(&egraph, &pattern);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].class_variable(0), sin);
+
+        let difference = lang.parse("(- 1 $0)").unwrap();
+        let egraph = EGraph::<()>::from_expression(lang.parse_no_vars("(- (sin 5) 1)").unwrap());
+        assert!(
+            AcMatcher::new(&lang)
+                .try_match(&egraph, &difference)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn matches_modulo_associativity() {
+        let lang = language();
+        let mut egraph =
+            EGraph::<()>::from_expression(lang.parse_no_vars("(* (+ (+ 1 2) 3) 4)").unwrap());
+        let sum = egraph.add_expression(lang.parse_no_vars("(+ (+ 1 2) 3)").unwrap());
+        let sum = egraph.containing_class(sum);
+        let one = egraph.find_literal(Literal::Int(1)).unwrap();
+        let matcher = AcMatcher::new(&lang);
+
+        let matches = matcher.try_match(&egraph, &lang.parse("(+ 3 (+ $0 2))").unwrap());
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].root(), sum);
+        assert_eq!(matches[0].class_variable(0), one);
+
+        // All three operands are matched in every order
+        let matches = matcher.try_match(&egraph, &lang.parse("(+ $0 $1 $2)").unwrap());
+        assert_eq!(matches.len(), 6);
+        let mut counters = MatchCounters::default();
+        assert!(
+            matcher
+                .try_match_counted(&egraph, &lang.parse("(+ $0 $0 $1)").unwrap(), &mut counters)
+                .is_empty()
+        );
+        assert!(counters.rejected_bindings > 0);
+        assert!(
+            matcher
+                .try_match(&egraph, &lang.parse("(+ 1 2 3 4)").unwrap())
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn saturation_without_structural_rules() {
+        let lang = language();
+        let rules = vec![Rule::from_strings("(+ $0 0)", "$0", &lang)];
+        let mut egraph =
+            EGraph::<()>::from_expression(lang.parse_no_vars("(* (+ 0 (sin 1)) 2)").unwrap());
+        let root = egraph.class_count() - 1;
+
+        SimpleSaturator::new(Box::new(AcMatcher::new(&lang))).saturate(
+            &mut egraph,
+            &rules,
+            &SaturationConfig::default(),
+        );
+        let simplified = egraph.add_expression(lang.parse_no_vars("(* (sin 1) 2)").unwrap());
+        assert_eq!(
+            egraph.canonical_class(root),
+            egraph.canonical_class(simplified)
+        );
+    }
+
+    #[test]
+    fn matches_flattened_symbols_in_order() {
+        let lang = Language::simple_math().declare_flattened("-");
+        let mut egraph = EGraph::<()>::default()
+            .with_flattened_symbols(lang.flattened_symbols().iter().copied());
+        let difference = egraph.add_expression(lang.parse_no_vars("(- 1 (- 2 3))").unwrap());
+        let difference = egraph.containing_class(difference);
+        let three = egraph.find_literal(Literal::Int(3)).unwrap();
+        let matcher = AcMatcher::new(&lang);
+
+        let matches = matcher.try_match(&egraph, &lang.parse("(- (- 1 2) $0)").unwrap());
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].root(), difference);
+        assert_eq!(matches[0].class_variable(0), three);
+        assert_eq!(
+            matcher
+                .try_match(&egraph, &lang.parse("(- $0 $1 $2)").unwrap())
+                .len(),
+            1
+        );
+        assert!(
+            matcher
+                .try_match(&egraph, &lang.parse("(- 3 $0 $1)").unwrap())
+                .is_empty()
+        );
+    }
+}
@@ -10,16 +10,27 @@ use crate::{
     rewriting::egraph::{ClassId, DynEGraph},
 };
 
-use super::{EGraphMatch, Matcher};
+use super::{EGraphMatch, MatchCounters, Matcher};
 
+/// Matcher trying every assignment of classes to the variables of a pattern and looking up
+/// the nodes of the pattern from its leaves up to its root.
+///
+/// Patterns without variables, e.g. bare literals like `1`, are looked up once, and a bare
+/// variable matches every class, without enumerating assignments.
+///
+/// Variables are assigned one after another and every subpattern is looked up as soon as
+/// all of its variables are assigned, so an assignment of the first variables which is
+/// incompatible with the e-graph is rejected before the remaining variables are enumerated.
 pub struct BottomUpMatcher;
 
+type Assignment = HashMap<VariableId, ClassId>;
+
 impl BottomUpMatcher {
     fn try_match_with_variable_assignment(
         &self,
         egraph: &dyn DynEGraph,
         expression: &Expression,
-        assignment: &HashMap<VariableId, ClassId>,
+        assignment: &Assignment,
     ) -> Option<ClassId> {
         match expression {
             Expression::Literal(literal) => egraph.find_literal(literal.clone()),
@@ -32,7 +43,7 @@ impl BottomUpMatcher {
         &self,
         egraph: &dyn DynEGraph,
         symbol: &Symbol<Expression>,
-        assignment: &HashMap<VariableId, ClassId>,
+        assignment: &Assignment,
     ) -> Option<ClassId> {
         let children = symbol
             .children
@@ -45,29 +56,158 @@ impl BottomUpMatcher {
             children,
         })
     }
+
+    /// `true` if all `patterns` are found in `egraph` under `assignment`.
+    fn all_found(
+        &self,
+        egraph: &dyn DynEGraph,
+        patterns: &[&Expression],
+        assignment: &Assignment,
+        counters: &mut MatchCounters,
+    ) -> bool {
+        patterns.iter().all(|pattern| {
+            counters.attempts += 1;
+            self.try_match_with_variable_assignment(egraph, pattern, assignment)
+                .is_some()
+        })
+    }
+
+    /// Extends `assignment` of the first variables of `variables` with every class for the
+    /// next one, rejecting the extensions under which `checks` of that variable are not found,
+    /// and collects the matches of `expression` under the complete assignments.
+    #[allow(clippy::too_many_arguments)]
+    fn assign(
+        &self,
+        egraph: &dyn DynEGraph,
+        expression: &Expression,
+        variables: &[VariableId],
+        class_ids: &[ClassId],
+        checks: &[Vec<&Expression>],
+        assignment: &mut Assignment,
+        counters: &mut MatchCounters,
+        matches: &mut Vec<EGraphMatch>,
+    ) {
+        let Some(&variable_id) = variables.get(assignment.len()) else {
+            counters.attempts += 1;
+            if let Some(root) =
+                self.try_match_with_variable_assignment(egraph, expression, assignment)
+            {
+                matches.push(EGraphMatch {
+                    root,
+                    substitutions: assignment.clone(),
+                });
+            }
+            return;
+        };
+
+        let checks_now = &checks[assignment.len() + 1];
+        for &class_id in class_ids {
+            assignment.insert(variable_id, class_id);
+            if self.all_found(egraph, checks_now, assignment, counters) {
+                self.assign(
+                    egraph, expression, variables, class_ids, checks, assignment, counters, matches,
+                );
+            } else {
+                counters.rejected_bindings += 1;
+            }
+            assignment.remove(&variable_id);
+        }
+    }
+}
+
+/// Collects the subpatterns of `expression` into `checks`, indexed by one plus the position
+/// in `variables` of their last assigned variable, or at 0 if they have no variables.
+/// Only the largest subpatterns becoming complete with a variable are collected, as looking
+/// them up looks up their children, and `expression` itself is not collected.
+///
+/// Returns the position of the last assigned variable of `expression`, if it has variables.
+fn collect_checks<'a>(
+    expression: &'a Expression,
+    variables: &[VariableId],
+    checks: &mut [Vec<&'a Expression>],
+) -> Option<usize> {
+    match expression {
+        Expression::Literal(_) => None,
+        Expression::Variable(variable_id) => variables.iter().position(|id| id == variable_id),
+        Expression::Symbol(symbol) => {
+            let levels: Vec<_> = symbol
+                .children
+                .iter()
+                .map(|child| collect_checks(child, variables, checks))
+                .collect();
+            let level = levels.iter().copied().max().flatten();
+            for (child, child_level) in symbol.children.iter().zip(levels) {
+                if !matches!(child, Expression::Variable(_)) && child_level != level {
+                    checks[child_level.map_or(0, |level| level + 1)].push(child);
+                }
+            }
+            level
+        }
+    }
 }
 
 impl Matcher for BottomUpMatcher {
     fn try_match(&self, egraph: &dyn DynEGraph, expression: &Expression) -> Vec<EGraphMatch> {
-        expression
-            .variables()
-            .iter()
-            .map(|variable_id| {
-                egraph
-                    .dyn_classes()
-                    .into_iter()
-                    .map(|(class_id, _)| (*variable_id, *class_id))
-            })
-            .multi_cartesian_product()
-            .filter_map(|assignment| {
-                let substitutions = HashMap::from_iter(assignment);
-                self.try_match_with_variable_assignment(egraph, expression, &substitutions)
-                    .map(|class_id| EGraphMatch {
-                        root: class_id,
-                        substitutions,
-                    })
-            })
-            .collect()
+        self.try_match_counted(egraph, expression, &mut MatchCounters::default())
+    }
+
+    fn try_match_counted(
+        &self,
+        egraph: &dyn DynEGraph,
+        expression: &Expression,
+        counters: &mut MatchCounters,
+    ) -> Vec<EGraphMatch> {
+        let variables = expression.variables();
+        if variables.is_empty() {
+            counters.attempts += 1;
+            let matches: Vec<_> = self
+                .try_match_with_variable_assignment(egraph, expression, &HashMap::new())
+                .map(|class_id| EGraphMatch {
+                    root: class_id,
+                    substitutions: HashMap::new(),
+                })
+                .into_iter()
+                .collect();
+            counters.matches += matches.len();
+            return matches;
+        }
+        if let Expression::Variable(variable_id) = expression {
+            let matches: Vec<_> = egraph
+                .dyn_classes()
+                .into_iter()
+                .map(|(&class_id, _)| EGraphMatch {
+                    root: class_id,
+                    substitutions: HashMap::from([(*variable_id, class_id)]),
+                })
+                .collect();
+            counters.matches += matches.len();
+            return matches;
+        }
+
+        // Sorted, so that matches are found in the same order in every run
+        let variables: Vec<_> = variables.into_iter().sorted().collect();
+        let mut checks = vec![Vec::new(); variables.len() + 1];
+        collect_checks(expression, &variables, &mut checks);
+        let mut matches = Vec::new();
+        if self.all_found(egraph, &checks[0], &HashMap::new(), counters) {
+            let class_ids: Vec<_> = egraph
+                .dyn_classes()
+                .into_iter()
+                .map(|(&class_id, _)| class_id)
+                .collect();
+            self.assign(
+                egraph,
+                expression,
+                &variables,
+                &class_ids,
+                &checks,
+                &mut HashMap::new(),
+                counters,
+                &mut matches,
+            );
+        }
+        counters.matches += matches.len();
+        matches
     }
 }
 
@@ -80,6 +220,11 @@ mod tests {
         super::super::tests::find_literal::<BottomUpMatcher, ()>(BottomUpMatcher);
     }
 
+    #[test]
+    fn find_string_literal() {
+        super::super::tests::find_string_literal::<BottomUpMatcher, ()>(BottomUpMatcher);
+    }
+
     #[test]
     fn find_symbol() {
         super::super::tests::find_symbol::<BottomUpMatcher, ()>(BottomUpMatcher);
@@ -106,4 +251,53 @@ mod tests {
             BottomUpMatcher,
         );
     }
+
+    #[test]
+    fn match_literal_rooted() {
+        super::super::tests::match_literal_rooted::<BottomUpMatcher, ()>(BottomUpMatcher);
+    }
+
+    #[test]
+    fn match_variable_rooted() {
+        super::super::tests::match_variable_rooted::<BottomUpMatcher, ()>(BottomUpMatcher);
+    }
+
+    #[test]
+    fn match_at_class() {
+        super::super::tests::match_at_class::<BottomUpMatcher, ()>(BottomUpMatcher);
+    }
+
+    #[test]
+    fn rejects_assignments_eagerly() {
+        use crate::{
+            language::Language,
+            rewriting::egraph::{
+                DynEGraph, EGraph,
+                matching::{MatchCounters, Matcher},
+            },
+        };
+
+        let lang = Language::simple_math();
+        let egraph = EGraph::<()>::from_expression(
+            lang.parse_no_vars("(+ (* 2 (sin 5)) (* 3 (sin 5)))")
+                .unwrap(),
+        );
+        let counted = |pattern: &str| {
+            let mut counters = MatchCounters::default();
+            BottomUpMatcher.try_match_counted(
+                &egraph,
+                &lang.parse(pattern).unwrap(),
+                &mut counters,
+            );
+            counters
+        };
+
+        // `$2` is only enumerated for the assignments of `$0` and `$1` found in the e-graph
+        let repeated = counted("(+ (* $0 $1) (* $0 (sin $2)))");
+        let distinct = counted("(+ (* $0 $1) (* $3 (sin $2)))");
+        assert_eq!(repeated.matches, 0);
+        assert_eq!(distinct.matches, 1);
+        assert!(repeated.rejected_bindings > 0);
+        assert!(repeated.attempts < egraph.class_count().pow(3));
+    }
 }
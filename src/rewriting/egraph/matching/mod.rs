@@ -3,11 +3,14 @@
 //! This module provides algorithms for matching patterns (expressions with variables)
 //! against the expressions in an e-graph.
 
+pub mod ac;
 pub mod bottom_up;
 pub mod top_down;
 
 use std::collections::HashMap;
 
+use itertools::Itertools;
+
 use crate::language::expression::{Expression, VariableId};
 
 use super::{ClassId, DynEGraph};
@@ -24,6 +27,7 @@ pub struct EGraphMatch {
 
 impl EGraphMatch {
     /// Creates an empty match with no substitutions.
+    #[cfg(test)]
     fn empty(root: ClassId) -> Self {
         EGraphMatch {
             root,
@@ -48,17 +52,6 @@ impl EGraphMatch {
         Some(self)
     }
 
-    /// Same as [`EGraphMatch::merge`] but generalized to more than two matches
-    fn merge_multiple(root: ClassId, matches: Vec<EGraphMatch>) -> Option<EGraphMatch> {
-        let mut total_match = EGraphMatch::empty(root);
-
-        for child_match in matches {
-            total_match = total_match.merge(root, child_match.clone())?;
-        }
-
-        Some(total_match)
-    }
-
     /// Returns the ID of the class which was matched against the root of the pattern
     pub fn root(&self) -> ClassId {
         self.root
@@ -68,10 +61,114 @@ impl EGraphMatch {
     pub fn class_variable(&self, variable_id: VariableId) -> ClassId {
         self.substitutions[&variable_id]
     }
+
+    /// Returns the ID of the class which was matched against the variable with ID `variable_id`,
+    /// or `None` if the pattern does not contain the variable
+    pub fn try_class_variable(&self, variable_id: VariableId) -> Option<ClassId> {
+        self.substitutions.get(&variable_id).copied()
+    }
+}
+
+/// A simultaneous match of several patterns, whose shared variables are matched against
+/// the same classes. Roots of the patterns may be matched in unrelated classes.
+#[derive(Clone, Debug)]
+pub struct MultiMatch {
+    roots: Vec<ClassId>,
+    matching: EGraphMatch,
+}
+
+impl MultiMatch {
+    /// Returns the IDs of the classes which were matched against the roots of the patterns,
+    /// in the order of the patterns
+    pub fn roots(&self) -> &[ClassId] {
+        &self.roots
+    }
+
+    /// Returns the match of all variables of the patterns, rooted at the root of the first one
+    pub fn matching(&self) -> &EGraphMatch {
+        &self.matching
+    }
+
+    /// Extends the match with a match of another pattern if their substitutions agree
+    fn join(&self, other: &EGraphMatch) -> Option<MultiMatch> {
+        let mut roots = self.roots.clone();
+        roots.push(other.root);
+        let matching = self
+            .matching
+            .clone()
+            .merge(self.matching.root, other.clone())?;
+
+        Some(MultiMatch { roots, matching })
+    }
+}
+
+/// Counters of the work done by a [`Matcher`], see [`Matcher::try_match_counted`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MatchCounters {
+    /// Number of subpatterns tried against classes
+    pub attempts: usize,
+    /// Number of occurrences of already bound variables rejected because their classes differed
+    pub rejected_bindings: usize,
+    /// Number of matches found
+    pub matches: usize,
 }
 
 pub trait Matcher {
     fn try_match(&self, egraph: &dyn DynEGraph, expression: &Expression) -> Vec<EGraphMatch>;
+
+    /// Same as [`Matcher::try_match`], but adds the work done to `counters`.
+    /// Matchers which do not override it count only the found matches.
+    fn try_match_counted(
+        &self,
+        egraph: &dyn DynEGraph,
+        expression: &Expression,
+        counters: &mut MatchCounters,
+    ) -> Vec<EGraphMatch> {
+        let matches = self.try_match(egraph, expression);
+        counters.matches += matches.len();
+        matches
+    }
+
+    /// Finds all matches of `expression` rooted in the class with canonical ID `class_id`.
+    /// Matchers which do not override it match in the whole e-graph and filter the matches.
+    fn try_match_at(
+        &self,
+        egraph: &dyn DynEGraph,
+        expression: &Expression,
+        class_id: ClassId,
+    ) -> Vec<EGraphMatch> {
+        self.try_match(egraph, expression)
+            .into_iter()
+            .filter(|matching| matching.root == class_id)
+            .collect()
+    }
+
+    /// Finds all simultaneous matches of `patterns`, see [`MultiMatch`].
+    /// Returns no matches if there are no patterns.
+    fn try_match_multi(&self, egraph: &dyn DynEGraph, patterns: &[Expression]) -> Vec<MultiMatch> {
+        let Some((first, rest)) = patterns.split_first() else {
+            return Vec::new();
+        };
+
+        let mut matches = self
+            .try_match(egraph, first)
+            .into_iter()
+            .map(|matching| MultiMatch {
+                roots: vec![matching.root],
+                matching,
+            })
+            .collect_vec();
+        for pattern in rest {
+            let pattern_matches = self.try_match(egraph, pattern);
+            matches = matches
+                .iter()
+                .cartesian_product(&pattern_matches)
+                .filter_map(|(multi, matching)| multi.join(matching))
+                .collect();
+        }
+
+        matches
+    }
 }
 
 #[cfg(test)]
@@ -79,8 +176,11 @@ mod tests {
     use std::collections::HashMap;
 
     use crate::{
-        language::{Language, expression::Literal},
-        rewriting::egraph::{Analysis, DynEGraph, EGraph, Node},
+        language::{Language, expression::Literal, symbol::Symbol},
+        rewriting::{
+            egraph::{Analysis, DynEGraph, EGraph, Node},
+            rule::Rule,
+        },
     };
 
     use super::{EGraphMatch, Matcher};
@@ -124,6 +224,27 @@ mod tests {
         assert!(matches[0].substitutions.is_empty());
     }
 
+    pub fn find_string_literal<M: Matcher, A: Analysis>(matcher: M) {
+        let lang = Language::simple_math();
+        let pattern = lang.parse(r#"(+ "x" $0)"#).unwrap();
+        let expr = lang
+            .parse_no_vars(r#"(* (+ "x" "y") (+ "y" "x") (+ "x" 1))"#)
+            .unwrap();
+        let egraph = EGraph::<A>::from_expression(expr);
+
+        let x_id = egraph
+            .node_id(&Node::Literal(Literal::string("x")))
+            .unwrap();
+        let y_id = egraph
+            .node_id(&Node::Literal(Literal::string("y")))
+            .unwrap();
+        assert_ne!(egraph.containing_class(x_id), egraph.containing_class(y_id));
+
+        let matches = matcher.try_match(&egraph, &pattern);
+
+        assert_eq!(matches.len(), 2);
+    }
+
     pub fn find_symbol<M: Matcher, A: Analysis>(matcher: M) {
         let lang = Language::simple_math();
         let sin = lang.parse("(sin (+ 5 3))").unwrap();
@@ -225,4 +346,83 @@ mod tests {
 
         assert_eq!(matches.len(), 0);
     }
+
+    pub fn match_literal_rooted<M: Matcher, A: Analysis>(matcher: M) {
+        let lang = Language::simple_math();
+        let mut egraph = EGraph::<A>::from_expression(lang.parse_no_vars("(+ 1 3)").unwrap());
+
+        assert!(
+            matcher
+                .try_match(&egraph, &lang.parse("2").unwrap())
+                .is_empty()
+        );
+
+        let one = egraph.find_literal(Literal::Int(1)).unwrap();
+        let three = egraph.find_literal(Literal::Int(3)).unwrap();
+        egraph.merge_classes(one, three);
+        let matches = matcher.try_match(&egraph, &lang.parse("1").unwrap());
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].root, egraph.canonical_class(one));
+        assert!(matches[0].substitutions.is_empty());
+
+        // Applying the rule merges the literals, after which they match in the same class
+        let rule = Rule::from_strings("1", "2", &lang);
+        assert_eq!(rule.apply(&mut egraph, &matcher), 1);
+        let two = egraph.find_literal(Literal::Int(2)).unwrap();
+        assert_eq!(
+            matcher.try_match(&egraph, &lang.parse("3").unwrap())[0].root,
+            egraph.canonical_class(two)
+        );
+    }
+
+    pub fn match_at_class<M: Matcher, A: Analysis>(matcher: M) {
+        let lang = Language::simple_math();
+        let (egraph, root) =
+            EGraph::<A>::from_expression_with_id(lang.parse_no_vars("(+ 1 (+ 2 3))").unwrap());
+        let pattern = lang.parse("(+ $0 $1)").unwrap();
+        let inner = egraph
+            .find_symbol(Symbol {
+                id: lang.get_id("+"),
+                children: vec![
+                    egraph.find_literal(Literal::Int(2)).unwrap(),
+                    egraph.find_literal(Literal::Int(3)).unwrap(),
+                ],
+            })
+            .unwrap();
+
+        for class_id in [root, inner] {
+            let matches = matcher.try_match_at(&egraph, &pattern, class_id);
+            assert_eq!(matches.len(), 1);
+            assert_eq!(matches[0].root, class_id);
+        }
+        let one = egraph.find_literal(Literal::Int(1)).unwrap();
+        assert!(matcher.try_match_at(&egraph, &pattern, one).is_empty());
+    }
+
+    pub fn match_variable_rooted<M: Matcher, A: Analysis>(matcher: M) {
+        let lang = Language::simple_math();
+        let egraph =
+            EGraph::<A>::from_expression(lang.parse_no_vars("(* (sin 5) (+ 5 2))").unwrap());
+
+        let mut matches = matcher.try_match(&egraph, &lang.parse("$3").unwrap());
+        matches.sort_by_key(|matching| matching.root);
+        let mut class_ids: Vec<_> = egraph
+            .dyn_classes()
+            .into_iter()
+            .map(|(&class_id, _)| class_id)
+            .collect();
+        class_ids.sort();
+
+        assert_eq!(
+            matches
+                .iter()
+                .map(|matching| matching.root)
+                .collect::<Vec<_>>(),
+            class_ids
+        );
+        for matching in &matches {
+            assert_eq!(matching.substitutions.len(), 1);
+            assert_eq!(matching.substitutions[&3], matching.root);
+        }
+    }
 }
@@ -1,108 +1,166 @@
 use std::collections::HashMap;
 
-use itertools::Itertools;
-
 use crate::{
-    index_selector::IndexSelector,
-    language::{expression::Expression, symbol::Symbol},
-    rewriting::egraph::{ClassId, DynEGraph, NodeId},
+    language::expression::{Expression, VariableId},
+    rewriting::egraph::{ClassId, DynEGraph},
 };
 
-use super::{EGraphMatch, Matcher};
+use super::{EGraphMatch, MatchCounters, Matcher};
 
+/// Matcher searching for patterns from their roots down to their leaves.
+///
+/// Matches are generated by backtracking: children of a pattern are matched one after another
+/// under the substitutions of the already matched ones, so a repeated variable is checked
+/// against its binding as soon as it is reached and incompatible matches of the remaining
+/// children are never generated.
 pub struct TopDownMatcher;
 
+type Substitutions = HashMap<VariableId, ClassId>;
+type Found<'a> = dyn FnMut(&mut Substitutions, &mut MatchCounters) + 'a;
+
 impl TopDownMatcher {
-    fn try_match_symbol_at_node(
+    /// Calls `found` with every extension of `substitutions` matching `expression`
+    /// in the class with ID `class_id`.
+    fn match_at_class(
         &self,
         egraph: &dyn DynEGraph,
-        node_id: NodeId,
-        symbol: &Symbol<Expression>,
-    ) -> Vec<EGraphMatch> {
-        let node = egraph.node(node_id);
-        let Some(node_symbol) = node.try_as_symbol() else {
-            return Vec::new();
-        };
-
-        if !node_symbol.same_shape_as(symbol) {
-            return Vec::new();
+        class_id: ClassId,
+        expression: &Expression,
+        substitutions: &mut Substitutions,
+        counters: &mut MatchCounters,
+        found: &mut Found,
+    ) {
+        counters.attempts += 1;
+        match expression {
+            Expression::Literal(literal) => {
+                if egraph.class_contains_literal(class_id, literal) {
+                    found(substitutions, counters);
+                }
+            }
+            Expression::Symbol(symbol) => {
+                for &node_id in egraph.dyn_class(class_id).iter_nodes() {
+                    if let Some(node_symbol) = egraph.node(node_id).try_as_symbol()
+                        && node_symbol.same_shape_as(symbol)
+                    {
+                        self.match_children(
+                            egraph,
+                            &node_symbol.children,
+                            &symbol.children,
+                            substitutions,
+                            counters,
+                            found,
+                        );
+                    }
+                }
+            }
+            Expression::Variable(variable_id) => match substitutions.get(variable_id) {
+                Some(&bound) if bound == class_id => found(substitutions, counters),
+                Some(_) => counters.rejected_bindings += 1,
+                None => {
+                    substitutions.insert(*variable_id, class_id);
+                    found(substitutions, counters);
+                    substitutions.remove(variable_id);
+                }
+            },
         }
+    }
 
-        let matches = egraph
-            .node(node_id)
-            .iter_children()
-            .zip_eq(symbol.children.iter())
-            .map(|(&child_class_id, child_expression)| {
-                self.try_match_at_class(egraph, child_class_id, child_expression)
-            })
-            .collect_vec();
-
-        let index_matches = IndexSelector::new(
-            matches
-                .iter()
-                .map(|child_matches| child_matches.len())
-                .collect_vec(),
+    /// Pushes all matches of `expression` rooted in the class with ID `class_id` to `matches`.
+    fn collect_matches(
+        &self,
+        egraph: &dyn DynEGraph,
+        class_id: ClassId,
+        expression: &Expression,
+        counters: &mut MatchCounters,
+        matches: &mut Vec<EGraphMatch>,
+    ) {
+        self.match_at_class(
+            egraph,
+            class_id,
+            expression,
+            &mut HashMap::new(),
+            counters,
+            &mut |substitutions, counters| {
+                counters.matches += 1;
+                matches.push(EGraphMatch {
+                    root: class_id,
+                    substitutions: substitutions.clone(),
+                });
+            },
         );
+    }
 
-        let mut all_matches = Vec::new();
-
-        for indices in index_matches {
-            let children_matches = indices
-                .iter()
-                .enumerate()
-                .map(|(idx, inner_idx)| &matches[idx][*inner_idx])
-                .collect_vec();
-
-            let root = egraph.containing_class(node_id);
+    /// Calls `found` with every extension of `substitutions` matching `expressions`
+    /// in the classes with IDs `class_ids`, in order.
+    fn match_children(
+        &self,
+        egraph: &dyn DynEGraph,
+        class_ids: &[ClassId],
+        expressions: &[Expression],
+        substitutions: &mut Substitutions,
+        counters: &mut MatchCounters,
+        found: &mut Found,
+    ) {
+        let (Some((&class_id, class_ids)), Some((expression, expressions))) =
+            (class_ids.split_first(), expressions.split_first())
+        else {
+            found(substitutions, counters);
+            return;
+        };
 
-            if let Some(matching) = EGraphMatch::merge_multiple(
-                root,
-                children_matches.iter().map(|x| (*x).clone()).collect_vec(),
-            ) {
-                all_matches.push(matching);
-            }
-        }
+        self.match_at_class(
+            egraph,
+            class_id,
+            expression,
+            substitutions,
+            counters,
+            &mut |substitutions, counters| {
+                self.match_children(
+                    egraph,
+                    class_ids,
+                    expressions,
+                    substitutions,
+                    counters,
+                    found,
+                )
+            },
+        );
+    }
+}
 
-        all_matches
+impl Matcher for TopDownMatcher {
+    fn try_match(&self, egraph: &dyn DynEGraph, expression: &Expression) -> Vec<EGraphMatch> {
+        self.try_match_counted(egraph, expression, &mut MatchCounters::default())
     }
 
-    fn try_match_at_class(
+    fn try_match_counted(
         &self,
         egraph: &dyn DynEGraph,
-        class_id: ClassId,
         expression: &Expression,
+        counters: &mut MatchCounters,
     ) -> Vec<EGraphMatch> {
-        match expression {
-            Expression::Literal(literal) => {
-                if egraph.class_contains_literal(class_id, literal) {
-                    vec![EGraphMatch {
-                        root: class_id,
-                        substitutions: HashMap::new(),
-                    }]
-                } else {
-                    Vec::new()
-                }
-            }
-            Expression::Symbol(symbol) => egraph
-                .dyn_class(class_id)
-                .iter_nodes()
-                .flat_map(|&node_id| self.try_match_symbol_at_node(egraph, node_id, symbol))
-                .collect(),
-            Expression::Variable(variable_id) => vec![EGraphMatch {
-                root: class_id,
-                substitutions: HashMap::from([(*variable_id, class_id)]),
-            }],
+        let mut matches = Vec::new();
+        for (&class_id, _) in egraph.dyn_classes() {
+            self.collect_matches(egraph, class_id, expression, counters, &mut matches);
         }
+        matches
     }
-}
 
-impl Matcher for TopDownMatcher {
-    fn try_match(&self, egraph: &dyn DynEGraph, expression: &Expression) -> Vec<EGraphMatch> {
-        egraph
-            .dyn_classes()
-            .iter()
-            .flat_map(|(class_id, _)| self.try_match_at_class(egraph, **class_id, expression))
-            .collect()
+    fn try_match_at(
+        &self,
+        egraph: &dyn DynEGraph,
+        expression: &Expression,
+        class_id: ClassId,
+    ) -> Vec<EGraphMatch> {
+        let mut matches = Vec::new();
+        self.collect_matches(
+            egraph,
+            class_id,
+            expression,
+            &mut MatchCounters::default(),
+            &mut matches,
+        );
+        matches
     }
 }
 
@@ -115,6 +173,11 @@ mod tests {
         super::super::tests::find_literal::<TopDownMatcher, ()>(TopDownMatcher);
     }
 
+    #[test]
+    fn find_string_literal() {
+        super::super::tests::find_string_literal::<TopDownMatcher, ()>(TopDownMatcher);
+    }
+
     #[test]
     fn find_symbol() {
         super::super::tests::find_symbol::<TopDownMatcher, ()>(TopDownMatcher);
@@ -141,4 +204,50 @@ mod tests {
             TopDownMatcher,
         );
     }
+
+    #[test]
+    fn match_literal_rooted() {
+        super::super::tests::match_literal_rooted::<TopDownMatcher, ()>(TopDownMatcher);
+    }
+
+    #[test]
+    fn match_variable_rooted() {
+        super::super::tests::match_variable_rooted::<TopDownMatcher, ()>(TopDownMatcher);
+    }
+
+    #[test]
+    fn match_at_class() {
+        super::super::tests::match_at_class::<TopDownMatcher, ()>(TopDownMatcher);
+    }
+
+    #[test]
+    fn checks_repeated_variables_eagerly() {
+        use crate::{
+            language::Language,
+            rewriting::egraph::{
+                EGraph,
+                matching::{MatchCounters, Matcher},
+            },
+        };
+
+        let lang = Language::simple_math();
+        let egraph = EGraph::<()>::from_expression(
+            lang.parse_no_vars("(+ (* 2 (sin 5)) (* 3 (sin 5)))")
+                .unwrap(),
+        );
+        let counted = |pattern: &str| {
+            let mut counters = MatchCounters::default();
+            TopDownMatcher.try_match_counted(&egraph, &lang.parse(pattern).unwrap(), &mut counters);
+            counters
+        };
+
+        // `(sin $2)` is never tried once `$0` conflicts with its binding
+        let repeated = counted("(+ (* $0 $1) (* $0 (sin $2)))");
+        let distinct = counted("(+ (* $0 $1) (* $3 (sin $2)))");
+        assert_eq!(repeated.matches, 0);
+        assert_eq!(distinct.matches, 1);
+        assert_eq!(repeated.rejected_bindings, 1);
+        assert_eq!(distinct.rejected_bindings, 0);
+        assert!(repeated.attempts < distinct.attempts);
+    }
 }
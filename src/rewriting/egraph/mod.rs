@@ -12,50 +12,107 @@
 //! - Various matching, extraction, and saturation algorithms
 
 pub mod class;
+pub mod compaction;
+pub mod diff;
 pub mod drawing;
 pub mod extraction;
 pub mod matching;
 pub mod node;
+mod normal_forms;
 pub mod saturation;
+mod serialization;
+mod snapshot;
+pub mod term_graph;
+#[cfg(feature = "verify")]
+pub mod verify;
 
 pub use class::Class;
 use class::DynClass;
 pub use class::analysis::Analysis;
+pub use compaction::{CompactionError, CompactionRemap};
+pub use diff::{EGraphDiff, diff};
 pub use node::Node;
+pub use snapshot::Snapshot;
+pub use term_graph::TermGraph;
 
-use std::collections::{HashMap, HashSet, hash_map};
+use std::collections::{BTreeSet, HashMap, HashSet, hash_map};
+use std::hash::{BuildHasherDefault, DefaultHasher};
+use std::sync::Arc;
+use std::vec;
 
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use class::table_local_cost::SymbolCosts;
 
 use crate::{
     language::{
         expression::{Literal, MixedExpression, VarFreeExpression},
+        sorts::{Sort, Sorts},
         symbol::{Symbol, SymbolId},
     },
+    metrics::EGraphMetrics,
     seen::Seen,
-    union_find::UnionFind,
+    union_find::{UnionFind, UnionFindStats},
 };
 
 pub type NodeId = usize;
 pub type ClassId = usize;
 
+/// Hasher of the containers of e-graphs. Unlike the default one, it is not randomly seeded,
+/// so the containers are iterated in the same order in every run, and with them
+/// the order of matches, the surviving duplicate nodes and extraction ties are reproducible.
+pub type FixedState = BuildHasherDefault<DefaultHasher>;
+/// [`HashMap`] with a [`FixedState`] hasher.
+pub type FixedHashMap<K, V> = HashMap<K, V, FixedState>;
+/// [`HashSet`] with a [`FixedState`] hasher.
+pub type FixedHashSet<T> = HashSet<T, FixedState>;
+
 /// An e-graph (equality graph) data structure.
 ///
 /// An e-graph efficiently represents a set of expressions and their equivalences.
 /// It uses a union-find data structure to track equivalence classes and maintains
-/// a hashcons to ensure node uniqueness.
+/// a hashcons to ensure node uniqueness. If the e-graph is given [`Sorts`],
+/// classes of different sorts are never merged. Classes can be tagged with strings,
+/// e.g. with the inputs they stem from, see [`EGraph::tag_class`]. Nested applications of flattened symbols
+/// in added expressions are merged into single nodes, see [`EGraph::with_flattened_symbols`].
 ///
 /// # Type Parameters
 ///
 /// * `A` - The analysis type for computing metadata about equivalence classes
-#[derive(Default, Clone)]
+///
+/// E-graphs can be serialized together with the analysis data of their classes,
+/// see [`EGraph::save_json`] and [`EGraph::load_json`]. Alternative branches can be explored
+/// without cloning the e-graph by rolling it back to a [`Snapshot`].
+#[derive(Default, Clone, Serialize, Deserialize)]
+#[serde(try_from = "serialization::EGraphData<A>")]
+#[serde(bound(serialize = "A: Serialize", deserialize = "A: Deserialize<'de>"))]
 pub struct EGraph<A: Analysis> {
     union_find: UnionFind,
-    nodes: HashMap<NodeId, Node>,
+    nodes: FixedHashMap<NodeId, Node>,
+    // Always kept behind canonical IDs
+    classes: FixedHashMap<ClassId, Class<A>>,
+    // Hashcons for canonical nodes, rebuilt after deserialization
+    #[serde(skip)]
+    node_hashcons: FixedHashMap<Node, NodeId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sorts: Option<Sorts>,
     // Always kept behind canonical IDs
-    classes: HashMap<ClassId, Class<A>>,
-    // Hashcons for canonical nodes
-    node_hashcons: HashMap<Node, NodeId>,
+    #[serde(skip_serializing_if = "HashSet::is_empty")]
+    frozen: FixedHashSet<ClassId>,
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    flattened: BTreeSet<SymbolId>,
+    // Always kept behind canonical IDs, without empty sets of tags
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    tags: FixedHashMap<ClassId, BTreeSet<String>>,
+    // Undo log of open snapshots
+    #[serde(skip)]
+    history: snapshot::History<A>,
+    #[serde(skip)]
+    metrics: EGraphMetrics,
+    // Costs used by analyses of added nodes, not serialized with their data
+    #[serde(skip)]
+    costs: Option<Arc<SymbolCosts>>,
 }
 
 impl<A: Analysis> EGraph<A> {
@@ -74,6 +131,210 @@ impl<A: Analysis> EGraph<A> {
         Self::from_expression_with_id(expression).0
     }
 
+    /// Creates an e-graph representing all of `expressions`, e.g. to simplify them
+    /// in a single saturation, and returns it with the IDs of their classes in their order.
+    /// Common subexpressions of the expressions are shared.
+    pub fn from_expressions(expressions: Vec<VarFreeExpression>) -> (Self, Vec<ClassId>) {
+        let mut egraph = Self::default();
+
+        let class_ids = expressions
+            .into_iter()
+            .map(|expression| egraph.add_expression(expression))
+            .collect();
+
+        (egraph, class_ids)
+    }
+
+    /// Adds both expressions and merges their classes, assuming that they are equal, e.g. `x = 2`.
+    /// An e-graph of such assumptions can serve as the background in which other expressions
+    /// are simplified, see [`TermRewritingSystem::rewrite_with_assumptions`].
+    /// Returns the canonical ID of the merged class as `Old(id)` if the expressions were
+    /// equal already or cannot be merged because of different sorts, or `New(id)` otherwise.
+    ///
+    /// [`TermRewritingSystem::rewrite_with_assumptions`]: crate::rewriting::system::TermRewritingSystem::rewrite_with_assumptions
+    pub fn assume_equal(
+        &mut self,
+        expression_1: VarFreeExpression,
+        expression_2: VarFreeExpression,
+    ) -> Seen<ClassId> {
+        let class_1_id = self.add_expression(expression_1);
+        let class_2_id = self.add_expression(expression_2);
+        self.merge_classes(class_1_id, class_2_id)
+    }
+
+    /// Makes the e-graph many-sorted, refusing merges of classes with different sorts.
+    pub fn with_sorts(mut self, sorts: Sorts) -> Self {
+        self.sorts = Some(sorts);
+        self
+    }
+
+    /// Makes analyses looking up costs of symbols, e.g.
+    /// [`TableLocalCost`](class::table_local_cost::TableLocalCost), use `costs` for nodes added
+    /// afterwards, so it should be called before any expression is added.
+    /// Costs are not serialized, see [`EGraph::save_json`].
+    pub fn with_costs(mut self, costs: Arc<SymbolCosts>) -> Self {
+        self.costs = Some(costs);
+        self
+    }
+
+    /// Returns the costs used by analyses, see [`EGraph::with_costs`].
+    pub fn costs(&self) -> Option<&SymbolCosts> {
+        self.costs.as_deref()
+    }
+
+    /// Makes the e-graph flatten the symbols with IDs `symbols`, so that e.g. `(+ a (+ b c))`
+    /// is added as `(+ a b c)` if `+` is flattened, see [`Language::declare_flattened`].
+    /// Only nested applications in a single added expression are flattened,
+    /// and not applications in the classes of its children.
+    ///
+    /// [`Language::declare_flattened`]: crate::language::Language::declare_flattened
+    pub fn with_flattened_symbols(mut self, symbols: impl IntoIterator<Item = SymbolId>) -> Self {
+        self.flattened = symbols.into_iter().collect();
+        self
+    }
+
+    /// Splices the children of `symbol` into the remaining children of its parent, the last
+    /// symbol on `pending`, if both apply the same flattened symbol, returning the next child.
+    /// Otherwise returns `symbol` back.
+    fn flatten_into_parent<E>(
+        &self,
+        pending: &mut [(SymbolId, vec::IntoIter<E>, Vec<usize>)],
+        symbol: Symbol<E>,
+    ) -> Result<E, Symbol<E>> {
+        match pending.last_mut() {
+            Some((parent_id, remaining, _))
+                if *parent_id == symbol.id
+                    && !symbol.children.is_empty()
+                    && self.flattened.contains(&symbol.id) =>
+            {
+                let mut children = symbol.children;
+                children.extend(remaining.by_ref());
+                *remaining = children.into_iter();
+                Ok(remaining.next().unwrap())
+            }
+            _ => Err(symbol),
+        }
+    }
+
+    /// Returns the sort of the class with id `class_id`, or `None` if the e-graph is unsorted
+    /// or the class contains only untyped nodes.
+    pub fn class_sort(&self, class_id: ClassId) -> Option<&Sort> {
+        let sorts = self.sorts.as_ref()?;
+        self.class(class_id)
+            .iter_nodes()
+            .find_map(|&node_id| match self.node(node_id) {
+                Node::Literal(literal) => sorts.literal_sort(literal.kind()),
+                Node::Symbol(symbol) => sorts.symbol_sort(symbol.id),
+            })
+    }
+
+    /// Freezes the class with id `class_id`.
+    ///
+    /// Rule applications never add new nodes to frozen classes, so the expressions they represent
+    /// stay as they are, though frozen classes can still be matched and merged with other
+    /// existing classes. A class created by merging a frozen class is frozen.
+    pub fn freeze_class(&mut self, class_id: ClassId) {
+        let class_id = self.canonical_class(class_id);
+        self.record_frozen(class_id);
+        self.frozen.insert(class_id);
+    }
+
+    /// Freezes the class with id `class_id` together with all classes reachable from it
+    /// through children of its nodes.
+    pub fn freeze_class_with_descendants(&mut self, class_id: ClassId) {
+        let mut stack = vec![self.canonical_class(class_id)];
+        while let Some(class_id) = stack.pop() {
+            if self.frozen.contains(&class_id) {
+                continue;
+            }
+            self.record_frozen(class_id);
+            self.frozen.insert(class_id);
+            for &node_id in self.class(class_id).nodes_ids() {
+                stack.extend(
+                    self.node(node_id)
+                        .iter_children()
+                        .map(|&child| self.canonical_class(child)),
+                );
+            }
+        }
+    }
+
+    /// Unfreezes the class with id `class_id`, without affecting its descendants.
+    pub fn unfreeze_class(&mut self, class_id: ClassId) {
+        let class_id = self.canonical_class(class_id);
+        self.record_frozen(class_id);
+        self.frozen.remove(&class_id);
+    }
+
+    /// `true` if the class with id `class_id` is frozen, see [`EGraph::freeze_class`].
+    pub fn is_frozen(&self, class_id: ClassId) -> bool {
+        self.frozen.contains(&self.canonical_class(class_id))
+    }
+
+    /// Tags the class with id `class_id` with `tag`, e.g. `origin: start-term`.
+    ///
+    /// Tags are metadata of classes which do not affect rewriting. A class created by merging
+    /// tagged classes has the tags of both, so e.g. classes unifying two tagged input terms
+    /// can be found with [`EGraph::classes_with_tag`].
+    pub fn tag_class(&mut self, class_id: ClassId, tag: &str) {
+        let class_id = self.canonical_class(class_id);
+        self.record_tags(class_id);
+        self.tags
+            .entry(class_id)
+            .or_default()
+            .insert(String::from(tag));
+    }
+
+    /// Removes `tag` from the class with id `class_id`, returning `true` if it had the tag.
+    pub fn untag_class(&mut self, class_id: ClassId, tag: &str) -> bool {
+        let class_id = self.canonical_class(class_id);
+        if !self.has_tag(class_id, tag) {
+            return false;
+        }
+
+        self.record_tags(class_id);
+        let tags = self.tags.get_mut(&class_id).unwrap();
+        tags.remove(tag);
+        if tags.is_empty() {
+            self.tags.remove(&class_id);
+        }
+        true
+    }
+
+    /// Returns the sorted tags of the class with id `class_id`.
+    pub fn class_tags(&self, class_id: ClassId) -> Vec<&str> {
+        self.tags
+            .get(&self.canonical_class(class_id))
+            .map(|tags| tags.iter().map(String::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns the sorted canonical IDs of classes tagged with `tag`.
+    pub fn classes_with_tag(&self, tag: &str) -> Vec<ClassId> {
+        self.tags
+            .iter()
+            .filter(|(_, tags)| tags.contains(tag))
+            .map(|(&class_id, _)| class_id)
+            .sorted()
+            .collect()
+    }
+
+    /// Returns the ID of the class containing `expression`, if the e-graph already contains it.
+    pub fn find_mixed_expression(&self, expression: &MixedExpression) -> Option<ClassId> {
+        match expression {
+            MixedExpression::Literal(literal) => self.find_literal(literal.clone()),
+            MixedExpression::Symbol(symbol) => self.find_symbol(Symbol {
+                id: symbol.id,
+                children: symbol
+                    .children
+                    .iter()
+                    .map(|child| self.find_mixed_expression(child))
+                    .collect::<Option<_>>()?,
+            }),
+            MixedExpression::Class(class_id) => Some(self.canonical_class(*class_id)),
+        }
+    }
+
     /// Adds a node to the egraph, returning `Old(id)` if the node exists, or `New(id)` if the node
     /// has been added by this call
     fn add_node(&mut self, mut node: Node) -> Seen<NodeId> {
@@ -85,6 +346,8 @@ impl<A: Analysis> EGraph<A> {
 
         let class_id = self.union_find.add();
         let node_id = class_id;
+        self.record_node(node_id);
+        self.record_class(class_id);
 
         if let Node::Symbol(symbol) = &node {
             for child in &symbol.children {
@@ -99,10 +362,54 @@ impl<A: Analysis> EGraph<A> {
 
         let class = Class::from_node(self, node_id);
         self.classes.insert(class_id, class);
+        self.modify_class(class_id);
 
         Seen::New(node_id)
     }
 
+    /// Lets the analysis modify the class with canonical id `class_id`, see [`Analysis::modify`]
+    fn modify_class(&mut self, class_id: ClassId) {
+        A::modify(self, class_id, |analysis| analysis);
+    }
+
+    /// Remakes the analysis data of ancestors of a class whose data changed,
+    /// for as long as it keeps changing, see [`Analysis::propagates`].
+    /// The analysis can then modify the classes whose data changed.
+    fn propagate_analysis(&mut self, class_id: ClassId) {
+        let mut changed = vec![class_id];
+        let mut remade = Vec::new();
+        while let Some(class_id) = changed.pop() {
+            let parents: Vec<ClassId> = self
+                .class(class_id)
+                .parents_ids()
+                .iter()
+                .map(|&parent_id| self.containing_class(parent_id))
+                .unique()
+                .collect();
+
+            for parent_id in parents {
+                let class = &self.classes[&parent_id];
+                let analysis = class
+                    .nodes_ids()
+                    .iter()
+                    .map(|&node_id| A::make(self, node_id))
+                    .reduce(A::merge)
+                    .unwrap_or_default();
+                if analysis.propagates(class.analysis()) {
+                    self.class_mut(parent_id).set_analysis(analysis);
+                    changed.push(parent_id);
+                    remade.push(parent_id);
+                }
+            }
+        }
+
+        // Modifications can merge classes, so they wait until the data stops changing
+        for class_id in remade {
+            let class_id = self.canonical_class(class_id);
+            self.modify_class(class_id);
+        }
+    }
+
     fn add_parent(&mut self, class_id: ClassId, parent_id: NodeId) {
         self.class_mut(class_id).parents_ids_mut().insert(parent_id);
     }
@@ -118,12 +425,18 @@ impl<A: Analysis> EGraph<A> {
 
     pub fn class_mut(&mut self, class_id: ClassId) -> &mut Class<A> {
         let class_id = self.canonical_class(class_id);
+        self.record_class(class_id);
         self.classes.get_mut(&class_id).unwrap()
     }
 
     /// Makes all nodes in an eclass have canonical children
     fn make_class_canonical(&mut self, class_id: ClassId) {
         let class_id = self.canonical_class(class_id);
+        if self.open_snapshots() > 0 {
+            for node_id in self.classes[&class_id].nodes_ids().clone() {
+                self.record_node(node_id);
+            }
+        }
         let class = &self.classes[&class_id];
         for &node_id in class.nodes_ids() {
             // Make node canonical
@@ -138,6 +451,7 @@ impl<A: Analysis> EGraph<A> {
         // has it as one of its children, and a merge has just been performed.
         self.make_class_canonical(class_id);
 
+        self.metrics.class_rebuilds.increment();
         let class = &self.classes[&class_id];
 
         // Find duplicate nodes
@@ -154,12 +468,18 @@ impl<A: Analysis> EGraph<A> {
         }
 
         // Remove duplicate nodes
+        self.record_class(class_id);
         let class = self.classes.get_mut(&class_id).unwrap();
         for node_id in to_remove {
             class.nodes_ids_mut().remove(&node_id);
         }
 
         // Make all nodes in parents have canonical class IDs
+        if self.open_snapshots() > 0 {
+            for parent in self.classes[&class_id].parents_ids().clone() {
+                self.record_node(parent);
+            }
+        }
         let class = &self.classes[&class_id];
         for parent in class.parents_ids() {
             for child in self.nodes.get_mut(parent).unwrap().iter_mut_children() {
@@ -203,15 +523,184 @@ impl<A: Analysis> EGraph<A> {
             .collect_vec()
     }
 
+    /// `true` if all equivalences represented by `other` hold in this e-graph,
+    /// i.e. if all nodes of every class of `other` represent terms equivalent in this e-graph.
+    ///
+    /// Classes of `other` are related to classes of this e-graph by adding the nodes of `other`
+    /// to a copy of this e-graph, starting from nodes without children, so equivalences following
+    /// by congruence are entailed even if this e-graph does not contain their terms.
+    /// Classes of `other` all of whose nodes lie on cycles cannot be reached this way,
+    /// so they have to be related to classes of this e-graph containing all of their nodes.
+    /// Both e-graphs should use the same language.
+    pub fn entails<B: Analysis>(&self, other: &EGraph<B>) -> bool {
+        let mut extended = self.clone();
+        let mut related: HashMap<ClassId, ClassId> = HashMap::new();
+        let mut pending: Vec<NodeId> = other
+            .classes
+            .values()
+            .flat_map(|class| class.iter_nodes().copied())
+            .collect();
+
+        loop {
+            let before = pending.len();
+            let mut consistent = true;
+            pending.retain(|&node_id| {
+                let node = match other.node(node_id) {
+                    Node::Literal(literal) => Node::Literal(literal.clone()),
+                    Node::Symbol(symbol) => {
+                        let Some(children) = symbol
+                            .children
+                            .iter()
+                            .map(|&child| related.get(&other.canonical_class(child)).copied())
+                            .collect()
+                        else {
+                            return true;
+                        };
+                        Node::Symbol(Symbol {
+                            id: symbol.id,
+                            children,
+                        })
+                    }
+                };
+
+                let added = extended.add_node(node).any();
+                let class_id = extended.containing_class(added);
+                let related_id = *related
+                    .entry(other.containing_class(node_id))
+                    .or_insert(class_id);
+                consistent &= related_id == class_id;
+                false
+            });
+
+            if !consistent {
+                return false;
+            }
+            if pending.len() == before {
+                // The remaining nodes depend on classes whose nodes all lie on cycles
+                return pending.is_empty() || extended.relate_cyclic(other, &related, &pending);
+            }
+        }
+    }
+
+    /// Checks if the classes of `other` which [`EGraph::entails`] could not relate,
+    /// because all of their nodes lie on cycles, can be related to classes of this e-graph
+    /// so that every node of `pending` is in the class related to its own.
+    ///
+    /// Every class of `other` keeps the classes it can be related to, which are narrowed down
+    /// by the nodes of `pending` and then searched with backtracking.
+    fn relate_cyclic<B: Analysis>(
+        &self,
+        other: &EGraph<B>,
+        related: &HashMap<ClassId, ClassId>,
+        pending: &[NodeId],
+    ) -> bool {
+        let mut nodes: HashMap<ClassId, Vec<&Symbol<ClassId>>> = HashMap::new();
+        for &node_id in pending {
+            if let Node::Symbol(symbol) = other.node(node_id) {
+                nodes
+                    .entry(other.containing_class(node_id))
+                    .or_default()
+                    .push(symbol);
+            }
+        }
+
+        let mut candidates: HashMap<ClassId, BTreeSet<ClassId>> = HashMap::new();
+        for symbol in nodes.values().flatten() {
+            for &child in &symbol.children {
+                let child = other.canonical_class(child);
+                let domain = match related.get(&child) {
+                    Some(&class_id) => BTreeSet::from([self.canonical_class(class_id)]),
+                    None => self.classes.keys().copied().collect(),
+                };
+                candidates.entry(child).or_insert(domain);
+            }
+        }
+        for &class_id in nodes.keys() {
+            let domain = match related.get(&class_id) {
+                Some(&related_id) => BTreeSet::from([self.canonical_class(related_id)]),
+                None => self.classes.keys().copied().collect(),
+            };
+            candidates.entry(class_id).or_insert(domain);
+        }
+
+        self.search_cyclic(other, &nodes, candidates)
+    }
+
+    fn search_cyclic<B: Analysis>(
+        &self,
+        other: &EGraph<B>,
+        nodes: &HashMap<ClassId, Vec<&Symbol<ClassId>>>,
+        mut candidates: HashMap<ClassId, BTreeSet<ClassId>>,
+    ) -> bool {
+        // Keeps only the classes containing a node like every pending node of the related class
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (&class_id, symbols) in nodes {
+                let narrowed: BTreeSet<_> = candidates[&class_id]
+                    .iter()
+                    .copied()
+                    .filter(|&candidate| {
+                        symbols.iter().all(|symbol| {
+                            self.class(candidate).iter_nodes().any(|&node_id| {
+                                let Node::Symbol(node) = self.node(node_id) else {
+                                    return false;
+                                };
+                                node.same_shape_as(symbol)
+                                    && node.children.iter().zip(&symbol.children).all(
+                                        |(&child, &other_child)| {
+                                            candidates[&other.canonical_class(other_child)]
+                                                .contains(&self.canonical_class(child))
+                                        },
+                                    )
+                            })
+                        })
+                    })
+                    .collect();
+                if narrowed.is_empty() {
+                    return false;
+                }
+                if narrowed.len() < candidates[&class_id].len() {
+                    candidates.insert(class_id, narrowed);
+                    changed = true;
+                }
+            }
+        }
+
+        let Some((&class_id, domain)) = candidates.iter().find(|(_, domain)| domain.len() > 1)
+        else {
+            return true;
+        };
+        domain.iter().any(|&candidate| {
+            let mut chosen = candidates.clone();
+            chosen.insert(class_id, BTreeSet::from([candidate]));
+            self.search_cyclic(other, nodes, chosen)
+        })
+    }
+
+    /// Returns canonical IDs of classes which were created by merging classes of `earlier`,
+    /// an older version of this e-graph.
+    pub fn merged_since(&self, earlier: &EGraph<A>) -> HashSet<ClassId> {
+        (0..earlier.union_find.size())
+            .filter(|&id| earlier.union_find.find_no_compress(id) == id)
+            .filter_map(|id| {
+                let canonical = self.union_find.find_no_compress(id);
+                (canonical != id).then_some(canonical)
+            })
+            .collect()
+    }
+
     fn rebuild_hashcons(&mut self) {
-        self.node_hashcons.clear();
-        for (&node_id, node) in self.nodes.iter() {
-            let canonical = node.canonical(self);
+        let node_hashcons = self.metrics.hashcons_rebuilds.time(|| {
             // `self.nodes` may contain duplicated nodes (e.g. if their children were merged at some point),
             // after rebuilding hashcons always refer to the id of the largest node_id referring
             // to a given node.
-            self.node_hashcons.insert(canonical, node_id);
-        }
+            self.nodes
+                .iter()
+                .map(|(&node_id, node)| (node.canonical(self), node_id))
+                .collect()
+        });
+        self.node_hashcons = node_hashcons;
     }
 }
 
@@ -235,16 +724,16 @@ pub trait DynEGraph {
     /// Returns the current number of nodes in the egraph
     fn actual_node_count(&self) -> usize;
 
-    fn parents(&self, class_id: ClassId) -> &HashSet<NodeId>;
+    fn parents(&self, class_id: ClassId) -> &FixedHashSet<NodeId>;
 
-    fn nodes(&self, class_id: ClassId) -> &HashSet<NodeId>;
+    fn nodes(&self, class_id: ClassId) -> &FixedHashSet<NodeId>;
 
     fn node(&self, node_id: NodeId) -> &Node;
 
     fn containing_class(&self, node_id: NodeId) -> ClassId;
 
     /// Merges given classes, returns the canonical ID of the merged class as `Old(id)`
-    /// if the IDs refered to a single class already, or `New(id)` otherwise
+    /// if the IDs refered to a single class already or cannot be merged, or `New(id)` otherwise
     fn merge_classes(&mut self, class_1_id: ClassId, class_2_id: ClassId) -> Seen<ClassId>;
 
     /// Finds symbols with a specified ID
@@ -265,11 +754,22 @@ pub trait DynEGraph {
     fn dyn_class(&self, class_id: ClassId) -> &dyn DynClass;
 
     fn dyn_class_mut(&mut self, class_id: ClassId) -> &mut dyn DynClass;
+
+    /// Returns counters of the work done by the union-find of the e-graph.
+    fn union_find_stats(&self) -> UnionFindStats;
+
+    /// `true` if the class with id `class_id` is tagged with `tag`, see [`EGraph::tag_class`].
+    fn has_tag(&self, class_id: ClassId, tag: &str) -> bool;
+
+    /// Returns the work done by the e-graph so far, recorded with the `metrics` feature,
+    /// see [`crate::metrics`]. Clones of the e-graph start with the metrics of the original.
+    fn metrics(&self) -> &EGraphMetrics;
 }
 
 impl<A: Analysis> DynEGraph for EGraph<A> {
     /// If a given node exists in the e-graph, returns it. Otherwise gives `None`.
     fn node_id(&self, node: &Node) -> Option<NodeId> {
+        self.metrics.node_lookups.increment();
         let node = node.canonical(self);
         self.node_hashcons.get(&node).copied()
     }
@@ -280,45 +780,103 @@ impl<A: Analysis> DynEGraph for EGraph<A> {
 
     /// Adds `expression` to the e-graph as a new class.
     fn add_expression(&mut self, expression: VarFreeExpression) -> NodeId {
-        let node = match expression {
-            VarFreeExpression::Literal(literal) => Node::Literal(literal),
-            VarFreeExpression::Symbol(symbol) => Node::Symbol(Symbol {
-                id: symbol.id,
-                children: symbol
-                    .children
-                    .into_iter()
-                    .map(|child| self.add_expression(child))
-                    .collect(),
-            }),
-        };
+        // Symbols on the path to `current` with their remaining children and IDs of added ones,
+        // kept on the heap so that deep expressions do not overflow the stack
+        let mut pending = Vec::new();
+        let mut current = expression;
+        loop {
+            let mut node_id = match current {
+                VarFreeExpression::Literal(literal) => self.add_node(Node::Literal(literal)).any(),
+                VarFreeExpression::Symbol(symbol) => {
+                    let symbol = match self.flatten_into_parent(&mut pending, symbol) {
+                        Ok(next) => {
+                            current = next;
+                            continue;
+                        }
+                        Err(symbol) => symbol,
+                    };
+                    let child_count = symbol.children.len();
+                    let mut remaining = symbol.children.into_iter();
+                    if let Some(first) = remaining.next() {
+                        pending.push((symbol.id, remaining, Vec::with_capacity(child_count)));
+                        current = first;
+                        continue;
+                    }
+                    self.add_node(Node::Symbol(Symbol {
+                        id: symbol.id,
+                        children: Vec::new(),
+                    }))
+                    .any()
+                }
+            };
 
-        self.add_node(node).any()
+            loop {
+                let Some((_, remaining, children)) = pending.last_mut() else {
+                    return node_id;
+                };
+                children.push(node_id);
+                if let Some(next) = remaining.next() {
+                    current = next;
+                    break;
+                }
+                let (id, _, children) = pending.pop().unwrap();
+                node_id = self.add_node(Node::Symbol(Symbol { id, children })).any();
+            }
+        }
     }
 
     fn add_mixed_expression(&mut self, expression: MixedExpression) -> Seen<ClassId> {
-        match expression {
-            MixedExpression::Literal(literal) => {
-                let node_id = self.add_node(Node::Literal(literal));
-                node_id.map(|node_id| self.containing_class(node_id))
-            }
-            MixedExpression::Symbol(symbol) => {
-                let node = Node::Symbol(Symbol {
-                    id: symbol.id,
-                    children: symbol
-                        .children
-                        .into_iter()
-                        .map(|child| self.add_mixed_expression(child).any())
-                        .collect(),
-                });
-
-                self.add_node(node)
+        // Same traversal as in `add_expression`
+        let mut pending = Vec::new();
+        let mut current = expression;
+        loop {
+            let mut class_id = match current {
+                MixedExpression::Literal(literal) => self
+                    .add_node(Node::Literal(literal))
+                    .map(|node_id| self.containing_class(node_id)),
+                MixedExpression::Symbol(symbol) => {
+                    let symbol = match self.flatten_into_parent(&mut pending, symbol) {
+                        Ok(next) => {
+                            current = next;
+                            continue;
+                        }
+                        Err(symbol) => symbol,
+                    };
+                    let child_count = symbol.children.len();
+                    let mut remaining = symbol.children.into_iter();
+                    if let Some(first) = remaining.next() {
+                        pending.push((symbol.id, remaining, Vec::with_capacity(child_count)));
+                        current = first;
+                        continue;
+                    }
+                    self.add_node(Node::Symbol(Symbol {
+                        id: symbol.id,
+                        children: Vec::new(),
+                    }))
                     .map(|node_id| self.containing_class(node_id))
+                }
+                MixedExpression::Class(class_id) => Seen::Old(self.canonical_class(class_id)),
+            };
+
+            loop {
+                let Some((_, remaining, children)) = pending.last_mut() else {
+                    return class_id;
+                };
+                children.push(class_id.any());
+                if let Some(next) = remaining.next() {
+                    current = next;
+                    break;
+                }
+                let (id, _, children) = pending.pop().unwrap();
+                class_id = self
+                    .add_node(Node::Symbol(Symbol { id, children }))
+                    .map(|node_id| self.containing_class(node_id));
             }
-            MixedExpression::Class(class_id) => Seen::Old(self.canonical_class(class_id)),
         }
     }
 
     fn node_mut(&mut self, node_id: NodeId) -> &mut Node {
+        self.record_node(node_id);
         self.nodes.get_mut(&node_id).unwrap()
     }
 
@@ -339,11 +897,11 @@ impl<A: Analysis> DynEGraph for EGraph<A> {
             .sum()
     }
 
-    fn parents(&self, class_id: ClassId) -> &HashSet<NodeId> {
+    fn parents(&self, class_id: ClassId) -> &FixedHashSet<NodeId> {
         self.class(class_id).parents_ids()
     }
 
-    fn nodes(&self, class_id: ClassId) -> &HashSet<NodeId> {
+    fn nodes(&self, class_id: ClassId) -> &FixedHashSet<NodeId> {
         self.class(class_id).nodes_ids()
     }
 
@@ -358,7 +916,8 @@ impl<A: Analysis> DynEGraph for EGraph<A> {
     }
 
     /// Merges given classes, returns the canonical ID of the merged class as `Old(id)`
-    /// if the IDs refered to a single class already, or `New(id)` otherwise
+    /// if the IDs refered to a single class already, or `New(id)` otherwise.
+    /// Classes of different sorts are not merged, in which case `Old` of the first class is returned.
     fn merge_classes(&mut self, class_1_id: ClassId, class_2_id: ClassId) -> Seen<ClassId> {
         let class_1_id = self.union_find.find(class_1_id);
         let class_2_id = self.union_find.find(class_2_id);
@@ -367,17 +926,61 @@ impl<A: Analysis> DynEGraph for EGraph<A> {
             return Seen::Old(class_1_id);
         }
 
-        self.union_find.union(class_1_id, class_2_id);
+        if let (Some(sort_1), Some(sort_2)) =
+            (self.class_sort(class_1_id), self.class_sort(class_2_id))
+            && sort_1 != sort_2
+        {
+            return Seen::Old(class_1_id);
+        }
 
+        // The class of the higher tree stays canonical, which keeps later finds short
+        let (class_1_id, class_2_id) =
+            if self.union_find.union_by_rank(class_1_id, class_2_id) == class_1_id {
+                (class_2_id, class_1_id)
+            } else {
+                (class_1_id, class_2_id)
+            };
+        self.metrics.unions.increment();
+
+        self.record_class(class_1_id);
+        self.record_class(class_2_id);
+        if self.frozen.contains(&class_1_id) {
+            self.record_frozen(class_1_id);
+            self.record_frozen(class_2_id);
+        }
+        let previous = [
+            self.classes[&class_1_id].analysis().clone(),
+            self.classes[&class_2_id].analysis().clone(),
+        ];
         let class_1 = self.classes.remove(&class_1_id).unwrap();
         self.classes.get_mut(&class_2_id).unwrap().merge(class_1);
+        if self.frozen.remove(&class_1_id) {
+            self.frozen.insert(class_2_id);
+        }
+        if self.tags.contains_key(&class_1_id) {
+            self.record_tags(class_1_id);
+            self.record_tags(class_2_id);
+            let tags = self.tags.remove(&class_1_id).unwrap();
+            self.tags.entry(class_2_id).or_default().extend(tags);
+        }
 
         self.rebuild_class(class_2_id);
 
         // After rebuilds, hashcons entries may be stale; rebuild it
         self.rebuild_hashcons();
 
-        Seen::New(class_2_id)
+        // Merges of parents during the rebuild may have changed the canonical ID
+        let class_id = self.union_find.find(class_2_id);
+        let analysis = self.classes[&class_id].analysis();
+        if previous
+            .iter()
+            .any(|previous| analysis.propagates(previous))
+        {
+            self.propagate_analysis(class_id);
+        }
+        self.modify_class(class_id);
+
+        Seen::New(self.union_find.find(class_id))
     }
     /// Finds symbols with a specified ID
     fn find_symbols(&self, symbol_id: SymbolId) -> Vec<NodeId> {
@@ -427,8 +1030,23 @@ impl<A: Analysis> DynEGraph for EGraph<A> {
 
     fn dyn_class_mut(&mut self, class_id: ClassId) -> &mut dyn DynClass {
         let class_id = self.canonical_class(class_id);
+        self.record_class(class_id);
         self.classes.get_mut(&class_id).unwrap()
     }
+
+    fn union_find_stats(&self) -> UnionFindStats {
+        self.union_find.stats()
+    }
+
+    fn has_tag(&self, class_id: ClassId, tag: &str) -> bool {
+        self.tags
+            .get(&self.canonical_class(class_id))
+            .is_some_and(|tags| tags.contains(tag))
+    }
+
+    fn metrics(&self) -> &EGraphMetrics {
+        &self.metrics
+    }
 }
 
 #[cfg(test)]
@@ -458,6 +1076,37 @@ mod tests {
         assert_eq!(graph.actual_node_count(), 7);
     }
 
+    #[test]
+    fn from_expressions() {
+        let lang = Language::simple_math();
+        let expressions = vec![
+            lang.parse_no_vars("(+ 1 2)").unwrap(),
+            lang.parse_no_vars("(* (+ 1 2) 3)").unwrap(),
+            lang.parse_no_vars("1").unwrap(),
+        ];
+
+        let (graph, roots) = EGraph::<()>::from_expressions(expressions);
+
+        // 1, 2, 3, + and *, shared between the expressions
+        assert_eq!(graph.class_count(), 5);
+        assert_eq!(roots.len(), 3);
+        assert_ne!(roots[0], roots[1]);
+        assert_eq!(graph.class_count(), graph.actual_node_count());
+    }
+
+    #[test]
+    fn add_deep_expression() {
+        let lang = Language::simple_math();
+        let depth = 10_000;
+        let string = format!("{}1{}", "(sin ".repeat(depth), ")".repeat(depth));
+        let expression = lang.parse_no_vars(&string).unwrap();
+
+        let graph = EGraph::<()>::from_expression(expression);
+
+        assert_eq!(graph.class_count(), depth + 1);
+        assert_eq!(graph.actual_node_count(), depth + 1);
+    }
+
     #[test]
     fn merge() {
         let lang = Language::simple_math();
@@ -487,6 +1136,34 @@ mod tests {
         assert_eq!(graph.actual_node_count(), 6);
     }
 
+    #[test]
+    fn merge_refuses_different_sorts() {
+        use crate::language::{
+            expression::LiteralKind,
+            sorts::{Sorts, SymbolSignature},
+        };
+
+        let lang = Language::default().add_symbol("<").add_symbol("+");
+        let sorts = Sorts::new()
+            .with_signature(0, SymbolSignature::new(&["int", "int"], "bool"))
+            .with_signature(1, SymbolSignature::new(&["int", "int"], "int"))
+            .with_literal_sort(LiteralKind::Int, "int");
+
+        let mut graph = EGraph::<()>::default().with_sorts(sorts);
+        let less = graph.add_expression(lang.parse_no_vars("(< 1 2)").unwrap());
+        let plus = graph.add_expression(lang.parse_no_vars("(+ 1 2)").unwrap());
+        let three = graph.add_expression(lang.parse_no_vars("3").unwrap());
+
+        let less = graph.containing_class(less);
+        let plus = graph.containing_class(plus);
+        let three = graph.containing_class(three);
+
+        assert_eq!(graph.class_sort(less).unwrap(), "bool");
+        assert!(graph.merge_classes(less, plus).new().is_none());
+        assert_ne!(graph.canonical_class(less), graph.canonical_class(plus));
+        assert!(graph.merge_classes(plus, three).new().is_some());
+    }
+
     #[test]
     fn node_and_node_id() {
         let mut egraph = EGraph::<()>::default();
@@ -581,6 +1258,23 @@ mod tests {
         assert_eq!(egraph.class_count(), 5);
     }
 
+    #[test]
+    fn string_literal_sharing() {
+        let mut egraph = EGraph::<()>::default();
+
+        let lang = Language::simple_math();
+        let expr_1: VarFreeExpression = lang.parse_no_vars(r#"(+ "a" "b")"#).unwrap();
+        let expr_2: VarFreeExpression = lang.parse_no_vars(r#"(* "b" "a")"#).unwrap();
+
+        egraph.add_expression(expr_1);
+        egraph.add_expression(expr_2);
+
+        assert_eq!(egraph.actual_node_count(), 4);
+        assert_eq!(egraph.class_count(), 4);
+        assert!(egraph.find_literal(Literal::string("a")).is_some());
+        assert!(egraph.find_literal(Literal::string("c")).is_none());
+    }
+
     #[test]
     fn class_merge_with_same_nodes() {
         let mut egraph = EGraph::<()>::default();
@@ -712,6 +1406,104 @@ mod tests {
         assert_eq!(egraph.class(final_plus_class_id).analysis().count(), 2);
     }
 
+    /// Folds additions of integer constants in `simple_math`
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct ConstantFolding(Option<i64>);
+
+    impl super::Analysis for ConstantFolding {
+        fn make(
+            egraph: &dyn super::class::analysis::AnalysisGraph<Self>,
+            node_id: super::NodeId,
+        ) -> Self {
+            match egraph.node(node_id) {
+                Node::Literal(Literal::Int(value)) => Self(Some(*value)),
+                Node::Symbol(symbol) if symbol.id == Language::simple_math().get_id("+") => {
+                    let value = |child| egraph.analysis(child).0;
+                    Self(
+                        value(symbol.children[0])
+                            .zip(value(symbol.children[1]))
+                            .map(|(a, b)| a + b),
+                    )
+                }
+                _ => Self(None),
+            }
+        }
+
+        fn merge(a: Self, b: Self) -> Self {
+            Self(a.0.or(b.0))
+        }
+
+        fn propagates(&self, previous: &Self) -> bool {
+            self != previous
+        }
+
+        fn modify<G: super::Analysis>(
+            egraph: &mut EGraph<G>,
+            class_id: super::ClassId,
+            data: impl Fn(&G) -> &Self,
+        ) {
+            if let Some(value) = data(egraph.class(class_id).analysis()).0
+                && !egraph.class_contains_literal(class_id, &Literal::Int(value))
+            {
+                let literal =
+                    egraph.add_expression(VarFreeExpression::Literal(Literal::Int(value)));
+                egraph.merge_classes(class_id, literal);
+            }
+        }
+    }
+
+    #[test]
+    fn analysis_modification() {
+        let lang = Language::simple_math();
+        let expr = lang.parse_no_vars("(* (+ 1 2) (+ 3 (+ 4 5)))").unwrap();
+        let egraph = EGraph::<ConstantFolding>::from_expression(expr);
+
+        let find = |value| egraph.find_literal(Literal::Int(value)).unwrap();
+        let sums = egraph.find_symbols(lang.get_id("+"));
+        assert_eq!(sums.len(), 3);
+        for sum in sums {
+            assert!(egraph.class(sum).analysis().0.is_some());
+        }
+        let product = egraph.containing_class(egraph.find_symbols(lang.get_id("*"))[0]);
+        let folded = egraph.node(egraph.class(product).iter_nodes().copied().next().unwrap());
+        assert_eq!(
+            folded.iter_children().copied().collect_vec(),
+            vec![find(3), find(12)]
+        );
+        // Literals 1 to 5, folded sums 9 and 12, and the product
+        assert_eq!(egraph.class_count(), 8);
+    }
+
+    #[test]
+    fn composite_analysis_modification() {
+        let lang = Language::simple_math();
+        let (egraph, root) =
+            EGraph::<(
+                super::class::literal_count::LiteralCountAnalysis,
+                ConstantFolding,
+            )>::from_expression_with_id(lang.parse_no_vars("(+ 1 (+ 2 3))").unwrap());
+
+        assert_eq!(egraph.class(root).analysis().1, ConstantFolding(Some(6)));
+        assert!(egraph.class_contains_literal(egraph.canonical_class(root), &Literal::Int(6)));
+    }
+
+    #[test]
+    fn propagated_analysis_modification() {
+        let lang = Language::simple_math();
+        let (mut egraph, root) = EGraph::<ConstantFolding>::from_expression_with_id(
+            lang.parse_no_vars("(+ (* 2 3) 1)").unwrap(),
+        );
+        assert_eq!(egraph.class(root).analysis().0, None);
+
+        let product = egraph.add_expression(lang.parse_no_vars("(* 2 3)").unwrap());
+        let six = egraph.add_expression(VarFreeExpression::Literal(Literal::Int(6)));
+        egraph.merge_classes(product, six);
+
+        // The sum is folded once the value of its child is propagated to it
+        assert_eq!(egraph.class(root).analysis().0, Some(7));
+        assert!(egraph.class_contains_literal(egraph.canonical_class(root), &Literal::Int(7)));
+    }
+
     fn assert_children_canonical<A: super::class::analysis::Analysis>(egraph: &EGraph<A>) {
         for (_cid, class) in egraph.iter_classes() {
             for &node_id in class.nodes_ids() {
@@ -783,4 +1575,228 @@ mod tests {
         rule.apply(&mut egraph, &TopDownMatcher);
         assert_children_canonical(&egraph);
     }
+
+    #[test]
+    fn frozen_classes_get_no_new_nodes() {
+        use crate::rewriting::egraph::matching::top_down::TopDownMatcher;
+        use crate::rewriting::rule::Rule;
+        let lang = Language::simple_math();
+        let mut egraph = EGraph::<()>::default();
+        let sum = egraph.add_expression(lang.parse_no_vars("(+ 1 (* 2 3))").unwrap());
+        let goal = egraph.add_expression(lang.parse_no_vars("(* 3 2)").unwrap());
+        egraph.freeze_class_with_descendants(goal);
+        assert!(egraph.is_frozen(egraph.find_literal(Literal::Int(2)).unwrap()));
+        assert!(!egraph.is_frozen(sum));
+
+        let commute_sum = Rule::from_strings("(+ $0 $1)", "(+ $1 $0)", &lang);
+        let commute_product = Rule::from_strings("(* $0 $1)", "(* $1 $0)", &lang);
+        assert_eq!(commute_sum.apply(&mut egraph, &TopDownMatcher), 1);
+        // Only `(* 2 3)` is commuted, into the existing frozen class of the goal
+        assert_eq!(commute_product.apply(&mut egraph, &TopDownMatcher), 1);
+        assert_eq!(commute_product.apply(&mut egraph, &TopDownMatcher), 0);
+
+        assert_eq!(egraph.nodes(sum).len(), 2);
+        assert_eq!(egraph.nodes(goal).len(), 2);
+        assert!(egraph.is_frozen(goal));
+        egraph.unfreeze_class(goal);
+        assert!(!egraph.is_frozen(goal));
+    }
+
+    #[test]
+    fn class_tags() {
+        let lang = Language::simple_math();
+        let mut egraph = EGraph::<()>::default();
+        let start = egraph.add_expression(lang.parse_no_vars("(+ 1 0)").unwrap());
+        let goal = egraph.add_expression(lang.parse_no_vars("1").unwrap());
+        egraph.tag_class(start, "origin: start-term");
+        egraph.tag_class(goal, "origin: goal-term");
+        assert_eq!(egraph.classes_with_tag("origin: goal-term"), [goal]);
+        assert!(!egraph.has_tag(goal, "origin: start-term"));
+
+        let snapshot = egraph.snapshot();
+        let merged = egraph.merge_classes(start, goal).any();
+        assert_eq!(
+            egraph.class_tags(merged),
+            ["origin: goal-term", "origin: start-term"]
+        );
+        assert_eq!(egraph.classes_with_tag("origin: start-term"), [merged]);
+        assert!(egraph.untag_class(start, "origin: start-term"));
+        assert!(!egraph.untag_class(start, "origin: start-term"));
+        assert_eq!(egraph.class_tags(goal), ["origin: goal-term"]);
+
+        egraph.rollback(snapshot);
+        assert_eq!(egraph.class_tags(start), ["origin: start-term"]);
+        assert_eq!(egraph.class_tags(goal), ["origin: goal-term"]);
+        assert!(
+            egraph
+                .class_tags(egraph.find_literal(Literal::Int(0)).unwrap())
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn entailment() {
+        use crate::rewriting::egraph::{
+            matching::bottom_up::BottomUpMatcher,
+            saturation::{SaturationConfig, Saturator, SimpleSaturator},
+        };
+        use crate::rewriting::rule::Rule;
+        let lang = Language::simple_math();
+        let saturated = |rules: &[Rule]| {
+            let mut egraph =
+                EGraph::<()>::from_expression(lang.parse_no_vars("(+ (* 3 2) 1)").unwrap());
+            SimpleSaturator::new(Box::new(BottomUpMatcher)).saturate(
+                &mut egraph,
+                rules,
+                &SaturationConfig::default(),
+            );
+            egraph
+        };
+
+        let commute = Rule::from_strings("(+ $0 $1)", "(+ $1 $0)", &lang);
+        let shift = Rule::from_strings("(* $0 2)", "(<< $0 1)", &lang);
+        let weaker = saturated(std::slice::from_ref(&commute));
+        let stronger = saturated(&[commute, shift]);
+        assert!(stronger.entails(&weaker));
+        assert!(!weaker.entails(&stronger));
+        assert!(weaker.entails(&weaker));
+
+        // `(sin 2)` and `(sin 3)` are equivalent by congruence
+        let mut merged = EGraph::<()>::from_expression(lang.parse_no_vars("(+ 2 3)").unwrap());
+        let two = merged.find_literal(Literal::Int(2)).unwrap();
+        let three = merged.find_literal(Literal::Int(3)).unwrap();
+        merged.merge_classes(two, three);
+        let mut sines = EGraph::<()>::default();
+        let sin_2 = sines.add_expression(lang.parse_no_vars("(sin 2)").unwrap());
+        let sin_3 = sines.add_expression(lang.parse_no_vars("(sin 3)").unwrap());
+        assert!(EGraph::<()>::default().entails(&sines));
+        sines.merge_classes(sin_2, sin_3);
+        assert!(merged.entails(&sines));
+        assert!(!EGraph::<()>::default().entails(&sines));
+    }
+
+    #[test]
+    fn entailment_of_cyclic_classes() {
+        let lang = Language::simple_math();
+        // A class of `1` and `(sin 1)`, with the cosine of it merged with `2` if `cosine` is set
+        let cyclic = |cosine: bool| {
+            let mut egraph = EGraph::<()>::from_expression(lang.parse_no_vars("(sin 1)").unwrap());
+            let one = egraph.find_literal(Literal::Int(1)).unwrap();
+            let sine = egraph.add_expression(lang.parse_no_vars("(sin 1)").unwrap());
+            let root = egraph.merge_classes(one, sine).any();
+            if cosine {
+                let cos = egraph.add_expression(lang.parse_no_vars("(cos (sin 1))").unwrap());
+                let two = egraph.add_expression(lang.parse_no_vars("2").unwrap());
+                egraph.merge_classes(cos, two);
+            }
+            (egraph, root)
+        };
+
+        // Without `1`, the only node of the class is `(sin $class)`
+        let (mut other, root) = cyclic(true);
+        let one = other.node_id(&Node::Literal(Literal::Int(1))).unwrap();
+        other
+            .classes
+            .get_mut(&root)
+            .unwrap()
+            .nodes_ids_mut()
+            .remove(&one);
+
+        assert!(cyclic(true).0.entails(&other));
+        assert!(!cyclic(false).0.entails(&other));
+        assert!(!EGraph::<()>::default().entails(&other));
+    }
+
+    #[test]
+    fn flattened_symbols() {
+        use crate::rewriting::rule::Rule;
+        let lang = Language::simple_math().declare_flattened("+");
+        let plus = lang.get_id("+");
+        let mut egraph = EGraph::<()>::default()
+            .with_flattened_symbols(lang.flattened_symbols().iter().copied());
+
+        let sum = egraph.add_expression(lang.parse_no_vars("(+ (+ 1 2) (* 3 (+ 4 5)) 6)").unwrap());
+        let children = |egraph: &EGraph<()>, node_id| {
+            egraph.node(node_id).try_as_symbol().unwrap().children.len()
+        };
+        assert_eq!(children(&egraph, sum), 4);
+        assert_eq!(egraph.find_symbols(plus).len(), 2);
+        let same = egraph.add_expression(
+            lang.parse_no_vars("(+ 1 (+ 2 (+ (* 3 (+ 4 5)) 6)))")
+                .unwrap(),
+        );
+        assert_eq!(same, sum);
+
+        // Right-hand sides of rules are flattened as well
+        let rule = Rule::from_strings("(* $0 $1)", "(+ $0 (+ $1 $1))", &lang);
+        rule.apply(
+            &mut egraph,
+            &crate::rewriting::egraph::matching::top_down::TopDownMatcher,
+        );
+        let product = egraph.containing_class(egraph.find_symbols(lang.get_id("*"))[0]);
+        assert!(egraph.nodes(product).iter().any(|&node_id| {
+            egraph.node(node_id).try_as_symbol().unwrap().id == plus
+                && children(&egraph, node_id) == 3
+        }));
+
+        let json = serde_json::to_string(&egraph).unwrap();
+        let mut loaded: EGraph<()> = serde_json::from_str(&json).unwrap();
+        let added = loaded.add_expression(lang.parse_no_vars("(+ 7 (+ 8 9))").unwrap());
+        assert_eq!(children(&loaded, added), 3);
+    }
+
+    #[test]
+    fn repeated_saturations_are_identical() {
+        use crate::{
+            macros::rules,
+            rewriting::egraph::{
+                extraction::{CostTable, Extractor, TableExtractor},
+                matching::{Matcher, bottom_up::BottomUpMatcher, top_down::TopDownMatcher},
+                saturation::{SaturationConfig, Saturator, SimpleSaturator},
+            },
+        };
+
+        let lang = Language::simple_math();
+        let rules = rules!(lang;
+            "(+ $0 $1)" => "(+ $1 $0)",
+            "(* $0 $1)" => "(* $1 $0)",
+            "(+ $0 (+ $1 $2))" => "(+ (+ $0 $1) $2)",
+            "(* $0 2)" => "(+ $0 $0)",
+            "(* $0 2)" => "(<< $0 1)",
+        );
+        let extractor = TableExtractor::new(
+            &CostTable {
+                default: 1,
+                ..Default::default()
+            },
+            &lang,
+        );
+        let expression = lang
+            .parse_no_vars("(+ (* 3 2) (+ (* 4 2) (+ 5 6)))")
+            .unwrap();
+
+        let run = |matcher: Box<dyn Matcher>| {
+            let (mut egraph, root) = EGraph::<()>::from_expression_with_id(expression.clone());
+            // Stopping early makes the result depend on the order of applications
+            SimpleSaturator::new(matcher).saturate(
+                &mut egraph,
+                &rules,
+                &SaturationConfig {
+                    max_applications: Some(40),
+                    ..Default::default()
+                },
+            );
+            let classes = egraph.iter_classes().map(|(&id, _)| id).collect_vec();
+            let nodes = egraph.nodes(root).iter().copied().collect_vec();
+            let winner = extractor.extract(&egraph, root).unwrap().winner().clone();
+            (classes, nodes, winner)
+        };
+
+        let bottom_up = run(Box::new(BottomUpMatcher));
+        let top_down = run(Box::new(TopDownMatcher));
+        for _ in 0..5 {
+            assert_eq!(run(Box::new(BottomUpMatcher)), bottom_up);
+            assert_eq!(run(Box::new(TopDownMatcher)), top_down);
+        }
+    }
 }
@@ -3,6 +3,8 @@
 //! This module provides the [`Node`] type representing individual nodes in an e-graph,
 //! which can be either literals or symbols with child class references.
 
+use serde::{Deserialize, Serialize};
+
 use crate::language::{expression::Literal, symbol::Symbol};
 
 use super::{Analysis, ClassId, DynEGraph, EGraph};
@@ -11,7 +13,7 @@ use super::{Analysis, ClassId, DynEGraph, EGraph};
 ///
 /// Nodes represent the actual expressions in the e-graph. Each node is either
 /// a literal value or a symbol with references to child equivalence classes.
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Node {
     /// A literal constant value
     Literal(Literal),
@@ -45,6 +47,7 @@ impl Node {
 
     /// Makes this node canonical by updating all child IDs to their canonical representatives.
     pub fn make_canonical<A: Analysis>(&mut self, graph: &EGraph<A>) {
+        graph.metrics().canonicalizations.increment();
         for child_id in self.iter_mut_children() {
             *child_id = graph.canonical_class(*child_id);
         }
@@ -0,0 +1,194 @@
+//! Enumeration of normal forms represented by e-graph classes.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::language::{expression::VarFreeExpression, symbol::Symbol};
+use crate::rewriting::rule::Rule;
+
+use super::{
+    Analysis, ClassId, DynEGraph, EGraph, Node, NodeId, extraction::reachable_classes,
+    matching::Matcher, saturation::report::RuleReport,
+};
+
+// Terms represented by a class together with their sizes
+type SizedTerms = Vec<(VarFreeExpression, usize)>;
+
+impl<A: Analysis> EGraph<A> {
+    /// Returns expressions of size at most `max_size` represented by the class with id `class_id`
+    /// which are in normal form with respect to `rules`, i.e. no left-hand side of a rule
+    /// matches any of their subexpressions. Reducibility is tested with `matcher`.
+    ///
+    /// Every subexpression of a normal form is a normal form too, so they are built bottom-up
+    /// from normal forms of child classes. The bound on size is needed, as classes in cycles
+    /// may represent infinitely many expressions. The normal forms are ordered by size.
+    pub fn normal_forms(
+        &self,
+        class_id: ClassId,
+        rules: &[Rule],
+        matcher: &dyn Matcher,
+        max_size: usize,
+    ) -> Vec<VarFreeExpression> {
+        let class_id = self.canonical_class(class_id);
+        let classes = reachable_classes(self, class_id);
+        let mut normal_forms: HashMap<ClassId, SizedTerms> = HashMap::new();
+        let mut checked = HashSet::new();
+
+        loop {
+            let mut changed = false;
+            for &class in &classes {
+                for &node_id in self.nodes(class) {
+                    for (term, size) in self.node_terms(node_id, &normal_forms, max_size) {
+                        if !checked.insert(term.clone()) || !is_irreducible(&term, rules, matcher) {
+                            continue;
+                        }
+                        normal_forms.entry(class).or_default().push((term, size));
+                        changed = true;
+                    }
+                }
+            }
+
+            if !changed {
+                break;
+            }
+        }
+
+        let mut result = normal_forms.remove(&class_id).unwrap_or_default();
+        result.sort_by_cached_key(|(term, size)| (*size, term.to_string()));
+        result.into_iter().map(|(term, _)| term).collect()
+    }
+
+    /// Returns terms of size at most `max_size` with the node `node_id` at their root
+    /// and children taken from `terms`.
+    fn node_terms(
+        &self,
+        node_id: NodeId,
+        terms: &HashMap<ClassId, SizedTerms>,
+        max_size: usize,
+    ) -> SizedTerms {
+        if max_size == 0 {
+            return Vec::new();
+        }
+
+        let symbol = match self.node(node_id) {
+            Node::Literal(literal) => {
+                return vec![(VarFreeExpression::Literal(literal.clone()), 1)];
+            }
+            Node::Symbol(symbol) => symbol,
+        };
+
+        // Combinations of children terms and their total sizes
+        let mut combinations: Vec<(Vec<VarFreeExpression>, usize)> = vec![(Vec::new(), 1)];
+        for &child in &symbol.children {
+            let Some(child_terms) = terms.get(&self.canonical_class(child)) else {
+                return Vec::new();
+            };
+            combinations = combinations
+                .iter()
+                .flat_map(|(children, size)| {
+                    child_terms
+                        .iter()
+                        .filter(move |(_, child_size)| size + child_size <= max_size)
+                        .map(move |(child_term, child_size)| {
+                            let mut children = children.clone();
+                            children.push(child_term.clone());
+                            (children, size + child_size)
+                        })
+                })
+                .collect();
+        }
+
+        combinations
+            .into_iter()
+            .map(|(children, size)| {
+                let term = VarFreeExpression::Symbol(Symbol {
+                    id: symbol.id,
+                    children,
+                });
+                (term, size)
+            })
+            .collect()
+    }
+}
+
+/// `true` if no left-hand side of `rules` matches any subexpression of `term`.
+fn is_irreducible(term: &VarFreeExpression, rules: &[Rule], matcher: &dyn Matcher) -> bool {
+    let egraph = EGraph::<()>::from_expression(term.clone());
+    rules.iter().all(|rule| {
+        rule.find_matches(&egraph, matcher, &mut RuleReport::default())
+            .is_empty()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        language::Language,
+        macros::rules,
+        rewriting::egraph::{
+            DynEGraph, EGraph,
+            matching::{Matcher, bottom_up::BottomUpMatcher, top_down::TopDownMatcher},
+        },
+    };
+
+    #[test]
+    fn enumerates_irreducible_members() {
+        let lang = Language::simple_math();
+        let rules = rules!(lang;
+            "(+ $0 0)" => "$0",
+            "(* $0 1)" => "$0",
+        );
+        let mut egraph = EGraph::<()>::default();
+        let root = egraph.add_expression(lang.parse_no_vars("(+ (* 2 1) 0)").unwrap());
+        let inner = egraph.add_expression(lang.parse_no_vars("(* 2 1)").unwrap());
+        let two = egraph.add_expression(lang.parse_no_vars("2").unwrap());
+        let other = egraph.add_expression(lang.parse_no_vars("(- 3 1)").unwrap());
+        egraph.merge_classes(
+            egraph.containing_class(root),
+            egraph.containing_class(inner),
+        );
+        egraph.merge_classes(egraph.containing_class(root), egraph.containing_class(two));
+        egraph.merge_classes(
+            egraph.containing_class(root),
+            egraph.containing_class(other),
+        );
+
+        for matcher in [&TopDownMatcher as &dyn Matcher, &BottomUpMatcher] {
+            let normal_forms =
+                egraph.normal_forms(egraph.containing_class(root), &rules, matcher, 10);
+            assert_eq!(
+                normal_forms,
+                [
+                    lang.parse_no_vars("2").unwrap(),
+                    lang.parse_no_vars("(- 3 1)").unwrap(),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn bounds_size_in_cycles() {
+        let lang = Language::simple_math();
+        let rules = rules!(lang; "(sin (sin $0))" => "$0");
+        let mut egraph = EGraph::<()>::default();
+        let zero = egraph.add_expression(lang.parse_no_vars("0").unwrap());
+        let sin = egraph.add_expression(lang.parse_no_vars("(sin 0)").unwrap());
+        let neg = egraph.add_expression(lang.parse_no_vars("(- 0)").unwrap());
+        egraph.merge_classes(egraph.containing_class(zero), egraph.containing_class(neg));
+        let zero = egraph.containing_class(zero);
+
+        // Class of 0 represents (- (- ... 0)), normal forms of (sin 0) have a single sin
+        let normal_forms =
+            egraph.normal_forms(egraph.containing_class(sin), &rules, &TopDownMatcher, 3);
+        assert_eq!(
+            normal_forms,
+            [
+                lang.parse_no_vars("(sin 0)").unwrap(),
+                lang.parse_no_vars("(sin (- 0))").unwrap(),
+            ]
+        );
+        assert_eq!(
+            egraph.normal_forms(zero, &rules, &TopDownMatcher, 4).len(),
+            4
+        );
+    }
+}
@@ -0,0 +1,242 @@
+//! Checkpoints of long saturations.
+//!
+//! A [`CheckpointingSaturator`] periodically saves the e-graph, the state of its scheduler
+//! and the progress of the saturation to a directory, so that a saturation interrupted
+//! e.g. by a crash can be resumed from the latest checkpoint instead of from the start.
+
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::{
+    rewriting::egraph::{Analysis, EGraph, matching::Matcher},
+    utils::json,
+};
+
+use super::{
+    SaturationConfig, SaturationReport, scheduled_saturator::ScheduledSaturator,
+    scheduler::Scheduler,
+};
+
+const CHECKPOINT_FILE: &str = "checkpoint.json";
+
+/// State of a saturation saved by a [`CheckpointingSaturator`].
+#[derive(Serialize, Deserialize)]
+#[serde(bound(serialize = "A: Serialize", deserialize = "A: Deserialize<'de>"))]
+pub struct Checkpoint<A: Analysis> {
+    /// The saturated e-graph
+    pub egraph: EGraph<A>,
+    /// State of the scheduler, see [`Scheduler::save_state`]
+    pub scheduler: serde_json::Value,
+    /// Progress of the saturation, with `stop_reason` set if it has finished
+    pub report: SaturationReport,
+}
+
+/// Saturator writing a [`Checkpoint`] to a directory every
+/// [`SaturationConfig::checkpoint_every`] iterations and when the saturation stops.
+///
+/// Only the latest checkpoint is kept. It is replaced atomically,
+/// so an interrupted write leaves the previous checkpoint intact.
+pub struct CheckpointingSaturator<A: Analysis> {
+    saturator: ScheduledSaturator<A>,
+    directory: PathBuf,
+}
+
+impl<A: Analysis + Serialize + DeserializeOwned> CheckpointingSaturator<A> {
+    /// Creates a saturator applying rules chosen by `scheduler` and saving checkpoints
+    /// to `directory`, which is created if needed.
+    pub fn new(scheduler: Box<dyn Scheduler<A>>, directory: impl Into<PathBuf>) -> Self {
+        Self {
+            saturator: ScheduledSaturator::new(scheduler),
+            directory: directory.into(),
+        }
+    }
+
+    /// Returns the path of the latest checkpoint.
+    pub fn checkpoint_path(&self) -> PathBuf {
+        self.directory.join(CHECKPOINT_FILE)
+    }
+
+    /// Loads the latest checkpoint, or returns `None` if there is none.
+    pub fn load_checkpoint(&self) -> Result<Option<Checkpoint<A>>, Box<dyn Error>> {
+        let path = self.checkpoint_path();
+        if !path.exists() {
+            return Ok(None);
+        }
+        json::load_json(path).map(Some)
+    }
+
+    /// Saturates `egraph` from the start, replacing any existing checkpoint.
+    pub fn run(
+        &mut self,
+        egraph: &mut EGraph<A>,
+        config: &SaturationConfig,
+        matcher: &dyn Matcher,
+    ) -> Result<SaturationReport, Box<dyn Error>> {
+        self.run_from(egraph, config, matcher, SaturationReport::default())
+    }
+
+    /// Continues the saturation saved in the latest checkpoint, replacing `egraph` with
+    /// the saved one. If there is no checkpoint, saturates `egraph` from the start.
+    ///
+    /// Limits from `config` apply to the whole saturation, including the part
+    /// done before the checkpoint.
+    pub fn resume(
+        &mut self,
+        egraph: &mut EGraph<A>,
+        config: &SaturationConfig,
+        matcher: &dyn Matcher,
+    ) -> Result<SaturationReport, Box<dyn Error>> {
+        let Some(checkpoint) = self.load_checkpoint()? else {
+            return self.run(egraph, config, matcher);
+        };
+
+        *egraph = checkpoint.egraph;
+        self.saturator
+            .scheduler_mut()
+            .restore_state(checkpoint.scheduler)?;
+        self.run_from(egraph, config, matcher, checkpoint.report)
+    }
+
+    fn run_from(
+        &mut self,
+        egraph: &mut EGraph<A>,
+        config: &SaturationConfig,
+        matcher: &dyn Matcher,
+        report: SaturationReport,
+    ) -> Result<SaturationReport, Box<dyn Error>> {
+        fs::create_dir_all(&self.directory)?;
+        let every = config.checkpoint_every;
+        let report = self.saturator.run_from(
+            egraph,
+            config,
+            matcher,
+            &(),
+            report,
+            |egraph, scheduler, report| match every {
+                Some(every) if every > 0 && report.iterations % every == 0 => {
+                    save_checkpoint(&self.directory, egraph, scheduler, report)
+                }
+                _ => Ok(()),
+            },
+        )?;
+
+        if every.is_some() {
+            save_checkpoint(&self.directory, egraph, self.saturator.scheduler(), &report)?;
+        }
+        Ok(report)
+    }
+}
+
+fn save_checkpoint<A: Analysis + Serialize>(
+    directory: &Path,
+    egraph: &EGraph<A>,
+    scheduler: &dyn Scheduler<A>,
+    report: &SaturationReport,
+) -> Result<(), Box<dyn Error>> {
+    #[derive(Serialize)]
+    #[serde(bound = "A: Serialize")]
+    struct CheckpointRef<'a, A: Analysis> {
+        egraph: &'a EGraph<A>,
+        scheduler: serde_json::Value,
+        report: &'a SaturationReport,
+    }
+
+    let temporary = directory.join(format!("{CHECKPOINT_FILE}.tmp"));
+    json::save_json(
+        &CheckpointRef {
+            egraph,
+            scheduler: scheduler.save_state(),
+            report,
+        },
+        &temporary,
+    )?;
+    fs::rename(temporary, directory.join(CHECKPOINT_FILE))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        language::Language,
+        macros::rules,
+        rewriting::egraph::{
+            DynEGraph, EGraph,
+            matching::top_down::TopDownMatcher,
+            saturation::{
+                SaturationConfig, SaturationStopReason, scheduled_saturator::ScheduledSaturator,
+                scheduler::RoundRobinScheduler,
+            },
+        },
+    };
+
+    use super::CheckpointingSaturator;
+
+    #[test]
+    fn resumes_interrupted_saturation() {
+        let lang = Language::simple_math();
+        let rules = rules!(lang;
+            "(+ $0 $1)" => "(+ $1 $0)",
+            "(* $0 2)" => "(<< $0 1)",
+            "(* $0 $1)" => "(* $1 $0)",
+        );
+        let expression = lang.parse_no_vars("(+ (* 3 2) (* 4 2))").unwrap();
+        let directory = std::env::temp_dir().join(format!("checkpoints-{}", std::process::id()));
+        let config = SaturationConfig {
+            checkpoint_every: Some(2),
+            ..Default::default()
+        };
+
+        let mut expected = EGraph::<()>::from_expression(expression.clone());
+        let full = ScheduledSaturator::new(Box::new(RoundRobinScheduler::new(rules.clone()))).run(
+            &mut expected,
+            &config,
+            &TopDownMatcher,
+        );
+
+        // Simulates a crash by stopping early
+        let mut egraph = EGraph::<()>::from_expression(expression.clone());
+        let mut saturator = CheckpointingSaturator::new(
+            Box::new(RoundRobinScheduler::new(rules.clone())),
+            &directory,
+        );
+        let interrupted = saturator
+            .run(
+                &mut egraph,
+                &SaturationConfig {
+                    max_applications: Some(3),
+                    ..config.clone()
+                },
+                &TopDownMatcher,
+            )
+            .unwrap();
+        assert_eq!(
+            interrupted.stop_reason,
+            Some(SaturationStopReason::MaxApplications)
+        );
+
+        let mut egraph = EGraph::<()>::default();
+        let mut saturator =
+            CheckpointingSaturator::new(Box::new(RoundRobinScheduler::new(rules)), &directory);
+        let resumed = saturator
+            .resume(&mut egraph, &config, &TopDownMatcher)
+            .unwrap();
+        let checkpoint = saturator.load_checkpoint().unwrap().unwrap();
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(resumed.stop_reason, Some(SaturationStopReason::Saturated));
+        assert_eq!(resumed.iterations, full.iterations);
+        assert_eq!(resumed.applications, full.applications);
+        assert_eq!(egraph.class_count(), expected.class_count());
+        assert_eq!(egraph.actual_node_count(), expected.actual_node_count());
+        assert_eq!(checkpoint.report.stop_reason, resumed.stop_reason);
+        assert_eq!(
+            checkpoint.egraph.actual_node_count(),
+            egraph.actual_node_count()
+        );
+    }
+}
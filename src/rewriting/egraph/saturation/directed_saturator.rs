@@ -1,32 +1,72 @@
+use std::rc::Rc;
+
 use crate::rewriting::{
     egraph::{EGraph, class::local_cost::LocalCost, matching::Matcher},
+    heuristic::Heuristic,
     rule::Rule,
 };
 
-use super::{SaturationConfig, SaturationStopReason, Saturator};
+use super::{ApplicationFilter, SaturationConfig, SaturationReport, Saturator};
 use crate::rewriting::egraph::saturation::scheduled_saturator::ScheduledSaturator;
-use crate::rewriting::egraph::saturation::scheduler::CostDirectedScheduler;
+use crate::rewriting::egraph::saturation::scheduler::{
+    CostDirectedScheduler, GoalDirectedScheduler, Scheduler,
+};
 
-pub struct DirectedSaturator {
+/// Saturator applying rules in the order of their local cost deltas,
+/// or best-first towards a goal if given a heuristic.
+pub struct DirectedSaturator<F = ()> {
     matcher: Box<dyn Matcher>,
+    heuristic: Option<Rc<dyn Heuristic>>,
+    filter: F,
 }
 
 impl DirectedSaturator {
     pub fn new(matcher: Box<dyn Matcher>) -> Self {
-        Self { matcher }
+        Self {
+            matcher,
+            heuristic: None,
+            filter: (),
+        }
     }
 }
 
-impl<LC: LocalCost + 'static> Saturator<LC> for DirectedSaturator {
+impl<F> DirectedSaturator<F> {
+    /// Makes the saturation best-first, applying in every step the match whose result
+    /// is estimated by `heuristic` to be closest to its goal, see [`GoalDirectedScheduler`].
+    pub fn with_heuristic(mut self, heuristic: Box<dyn Heuristic>) -> Self {
+        self.heuristic = Some(Rc::from(heuristic));
+        self
+    }
+
+    /// Makes the saturator skip rule applications rejected by `filter`.
+    pub fn with_filter<G>(self, filter: G) -> DirectedSaturator<G> {
+        DirectedSaturator {
+            matcher: self.matcher,
+            heuristic: self.heuristic,
+            filter,
+        }
+    }
+}
+
+impl<LC: LocalCost + 'static, F: ApplicationFilter<LC>> Saturator<LC> for DirectedSaturator<F> {
     fn saturate(
         &self,
         egraph: &mut EGraph<LC>,
         rules: &[Rule],
         config: &SaturationConfig,
-    ) -> SaturationStopReason {
-        let scheduler = Box::new(CostDirectedScheduler::<LC>::new(rules.to_vec()));
+    ) -> SaturationReport {
+        let scheduler: Box<dyn Scheduler<LC>> = match &self.heuristic {
+            Some(heuristic) => Box::new(GoalDirectedScheduler::<LC>::new(
+                rules.to_vec(),
+                heuristic.clone(),
+            )),
+            None => Box::new(CostDirectedScheduler::<LC>::new(
+                rules.to_vec(),
+                egraph.costs(),
+            )),
+        };
         let mut saturator = ScheduledSaturator::new(scheduler);
-        saturator.run(egraph, config, &*self.matcher)
+        saturator.run_filtered(egraph, config, &*self.matcher, &self.filter)
     }
 }
 
@@ -61,7 +101,10 @@ mod tests {
         config: &SaturationConfig,
     ) -> SaturationStopReason {
         let saturator = DirectedSaturator::new(Box::new(BottomUpMatcher));
-        saturator.saturate(egraph, rules, config)
+        saturator
+            .saturate(egraph, rules, config)
+            .stop_reason
+            .unwrap()
     }
 
     // Helpers to reduce repetition in assertions and runs
@@ -0,0 +1,306 @@
+//! Filters of rule applications.
+//!
+//! Saturators consult an [`ApplicationFilter`] before every application of a rule,
+//! which lets them skip applications creating too large terms, see [`TermBounds`],
+//! or applications which are invalid according to an analysis, e.g. applying
+//! `(/ $0 $0) => 1` where `$0` may be zero. An [`IterationBudget`] rejects all applications
+//! once a limit of the saturation is hit, ending the iteration early.
+
+use std::{cell::Cell, time::Instant};
+
+use crate::{
+    language::expression::MixedExpression,
+    rewriting::{
+        dynamic_rule::DynamicRule,
+        egraph::{
+            Analysis, ClassId, DynEGraph, EGraph,
+            class::{cost_lower_bound::CostLowerBound, local_cost::LocalCost, term_size::TermSize},
+            matching::EGraphMatch,
+        },
+        rule::Rule,
+    },
+    seen::Seen,
+};
+
+use super::{SaturationConfig, SaturationReport, SaturationStopReason, TermBounds};
+
+/// Decides which matches of rules may be applied.
+pub trait ApplicationFilter<A: Analysis> {
+    /// `true` if `rule` may be applied at `matching` in `egraph`.
+    fn accept(&self, egraph: &EGraph<A>, matching: &EGraphMatch, rule: &Rule) -> bool;
+
+    /// `true` if the dynamic `rule` may be applied at `matching` in `egraph`.
+    ///
+    /// The right-hand side of a dynamic rule is only known once it is built, so the filter
+    /// is consulted after it has been added to `egraph` as `added`, and the addition is rolled
+    /// back if the application is rejected. Filters which do not override it accept
+    /// all applications of dynamic rules.
+    fn accept_dynamic(
+        &self,
+        _egraph: &EGraph<A>,
+        _matching: &EGraphMatch,
+        _rule: &DynamicRule,
+        _added: &Seen<ClassId>,
+    ) -> bool {
+        true
+    }
+}
+
+/// Accepts all applications.
+impl<A: Analysis> ApplicationFilter<A> for () {
+    fn accept(&self, _egraph: &EGraph<A>, _matching: &EGraphMatch, _rule: &Rule) -> bool {
+        true
+    }
+}
+
+/// Accepts applications which do not add nodes representing terms outside of the bounds.
+impl<A: Analysis> ApplicationFilter<A> for TermBounds {
+    fn accept(&self, egraph: &EGraph<A>, matching: &EGraphMatch, rule: &Rule) -> bool {
+        if self.is_unbounded() {
+            return true;
+        }
+
+        let to_add = rule.to().clone().mixed_expression(matching);
+        if matches!(to_add, MixedExpression::Class(_)) {
+            return true;
+        }
+
+        self.admits_addition(egraph, matching, mixed_term_size(egraph, &to_add), || {
+            mixed_cost_lower_bound(egraph, &to_add)
+        })
+    }
+
+    fn accept_dynamic(
+        &self,
+        egraph: &EGraph<A>,
+        matching: &EGraphMatch,
+        _rule: &DynamicRule,
+        added: &Seen<ClassId>,
+    ) -> bool {
+        let Seen::New(class_id) = added else {
+            return true;
+        };
+        if self.is_unbounded() {
+            return true;
+        }
+
+        let analysis = egraph.class(*class_id).analysis();
+        self.admits_addition(egraph, matching, analysis.term_size(), || {
+            analysis.cost_lower_bound()
+        })
+    }
+}
+
+impl TermBounds {
+    /// `true` if a term of size and depth given by `term` and cost bounded from below by `cost`
+    /// may be added at `matching`, where `None` stands for an unknown value.
+    fn admits_addition<A: Analysis>(
+        &self,
+        egraph: &EGraph<A>,
+        matching: &EGraphMatch,
+        term: Option<TermSize>,
+        cost: impl FnOnce() -> Option<usize>,
+    ) -> bool {
+        let admits_cost = || {
+            let Some(max_increase) = self.max_cost_increase else {
+                return true;
+            };
+            let root = egraph.class(matching.root()).analysis().cost_lower_bound();
+            root.zip(cost())
+                .is_none_or(|(root, cost)| cost <= root + max_increase)
+        };
+        term.is_none_or(|term| self.admits(term)) && admits_cost()
+    }
+}
+
+impl<A, F> ApplicationFilter<A> for F
+where
+    A: Analysis,
+    F: Fn(&EGraph<A>, &EGraphMatch, &Rule) -> bool,
+{
+    fn accept(&self, egraph: &EGraph<A>, matching: &EGraphMatch, rule: &Rule) -> bool {
+        self(egraph, matching, rule)
+    }
+}
+
+/// Accepts applications accepted by both filters.
+impl<A, F, G> ApplicationFilter<A> for (&F, &G)
+where
+    A: Analysis,
+    F: ApplicationFilter<A> + ?Sized,
+    G: ApplicationFilter<A> + ?Sized,
+{
+    fn accept(&self, egraph: &EGraph<A>, matching: &EGraphMatch, rule: &Rule) -> bool {
+        self.0.accept(egraph, matching, rule) && self.1.accept(egraph, matching, rule)
+    }
+
+    fn accept_dynamic(
+        &self,
+        egraph: &EGraph<A>,
+        matching: &EGraphMatch,
+        rule: &DynamicRule,
+        added: &Seen<ClassId>,
+    ) -> bool {
+        self.0.accept_dynamic(egraph, matching, rule, added)
+            && self.1.accept_dynamic(egraph, matching, rule, added)
+    }
+}
+
+/// Accepts applications until a limit of a [`SaturationConfig`] is hit during an iteration,
+/// including [`SaturationConfig::max_applications_per_iteration`], or its stop condition holds
+/// after an application.
+///
+/// An application is counted if it changed the e-graph, which is detected when the next one
+/// is considered, so the budget should be consulted last, after all other filters accepted.
+/// The number of nodes is estimated from above by assuming that no nodes created during
+/// the iteration were deduplicated. Applications of dynamic rules are considered after their
+/// right-hand sides are added, so the nodes they add already count towards the limits.
+pub struct IterationBudget<'a> {
+    config: &'a SaturationConfig,
+    start: Instant,
+    /// Applications, nodes and total nodes before the iteration
+    applications: usize,
+    node_count: usize,
+    total_node_count: usize,
+    /// Total nodes and classes before the last accepted application
+    last_state: Cell<Option<(usize, usize)>>,
+    applied: Cell<usize>,
+    exhausted: Cell<Option<SaturationStopReason>>,
+}
+
+impl<'a> IterationBudget<'a> {
+    /// Creates the budget of an iteration of a saturation of `egraph` which started at `start`
+    /// and has already made the progress given by `report`.
+    pub fn new(
+        egraph: &dyn DynEGraph,
+        report: &SaturationReport,
+        start: Instant,
+        config: &'a SaturationConfig,
+    ) -> Self {
+        let node_count = match config.max_nodes {
+            Some(_) => egraph.actual_node_count(),
+            None => 0,
+        };
+        Self {
+            config,
+            start,
+            applications: report.applications,
+            node_count,
+            total_node_count: egraph.total_node_count(),
+            last_state: Cell::new(None),
+            applied: Cell::new(0),
+            exhausted: Cell::new(None),
+        }
+    }
+
+    /// Returns the limit which made the budget reject applications, if any. Exhausting
+    /// [`SaturationConfig::max_applications_per_iteration`] is reported as
+    /// [`SaturationStopReason::MaxApplications`].
+    pub fn exhausted(&self) -> Option<SaturationStopReason> {
+        self.exhausted.get()
+    }
+
+    fn exceeded_limit(
+        &self,
+        total_node_count: usize,
+        class_count: usize,
+    ) -> Option<SaturationStopReason> {
+        if self
+            .config
+            .max_applications_per_iteration
+            .is_some_and(|limit| self.applied.get() >= limit)
+        {
+            return Some(SaturationStopReason::MaxApplications);
+        }
+
+        super::exceeded_limit(
+            self.config,
+            self.applications + self.applied.get(),
+            self.start,
+            || self.node_count + (total_node_count - self.total_node_count),
+            class_count,
+        )
+    }
+
+    /// `true` if another application may be made in `egraph`.
+    fn admits_next<A: Analysis>(&self, egraph: &EGraph<A>) -> bool {
+        if self.exhausted.get().is_some() {
+            return false;
+        }
+
+        // Applications either add nodes or merge classes
+        let state = (egraph.total_node_count(), egraph.class_count());
+        if self.last_state.get().is_some_and(|last| last != state) {
+            self.applied.set(self.applied.get() + 1);
+            if self.config.should_stop(egraph) {
+                self.exhausted
+                    .set(Some(SaturationStopReason::StopConditionHolds));
+                return false;
+            }
+        }
+
+        if let Some(reason) = self.exceeded_limit(state.0, state.1) {
+            self.exhausted.set(Some(reason));
+            return false;
+        }
+        self.last_state.set(Some(state));
+        true
+    }
+}
+
+impl<A: Analysis> ApplicationFilter<A> for IterationBudget<'_> {
+    fn accept(&self, egraph: &EGraph<A>, _matching: &EGraphMatch, _rule: &Rule) -> bool {
+        self.admits_next(egraph)
+    }
+
+    fn accept_dynamic(
+        &self,
+        egraph: &EGraph<A>,
+        _matching: &EGraphMatch,
+        _rule: &DynamicRule,
+        _added: &Seen<ClassId>,
+    ) -> bool {
+        self.admits_next(egraph)
+    }
+}
+
+/// Lower bound on the cost of `expression` with the costs of [`CostLowerBound`],
+/// `None` if the analysis does not track the bounds of classes.
+fn mixed_cost_lower_bound<A: Analysis>(
+    egraph: &EGraph<A>,
+    expression: &MixedExpression,
+) -> Option<usize> {
+    match expression {
+        MixedExpression::Literal(literal) => {
+            Some(CostLowerBound::literal_cost(literal, egraph.costs()).value())
+        }
+        MixedExpression::Class(class_id) => egraph.class(*class_id).analysis().cost_lower_bound(),
+        MixedExpression::Symbol(symbol) => Some(
+            CostLowerBound::symbol_cost(symbol.id, egraph.costs()).value()
+                + symbol
+                    .children
+                    .iter()
+                    .map(|child| mixed_cost_lower_bound(egraph, child))
+                    .sum::<Option<usize>>()?,
+        ),
+    }
+}
+
+/// Size and depth of the smallest term represented by `expression`,
+/// `None` if the analysis of one of its classes does not track them.
+fn mixed_term_size<A: Analysis>(
+    egraph: &EGraph<A>,
+    expression: &MixedExpression,
+) -> Option<TermSize> {
+    match expression {
+        MixedExpression::Literal(_) => Some(TermSize::leaf()),
+        MixedExpression::Class(class_id) => egraph.class(*class_id).analysis().term_size(),
+        MixedExpression::Symbol(symbol) => Some(TermSize::node(
+            symbol
+                .children
+                .iter()
+                .map(|child| mixed_term_size(egraph, child))
+                .collect::<Option<Vec<_>>>()?,
+        )),
+    }
+}
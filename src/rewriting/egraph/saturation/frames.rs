@@ -0,0 +1,156 @@
+//! Frames of e-graphs written during saturation.
+//!
+//! A [`FrameDump`] set in [`SaturationConfig::frame_dump`](super::SaturationConfig::frame_dump)
+//! writes the e-graph to a numbered file before the first iteration and after every one,
+//! e.g. `frame-00000.dot`, `frame-00001.dot`, ..., from which an animation of the growth
+//! of the e-graph can be rendered, e.g. with
+//! `for f in frame-*.dot; do dot -Tpng "$f" -o "${f%.dot}.png"; done`.
+//!
+//! IDs of nodes and classes do not change during saturation and both are written
+//! in the order of their IDs, so that a frame differs from the previous one
+//! only by the added nodes and merged classes and layouts stay similar between frames.
+
+use std::{error::Error, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::language::Language;
+use crate::rewriting::egraph::{Analysis, EGraph};
+
+/// Format of the frames written by a [`FrameDump`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FrameFormat {
+    /// Graphviz DOT, see [`EGraph::dot`]
+    #[default]
+    Dot,
+    /// JSON of the `egraph-serialize` crate, see [`EGraph::serialized_json`]
+    Json,
+}
+
+impl FrameFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            FrameFormat::Dot => "dot",
+            FrameFormat::Json => "json",
+        }
+    }
+}
+
+/// Writes frames of an e-graph being saturated to a directory.
+#[derive(Clone, Debug)]
+pub struct FrameDump {
+    directory: PathBuf,
+    language: Language,
+    format: FrameFormat,
+}
+
+impl FrameDump {
+    /// Creates a dump writing DOT frames with symbols of `language` to `directory`,
+    /// which is created if needed.
+    pub fn new(directory: impl Into<PathBuf>, language: Language) -> Self {
+        Self {
+            directory: directory.into(),
+            language,
+            format: FrameFormat::default(),
+        }
+    }
+
+    /// Sets the format of the frames.
+    pub fn with_format(mut self, format: FrameFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Returns the path of the frame written after `iteration` iterations.
+    pub fn frame_path(&self, iteration: usize) -> PathBuf {
+        self.directory
+            .join(format!("frame-{iteration:05}.{}", self.format.extension()))
+    }
+
+    /// Writes `egraph` as the frame after `iteration` iterations.
+    pub fn write<A: Analysis>(
+        &self,
+        egraph: &EGraph<A>,
+        iteration: usize,
+    ) -> Result<(), Box<dyn Error>> {
+        std::fs::create_dir_all(&self.directory)?;
+        let path = self.frame_path(iteration);
+        match self.format {
+            FrameFormat::Dot => egraph.save_dot(&self.language, path)?,
+            FrameFormat::Json => egraph.save_serialized_json(&self.language, path)?,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        language::Language,
+        macros::rules,
+        rewriting::egraph::{
+            EGraph,
+            class::simple_math_local_cost::SimpleMathLocalCost,
+            matching::bottom_up::BottomUpMatcher,
+            saturation::{SaturationConfig, Saturator, directed_saturator::DirectedSaturator},
+        },
+        utils::json,
+    };
+
+    use super::{FrameDump, FrameFormat};
+
+    #[test]
+    fn writes_frame_after_every_iteration() {
+        let lang = Language::simple_math();
+        let rules = rules!(lang;
+            "(* $0 2)" => "(<< $0 1)",
+            "(+ $0 $1)" => "(+ $1 $0)",
+        );
+        let directory = std::env::temp_dir().join(format!("frames-{}", std::process::id()));
+        let dump = FrameDump::new(&directory, lang.clone()).with_format(FrameFormat::Json);
+        let config = SaturationConfig::default().with_frame_dump(dump.clone());
+
+        let mut egraph = EGraph::<SimpleMathLocalCost>::from_expression(
+            lang.parse_no_vars("(+ (* 3 2) 1)").unwrap(),
+        );
+        let report = DirectedSaturator::new(Box::new(BottomUpMatcher)).saturate(
+            &mut egraph,
+            &rules,
+            &config,
+        );
+
+        for iteration in 0..=report.iterations {
+            assert!(dump.frame_path(iteration).exists());
+        }
+        assert!(!dump.frame_path(report.iterations + 1).exists());
+        assert!(report.frame_errors.is_empty());
+
+        let first: serde_json::Value = json::load_json(dump.frame_path(0)).unwrap();
+        let last: serde_json::Value = json::load_json(dump.frame_path(report.iterations)).unwrap();
+        assert_eq!(first["nodes"].as_object().unwrap().len(), 5);
+        assert_eq!(last, egraph.serialized_json(&lang));
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn reports_unwritable_frames() {
+        let lang = Language::simple_math();
+        let rules = rules!(lang; "(* $0 2)" => "(<< $0 1)");
+        // A file where the directory should be
+        let file = std::env::temp_dir().join(format!("frames-file-{}", std::process::id()));
+        std::fs::write(&file, "").unwrap();
+        let config = SaturationConfig::default()
+            .with_frame_dump(FrameDump::new(file.join("frames"), lang.clone()));
+
+        let mut egraph =
+            EGraph::<SimpleMathLocalCost>::from_expression(lang.parse_no_vars("(* 3 2)").unwrap());
+        let report = DirectedSaturator::new(Box::new(BottomUpMatcher)).saturate(
+            &mut egraph,
+            &rules,
+            &config,
+        );
+
+        assert_eq!(report.frame_errors.len(), report.iterations + 1);
+        std::fs::remove_file(file).unwrap();
+    }
+}
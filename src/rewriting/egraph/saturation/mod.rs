@@ -4,22 +4,42 @@
 //! repeatedly applying rewrite rules to an e-graph until a fixed point is reached
 //! or resource limits are hit.
 
-use std::time::{Duration, Instant};
+use std::{
+    fmt,
+    rc::Rc,
+    time::{Duration, Instant},
+};
 
-use crate::rewriting::rule::Rule;
+use serde::{Deserialize, Serialize};
 
-use super::{Analysis, DynEGraph, EGraph};
+use crate::rewriting::{dynamic_rule::RuleLike, rule::Rule};
 
+use super::{Analysis, DynEGraph, EGraph, class::term_size::TermSize};
+
+pub mod checkpoint;
+pub use checkpoint::CheckpointingSaturator;
 pub mod simple_saturator;
 pub use simple_saturator::SimpleSaturator;
 pub mod directed_saturator;
+pub mod filter;
+pub use filter::{ApplicationFilter, IterationBudget};
+pub mod frames;
+pub use frames::{FrameDump, FrameFormat};
+pub mod report;
+pub use report::{ProgressCallback, RuleReport, SaturationReport};
 pub mod scheduled_saturator;
 pub mod scheduler;
 
 /// Configuration for equality saturation.
 ///
 /// Defines resource limits that control when saturation should stop.
-#[derive(Clone, Debug, Default)]
+/// Missing fields are deserialized as their defaults, e.g. no limits.
+///
+/// Limits are checked between iterations and, through an [`IterationBudget`], before every
+/// rule application within an iteration, so that a single iteration applying many matches
+/// cannot exceed them by much.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SaturationConfig {
     /// Maximum number of nodes in the e-graph
     pub max_nodes: Option<usize>,
@@ -27,12 +47,161 @@ pub struct SaturationConfig {
     pub max_classes: Option<usize>,
     /// Maximum number of rule applications
     pub max_applications: Option<usize>,
+    /// Maximum number of iterations, i.e. scheduler steps
+    pub max_iterations: Option<usize>,
+    /// Maximum number of rule applications in a single iteration, the remaining matches
+    /// are left for the next iterations
+    pub max_applications_per_iteration: Option<usize>,
     /// Maximum time to spend saturating
     pub time_limit: Option<Duration>,
+    /// Maximum depth of the smallest terms represented by nodes created by rule applications
+    pub max_term_depth: Option<usize>,
+    /// Maximum size of the smallest terms represented by nodes created by rule applications
+    pub max_term_size: Option<usize>,
+    /// Maximum excess of the cost of terms created by rule applications over the cheapest
+    /// term of the class they are added to, see [`TermBounds::max_cost_increase`]
+    pub max_cost_increase: Option<usize>,
+    /// Whether reachability checks remove rules which cannot contribute to rewriting the source
+    /// into a goal according to the abelianized relaxation before saturating,
+    /// see [`supporting_rules`](crate::rewriting::abelian::supporting_rules)
+    pub preprocess: bool,
+    /// Number of iterations between checkpoints written by a [`CheckpointingSaturator`],
+    /// none are written if `None`
+    pub checkpoint_every: Option<usize>,
+    /// Called after every iteration with the current state of the saturation
+    #[serde(skip)]
+    pub progress_callback: Option<ProgressCallback>,
+    /// Writes a frame of the e-graph before the first iteration and after every one
+    #[serde(skip)]
+    pub frame_dump: Option<FrameDump>,
+    /// Stops the saturation as soon as it holds, checked after every rule application
+    #[serde(skip)]
+    pub stop_when: Option<StopCondition>,
+}
+
+impl SaturationConfig {
+    /// Sets a function to be called after every iteration of the saturation.
+    pub fn with_progress_callback(
+        mut self,
+        callback: impl FnMut(&SaturationReport) + 'static,
+    ) -> Self {
+        self.progress_callback = Some(ProgressCallback::new(callback));
+        self
+    }
+
+    /// Sets a condition stopping the saturation as soon as it holds, e.g. when a literal
+    /// appears in the class of the saturated expression.
+    pub fn with_stop_when(mut self, condition: impl Fn(&dyn DynEGraph) -> bool + 'static) -> Self {
+        self.stop_when = Some(StopCondition::new(condition));
+        self
+    }
+
+    /// `true` if the stop condition holds for `egraph`.
+    pub fn should_stop(&self, egraph: &dyn DynEGraph) -> bool {
+        self.stop_when
+            .as_ref()
+            .is_some_and(|condition| condition.holds(egraph))
+    }
+
+    /// Sets where frames of the e-graph are written during the saturation.
+    pub fn with_frame_dump(mut self, frame_dump: FrameDump) -> Self {
+        self.frame_dump = Some(frame_dump);
+        self
+    }
+
+    /// Returns the bounds on terms created by rule applications.
+    pub fn term_bounds(&self) -> TermBounds {
+        TermBounds {
+            max_size: self.max_term_size,
+            max_depth: self.max_term_depth,
+            max_cost_increase: self.max_cost_increase,
+        }
+    }
+
+    /// Calls the progress callback, if there is one.
+    pub fn report_progress(&self, report: &SaturationReport) {
+        if let Some(callback) = &self.progress_callback {
+            callback.call(report);
+        }
+    }
+
+    /// Writes the frame of `egraph` after the iterations of `report`, if frames are dumped.
+    /// Failing to write a frame does not stop the saturation, the error is recorded in
+    /// [`SaturationReport::frame_errors`].
+    pub fn dump_frame<A: Analysis>(&self, egraph: &EGraph<A>, report: &mut SaturationReport) {
+        if let Some(frame_dump) = &self.frame_dump
+            && let Err(error) = frame_dump.write(egraph, report.iterations)
+        {
+            report.frame_errors.push(format!(
+                "Failed to write frame {}: {error}",
+                report.iterations
+            ));
+        }
+    }
+}
+
+type StopFn = dyn Fn(&dyn DynEGraph) -> bool;
+
+/// A condition on the e-graph stopping a saturation, see [`SaturationConfig::stop_when`].
+///
+/// Clones of a condition share the underlying function.
+#[derive(Clone)]
+pub struct StopCondition(Rc<StopFn>);
+
+impl StopCondition {
+    /// Creates a condition which holds for e-graphs for which `condition` returns `true`.
+    pub fn new(condition: impl Fn(&dyn DynEGraph) -> bool + 'static) -> Self {
+        Self(Rc::new(condition))
+    }
+
+    /// Checks if the condition holds for `egraph`, i.e. if its saturation should stop.
+    pub fn holds(&self, egraph: &dyn DynEGraph) -> bool {
+        (self.0)(egraph)
+    }
+}
+
+impl fmt::Debug for StopCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("StopCondition")
+    }
+}
+
+/// Bounds on the terms created by rule applications.
+///
+/// Applications which would add a node whose smallest represented term is larger or deeper
+/// than allowed are skipped. The sizes of represented terms are taken from
+/// [`Analysis::term_size`], so the bounds have no effect on e-graphs whose analysis
+/// does not track them, e.g. without [`TermSize`].
+/// Analysis data is not updated when the classes of children are merged, so the tracked sizes
+/// can be larger than the actual ones, which makes the bounds stricter.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct TermBounds {
+    pub max_size: Option<usize>,
+    pub max_depth: Option<usize>,
+    /// Maximum excess of a lower bound on the cost of an added term over the lower bound of
+    /// the class it is added to, both taken from [`Analysis::cost_lower_bound`].
+    /// Applications creating obviously expensive terms, e.g. the right-hand sides of expanding
+    /// rules, are skipped, which prunes directed saturations early.
+    /// It has no effect on e-graphs whose analysis does not track the bounds, e.g. without
+    /// [`CostLowerBound`](super::class::cost_lower_bound::CostLowerBound).
+    pub max_cost_increase: Option<usize>,
+}
+
+impl TermBounds {
+    /// `true` if no bound is set.
+    pub fn is_unbounded(&self) -> bool {
+        self.max_size.is_none() && self.max_depth.is_none() && self.max_cost_increase.is_none()
+    }
+
+    /// `true` if a term of size and depth given by `term` is within the bounds.
+    pub fn admits(&self, term: TermSize) -> bool {
+        self.max_size.is_none_or(|max| term.size <= max)
+            && self.max_depth.is_none_or(|max| term.depth <= max)
+    }
 }
 
 /// Reason why saturation stopped.
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SaturationStopReason {
     /// Reached a fixed point (no more rules can be applied)
     Saturated,
@@ -42,6 +211,10 @@ pub enum SaturationStopReason {
     MaxClasses,
     /// Hit the maximum applications limit
     MaxApplications,
+    /// Hit the maximum iterations limit
+    MaxIterations,
+    /// The stop condition holds
+    StopConditionHolds,
     /// Hit the time limit
     Timeout,
 }
@@ -51,6 +224,7 @@ pub enum SaturationStopReason {
 /// # Arguments
 ///
 /// * `egraph` - The e-graph being saturated
+/// * `iterations` - The number of iterations so far
 /// * `applications` - The number of rule applications so far
 /// * `start` - The time when saturation started
 /// * `cfg` - The saturation configuration
@@ -60,9 +234,38 @@ pub enum SaturationStopReason {
 /// Returns `Some(reason)` if a limit was hit, `None` otherwise
 pub fn check_limits(
     egraph: &dyn DynEGraph,
+    iterations: usize,
     applications: usize,
     start: Instant,
     cfg: &SaturationConfig,
+) -> Option<SaturationStopReason> {
+    if cfg.should_stop(egraph) {
+        return Some(SaturationStopReason::StopConditionHolds);
+    }
+
+    if let Some(limit) = cfg.max_iterations
+        && iterations >= limit
+    {
+        return Some(SaturationStopReason::MaxIterations);
+    }
+
+    exceeded_limit(
+        cfg,
+        applications,
+        start,
+        || egraph.actual_node_count(),
+        egraph.class_count(),
+    )
+}
+
+/// Checks the limits of `cfg` other than the number of iterations,
+/// computing the number of nodes only if it is limited.
+fn exceeded_limit(
+    cfg: &SaturationConfig,
+    applications: usize,
+    start: Instant,
+    node_count: impl FnOnce() -> usize,
+    class_count: usize,
 ) -> Option<SaturationStopReason> {
     if let Some(limit) = cfg.time_limit
         && start.elapsed() >= limit
@@ -77,13 +280,13 @@ pub fn check_limits(
     }
 
     if let Some(limit) = cfg.max_nodes
-        && egraph.actual_node_count() >= limit
+        && node_count() >= limit
     {
         return Some(SaturationStopReason::MaxNodes);
     }
 
     if let Some(limit) = cfg.max_classes
-        && egraph.class_count() >= limit
+        && class_count >= limit
     {
         return Some(SaturationStopReason::MaxClasses);
     }
@@ -92,10 +295,50 @@ pub fn check_limits(
 }
 
 pub trait Saturator<A: Analysis> {
+    /// Saturates `egraph` with `rules` until a limit from `config` is hit or nothing changes.
+    ///
+    /// # Returns
+    ///
+    /// Returns a report with the reason why the saturation stopped and statistics of `rules`
     fn saturate(
         &self,
         egraph: &mut EGraph<A>,
         rules: &[Rule],
         config: &SaturationConfig,
-    ) -> SaturationStopReason;
+    ) -> SaturationReport;
+
+    /// Same as [`Saturator::saturate`], but `rules` can also have dynamic right-hand sides.
+    ///
+    /// Saturators which do not override it, e.g. ones choosing rules by their right-hand sides,
+    /// return [`UnsupportedDynamicRules`] if some of `rules` are dynamic,
+    /// and otherwise saturate with the static rules.
+    fn saturate_dynamic(
+        &self,
+        egraph: &mut EGraph<A>,
+        rules: &[RuleLike],
+        config: &SaturationConfig,
+    ) -> Result<SaturationReport, UnsupportedDynamicRules> {
+        let rules = rules
+            .iter()
+            .map(|rule| rule.as_static().cloned())
+            .collect::<Option<Vec<_>>>()
+            .ok_or(UnsupportedDynamicRules)?;
+        Ok(self.saturate(egraph, &rules, config))
+    }
 }
+
+/// Error returned by [`Saturator::saturate_dynamic`] of saturators which cannot apply
+/// rules with dynamic right-hand sides.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnsupportedDynamicRules;
+
+impl fmt::Display for UnsupportedDynamicRules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Saturator cannot apply rules with dynamic right-hand sides"
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedDynamicRules {}
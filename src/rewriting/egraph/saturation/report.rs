@@ -0,0 +1,99 @@
+//! Progress reporting of equality saturation.
+
+use std::{cell::RefCell, fmt, rc::Rc, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{rewriting::egraph::DynEGraph, union_find::UnionFindStats};
+
+use super::SaturationStopReason;
+
+/// Statistics of a single rule gathered during saturation.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuleReport {
+    /// Number of matches of the left-hand side of the rule found in the e-graph
+    pub matches: usize,
+    /// Number of matches which changed the e-graph
+    pub applications: usize,
+    /// Number of applications which merged two classes
+    pub unions: usize,
+    /// Time spent searching for matches
+    pub match_time: Duration,
+    /// Time spent adding right-hand sides of matches to the e-graph
+    pub apply_time: Duration,
+}
+
+impl RuleReport {
+    /// Total time spent on the rule.
+    pub fn time(&self) -> Duration {
+        self.match_time + self.apply_time
+    }
+}
+
+/// State of a saturation, passed to the [`ProgressCallback`] after every iteration
+/// and returned by [`Saturator::saturate`](super::Saturator::saturate).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SaturationReport {
+    /// Why the saturation stopped, `None` while it is still running
+    pub stop_reason: Option<SaturationStopReason>,
+    /// Number of scheduler steps performed so far
+    pub iterations: usize,
+    /// Total number of rule applications
+    pub applications: usize,
+    /// Number of nodes in the e-graph
+    pub node_count: usize,
+    /// Number of classes in the e-graph
+    pub class_count: usize,
+    /// Time since the start of the saturation
+    pub elapsed: Duration,
+    /// Statistics of rules, indexed like the rules given to the saturator
+    pub rules: Vec<RuleReport>,
+    /// Work done by the union-find of the e-graph since its creation
+    pub union_find: UnionFindStats,
+    /// Errors of writing frames, see [`SaturationConfig::frame_dump`](super::SaturationConfig::frame_dump)
+    pub frame_errors: Vec<String>,
+}
+
+impl SaturationReport {
+    /// Returns statistics of the rule with index `index`, creating them if needed.
+    pub fn rule_mut(&mut self, index: usize) -> &mut RuleReport {
+        if index >= self.rules.len() {
+            self.rules.resize_with(index + 1, Default::default);
+        }
+        &mut self.rules[index]
+    }
+
+    /// Updates the e-graph size and elapsed time.
+    pub(crate) fn snapshot(&mut self, egraph: &dyn DynEGraph, elapsed: Duration) {
+        self.node_count = egraph.actual_node_count();
+        self.class_count = egraph.class_count();
+        self.union_find = egraph.union_find_stats();
+        self.elapsed = elapsed;
+    }
+}
+
+type ProgressFn = dyn FnMut(&SaturationReport);
+
+/// A function called with a [`SaturationReport`] after every iteration of a saturation.
+///
+/// Clones of a callback share the underlying function.
+#[derive(Clone)]
+pub struct ProgressCallback(Rc<RefCell<ProgressFn>>);
+
+impl ProgressCallback {
+    /// Wraps `callback`, which is called with the report of the saturation so far.
+    pub fn new(callback: impl FnMut(&SaturationReport) + 'static) -> Self {
+        Self(Rc::new(RefCell::new(callback)))
+    }
+
+    /// Calls the wrapped function with `report`.
+    pub fn call(&self, report: &SaturationReport) {
+        (self.0.borrow_mut())(report)
+    }
+}
+
+impl fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
@@ -1,10 +1,14 @@
+use std::convert::Infallible;
 use std::time::Instant;
 
 use super::super::Analysis;
 use crate::rewriting::egraph::EGraph;
 use crate::rewriting::egraph::matching::Matcher;
 use crate::rewriting::egraph::saturation::scheduler::Scheduler;
-use crate::rewriting::egraph::saturation::{SaturationConfig, SaturationStopReason, check_limits};
+use crate::rewriting::egraph::saturation::{
+    ApplicationFilter, IterationBudget, SaturationConfig, SaturationReport, SaturationStopReason,
+    check_limits,
+};
 
 pub struct ScheduledSaturator<A> {
     scheduler: Box<dyn Scheduler<A>>,
@@ -20,22 +24,98 @@ impl<A: Analysis> ScheduledSaturator<A> {
         egraph: &mut EGraph<A>,
         config: &SaturationConfig,
         matcher: &dyn Matcher,
-    ) -> SaturationStopReason {
-        let start = Instant::now();
-        let mut applications: usize = 0;
+    ) -> SaturationReport {
+        self.run_filtered(egraph, config, matcher, &())
+    }
+
+    /// Same as [`ScheduledSaturator::run`], but skips applications rejected by `filter`.
+    pub fn run_filtered(
+        &mut self,
+        egraph: &mut EGraph<A>,
+        config: &SaturationConfig,
+        matcher: &dyn Matcher,
+        filter: &dyn ApplicationFilter<A>,
+    ) -> SaturationReport {
+        let Ok(report) = self.run_from(
+            egraph,
+            config,
+            matcher,
+            filter,
+            SaturationReport::default(),
+            |_, _, _| Ok::<_, Infallible>(()),
+        );
+        report
+    }
 
-        loop {
-            if let Some(reason) = check_limits(egraph, applications, start, config) {
-                return reason;
+    /// Returns the scheduler choosing the rules to apply.
+    pub fn scheduler(&self) -> &dyn Scheduler<A> {
+        &*self.scheduler
+    }
+
+    /// Returns the scheduler choosing the rules to apply.
+    pub fn scheduler_mut(&mut self) -> &mut dyn Scheduler<A> {
+        &mut *self.scheduler
+    }
+
+    /// Continues a saturation which has already made the progress given by `report`,
+    /// calling `after_iteration` after every iteration and stopping on its first error.
+    pub(super) fn run_from<E>(
+        &mut self,
+        egraph: &mut EGraph<A>,
+        config: &SaturationConfig,
+        matcher: &dyn Matcher,
+        filter: &dyn ApplicationFilter<A>,
+        mut report: SaturationReport,
+        mut after_iteration: impl FnMut(
+            &EGraph<A>,
+            &dyn Scheduler<A>,
+            &SaturationReport,
+        ) -> Result<(), E>,
+    ) -> Result<SaturationReport, E> {
+        let now = Instant::now();
+        let start = now.checked_sub(report.elapsed).unwrap_or(now);
+        let bounds = config.term_bounds();
+        let filter = (&bounds, filter);
+        report.stop_reason = None;
+        if report.iterations == 0 {
+            config.dump_frame(egraph, &mut report);
+        }
+
+        let reason = loop {
+            if let Some(reason) = check_limits(
+                egraph,
+                report.iterations,
+                report.applications,
+                start,
+                config,
+            ) {
+                break reason;
             }
 
-            let applied = self.scheduler.apply_next(egraph, matcher);
+            let budget = IterationBudget::new(egraph, &report, start, config);
+            let applied = self.scheduler.apply_next_reported(
+                egraph,
+                matcher,
+                &(&filter, &budget),
+                &mut report,
+            );
+            report.iterations += 1;
+            report.applications += applied;
+            report.snapshot(egraph, start.elapsed());
+            config.report_progress(&report);
+            config.dump_frame(egraph, &mut report);
+
             if applied == 0 {
-                return SaturationStopReason::Saturated;
+                break budget
+                    .exhausted()
+                    .unwrap_or(SaturationStopReason::Saturated);
             }
+            after_iteration(egraph, &*self.scheduler, &report)?;
+        };
 
-            applications += applied;
-        }
+        report.snapshot(egraph, start.elapsed());
+        report.stop_reason = Some(reason);
+        Ok(report)
     }
 }
 
@@ -83,8 +163,8 @@ mod tests {
         let scheduler = Box::new(TestScheduler::new(1, rules)); // Apply rules once
         let mut saturator = ScheduledSaturator::new(scheduler);
 
-        let reason = saturator.run(&mut egraph, &SaturationConfig::default(), &TopDownMatcher);
-        assert_eq!(reason, SaturationStopReason::Saturated);
+        let report = saturator.run(&mut egraph, &SaturationConfig::default(), &TopDownMatcher);
+        assert_eq!(report.stop_reason, Some(SaturationStopReason::Saturated));
 
         assert_eq!(egraph.class_count(), 1);
         assert_eq!(egraph.actual_node_count(), 2);
@@ -104,8 +184,8 @@ mod tests {
         let scheduler = Box::new(TestScheduler::new(2, rules)); // Apply rules twice
         let mut saturator = ScheduledSaturator::new(scheduler);
 
-        let reason = saturator.run(&mut egraph, &SaturationConfig::default(), &TopDownMatcher);
-        assert_eq!(reason, SaturationStopReason::Saturated);
+        let report = saturator.run(&mut egraph, &SaturationConfig::default(), &TopDownMatcher);
+        assert_eq!(report.stop_reason, Some(SaturationStopReason::Saturated));
 
         assert_eq!(egraph.class_count(), 1);
         assert_eq!(egraph.actual_node_count(), 3);
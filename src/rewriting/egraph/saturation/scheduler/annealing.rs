@@ -0,0 +1,227 @@
+use std::marker::PhantomData;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::rewriting::egraph::EGraph;
+use crate::rewriting::egraph::class::{local_cost::LocalCost, table_local_cost::SymbolCosts};
+use crate::rewriting::egraph::matching::Matcher;
+use crate::rewriting::egraph::saturation::{SaturationReport, filter::ApplicationFilter};
+use crate::rewriting::rule::Rule;
+
+use super::Scheduler;
+use super::cost_directed::rule_cost;
+
+/// Temperature of an [`AnnealingScheduler`] in consecutive steps, i.e. the probability
+/// of trying a random non-improving rule instead of the greedy choice.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TemperatureSchedule {
+    /// `initial * decay^step`
+    Exponential { initial: f64, decay: f64 },
+    /// Decreases from `initial` to zero over `steps` steps
+    Linear { initial: f64, steps: usize },
+}
+
+impl Default for TemperatureSchedule {
+    fn default() -> Self {
+        TemperatureSchedule::Exponential {
+            initial: 0.5,
+            decay: 0.95,
+        }
+    }
+}
+
+impl TemperatureSchedule {
+    /// Returns the temperature in step `step`, between 0 and 1.
+    pub fn temperature(&self, step: usize) -> f64 {
+        let temperature = match *self {
+            TemperatureSchedule::Exponential { initial, decay } => {
+                initial * decay.powi(step.min(i32::MAX as usize) as i32)
+            }
+            TemperatureSchedule::Linear { initial, steps } => {
+                initial * (1.0 - step as f64 / steps.max(1) as f64)
+            }
+        };
+        temperature.clamp(0.0, 1.0)
+    }
+}
+
+/// Cost-directed scheduler with simulated annealing.
+///
+/// Like [`CostDirectedScheduler`](super::CostDirectedScheduler), it applies the first rule
+/// that makes progress in the order of `rule_cost`, but with probability given by the
+/// temperature of the current step it first tries the non-improving rules in random order.
+/// As the temperature decays, the scheduler becomes greedy.
+pub struct AnnealingScheduler<LC: LocalCost> {
+    // Rules together with their indices in the original order
+    rules: Vec<(usize, Rule)>,
+    // Positions in `rules` of rules which do not decrease the cost
+    non_improving: Vec<usize>,
+    schedule: TemperatureSchedule,
+    step: usize,
+    rng: StdRng,
+    _phantom: PhantomData<LC>,
+}
+
+impl<LC: LocalCost> AnnealingScheduler<LC> {
+    /// Creates a scheduler applying `rules` in the order of their cost deltas with `costs`,
+    /// which should be the costs of the saturated e-graph, with temperatures of `schedule`.
+    pub fn new(
+        rules: Vec<Rule>,
+        schedule: TemperatureSchedule,
+        costs: Option<&SymbolCosts>,
+    ) -> Self {
+        let mut rules: Vec<_> = rules.into_iter().enumerate().collect();
+        rules.sort_by_key(|(_, a)| rule_cost::<LC>(a, costs));
+        let non_improving = rules
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, rule))| rule_cost::<LC>(rule, costs) >= LC::default())
+            .map(|(position, _)| position)
+            .collect();
+        Self {
+            rules,
+            non_improving,
+            schedule,
+            step: 0,
+            rng: StdRng::from_entropy(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Makes the random choices of the scheduler reproducible.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    fn apply_first(
+        &self,
+        positions: impl IntoIterator<Item = usize>,
+        egraph: &mut EGraph<LC>,
+        matcher: &dyn Matcher,
+        filter: &dyn ApplicationFilter<LC>,
+        report: &mut SaturationReport,
+    ) -> usize {
+        for position in positions {
+            let (index, rule) = &self.rules[position];
+            let applied = rule.apply_filtered(egraph, matcher, filter, report.rule_mut(*index));
+            if applied > 0 {
+                return applied;
+            }
+        }
+
+        0
+    }
+}
+
+impl<LC: LocalCost> Scheduler<LC> for AnnealingScheduler<LC> {
+    fn apply_next(&mut self, egraph: &mut EGraph<LC>, matcher: &dyn Matcher) -> usize {
+        self.apply_next_reported(egraph, matcher, &(), &mut SaturationReport::default())
+    }
+
+    fn apply_next_reported(
+        &mut self,
+        egraph: &mut EGraph<LC>,
+        matcher: &dyn Matcher,
+        filter: &dyn ApplicationFilter<LC>,
+        report: &mut SaturationReport,
+    ) -> usize {
+        let temperature = self.schedule.temperature(self.step);
+        self.step += 1;
+
+        if self.rng.gen_bool(temperature) {
+            let mut random = self.non_improving.clone();
+            random.shuffle(&mut self.rng);
+            let applied = self.apply_first(random, egraph, matcher, filter, report);
+            if applied > 0 {
+                return applied;
+            }
+        }
+
+        self.apply_first(0..self.rules.len(), egraph, matcher, filter, report)
+    }
+
+    /// Only the step is saved, so random choices after a restart differ from the original run.
+    fn save_state(&self) -> serde_json::Value {
+        self.step.into()
+    }
+
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<(), serde_json::Error> {
+        self.step = serde_json::from_value(state)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::language::Language;
+    use crate::macros::rules;
+    use crate::rewriting::egraph::class::simple_math_local_cost::SimpleMathLocalCost;
+    use crate::rewriting::egraph::matching::top_down::TopDownMatcher;
+    use crate::rewriting::egraph::saturation::SaturationReport;
+    use crate::rewriting::egraph::saturation::TermBounds;
+    use crate::rewriting::egraph::saturation::scheduler::Scheduler;
+    use crate::rewriting::egraph::{DynEGraph, EGraph};
+
+    use super::{AnnealingScheduler, TemperatureSchedule};
+
+    #[test]
+    fn temperature_decays() {
+        let exponential = TemperatureSchedule::Exponential {
+            initial: 0.8,
+            decay: 0.5,
+        };
+        assert_eq!(exponential.temperature(0), 0.8);
+        assert_eq!(exponential.temperature(2), 0.2);
+
+        let linear = TemperatureSchedule::Linear {
+            initial: 1.0,
+            steps: 4,
+        };
+        assert_eq!(linear.temperature(1), 0.75);
+        assert_eq!(linear.temperature(10), 0.0);
+    }
+
+    #[test]
+    fn hot_scheduler_tries_non_improving_rules_first() {
+        let lang = Language::simple_math();
+        // Costs with SimpleMathLocalCost:
+        //   (+ $0 0) => $0        delta = -2
+        //   (+ $0 $1) => (+ $1 $0) delta = 0
+        let rules = rules!(lang;
+            "(+ $0 0)" => "$0",
+            "(+ $0 $1)" => "(+ $1 $0)",
+        );
+
+        let mut egraph =
+            EGraph::<SimpleMathLocalCost>::from_expression(lang.parse_no_vars("(+ 3 0)").unwrap());
+        let mut sched = AnnealingScheduler::<SimpleMathLocalCost>::new(
+            rules,
+            TemperatureSchedule::Linear {
+                initial: 1.0,
+                steps: 1,
+            },
+            None,
+        )
+        .with_seed(0);
+        let mut report = SaturationReport::default();
+
+        // Always random in the first step, always greedy afterwards
+        let matcher = &TopDownMatcher;
+        let bounds = TermBounds::default();
+        assert_eq!(
+            sched.apply_next_reported(&mut egraph, matcher, &bounds, &mut report),
+            1
+        );
+        assert_eq!(report.rules[1].applications, 1);
+        assert_eq!(report.rules[0].applications, 0);
+        assert_eq!(
+            sched.apply_next_reported(&mut egraph, matcher, &bounds, &mut report),
+            1
+        );
+        assert_eq!(report.rules[0].applications, 1);
+        assert_eq!(egraph.class_count(), 2);
+    }
+}
@@ -1,27 +1,33 @@
 use std::marker::PhantomData;
 
 use crate::rewriting::egraph::EGraph;
-use crate::rewriting::egraph::class::local_cost::LocalCost;
+use crate::rewriting::egraph::class::{local_cost::LocalCost, table_local_cost::SymbolCosts};
 use crate::rewriting::egraph::matching::Matcher;
+use crate::rewriting::egraph::saturation::{SaturationReport, filter::ApplicationFilter};
 use crate::rewriting::rule::Rule;
 
 use super::Scheduler;
 
-/// Computes the local-cost delta of a rule for a given `LocalCost` analysis.
-pub fn rule_cost<LC: LocalCost>(rule: &Rule) -> LC {
-    LC::expression_cost(rule.to()) - LC::expression_cost(rule.from())
+/// Computes the local-cost delta of a rule for a given `LocalCost` analysis
+/// with the costs of an e-graph, see [`EGraph::costs`].
+pub fn rule_cost<LC: LocalCost>(rule: &Rule, costs: Option<&SymbolCosts>) -> LC {
+    LC::expression_cost(rule.to(), costs) - LC::expression_cost(rule.from(), costs)
 }
 
 /// Cost-directed scheduler that orders rules by `rule_cost` (ascending) and
 /// applies the first rule that makes progress in each step.
 pub struct CostDirectedScheduler<LC: LocalCost> {
-    rules: Vec<Rule>,
+    // Rules together with their indices in the original order
+    rules: Vec<(usize, Rule)>,
     _phantom: PhantomData<LC>,
 }
 
 impl<LC: LocalCost> CostDirectedScheduler<LC> {
-    pub fn new(mut rules: Vec<Rule>) -> Self {
-        rules.sort_by_key(|a| rule_cost::<LC>(a));
+    /// Creates a scheduler applying `rules` in the order of their cost deltas with `costs`,
+    /// which should be the costs of the saturated e-graph.
+    pub fn new(rules: Vec<Rule>, costs: Option<&SymbolCosts>) -> Self {
+        let mut rules: Vec<_> = rules.into_iter().enumerate().collect();
+        rules.sort_by_key(|(_, a)| rule_cost::<LC>(a, costs));
         Self {
             rules,
             _phantom: PhantomData,
@@ -31,8 +37,18 @@ impl<LC: LocalCost> CostDirectedScheduler<LC> {
 
 impl<LC: LocalCost> Scheduler<LC> for CostDirectedScheduler<LC> {
     fn apply_next(&mut self, egraph: &mut EGraph<LC>, matcher: &dyn Matcher) -> usize {
-        for rule in self.rules.iter() {
-            let applied = rule.apply(egraph, matcher);
+        self.apply_next_reported(egraph, matcher, &(), &mut SaturationReport::default())
+    }
+
+    fn apply_next_reported(
+        &mut self,
+        egraph: &mut EGraph<LC>,
+        matcher: &dyn Matcher,
+        filter: &dyn ApplicationFilter<LC>,
+        report: &mut SaturationReport,
+    ) -> usize {
+        for (index, rule) in self.rules.iter() {
+            let applied = rule.apply_filtered(egraph, matcher, filter, report.rule_mut(*index));
             if applied > 0 {
                 return applied;
             }
@@ -71,7 +87,7 @@ mod tests {
             "(+ $0 0)" => "$0",        // cheap
         ];
 
-        let mut sched = CostDirectedScheduler::<SimpleMathLocalCost>::new(rules);
+        let mut sched = CostDirectedScheduler::<SimpleMathLocalCost>::new(rules, None);
         let applied = sched.apply_next(&mut egraph, &TopDownMatcher);
         assert_eq!(applied, 1, "scheduler should make progress on first step");
 
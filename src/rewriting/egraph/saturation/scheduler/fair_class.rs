@@ -0,0 +1,155 @@
+use std::collections::BTreeMap;
+use std::num::NonZeroUsize;
+
+use crate::rewriting::egraph::matching::Matcher;
+use crate::rewriting::egraph::saturation::{SaturationReport, filter::ApplicationFilter};
+use crate::rewriting::egraph::{Analysis, ClassId, DynEGraph, EGraph};
+use crate::rewriting::rule::Rule;
+
+use super::Scheduler;
+
+/// Scheduler that round-robins over e-classes rather than rules.
+///
+/// In every step it finds matches of all rules and applies at most `per_class` of them
+/// rooted in each class, trying rules in their order. Classes are visited in the order
+/// of their IDs, starting after the first class which had a rule applied in the previous step,
+/// so that a single class with many matches cannot use up the whole application budget
+/// and the class visited first advances from step to step.
+pub struct FairClassScheduler {
+    rules: Vec<Rule>,
+    per_class: NonZeroUsize,
+    // Class after which the next step starts visiting classes
+    start_after: Option<ClassId>,
+}
+
+impl FairClassScheduler {
+    /// Creates a scheduler applying at most `per_class` matches rooted in each class per step.
+    pub fn new(rules: Vec<Rule>, per_class: NonZeroUsize) -> Self {
+        Self {
+            rules,
+            per_class,
+            start_after: None,
+        }
+    }
+}
+
+impl<A: Analysis> Scheduler<A> for FairClassScheduler {
+    fn apply_next(&mut self, egraph: &mut EGraph<A>, matcher: &dyn Matcher) -> usize {
+        self.apply_next_reported(egraph, matcher, &(), &mut SaturationReport::default())
+    }
+
+    fn apply_next_reported(
+        &mut self,
+        egraph: &mut EGraph<A>,
+        matcher: &dyn Matcher,
+        filter: &dyn ApplicationFilter<A>,
+        report: &mut SaturationReport,
+    ) -> usize {
+        let mut by_class = BTreeMap::<ClassId, Vec<_>>::new();
+        for (index, rule) in self.rules.iter().enumerate() {
+            for matching in rule.find_matches(egraph, matcher, report.rule_mut(index)) {
+                by_class
+                    .entry(egraph.canonical_class(matching.root()))
+                    .or_default()
+                    .push((index, matching));
+            }
+        }
+
+        let split = match self.start_after {
+            Some(start_after) => by_class.split_off(&(start_after + 1)),
+            None => BTreeMap::new(),
+        };
+
+        let mut applied = 0;
+        let mut first_applied = None;
+        for (class_id, matches) in split.into_iter().chain(by_class) {
+            let mut class_applied = 0;
+            for (index, matching) in matches {
+                if class_applied == self.per_class.get() {
+                    break;
+                }
+                if self.rules[index].apply_match(egraph, &matching, filter, report.rule_mut(index))
+                {
+                    class_applied += 1;
+                }
+            }
+            if class_applied > 0 && first_applied.is_none() {
+                first_applied = Some(class_id);
+            }
+            applied += class_applied;
+        }
+        // Applications give classes new IDs, so the next step has to find this one by its current ID
+        if let Some(class_id) = first_applied {
+            self.start_after = Some(egraph.canonical_class(class_id));
+        }
+
+        applied
+    }
+
+    fn save_state(&self) -> serde_json::Value {
+        self.start_after.into()
+    }
+
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<(), serde_json::Error> {
+        self.start_after = serde_json::from_value(state)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::language::Language;
+    use crate::macros::rules;
+    use crate::rewriting::egraph::matching::top_down::TopDownMatcher;
+    use crate::rewriting::egraph::saturation::scheduler::Scheduler;
+    use crate::rewriting::egraph::{DynEGraph, EGraph};
+
+    use std::num::NonZeroUsize;
+
+    use super::FairClassScheduler;
+
+    #[test]
+    fn applies_at_most_per_class() {
+        let lang = Language::simple_math();
+        let mut egraph = EGraph::<()>::default();
+        let left = egraph.add_expression(lang.parse_no_vars("(* 3 2)").unwrap());
+        let right = egraph.add_expression(lang.parse_no_vars("(* 5 2)").unwrap());
+        let rules = rules!(lang;
+            "(* $0 2)" => "(<< $0 1)",
+            "(* $0 2)" => "(+ $0 $0)",
+        );
+
+        let mut sched = FairClassScheduler::new(rules, NonZeroUsize::MIN);
+        assert_eq!(sched.apply_next(&mut egraph, &TopDownMatcher), 2);
+        assert_eq!(egraph.nodes(left).len(), 2);
+        assert_eq!(egraph.nodes(right).len(), 2);
+
+        assert_eq!(sched.apply_next(&mut egraph, &TopDownMatcher), 2);
+        assert_eq!(egraph.nodes(left).len(), 3);
+        assert_eq!(egraph.nodes(right).len(), 3);
+
+        assert_eq!(sched.apply_next(&mut egraph, &TopDownMatcher), 0);
+    }
+
+    #[test]
+    fn rotates_starting_class() {
+        let lang = Language::simple_math();
+        let mut egraph = EGraph::<()>::default();
+        let classes = ["(* 3 2)", "(* 5 2)", "(* 7 2)"]
+            .map(|expr| egraph.add_expression(lang.parse_no_vars(expr).unwrap()));
+        let rules = rules!(lang;
+            "(* $0 2)" => "(<< $0 1)",
+            "(* $0 2)" => "(+ $0 $0)",
+        );
+
+        let mut sched = FairClassScheduler::new(rules, NonZeroUsize::MIN);
+        for node_id in &classes[..2] {
+            sched.apply_next(&mut egraph, &TopDownMatcher);
+            let start = serde_json::from_value(Scheduler::<()>::save_state(&sched)).unwrap();
+            assert_eq!(
+                egraph.canonical_class(start),
+                egraph.containing_class(*node_id)
+            );
+        }
+    }
+}
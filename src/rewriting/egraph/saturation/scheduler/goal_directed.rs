@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use crate::compact::SinglyCompact;
+use crate::language::expression::Expression;
+use crate::language::symbol::Symbol;
+use crate::rewriting::egraph::EGraph;
+use crate::rewriting::egraph::class::local_cost::LocalCost;
+use crate::rewriting::egraph::extraction::{ExtractionResult, SimpleExtractor, children_cost_sum};
+use crate::rewriting::egraph::matching::{EGraphMatch, Matcher};
+use crate::rewriting::egraph::saturation::{SaturationReport, filter::ApplicationFilter};
+use crate::rewriting::egraph::{ClassId, DynEGraph};
+use crate::rewriting::heuristic::Heuristic;
+use crate::rewriting::rule::Rule;
+
+use super::Scheduler;
+use super::cost_directed::rule_cost;
+use super::ranked::{Candidate, MatchRanker, RankedScheduler};
+
+/// Ranker of a [`GoalDirectedScheduler`], ordering matches by the estimates
+/// of a [`Heuristic`] of their results.
+///
+/// The right-hand side of every match is instantiated with the cheapest expressions
+/// of the matched classes according to the `LocalCost` analysis and estimated by the heuristic.
+/// Ties are broken by `rule_cost` and then by the order of rules.
+pub struct HeuristicRanker<LC: LocalCost> {
+    heuristic: Rc<dyn Heuristic>,
+    _phantom: PhantomData<LC>,
+}
+
+impl<LC: LocalCost> HeuristicRanker<LC> {
+    /// Creates a ranker preferring matches whose results `heuristic` estimates
+    /// to be closest to its goal.
+    pub fn new(heuristic: Rc<dyn Heuristic>) -> Self {
+        Self {
+            heuristic,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn estimate(
+        &self,
+        rule: &Rule,
+        matching: &EGraphMatch,
+        egraph: &EGraph<LC>,
+        representatives: &HashMap<ClassId, ExtractionResult<LC>>,
+    ) -> SinglyCompact<u32> {
+        match instantiate(rule.to(), matching, egraph, representatives) {
+            Some(expression) => self.heuristic.lower_bound_dist(&expression),
+            None => SinglyCompact::Infinite,
+        }
+    }
+}
+
+/// Best-first scheduler that applies the match whose result is estimated by a [`Heuristic`]
+/// to be closest to its goal.
+///
+/// It is a [`RankedScheduler`] ordering matches with a [`HeuristicRanker`],
+/// so every step applies only the first match that changes the e-graph.
+pub struct GoalDirectedScheduler<LC: LocalCost>(RankedScheduler<HeuristicRanker<LC>>);
+
+impl<LC: LocalCost> GoalDirectedScheduler<LC> {
+    /// Creates a scheduler applying `rules` best-first according to `heuristic`.
+    pub fn new(rules: Vec<Rule>, heuristic: Rc<dyn Heuristic>) -> Self {
+        Self(RankedScheduler::new(rules, HeuristicRanker::new(heuristic)))
+    }
+}
+
+impl<LC: LocalCost> Scheduler<LC> for GoalDirectedScheduler<LC> {
+    fn apply_next(&mut self, egraph: &mut EGraph<LC>, matcher: &dyn Matcher) -> usize {
+        self.0.apply_next(egraph, matcher)
+    }
+
+    fn apply_next_reported(
+        &mut self,
+        egraph: &mut EGraph<LC>,
+        matcher: &dyn Matcher,
+        filter: &dyn ApplicationFilter<LC>,
+        report: &mut SaturationReport,
+    ) -> usize {
+        self.0.apply_next_reported(egraph, matcher, filter, report)
+    }
+}
+
+/// Substitutes the representatives of the matched classes for the variables of `pattern`.
+fn instantiate<LC: LocalCost>(
+    pattern: &Expression,
+    matching: &EGraphMatch,
+    egraph: &EGraph<LC>,
+    representatives: &HashMap<ClassId, ExtractionResult<LC>>,
+) -> Option<Expression> {
+    Some(match pattern {
+        Expression::Literal(literal) => Expression::Literal(literal.clone()),
+        Expression::Variable(variable_id) => {
+            let class_id = egraph.canonical_class(matching.class_variable(*variable_id));
+            representatives.get(&class_id)?.winner().to_expression()
+        }
+        Expression::Symbol(symbol) => Expression::Symbol(Symbol {
+            id: symbol.id,
+            children: symbol
+                .children
+                .iter()
+                .map(|child| instantiate(child, matching, egraph, representatives))
+                .collect::<Option<_>>()?,
+        }),
+    })
+}
+
+impl<LC: LocalCost> MatchRanker<LC> for HeuristicRanker<LC> {
+    fn rank(
+        &mut self,
+        egraph: &EGraph<LC>,
+        rules: &[Rule],
+        candidates: &[Candidate],
+    ) -> Vec<usize> {
+        let table = egraph.costs();
+        let extractor = SimpleExtractor::<LC, _, _>::new(
+            |literal| LC::literal_cost(literal, table),
+            |symbol, costs| {
+                Some(LC::symbol_cost(symbol.id, table) + children_cost_sum(symbol, costs)?)
+            },
+        );
+        let matched_classes = candidates.iter().flat_map(|candidate| {
+            rules[candidate.rule_index]
+                .to()
+                .find_all_variables()
+                .into_keys()
+                .map(|variable_id| candidate.matching.class_variable(variable_id))
+                .collect::<Vec<_>>()
+        });
+        let representatives = extractor.extract_classes(egraph, matched_classes);
+
+        let mut estimated: Vec<_> = candidates
+            .iter()
+            .enumerate()
+            .map(|(position, candidate)| {
+                let rule = &rules[candidate.rule_index];
+                let estimate = self.estimate(rule, &candidate.matching, egraph, &representatives);
+                (
+                    (estimate, rule_cost::<LC>(rule, table), candidate.rule_index),
+                    position,
+                )
+            })
+            .collect();
+        estimated.sort_by(|(a, _), (b, _)| a.cmp(b));
+        estimated
+            .into_iter()
+            .map(|(_, position)| position)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::compact::SinglyCompact;
+    use crate::language::Language;
+    use crate::language::expression::Expression;
+    use crate::macros::rules;
+    use crate::rewriting::egraph::EGraph;
+    use crate::rewriting::egraph::class::simple_math_local_cost::SimpleMathLocalCost;
+    use crate::rewriting::egraph::matching::top_down::TopDownMatcher;
+    use crate::rewriting::egraph::saturation::SaturationReport;
+    use crate::rewriting::egraph::saturation::TermBounds;
+    use crate::rewriting::egraph::saturation::scheduler::Scheduler;
+    use crate::rewriting::heuristic::Heuristic;
+
+    use super::GoalDirectedScheduler;
+
+    /// Distance 0 from the goal itself and 1 from anything else
+    struct GoalHeuristic(Expression);
+
+    impl Heuristic for GoalHeuristic {
+        fn lower_bound_dist(&self, expression: &Expression) -> SinglyCompact<u32> {
+            SinglyCompact::Finite(u32::from(*expression != self.0))
+        }
+    }
+
+    #[test]
+    fn prefers_matches_reaching_goal() {
+        let lang = Language::simple_math();
+        let mut egraph =
+            EGraph::<SimpleMathLocalCost>::from_expression(lang.parse_no_vars("(* 3 2)").unwrap());
+        // The shift is cheaper, but commuting reaches the goal
+        let rules = rules!(lang;
+            "(* $0 2)" => "(<< $0 1)",
+            "(* $0 $1)" => "(* $1 $0)",
+        );
+        let goal = lang.parse("(* 2 3)").unwrap();
+        let mut sched =
+            GoalDirectedScheduler::<SimpleMathLocalCost>::new(rules, Rc::new(GoalHeuristic(goal)));
+
+        let mut report = SaturationReport::default();
+        let applied = sched.apply_next_reported(
+            &mut egraph,
+            &TopDownMatcher,
+            &TermBounds::default(),
+            &mut report,
+        );
+        assert_eq!(applied, 1);
+        assert_eq!(report.rules[0].applications, 0);
+        assert_eq!(report.rules[1].applications, 1);
+        assert_eq!(report.rules[1].matches, 1);
+
+        assert_eq!(sched.apply_next(&mut egraph, &TopDownMatcher), 1);
+        assert_eq!(sched.apply_next(&mut egraph, &TopDownMatcher), 0);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::rewriting::egraph::matching::Matcher;
+use crate::rewriting::egraph::saturation::{SaturationReport, filter::ApplicationFilter};
 use crate::rewriting::egraph::{Analysis, EGraph};
 
 /// The `Scheduler` trait defines a strategy for choosing which rule to try next
@@ -6,10 +7,45 @@ use crate::rewriting::egraph::{Analysis, EGraph};
 /// applications performed in this step (0 means no rule applied, i.e., saturated).
 pub trait Scheduler<A: Analysis> {
     fn apply_next(&mut self, egraph: &mut EGraph<A>, matcher: &dyn Matcher) -> usize;
+
+    /// Same as [`Scheduler::apply_next`], but skips applications rejected by `filter`
+    /// and records statistics of the tried rules in `report`.
+    /// Schedulers which do not override it ignore the filter and record nothing.
+    fn apply_next_reported(
+        &mut self,
+        egraph: &mut EGraph<A>,
+        matcher: &dyn Matcher,
+        filter: &dyn ApplicationFilter<A>,
+        report: &mut SaturationReport,
+    ) -> usize {
+        let _ = (filter, report);
+        self.apply_next(egraph, matcher)
+    }
+
+    /// Returns the state of the scheduler which should survive restarting a saturation
+    /// from a checkpoint, see [`CheckpointingSaturator`](super::CheckpointingSaturator).
+    /// Schedulers which do not override it are stateless and return `null`.
+    fn save_state(&self) -> serde_json::Value {
+        serde_json::Value::Null
+    }
+
+    /// Restores a state returned by [`Scheduler::save_state`] of a scheduler with the same rules.
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<(), serde_json::Error> {
+        let _ = state;
+        Ok(())
+    }
 }
 
+pub mod annealing;
 pub mod cost_directed;
+pub mod fair_class;
+pub mod goal_directed;
+pub mod ranked;
 pub mod round_robin;
 
+pub use annealing::{AnnealingScheduler, TemperatureSchedule};
 pub use cost_directed::CostDirectedScheduler;
+pub use fair_class::FairClassScheduler;
+pub use goal_directed::{GoalDirectedScheduler, HeuristicRanker};
+pub use ranked::{Candidate, MatchRanker, RankedScheduler};
 pub use round_robin::RoundRobinScheduler;
@@ -0,0 +1,132 @@
+use crate::rewriting::egraph::matching::{EGraphMatch, Matcher};
+use crate::rewriting::egraph::saturation::{SaturationReport, filter::ApplicationFilter};
+use crate::rewriting::egraph::{Analysis, EGraph};
+use crate::rewriting::rule::Rule;
+
+use super::Scheduler;
+
+/// A match of the left-hand side of a rule which a [`RankedScheduler`] can apply.
+#[derive(Clone, Debug)]
+pub struct Candidate {
+    /// Index of the matched rule in the rules of the scheduler
+    pub rule_index: usize,
+    /// The match of the left-hand side of the rule
+    pub matching: EGraphMatch,
+}
+
+/// Strategy of a [`RankedScheduler`] choosing which of the candidate matches to apply.
+pub trait MatchRanker<A: Analysis> {
+    /// Returns the positions in `candidates` of the matches to try, best first.
+    /// Matches which are left out are not applied in this step.
+    fn rank(&mut self, egraph: &EGraph<A>, rules: &[Rule], candidates: &[Candidate]) -> Vec<usize>;
+}
+
+impl<A, F> MatchRanker<A> for F
+where
+    A: Analysis,
+    F: FnMut(&EGraph<A>, &[Rule], &[Candidate]) -> Vec<usize>,
+{
+    fn rank(&mut self, egraph: &EGraph<A>, rules: &[Rule], candidates: &[Candidate]) -> Vec<usize> {
+        self(egraph, rules, candidates)
+    }
+}
+
+/// Scheduler ranking individual matches instead of rules.
+///
+/// In every step it finds the matches of all rules, lets its [`MatchRanker`] order them
+/// and applies only the first one that changes the e-graph.
+pub struct RankedScheduler<R> {
+    rules: Vec<Rule>,
+    ranker: R,
+}
+
+impl<R> RankedScheduler<R> {
+    /// Creates a scheduler applying matches of `rules` in the order given by `ranker`.
+    pub fn new(rules: Vec<Rule>, ranker: R) -> Self {
+        Self { rules, ranker }
+    }
+
+    /// Returns the strategy ordering the matches.
+    pub fn ranker(&self) -> &R {
+        &self.ranker
+    }
+}
+
+impl<A: Analysis, R: MatchRanker<A>> Scheduler<A> for RankedScheduler<R> {
+    fn apply_next(&mut self, egraph: &mut EGraph<A>, matcher: &dyn Matcher) -> usize {
+        self.apply_next_reported(egraph, matcher, &(), &mut SaturationReport::default())
+    }
+
+    fn apply_next_reported(
+        &mut self,
+        egraph: &mut EGraph<A>,
+        matcher: &dyn Matcher,
+        filter: &dyn ApplicationFilter<A>,
+        report: &mut SaturationReport,
+    ) -> usize {
+        let mut candidates = Vec::new();
+        for (rule_index, rule) in self.rules.iter().enumerate() {
+            for matching in rule.find_matches(egraph, matcher, report.rule_mut(rule_index)) {
+                candidates.push(Candidate {
+                    rule_index,
+                    matching,
+                });
+            }
+        }
+
+        for position in self.ranker.rank(egraph, &self.rules, &candidates) {
+            let Candidate {
+                rule_index,
+                matching,
+            } = &candidates[position];
+            if self.rules[*rule_index].apply_match(
+                egraph,
+                matching,
+                filter,
+                report.rule_mut(*rule_index),
+            ) {
+                return 1;
+            }
+        }
+
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::language::Language;
+    use crate::macros::rules;
+    use crate::rewriting::egraph::matching::top_down::TopDownMatcher;
+    use crate::rewriting::egraph::saturation::scheduler::Scheduler;
+    use crate::rewriting::egraph::{DynEGraph, EGraph};
+    use crate::rewriting::rule::Rule;
+
+    use super::{Candidate, RankedScheduler};
+
+    #[test]
+    fn applies_best_ranked_match() {
+        let lang = Language::simple_math();
+        let mut egraph = EGraph::<()>::default();
+        let small = egraph.add_expression(lang.parse_no_vars("(* 3 2)").unwrap());
+        let large = egraph.add_expression(lang.parse_no_vars("(* 9 2)").unwrap());
+        let rules = rules!(lang;
+            "(* $0 2)" => "(<< $0 1)",
+        );
+        // Prefers matches in classes with larger IDs
+        let ranker = |_: &EGraph<()>, _: &[Rule], candidates: &[Candidate]| {
+            let mut positions: Vec<_> = (0..candidates.len()).collect();
+            positions
+                .sort_by_key(|&position| std::cmp::Reverse(candidates[position].matching.root()));
+            positions
+        };
+        let mut sched = RankedScheduler::new(rules, ranker);
+
+        assert_eq!(sched.apply_next(&mut egraph, &TopDownMatcher), 1);
+        assert_eq!(egraph.nodes(large).len(), 2);
+        assert_eq!(egraph.nodes(small).len(), 1);
+        assert_eq!(sched.apply_next(&mut egraph, &TopDownMatcher), 1);
+        assert_eq!(egraph.nodes(small).len(), 2);
+        assert_eq!(sched.apply_next(&mut egraph, &TopDownMatcher), 0);
+    }
+}
@@ -1,20 +1,21 @@
+use crate::rewriting::dynamic_rule::RuleLike;
 use crate::rewriting::egraph::matching::Matcher;
+use crate::rewriting::egraph::saturation::{SaturationReport, filter::ApplicationFilter};
 use crate::rewriting::egraph::{Analysis, EGraph};
-use crate::rewriting::rule::Rule;
 
 use super::Scheduler;
 
 /// Round-robin scheduler that cycles through rules and applies the first
 /// applicable rule. It advances the starting index after each successful step.
 pub struct RoundRobinScheduler {
-    rules: Vec<Rule>,
+    rules: Vec<RuleLike>,
     next_index: usize,
 }
 
 impl RoundRobinScheduler {
-    pub fn new(rules: Vec<Rule>) -> Self {
+    pub fn new(rules: Vec<impl Into<RuleLike>>) -> Self {
         Self {
-            rules,
+            rules: rules.into_iter().map(Into::into).collect(),
             next_index: 0,
         }
     }
@@ -22,11 +23,22 @@ impl RoundRobinScheduler {
 
 impl<A: Analysis> Scheduler<A> for RoundRobinScheduler {
     fn apply_next(&mut self, egraph: &mut EGraph<A>, matcher: &dyn Matcher) -> usize {
+        self.apply_next_reported(egraph, matcher, &(), &mut SaturationReport::default())
+    }
+
+    fn apply_next_reported(
+        &mut self,
+        egraph: &mut EGraph<A>,
+        matcher: &dyn Matcher,
+        filter: &dyn ApplicationFilter<A>,
+        report: &mut SaturationReport,
+    ) -> usize {
         let n = self.rules.len();
 
         for offset in 0..n {
             let idx = (self.next_index + offset) % n;
-            let applied = self.rules[idx].apply(egraph, matcher);
+            let applied =
+                self.rules[idx].apply_filtered(egraph, matcher, filter, report.rule_mut(idx));
             if applied > 0 {
                 self.next_index = (idx + 1) % n;
                 return applied;
@@ -35,6 +47,15 @@ impl<A: Analysis> Scheduler<A> for RoundRobinScheduler {
 
         0
     }
+
+    fn save_state(&self) -> serde_json::Value {
+        self.next_index.into()
+    }
+
+    fn restore_state(&mut self, state: serde_json::Value) -> Result<(), serde_json::Error> {
+        self.next_index = serde_json::from_value(state)?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
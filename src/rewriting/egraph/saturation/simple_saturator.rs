@@ -1,47 +1,79 @@
-use crate::rewriting::rule::Rule;
+use crate::rewriting::{dynamic_rule::RuleLike, rule::Rule};
 
-use super::{Analysis, EGraph, SaturationConfig, SaturationStopReason, Saturator};
+use super::{
+    Analysis, ApplicationFilter, EGraph, SaturationConfig, SaturationReport, Saturator,
+    UnsupportedDynamicRules,
+};
 use crate::rewriting::egraph::matching::Matcher;
 use crate::rewriting::egraph::saturation::scheduled_saturator::ScheduledSaturator;
 use crate::rewriting::egraph::saturation::scheduler::RoundRobinScheduler;
 
-pub struct SimpleSaturator {
+pub struct SimpleSaturator<F = ()> {
     matcher: Box<dyn Matcher>,
+    filter: F,
 }
 
 impl SimpleSaturator {
     pub fn new(matcher: Box<dyn Matcher>) -> Self {
-        Self { matcher }
+        Self {
+            matcher,
+            filter: (),
+        }
     }
 }
 
-impl<A: Analysis> Saturator<A> for SimpleSaturator {
+impl<F> SimpleSaturator<F> {
+    /// Makes the saturator skip rule applications rejected by `filter`.
+    pub fn with_filter<G>(self, filter: G) -> SimpleSaturator<G> {
+        SimpleSaturator {
+            matcher: self.matcher,
+            filter,
+        }
+    }
+}
+
+impl<A: Analysis, F: ApplicationFilter<A>> Saturator<A> for SimpleSaturator<F> {
     fn saturate(
         &self,
         egraph: &mut EGraph<A>,
         rules: &[Rule],
         config: &SaturationConfig,
-    ) -> SaturationStopReason {
+    ) -> SaturationReport {
+        let scheduler = Box::new(RoundRobinScheduler::new(rules.to_vec()));
+        let mut saturator = ScheduledSaturator::new(scheduler);
+        saturator.run_filtered(egraph, config, &*self.matcher, &self.filter)
+    }
+
+    fn saturate_dynamic(
+        &self,
+        egraph: &mut EGraph<A>,
+        rules: &[RuleLike],
+        config: &SaturationConfig,
+    ) -> Result<SaturationReport, UnsupportedDynamicRules> {
         let scheduler = Box::new(RoundRobinScheduler::new(rules.to_vec()));
         let mut saturator = ScheduledSaturator::new(scheduler);
-        saturator.run(egraph, config, &*self.matcher)
+        Ok(saturator.run_filtered(egraph, config, &*self.matcher, &self.filter))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::time::Duration;
+    use std::{cell::RefCell, rc::Rc, time::Duration};
 
     use crate::{
-        language::Language,
+        language::{Language, expression::Literal},
         rewriting::{
-            egraph::{DynEGraph, EGraph, matching::bottom_up::BottomUpMatcher},
+            egraph::{
+                DynEGraph, EGraph,
+                class::term_size::TermSize,
+                matching::{EGraphMatch, bottom_up::BottomUpMatcher},
+            },
             rule::Rule,
         },
     };
 
     use super::SimpleSaturator;
-    use super::{SaturationConfig, SaturationStopReason, Saturator};
+    use crate::rewriting::egraph::saturation::{SaturationConfig, SaturationStopReason, Saturator};
 
     fn default_rules(lang: &Language) -> Vec<Rule> {
         vec![
@@ -60,7 +92,10 @@ mod tests {
         config: &SaturationConfig,
     ) -> SaturationStopReason {
         let saturator = SimpleSaturator::new(Box::new(BottomUpMatcher));
-        saturator.saturate(egraph, rules, config)
+        saturator
+            .saturate(egraph, rules, config)
+            .stop_reason
+            .unwrap()
     }
 
     #[test]
@@ -77,8 +112,20 @@ mod tests {
             EGraph::<()>::from_expression(lang.parse_no_vars("(/ (* (sin 5) 2) 2)").unwrap());
 
         let saturator = SimpleSaturator::new(Box::new(BottomUpMatcher));
-        let reason = saturator.saturate(&mut egraph, &rules, &SaturationConfig::default());
-        assert_eq!(reason, SaturationStopReason::Saturated);
+        let report = saturator.saturate(&mut egraph, &rules, &SaturationConfig::default());
+        assert_eq!(report.stop_reason, Some(SaturationStopReason::Saturated));
+        assert_eq!(report.node_count, 9);
+        assert_eq!(report.union_find.finds > 0, crate::metrics::ENABLED);
+        assert_eq!(report.rules.len(), 4);
+        assert_eq!(
+            report
+                .rules
+                .iter()
+                .map(|rule| rule.applications)
+                .sum::<usize>(),
+            report.applications
+        );
+        assert!(report.rules[3].unions > 0);
 
         assert_eq!(egraph.class_count(), 5);
         assert_eq!(egraph.actual_node_count(), 9);
@@ -151,4 +198,171 @@ mod tests {
         );
         assert_eq!(reason, SaturationStopReason::MaxClasses);
     }
+
+    #[test]
+    fn stops_on_max_iterations() {
+        let lang = Language::simple_math();
+        let rules = default_rules(&lang);
+        let mut egraph = new_egraph(&lang, "(* (* 3 2) 1)");
+
+        let saturator = SimpleSaturator::new(Box::new(BottomUpMatcher));
+        let config = SaturationConfig {
+            max_iterations: Some(1),
+            ..Default::default()
+        };
+        let report = saturator.saturate(&mut egraph, &rules, &config);
+        assert_eq!(
+            report.stop_reason,
+            Some(SaturationStopReason::MaxIterations)
+        );
+        assert_eq!(report.iterations, 1);
+    }
+
+    #[test]
+    fn stops_when_condition_holds() {
+        let lang = Language::simple_math();
+        let rules = vec![Rule::from_strings("(* $0 1)", "$0", &lang)];
+        let mut egraph = new_egraph(&lang, "(+ (* 3 1) (* 4 1))");
+        let class_count = egraph.class_count();
+
+        let saturator = SimpleSaturator::new(Box::new(BottomUpMatcher));
+        let config = SaturationConfig::default()
+            .with_stop_when(move |egraph| egraph.class_count() < class_count);
+        let report = saturator.saturate(&mut egraph, &rules, &config);
+        assert_eq!(
+            report.stop_reason,
+            Some(SaturationStopReason::StopConditionHolds)
+        );
+        assert_eq!(report.applications, 1);
+        assert_eq!(report.iterations, 1);
+    }
+
+    #[test]
+    fn limits_applications_within_iteration() {
+        let lang = Language::simple_math();
+        let rules = vec![Rule::from_strings("(* $0 2)", "(<< $0 1)", &lang)];
+        let saturator = SimpleSaturator::new(Box::new(BottomUpMatcher));
+
+        let mut egraph = new_egraph(&lang, "(+ (* 3 2) (* 4 2))");
+        let config = SaturationConfig {
+            max_applications: Some(1),
+            ..Default::default()
+        };
+        let report = saturator.saturate(&mut egraph, &rules, &config);
+        assert_eq!(
+            report.stop_reason,
+            Some(SaturationStopReason::MaxApplications)
+        );
+        assert_eq!(report.applications, 1);
+
+        let mut egraph = new_egraph(&lang, "(+ (* 3 2) (* 4 2))");
+        let config = SaturationConfig {
+            max_applications_per_iteration: Some(1),
+            ..Default::default()
+        };
+        let report = saturator.saturate(&mut egraph, &rules, &config);
+        assert_eq!(report.stop_reason, Some(SaturationStopReason::Saturated));
+        assert_eq!(report.applications, 2);
+        assert_eq!(report.iterations, 3);
+    }
+
+    #[test]
+    fn reports_progress() {
+        let lang = Language::simple_math();
+        let rules = default_rules(&lang);
+        let mut egraph = new_egraph(&lang, "(* (* 3 2) 1)");
+
+        let reports = Rc::new(RefCell::new(Vec::new()));
+        let config = SaturationConfig::default().with_progress_callback({
+            let reports = reports.clone();
+            move |report| reports.borrow_mut().push(report.clone())
+        });
+        let reason = run(&mut egraph, &rules, &config);
+        assert_eq!(reason, SaturationStopReason::Saturated);
+
+        let reports = reports.borrow();
+        let iterations: Vec<_> = reports.iter().map(|report| report.iterations).collect();
+        assert_eq!(iterations, (1..=reports.len()).collect::<Vec<_>>());
+
+        let last = reports.last().unwrap();
+        assert_eq!(last.node_count, egraph.actual_node_count());
+        assert_eq!(last.class_count, egraph.class_count());
+        assert_eq!(last.rules.len(), 2);
+        assert_eq!(last.rules[0].applications, 1);
+        assert_eq!(last.rules[1].applications, 1);
+        assert_eq!(
+            last.applications,
+            last.rules
+                .iter()
+                .map(|rule| rule.applications)
+                .sum::<usize>()
+        );
+    }
+
+    #[test]
+    fn respects_application_filter() {
+        let lang = Language::simple_math();
+        let rules = vec![Rule::from_strings("(/ $0 $0)", "1", &lang)];
+        let mut egraph = new_egraph(&lang, "(+ (/ 0 0) (/ 2 2))");
+        let zero = egraph.find_literal(Literal::Int(0)).unwrap();
+        let nonzero = |egraph: &EGraph<()>, matching: &EGraphMatch, _rule: &Rule| {
+            !egraph.class_contains_literal(matching.class_variable(0), &Literal::Int(0))
+        };
+
+        let saturator = SimpleSaturator::new(Box::new(BottomUpMatcher)).with_filter(nonzero);
+        let report = saturator.saturate(&mut egraph, &rules, &SaturationConfig::default());
+        assert_eq!(report.stop_reason, Some(SaturationStopReason::Saturated));
+        assert_eq!(report.applications, 1);
+
+        let one = egraph.find_literal(Literal::Int(1)).unwrap();
+        let halves = egraph.find_symbols(lang.get_id("/"));
+        let classes = halves
+            .iter()
+            .map(|&node_id| egraph.containing_class(node_id))
+            .collect::<Vec<_>>();
+        assert!(classes.contains(&one));
+        assert!(classes.iter().any(|&class_id| class_id != one));
+        assert_ne!(egraph.canonical_class(zero), one);
+    }
+
+    #[test]
+    fn respects_term_bounds() {
+        let lang = Language::simple_math();
+        let rules = vec![Rule::from_strings("(* $0 2)", "(* (* $0 1) 2)", &lang)];
+        let saturate = |config: &SaturationConfig| {
+            let mut egraph =
+                EGraph::<TermSize>::from_expression(lang.parse_no_vars("(* 3 2)").unwrap());
+            let report = SimpleSaturator::new(Box::new(BottomUpMatcher)).saturate(
+                &mut egraph,
+                &rules,
+                config,
+            );
+            (egraph, report.stop_reason.unwrap())
+        };
+
+        let (_, reason) = saturate(&SaturationConfig {
+            max_nodes: Some(50),
+            ..Default::default()
+        });
+        assert_eq!(reason, SaturationStopReason::MaxNodes);
+
+        let (egraph, reason) = saturate(&SaturationConfig {
+            max_nodes: Some(50),
+            max_term_depth: Some(3),
+            ..Default::default()
+        });
+        assert_eq!(reason, SaturationStopReason::Saturated);
+        assert!(
+            egraph
+                .iter_classes()
+                .all(|(_, class)| class.analysis().depth <= 3)
+        );
+
+        let (egraph, reason) = saturate(&SaturationConfig {
+            max_term_size: Some(5),
+            ..Default::default()
+        });
+        assert_eq!(reason, SaturationStopReason::Saturated);
+        assert_eq!(egraph.actual_node_count(), 6);
+    }
 }
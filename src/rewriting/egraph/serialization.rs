@@ -0,0 +1,226 @@
+//! Serialization of e-graphs.
+//!
+//! An e-graph is stored as its nodes, union-find and classes together with their analysis data,
+//! so that a saturation can be checkpointed and later inspected or resumed in another process.
+//! The hashcons is not stored, as it is fully determined by the rest of the e-graph
+//! and is rebuilt on load.
+
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::{
+    language::{sorts::Sorts, symbol::SymbolId},
+    union_find::UnionFind,
+    utils::json,
+};
+
+use super::{
+    Analysis, Class, ClassId, EGraph, FixedHashMap, FixedHashSet, Node, NodeId, class::DynClass,
+};
+
+/// Serialized form of an [`EGraph`], checked for consistency before conversion.
+#[derive(Deserialize)]
+pub(super) struct EGraphData<A: Analysis> {
+    union_find: UnionFind,
+    nodes: FixedHashMap<NodeId, Node>,
+    classes: FixedHashMap<ClassId, Class<A>>,
+    #[serde(default)]
+    sorts: Option<Sorts>,
+    #[serde(default)]
+    frozen: FixedHashSet<ClassId>,
+    #[serde(default)]
+    flattened: BTreeSet<SymbolId>,
+    #[serde(default)]
+    tags: FixedHashMap<ClassId, BTreeSet<String>>,
+}
+
+impl<A: Analysis> TryFrom<EGraphData<A>> for EGraph<A> {
+    type Error = String;
+
+    fn try_from(data: EGraphData<A>) -> Result<Self, Self::Error> {
+        if !data.union_find.is_forest() {
+            return Err(String::from(
+                "Parents of the union-find are out of range or form a cycle",
+            ));
+        }
+
+        let size = data.union_find.size();
+        let check_id = |id: usize| {
+            if id < size && data.union_find.parent(id) < size {
+                Ok(())
+            } else {
+                Err(format!("ID {id} is out of range of the union-find"))
+            }
+        };
+
+        for (&node_id, node) in &data.nodes {
+            check_id(node_id)?;
+            node.iter_children()
+                .try_for_each(|&child| check_id(child))?;
+        }
+
+        for (&class_id, class) in &data.classes {
+            check_id(class_id)?;
+            if data.union_find.find_no_compress(class_id) != class_id {
+                return Err(format!("Class {class_id} is not canonical"));
+            }
+            for node_id in class.nodes_ids().iter().chain(class.parents_ids()) {
+                if !data.nodes.contains_key(node_id) {
+                    return Err(format!("Class {class_id} refers to missing node {node_id}"));
+                }
+            }
+        }
+
+        for &class_id in &data.frozen {
+            if !data.classes.contains_key(&class_id) {
+                return Err(format!("Frozen class {class_id} does not exist"));
+            }
+        }
+
+        for &class_id in data.tags.keys() {
+            if !data.classes.contains_key(&class_id) {
+                return Err(format!("Tagged class {class_id} does not exist"));
+            }
+        }
+
+        let mut egraph = Self {
+            union_find: data.union_find,
+            nodes: data.nodes,
+            classes: data.classes,
+            node_hashcons: FixedHashMap::default(),
+            sorts: data.sorts,
+            frozen: data.frozen,
+            flattened: data.flattened,
+            tags: data.tags,
+            history: Default::default(),
+            metrics: Default::default(),
+            costs: None,
+        };
+        egraph.rebuild_hashcons();
+        Ok(egraph)
+    }
+}
+
+impl<A: Analysis + Serialize> EGraph<A> {
+    /// Saves the e-graph to a JSON file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path where the JSON file should be saved
+    pub fn save_json<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        json::save_json(self, path)
+    }
+}
+
+impl<A: Analysis + DeserializeOwned> EGraph<A> {
+    /// Loads an e-graph saved with [`EGraph::save_json`].
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the JSON file
+    pub fn load_json<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        json::load_json(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        language::{Language, expression::Literal},
+        rewriting::{
+            egraph::{
+                DynEGraph, EGraph, Node,
+                class::{DynClass, literal_count::LiteralCountAnalysis},
+                matching::bottom_up::BottomUpMatcher,
+                saturation::{SaturationConfig, SaturationStopReason, Saturator, SimpleSaturator},
+            },
+            rule::Rule,
+        },
+    };
+
+    fn rules(lang: &Language) -> Vec<Rule> {
+        vec![
+            Rule::from_strings("(* $0 2)", "(<< $0 1)", lang),
+            Rule::from_strings("(* $0 1)", "$0", lang),
+            Rule::from_strings("(/ (* $0 $1) $2)", "(* $0 (/ $1 $2))", lang),
+            Rule::from_strings("(/ $0 $0)", "1", lang),
+        ]
+    }
+
+    #[test]
+    fn round_trip() {
+        let lang = Language::simple_math();
+        let mut egraph = EGraph::<LiteralCountAnalysis>::from_expression(
+            lang.parse_no_vars("(/ (* (sin 5) 2) 2)").unwrap(),
+        );
+        let saturator = SimpleSaturator::new(Box::new(BottomUpMatcher));
+        saturator.saturate(&mut egraph, &rules(&lang), &SaturationConfig::default());
+        let five = egraph.find_literal(Literal::Int(5)).unwrap();
+        egraph.freeze_class(five);
+        egraph.tag_class(five, "origin: input");
+
+        let json = serde_json::to_string(&egraph).unwrap();
+        let loaded: EGraph<LiteralCountAnalysis> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(loaded.class_count(), egraph.class_count());
+        assert_eq!(loaded.actual_node_count(), egraph.actual_node_count());
+        assert!(loaded.union_find == egraph.union_find);
+        assert_eq!(loaded.frozen, egraph.frozen);
+        assert_eq!(loaded.tags, egraph.tags);
+        for (class_id, class) in &egraph.classes {
+            let loaded_class = &loaded.classes[class_id];
+            assert_eq!(loaded_class.nodes_ids(), class.nodes_ids());
+            assert_eq!(loaded_class.analysis(), class.analysis());
+        }
+
+        let two = Node::Literal(Literal::Int(2));
+        assert!(loaded.node_id(&two).is_some());
+        assert_eq!(loaded.node_id(&two), egraph.node_id(&two));
+    }
+
+    #[test]
+    fn resume_saturation() {
+        let lang = Language::simple_math();
+        let rules = rules(&lang);
+        let mut egraph =
+            EGraph::<()>::from_expression(lang.parse_no_vars("(/ (* (sin 5) 2) 2)").unwrap());
+        let saturator = SimpleSaturator::new(Box::new(BottomUpMatcher));
+        let report = saturator.saturate(
+            &mut egraph,
+            &rules,
+            &SaturationConfig {
+                max_applications: Some(1),
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            report.stop_reason,
+            Some(SaturationStopReason::MaxApplications)
+        );
+
+        let path = std::env::temp_dir().join(format!("egraph-{}.json", std::process::id()));
+        egraph.save_json(&path).unwrap();
+        let mut loaded = EGraph::<()>::load_json(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let report = saturator.saturate(&mut loaded, &rules, &SaturationConfig::default());
+        assert_eq!(report.stop_reason, Some(SaturationStopReason::Saturated));
+        assert_eq!(loaded.class_count(), 5);
+        assert_eq!(loaded.actual_node_count(), 9);
+    }
+
+    #[test]
+    fn rejects_inconsistent_data() {
+        let lang = Language::simple_math();
+        let egraph = EGraph::<()>::from_expression(lang.parse_no_vars("(sin 5)").unwrap());
+        let mut json = serde_json::to_value(&egraph).unwrap();
+        json["union_find"] = serde_json::json!([0]);
+        assert!(serde_json::from_value::<EGraph<()>>(json.clone()).is_err());
+
+        json["union_find"] = serde_json::json!([1, 0]);
+        assert!(serde_json::from_value::<EGraph<()>>(json).is_err());
+    }
+}
@@ -0,0 +1,264 @@
+//! Snapshots of e-graphs.
+//!
+//! Instead of cloning the whole e-graph, taking a snapshot starts an undo log. The previous
+//! values of nodes and classes are recorded the first time they change after the latest snapshot,
+//! together with changes of frozen classes, tags and unions in the union-find. Rolling back restores
+//! the recorded values in reverse order and rebuilds the hashcons.
+
+use std::collections::{BTreeSet, HashSet};
+
+use crate::union_find::UnionFindSnapshot;
+
+use super::{Analysis, Class, ClassId, EGraph, Node, NodeId};
+
+/// A state of an [`EGraph`] which it can be rolled back to, see [`EGraph::snapshot`].
+#[derive(Debug)]
+pub struct Snapshot {
+    serial: usize,
+    depth: usize,
+    log_len: usize,
+    union_find: UnionFindSnapshot,
+}
+
+/// A previous value of a part of an e-graph, `None` if it did not exist.
+#[derive(Clone)]
+enum Change<A: Analysis> {
+    Node(NodeId, Option<Node>),
+    Class(ClassId, Option<Class<A>>),
+    Frozen(ClassId, bool),
+    Tags(ClassId, Option<BTreeSet<String>>),
+}
+
+/// Undo log of an e-graph with open snapshots.
+#[derive(Clone, Default)]
+pub(super) struct History<A: Analysis> {
+    log: Vec<Change<A>>,
+    // Serial numbers of open snapshots and the lengths of the log when they were taken
+    snapshots: Vec<(usize, usize)>,
+    next_serial: usize,
+    // Nodes and classes whose values have been recorded since the latest snapshot
+    recorded_nodes: HashSet<NodeId>,
+    recorded_classes: HashSet<ClassId>,
+}
+
+impl<A: Analysis> EGraph<A> {
+    /// Takes a snapshot of the e-graph, which can later be restored with [`EGraph::rollback`].
+    ///
+    /// Changes are recorded until the snapshot is rolled back or kept with [`EGraph::commit`],
+    /// so that only the changed parts of the e-graph are copied. Snapshots can be nested,
+    /// and closing a snapshot closes all snapshots taken after it.
+    pub fn snapshot(&mut self) -> Snapshot {
+        let history = &mut self.history;
+        let serial = history.next_serial;
+        history.next_serial += 1;
+        history.snapshots.push((serial, history.log.len()));
+        history.recorded_nodes.clear();
+        history.recorded_classes.clear();
+
+        Snapshot {
+            serial,
+            depth: history.snapshots.len() - 1,
+            log_len: history.log.len(),
+            union_find: self.union_find.snapshot(),
+        }
+    }
+
+    /// Restores the state of the e-graph from when `snapshot` was taken.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `snapshot` has already been closed by rolling back or committing
+    /// an earlier snapshot.
+    pub fn rollback(&mut self, snapshot: Snapshot) {
+        self.close_snapshot(&snapshot);
+
+        for change in self.history.log.drain(snapshot.log_len..).rev() {
+            match change {
+                Change::Node(node_id, Some(node)) => {
+                    self.nodes.insert(node_id, node);
+                }
+                Change::Node(node_id, None) => {
+                    self.nodes.remove(&node_id);
+                }
+                Change::Class(class_id, Some(class)) => {
+                    self.classes.insert(class_id, class);
+                }
+                Change::Class(class_id, None) => {
+                    self.classes.remove(&class_id);
+                }
+                Change::Frozen(class_id, true) => {
+                    self.frozen.insert(class_id);
+                }
+                Change::Frozen(class_id, false) => {
+                    self.frozen.remove(&class_id);
+                }
+                Change::Tags(class_id, Some(tags)) => {
+                    self.tags.insert(class_id, tags);
+                }
+                Change::Tags(class_id, None) => {
+                    self.tags.remove(&class_id);
+                }
+            }
+        }
+        self.history.recorded_nodes.clear();
+        self.history.recorded_classes.clear();
+        self.union_find.rollback(snapshot.union_find);
+
+        self.forget_closed_history();
+        self.rebuild_hashcons();
+    }
+
+    /// Keeps all changes made since `snapshot` was taken and closes it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `snapshot` has already been closed by rolling back or committing
+    /// an earlier snapshot.
+    pub fn commit(&mut self, snapshot: Snapshot) {
+        self.close_snapshot(&snapshot);
+        self.forget_closed_history();
+    }
+
+    /// Returns the number of snapshots which have been neither rolled back nor committed.
+    pub fn open_snapshots(&self) -> usize {
+        self.history.snapshots.len()
+    }
+
+    fn close_snapshot(&mut self, snapshot: &Snapshot) {
+        let snapshots = &mut self.history.snapshots;
+        assert!(
+            snapshots.get(snapshot.depth).map(|&(serial, _)| serial) == Some(snapshot.serial),
+            "Snapshot has already been closed"
+        );
+        snapshots.truncate(snapshot.depth);
+    }
+
+    fn forget_closed_history(&mut self) {
+        if self.history.snapshots.is_empty() {
+            self.history.log.clear();
+            self.history.recorded_nodes.clear();
+            self.history.recorded_classes.clear();
+            self.union_find.forget_snapshots();
+        }
+    }
+
+    /// Records the value of the node with id `node_id` before it is changed.
+    pub(super) fn record_node(&mut self, node_id: NodeId) {
+        let history = &mut self.history;
+        if !history.snapshots.is_empty() && history.recorded_nodes.insert(node_id) {
+            let node = self.nodes.get(&node_id).cloned();
+            history.log.push(Change::Node(node_id, node));
+        }
+    }
+
+    /// Records the value of the class with canonical id `class_id` before it is changed.
+    pub(super) fn record_class(&mut self, class_id: ClassId) {
+        let history = &mut self.history;
+        if !history.snapshots.is_empty() && history.recorded_classes.insert(class_id) {
+            let class = self.classes.get(&class_id).cloned();
+            history.log.push(Change::Class(class_id, class));
+        }
+    }
+
+    /// Records whether the class with canonical id `class_id` is frozen before it changes.
+    pub(super) fn record_frozen(&mut self, class_id: ClassId) {
+        if !self.history.snapshots.is_empty() {
+            let frozen = self.frozen.contains(&class_id);
+            self.history.log.push(Change::Frozen(class_id, frozen));
+        }
+    }
+
+    /// Records the tags of the class with canonical id `class_id` before they change.
+    pub(super) fn record_tags(&mut self, class_id: ClassId) {
+        if !self.history.snapshots.is_empty() {
+            let tags = self.tags.get(&class_id).cloned();
+            self.history.log.push(Change::Tags(class_id, tags));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        language::{Language, expression::Literal, symbol::Symbol},
+        macros::rules,
+        rewriting::egraph::{
+            DynEGraph, EGraph,
+            matching::bottom_up::BottomUpMatcher,
+            saturation::{SaturationConfig, Saturator, SimpleSaturator},
+        },
+    };
+
+    #[test]
+    fn rollback_restores_egraph() {
+        let lang = Language::simple_math();
+        let mut egraph = EGraph::<()>::from_expression(lang.parse_no_vars("(+ 1 2)").unwrap());
+        let one = egraph.find_literal(Literal::Int(1)).unwrap();
+        let two = egraph.find_literal(Literal::Int(2)).unwrap();
+
+        let snapshot = egraph.snapshot();
+        let product = egraph.add_expression(lang.parse_no_vars("(* 2 1)").unwrap());
+        egraph.merge_classes(one, two);
+        egraph.freeze_class(one);
+        assert_eq!(egraph.class_count(), 3);
+
+        egraph.rollback(snapshot);
+        assert_eq!(egraph.open_snapshots(), 0);
+        assert_eq!(egraph.class_count(), 3);
+        assert_eq!(egraph.total_node_count(), 3);
+        assert_eq!(egraph.actual_node_count(), 3);
+        assert_ne!(egraph.canonical_class(one), egraph.canonical_class(two));
+        assert!(!egraph.is_frozen(one));
+        assert!(egraph.parents(one).len() == 1 && egraph.parents(two).len() == 1);
+
+        // Freed IDs are reused
+        let readded = egraph.add_expression(lang.parse_no_vars("(* 2 1)").unwrap());
+        assert_eq!(readded, product);
+    }
+
+    #[test]
+    fn nested_snapshots() {
+        let lang = Language::simple_math();
+        let mut egraph = EGraph::<()>::from_expression(lang.parse_no_vars("(+ 1 2)").unwrap());
+        let one = egraph.find_literal(Literal::Int(1)).unwrap();
+        let two = egraph.find_literal(Literal::Int(2)).unwrap();
+        let rules = rules!(lang;
+            "(+ $0 $1)" => "(+ $1 $0)",
+            "(+ $0 $1)" => "(* $0 $1)",
+        );
+        let saturator = SimpleSaturator::new(Box::new(BottomUpMatcher));
+
+        let outer = egraph.snapshot();
+        saturator.saturate(&mut egraph, &rules[..1], &SaturationConfig::default());
+        let commuted = egraph.actual_node_count();
+        let inner = egraph.snapshot();
+        saturator.saturate(&mut egraph, &rules, &SaturationConfig::default());
+        assert!(egraph.actual_node_count() > commuted);
+
+        egraph.rollback(inner);
+        assert_eq!(egraph.open_snapshots(), 1);
+        assert_eq!(egraph.actual_node_count(), commuted);
+        let inner = egraph.snapshot();
+        saturator.saturate(&mut egraph, &rules, &SaturationConfig::default());
+        egraph.commit(inner);
+        assert!(egraph.actual_node_count() > commuted);
+
+        egraph.rollback(outer);
+        assert_eq!(egraph.actual_node_count(), 3);
+        let product = Symbol {
+            id: lang.get_id("*"),
+            children: vec![one, two],
+        };
+        assert!(egraph.find_symbol(product).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Snapshot has already been closed")]
+    fn closed_snapshot_panics() {
+        let mut egraph = EGraph::<()>::default();
+        let outer = egraph.snapshot();
+        let inner = egraph.snapshot();
+        egraph.rollback(outer);
+        egraph.rollback(inner);
+    }
+}
@@ -0,0 +1,154 @@
+//! Conversion of e-graphs into explicit graphs of classes.
+//!
+//! [`EGraph::term_graph`] returns an [`EdgeDataGraph`] with a vertex for every class
+//! and an edge from a class to each class which is a child of one of its nodes,
+//! so that the algorithms of the [`graph`](crate::graph) module, e.g. strongly connected
+//! components and reachability, as well as its DOT and GraphML output, apply to e-graphs.
+//! It is meant for small e-graphs, as the explicit graph is built eagerly.
+
+use std::{collections::HashMap, fmt::Display};
+
+use itertools::Itertools;
+
+use crate::{
+    graph::{EdgeDataGraph, VertexId},
+    language::Language,
+};
+
+use super::{Analysis, ClassId, EGraph, Node, class::DynClass};
+
+/// Vertex of a [`TermGraph`] standing for a class.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClassVertex {
+    /// Canonical ID of the class
+    pub class_id: ClassId,
+    /// Labels of the nodes of the class without children, i.e. literals and constants
+    pub leaves: Vec<String>,
+}
+
+impl Display for ClassVertex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Class {}", self.class_id)?;
+        if !self.leaves.is_empty() {
+            write!(f, ": {}", self.leaves.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Edge of a [`TermGraph`] from a class to a child class.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NodeEdge {
+    /// Labels of the nodes of the parent class having the child, each as `symbol.index`
+    /// with the index of the child among the children of the node
+    pub labels: Vec<String>,
+}
+
+impl Display for NodeEdge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.labels.join(", "))
+    }
+}
+
+/// An e-graph as an explicit graph of classes, see [`EGraph::term_graph`].
+pub struct TermGraph {
+    /// Classes with edges to their children
+    pub graph: EdgeDataGraph<ClassVertex, NodeEdge>,
+    /// Vertex of every class
+    pub vertex_of: HashMap<ClassId, VertexId>,
+}
+
+impl TermGraph {
+    /// Returns the class of `vertex`.
+    pub fn class_of(&self, vertex: VertexId) -> ClassId {
+        self.graph.data_graph.data[vertex].class_id
+    }
+}
+
+impl<A: Analysis> EGraph<A> {
+    /// Converts the e-graph into a [`TermGraph`], with vertices numbered in the increasing order
+    /// of class IDs and nodes labelled with the symbol names of `language`.
+    pub fn term_graph(&self, language: &Language) -> TermGraph {
+        let mut graph = EdgeDataGraph::new();
+        let mut vertex_of = HashMap::new();
+        let class_ids: Vec<_> = self.classes.keys().copied().sorted().collect();
+
+        for &class_id in &class_ids {
+            let leaves = self.classes[&class_id]
+                .nodes_ids()
+                .iter()
+                .filter_map(|node_id| match &self.nodes[node_id] {
+                    Node::Literal(literal) => Some(literal.to_string()),
+                    Node::Symbol(symbol) if symbol.children.is_empty() => {
+                        Some(language.get_symbol(symbol.id).to_string())
+                    }
+                    Node::Symbol(_) => None,
+                })
+                .sorted()
+                .collect();
+            vertex_of.insert(class_id, graph.add_vertex(ClassVertex { class_id, leaves }));
+        }
+
+        for &class_id in &class_ids {
+            let mut labels: HashMap<ClassId, Vec<String>> = HashMap::new();
+            for node_id in self.classes[&class_id].nodes_ids() {
+                if let Node::Symbol(symbol) = &self.nodes[node_id] {
+                    let name = language.get_symbol(symbol.id);
+                    for (index, &child) in symbol.children.iter().enumerate() {
+                        labels
+                            .entry(self.union_find.find(child))
+                            .or_default()
+                            .push(format!("{name}.{index}"));
+                    }
+                }
+            }
+
+            for (child, mut labels) in labels.into_iter().sorted_by_key(|(child, _)| *child) {
+                labels.sort();
+                graph.add_edge(vertex_of[&class_id], vertex_of[&child], NodeEdge { labels });
+            }
+        }
+
+        TermGraph { graph, vertex_of }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::language::Language;
+    use crate::macros::rules;
+    use crate::rewriting::egraph::{
+        DynEGraph, EGraph,
+        matching::top_down::TopDownMatcher,
+        saturation::{SaturationConfig, Saturator, SimpleSaturator},
+    };
+
+    #[test]
+    fn term_graph_of_saturated_egraph() {
+        let lang = Language::simple_math();
+        let expression = lang.parse_no_vars("(* (+ 1 0) 2)").unwrap();
+        let (mut egraph, root) = EGraph::<()>::from_expression_with_id(expression);
+        SimpleSaturator::new(Box::new(TopDownMatcher)).saturate(
+            &mut egraph,
+            &rules!(lang; "(+ $0 0)" => "$0"),
+            &SaturationConfig::default(),
+        );
+        let root = egraph.canonical_class(root);
+
+        let term_graph = egraph.term_graph(&lang);
+        let graph = &term_graph.graph;
+        assert_eq!(graph.data_graph.graph.num_vertices(), 4);
+
+        let root_vertex = term_graph.vertex_of[&root];
+        let one = graph
+            .find_vertex(|vertex| vertex.leaves == ["1"])
+            .unwrap()
+            .0;
+        assert_eq!(term_graph.class_of(root_vertex), root);
+        assert!(graph.data_graph.graph.is_reachable(root_vertex, one));
+
+        // `(+ 1 0)` was merged with `1`, which now has a loop to itself
+        assert_eq!(graph.get_edge_data(one, one).unwrap().labels, ["+.0"]);
+        assert_eq!(graph.data_graph.graph.cyclic_vertices(), [one]);
+    }
+}
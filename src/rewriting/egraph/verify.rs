@@ -0,0 +1,361 @@
+//! Checking soundness of saturation against a model.
+//!
+//! Every rule applied during saturation should preserve the meaning of expressions,
+//! so all nodes of a class have to evaluate to the same value in any model of the rewriting system.
+//! [`check_model`] evaluates the classes of an e-graph under random values of constants
+//! (symbols without children) and reports the first class whose nodes disagree.
+//! Symbols and literals which the model cannot interpret, or for which evaluation fails
+//! (e.g. division by zero), are skipped.
+//! Models can be implemented directly, as [`SimpleMathModel`] is, or given by an
+//! [`Interpreter`] through [`InterpreterModel`].
+//!
+//! This module is available with the `verify` feature.
+
+use std::{collections::HashMap, error::Error, fmt};
+
+use rand::RngCore;
+
+use crate::language::{Language, eval::Interpreter, expression::Literal, symbol::SymbolId};
+
+use super::{Analysis, ClassId, DynEGraph, EGraph, Node, NodeId, class::DynClass};
+
+/// An interpretation of the symbols and literals of a language.
+pub trait Model {
+    type Value: Clone + PartialEq + fmt::Debug;
+
+    /// Value of `literal`, `None` if it is not interpreted.
+    fn literal(&self, literal: &Literal) -> Option<Self::Value>;
+
+    /// Random value of the constant `symbol`, `None` if it is not interpreted.
+    fn constant(&self, symbol: SymbolId, rng: &mut dyn RngCore) -> Option<Self::Value>;
+
+    /// Value of `symbol` applied to `children`, `None` if it is not interpreted
+    /// or undefined for these arguments.
+    fn apply(&self, symbol: SymbolId, children: &[Self::Value]) -> Option<Self::Value>;
+}
+
+/// Model of the language of [`Language::simple_math`] over `i64` with wrapping arithmetic.
+///
+/// `/` is integer division, `<<` and `>>` are shifts by less than 64 bits.
+/// `sin`, `cos` and other symbols with children are not interpreted,
+/// while symbols without children are constants with random values.
+#[derive(Clone, Debug)]
+pub struct SimpleMathModel {
+    add: Option<SymbolId>,
+    sub: Option<SymbolId>,
+    mul: Option<SymbolId>,
+    div: Option<SymbolId>,
+    shl: Option<SymbolId>,
+    shr: Option<SymbolId>,
+}
+
+impl SimpleMathModel {
+    pub fn new(language: &Language) -> Self {
+        Self {
+            add: language.try_get_id("+"),
+            sub: language.try_get_id("-"),
+            mul: language.try_get_id("*"),
+            div: language.try_get_id("/"),
+            shl: language.try_get_id("<<"),
+            shr: language.try_get_id(">>"),
+        }
+    }
+}
+
+impl Model for SimpleMathModel {
+    type Value = i64;
+
+    fn literal(&self, literal: &Literal) -> Option<i64> {
+        match literal {
+            Literal::Int(value) => Some(*value),
+            Literal::UInt(value) => i64::try_from(*value).ok(),
+            _ => None,
+        }
+    }
+
+    fn constant(&self, _symbol: SymbolId, rng: &mut dyn RngCore) -> Option<i64> {
+        Some(rng.next_u64() as i64)
+    }
+
+    fn apply(&self, symbol: SymbolId, children: &[i64]) -> Option<i64> {
+        let &[a, b] = children else {
+            return None;
+        };
+        let symbol = Some(symbol);
+
+        if symbol == self.add {
+            Some(a.wrapping_add(b))
+        } else if symbol == self.sub {
+            Some(a.wrapping_sub(b))
+        } else if symbol == self.mul {
+            Some(a.wrapping_mul(b))
+        } else if symbol == self.div {
+            a.checked_div(b)
+        } else if symbol == self.shl {
+            u32::try_from(b).ok().and_then(|b| a.checked_shl(b))
+        } else if symbol == self.shr {
+            u32::try_from(b).ok().and_then(|b| a.checked_shr(b))
+        } else {
+            None
+        }
+    }
+}
+
+/// Model given by an [`Interpreter`], with constants taking values from a fixed set of literals.
+///
+/// This allows checking e.g. rewriting systems of the logic language with
+/// [`FunctionInterpreter::logic`](crate::language::eval::FunctionInterpreter::logic)
+/// and constants ranging over `0` and `1`.
+pub struct InterpreterModel<'i, I: Interpreter> {
+    interpreter: &'i I,
+    constants: Vec<Literal>,
+}
+
+impl<'i, I: Interpreter> InterpreterModel<'i, I> {
+    /// Creates a model in which constants take values uniformly from `constants`,
+    /// which has to be non-empty.
+    pub fn new(interpreter: &'i I, constants: Vec<Literal>) -> Self {
+        assert!(!constants.is_empty(), "Constants need possible values");
+        Self {
+            interpreter,
+            constants,
+        }
+    }
+}
+
+impl<I: Interpreter> Model for InterpreterModel<'_, I> {
+    type Value = Literal;
+
+    fn literal(&self, literal: &Literal) -> Option<Literal> {
+        Some(literal.clone())
+    }
+
+    fn constant(&self, symbol: SymbolId, rng: &mut dyn RngCore) -> Option<Literal> {
+        match self.interpreter.apply(symbol, &[]) {
+            Ok(value) => Some(value),
+            Err(_) => Some(self.constants[rng.next_u64() as usize % self.constants.len()].clone()),
+        }
+    }
+
+    fn apply(&self, symbol: SymbolId, children: &[Literal]) -> Option<Literal> {
+        self.interpreter.apply(symbol, children).ok()
+    }
+}
+
+/// A class whose nodes evaluate to different values.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModelViolation<V> {
+    /// Index of the sample in which the violation was found
+    pub sample: usize,
+    pub class_id: ClassId,
+    /// Value of the class, given by one of its nodes
+    pub expected: V,
+    /// Node which evaluates to a different value
+    pub node_id: NodeId,
+    pub found: V,
+}
+
+impl<V: fmt::Debug> fmt::Display for ModelViolation<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Node {} of class {} evaluates to {:?} instead of {:?} in sample {}",
+            self.node_id, self.class_id, self.found, self.expected, self.sample
+        )
+    }
+}
+
+impl<V: fmt::Debug> Error for ModelViolation<V> {}
+
+struct Evaluation<'a, A: Analysis, M: Model> {
+    egraph: &'a EGraph<A>,
+    model: &'a M,
+    rng: &'a mut dyn RngCore,
+    constants: HashMap<SymbolId, Option<M::Value>>,
+    values: HashMap<ClassId, M::Value>,
+}
+
+impl<A: Analysis, M: Model> Evaluation<'_, A, M> {
+    fn node(&mut self, node_id: NodeId) -> Option<M::Value> {
+        match self.egraph.node(node_id) {
+            Node::Literal(literal) => self.model.literal(literal),
+            Node::Symbol(symbol) if symbol.children.is_empty() => self
+                .constants
+                .entry(symbol.id)
+                .or_insert_with(|| self.model.constant(symbol.id, self.rng))
+                .clone(),
+            Node::Symbol(symbol) => {
+                let children = symbol
+                    .children
+                    .iter()
+                    .map(|&child| {
+                        self.values
+                            .get(&self.egraph.canonical_class(child))
+                            .cloned()
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                self.model.apply(symbol.id, &children)
+            }
+        }
+    }
+
+    /// Assigns values to all classes with at least one node which can be evaluated.
+    fn evaluate_classes(&mut self) {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (&class_id, class) in self.egraph.iter_classes() {
+                if self.values.contains_key(&class_id) {
+                    continue;
+                }
+                for &node_id in class.iter_nodes() {
+                    if let Some(value) = self.node(node_id) {
+                        self.values.insert(class_id, value);
+                        changed = true;
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Checks that all nodes of every class of `egraph` evaluate to the same value in `model`,
+/// using `samples` independent random values of constants drawn from `rng`.
+pub fn check_model<A: Analysis, M: Model>(
+    egraph: &EGraph<A>,
+    model: &M,
+    samples: usize,
+    rng: &mut dyn RngCore,
+) -> Result<(), ModelViolation<M::Value>> {
+    for sample in 0..samples {
+        let mut evaluation = Evaluation {
+            egraph,
+            model,
+            rng: &mut *rng,
+            constants: HashMap::new(),
+            values: HashMap::new(),
+        };
+        evaluation.evaluate_classes();
+
+        for (&class_id, class) in egraph.iter_classes() {
+            let Some(expected) = evaluation.values.get(&class_id).cloned() else {
+                continue;
+            };
+            for &node_id in class.iter_nodes() {
+                if let Some(found) = evaluation.node(node_id)
+                    && found != expected
+                {
+                    return Err(ModelViolation {
+                        sample,
+                        class_id,
+                        expected,
+                        node_id,
+                        found,
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use super::{InterpreterModel, SimpleMathModel, check_model};
+    use crate::{
+        language::{Language, eval::FunctionInterpreter, expression::Literal},
+        macros::rules,
+        rewriting::egraph::{
+            EGraph,
+            matching::bottom_up::BottomUpMatcher,
+            saturation::{SaturationConfig, Saturator, SimpleSaturator},
+        },
+        rewriting::rule::Rule,
+    };
+
+    fn rng() -> StdRng {
+        StdRng::seed_from_u64(0)
+    }
+
+    fn saturated(rules: &[Rule], expression: &str) -> EGraph<()> {
+        let lang = Language::simple_math().add_symbol("x").add_symbol("y");
+        let mut egraph = EGraph::from_expression(lang.parse_no_vars(expression).unwrap());
+        SimpleSaturator::new(Box::new(BottomUpMatcher)).saturate(
+            &mut egraph,
+            rules,
+            &SaturationConfig {
+                max_nodes: Some(200),
+                ..Default::default()
+            },
+        );
+        egraph
+    }
+
+    #[test]
+    fn sound_rules_satisfy_model() {
+        let lang = Language::simple_math().add_symbol("x").add_symbol("y");
+        let rules = rules!(lang;
+            "(* $0 2)" => "(<< $0 1)",
+            "(+ $0 $1)" => "(+ $1 $0)",
+            "(* $0 (+ $1 $2))" => "(+ (* $0 $1) (* $0 $2))",
+            "(/ $0 1)" => "$0",
+        );
+        let egraph = saturated(&rules, "(/ (* (+ (x) (y)) 2) 1)");
+
+        assert_eq!(
+            check_model(&egraph, &SimpleMathModel::new(&lang), 20, &mut rng()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn unsound_rule_violates_model() {
+        let lang = Language::simple_math().add_symbol("x").add_symbol("y");
+        let rules = rules!(lang; "(/ (* $0 $1) $2)" => "(* $0 (/ $1 $2))");
+        let egraph = saturated(&rules, "(/ (* (x) (y)) 3)");
+
+        assert!(check_model(&egraph, &SimpleMathModel::new(&lang), 20, &mut rng()).is_err());
+    }
+
+    #[test]
+    fn logic_interpreter_model() {
+        let lang = Language::default()
+            .add_symbol("and")
+            .add_symbol("or")
+            .add_symbol("not")
+            .add_symbol("imp")
+            .add_symbol("p")
+            .add_symbol("q");
+        let rules = rules!(lang;
+            "(imp $0 $1)" => "(or (not $0) $1)",
+            "(and $0 1)" => "$0",
+            "(or $0 $1)" => "(or $1 $0)",
+            "(not (not $0))" => "$0",
+        );
+        let mut egraph = EGraph::<()>::from_expression(
+            lang.parse_no_vars("(imp (p) (and (not (not (q))) 1))")
+                .unwrap(),
+        );
+        SimpleSaturator::new(Box::new(BottomUpMatcher)).saturate(
+            &mut egraph,
+            &rules,
+            &SaturationConfig::default(),
+        );
+
+        let interpreter = FunctionInterpreter::logic(&lang);
+        let model = InterpreterModel::new(&interpreter, vec![Literal::Int(0), Literal::Int(1)]);
+        assert_eq!(check_model(&egraph, &model, 20, &mut rng()), Ok(()));
+
+        let unsound = rules!(lang; "(or $0 $1)" => "$0");
+        SimpleSaturator::new(Box::new(BottomUpMatcher)).saturate(
+            &mut egraph,
+            &unsound,
+            &SaturationConfig::default(),
+        );
+        assert!(check_model(&egraph, &model, 20, &mut rng()).is_err());
+    }
+}
@@ -0,0 +1,316 @@
+//! Experiments with heuristics on random pairs of expressions.
+//!
+//! The heuristics demo and the dataset binaries all generate a random expression E
+//! of a given size with variables, rewrite it randomly into E' and evaluate heuristics
+//! on the pair. A [`HeuristicExperimentConfig`] describes such an experiment, an [`Experiment`]
+//! generates its expressions reproducibly from a seed, and [`run_heuristic_experiment`] and
+//! [`Experiment::distance_record`] return the measured values as structured results.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
+
+use crate::benchmark::{
+    RandomGenerationConfig, VariableGenerationConfig,
+    generate_random_expression_by_size_with_variables,
+};
+use crate::compact::SinglyCompact;
+use crate::language::{
+    arities::Arities,
+    expression::{AnyExpression, Expression},
+};
+use crate::rewriting::{
+    heuristic::{AbelianPathHeuristic, Heuristic},
+    linear_heuristic::DistanceRecord,
+    random::rewrite_expression,
+    search::bfs_rewrite_limited,
+    system::TermRewritingSystem,
+};
+use crate::utils::{json::load_json, rng::seeded_rng};
+
+/// Parameters of an experiment on random pairs of expressions.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeuristicExperimentConfig {
+    /// Directory with `language.json`, `trs.json` and `arities.json` of the system
+    pub trs: PathBuf,
+    /// Size of the generated expressions
+    pub size: usize,
+    /// Number of variables, which have IDs from 0 to `variables - 1`
+    pub variables: usize,
+    /// Number of random rewrites applied to a generated expression to obtain its pair
+    pub applications: usize,
+    /// Seed of the random choices, a random one is used if `None`
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+impl HeuristicExperimentConfig {
+    /// Creates a config of an experiment with the system in `trs` and a random seed.
+    pub fn new(
+        trs: impl Into<PathBuf>,
+        size: usize,
+        variables: usize,
+        applications: usize,
+    ) -> Self {
+        Self {
+            trs: trs.into(),
+            size,
+            variables,
+            applications,
+            seed: None,
+        }
+    }
+
+    /// Sets the seed of the random choices.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+}
+
+/// A pair of a random expression and its random rewrite.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RewritePair {
+    /// Random expression
+    pub from: Expression,
+    /// `from` after the random rewrites
+    pub to: Expression,
+}
+
+/// A state of an experiment: the loaded system and the random number generator
+/// of the generated expressions.
+pub struct Experiment {
+    config: HeuristicExperimentConfig,
+    trs: TermRewritingSystem,
+    arities: Arities,
+    generation: RandomGenerationConfig,
+    rng: StdRng,
+    seed: u64,
+}
+
+impl Experiment {
+    /// Loads the system of `config`, seeding the random number generator with its seed.
+    pub fn new(config: &HeuristicExperimentConfig) -> Result<Self, Box<dyn Error>> {
+        let trs = TermRewritingSystem::from_directory(&config.trs)?;
+        let arities: Arities = load_json(config.trs.join("arities.json"))?;
+        Ok(Self::with_system(config, trs, arities))
+    }
+
+    /// Creates an experiment on `trs` with symbols of `arities`, ignoring `config.trs`.
+    pub fn with_system(
+        config: &HeuristicExperimentConfig,
+        trs: TermRewritingSystem,
+        arities: Arities,
+    ) -> Self {
+        let language = trs.language();
+        let mut generation = RandomGenerationConfig::from_language(language);
+        for symbol_id in 0..language.symbol_count() {
+            if let Some(symbol_arities) = arities.get(symbol_id) {
+                generation.symbol_arities[symbol_id] = symbol_arities.to_vec();
+            }
+        }
+        if config.variables > 0 {
+            generation.variable_config = Some(VariableGenerationConfig {
+                variable_range: (0, config.variables - 1),
+                variable_probability: 0.5,
+            });
+        }
+
+        let (rng, seed) = seeded_rng(config.seed);
+        Self {
+            config: config.clone(),
+            trs,
+            arities,
+            generation,
+            rng,
+            seed,
+        }
+    }
+
+    /// Returns the seed of the random choices, which reproduces the experiment.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns the system of the experiment.
+    pub fn trs(&self) -> &TermRewritingSystem {
+        &self.trs
+    }
+
+    /// Returns the arities of the symbols of the system, used to generate expressions.
+    pub fn arities(&self) -> &Arities {
+        &self.arities
+    }
+
+    /// Returns the random number generator, for further random choices of the experiment.
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+
+    /// Generates a random expression of the size of the config.
+    pub fn random_expression(&mut self) -> Result<Expression, Box<dyn Error>> {
+        Ok(generate_random_expression_by_size_with_variables(
+            self.trs.language(),
+            self.config.size,
+            &mut self.rng,
+            &self.generation,
+        )?)
+    }
+
+    /// Applies the number of random rewrites of the config to `expression`.
+    pub fn rewrite(&mut self, expression: Expression) -> Expression {
+        rewrite_expression(
+            expression,
+            self.trs.rules(),
+            self.config.applications,
+            &mut self.rng,
+        )
+    }
+
+    /// Generates a random expression together with its random rewrite.
+    pub fn random_pair(&mut self) -> Result<RewritePair, Box<dyn Error>> {
+        let from = self.random_expression()?;
+        let to = self.rewrite(from.clone());
+        Ok(RewritePair { from, to })
+    }
+
+    /// Labels a random pair with its rewrite distance, found by breadth-first search up to
+    /// `max_distance` rewrites visiting at most `max_visited` expressions,
+    /// and the values of the implemented heuristics.
+    pub fn distance_record(
+        &mut self,
+        max_distance: usize,
+        max_visited: usize,
+    ) -> Result<DistanceRecord, Box<dyn Error>> {
+        let RewritePair { from, to } = self.random_pair()?;
+        let distance = bfs_rewrite_limited(&from, &to, self.trs.rules(), max_distance, max_visited)
+            .map(|steps| steps.len());
+        let abelian_path = AbelianPathHeuristic::new(&to, &self.trs, &self.arities);
+        let heuristics = BTreeMap::from([(
+            String::from("abelian_path"),
+            finite(abelian_path.lower_bound_dist(&from)),
+        )]);
+
+        let language = self.trs.language();
+        Ok(DistanceRecord {
+            from: from.with_language(language).to_string(),
+            to: to.with_language(language).to_string(),
+            applications: self.config.applications,
+            distance,
+            heuristics,
+        })
+    }
+}
+
+/// A value of a heuristic together with the time of its evaluation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HeuristicEvaluation {
+    /// Value of the heuristic
+    pub distance: SinglyCompact<u32>,
+    /// Time the evaluation took
+    pub time: Duration,
+}
+
+impl HeuristicEvaluation {
+    /// Evaluates `heuristic` at `expression`, measuring the time it takes.
+    pub fn measure(heuristic: &impl Heuristic, expression: &Expression) -> Self {
+        let start = Instant::now();
+        let distance = heuristic.lower_bound_dist(expression);
+        Self {
+            distance,
+            time: start.elapsed(),
+        }
+    }
+}
+
+/// Results of [`run_heuristic_experiment`].
+#[derive(Clone, Debug)]
+pub struct HeuristicExperimentResult {
+    /// Seed reproducing the experiment
+    pub seed: u64,
+    /// Random expression E and its random rewrite E', the goal of the heuristic
+    pub pair: RewritePair,
+    /// Random expression F unrelated to E and E'
+    pub unrelated: Expression,
+    /// Time of the construction of the heuristic for E'
+    pub construction_time: Duration,
+    /// Heuristic at E
+    pub from: HeuristicEvaluation,
+    /// Heuristic at E', which is 0 for admissible heuristics
+    pub to: HeuristicEvaluation,
+    /// Heuristic at F
+    pub unrelated_evaluation: HeuristicEvaluation,
+}
+
+/// Generates random expressions E and F, rewrites E into E' and evaluates
+/// the [`AbelianPathHeuristic`] of the goal E' at E, E' and F.
+pub fn run_heuristic_experiment(
+    config: &HeuristicExperimentConfig,
+) -> Result<HeuristicExperimentResult, Box<dyn Error>> {
+    let mut experiment = Experiment::new(config)?;
+    run_heuristic_experiment_on(&mut experiment)
+}
+
+/// Same as [`run_heuristic_experiment`], with expressions generated by `experiment`.
+pub fn run_heuristic_experiment_on(
+    experiment: &mut Experiment,
+) -> Result<HeuristicExperimentResult, Box<dyn Error>> {
+    let from = experiment.random_expression()?;
+    let unrelated = experiment.random_expression()?;
+    let to = experiment.rewrite(from.clone());
+
+    let start = Instant::now();
+    let heuristic = AbelianPathHeuristic::new(&to, experiment.trs(), experiment.arities());
+    let construction_time = start.elapsed();
+
+    Ok(HeuristicExperimentResult {
+        seed: experiment.seed(),
+        from: HeuristicEvaluation::measure(&heuristic, &from),
+        to: HeuristicEvaluation::measure(&heuristic, &to),
+        unrelated_evaluation: HeuristicEvaluation::measure(&heuristic, &unrelated),
+        construction_time,
+        pair: RewritePair { from, to },
+        unrelated,
+    })
+}
+
+fn finite(distance: SinglyCompact<u32>) -> Option<u32> {
+    match distance {
+        SinglyCompact::Finite(distance) => Some(distance),
+        SinglyCompact::Infinite => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{Experiment, HeuristicExperimentConfig, run_heuristic_experiment};
+    use crate::language::expression::AnyExpression;
+
+    fn config() -> HeuristicExperimentConfig {
+        let trs = Path::new(env!("CARGO_MANIFEST_DIR")).join("jsons/simple-math");
+        HeuristicExperimentConfig::new(trs, 7, 2, 2).with_seed(11)
+    }
+
+    #[test]
+    fn experiments_are_reproducible() {
+        let mut first = Experiment::new(&config()).unwrap();
+        let mut second = Experiment::new(&config()).unwrap();
+        assert_eq!(first.seed(), 11);
+        assert_eq!(first.random_pair().unwrap(), second.random_pair().unwrap());
+
+        let record = first.distance_record(2, 100_000).unwrap();
+        assert_eq!(record, second.distance_record(2, 100_000).unwrap());
+        assert_eq!(record.applications, 2);
+        assert!(record.heuristics.contains_key("abelian_path"));
+
+        let result = run_heuristic_experiment(&config()).unwrap();
+        assert_eq!(result.seed, 11);
+        assert_eq!(result.pair.from.iter_subexpressions().count(), 7);
+    }
+}
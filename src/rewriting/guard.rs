@@ -0,0 +1,283 @@
+//! Guards restricting the matches of rules to numeric literals.
+//!
+//! Variables of rule patterns written as `#0`, `#1`, ... instead of `$0`, `$1`, ... only match
+//! numeric literals, or classes containing them in e-graphs. A [`Guard`] keeps track of such
+//! variables together with comparisons of their values, e.g. `#1 != 0`, all of which must hold
+//! for the rule to be applied. This lets rules such as `(/ $0 #1) => (* $0 (/ 1 #1)) if #1 != 0`
+//! be written as patterns.
+
+use std::{cmp::Ordering, collections::BTreeSet, fmt};
+
+use serde::{Deserialize, Serialize};
+
+use crate::language::{
+    Language,
+    expression::{AnyExpression, Expression, Literal, VariableId},
+};
+
+/// Comparison operator of a [`Comparison`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ComparisonOperator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl ComparisonOperator {
+    /// Operators with their tokens, those which are prefixes of others last.
+    const TOKENS: [(&str, ComparisonOperator); 6] = [
+        ("==", ComparisonOperator::Eq),
+        ("!=", ComparisonOperator::Ne),
+        ("<=", ComparisonOperator::Le),
+        (">=", ComparisonOperator::Ge),
+        ("<", ComparisonOperator::Lt),
+        (">", ComparisonOperator::Gt),
+    ];
+
+    /// `true` if values ordered by `ordering` are in the relation.
+    fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            ComparisonOperator::Eq => ordering.is_eq(),
+            ComparisonOperator::Ne => ordering.is_ne(),
+            ComparisonOperator::Lt => ordering.is_lt(),
+            ComparisonOperator::Le => ordering.is_le(),
+            ComparisonOperator::Gt => ordering.is_gt(),
+            ComparisonOperator::Ge => ordering.is_ge(),
+        }
+    }
+
+    fn token(self) -> &'static str {
+        Self::TOKENS
+            .iter()
+            .find(|(_, operator)| *operator == self)
+            .unwrap()
+            .0
+    }
+}
+
+/// Operand of a [`Comparison`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Operand {
+    /// Value of the numeric literal matched by a variable
+    Variable(VariableId),
+    Literal(Literal),
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Variable(variable_id) => write!(f, "#{variable_id}"),
+            Operand::Literal(literal) => write!(f, "{literal}"),
+        }
+    }
+}
+
+/// A comparison of values of numeric literals, e.g. `#0 < #1` or `#1 != 0`.
+/// Values of literals of different kinds are compared, so that e.g. `1` and `1.0` are equal.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Comparison {
+    pub left: Operand,
+    pub operator: ComparisonOperator,
+    pub right: Operand,
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.left, self.operator.token(), self.right)
+    }
+}
+
+/// Conditions on the matches of a rule: variables which only match numeric literals
+/// and comparisons of their values.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Guard {
+    /// Variables only matching numeric literals, written as `#n` in patterns
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub literal_variables: BTreeSet<VariableId>,
+    /// Comparisons which must all hold
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub comparisons: Vec<Comparison>,
+}
+
+impl Guard {
+    /// Creates a guard with no conditions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restricts the variables with IDs `variables` to numeric literals.
+    pub fn with_literal_variables(
+        mut self,
+        variables: impl IntoIterator<Item = VariableId>,
+    ) -> Self {
+        self.literal_variables.extend(variables);
+        self
+    }
+
+    /// Adds `comparison`, restricting its variables to numeric literals.
+    pub fn with_comparison(mut self, comparison: Comparison) -> Self {
+        for operand in [&comparison.left, &comparison.right] {
+            if let Operand::Variable(variable_id) = operand {
+                self.literal_variables.insert(*variable_id);
+            }
+        }
+        self.comparisons.push(comparison);
+        self
+    }
+
+    /// Parses comparisons joined by `&&`, e.g. `#0 != 0 && #0 < #1`. Operands are
+    /// variables written as `#n` or numeric literals, and operators are
+    /// `==`, `!=`, `<`, `<=`, `>` and `>=`.
+    ///
+    /// # Arguments
+    ///
+    /// * `string` - The string representation of the comparisons
+    ///
+    /// # Returns
+    ///
+    /// Returns a guard with the comparisons, or an error if parsing fails
+    pub fn parse(string: &str) -> anyhow::Result<Self> {
+        string
+            .split("&&")
+            .try_fold(Self::new(), |guard, comparison| {
+                let comparison = comparison.trim();
+                let Some((token, operator)) = ComparisonOperator::TOKENS
+                    .into_iter()
+                    .find(|(token, _)| comparison.contains(token))
+                else {
+                    anyhow::bail!("Comparison {comparison} has no comparison operator");
+                };
+                let (left, right) = comparison.split_once(token).unwrap();
+                Ok(guard.with_comparison(Comparison {
+                    left: parse_operand(left.trim())?,
+                    operator,
+                    right: parse_operand(right.trim())?,
+                }))
+            })
+    }
+
+    /// `true` if the guard has no conditions.
+    pub fn is_empty(&self) -> bool {
+        self.literal_variables.is_empty() && self.comparisons.is_empty()
+    }
+
+    /// `true` if every literal variable is bound to a numeric literal by `literal_of`
+    /// and all comparisons hold. Variables which are not bound never satisfy the guard.
+    pub fn admits(&self, literal_of: impl Fn(VariableId) -> Option<Literal>) -> bool {
+        let mut values = Vec::with_capacity(self.literal_variables.len());
+        for &variable_id in &self.literal_variables {
+            match literal_of(variable_id) {
+                Some(literal) if literal.is_numeric() => values.push((variable_id, literal)),
+                _ => return false,
+            }
+        }
+        let value = |operand: &Operand| match operand {
+            Operand::Variable(variable_id) => values
+                .iter()
+                .find(|(id, _)| id == variable_id)
+                .map(|(_, literal)| literal.clone()),
+            Operand::Literal(literal) => Some(literal.clone()),
+        };
+
+        self.comparisons.iter().all(|comparison| {
+            let (Some(left), Some(right)) = (value(&comparison.left), value(&comparison.right))
+            else {
+                return false;
+            };
+            left.cmp_value(&right)
+                .is_some_and(|ordering| comparison.operator.holds(ordering))
+        })
+    }
+
+    /// Writes `pattern` with `language`, with the literal variables written as `#n`.
+    pub fn format_pattern(&self, pattern: &Expression, language: &Language) -> String {
+        match pattern {
+            Expression::Variable(variable_id) if self.literal_variables.contains(variable_id) => {
+                format!("#{variable_id}")
+            }
+            Expression::Symbol(symbol)
+                if !symbol.children.is_empty() && !self.literal_variables.is_empty() =>
+            {
+                let mut string = format!("({}", language.get_symbol(symbol.id));
+                for child in &symbol.children {
+                    string.push(' ');
+                    string.push_str(&self.format_pattern(child, language));
+                }
+                string.push(')');
+                string
+            }
+            _ => pattern.with_language(language).to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Guard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, comparison) in self.comparisons.iter().enumerate() {
+            if index > 0 {
+                write!(f, " && ")?;
+            }
+            write!(f, "{comparison}")?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_operand(string: &str) -> anyhow::Result<Operand> {
+    if let Some(variable_id) = string.strip_prefix('#') {
+        return Ok(Operand::Variable(variable_id.parse()?));
+    }
+    match Language::default().parse(string)? {
+        Expression::Literal(literal) if literal.is_numeric() => Ok(Operand::Literal(literal)),
+        _ => anyhow::bail!("Operand {string} is neither a literal variable nor a number"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::language::{Language, expression::Literal};
+
+    use super::{Comparison, ComparisonOperator, Guard, Operand};
+
+    #[test]
+    fn parses_and_checks_comparisons() {
+        let guard = Guard::parse("#1 != 0 && #0 <= #1").unwrap();
+        assert_eq!(
+            guard.comparisons[0],
+            Comparison {
+                left: Operand::Variable(1),
+                operator: ComparisonOperator::Ne,
+                right: Operand::Literal(Literal::Int(0)),
+            }
+        );
+        assert_eq!(guard.literal_variables.len(), 2);
+        assert_eq!(guard.to_string(), "#1 != 0 && #0 <= #1");
+
+        let bindings = |values: [Literal; 2]| move |id: usize| values.get(id).cloned();
+        assert!(guard.admits(bindings([Literal::Int(1), Literal::float(1.0)])));
+        assert!(!guard.admits(bindings([Literal::Int(0), Literal::Rational(0, 1)])));
+        assert!(!guard.admits(bindings([Literal::Int(3), Literal::UInt(2)])));
+        assert!(!guard.admits(bindings([Literal::Int(1), Literal::string("2")])));
+        assert!(!guard.admits(|_| None));
+
+        assert!(Guard::parse("#0 = 1").is_err());
+        assert!(Guard::parse("$0 != 1").is_err());
+        assert!(Guard::parse("#0 > \"a\"").is_err());
+    }
+
+    #[test]
+    fn formats_literal_variables() {
+        let lang = Language::simple_math();
+        let (pattern, literal_variables) = lang.parse_pattern("(+ $0 (* #1 #1))").unwrap();
+        let guard = Guard::new().with_literal_variables(literal_variables);
+        assert_eq!(guard.literal_variables.len(), 1);
+        assert_eq!(guard.format_pattern(&pattern, &lang), "(+ $0 (* #1 #1))");
+        assert_eq!(
+            Guard::new().format_pattern(&pattern, &lang),
+            "(+ $0 (* $1 $1))"
+        );
+    }
+}
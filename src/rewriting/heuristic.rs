@@ -23,6 +23,15 @@
 //! - `max_{x ∈ ∅} y = 0`
 //! - If the ILP has no solution, `θ(A, d) = ∞`
 //!
+//! Searches estimate every expression obtained by a single rewrite of an already estimated one,
+//! so heuristics may keep a [`HeuristicState`] of an expression and update it after a rewrite,
+//! see [`Heuristic::lower_bound_dist_after`]. The ILP heuristic keeps the path vectors,
+//! recomputes only the paths through the rewritten subexpression and caches solutions of ILPs,
+//! as most differences of paths of a child are those of its parent.
+//!
+//! The values from which a bound is computed are reported by [`Heuristic::explain`],
+//! e.g. to find out which variable makes the ILP heuristic infinite on a reachable goal.
+//!
 //! # Examples
 //!
 //! Creating and using an ILP heuristic:
@@ -65,18 +74,21 @@
 //! }
 //! ```
 use crate::compact::SinglyCompact;
-use crate::language::{Language, arities::Arities, expression::{Expression, VariableId}};
+use crate::language::{
+    arities::Arities,
+    expression::{Expression, OwnedPath, VariableId},
+};
 use crate::rewriting::{
-    ilp::create_ilp_problem,
-    strings::{
-        get_path_abelian_vectors_to_variables,
-        rules_to_abelian_matrix, to_string_language, PathAbelianVector,
-    },
+    abelian::Lattice,
+    direct::RewritePosition,
+    ilp::{IlpConfig, IlpStatistics, solve_ilp_problem},
+    strings::{PathAbelianVector, SparseAbelianVector, StringRewritingSystem},
     system::TermRewritingSystem,
 };
-use good_lp::{Solution, SolverModel, default_solver};
-use nalgebra::DVector;
+use itertools::Itertools;
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::fmt;
 
 /// A heuristic function that provides a lower bound on the distance to a goal.
 ///
@@ -94,6 +106,151 @@ pub trait Heuristic {
     /// Returns a lower bound estimate of the distance, which may be infinite
     /// if the goal cannot be reached from this expression.
     fn lower_bound_dist(&self, expression: &Expression) -> SinglyCompact<u32>;
+
+    /// Computes the same lower bound as [`Heuristic::lower_bound_dist`] together with the state
+    /// of `expression`, from which bounds for the results of rewrites of `expression` are computed
+    /// by [`Heuristic::lower_bound_dist_after`].
+    fn lower_bound_dist_with_state(
+        &self,
+        expression: &Expression,
+    ) -> (SinglyCompact<u32>, HeuristicState) {
+        (self.lower_bound_dist(expression), HeuristicState::default())
+    }
+
+    /// Computes a lower bound on the distance from `child`, obtained by a rewrite at `position`
+    /// of the expression with the state `parent`, together with the state of `child`.
+    ///
+    /// The bound equals [`Heuristic::lower_bound_dist`] of `child`, but may be computed
+    /// incrementally. By default it is computed from scratch.
+    fn lower_bound_dist_after(
+        &self,
+        parent: &HeuristicState,
+        position: &RewritePosition,
+        child: &Expression,
+    ) -> (SinglyCompact<u32>, HeuristicState) {
+        let _ = (parent, position);
+        self.lower_bound_dist_with_state(child)
+    }
+
+    /// Reports the values from which the bound of `expression` is computed.
+    /// By default the breakdown is empty.
+    fn explain(&self, expression: &Expression) -> HeuristicBreakdown {
+        let _ = expression;
+        HeuristicBreakdown::default()
+    }
+}
+
+/// Data kept by a [`Heuristic`] about an expression to update its bound incrementally
+/// after a rewrite, see [`Heuristic::lower_bound_dist_after`].
+///
+/// Heuristics which compute their bounds from scratch keep an empty state.
+#[derive(Debug, Clone, Default)]
+pub struct HeuristicState {
+    /// Abelianized vectors of the paths to variables of the expression, kept by path heuristics
+    paths: Vec<PathAbelianVector>,
+}
+
+/// Values from which a [`Heuristic`] computes its bound, see [`Heuristic::explain`].
+///
+/// For path heuristics, the bound is the maximum of the bounds of [`VariableBreakdown`]s.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeuristicBreakdown {
+    /// Bounds of the variables of the expression or the goal, sorted by their IDs
+    pub variables: Vec<VariableBreakdown>,
+}
+
+impl HeuristicBreakdown {
+    /// Returns the maximum of the bounds of the variables, 0 if there are none.
+    pub fn bound(&self) -> SinglyCompact<u32> {
+        self.variables
+            .iter()
+            .map(|variable| variable.bound)
+            .max()
+            .unwrap_or(SinglyCompact::Finite(0))
+    }
+
+    /// Returns the variables with infinite bounds, which make the bound infinite.
+    pub fn infinite_variables(&self) -> impl Iterator<Item = &VariableBreakdown> {
+        self.variables
+            .iter()
+            .filter(|variable| variable.bound.is_infinite())
+    }
+}
+
+impl fmt::Display for HeuristicBreakdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for variable in &self.variables {
+            writeln!(f, "${}: {}", variable.variable_id, variable.bound)?;
+            for target in &variable.target_paths {
+                write!(
+                    f,
+                    "  goal path at {}: {}",
+                    format_position(&target.position),
+                    target.bound
+                )?;
+                if target.current_paths.is_empty() {
+                    write!(f, " (no paths in the expression)")?;
+                }
+                writeln!(f)?;
+                for current in &target.current_paths {
+                    write!(
+                        f,
+                        "    path at {}: {}",
+                        format_position(&current.position),
+                        current.bound
+                    )?;
+                    if !current.in_lattice {
+                        write!(f, " (not in the lattice of the rules)")?;
+                    }
+                    writeln!(f)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Bound of a variable: the maximum over the paths to it in the goal of the minimum
+/// over the paths to it in the expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariableBreakdown {
+    /// Variable whose paths are compared
+    pub variable_id: VariableId,
+    /// Maximum of the bounds of `target_paths`, 0 if the goal has no paths to the variable
+    pub bound: SinglyCompact<u32>,
+    /// Bounds of the paths to the variable in the goal
+    pub target_paths: Vec<TargetPathBreakdown>,
+}
+
+/// Bound of a path to a variable in the goal: the minimum over the paths to the variable
+/// in the expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetPathBreakdown {
+    /// Position of the variable occurrence in the goal
+    pub position: OwnedPath,
+    /// Minimum of the bounds of `current_paths`, infinite if there are none
+    pub bound: SinglyCompact<u32>,
+    /// Bounds of the differences from the paths to the variable in the expression
+    pub current_paths: Vec<PathDifferenceBound>,
+}
+
+/// Bound of the difference between a path in the goal and a path in the expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathDifferenceBound {
+    /// Position of the variable occurrence in the expression
+    pub position: OwnedPath,
+    /// Solution of the ILP of the difference of the abelianized vectors of the paths
+    pub bound: SinglyCompact<u32>,
+    /// `false` if the difference is not an integer combination of the abelianized rules,
+    /// in which case it is infinite without solving the ILP
+    pub in_lattice: bool,
+}
+
+fn format_position(position: &OwnedPath) -> String {
+    match position.0.is_empty() {
+        true => String::from("root"),
+        false => position.0.iter().join("."),
+    }
 }
 
 /// A factory for constructing heuristics.
@@ -134,17 +291,25 @@ pub trait HeuristicConstructor {
 /// - a(p) is the abelianized vector of path p
 /// - θ(A, d) is the solution to the ILP minimize 1^T x subject to Ax = d, x ≥ 0, x integer
 /// - M_T is the abelianized matrix of T_s (the induced string rewriting system)
+///
+/// The ILPs are solved by the backend and with the time limit of an [`IlpConfig`], see
+/// [`AbelianPathHeuristic::with_ilp_config`], and statistics of the solved problems
+/// are available from [`AbelianPathHeuristic::ilp_statistics`].
 pub struct AbelianPathHeuristic {
     /// Grouped target paths by variable ID (precomputed for performance)
     target_by_var: HashMap<VariableId, Vec<PathAbelianVector>>,
-    /// The abelianized matrix of the induced string rewriting system T_s
-    abelian_matrix: nalgebra::DMatrix<i32>,
-    /// The string language for computing paths
-    string_lang: Language,
-    /// Arities for the symbols
-    arities: Arities,
-    /// The original language
-    lang: Language,
+    /// The induced string rewriting system T_s, owning the abelianized matrix M_T
+    /// and the string language for computing paths
+    srs: StringRewritingSystem,
+    /// Integer lattice generated by the columns of M_T, rejecting unreachable differences
+    /// without solving ILPs
+    lattice: Lattice,
+    /// Backend, time limit and timeout policy of the ILPs
+    ilp_config: IlpConfig,
+    /// Statistics of the ILPs solved so far
+    ilp_statistics: RefCell<IlpStatistics>,
+    /// Solutions of the ILPs solved so far by their difference vectors
+    ilp_cache: RefCell<HashMap<SparseAbelianVector, SinglyCompact<u32>>>,
 }
 
 impl AbelianPathHeuristic {
@@ -160,17 +325,11 @@ impl AbelianPathHeuristic {
     ///
     /// Returns a new `AbelianPathHeuristic` instance.
     pub fn new(target_expr: &Expression, trs: &TermRewritingSystem, arities: &Arities) -> Self {
-        let lang = trs.language().clone();
-        let string_lang = to_string_language(&lang, arities);
-        
+        let srs = StringRewritingSystem::from_trs(trs, arities);
+
         // Precompute abelianized vectors for all paths in the target expression
-        let target_paths = get_path_abelian_vectors_to_variables(
-            target_expr,
-            &lang,
-            &string_lang,
-            arities,
-        );
-        
+        let target_paths = srs.path_abelian_vectors_to_variables(target_expr);
+
         // Precompute target paths grouped by variable ID for performance
         let mut target_by_var: HashMap<VariableId, Vec<PathAbelianVector>> = HashMap::new();
         for path in &target_paths {
@@ -179,33 +338,30 @@ impl AbelianPathHeuristic {
                 .or_default()
                 .push(path.clone());
         }
-        
-        // Convert TRS rules to induced string rewriting rules
-        // M_T is the abelianized matrix of T_s (the induced string rewriting system)
-        let induced_rules: Vec<_> = trs.rules()
-            .iter()
-            .flat_map(|rule| {
-                crate::rewriting::strings::rule_to_induced_rules(
-                    rule,
-                    &lang,
-                    &string_lang,
-                    arities,
-                )
-            })
-            .collect();
-        
-        // Compute the abelianized matrix from the induced rules
-        let abelian_matrix = rules_to_abelian_matrix(&induced_rules, &string_lang);
-        
+
+        let lattice = Lattice::new(srs.abelian_matrix());
+
         Self {
             target_by_var,
-            abelian_matrix,
-            string_lang,
-            arities: arities.clone(),
-            lang,
+            srs,
+            lattice,
+            ilp_config: IlpConfig::default(),
+            ilp_statistics: RefCell::new(IlpStatistics::default()),
+            ilp_cache: RefCell::new(HashMap::new()),
         }
     }
-    
+
+    /// Sets the backend, the time limit and the timeout policy of the solved ILPs.
+    pub fn with_ilp_config(mut self, ilp_config: IlpConfig) -> Self {
+        self.ilp_config = ilp_config;
+        self
+    }
+
+    /// Returns statistics of the ILPs solved so far, e.g. for benchmark reports.
+    pub fn ilp_statistics(&self) -> IlpStatistics {
+        self.ilp_statistics.borrow().clone()
+    }
+
     /// Solves the ILP problem for a given difference vector.
     ///
     /// This method solves the Integer Linear Programming problem:
@@ -222,60 +378,70 @@ impl AbelianPathHeuristic {
     /// # Arguments
     ///
     /// * `diff_vector` - The difference vector d = a(ω) - a(α) representing the
-    ///   difference between target and current path abelianized vectors
+    ///   difference between target and current path abelianized vectors,
+    ///   converted to a dense vector only for the ILP solver
     ///
     /// # Returns
     ///
     /// Returns:
     /// - `Finite(n)` where n is the optimal objective value (minimum sum of rule applications)
     /// - `Infinite` if the ILP problem is infeasible (target unreachable)
+    /// - if the time limit is exceeded, the bound given by the timeout policy, see
+    ///   [`TimeoutPolicy`](crate::rewriting::ilp::TimeoutPolicy)
     ///
     /// # Special Cases
     ///
     /// - If there are no rules (empty TRS), returns `Finite(0)` if diff_vector is zero,
     ///   otherwise `Infinite`
-    fn solve_ilp(&self, diff_vector: &DVector<i32>) -> SinglyCompact<u32> {
-        if self.abelian_matrix.ncols() == 0 {
+    /// - If diff_vector is not even an integer combination of the columns of M_T,
+    ///   returns `Infinite` without solving the ILP, see [`Lattice`]
+    /// - If the ILP was already solved, returns the cached solution
+    fn solve_ilp(&self, diff_vector: &SparseAbelianVector) -> SinglyCompact<u32> {
+        if let Some(&solution) = self.ilp_cache.borrow().get(diff_vector) {
+            return solution;
+        }
+        let solution = self.solve_ilp_uncached(diff_vector);
+        self.ilp_cache
+            .borrow_mut()
+            .insert(diff_vector.clone(), solution);
+        solution
+    }
+
+    fn solve_ilp_uncached(&self, diff_vector: &SparseAbelianVector) -> SinglyCompact<u32> {
+        let abelian_matrix = self.srs.abelian_matrix();
+        if abelian_matrix.ncols() == 0 {
             // No rules available
-            return if diff_vector.iter().all(|&x| x == 0) {
+            return if diff_vector.is_zero() {
                 SinglyCompact::Finite(0)
             } else {
                 SinglyCompact::Infinite
             };
         }
-        
-        let (model, vars) = create_ilp_problem(&self.abelian_matrix, diff_vector, default_solver);
-        
-        match model.solve() {
-            Ok(solution) => {
-                let obj_value: f64 = vars.iter().map(|&v| solution.value(v)).sum();
-                // Round to nearest integer (should already be integer due to ILP)
-                SinglyCompact::Finite(obj_value.round() as u32)
-            }
-            Err(_) => SinglyCompact::Infinite,
+        if !self.lattice.contains(diff_vector) {
+            return SinglyCompact::Infinite;
+        }
+
+        let dense_diff = diff_vector.to_dense(abelian_matrix.nrows());
+        let (outcome, time) = solve_ilp_problem(abelian_matrix, &dense_diff, &self.ilp_config);
+        self.ilp_statistics.borrow_mut().record(&outcome, time);
+
+        match outcome.value() {
+            Some(value) => SinglyCompact::Finite(value),
+            None => SinglyCompact::Infinite,
         }
     }
-}
 
-impl Heuristic for AbelianPathHeuristic {
-    fn lower_bound_dist(&self, expression: &Expression) -> SinglyCompact<u32> {
-        // Get all paths in the current expression
-        let current_paths = get_path_abelian_vectors_to_variables(
-            expression,
-            &self.lang,
-            &self.string_lang,
-            &self.arities,
-        );
-        
+    /// Computes the bound of an expression whose paths to variables are `current_paths`.
+    fn lower_bound_dist_of_paths(&self, current_paths: &[PathAbelianVector]) -> SinglyCompact<u32> {
         // Group paths by variable ID
         let mut current_by_var: HashMap<VariableId, Vec<&PathAbelianVector>> = HashMap::new();
-        for path in &current_paths {
+        for path in current_paths {
             current_by_var
                 .entry(path.variable_id)
                 .or_default()
                 .push(path);
         }
-        
+
         // Get all variables from either expression
         let mut all_vars: Vec<VariableId> = current_by_var.keys().copied().collect();
         for &var_id in self.target_by_var.keys() {
@@ -283,17 +449,24 @@ impl Heuristic for AbelianPathHeuristic {
                 all_vars.push(var_id);
             }
         }
-        
+
         let mut max_over_vars = SinglyCompact::Finite(0);
-        
+
         // For each variable v in V_{e,e'}
         for var_id in all_vars {
-            let current_paths_for_var = current_by_var.get(&var_id).map(|v| v.as_slice()).unwrap_or(&[]);
-            let target_paths_for_var = self.target_by_var.get(&var_id).map(|v| v.as_slice()).unwrap_or(&[]);
-            
+            let current_paths_for_var = current_by_var
+                .get(&var_id)
+                .map(|v| v.as_slice())
+                .unwrap_or(&[]);
+            let target_paths_for_var = self
+                .target_by_var
+                .get(&var_id)
+                .map(|v| v.as_slice())
+                .unwrap_or(&[]);
+
             // max_{ω ∈ Ω^{e'}_v} min_{α ∈ Ω^e_v} θ(...)
             let mut max_over_target = SinglyCompact::Finite(0);
-            
+
             // For each path ω in Ω^{e'}_v (paths in target expression)
             for target_path in target_paths_for_var {
                 // Take minimum over current paths using iterator
@@ -307,28 +480,124 @@ impl Heuristic for AbelianPathHeuristic {
                     })
                     .min()
                     .unwrap_or(SinglyCompact::Infinite); // Convention: min over empty set = ∞
-                
+
                 // Take maximum over target paths
                 if min_over_current > max_over_target {
                     max_over_target = min_over_current;
                 }
             }
-            
+
             // Short-circuit if max_over_target is infinite
             if max_over_target.is_infinite() {
                 return SinglyCompact::Infinite;
             }
-            
+
             // Take maximum over variables
             if max_over_target > max_over_vars {
                 max_over_vars = max_over_target;
             }
         }
-        
+
         max_over_vars
     }
 }
 
+impl Heuristic for AbelianPathHeuristic {
+    fn lower_bound_dist(&self, expression: &Expression) -> SinglyCompact<u32> {
+        // Get all paths in the current expression
+        self.lower_bound_dist_of_paths(&self.srs.path_abelian_vectors_to_variables(expression))
+    }
+
+    fn lower_bound_dist_with_state(
+        &self,
+        expression: &Expression,
+    ) -> (SinglyCompact<u32>, HeuristicState) {
+        let paths = self.srs.path_abelian_vectors_to_variables(expression);
+        (
+            self.lower_bound_dist_of_paths(&paths),
+            HeuristicState { paths },
+        )
+    }
+
+    fn lower_bound_dist_after(
+        &self,
+        parent: &HeuristicState,
+        position: &RewritePosition,
+        child: &Expression,
+    ) -> (SinglyCompact<u32>, HeuristicState) {
+        // Only paths through the rewritten subexpression change
+        let mut paths: Vec<_> = parent
+            .paths
+            .iter()
+            .filter(|path| !path.position.0.starts_with(&position.path.0))
+            .cloned()
+            .collect();
+        paths.extend(self.srs.path_abelian_vectors_below(child, &position.path));
+
+        (
+            self.lower_bound_dist_of_paths(&paths),
+            HeuristicState { paths },
+        )
+    }
+
+    fn explain(&self, expression: &Expression) -> HeuristicBreakdown {
+        let current_paths = self.srs.path_abelian_vectors_to_variables(expression);
+        let variable_ids = current_paths
+            .iter()
+            .map(|path| path.variable_id)
+            .chain(self.target_by_var.keys().copied())
+            .sorted()
+            .dedup();
+
+        let variables = variable_ids
+            .map(|variable_id| {
+                let target_paths: Vec<_> = self
+                    .target_by_var
+                    .get(&variable_id)
+                    .map(|v| v.as_slice())
+                    .unwrap_or(&[])
+                    .iter()
+                    .map(|target_path| {
+                        let current_paths: Vec<_> = current_paths
+                            .iter()
+                            .filter(|current_path| current_path.variable_id == variable_id)
+                            .map(|current_path| {
+                                let diff = &target_path.vector - &current_path.vector;
+                                PathDifferenceBound {
+                                    position: current_path.position.clone(),
+                                    bound: self.solve_ilp(&diff),
+                                    in_lattice: self.lattice.contains(&diff),
+                                }
+                            })
+                            .collect();
+                        TargetPathBreakdown {
+                            position: target_path.position.clone(),
+                            bound: current_paths
+                                .iter()
+                                .map(|current| current.bound)
+                                .min()
+                                .unwrap_or(SinglyCompact::Infinite),
+                            current_paths,
+                        }
+                    })
+                    .collect();
+
+                VariableBreakdown {
+                    variable_id,
+                    bound: target_paths
+                        .iter()
+                        .map(|target| target.bound)
+                        .max()
+                        .unwrap_or(SinglyCompact::Finite(0)),
+                    target_paths,
+                }
+            })
+            .collect();
+
+        HeuristicBreakdown { variables }
+    }
+}
+
 /// Default constructor for abelian path heuristics.
 ///
 /// This constructor creates `AbelianPathHeuristic` instances. It requires arities to be
@@ -336,17 +605,23 @@ impl Heuristic for AbelianPathHeuristic {
 pub struct AbelianPathHeuristicConstructor {
     /// Arities for symbols in the language
     pub arities: Arities,
+    /// Backend, time limit and timeout policy of the ILPs of the constructed heuristics
+    pub ilp_config: IlpConfig,
 }
 
 impl HeuristicConstructor for AbelianPathHeuristicConstructor {
     fn construct(&self, expression: &Expression, trs: &TermRewritingSystem) -> Box<dyn Heuristic> {
-        Box::new(AbelianPathHeuristic::new(expression, trs, &self.arities))
+        Box::new(
+            AbelianPathHeuristic::new(expression, trs, &self.arities)
+                .with_ilp_config(self.ilp_config.clone()),
+        )
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::language::{Language, expression::OwnedPath};
     use crate::macros::rules;
     use std::collections::HashMap;
 
@@ -386,10 +661,10 @@ mod tests {
 
         let target = lang.parse("(* $0 $1)").unwrap();
         let current = lang.parse("(+ $0 $1)").unwrap();
-        
+
         let heuristic = AbelianPathHeuristic::new(&target, &trs, &arities);
         let dist = heuristic.lower_bound_dist(&current);
-        
+
         // Should require at least 1 rule application
         assert!(dist.is_finite());
         if let SinglyCompact::Finite(d) = dist {
@@ -412,14 +687,99 @@ mod tests {
         // Expressions with no variables
         let target = lang.parse("(+ 5 6)").unwrap();
         let current = lang.parse("(+ 7 8)").unwrap();
-        
+
         let heuristic = AbelianPathHeuristic::new(&target, &trs, &arities);
         let dist = heuristic.lower_bound_dist(&current);
-        
+
         // With no variables, distance should be 0 (convention: max over empty set = 0)
         assert_eq!(dist, SinglyCompact::Finite(0));
     }
 
+    #[test]
+    fn test_ilp_heuristic_incremental() {
+        let lang = Language::default().add_symbol("+").add_symbol("*");
+        let mut arities_map = HashMap::new();
+        arities_map.insert(0, 2);
+        arities_map.insert(1, 2);
+        let arities = Arities::from(arities_map);
+
+        // Without rules only expressions with the same paths are at a finite distance
+        let trs = TermRewritingSystem::new(lang.clone(), vec![]);
+        let target = lang.parse("(+ $0 (+ $1 $0))").unwrap();
+        let heuristic = AbelianPathHeuristic::new(&target, &trs, &arities);
+
+        let parent = lang.parse("(+ $0 (* $1 $0))").unwrap();
+        let (parent_dist, parent_state) = heuristic.lower_bound_dist_with_state(&parent);
+        assert_eq!(parent_dist, SinglyCompact::Infinite);
+
+        let position = RewritePosition {
+            path: OwnedPath(vec![1]),
+            rule_index: 0,
+        };
+        let child = lang.parse("(+ $0 (+ $1 $0))").unwrap();
+        let (child_dist, child_state) =
+            heuristic.lower_bound_dist_after(&parent_state, &position, &child);
+        assert_eq!(child_dist, SinglyCompact::Finite(0));
+        assert_eq!(child_dist, heuristic.lower_bound_dist(&child));
+
+        let (_, scratch_state) = heuristic.lower_bound_dist_with_state(&child);
+        assert_eq!(child_state.paths.len(), scratch_state.paths.len());
+        assert!(
+            child_state
+                .paths
+                .iter()
+                .all(|path| scratch_state.paths.contains(path))
+        );
+    }
+
+    #[test]
+    fn test_ilp_heuristic_explain() {
+        let lang = Language::default()
+            .add_symbol("+")
+            .add_symbol("*")
+            .add_symbol("-");
+        let arities = Arities::from(HashMap::from([(0, 2), (1, 2), (2, 2)]));
+
+        let rules = rules!(lang;
+            "(+ $0 $1)" => "(* $0 $1)"
+        );
+        let trs = TermRewritingSystem::new(lang.clone(), rules);
+        let target = lang.parse("(* $0 $1)").unwrap();
+        let current = lang.parse("(- $0 $2)").unwrap();
+        let heuristic = AbelianPathHeuristic::new(&target, &trs, &arities);
+
+        let breakdown = heuristic.explain(&current);
+        assert_eq!(breakdown.bound(), heuristic.lower_bound_dist(&current));
+        let ids: Vec<_> = breakdown.variables.iter().map(|v| v.variable_id).collect();
+        assert_eq!(ids, vec![0, 1, 2]);
+        let infinite: Vec<_> = breakdown
+            .infinite_variables()
+            .map(|v| v.variable_id)
+            .collect();
+        assert_eq!(infinite, vec![0, 1]);
+
+        // `-` cannot be rewritten into `*`, so the only path to $0 is not in the lattice
+        let target_paths = &breakdown.variables[0].target_paths;
+        assert_eq!(target_paths.len(), 1);
+        assert_eq!(target_paths[0].position, OwnedPath(vec![0]));
+        assert_eq!(target_paths[0].current_paths.len(), 1);
+        assert!(!target_paths[0].current_paths[0].in_lattice);
+        // $1 does not occur in the expression and $2 does not occur in the goal
+        assert!(
+            breakdown.variables[1].target_paths[0]
+                .current_paths
+                .is_empty()
+        );
+        assert_eq!(breakdown.variables[2].bound, SinglyCompact::Finite(0));
+
+        assert_eq!(
+            breakdown.to_string(),
+            "$0: ∞\n  goal path at 0: ∞\n    path at 0: ∞ (not in the lattice of the rules)\n\
+             $1: ∞\n  goal path at 1: ∞ (no paths in the expression)\n\
+             $2: 0\n"
+        );
+    }
+
     #[test]
     fn test_heuristic_constructor() {
         let lang = Language::default().add_symbol("+");
@@ -432,12 +792,15 @@ mod tests {
         );
         let trs = TermRewritingSystem::new(lang.clone(), rules);
 
-        let constructor = AbelianPathHeuristicConstructor { arities };
+        let constructor = AbelianPathHeuristicConstructor {
+            arities,
+            ilp_config: IlpConfig::default(),
+        };
         let expr = lang.parse("(+ $0 $1)").unwrap();
-        
+
         let heuristic = constructor.construct(&expr, &trs);
         let dist = heuristic.lower_bound_dist(&expr);
-        
+
         assert_eq!(dist, SinglyCompact::Finite(0));
     }
 }
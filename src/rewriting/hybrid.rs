@@ -0,0 +1,401 @@
+//! Hybrid rewriting combining equality saturation with A* search.
+//!
+//! [`hybrid_rewrite`] first saturates a budgeted e-graph of the start expression.
+//! If the target is not represented in the class of the start expression once the budget
+//! is exhausted, the member of the class which the heuristic considers closest to the target
+//! is extracted and the remaining distance is searched for with [`a_star_rewrite`].
+//! The length of the found sequence bounds the distance from the extracted expression,
+//! and together with a sequence from the start expression to the extracted one,
+//! it bounds the distance from the start expression.
+
+use std::collections::HashMap;
+
+use crate::compact::SinglyCompact;
+use crate::language::{
+    expression::{MixedExpression, VarFreeExpression},
+    symbol::Symbol,
+};
+use crate::rewriting::egraph::{
+    Analysis, ClassId, DynEGraph, EGraph, Node, NodeId,
+    extraction::{Extractor, SimpleExtractor, TargetedExtractor, children_cost_sum},
+    matching::Matcher,
+    saturation::{
+        SaturationConfig, SaturationReport, scheduled_saturator::ScheduledSaturator,
+        scheduler::RoundRobinScheduler,
+    },
+};
+use crate::rewriting::heuristic::Heuristic;
+use crate::rewriting::rule::Rule;
+use crate::rewriting::search::{RewriteStep, a_star_rewrite, bfs_rewrite_limited};
+
+/// Configuration of [`hybrid_rewrite`].
+#[derive(Clone, Debug)]
+pub struct HybridConfig {
+    /// Limits of the saturation of the start expression
+    pub saturation: SaturationConfig,
+    /// Maximum number of expressions expanded by the search, see [`a_star_rewrite`],
+    /// and visited by the search for the sequence from the start expression
+    pub max_expansions: usize,
+}
+
+impl Default for HybridConfig {
+    fn default() -> Self {
+        Self {
+            saturation: SaturationConfig {
+                max_nodes: Some(10_000),
+                ..Default::default()
+            },
+            max_expansions: 10_000,
+        }
+    }
+}
+
+/// How [`hybrid_rewrite`] finished.
+#[derive(Clone, Debug)]
+pub enum HybridOutcome {
+    /// The target is represented in the class of the start expression
+    InEGraph,
+    /// The target was reached by the search from `intermediate`,
+    /// a member of the class of the start expression
+    Searched {
+        /// The member of the class of the start expression the search started from
+        intermediate: VarFreeExpression,
+        /// The rewrite sequence from `intermediate` to the target
+        steps: Vec<RewriteStep>,
+    },
+    /// The search from `intermediate` did not reach the target
+    NotFound {
+        /// The member of the class of the start expression the search started from
+        intermediate: VarFreeExpression,
+    },
+}
+
+/// Result of [`hybrid_rewrite`].
+pub struct HybridResult<A: Analysis> {
+    /// The saturated e-graph of the start expression
+    pub egraph: EGraph<A>,
+    /// How the rewriting finished
+    pub outcome: HybridOutcome,
+    /// Report of the saturation of the start expression
+    pub report: SaturationReport,
+    /// Length of a rewrite sequence from the start expression to the target, which bounds
+    /// the distance between them from above. The e-graph only shows that the intermediate
+    /// expression or the target can be reached, so the sequence reaching it is searched for
+    /// with [`bfs_rewrite_limited`]. `None` if the target was not reached or that search failed.
+    pub distance_bound: Option<usize>,
+}
+
+/// Rewrites `start` into `end` by saturation followed by A* search guided by `heuristic`,
+/// see the [module](self) documentation. Rules are applied in the round-robin order.
+pub fn hybrid_rewrite<A: Analysis>(
+    start: &VarFreeExpression,
+    end: &VarFreeExpression,
+    rules: &[Rule],
+    heuristic: &dyn Heuristic,
+    matcher: &dyn Matcher,
+    config: &HybridConfig,
+) -> HybridResult<A> {
+    let (mut egraph, start_class) = EGraph::<A>::from_expression_with_id(start.clone());
+    let report = ScheduledSaturator::new(Box::new(RoundRobinScheduler::new(rules.to_vec()))).run(
+        &mut egraph,
+        &config.saturation,
+        matcher,
+    );
+    let start_class = egraph.canonical_class(start_class);
+
+    // Length of a sequence from the start expression to `expression`
+    let prefix_length = |expression: &VarFreeExpression| {
+        bfs_rewrite_limited(
+            &start.to_expression(),
+            &expression.to_expression(),
+            rules,
+            usize::MAX,
+            config.max_expansions,
+        )
+        .map(|steps| steps.len())
+    };
+
+    if egraph.find_mixed_expression(&to_mixed(end)) == Some(start_class) {
+        return HybridResult {
+            egraph,
+            outcome: HybridOutcome::InEGraph,
+            report,
+            distance_bound: prefix_length(end),
+        };
+    }
+
+    let intermediate = candidates(&egraph, start_class, end, heuristic)
+        .into_iter()
+        .min_by_key(|candidate| heuristic.lower_bound_dist(&candidate.to_expression()))
+        .unwrap_or_else(|| start.clone());
+    let outcome = match a_star_rewrite(
+        &intermediate.to_expression(),
+        &end.to_expression(),
+        rules,
+        heuristic,
+        config.max_expansions,
+    ) {
+        Some(steps) => HybridOutcome::Searched {
+            intermediate,
+            steps,
+        },
+        None => HybridOutcome::NotFound { intermediate },
+    };
+
+    let distance_bound = match &outcome {
+        HybridOutcome::Searched {
+            intermediate,
+            steps,
+        } => prefix_length(intermediate).map(|length| length + steps.len()),
+        _ => None,
+    };
+
+    HybridResult {
+        egraph,
+        outcome,
+        report,
+        distance_bound,
+    }
+}
+
+/// Members of the class `class_id` greedily considered for continuing the search:
+/// the one closest to `end` in the tree edit distance, see [`TargetedExtractor`],
+/// and the one minimizing `heuristic`, see [`closest_members`].
+fn candidates(
+    egraph: &dyn DynEGraph,
+    class_id: ClassId,
+    end: &VarFreeExpression,
+    heuristic: &dyn Heuristic,
+) -> Vec<VarFreeExpression> {
+    let mut candidates: Vec<_> = TargetedExtractor::new(end.clone())
+        .extract(egraph, class_id)
+        .map(|result| result.winner().clone())
+        .into_iter()
+        .collect();
+
+    candidates.extend(
+        closest_members(egraph, heuristic)
+            .remove(&class_id)
+            .map(|(_, member)| member),
+    );
+    candidates
+}
+
+/// Members of all classes greedily minimizing `heuristic`, together with its estimates.
+///
+/// Starting from the smallest members, the member of every class is replaced by any of
+/// the nodes of the class applied to the members of its children, if `heuristic` considers
+/// the result closer, until no member changes or there were as many rounds as classes.
+fn closest_members(
+    egraph: &dyn DynEGraph,
+    heuristic: &dyn Heuristic,
+) -> HashMap<ClassId, (SinglyCompact<u32>, VarFreeExpression)> {
+    let class_ids: Vec<ClassId> = egraph
+        .dyn_classes()
+        .into_iter()
+        .map(|(&id, _)| id)
+        .collect();
+    let mut members: HashMap<_, _> = SimpleExtractor::<usize, _, _>::new(
+        |_| 1,
+        |symbol, costs| Some(1 + children_cost_sum(symbol, costs)?),
+    )
+    .extract_classes(egraph, class_ids.iter().copied())
+    .into_iter()
+    .map(|(class_id, result)| {
+        let member = result.winner().clone();
+        (
+            class_id,
+            (heuristic.lower_bound_dist(&member.to_expression()), member),
+        )
+    })
+    .collect();
+
+    for _ in 0..class_ids.len() {
+        let mut changed = false;
+        for &class_id in &class_ids {
+            for &node_id in egraph.dyn_class(class_id).iter_nodes() {
+                let Some(candidate) = instantiate_node(egraph, node_id, &members) else {
+                    continue;
+                };
+                let estimate = heuristic.lower_bound_dist(&candidate.to_expression());
+                if members
+                    .get(&class_id)
+                    .is_none_or(|(best, _)| estimate < *best)
+                {
+                    members.insert(class_id, (estimate, candidate));
+                    changed = true;
+                }
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    members
+}
+
+/// Applies the node `node_id` to the members of its children classes,
+/// if all of them have one.
+fn instantiate_node(
+    egraph: &dyn DynEGraph,
+    node_id: NodeId,
+    members: &HashMap<ClassId, (SinglyCompact<u32>, VarFreeExpression)>,
+) -> Option<VarFreeExpression> {
+    match egraph.node(node_id) {
+        Node::Literal(literal) => Some(VarFreeExpression::Literal(literal.clone())),
+        Node::Symbol(symbol) => Some(VarFreeExpression::Symbol(Symbol {
+            id: symbol.id,
+            children: symbol
+                .children
+                .iter()
+                .map(|&child| {
+                    members
+                        .get(&egraph.canonical_class(child))
+                        .map(|(_, member)| member.clone())
+                })
+                .collect::<Option<_>>()?,
+        })),
+    }
+}
+
+fn to_mixed(expression: &VarFreeExpression) -> MixedExpression {
+    match expression {
+        VarFreeExpression::Literal(literal) => MixedExpression::Literal(literal.clone()),
+        VarFreeExpression::Symbol(symbol) => MixedExpression::Symbol(Symbol {
+            id: symbol.id,
+            children: symbol.children.iter().map(to_mixed).collect(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compact::SinglyCompact;
+    use crate::language::{
+        Language,
+        expression::{AnyExpression, Expression},
+        symbol::SymbolId,
+        topology::expression_size,
+    };
+    use crate::macros::rules;
+    use crate::rewriting::egraph::{
+        matching::top_down::TopDownMatcher, saturation::SaturationConfig,
+    };
+    use crate::rewriting::heuristic::Heuristic;
+
+    use super::{HybridConfig, HybridOutcome, hybrid_rewrite};
+
+    /// Half of the difference of sizes, as the rules remove two nodes at a time
+    struct SizeHeuristic(u32);
+
+    impl Heuristic for SizeHeuristic {
+        fn lower_bound_dist(&self, expression: &Expression) -> SinglyCompact<u32> {
+            SinglyCompact::Finite(expression_size(expression).abs_diff(self.0) / 2)
+        }
+    }
+
+    /// Number of occurrences of a symbol
+    struct SymbolCount(SymbolId);
+
+    impl Heuristic for SymbolCount {
+        fn lower_bound_dist(&self, expression: &Expression) -> SinglyCompact<u32> {
+            let count = expression
+                .iter_subexpressions()
+                .filter(|subexpression| {
+                    matches!(subexpression, Expression::Symbol(symbol) if symbol.id == self.0)
+                })
+                .count();
+            SinglyCompact::Finite(count as u32)
+        }
+    }
+
+    #[test]
+    fn finds_target_in_egraph() {
+        let lang = Language::simple_math();
+        let rules = rules!(lang;
+            "(* $0 2)" => "(<< $0 1)",
+        );
+        let start = lang.parse_no_vars("(+ (* 3 2) 4)").unwrap();
+        let end = lang.parse_no_vars("(+ (<< 3 1) 4)").unwrap();
+
+        let result = hybrid_rewrite::<()>(
+            &start,
+            &end,
+            &rules,
+            &SizeHeuristic(5),
+            &TopDownMatcher,
+            &HybridConfig::default(),
+        );
+        assert!(matches!(result.outcome, HybridOutcome::InEGraph));
+        assert_eq!(result.distance_bound, Some(1));
+    }
+
+    #[test]
+    fn continues_with_search() {
+        let lang = Language::simple_math();
+        let rules = rules!(lang;
+            "(+ $0 0)" => "$0",
+            "(* $0 1)" => "$0",
+        );
+        let start = lang.parse_no_vars("(+ (* 5 1) 0)").unwrap();
+        let end = lang.parse_no_vars("5").unwrap();
+        let config = HybridConfig {
+            saturation: SaturationConfig {
+                max_applications: Some(1),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let result = hybrid_rewrite::<()>(
+            &start,
+            &end,
+            &rules,
+            &SizeHeuristic(1),
+            &TopDownMatcher,
+            &config,
+        );
+        assert_eq!(result.report.applications, 1);
+        let HybridOutcome::Searched {
+            intermediate,
+            steps,
+        } = result.outcome
+        else {
+            panic!("Expected a search from an intermediate expression");
+        };
+        assert_eq!(intermediate, lang.parse_no_vars("(* 5 1)").unwrap());
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0].expression, end.to_expression());
+        assert_eq!(result.distance_bound, Some(2));
+    }
+
+    #[test]
+    fn minimizes_heuristic_over_classes() {
+        let lang = Language::simple_math();
+        let rules = rules!(lang;
+            "(* $0 2)" => "(<< (+ $0 0) 1)",
+        );
+        let start = lang.parse_no_vars("(sin (* 3 2))").unwrap();
+        let end = lang.parse_no_vars("(cos 1)").unwrap();
+
+        // The larger member of the class of the argument has fewer multiplications
+        let result = hybrid_rewrite::<()>(
+            &start,
+            &end,
+            &rules,
+            &SymbolCount(lang.get_id("*")),
+            &TopDownMatcher,
+            &HybridConfig::default(),
+        );
+        let HybridOutcome::NotFound { intermediate } = result.outcome else {
+            panic!("Expected the search to fail");
+        };
+        assert_eq!(
+            intermediate,
+            lang.parse_no_vars("(sin (<< (+ 3 0) 1))").unwrap()
+        );
+        assert_eq!(result.distance_bound, None);
+    }
+}
@@ -2,9 +2,224 @@
 //!
 //! This module provides utilities for creating and solving ILP problems
 //! using the `good_lp` library.
+//! Solvers can get stuck on pathological problems, so [`solve_ilp_problem`] solves
+//! the problem of [`create_ilp_problem`] with a time limit given by an [`IlpConfig`].
+//!
+//! The problems of [`solve_ilp_problem`] are solved by an [`IlpSolver`] chosen at runtime
+//! with an [`IlpBackend`]. Backends other than the default solver of `good_lp` are enabled
+//! by cargo features: `cbc` (enabled by default) for CBC, which needs the CBC library
+//! installed, and `microlp` for the pure Rust microlp solver.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use good_lp::*;
 use nalgebra::{DMatrix, DVector};
+use serde::{Deserialize, Serialize};
+
+use crate::metrics;
+
+/// What [`solve_ilp_problem`] reports when the solver does not finish before the deadline.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimeoutPolicy {
+    /// Report no bound, which heuristics treat as an infinite distance
+    Infinite,
+    /// Report the optimum of the linear relaxation rounded up,
+    /// which is a lower bound on the optimum of the ILP
+    #[default]
+    BestBound,
+}
+
+/// Solvers of ILPs selectable at runtime, see [`IlpBackend::solver`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IlpBackend {
+    /// The default solver of `good_lp`, which is CBC if the `cbc` feature is enabled
+    #[default]
+    Default,
+    /// CBC, respecting [`IlpConfig::threads`], [`IlpConfig::log`] and [`IlpConfig::time_limit`]
+    #[cfg(feature = "cbc")]
+    Cbc,
+    /// The pure Rust microlp solver
+    #[cfg(feature = "microlp")]
+    MicroLp,
+}
+
+impl IlpBackend {
+    /// Returns the solver of the backend.
+    pub fn solver(&self) -> Arc<dyn IlpSolver> {
+        match self {
+            #[cfg(feature = "cbc")]
+            IlpBackend::Default => Arc::new(CbcSolver),
+            #[cfg(not(feature = "cbc"))]
+            IlpBackend::Default => Arc::new(GoodLpSolver(default_solver)),
+            #[cfg(feature = "cbc")]
+            IlpBackend::Cbc => Arc::new(CbcSolver),
+            #[cfg(feature = "microlp")]
+            IlpBackend::MicroLp => Arc::new(GoodLpSolver(microlp)),
+        }
+    }
+}
+
+/// Configuration of [`solve_ilp_problem`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IlpConfig {
+    /// Solver of the problems
+    pub backend: IlpBackend,
+    /// Number of threads used by solvers supporting parallelism, their default if `None`
+    pub threads: Option<usize>,
+    /// Maximum time the solver may spend on a problem, no limit if `None`.
+    /// Solvers without time limits, e.g. microlp, ignore it
+    pub time_limit: Option<Duration>,
+    /// What to report if `time_limit` is exceeded
+    pub on_timeout: TimeoutPolicy,
+    /// Print the size, the outcome and the duration of every solved problem
+    /// to the standard error output
+    pub log: bool,
+}
+
+impl IlpConfig {
+    /// Sets the time limit of a single problem.
+    pub fn with_time_limit(mut self, time_limit: Duration) -> Self {
+        self.time_limit = Some(time_limit);
+        self
+    }
+
+    /// Sets what is reported for problems exceeding the time limit.
+    pub fn with_timeout_policy(mut self, on_timeout: TimeoutPolicy) -> Self {
+        self.on_timeout = on_timeout;
+        self
+    }
+
+    /// Sets the solver of the problems.
+    pub fn with_backend(mut self, backend: IlpBackend) -> Self {
+        self.backend = backend;
+        self
+    }
+}
+
+/// A solver of the problems of [`solve_ilp_problem`].
+pub trait IlpSolver: Send + Sync {
+    /// Human readable name of the solver
+    fn name(&self) -> &'static str;
+
+    /// Minimizes 1^T x subject to Ax = d, x >= 0, x integer, as in [`create_ilp_problem`],
+    /// stopping after the time limit of `config` if the solver supports one.
+    /// Returns [`IlpOutcome::Optimal`], [`IlpOutcome::Infeasible`] or, if the solver was stopped,
+    /// [`IlpOutcome::TimedOut`] without a bound.
+    fn minimize_sum(&self, a: &DMatrix<i32>, d: &DVector<i32>, config: &IlpConfig) -> IlpOutcome;
+
+    /// Returns the optimum of the linear relaxation of the problem of [`IlpSolver::minimize_sum`]
+    /// rounded up, or `None` if the relaxation is infeasible.
+    fn relaxation_bound(&self, a: &DMatrix<i32>, d: &DVector<i32>) -> Option<u32>;
+}
+
+/// Any solver of `good_lp`, ignoring solver-specific settings of [`IlpConfig`],
+/// including the time limit.
+#[derive(Clone, Copy, Debug)]
+pub struct GoodLpSolver<S>(pub S);
+
+impl<S: Solver + Clone + Send + Sync> IlpSolver for GoodLpSolver<S> {
+    fn name(&self) -> &'static str {
+        S::name()
+    }
+
+    fn minimize_sum(&self, a: &DMatrix<i32>, d: &DVector<i32>, _: &IlpConfig) -> IlpOutcome {
+        minimize_sum(a, d, self.0.clone())
+    }
+
+    fn relaxation_bound(&self, a: &DMatrix<i32>, d: &DVector<i32>) -> Option<u32> {
+        relaxation_bound(a, d, self.0.clone())
+    }
+}
+
+/// CBC with the number of threads, logging and the time limit of [`IlpConfig`].
+///
+/// Problems stopped by the time limit are reported as timed out even if CBC found a solution,
+/// as the solution need not be optimal and is therefore not a lower bound.
+#[cfg(feature = "cbc")]
+#[derive(Clone, Copy, Debug)]
+pub struct CbcSolver;
+
+#[cfg(feature = "cbc")]
+impl IlpSolver for CbcSolver {
+    fn name(&self) -> &'static str {
+        "Coin Cbc"
+    }
+
+    fn minimize_sum(&self, a: &DMatrix<i32>, d: &DVector<i32>, config: &IlpConfig) -> IlpOutcome {
+        minimize_sum(a, d, |problem: good_lp::variable::UnsolvedProblem| {
+            let mut model = coin_cbc(problem);
+            if let Some(threads) = config.threads {
+                model.set_parameter("threads", &threads.to_string());
+            }
+            model.set_parameter("logLevel", if config.log { "1" } else { "0" });
+            if let Some(time_limit) = config.time_limit {
+                model.set_parameter("sec", &time_limit.as_secs_f64().to_string());
+            }
+            model
+        })
+    }
+
+    fn relaxation_bound(&self, a: &DMatrix<i32>, d: &DVector<i32>) -> Option<u32> {
+        relaxation_bound(a, d, coin_cbc)
+    }
+}
+
+/// The outcome of [`solve_ilp_problem`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IlpOutcome {
+    /// The optimal objective value
+    Optimal(u32),
+    /// The problem has no solution, or the solver failed
+    Infeasible,
+    /// The solver did not finish in time, `bound` is a lower bound on the optimal
+    /// objective value given by the [`TimeoutPolicy`], `None` if there is none
+    /// or the problem is infeasible
+    TimedOut { bound: Option<u32> },
+}
+
+impl IlpOutcome {
+    /// Returns the optimal objective value, or the bound if the solver did not finish in time.
+    pub fn value(&self) -> Option<u32> {
+        match self {
+            IlpOutcome::Optimal(value) => Some(*value),
+            IlpOutcome::Infeasible => None,
+            IlpOutcome::TimedOut { bound } => *bound,
+        }
+    }
+}
+
+/// Statistics of the problems solved by [`solve_ilp_problem`], e.g. by a heuristic.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IlpStatistics {
+    /// Number of problems solved to optimality
+    pub optimal: usize,
+    /// Number of infeasible problems
+    pub infeasible: usize,
+    /// Number of problems exceeding the time limit
+    pub timed_out: usize,
+    /// Total time spent solving, including waiting for problems exceeding the time limit
+    pub total_time: Duration,
+}
+
+impl IlpStatistics {
+    /// Records a problem solved in `time`.
+    pub fn record(&mut self, outcome: &IlpOutcome, time: Duration) {
+        match outcome {
+            IlpOutcome::Optimal(_) => self.optimal += 1,
+            IlpOutcome::Infeasible => self.infeasible += 1,
+            IlpOutcome::TimedOut { .. } => self.timed_out += 1,
+        }
+        self.total_time += time;
+    }
+
+    /// Returns the total number of recorded problems.
+    pub fn problems(&self) -> usize {
+        self.optimal + self.infeasible + self.timed_out
+    }
+}
 
 /// Creates an ILP problem for the given matrix A and vector d.
 ///
@@ -79,7 +294,180 @@ pub fn create_ilp_problem<S: Solver>(
     let objective: Expression = x.iter().copied().sum();
 
     // Start building the problem
-    let mut problem = vars.minimise(objective).using(solver);
+    let problem = vars.minimise(objective).using(solver);
+
+    (with_equality_constraints(problem, a, d, &x), x)
+}
+
+/// Solves the problem of [`create_ilp_problem`], minimizing 1^T x subject to Ax = d, x >= 0,
+/// x integer, by the solver of the backend of `config` with its time limit.
+///
+/// The time limit is enforced by the solver itself, see [`IlpConfig::time_limit`],
+/// and the bound of problems exceeding it is given by [`IlpConfig::on_timeout`].
+///
+/// # Returns
+///
+/// Returns the outcome of the solver, to be recorded in [`IlpStatistics`], and the time it took
+///
+/// # Panics
+///
+/// Panics if the dimensions of A and d don't match (A.nrows() must equal d.len()).
+pub fn solve_ilp_problem(
+    a: &DMatrix<i32>,
+    d: &DVector<i32>,
+    config: &IlpConfig,
+) -> (IlpOutcome, Duration) {
+    let start = Instant::now();
+    let solver = config.backend.solver();
+    let outcome = match solver.minimize_sum(a, d, config) {
+        IlpOutcome::TimedOut { .. } => IlpOutcome::TimedOut {
+            bound: match config.on_timeout {
+                TimeoutPolicy::Infinite => None,
+                TimeoutPolicy::BestBound => solver.relaxation_bound(a, d),
+            },
+        },
+        outcome => outcome,
+    };
+    let time = start.elapsed();
+
+    if config.log {
+        eprintln!(
+            "{} ILP with {} constraints and {} variables: {outcome:?} in {time:?}",
+            solver.name(),
+            a.nrows(),
+            a.ncols()
+        );
+    }
+
+    (outcome, time)
+}
+
+/// Solves the problem of [`create_ilp_problem`], reporting solutions found before
+/// the time limit of the model, if any, as [`IlpOutcome::TimedOut`].
+fn minimize_sum<S: Solver>(a: &DMatrix<i32>, d: &DVector<i32>, solver: S) -> IlpOutcome {
+    let (model, vars) = create_ilp_problem(a, d, solver);
+    match metrics::ILP_SOLVES.time(|| model.solve()) {
+        Ok(solution) if matches!(solution.status(), SolutionStatus::TimeLimit) => {
+            IlpOutcome::TimedOut { bound: None }
+        }
+        Ok(solution) => {
+            let objective: f64 = vars.iter().map(|&v| solution.value(v)).sum();
+            IlpOutcome::Optimal(objective.round() as u32)
+        }
+        Err(_) => IlpOutcome::Infeasible,
+    }
+}
+
+/// Returns the optimum of the linear relaxation of the problem of [`create_ilp_problem`]
+/// rounded up, or `None` if the relaxation is infeasible.
+fn relaxation_bound<S: Solver>(a: &DMatrix<i32>, d: &DVector<i32>, solver: S) -> Option<u32> {
+    let mut vars = ProblemVariables::new();
+    let x: Vec<Variable> = (0..a.ncols())
+        .map(|_| vars.add(variable().min(0)))
+        .collect();
+    let objective: Expression = x.iter().copied().sum();
+    let problem = vars.minimise(objective).using(solver);
+
+    let model = with_equality_constraints(problem, a, d, &x);
+    let solution = metrics::ILP_SOLVES.time(|| model.solve()).ok()?;
+    let objective: f64 = x.iter().map(|&v| solution.value(v)).sum();
+    // Tolerate rounding errors of the solver
+    Some((objective - 1e-6).ceil().max(0.0) as u32)
+}
+
+/// Creates an ILP feasibility problem for the given matrix A and vector d.
+///
+/// The problem is to find any integer x such that Ax = d, additionally with x >= 0
+/// if `nonnegative` is `true`. The objective is constant, so solvers stop
+/// at the first feasible solution.
+///
+/// # Arguments
+///
+/// * `a` - The constraint matrix A (dimensions: m x n)
+/// * `d` - The right-hand side vector d (dimensions: m)
+/// * `nonnegative` - Whether x is constrained to be nonnegative
+/// * `solver` - The solver to use for solving the problem
+///
+/// # Returns
+///
+/// Returns a tuple `(model, variables)` as [`create_ilp_problem`] does
+///
+/// # Panics
+///
+/// Panics if the dimensions of A and d don't match (A.nrows() must equal d.len()).
+pub fn create_ilp_feasibility_problem<S: Solver>(
+    a: &DMatrix<i32>,
+    d: &DVector<i32>,
+    nonnegative: bool,
+    solver: S,
+) -> (S::Model, Vec<Variable>) {
+    let definition = variable().integer();
+    let definition = if nonnegative {
+        definition.min(0)
+    } else {
+        definition
+    };
+    feasibility_problem(a, d, definition, solver)
+}
+
+/// Creates the linear relaxation of the feasibility problem of [`create_ilp_feasibility_problem`],
+/// i.e. the problem of finding any real x such that Ax = d, with x >= 0 if `nonnegative` is set.
+///
+/// Infeasibility of the relaxation implies infeasibility of the integer problem,
+/// and linear problems are much cheaper to solve.
+///
+/// # Panics
+///
+/// Panics if the dimensions of A and d don't match (A.nrows() must equal d.len()).
+pub fn create_lp_feasibility_problem<S: Solver>(
+    a: &DMatrix<i32>,
+    d: &DVector<i32>,
+    nonnegative: bool,
+    solver: S,
+) -> (S::Model, Vec<Variable>) {
+    let definition = if nonnegative {
+        variable().min(0)
+    } else {
+        variable()
+    };
+    feasibility_problem(a, d, definition, solver)
+}
+
+/// Creates a problem with a constant objective, constraints Ax = d
+/// and variables x defined by `definition`.
+fn feasibility_problem<S: Solver>(
+    a: &DMatrix<i32>,
+    d: &DVector<i32>,
+    definition: VariableDefinition,
+    solver: S,
+) -> (S::Model, Vec<Variable>) {
+    assert_eq!(
+        a.nrows(),
+        d.len(),
+        "Matrix A has {} rows but vector d has {} elements. Dimensions must match.",
+        a.nrows(),
+        d.len()
+    );
+
+    let mut vars = ProblemVariables::new();
+    let x: Vec<Variable> = (0..a.ncols())
+        .map(|_| vars.add(definition.clone()))
+        .collect();
+
+    let problem = vars.minimise(Expression::from(0)).using(solver);
+
+    (with_equality_constraints(problem, a, d, &x), x)
+}
+
+/// Adds constraints Ax = d to `problem` with variables `x`.
+fn with_equality_constraints<M: SolverModel>(
+    mut problem: M,
+    a: &DMatrix<i32>,
+    d: &DVector<i32>,
+    x: &[Variable],
+) -> M {
+    let m = a.nrows();
+    let n = a.ncols();
 
     // Add constraints: Ax = d
     for i in 0..m {
@@ -96,7 +484,7 @@ pub fn create_ilp_problem<S: Solver>(
         problem = problem.with(constraint!(constraint_expr == d[i]));
     }
 
-    (problem, x)
+    problem
 }
 
 #[cfg(test)]
@@ -104,6 +492,46 @@ mod tests {
     use super::*;
     use nalgebra::{DMatrix, DVector};
 
+    #[test]
+    fn test_solve_ilp_problem_with_time_limit() {
+        let a = DMatrix::from_row_slice(1, 3, &[1, 2, 3]);
+        let d = DVector::from_vec(vec![12]);
+        let config = IlpConfig::default().with_time_limit(Duration::from_secs(60));
+
+        let (outcome, time) = solve_ilp_problem(&a, &d, &config);
+        assert_eq!(outcome, IlpOutcome::Optimal(4));
+
+        let mut statistics = IlpStatistics::default();
+        statistics.record(&outcome, time);
+        let infeasible = DVector::from_vec(vec![-1]);
+        let (outcome, time) = solve_ilp_problem(&a, &infeasible, &config);
+        assert_eq!(outcome, IlpOutcome::Infeasible);
+        statistics.record(&outcome, time);
+        assert_eq!((statistics.optimal, statistics.infeasible), (1, 1));
+        assert_eq!(statistics.problems(), 2);
+    }
+
+    #[test]
+    fn test_ilp_backend_from_config() {
+        let config: IlpConfig =
+            serde_json::from_str(r#"{ "backend": "default", "threads": 2 }"#).unwrap();
+        assert_eq!(config.backend, IlpBackend::Default);
+        assert_eq!(config.threads, Some(2));
+        assert_eq!(config.backend.solver().name(), solver_name(default_solver));
+    }
+
+    #[test]
+    fn test_relaxation_bound() {
+        // The relaxation reaches 7/3 with x3 = 7/3, while the ILP needs x2 = 2, x3 = 1
+        let a = DMatrix::from_row_slice(1, 3, &[1, 2, 3]);
+        let d = DVector::from_vec(vec![7]);
+        assert_eq!(relaxation_bound(&a, &d, default_solver), Some(3));
+        assert_eq!(
+            relaxation_bound(&a, &DVector::from_vec(vec![-1]), default_solver),
+            None
+        );
+    }
+
     #[test]
     fn test_create_ilp_problem_simple() {
         // Simple problem: minimize x1 + x2 subject to x1 + x2 = 5
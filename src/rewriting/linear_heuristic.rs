@@ -0,0 +1,406 @@
+//! Learned heuristics combining cheap features of expressions linearly.
+//!
+//! A [`LinearFeatureHeuristic`] estimates the rewrite distance from an expression `e`
+//! to a target `e'` as a weighted sum of features of the pair:
+//! - `bias`, always 1
+//! - `size` and `depth`, absolute differences of sizes and depths of `e` and `e'`
+//! - `symbol:<name>`, absolute difference of the numbers of occurrences of a symbol
+//! - `abelian_path_norm`, like the formula of [`AbelianPathHeuristic`](super::heuristic::AbelianPathHeuristic),
+//!   but with the ILP replaced by the L1 norm of the difference of abelianized paths
+//!
+//! The weights are usually fitted by least squares to a dataset of pairs with known
+//! distances, such as the ones written by the `dataset_gen` binary, see [`LinearWeights::fit`].
+//! Unlike the ILP heuristic, the estimates are not guaranteed to be lower bounds.
+
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use nalgebra::{DMatrix, DVector};
+use serde::{Deserialize, Serialize};
+
+use crate::compact::SinglyCompact;
+use crate::language::{
+    Language,
+    arities::Arities,
+    expression::{Expression, VariableId},
+    topology::expression_size,
+};
+use crate::rewriting::{
+    heuristic::{Heuristic, HeuristicConstructor},
+    strings::{PathAbelianVector, SparseAbelianVector, StringRewritingSystem},
+    system::TermRewritingSystem,
+};
+use crate::utils::json::{load_json, save_json};
+
+/// Singular values below this fraction of the largest one are ignored when fitting
+const FIT_EPSILON: f64 = 1e-9;
+
+/// A line of a distance-labelled dataset, as written by the `dataset_gen` binary.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DistanceRecord {
+    /// Start expression
+    pub from: String,
+    /// Goal expression
+    pub to: String,
+    /// Number of random rewrites applied to `from` to obtain `to`
+    pub applications: usize,
+    /// Shortest rewrite distance from `from` to `to`, or `None` if it is unknown
+    pub distance: Option<usize>,
+    /// Values of heuristics estimating the distance by their names, `None` if infinite
+    pub heuristics: BTreeMap<String, Option<u32>>,
+}
+
+/// A pair of expressions with a known rewrite distance.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DistanceSample {
+    /// Start expression
+    pub from: Expression,
+    /// Goal expression
+    pub to: Expression,
+    /// Shortest rewrite distance from `from` to `to`
+    pub distance: usize,
+}
+
+/// Loads a JSONL file of [`DistanceRecord`]s, parsing their expressions in `language`.
+/// Records with unknown distances are skipped.
+pub fn load_distance_dataset<P: AsRef<Path>>(
+    path: P,
+    language: &Language,
+) -> Result<Vec<DistanceSample>, Box<dyn Error>> {
+    let mut samples = Vec::new();
+    for line in fs::read_to_string(path)?.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: DistanceRecord = serde_json::from_str(line)?;
+        if let Some(distance) = record.distance {
+            samples.push(DistanceSample {
+                from: language.parse(&record.from)?,
+                to: language.parse(&record.to)?,
+                distance,
+            });
+        }
+    }
+    Ok(samples)
+}
+
+/// Computes the features of pairs of expressions, see the [module](self) documentation.
+pub struct FeatureExtractor {
+    srs: StringRewritingSystem,
+}
+
+/// Features of an expression which do not depend on the other expression of a pair.
+struct ExpressionFeatures {
+    size: u32,
+    depth: u32,
+    symbols: SparseAbelianVector,
+    paths: HashMap<VariableId, Vec<SparseAbelianVector>>,
+}
+
+impl FeatureExtractor {
+    /// Creates an extractor for expressions of `trs`, with paths abelianized
+    /// according to `arities`.
+    pub fn new(trs: &TermRewritingSystem, arities: &Arities) -> Self {
+        Self {
+            srs: StringRewritingSystem::from_trs(trs, arities),
+        }
+    }
+
+    /// Names of the features, in the order in which they are returned by [`Self::features`].
+    pub fn feature_names(&self) -> Vec<String> {
+        let language = self.srs.language();
+        ["bias", "size", "depth", "abelian_path_norm"]
+            .into_iter()
+            .map(String::from)
+            .chain(
+                (0..language.symbol_count())
+                    .map(|id| format!("symbol:{}", language.get_symbol(id))),
+            )
+            .collect()
+    }
+
+    /// Computes the features of the pair of `expression` and `target`.
+    pub fn features(&self, expression: &Expression, target: &Expression) -> Vec<f64> {
+        self.pair_features(
+            &self.expression_features(expression),
+            &self.expression_features(target),
+        )
+    }
+
+    fn expression_features(&self, expression: &Expression) -> ExpressionFeatures {
+        let mut paths: HashMap<_, Vec<_>> = HashMap::new();
+        for PathAbelianVector {
+            vector,
+            variable_id,
+            ..
+        } in self.srs.path_abelian_vectors_to_variables(expression)
+        {
+            paths.entry(variable_id).or_default().push(vector);
+        }
+
+        ExpressionFeatures {
+            size: expression_size(expression),
+            depth: depth(expression),
+            symbols: SparseAbelianVector::from_expression(expression),
+            paths,
+        }
+    }
+
+    fn pair_features(&self, current: &ExpressionFeatures, target: &ExpressionFeatures) -> Vec<f64> {
+        let symbols = &target.symbols - &current.symbols;
+        let mut features = vec![
+            1.0,
+            current.size.abs_diff(target.size) as f64,
+            current.depth.abs_diff(target.depth) as f64,
+            abelian_path_norm(current, target) as f64,
+        ];
+        features.extend(
+            (0..self.srs.language().symbol_count()).map(|id| symbols.get(id).unsigned_abs() as f64),
+        );
+        features
+    }
+}
+
+/// Maximum over variables and paths to them in `target` of the minimum over paths
+/// to the same variable in `current` of the L1 norms of differences of the paths.
+/// Paths with no counterparts in `current` are compared with the empty path.
+fn abelian_path_norm(current: &ExpressionFeatures, target: &ExpressionFeatures) -> u32 {
+    let norm =
+        |vector: &SparseAbelianVector| vector.iter().map(|(_, count)| count.unsigned_abs()).sum();
+
+    target
+        .paths
+        .iter()
+        .flat_map(|(variable_id, target_paths)| {
+            let current_paths = current.paths.get(variable_id);
+            target_paths.iter().map(move |target_path| {
+                current_paths
+                    .and_then(|paths| paths.iter().map(|path| norm(&(target_path - path))).min())
+                    .unwrap_or_else(|| norm(target_path))
+            })
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+fn depth(expression: &Expression) -> u32 {
+    match expression {
+        Expression::Symbol(symbol) => 1 + symbol.children.iter().map(depth).max().unwrap_or(0),
+        _ => 1,
+    }
+}
+
+/// Weights of the features of a [`LinearFeatureHeuristic`] by their names.
+/// Features without weights are ignored.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LinearWeights {
+    /// Weights by names of features, see [`FeatureExtractor::feature_names`]
+    pub weights: BTreeMap<String, f64>,
+}
+
+impl LinearWeights {
+    /// Loads weights from a JSON file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        load_json(path)
+    }
+
+    /// Saves the weights to a JSON file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        save_json(self, path)
+    }
+
+    /// Fits the weights minimizing the squared errors of the estimated distances of `samples`.
+    /// If the features are linearly dependent, the weights with the smallest norm are chosen.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the least squares problem cannot be solved.
+    pub fn fit(
+        samples: &[DistanceSample],
+        extractor: &FeatureExtractor,
+    ) -> Result<Self, Box<dyn Error>> {
+        let names = extractor.feature_names();
+        if samples.is_empty() {
+            return Ok(Self::default());
+        }
+
+        let rows: Vec<_> = samples
+            .iter()
+            .flat_map(|sample| extractor.features(&sample.from, &sample.to))
+            .collect();
+        let features = DMatrix::from_row_slice(samples.len(), names.len(), &rows);
+        let distances = DVector::from_iterator(
+            samples.len(),
+            samples.iter().map(|sample| sample.distance as f64),
+        );
+
+        let svd = features.svd(true, true);
+        let epsilon = FIT_EPSILON * svd.singular_values.max();
+        let weights = svd.solve(&distances, epsilon)?;
+
+        Ok(Self {
+            weights: names.into_iter().zip(weights.iter().copied()).collect(),
+        })
+    }
+
+    /// Weights in the order of [`FeatureExtractor::feature_names`].
+    fn aligned(&self, extractor: &FeatureExtractor) -> Vec<f64> {
+        extractor
+            .feature_names()
+            .iter()
+            .map(|name| self.weights.get(name).copied().unwrap_or(0.0))
+            .collect()
+    }
+}
+
+/// A heuristic estimating distances to a target expression with a linear model of features
+/// of expressions, see the [module](self) documentation.
+pub struct LinearFeatureHeuristic {
+    extractor: FeatureExtractor,
+    target: ExpressionFeatures,
+    weights: Vec<f64>,
+}
+
+impl LinearFeatureHeuristic {
+    /// Creates a heuristic estimating distances to `target_expr` in `trs`, with paths
+    /// abelianized according to `arities` and features weighted by `weights`.
+    pub fn new(
+        target_expr: &Expression,
+        trs: &TermRewritingSystem,
+        arities: &Arities,
+        weights: &LinearWeights,
+    ) -> Self {
+        let extractor = FeatureExtractor::new(trs, arities);
+        Self {
+            target: extractor.expression_features(target_expr),
+            weights: weights.aligned(&extractor),
+            extractor,
+        }
+    }
+
+    /// Returns the estimated distance before rounding.
+    pub fn estimate(&self, expression: &Expression) -> f64 {
+        self.extractor
+            .pair_features(
+                &self.extractor.expression_features(expression),
+                &self.target,
+            )
+            .iter()
+            .zip(&self.weights)
+            .map(|(feature, weight)| feature * weight)
+            .sum()
+    }
+}
+
+impl Heuristic for LinearFeatureHeuristic {
+    fn lower_bound_dist(&self, expression: &Expression) -> SinglyCompact<u32> {
+        SinglyCompact::Finite(self.estimate(expression).max(0.0).round() as u32)
+    }
+}
+
+/// Constructor of [`LinearFeatureHeuristic`]s with fixed weights.
+pub struct LinearFeatureHeuristicConstructor {
+    /// Arities for symbols in the language
+    pub arities: Arities,
+    /// Weights of the features of all constructed heuristics
+    pub weights: LinearWeights,
+}
+
+impl HeuristicConstructor for LinearFeatureHeuristicConstructor {
+    fn construct(&self, expression: &Expression, trs: &TermRewritingSystem) -> Box<dyn Heuristic> {
+        Box::new(LinearFeatureHeuristic::new(
+            expression,
+            trs,
+            &self.arities,
+            &self.weights,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::compact::SinglyCompact;
+    use crate::language::{Language, arities::Arities};
+    use crate::macros::rules;
+    use crate::rewriting::heuristic::HeuristicConstructor;
+    use crate::rewriting::system::TermRewritingSystem;
+
+    use super::{
+        DistanceSample, FeatureExtractor, LinearFeatureHeuristicConstructor, LinearWeights,
+    };
+
+    fn system() -> (TermRewritingSystem, Arities) {
+        let lang = Language::default().add_symbol("+").add_symbol("*");
+        let rules = rules!(lang;
+            "(+ $0 0)" => "$0",
+            "(* $0 1)" => "$0",
+        );
+        let arities = Arities::from(HashMap::from([(0, 2), (1, 2)]));
+        (TermRewritingSystem::new(lang, rules), arities)
+    }
+
+    #[test]
+    fn features() {
+        let (trs, arities) = system();
+        let lang = trs.language();
+        let extractor = FeatureExtractor::new(&trs, &arities);
+
+        assert_eq!(
+            extractor.feature_names(),
+            [
+                "bias",
+                "size",
+                "depth",
+                "abelian_path_norm",
+                "symbol:+",
+                "symbol:*"
+            ]
+        );
+        let features = extractor.features(
+            &lang.parse("(+ (* $0 1) 0)").unwrap(),
+            &lang.parse("$0").unwrap(),
+        );
+        assert_eq!(features, [1.0, 4.0, 2.0, 2.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn fits_distances() {
+        let (trs, arities) = system();
+        let lang = trs.language();
+        let samples: Vec<_> = [
+            ("$0", "$0", 0),
+            ("(+ $0 0)", "$0", 1),
+            ("(* $0 1)", "$0", 1),
+            ("(+ (* $0 1) 0)", "$0", 2),
+            ("(* (+ (* $0 1) 0) 1)", "$0", 3),
+            ("(+ $1 (* $0 1))", "(+ $1 $0)", 1),
+        ]
+        .into_iter()
+        .map(|(from, to, distance)| DistanceSample {
+            from: lang.parse(from).unwrap(),
+            to: lang.parse(to).unwrap(),
+            distance,
+        })
+        .collect();
+
+        let weights = LinearWeights::fit(&samples, &FeatureExtractor::new(&trs, &arities)).unwrap();
+        let constructor = LinearFeatureHeuristicConstructor { arities, weights };
+        for sample in &samples {
+            let heuristic = constructor.construct(&sample.to, &trs);
+            assert_eq!(
+                heuristic.lower_bound_dist(&sample.from),
+                SinglyCompact::Finite(sample.distance as u32)
+            );
+        }
+
+        let path = std::env::temp_dir().join(format!("weights-{}.json", std::process::id()));
+        constructor.weights.save(&path).unwrap();
+        let loaded = LinearWeights::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), constructor.weights);
+    }
+}
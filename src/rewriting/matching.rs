@@ -2,15 +2,17 @@
 //!
 //! This module provides pattern matching capabilities for both variable-free
 //! expressions and expressions with variables, allowing patterns with variables
-//! to be matched against concrete expressions.
+//! to be matched against concrete expressions. Matching of expressions with variables
+//! can respect the scopes of variables bound by binder symbols, see
+//! [`Expression::try_match_expression_with_binders`].
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 use itertools::Itertools;
 
 use crate::language::{
     expression::{Expression, VarFreeExpression, VariableId},
-    symbol::Symbol,
+    symbol::{Symbol, SymbolId},
 };
 
 /// A pattern match result for variable-free expressions.
@@ -29,6 +31,9 @@ pub struct Match<'e> {
 #[derive(Clone, Debug, Default)]
 pub struct ExpressionMatch {
     substitutions: HashMap<VariableId, Expression>,
+    /// Variables bound in the matched expression which occur free in substitutions of pattern
+    /// variables, with the pattern variables matching their binders
+    captures: HashMap<VariableId, BTreeMap<VariableId, VariableId>>,
 }
 
 impl<'e> Match<'e> {
@@ -91,15 +96,54 @@ impl ExpressionMatch {
         let mut new_match = self.clone();
         for (key, value) in &other.substitutions {
             if let Some(existing) = new_match.substitutions.get(key) {
-                if existing != value {
+                if existing != value || self.captures.get(key) != other.captures.get(key) {
                     return None;
                 }
             } else {
                 new_match.substitutions.insert(*key, value.clone());
+                if let Some(captures) = other.captures.get(key) {
+                    new_match.captures.insert(*key, captures.clone());
+                }
             }
         }
         Some(new_match)
     }
+
+    /// Returns the variables bound in the matched expression which occur free in the
+    /// substitution of `variable`, with the pattern variables matching their binders.
+    /// Empty for matches which do not respect binders.
+    pub fn captures(&self, variable: VariableId) -> Option<&BTreeMap<VariableId, VariableId>> {
+        self.captures.get(&variable)
+    }
+
+    /// Sets the substitution of a pattern variable matched at `expression`, returning `false`
+    /// if it conflicts with an earlier one, i.e. the variable matched an expression which is not
+    /// equal up to renaming of variables bound by `binders`, or the same one with variables
+    /// bound by different binders.
+    fn try_set_in_scope(
+        &mut self,
+        variable: VariableId,
+        expression: &Expression,
+        captures: BTreeMap<VariableId, VariableId>,
+        binders: &BTreeSet<SymbolId>,
+    ) -> bool {
+        match self.substitutions.get(&variable) {
+            Some(existing) => {
+                existing.alpha_eq_binding(expression, binders)
+                    && self
+                        .captures
+                        .get(&variable)
+                        .map_or(captures.is_empty(), |existing| *existing == captures)
+            }
+            None => {
+                self.set(variable, expression.clone());
+                if !captures.is_empty() {
+                    self.captures.insert(variable, captures);
+                }
+                true
+            }
+        }
+    }
 }
 
 impl Expression {
@@ -198,6 +242,226 @@ impl Expression {
         }
     }
 
+    /// Tries to match a pattern against an expression with variables like
+    /// [`Self::try_match_expression`], respecting scopes of variables bound by `binders`.
+    ///
+    /// A binder of the pattern matches a binder of the expression, whose bound variable is
+    /// substituted for the bound variable of the pattern, which matches only that variable
+    /// within the binder, e.g. `(lam $0 (f $0 $1))` matches `(lam $5 (f $5 (g $5)))`.
+    /// Other pattern variables may match subexpressions using variables bound by enclosing
+    /// binders, which is recorded, see [`ExpressionMatch::captures`], and all their occurrences
+    /// must use them in the same way. Patterns should bind distinct variables in distinct binders.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - The pattern to match (may contain pattern variables)
+    /// * `expression` - The expression to match against (may contain expression variables)
+    /// * `binders` - The IDs of binder symbols, see [`Language::binders`](crate::language::Language::binders)
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(match)` if the pattern matches, `None` otherwise.
+    pub fn try_match_expression_with_binders(
+        pattern: &Expression,
+        expression: &Expression,
+        binders: &BTreeSet<SymbolId>,
+    ) -> Option<ExpressionMatch> {
+        let mut matching = ExpressionMatch::default();
+        Self::match_in_scope(pattern, expression, binders, &mut Vec::new(), &mut matching)
+            .then_some(matching)
+    }
+
+    /// `scope` holds the pairs of variables bound by the enclosing binders of the pattern
+    /// and of the expression, innermost last.
+    fn match_in_scope(
+        pattern: &Expression,
+        expression: &Expression,
+        binders: &BTreeSet<SymbolId>,
+        scope: &mut Vec<(VariableId, VariableId)>,
+        matching: &mut ExpressionMatch,
+    ) -> bool {
+        match (pattern, expression) {
+            (Expression::Variable(pattern_var), expression) => {
+                if let Some((_, bound)) = scope.iter().rev().find(|(var, _)| var == pattern_var) {
+                    return *expression == Expression::Variable(*bound);
+                }
+                let captures = expression
+                    .free_variables(binders)
+                    .into_iter()
+                    .filter_map(|free| {
+                        let (binder_var, _) =
+                            scope.iter().rev().find(|(_, bound)| *bound == free)?;
+                        Some((free, *binder_var))
+                    })
+                    .collect();
+                matching.try_set_in_scope(*pattern_var, expression, captures, binders)
+            }
+            (Expression::Symbol(pattern_symbol), Expression::Symbol(symbol)) => {
+                if !pattern_symbol.same_shape_as(symbol) {
+                    return false;
+                }
+                match (
+                    pattern.bound_variable(binders),
+                    expression.bound_variable(binders),
+                ) {
+                    (Some(pattern_var), Some(bound)) => {
+                        // The bound variable refers to its own binder
+                        let captures = BTreeMap::from([(bound, pattern_var)]);
+                        if !matching.try_set_in_scope(
+                            pattern_var,
+                            &symbol.children[0],
+                            captures,
+                            binders,
+                        ) {
+                            return false;
+                        }
+                        scope.push((pattern_var, bound));
+                        let matched = pattern_symbol.children[1..]
+                            .iter()
+                            .zip(&symbol.children[1..])
+                            .all(|(pattern, expression)| {
+                                Self::match_in_scope(pattern, expression, binders, scope, matching)
+                            });
+                        scope.pop();
+                        matched
+                    }
+                    (None, None) => pattern_symbol.children.iter().zip(&symbol.children).all(
+                        |(pattern, expression)| {
+                            Self::match_in_scope(pattern, expression, binders, scope, matching)
+                        },
+                    ),
+                    _ => false,
+                }
+            }
+            (Expression::Literal(pattern_literal), Expression::Literal(literal)) => {
+                pattern_literal == literal
+            }
+            _ => false,
+        }
+    }
+
+    /// Instantiates a pattern with a match found by [`Self::try_match_expression_with_binders`].
+    ///
+    /// Variables bound by binders of the pattern keep the names they had in the matched
+    /// expression, unless that would capture a free variable, in which case they are renamed
+    /// to fresh ones. Returns `None` if a variable bound in the matched expression would escape
+    /// the scope of its binder, e.g. when instantiating `$1` after matching
+    /// `(lam $0 (f $0 $1))` at `(lam $5 (f $5 (g $5)))`.
+    pub fn instantiate_expression_with_binders(
+        pattern: &Expression,
+        matching: &ExpressionMatch,
+        binders: &BTreeSet<SymbolId>,
+    ) -> Option<Expression> {
+        let mut next_fresh = matching
+            .substitutions
+            .values()
+            .chain([pattern])
+            .filter_map(Expression::max_variable_id)
+            .max()
+            .map_or(0, |id| id + 1);
+        Self::instantiate_in_scope(
+            pattern,
+            matching,
+            binders,
+            &mut HashMap::new(),
+            &mut next_fresh,
+        )
+    }
+
+    /// `names` holds the variables bound by the instantiated enclosing binders of the pattern
+    /// for their pattern variables.
+    fn instantiate_in_scope(
+        pattern: &Expression,
+        matching: &ExpressionMatch,
+        binders: &BTreeSet<SymbolId>,
+        names: &mut HashMap<VariableId, VariableId>,
+        next_fresh: &mut VariableId,
+    ) -> Option<Expression> {
+        match pattern {
+            Expression::Literal(literal) => Some(Expression::Literal(literal.clone())),
+            Expression::Variable(var_id) => {
+                if let Some(&name) = names.get(var_id) {
+                    return Some(Expression::Variable(name));
+                }
+                let Some(substitute) = matching.at(*var_id) else {
+                    return Some(Expression::Variable(*var_id));
+                };
+                let mut substitute = substitute.clone();
+                for (&bound, binder_var) in matching.captures(*var_id).into_iter().flatten() {
+                    let name = names.get(binder_var)?;
+                    substitute.substitute_avoiding_capture(
+                        bound,
+                        &Expression::Variable(*name),
+                        binders,
+                    );
+                }
+                Some(substitute)
+            }
+            Expression::Symbol(symbol) => {
+                let Some(binder_var) = pattern.bound_variable(binders) else {
+                    return Some(Expression::Symbol(Symbol {
+                        id: symbol.id,
+                        children: symbol
+                            .children
+                            .iter()
+                            .map(|child| {
+                                Self::instantiate_in_scope(
+                                    child, matching, binders, names, next_fresh,
+                                )
+                            })
+                            .collect::<Option<_>>()?,
+                    }));
+                };
+
+                // The body is instantiated with a fresh bound variable, which is then renamed
+                // to the matched one if it does not occur free in the body
+                let placeholder = *next_fresh;
+                *next_fresh += 1;
+                let shadowed = names.insert(binder_var, placeholder);
+                let body = symbol.children[1..]
+                    .iter()
+                    .map(|child| {
+                        Self::instantiate_in_scope(child, matching, binders, names, next_fresh)
+                    })
+                    .collect::<Option<Vec<_>>>();
+                match shadowed {
+                    Some(shadowed) => names.insert(binder_var, shadowed),
+                    None => names.remove(&binder_var),
+                };
+                let mut body = body?;
+
+                let name = match matching.at(binder_var) {
+                    Some(Expression::Variable(name))
+                        if !body
+                            .iter()
+                            .any(|child| child.free_variables(binders).contains(name)) =>
+                    {
+                        for child in &mut body {
+                            child.substitute_avoiding_capture(
+                                placeholder,
+                                &Expression::Variable(*name),
+                                binders,
+                            );
+                        }
+                        *name
+                    }
+                    _ => placeholder,
+                };
+                // Renaming may have introduced fresh variables in nested binders
+                if let Some(max) = body.iter().filter_map(Expression::max_variable_id).max() {
+                    *next_fresh = (*next_fresh).max(max + 1);
+                }
+                Some(Expression::Symbol(Symbol {
+                    id: symbol.id,
+                    children: [Expression::Variable(name)]
+                        .into_iter()
+                        .chain(body)
+                        .collect(),
+                }))
+            }
+        }
+    }
+
     /// Instantiates a pattern with matched expression variables to produce an expression.
     ///
     /// # Arguments
@@ -236,10 +500,12 @@ impl Expression {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeMap;
+
     use super::Match;
     use crate::language::{
         Language,
-        expression::{Literal, VarFreeExpression},
+        expression::{Expression, Literal, VarFreeExpression},
     };
 
     #[test]
@@ -344,4 +610,120 @@ mod tests {
             .unwrap();
         assert!(pattern.try_match(&expr).is_none());
     }
+
+    #[test]
+    fn match_respects_binders() {
+        let lang = Language::default()
+            .add_symbol("lam")
+            .add_symbol("app")
+            .add_symbol("f")
+            .declare_binder("lam");
+        let binders = lang.binders();
+        let parse = |expression| lang.parse(expression).unwrap();
+
+        let pattern = parse("(lam $0 (app $1 $0))");
+        let matching = Expression::try_match_expression_with_binders(
+            &pattern,
+            &parse("(lam $5 (app (f $5) $5))"),
+            binders,
+        )
+        .unwrap();
+        assert_eq!(matching.at(1), Some(&parse("(f $5)")));
+        assert_eq!(matching.captures(1), Some(&BTreeMap::from([(5, 0)])));
+
+        // The bound variable of the pattern only matches the bound variable
+        assert!(
+            Expression::try_match_expression_with_binders(
+                &pattern,
+                &parse("(lam $5 (app $5 $6))"),
+                binders
+            )
+            .is_none()
+        );
+        // Occurrences of $1 refer to different variables, bound in one of them only
+        let pattern = parse("(app (lam $0 $1) $1)");
+        assert!(
+            Expression::try_match_expression_with_binders(
+                &pattern,
+                &parse("(app (lam $5 $5) $5)"),
+                binders
+            )
+            .is_none()
+        );
+        assert!(
+            Expression::try_match_expression_with_binders(
+                &pattern,
+                &parse("(app (lam $5 $6) $6)"),
+                binders
+            )
+            .is_some()
+        );
+        // Without binders both occurrences match the same variable
+        assert!(
+            Expression::try_match_expression(&pattern, &parse("(app (lam $5 $5) $5)")).is_some()
+        );
+    }
+
+    #[test]
+    fn nonlinear_match_up_to_bound_variables() {
+        let lang = Language::default()
+            .add_symbol("lam")
+            .add_symbol("app")
+            .add_symbol("f")
+            .declare_binder("lam");
+        let binders = lang.binders();
+        let parse = |expression| lang.parse(expression).unwrap();
+        let pattern = parse("(app $0 $0)");
+
+        assert!(
+            Expression::try_match_expression_with_binders(
+                &pattern,
+                &parse("(app (lam $1 (f $1)) (lam $2 (f $2)))"),
+                binders
+            )
+            .is_some()
+        );
+        // Free variables still have to be equal
+        assert!(
+            Expression::try_match_expression_with_binders(
+                &pattern,
+                &parse("(app (lam $1 (f $3)) (lam $2 (f $4)))"),
+                binders
+            )
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn instantiation_respects_binders() {
+        let lang = Language::default()
+            .add_symbol("lam")
+            .add_symbol("app")
+            .add_symbol("f")
+            .declare_binder("lam");
+        let binders = lang.binders();
+        let parse = |expression| lang.parse(expression).unwrap();
+
+        let matching = Expression::try_match_expression_with_binders(
+            &parse("(app (lam $0 $1) $2)"),
+            &parse("(app (lam $5 (f $5 $6)) $5)"),
+            binders,
+        )
+        .unwrap();
+        let instantiate = |pattern| {
+            Expression::instantiate_expression_with_binders(&parse(pattern), &matching, binders)
+        };
+
+        assert_eq!(
+            instantiate("(lam $0 (app $1 $2))"),
+            Some(parse("(lam $7 (app (f $7 $6) $5))"))
+        );
+        assert_eq!(
+            instantiate("(lam $0 (app $1 $1))"),
+            Some(parse("(lam $5 (app (f $5 $6) (f $5 $6)))"))
+        );
+        // The bound variable would escape its binder
+        assert_eq!(instantiate("(app $1 $2)"), None);
+        assert_eq!(instantiate("$0"), None);
+    }
 }
@@ -2,14 +2,24 @@
 //!
 //! This module contains the core components of the term rewriting system.
 
+pub mod abelian;
 pub mod direct;
+pub mod dynamic_rule;
 pub mod egraph;
+pub mod experiments;
+pub mod guard;
 pub mod heuristic;
+pub mod hybrid;
 pub mod ilp;
+pub mod linear_heuristic;
 pub mod matching;
+pub mod multi_rule;
 pub mod random;
 pub mod reachability;
 pub mod rule;
+pub mod search;
+pub mod simplify;
 pub mod strings;
 pub mod system;
+pub mod termination;
 pub mod unification;
@@ -0,0 +1,276 @@
+//! Rewrite rules with several patterns on the left-hand side.
+//!
+//! This module provides the [`MultiRule`] struct, which is applied where all of its patterns
+//! match at once, possibly in unrelated classes of an e-graph.
+
+use std::{collections::HashSet, error::Error, fmt, time::Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::language::{
+    Language,
+    expression::{Expression, VariableId},
+};
+
+use super::egraph::{
+    Analysis, DynEGraph, EGraph,
+    matching::{Matcher, MultiMatch},
+    saturation::report::RuleReport,
+};
+
+/// A rewrite rule matching several patterns simultaneously.
+///
+/// The rule matches wherever all of its patterns match with consistent substitutions
+/// of their shared variables, see [`MultiMatch`]. For every match, both sides of each
+/// of its equalities are instantiated and added to the e-graph, and their classes are merged.
+/// E.g. the rule with patterns `(= $0 $1)` and `(f $0)` and the equality `(f $0) = (f $1)`
+/// adds `(f b)` equivalent to `(f a)` whenever `(= a b)` and `(f a)` are present anywhere.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct MultiRule {
+    patterns: Vec<Expression>,
+    equalities: Vec<(Expression, Expression)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+}
+
+/// Error of a [`MultiRule`] whose equalities use a variable bound by none of its patterns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnboundVariableError(pub VariableId);
+
+impl fmt::Display for UnboundVariableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Variable {} is not bound by any pattern",
+            Expression::variable_name(self.0)
+        )
+    }
+}
+
+impl Error for UnboundVariableError {}
+
+impl MultiRule {
+    /// Creates a rule from expression patterns and equalities of expressions.
+    ///
+    /// # Arguments
+    ///
+    /// * `patterns` - The patterns which have to match simultaneously
+    /// * `equalities` - Pairs of expressions made equivalent by every match
+    pub fn new(
+        patterns: Vec<Expression>,
+        equalities: Vec<(Expression, Expression)>,
+    ) -> Result<Self, UnboundVariableError> {
+        let bound: HashSet<_> = patterns.iter().flat_map(Expression::variables).collect();
+        if let Some(&unbound) = equalities
+            .iter()
+            .flat_map(|(left, right)| left.variables().into_iter().chain(right.variables()))
+            .collect::<HashSet<_>>()
+            .difference(&bound)
+            .min()
+        {
+            return Err(UnboundVariableError(unbound));
+        }
+
+        Ok(Self {
+            patterns,
+            equalities,
+            name: None,
+        })
+    }
+
+    /// Creates a rule from string patterns and equalities.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an expression cannot be parsed or uses a variable bound by no pattern
+    pub fn from_strings(
+        patterns: &[&str],
+        equalities: &[(&str, &str)],
+        language: &Language,
+    ) -> Self {
+        let parse = |expression| language.parse(expression).unwrap();
+        Self::new(
+            patterns.iter().map(|pattern| parse(pattern)).collect(),
+            equalities
+                .iter()
+                .map(|(left, right)| (parse(left), parse(right)))
+                .collect(),
+        )
+        .unwrap()
+    }
+
+    /// Names the rule.
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = Some(String::from(name));
+        self
+    }
+
+    /// Returns the name of the rule, if it has one.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the patterns which have to match simultaneously.
+    pub fn patterns(&self) -> &[Expression] {
+        &self.patterns
+    }
+
+    /// Returns the pairs of expressions made equivalent by every match.
+    pub fn equalities(&self) -> &[(Expression, Expression)] {
+        &self.equalities
+    }
+
+    /// Returns the number of matches at which the rule changed the e-graph.
+    /// Equalities which would add new nodes to frozen classes are skipped,
+    /// see [`EGraph::freeze_class`].
+    pub fn apply<A: Analysis>(
+        &self,
+        egraph: &mut EGraph<A>,
+        matcher: &(impl Matcher + ?Sized),
+    ) -> usize {
+        self.apply_reported(egraph, matcher, &mut RuleReport::default())
+    }
+
+    /// Same as [`MultiRule::apply`], but also adds the numbers of matches and applications
+    /// to `report`.
+    pub fn apply_reported<A: Analysis>(
+        &self,
+        egraph: &mut EGraph<A>,
+        matcher: &(impl Matcher + ?Sized),
+        report: &mut RuleReport,
+    ) -> usize {
+        let start = Instant::now();
+        let matches = matcher.try_match_multi(egraph, &self.patterns);
+        report.matches += matches.len();
+        report.match_time += start.elapsed();
+
+        matches
+            .iter()
+            .filter(|matching| self.apply_match(egraph, matching, report))
+            .count()
+    }
+
+    /// Makes both sides of every equality equivalent at `matching`.
+    /// Returns `true` if the e-graph has changed, in which case the application is added to `report`.
+    pub fn apply_match<A: Analysis>(
+        &self,
+        egraph: &mut EGraph<A>,
+        matching: &MultiMatch,
+        report: &mut RuleReport,
+    ) -> bool {
+        let start = Instant::now();
+        let mut applied = false;
+        for (left, right) in &self.equalities {
+            let left = left.clone().mixed_expression(matching.matching());
+            let right = right.clone().mixed_expression(matching.matching());
+            let frozen = |side| {
+                egraph
+                    .find_mixed_expression(side)
+                    .is_some_and(|class_id| egraph.is_frozen(class_id))
+            };
+            if (frozen(&left) && egraph.find_mixed_expression(&right).is_none())
+                || (frozen(&right) && egraph.find_mixed_expression(&left).is_none())
+            {
+                continue;
+            }
+
+            let left = egraph.add_mixed_expression(left);
+            let right = egraph.add_mixed_expression(right);
+            let merged = egraph
+                .merge_classes(*left.as_ref().any(), *right.as_ref().any())
+                .new()
+                .is_some();
+            if merged {
+                report.unions += 1;
+            }
+            applied |= merged || left.new().is_some() || right.new().is_some();
+        }
+
+        if applied {
+            report.applications += 1;
+        }
+        report.apply_time += start.elapsed();
+        applied
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        language::{Language, expression::Literal},
+        rewriting::egraph::{
+            DynEGraph, EGraph,
+            matching::{Matcher, bottom_up::BottomUpMatcher, top_down::TopDownMatcher},
+        },
+    };
+
+    use super::{MultiRule, UnboundVariableError};
+
+    fn language() -> Language {
+        Language::default().add_symbol("eq").add_symbol("f")
+    }
+
+    fn egraph(lang: &Language) -> EGraph<()> {
+        let mut egraph = EGraph::default();
+        egraph.add_expression(lang.parse_no_vars("(eq 1 2)").unwrap());
+        egraph.add_expression(lang.parse_no_vars("(f 1)").unwrap());
+        egraph.add_expression(lang.parse_no_vars("(f 3)").unwrap());
+        egraph
+    }
+
+    #[test]
+    fn matches_with_consistent_substitutions() {
+        let lang = language();
+        let egraph = egraph(&lang);
+        let patterns = [
+            lang.parse("(eq $0 $1)").unwrap(),
+            lang.parse("(f $0)").unwrap(),
+        ];
+        let one = egraph.find_literal(Literal::Int(1)).unwrap();
+        let equality = egraph.containing_class(egraph.find_symbols(lang.get_id("eq"))[0]);
+
+        for matches in [
+            TopDownMatcher.try_match_multi(&egraph, &patterns),
+            BottomUpMatcher.try_match_multi(&egraph, &patterns),
+        ] {
+            assert_eq!(matches.len(), 1);
+            assert_eq!(matches[0].roots()[0], equality);
+            assert_ne!(matches[0].roots()[1], equality);
+            assert_eq!(matches[0].matching().class_variable(0), one);
+        }
+        assert!(TopDownMatcher.try_match_multi(&egraph, &[]).is_empty());
+    }
+
+    #[test]
+    fn adds_nodes_and_unions() {
+        let lang = language();
+        let mut egraph = egraph(&lang);
+        let congruence =
+            MultiRule::from_strings(&["(eq $0 $1)", "(f $0)"], &[("(f $0)", "(f $1)")], &lang);
+
+        assert_eq!(congruence.apply(&mut egraph, &BottomUpMatcher), 1);
+        assert_eq!(congruence.apply(&mut egraph, &BottomUpMatcher), 0);
+        let f_1 = egraph.add_expression(lang.parse_no_vars("(f 1)").unwrap());
+        let f_2 = egraph.add_expression(lang.parse_no_vars("(f 2)").unwrap());
+        assert_eq!(egraph.canonical_class(f_1), egraph.canonical_class(f_2));
+        let f_3 = egraph.add_expression(lang.parse_no_vars("(f 3)").unwrap());
+        assert_ne!(egraph.canonical_class(f_1), egraph.canonical_class(f_3));
+        assert_eq!(egraph.class_count(), 6);
+
+        let equality = MultiRule::from_strings(&["(eq $0 $1)"], &[("$0", "$1")], &lang);
+        assert_eq!(equality.apply(&mut egraph, &TopDownMatcher), 1);
+        let one = egraph.find_literal(Literal::Int(1)).unwrap();
+        let two = egraph.find_literal(Literal::Int(2)).unwrap();
+        assert_eq!(one, two);
+    }
+
+    #[test]
+    fn unbound_variable() {
+        let lang = language();
+        let rule = MultiRule::new(
+            vec![lang.parse("(f $0)").unwrap()],
+            vec![(lang.parse("$0").unwrap(), lang.parse("$1").unwrap())],
+        );
+        assert_eq!(rule, Err(UnboundVariableError(1)));
+    }
+}
@@ -67,15 +67,20 @@ pub fn rewrite_expression(
 /// * `expression` - The expression to rewrite
 /// * `rules` - The rewrite rules to apply
 /// * `n` - The number of rewrites to perform
+/// * `rng` - Random number generator
 ///
 /// # Returns
 ///
 /// Returns the rewritten expression after n random rewrites have been applied.
 /// If no rewrites are possible at any step, returns the expression as-is.
-pub fn rewrite(expression: VarFreeExpression, rules: &[Rule], n: usize) -> VarFreeExpression {
-    let mut rng = rand::thread_rng();
+pub fn rewrite(
+    expression: VarFreeExpression,
+    rules: &[Rule],
+    n: usize,
+    rng: &mut impl Rng,
+) -> VarFreeExpression {
     let expr = expression.to_expression();
-    let rewritten = rewrite_expression(expr, rules, n, &mut rng);
+    let rewritten = rewrite_expression(expr, rules, n, rng);
     rewritten.without_variables().unwrap_or(expression)
 }
 
@@ -83,6 +88,7 @@ pub fn rewrite(expression: VarFreeExpression, rules: &[Rule], n: usize) -> VarFr
 mod tests {
     use super::*;
     use crate::language::Language;
+    use rand::{SeedableRng, rngs::StdRng};
 
     #[test]
     fn test_no_rewrites_possible() {
@@ -90,7 +96,7 @@ mod tests {
         let expr = lang.parse_no_vars("42").unwrap();
         let rules = vec![Rule::from_strings("(+ 0 $0)", "$0", &lang)];
 
-        let result = rewrite(expr.clone(), &rules, 5);
+        let result = rewrite(expr.clone(), &rules, 5, &mut StdRng::seed_from_u64(0));
         assert_eq!(result, expr);
     }
 
@@ -100,7 +106,7 @@ mod tests {
         let expr = lang.parse_no_vars("(+ 0 5)").unwrap();
         let rules = vec![Rule::from_strings("(+ 0 $0)", "$0", &lang)];
 
-        let result = rewrite(expr, &rules, 1);
+        let result = rewrite(expr, &rules, 1, &mut StdRng::seed_from_u64(0));
         let expected = lang.parse_no_vars("5").unwrap();
         assert_eq!(result, expected);
     }
@@ -112,7 +118,7 @@ mod tests {
         let rules = vec![Rule::from_strings("(+ 0 $0)", "$0", &lang)];
 
         // After 2 rewrites, should reduce to 5
-        let result = rewrite(expr, &rules, 2);
+        let result = rewrite(expr, &rules, 2, &mut StdRng::seed_from_u64(0));
         let expected = lang.parse_no_vars("5").unwrap();
         assert_eq!(result, expected);
     }
@@ -127,7 +133,7 @@ mod tests {
         ];
 
         // Apply rewrites - result should be valid but may vary
-        let result = rewrite(expr, &rules, 3);
+        let result = rewrite(expr, &rules, 3, &mut StdRng::seed_from_u64(0));
 
         // Just check that it produces a valid expression
         // (exact result depends on random choices)
@@ -137,6 +143,21 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_same_seed_same_rewrites() {
+        let lang = Language::simple_math();
+        let expr = lang.parse_no_vars("(* (+ 1 2) (+ 3 (* 4 5)))").unwrap();
+        let rules = vec![
+            Rule::from_strings("(* $0 $1)", "(* $1 $0)", &lang),
+            Rule::from_strings("(+ $0 $1)", "(+ $1 $0)", &lang),
+        ];
+
+        let results: Vec<_> = (0..2)
+            .map(|_| rewrite(expr.clone(), &rules, 10, &mut StdRng::seed_from_u64(7)))
+            .collect();
+        assert_eq!(results[0], results[1]);
+    }
+
     #[test]
     fn test_instantiate_expression() {
         let lang = Language::simple_math();
@@ -1,17 +1,31 @@
 //! Reachability analysis for term rewriting systems.
 //!
 //! This module provides functionality to determine if two expressions can be
-//! made equivalent through the application of rewrite rules.
+//! made equivalent through the application of rewrite rules, or if an expression
+//! can be rewritten into an instance of a pattern.
+//! Classes of the input expressions are tagged with [`SOURCE_TAG`] and [`goal_tag`],
+//! so that the resulting e-graphs record which classes stem from which input.
 
 use std::time::{Duration, Instant};
 
-use crate::language::expression::VarFreeExpression;
-use crate::rewriting::egraph::matching::Matcher;
+use crate::language::expression::{Expression, VarFreeExpression};
+use crate::rewriting::abelian::supporting_rules;
+use crate::rewriting::egraph::matching::{EGraphMatch, Matcher};
 use crate::rewriting::egraph::saturation::scheduler::Scheduler;
-use crate::rewriting::egraph::saturation::{SaturationConfig, SaturationStopReason, check_limits};
+use crate::rewriting::egraph::saturation::{
+    IterationBudget, SaturationConfig, SaturationReport, SaturationStopReason, check_limits,
+};
 use crate::rewriting::egraph::{Analysis, ClassId, DynEGraph, EGraph};
 use crate::rewriting::rule::Rule;
 
+/// Tag of the class of the source expression, see [`EGraph::tag_class`].
+pub const SOURCE_TAG: &str = "origin: source";
+
+/// Returns the tag of the class of the goal with index `index`, see [`EGraph::tag_class`].
+pub fn goal_tag(index: usize) -> String {
+    format!("origin: goal {index}")
+}
+
 /// Reason why reachability analysis stopped.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum ReachabilityStopReason {
@@ -21,6 +35,9 @@ pub enum ReachabilityStopReason {
     Limit(SaturationStopReason),
     /// No more rule applications possible and the classes remain distinct.
     SaturatedNoUnification,
+    /// The expressions were proven not to unify before any rewriting,
+    /// see [`AbelianPrefilter`](crate::rewriting::abelian::AbelianPrefilter).
+    Pruned,
 }
 
 /// Result of a reachability analysis.
@@ -33,10 +50,18 @@ pub struct ReachabilityResult<A: Analysis> {
     pub egraph: EGraph<A>,
     /// The reason why the analysis stopped
     pub reason: ReachabilityStopReason,
+    /// Index of the goal which was unified with the source, if any
+    pub goal: Option<usize>,
+    /// Match of the goal pattern in the class of the source, see [`pattern_reachable`]
+    pub goal_match: Option<EGraphMatch>,
+    /// The number of rules removed before saturation, see [`SaturationConfig::preprocess`]
+    pub pruned_rules: usize,
     /// The number of rule applications performed
     pub applications: usize,
     /// The duration of the analysis
     pub duration: Duration,
+    /// Errors of writing frames, see [`SaturationReport::frame_errors`]
+    pub frame_errors: Vec<String>,
 }
 
 /// Check if two terms can reach a common form through equality saturation.
@@ -63,47 +88,223 @@ pub fn terms_reachable<A, F>(
     matcher: &dyn Matcher,
     build_scheduler: F,
 ) -> ReachabilityResult<A>
+where
+    A: Analysis,
+    F: FnOnce(&[Rule]) -> Box<dyn Scheduler<A>>,
+{
+    terms_reachable_any(
+        rules,
+        expr_a,
+        vec![expr_b],
+        config,
+        matcher,
+        build_scheduler,
+    )
+}
+
+/// Check if `source` can reach a common form with any of `goals` through equality saturation.
+///
+/// Saturation stops as soon as the class of `source` is unified with the class of a goal,
+/// also in the middle of an iteration, and the index of the goal is given in
+/// [`ReachabilityResult::goal`]. If several goals are unified with the source at once,
+/// the first of them is reported.
+///
+/// If [`SaturationConfig::preprocess`] is set, rules which cannot be used to rewrite `source`
+/// into any goal according to [`supporting_rules`] are not given to the scheduler.
+///
+/// # Arguments
+///
+/// * `rules` - The rewrite rules to apply
+/// * `source` - The expression to be rewritten
+/// * `goals` - The expressions to be reached
+/// * `config` - Configuration for saturation limits
+/// * `matcher` - The matcher to use for pattern matching
+/// * `build_scheduler` - A function that creates a scheduler for the rules
+pub fn terms_reachable_any<A, F>(
+    rules: &[Rule],
+    source: VarFreeExpression,
+    goals: Vec<VarFreeExpression>,
+    config: &SaturationConfig,
+    matcher: &dyn Matcher,
+    build_scheduler: F,
+) -> ReachabilityResult<A>
+where
+    A: Analysis,
+    F: FnOnce(&[Rule]) -> Box<dyn Scheduler<A>>,
+{
+    let start = Instant::now();
+
+    let kept_rules: Vec<_> = if config.preprocess {
+        supporting_rules(rules, &source, &goals)
+            .into_iter()
+            .map(|index| rules[index].clone())
+            .collect()
+    } else {
+        rules.to_vec()
+    };
+    let pruned_rules = rules.len() - kept_rules.len();
+
+    // Build e-graph seeded with the source, then add the goals.
+    let (mut egraph, source_class) = EGraph::<A>::from_expression_with_id(source);
+    egraph.tag_class(source_class, SOURCE_TAG);
+    let goal_tags: Vec<_> = (0..goals.len()).map(goal_tag).collect();
+    for (goal, tag) in goals.into_iter().zip(&goal_tags) {
+        let goal_class = egraph.add_expression(goal);
+        egraph.tag_class(goal_class, tag);
+    }
+
+    // Stop applying matches of an iteration as soon as a goal is reached
+    let user_condition = config.stop_when.clone();
+    let reached_tags = goal_tags.clone();
+    let config = &config.clone().with_stop_when(move |egraph| {
+        reached_tags
+            .iter()
+            .any(|tag| egraph.has_tag(source_class, tag))
+            || user_condition
+                .as_ref()
+                .is_some_and(|condition| condition.holds(egraph))
+    });
+
+    let (reason, goal, report) = saturate_until(
+        &mut egraph,
+        source_class,
+        build_scheduler(&kept_rules),
+        config,
+        matcher,
+        start,
+        |egraph, class_id| {
+            goal_tags
+                .iter()
+                .position(|tag| egraph.has_tag(class_id, tag))
+        },
+    );
+
+    ReachabilityResult {
+        egraph,
+        reason,
+        goal,
+        goal_match: None,
+        pruned_rules,
+        applications: report.applications,
+        duration: start.elapsed(),
+        frame_errors: report.frame_errors,
+    }
+}
+
+/// Check if `source` can be rewritten into an instance of the pattern `goal`
+/// through equality saturation, e.g. into some term of the form `(* 1 $0)`.
+///
+/// Saturation stops as soon as `goal` matches in the class of `source`, and the match is given
+/// in [`ReachabilityResult::goal_match`]. The pattern is matched with `matcher` after every
+/// iteration, not after every rule application. [`SaturationConfig::preprocess`] is ignored,
+/// as the supporting rules can only be computed for goals without variables.
+///
+/// # Arguments
+///
+/// * `rules` - The rewrite rules to apply
+/// * `source` - The expression to be rewritten
+/// * `goal` - The pattern to be reached
+/// * `config` - Configuration for saturation limits
+/// * `matcher` - The matcher to use for pattern matching
+/// * `build_scheduler` - A function that creates a scheduler for the rules
+pub fn pattern_reachable<A, F>(
+    rules: &[Rule],
+    source: VarFreeExpression,
+    goal: &Expression,
+    config: &SaturationConfig,
+    matcher: &dyn Matcher,
+    build_scheduler: F,
+) -> ReachabilityResult<A>
 where
     A: Analysis,
     F: FnOnce(&[Rule]) -> Box<dyn Scheduler<A>>,
 {
     let start = Instant::now();
+    let (mut egraph, source_class) = EGraph::<A>::from_expression_with_id(source);
+    egraph.tag_class(source_class, SOURCE_TAG);
 
-    // Build e-graph seeded with expr_a, then add expr_b and identify their classes.
-    let (mut egraph, a_root_node_class) = EGraph::<A>::from_expression_with_id(expr_a);
-    let b_root_node = egraph.add_expression(expr_b);
-    let mut a_class = egraph.canonical_class(a_root_node_class);
-    let mut b_class = egraph.canonical_class(egraph.containing_class(b_root_node));
+    let (reason, goal_match, report) = saturate_until(
+        &mut egraph,
+        source_class,
+        build_scheduler(rules),
+        config,
+        matcher,
+        start,
+        |egraph, class_id| {
+            matcher
+                .try_match_at(egraph, goal, class_id)
+                .into_iter()
+                .next()
+        },
+    );
 
-    let mut scheduler = build_scheduler(rules);
-    let mut applications = 0;
+    ReachabilityResult {
+        egraph,
+        reason,
+        goal: goal_match.as_ref().map(|_| 0),
+        goal_match,
+        pruned_rules: 0,
+        applications: report.applications,
+        duration: start.elapsed(),
+        frame_errors: report.frame_errors,
+    }
+}
+
+/// Saturates `egraph` with rules chosen by `scheduler` until `reached` finds a goal
+/// in the canonical class of `source_class`, checking it before every iteration.
+///
+/// # Returns
+///
+/// Returns the reason why the saturation stopped, the found goal and the final report
+fn saturate_until<A: Analysis, G>(
+    egraph: &mut EGraph<A>,
+    source_class: ClassId,
+    mut scheduler: Box<dyn Scheduler<A>>,
+    config: &SaturationConfig,
+    matcher: &dyn Matcher,
+    start: Instant,
+    reached: impl Fn(&EGraph<A>, ClassId) -> Option<G>,
+) -> (ReachabilityStopReason, Option<G>, SaturationReport) {
+    let mut report = SaturationReport::default();
+    let bounds = config.term_bounds();
+    config.dump_frame(egraph, &mut report);
 
     let reason = loop {
         // Re-check canonical classes before attempting the next step.
-        a_class = egraph.canonical_class(a_class);
-        b_class = egraph.canonical_class(b_class);
-        if a_class == b_class {
-            break ReachabilityStopReason::ReachedCommonForm { class_id: a_class };
+        let class_id = egraph.canonical_class(source_class);
+        if let Some(goal) = reached(egraph, class_id) {
+            let reason = ReachabilityStopReason::ReachedCommonForm { class_id };
+            return (reason, Some(goal), report);
         }
 
-        if let Some(limit) = check_limits(&egraph, applications, start, config) {
+        if let Some(limit) = check_limits(
+            egraph,
+            report.iterations,
+            report.applications,
+            start,
+            config,
+        ) {
             break ReachabilityStopReason::Limit(limit);
         }
 
-        let applied = scheduler.apply_next(&mut egraph, matcher);
+        let budget = IterationBudget::new(egraph, &report, start, config);
+        let applied =
+            scheduler.apply_next_reported(egraph, matcher, &(&bounds, &budget), &mut report);
+        report.iterations += 1;
+        report.applications += applied;
+        report.snapshot(egraph, start.elapsed());
+        config.report_progress(&report);
+        config.dump_frame(egraph, &mut report);
+
         if applied == 0 {
-            break ReachabilityStopReason::SaturatedNoUnification;
+            break match budget.exhausted() {
+                Some(limit) => ReachabilityStopReason::Limit(limit),
+                None => ReachabilityStopReason::SaturatedNoUnification,
+            };
         }
-
-        applications += applied;
     };
 
-    ReachabilityResult {
-        egraph,
-        reason,
-        applications,
-        duration: start.elapsed(),
-    }
+    (reason, None, report)
 }
 
 /// Convenience wrapper: Round-Robin over `rules`.
@@ -202,4 +403,161 @@ mod tests {
         );
         assert_eq!(res.applications, 1);
     }
+
+    #[test]
+    fn stops_in_the_middle_of_iteration() {
+        use crate::rewriting::egraph::saturation::scheduler::RoundRobinScheduler;
+        let lang = Language::simple_math();
+        let rules = rules!(lang;
+            "(* $0 1)" => "$0",
+        );
+        let goals = vec![
+            lang.parse_no_vars("(+ 3 (* 4 1))").unwrap(),
+            lang.parse_no_vars("(+ (* 3 1) 4)").unwrap(),
+        ];
+
+        let res: ReachabilityResult<()> = terms_reachable_any(
+            &rules,
+            lang.parse_no_vars("(+ (* 3 1) (* 4 1))").unwrap(),
+            goals,
+            &SaturationConfig::default(),
+            &TopDownMatcher,
+            |rs| Box::new(RoundRobinScheduler::new(rs.to_vec())),
+        );
+
+        assert!(matches!(
+            res.reason,
+            ReachabilityStopReason::ReachedCommonForm { .. }
+        ));
+        assert_eq!(res.applications, 1);
+    }
+
+    #[test]
+    fn reaches_pattern() {
+        use crate::rewriting::egraph::saturation::scheduler::RoundRobinScheduler;
+        let lang = Language::simple_math();
+        let rules = rules!(lang;
+            "(* $0 $1)" => "(* $1 $0)",
+            "(+ $0 0)" => "$0",
+        );
+        let source = lang.parse_no_vars("(+ (* (sin 2) 1) 0)").unwrap();
+        let reach = |pattern: &str| -> ReachabilityResult<()> {
+            pattern_reachable(
+                &rules,
+                source.clone(),
+                &lang.parse(pattern).unwrap(),
+                &SaturationConfig::default(),
+                &TopDownMatcher,
+                |rs| Box::new(RoundRobinScheduler::new(rs.to_vec())),
+            )
+        };
+
+        let res = reach("(* 1 $0)");
+        assert!(matches!(
+            res.reason,
+            ReachabilityStopReason::ReachedCommonForm { .. }
+        ));
+        assert_eq!(res.goal, Some(0));
+        let matching = res.goal_match.unwrap();
+        let sin = res
+            .egraph
+            .find_symbols(lang.get_id("sin"))
+            .into_iter()
+            .map(|node_id| res.egraph.containing_class(node_id))
+            .next()
+            .unwrap();
+        assert_eq!(
+            res.egraph.canonical_class(matching.class_variable(0)),
+            res.egraph.canonical_class(sin)
+        );
+
+        let res = reach("(+ $0 $0)");
+        assert_eq!(res.reason, ReachabilityStopReason::SaturatedNoUnification);
+        assert!(res.goal_match.is_none());
+    }
+
+    #[test]
+    fn reaches_any_goal() {
+        use crate::rewriting::egraph::saturation::scheduler::RoundRobinScheduler;
+        let lang = Language::simple_math();
+        let rules = rules!(lang;
+            "1" => "2",
+            "2" => "3",
+        );
+        let goals = vec![
+            lang.parse_no_vars("4").unwrap(),
+            lang.parse_no_vars("3").unwrap(),
+            lang.parse_no_vars("2").unwrap(),
+        ];
+
+        let res: ReachabilityResult<()> = terms_reachable_any(
+            &rules,
+            lang.parse_no_vars("1").unwrap(),
+            goals,
+            &SaturationConfig::default(),
+            &TopDownMatcher,
+            |rs| Box::new(RoundRobinScheduler::new(rs.to_vec())),
+        );
+
+        assert!(matches!(
+            res.reason,
+            ReachabilityStopReason::ReachedCommonForm { .. }
+        ));
+        assert_eq!(res.goal, Some(2));
+        assert_eq!(res.applications, 1);
+        let source_class = res.egraph.classes_with_tag(SOURCE_TAG);
+        assert_eq!(source_class, res.egraph.classes_with_tag(&goal_tag(2)));
+        assert!(!res.egraph.has_tag(source_class[0], &goal_tag(1)));
+
+        let res: ReachabilityResult<()> = terms_reachable_any(
+            &rules,
+            lang.parse_no_vars("1").unwrap(),
+            vec![lang.parse_no_vars("4").unwrap()],
+            &SaturationConfig::default(),
+            &TopDownMatcher,
+            |rs| Box::new(RoundRobinScheduler::new(rs.to_vec())),
+        );
+        assert_eq!(res.reason, ReachabilityStopReason::SaturatedNoUnification);
+        assert_eq!(res.goal, None);
+    }
+
+    #[test]
+    fn preprocessing_prunes_useless_rules() {
+        let lang = Language::simple_math();
+        let rules = rules!(lang;
+            "(+ $0 $1)" => "(* $0 $1)",
+            "(sin $0)" => "(cos $0)",
+        );
+        let source = lang.parse_no_vars("(+ 1 2)").unwrap();
+        let goal = lang.parse_no_vars("(* 1 2)").unwrap();
+        let cfg = SaturationConfig {
+            preprocess: true,
+            ..Default::default()
+        };
+
+        let res: ReachabilityResult<()> =
+            terms_reachable_round_robin(&rules, source, goal, &cfg, &TopDownMatcher);
+        assert_eq!(res.pruned_rules, 1);
+        assert_eq!(res.goal, Some(0));
+    }
+
+    #[test]
+    fn preprocessing_keeps_rules_rewriting_goals() {
+        let lang = Language::simple_math();
+        let rules = rules!(lang;
+            "(+ $0 $1)" => "(* $0 $1)",
+            "(<< $0 $1)" => "(* $0 $1)",
+        );
+        let source = lang.parse_no_vars("(+ 1 2)").unwrap();
+        let goal = lang.parse_no_vars("(<< 1 2)").unwrap();
+        let cfg = SaturationConfig {
+            preprocess: true,
+            ..Default::default()
+        };
+
+        let res: ReachabilityResult<()> =
+            terms_reachable_round_robin(&rules, source, goal, &cfg, &TopDownMatcher);
+        assert_eq!(res.pruned_rules, 0);
+        assert_eq!(res.goal, Some(0));
+    }
 }
@@ -3,36 +3,141 @@
 //! This module provides the [`Rule`] struct that represents a rewrite rule
 //! (from pattern => to pattern) and handles its application to e-graphs.
 
-use crate::language::{Language, expression::Expression};
+use crate::language::{
+    Language,
+    expression::{Expression, VariableId},
+    sorts::{SortError, VariableSorts},
+    symbol::SymbolIdRemap,
+};
+use crate::metrics;
+use crate::seen::Seen;
 
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Instant;
 
-use super::egraph::{Analysis, DynEGraph, EGraph, matching::Matcher};
+use super::egraph::{
+    Analysis, ClassId, DynEGraph, EGraph, Node,
+    class::DynClass,
+    matching::{EGraphMatch, MatchCounters, Matcher},
+    saturation::{filter::ApplicationFilter, report::RuleReport},
+};
+use super::guard::Guard;
+use super::matching::ExpressionMatch;
 
 /// A rewrite rule for term rewriting.
 ///
 /// A rule consists of a pattern to match (`from`) and a replacement pattern (`to`).
 /// When the `from` pattern matches an expression in the e-graph, the `to` pattern
 /// is instantiated and added, with the matched class and new class being merged.
+/// A rule may carry a name, which is used to attribute results to it,
+/// a cost of its applications in searches for rewrite sequences,
+/// and a [`Guard`] restricting its matches to numeric literals satisfying some comparisons.
 #[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
 pub struct Rule {
     from: Expression,
     to: Expression,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cost: Option<usize>,
+    #[serde(default, skip_serializing_if = "Guard::is_empty")]
+    guard: Guard,
 }
 
 impl Rule {
-    /// Creates a rule from string patterns.
+    /// Creates a rule from string patterns, see [`Rule::try_from_strings`].
     ///
     /// # Arguments
     ///
     /// * `from` - The pattern to match (left-hand side)
     /// * `to` - The replacement pattern (right-hand side)
     /// * `language` - The language to use for parsing the patterns
+    ///
+    /// # Panics
+    ///
+    /// Panics if a pattern cannot be parsed or, for many-sorted languages, if the rule is ill-sorted
     pub fn from_strings(from: &str, to: &str, language: &Language) -> Self {
-        let from = language.parse(from).unwrap();
-        let to = language.parse(to).unwrap();
+        Self::try_from_strings(from, to, language).unwrap()
+    }
+
+    /// Creates a rule from string patterns, in which variables written as `#n`
+    /// only match numeric literals, see [`Language::parse_pattern`].
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - The pattern to match (left-hand side)
+    /// * `to` - The replacement pattern (right-hand side)
+    /// * `language` - The language to use for parsing the patterns
+    ///
+    /// # Returns
+    ///
+    /// Returns the rule, or an error if a pattern cannot be parsed or,
+    /// for many-sorted languages, if the rule is ill-sorted
+    pub fn try_from_strings(from: &str, to: &str, language: &Language) -> anyhow::Result<Self> {
+        let (from, from_literal_variables) = language.parse_pattern(from)?;
+        let (to, to_literal_variables) = language.parse_pattern(to)?;
+
+        Ok(Self::checked(from, to, language)?.with_guard(
+            Guard::new()
+                .with_literal_variables(from_literal_variables)
+                .with_literal_variables(to_literal_variables),
+        ))
+    }
+
+    /// Creates a rule from patterns in infix notation, see [`Language::parse_infix`].
+    /// Named variables are shared between both sides.
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - The pattern to match (left-hand side)
+    /// * `to` - The replacement pattern (right-hand side)
+    /// * `language` - The language to use for parsing the patterns
+    ///
+    /// # Panics
+    ///
+    /// Panics if a pattern cannot be parsed or, for many-sorted languages, if the rule is ill-sorted
+    pub fn from_infix_strings(from: &str, to: &str, language: &Language) -> Self {
+        let mut variables = HashMap::new();
+        let from = language
+            .parse_infix_with_variables(from, &mut variables)
+            .unwrap();
+        let to = language
+            .parse_infix_with_variables(to, &mut variables)
+            .unwrap();
+
+        Self::checked(from, to, language).unwrap()
+    }
+
+    /// Creates a rule from expression patterns, checking that both sides are well-sorted
+    /// and have the same sort if `language` declares sorts.
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - The pattern to match (left-hand side)
+    /// * `to` - The replacement pattern (right-hand side)
+    /// * `language` - The language whose sorts are checked
+    pub fn checked(
+        from: Expression,
+        to: Expression,
+        language: &Language,
+    ) -> Result<Self, SortError> {
+        if let Some(sorts) = language.sorts() {
+            let mut variables = VariableSorts::new();
+            let from_sort = sorts.check_with(&from, None, &mut variables)?;
+            let to_sort = sorts.check_with(&to, from_sort.as_ref(), &mut variables)?;
+            // Variables on the right-hand side may have determined the sort of the left one
+            sorts.check_with(&from, to_sort.as_ref(), &mut variables)?;
+        }
 
-        Self { from, to }
+        Ok(Self {
+            from,
+            to,
+            name: None,
+            cost: None,
+            guard: Guard::new(),
+        })
     }
 
     /// Creates a rule from expression patterns.
@@ -42,7 +147,89 @@ impl Rule {
     /// * `from` - The pattern to match (left-hand side)
     /// * `to` - The replacement pattern (right-hand side)
     pub fn from_expressions(from: Expression, to: Expression) -> Self {
-        Self { from, to }
+        Self {
+            from,
+            to,
+            name: None,
+            cost: None,
+            guard: Guard::new(),
+        }
+    }
+
+    /// Names the rule.
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = Some(String::from(name));
+        self
+    }
+
+    /// Returns the name of the rule, if it has one.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Sets the cost of an application of the rule.
+    pub fn with_cost(mut self, cost: usize) -> Self {
+        self.cost = Some(cost);
+        self
+    }
+
+    /// Returns the cost of an application of the rule, 1 unless set by [`Rule::with_cost`].
+    pub fn cost(&self) -> usize {
+        self.cost.unwrap_or(1)
+    }
+
+    /// Adds the conditions of `guard` to the guard of the rule.
+    pub fn with_guard(mut self, guard: Guard) -> Self {
+        self.guard.literal_variables.extend(guard.literal_variables);
+        self.guard.comparisons.extend(guard.comparisons);
+        self
+    }
+
+    /// Returns the guard of the rule, which has no conditions unless set by [`Rule::with_guard`]
+    /// or by writing variables as `#n`.
+    pub fn guard(&self) -> &Guard {
+        &self.guard
+    }
+
+    /// Returns the name of the rule or, for unnamed rules, its patterns as `from => to`,
+    /// followed by `if` and the comparisons of its guard if it has any.
+    pub fn label(&self, language: &Language) -> String {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
+        let (from, to) = self.format_sides(language);
+        match self.guard.comparisons.is_empty() {
+            true => format!("{from} => {to}"),
+            false => format!("{from} => {to} if {}", self.guard),
+        }
+    }
+
+    /// Returns both patterns written with `language`, with variables
+    /// only matching numeric literals written as `#n`.
+    pub fn format_sides(&self, language: &Language) -> (String, String) {
+        (
+            self.guard.format_pattern(&self.from, language),
+            self.guard.format_pattern(&self.to, language),
+        )
+    }
+
+    /// Returns the rule with its sides swapped.
+    /// The reversed rule of a rule named `name` is named `name-rev` and has the same cost and guard.
+    pub fn reversed(&self) -> Self {
+        Self {
+            from: self.to.clone(),
+            to: self.from.clone(),
+            name: self.name.as_ref().map(|name| format!("{name}-rev")),
+            cost: self.cost,
+            guard: self.guard.clone(),
+        }
+    }
+
+    /// Replaces the IDs of symbols of both patterns according to `remap`,
+    /// see [`Language::merge`].
+    pub fn remap_symbols(&mut self, remap: &SymbolIdRemap) {
+        self.from.remap_symbols(remap);
+        self.to.remap_symbols(remap);
     }
 
     /// Returns the pattern to match (left-hand side).
@@ -55,27 +242,205 @@ impl Rule {
         &self.to
     }
 
-    /// Returns the number of positions at which the rule was applied
+    /// `true` if every variable occurs equally many times on both sides of the rule,
+    /// so that every application changes the counts of symbols in the same way.
+    pub fn is_linear(&self) -> bool {
+        let from = self.from.find_all_variables();
+        let to = self.to.find_all_variables();
+        from.len() == to.len()
+            && from
+                .iter()
+                .all(|(variable_id, paths)| to.get(variable_id).map(Vec::len) == Some(paths.len()))
+    }
+
+    /// Returns the number of positions at which the rule was applied.
+    /// Applications which would add new nodes to frozen classes are skipped,
+    /// see [`EGraph::freeze_class`].
     pub fn apply<A: Analysis>(
         &self,
         egraph: &mut EGraph<A>,
         matcher: &(impl Matcher + ?Sized),
     ) -> usize {
-        matcher
-            .try_match(egraph, &self.from)
-            .into_iter()
-            .map(|matching| {
-                let to_add = self.to.clone().mixed_expression(&matching);
-                let added = egraph.add_mixed_expression(to_add);
-                egraph
-                    .merge_classes(matching.root(), *added.as_ref().any())
-                    .new()
-                    .is_some()
-                    || added.new().is_some()
+        self.apply_reported(egraph, matcher, &mut RuleReport::default())
+    }
+
+    /// Same as [`Rule::apply`], but also adds the numbers of matches and applications to `report`.
+    pub fn apply_reported<A: Analysis>(
+        &self,
+        egraph: &mut EGraph<A>,
+        matcher: &(impl Matcher + ?Sized),
+        report: &mut RuleReport,
+    ) -> usize {
+        self.apply_filtered(egraph, matcher, &(), report)
+    }
+
+    /// Same as [`Rule::apply_reported`], but skips applications rejected by `filter`,
+    /// e.g. ones which would add nodes representing terms outside of
+    /// [`TermBounds`](super::egraph::saturation::TermBounds).
+    ///
+    /// Matches substituting the same classes for the variables of the right-hand side,
+    /// e.g. all matches of a rule with a ground right-hand side, share its instance:
+    /// it is added to the e-graph once and merged with the roots of the other matches.
+    pub fn apply_filtered<A: Analysis>(
+        &self,
+        egraph: &mut EGraph<A>,
+        matcher: &(impl Matcher + ?Sized),
+        filter: &dyn ApplicationFilter<A>,
+        report: &mut RuleReport,
+    ) -> usize {
+        let matches = self.find_matches(egraph, matcher, report);
+        let mut instances = SharedInstances::new(&self.to);
+        matches
+            .iter()
+            .filter(|matching| {
+                self.apply_match_shared(egraph, matching, filter, report, Some(&mut instances))
             })
-            .filter(|x| *x)
             .count()
     }
+
+    /// Finds all matches of the left-hand side of the rule in `egraph` admitted by its guard,
+    /// adding their number and the time spent matching to `report`.
+    pub fn find_matches<A: Analysis>(
+        &self,
+        egraph: &EGraph<A>,
+        matcher: &(impl Matcher + ?Sized),
+        report: &mut RuleReport,
+    ) -> Vec<EGraphMatch> {
+        let start = Instant::now();
+        let mut matches = if metrics::ENABLED {
+            let mut counters = MatchCounters::default();
+            let matches = matcher.try_match_counted(egraph, &self.from, &mut counters);
+            let metrics = egraph.metrics();
+            metrics.match_attempts.add(counters.attempts as u64);
+            metrics.matches.add(counters.matches as u64);
+            matches
+        } else {
+            matcher.try_match(egraph, &self.from)
+        };
+        if !self.guard.is_empty() {
+            matches.retain(|matching| self.admits(egraph, matching));
+        }
+        report.matches += matches.len();
+        report.match_time += start.elapsed();
+        matches
+    }
+
+    /// `true` if the guard of the rule holds at `matching`, with each literal variable
+    /// standing for the least numeric literal in its class.
+    pub fn admits<A: Analysis>(&self, egraph: &EGraph<A>, matching: &EGraphMatch) -> bool {
+        self.guard.admits(|variable_id| {
+            let class_id = matching.try_class_variable(variable_id)?;
+            egraph
+                .class(class_id)
+                .iter_nodes()
+                .filter_map(|&node_id| match egraph.node(node_id) {
+                    Node::Literal(literal) if literal.is_numeric() => Some(literal.clone()),
+                    _ => None,
+                })
+                .min()
+        })
+    }
+
+    /// `true` if the guard of the rule holds at `matching` of its left-hand side
+    /// against an expression.
+    pub fn admits_expression_match(&self, matching: &ExpressionMatch) -> bool {
+        self.guard
+            .admits(|variable_id| match matching.at(variable_id)? {
+                Expression::Literal(literal) => Some(literal.clone()),
+                _ => None,
+            })
+    }
+
+    /// Applies the rule at `matching`, unless it is rejected by `filter`
+    /// or it would add new nodes to a frozen class.
+    /// Returns `true` if the e-graph has changed, in which case the application is added to `report`.
+    pub fn apply_match<A: Analysis>(
+        &self,
+        egraph: &mut EGraph<A>,
+        matching: &EGraphMatch,
+        filter: &dyn ApplicationFilter<A>,
+        report: &mut RuleReport,
+    ) -> bool {
+        self.apply_match_shared(egraph, matching, filter, report, None)
+    }
+
+    /// Same as [`Rule::apply_match`], but reuses the instances of the right-hand side
+    /// added by earlier applications in `instances`.
+    fn apply_match_shared<A: Analysis>(
+        &self,
+        egraph: &mut EGraph<A>,
+        matching: &EGraphMatch,
+        filter: &dyn ApplicationFilter<A>,
+        report: &mut RuleReport,
+        instances: Option<&mut SharedInstances>,
+    ) -> bool {
+        let start = Instant::now();
+        if !filter.accept(egraph, matching, self) {
+            return false;
+        }
+
+        let signature = instances
+            .as_ref()
+            .map(|instances| instances.signature(egraph, matching));
+        let shared = instances
+            .as_ref()
+            .zip(signature.as_ref())
+            .and_then(|(instances, signature)| instances.classes.get(signature).copied());
+        let added = match shared {
+            // The instance is already in the e-graph, adding it again would only find it
+            Some(class_id) => Seen::Old(class_id),
+            None => {
+                let to_add = self.to.clone().mixed_expression(matching);
+                if egraph.is_frozen(matching.root())
+                    && egraph.find_mixed_expression(&to_add).is_none()
+                {
+                    return false;
+                }
+                let added = egraph.add_mixed_expression(to_add);
+                if let (Some(instances), Some(signature)) = (instances, signature) {
+                    instances.classes.insert(signature, *added.as_ref().any());
+                }
+                added
+            }
+        };
+        let merged = egraph
+            .merge_classes(matching.root(), *added.as_ref().any())
+            .new()
+            .is_some();
+        if merged {
+            report.unions += 1;
+        }
+        let applied = merged || added.new().is_some();
+        if applied {
+            report.applications += 1;
+        }
+        report.apply_time += start.elapsed();
+        applied
+    }
+}
+
+/// Classes of the instances of a right-hand side added to an e-graph, keyed by the classes
+/// substituted for its variables in the order of their IDs.
+struct SharedInstances {
+    variables: Vec<VariableId>,
+    classes: HashMap<Vec<ClassId>, ClassId>,
+}
+
+impl SharedInstances {
+    fn new(to: &Expression) -> Self {
+        Self {
+            variables: to.variables().into_iter().sorted().collect(),
+            classes: HashMap::new(),
+        }
+    }
+
+    /// Canonical classes substituted by `matching` for the variables of the right-hand side.
+    fn signature(&self, egraph: &dyn DynEGraph, matching: &EGraphMatch) -> Vec<ClassId> {
+        self.variables
+            .iter()
+            .map(|&variable_id| egraph.canonical_class(matching.class_variable(variable_id)))
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -121,6 +486,120 @@ mod tests {
         assert_eq!(TopDownMatcher.try_match(&egraph, &expected).len(), 1);
     }
 
+    #[test]
+    fn shares_instances_of_right_hand_sides() {
+        use crate::rewriting::egraph::saturation::report::RuleReport;
+
+        let lang = Language::simple_math();
+        let expression = lang
+            .parse_no_vars("(+ (* (sin 1) 0) (+ (* (sin 2) 0) (* (sin 1) (sin 1))))")
+            .unwrap();
+        let rules = [
+            Rule::from_strings("(* $0 0)", "(- 0 0)", &lang),
+            Rule::from_strings("(* $0 $1)", "(+ $0 (sin $0))", &lang),
+        ];
+
+        for rule in &rules {
+            let mut shared = EGraph::<()>::from_expression(expression.clone());
+            let mut separate = shared.clone();
+            let applied = rule.apply(&mut shared, &TopDownMatcher);
+
+            let matches = TopDownMatcher.try_match(&separate, rule.from());
+            let separately_applied = matches
+                .iter()
+                .filter(|matching| {
+                    rule.apply_match(&mut separate, matching, &(), &mut RuleReport::default())
+                })
+                .count();
+
+            assert_eq!(applied, separately_applied);
+            assert_eq!(shared.actual_node_count(), separate.actual_node_count());
+            assert_eq!(shared.class_count(), separate.class_count());
+            assert!(shared.entails(&separate) && separate.entails(&shared));
+        }
+    }
+
+    #[test]
+    fn guarded_rule_application() {
+        use crate::rewriting::{
+            direct::{find_all_rewrite_positions_expr, rewrite_once},
+            guard::Guard,
+        };
+
+        let lang = Language::simple_math();
+        let mut egraph = EGraph::<()>::from_expression(
+            lang.parse_no_vars("(+ (/ 6 2) (/ 6 0) (/ 6 (sin 2)))")
+                .unwrap(),
+        );
+        let rule = Rule::from_strings("(/ $0 #1)", "(* $0 (/ 1 #1))", &lang)
+            .with_guard(Guard::parse("#1 != 0").unwrap());
+        assert_eq!(rule.label(&lang), "(/ $0 #1) => (* $0 (/ 1 #1)) if #1 != 0");
+
+        assert_eq!(rule.apply(&mut egraph, &TopDownMatcher), 1);
+        let applied = lang.parse("(* 6 (/ 1 2))").unwrap();
+        assert_eq!(TopDownMatcher.try_match(&egraph, &applied).len(), 1);
+
+        let expression = lang.parse("(+ (/ $0 4) (/ $0 0) (/ $0 $1))").unwrap();
+        let rules = [rule];
+        assert_eq!(
+            find_all_rewrite_positions_expr(&expression, &rules).len(),
+            1
+        );
+        assert_eq!(
+            rewrite_once(expression, &rules[0]).unwrap(),
+            lang.parse("(+ (* $0 (/ 1 4)) (/ $0 0) (/ $0 $1))").unwrap()
+        );
+    }
+
+    #[test]
+    fn sorted_rule_construction() {
+        use crate::language::{
+            expression::LiteralKind,
+            sorts::{Sorts, SymbolSignature},
+        };
+
+        let lang = Language::default().add_symbol("<").add_symbol("not");
+        let sorts = Sorts::new()
+            .with_signature(0, SymbolSignature::new(&["int", "int"], "bool"))
+            .with_signature(1, SymbolSignature::new(&["bool"], "bool"))
+            .with_literal_sort(LiteralKind::Int, "int");
+        let lang = lang.with_sorts(sorts);
+
+        let parse = |string| lang.parse(string).unwrap();
+
+        assert!(Rule::checked(parse("(not (not $0))"), parse("$0"), &lang).is_ok());
+        assert!(Rule::checked(parse("(not (< $0 $1))"), parse("(< $1 $0)"), &lang).is_ok());
+        assert!(Rule::checked(parse("(< $0 $0)"), parse("0"), &lang).is_err());
+        assert!(Rule::checked(parse("$0"), parse("(not $0)"), &lang).is_ok());
+        assert!(Rule::checked(parse("(< $0 1)"), parse("(not $0)"), &lang).is_err());
+    }
+
+    #[test]
+    fn rule_from_infix_strings() {
+        let lang = Language::simple_math();
+        assert_eq!(
+            Rule::from_infix_strings("x * (y + z)", "x * y + x * z", &lang),
+            Rule::from_strings("(* $0 (+ $1 $2))", "(+ (* $0 $1) (* $0 $2))", &lang)
+        );
+    }
+
+    #[test]
+    fn named_rules() {
+        let lang = Language::simple_math();
+        let rule = Rule::from_strings("(+ $0 0)", "$0", &lang);
+        assert_eq!(rule.name(), None);
+        assert_eq!(rule.label(&lang), "(+ $0 0) => $0");
+        assert_eq!(rule.reversed().name(), None);
+
+        let rule = rule.with_name("add-zero");
+        assert_eq!(rule.label(&lang), "add-zero");
+
+        let reversed = rule.reversed();
+        assert_eq!(reversed.name(), Some("add-zero-rev"));
+        assert_eq!(reversed.from(), rule.to());
+        assert_eq!(reversed.to(), rule.from());
+    }
+
     #[test]
     fn test_rule_serialization() {
         let lang = Language::simple_math();
@@ -129,5 +608,10 @@ mod tests {
         let deserialized: Rule = serde_json::from_str(&serialized).unwrap();
         assert_eq!(rule.from(), deserialized.from());
         assert_eq!(rule.to(), deserialized.to());
+
+        let rule = rule.with_name("double");
+        let serialized = serde_json::to_string(&rule).unwrap();
+        let deserialized: Rule = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(rule, deserialized);
     }
 }
@@ -0,0 +1,518 @@
+//! Searching for rewrite sequences between expressions.
+//!
+//! The search space has expressions as states and single rule applications, as in
+//! [`direct`](super::direct) rewriting, as transitions. [`neighbors`] generates the
+//! transitions and is shared by all search procedures, [`bfs_rewrite`] finds exact
+//! cheapest rewrite sequences and serves as the ground truth for heuristic searches
+//! such as [`a_star_rewrite`]. Every application costs [`Rule::cost`], which is 1 by default,
+//! so that the cheapest sequences are the shortest ones.
+//!
+//! Variables in expressions are treated as symbolic constants.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+
+use itertools::Itertools;
+
+use crate::compact::SinglyCompact;
+use crate::language::expression::Expression;
+use crate::rewriting::direct::{
+    RewritePosition, apply_rewrite_at_position_expr, find_all_rewrite_positions_expr,
+};
+use crate::rewriting::heuristic::{Heuristic, HeuristicState};
+use crate::rewriting::rule::Rule;
+
+/// A single rule application in a rewrite sequence.
+#[derive(Debug, Clone)]
+pub struct RewriteStep {
+    /// Where and which rule was applied
+    pub position: RewritePosition,
+    /// The expression after the application
+    pub expression: Expression,
+}
+
+/// Returns all expressions obtained from `expression` by a single application of one of `rules`.
+pub fn neighbors(expression: &Expression, rules: &[Rule]) -> Vec<RewriteStep> {
+    find_all_rewrite_positions_expr(expression, rules)
+        .into_iter()
+        .map(|position| RewriteStep {
+            expression: apply_rewrite_at_position_expr(expression.clone(), rules, &position),
+            position,
+        })
+        .collect()
+}
+
+/// Returns the total cost of the rule applications of `steps`, see [`Rule::cost`].
+pub fn sequence_cost(steps: &[RewriteStep], rules: &[Rule]) -> usize {
+    steps
+        .iter()
+        .map(|step| rules[step.position.rule_index].cost())
+        .sum()
+}
+
+/// Finds a cheapest sequence of rule applications rewriting `start` into `end`.
+/// If all applications have the same cost, this is breadth-first search finding
+/// a shortest sequence, otherwise it is uniform-cost search.
+///
+/// Expressions are not expanded after `max_depth` steps. As every expression is only reached
+/// by its cheapest sequence, with rules of different costs a sequence of at most `max_depth`
+/// steps may be missed if it passes through an expression reached more cheaply in more steps.
+///
+/// # Returns
+///
+/// Returns the steps of the sequence, which is empty if `start` equals `end`,
+/// or `None` if `end` cannot be reached in at most `max_depth` steps.
+pub fn bfs_rewrite(
+    start: &Expression,
+    end: &Expression,
+    rules: &[Rule],
+    max_depth: usize,
+) -> Option<Vec<RewriteStep>> {
+    bfs_rewrite_limited(start, end, rules, max_depth, usize::MAX)
+}
+
+/// Same as [`bfs_rewrite`], but gives up and returns `None` as soon as more than
+/// `max_visited` expressions are reached, which bounds the memory used by the search.
+pub fn bfs_rewrite_limited(
+    start: &Expression,
+    end: &Expression,
+    rules: &[Rule],
+    max_depth: usize,
+    max_visited: usize,
+) -> Option<Vec<RewriteStep>> {
+    if rules.iter().map(Rule::cost).all_equal() {
+        unit_cost_rewrite(start, end, rules, max_depth, max_visited)
+    } else {
+        uniform_cost_rewrite(start, end, rules, max_depth, max_visited)
+    }
+}
+
+fn unit_cost_rewrite(
+    start: &Expression,
+    end: &Expression,
+    rules: &[Rule],
+    max_depth: usize,
+    max_visited: usize,
+) -> Option<Vec<RewriteStep>> {
+    // Every reached expression with the step reaching it, `None` for `start`
+    let mut parents: HashMap<Expression, Option<(Expression, RewritePosition)>> =
+        HashMap::from([(start.clone(), None)]);
+    let mut queue = VecDeque::from([(start.clone(), 0)]);
+
+    while let Some((expression, depth)) = queue.pop_front() {
+        if expression == *end {
+            return Some(reconstruct(&parents, expression));
+        }
+        if depth == max_depth {
+            continue;
+        }
+
+        for step in neighbors(&expression, rules) {
+            if !parents.contains_key(&step.expression) {
+                parents.insert(
+                    step.expression.clone(),
+                    Some((expression.clone(), step.position)),
+                );
+                queue.push_back((step.expression, depth + 1));
+            }
+        }
+        if parents.len() > max_visited {
+            return None;
+        }
+    }
+
+    None
+}
+
+fn uniform_cost_rewrite(
+    start: &Expression,
+    end: &Expression,
+    rules: &[Rule],
+    max_depth: usize,
+    max_visited: usize,
+) -> Option<Vec<RewriteStep>> {
+    let mut parents: HashMap<Expression, Option<(Expression, RewritePosition)>> =
+        HashMap::from([(start.clone(), None)]);
+    let mut costs = HashMap::from([(start.clone(), 0)]);
+    // Ordered by costs so far, then by the numbers of steps so far
+    let mut queue = BinaryHeap::from([Reverse((0, 0, 0))]);
+    let mut queued = vec![start.clone()];
+
+    while let Some(Reverse((cost, depth, index))) = queue.pop() {
+        let expression = queued[index].clone();
+        if costs[&expression] < cost {
+            continue;
+        }
+        if expression == *end {
+            return Some(reconstruct(&parents, expression));
+        }
+        if depth == max_depth {
+            continue;
+        }
+
+        for step in neighbors(&expression, rules) {
+            let next_cost = cost + rules[step.position.rule_index].cost();
+            if costs
+                .get(&step.expression)
+                .is_some_and(|&known| known <= next_cost)
+            {
+                continue;
+            }
+
+            costs.insert(step.expression.clone(), next_cost);
+            parents.insert(
+                step.expression.clone(),
+                Some((expression.clone(), step.position)),
+            );
+            queue.push(Reverse((next_cost, depth + 1, queued.len())));
+            queued.push(step.expression);
+        }
+        if costs.len() > max_visited {
+            return None;
+        }
+    }
+
+    None
+}
+
+/// Finds a sequence of rule applications rewriting `start` into `end` by A* search
+/// guided by `heuristic`, which should estimate distances to `end`.
+/// Expressions for which `heuristic` is infinite are never expanded.
+///
+/// The sequence is a cheapest one if `heuristic` never overestimates the distances.
+/// As heuristics bound the numbers of steps, their estimates are multiplied by the smallest
+/// cost of `rules`, which keeps them admissible for rules of different costs.
+/// Estimates of neighbors are computed incrementally from the states of the expanded expressions,
+/// see [`Heuristic::lower_bound_dist_after`].
+///
+/// # Returns
+///
+/// Returns the steps of the sequence, or `None` if `end` was not reached
+/// after expanding `max_expansions` expressions.
+pub fn a_star_rewrite(
+    start: &Expression,
+    end: &Expression,
+    rules: &[Rule],
+    heuristic: &dyn Heuristic,
+    max_expansions: usize,
+) -> Option<Vec<RewriteStep>> {
+    let (SinglyCompact::Finite(estimate), state) = heuristic.lower_bound_dist_with_state(start)
+    else {
+        return None;
+    };
+    let scale = rules.iter().map(Rule::cost).min().unwrap_or(1);
+
+    let mut parents: HashMap<Expression, Option<(Expression, RewritePosition)>> =
+        HashMap::from([(start.clone(), None)]);
+    let mut distances = HashMap::from([(start.clone(), 0)]);
+    // Ordered by estimated total costs, then by the costs so far,
+    // the last elements keep the order of insertion among ties
+    let mut queue = BinaryHeap::from([Reverse((estimate as usize * scale, 0, 0))]);
+    let mut queued: Vec<(Expression, HeuristicState)> = vec![(start.clone(), state)];
+    let mut expansions = 0;
+
+    while let Some(Reverse((_, distance, index))) = queue.pop() {
+        let expression = queued[index].0.clone();
+        if distances[&expression] < distance {
+            continue;
+        }
+        if expression == *end {
+            return Some(reconstruct(&parents, expression));
+        }
+        if expansions == max_expansions {
+            return None;
+        }
+        expansions += 1;
+
+        for step in neighbors(&expression, rules) {
+            let next_distance = distance + rules[step.position.rule_index].cost();
+            if distances
+                .get(&step.expression)
+                .is_some_and(|&known| known <= next_distance)
+            {
+                continue;
+            }
+            let (SinglyCompact::Finite(estimate), state) = heuristic.lower_bound_dist_after(
+                &queued[index].1,
+                &step.position,
+                &step.expression,
+            ) else {
+                continue;
+            };
+
+            distances.insert(step.expression.clone(), next_distance);
+            parents.insert(
+                step.expression.clone(),
+                Some((expression.clone(), step.position)),
+            );
+            queue.push(Reverse((
+                next_distance + estimate as usize * scale,
+                next_distance,
+                queued.len(),
+            )));
+            queued.push((step.expression, state));
+        }
+    }
+
+    None
+}
+
+/// How [`minimize_sequence`] shortens a rewrite sequence.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Minimization {
+    /// Drops the parts of the sequence returning to an expression it already passed
+    /// and replaces the longest parts by single applications which are not more expensive,
+    /// going from the start of the sequence to its end
+    Greedy,
+    /// Replaces the sequence by a cheapest one between its endpoints found by
+    /// [`bfs_rewrite_limited`], or falls back to [`Minimization::Greedy`] if more than
+    /// `max_visited` expressions are reached
+    Optimal { max_visited: usize },
+}
+
+/// Shortens `steps` rewriting `start`, e.g. found by [`a_star_rewrite`], into a sequence
+/// rewriting `start` into the same expression at no higher cost, so that proofs written
+/// from it, see [`proof_trace`](crate::utils::proof_export::proof_trace), are short.
+pub fn minimize_sequence(
+    start: &Expression,
+    steps: &[RewriteStep],
+    rules: &[Rule],
+    minimization: Minimization,
+) -> Vec<RewriteStep> {
+    let Some(last) = steps.last() else {
+        return Vec::new();
+    };
+
+    if let Minimization::Optimal { max_visited } = minimization
+        && let Some(shortest) =
+            bfs_rewrite_limited(start, &last.expression, rules, steps.len(), max_visited)
+        && sequence_cost(&shortest, rules) <= sequence_cost(steps, rules)
+    {
+        return shortest;
+    }
+
+    // `expressions[i]` is the expression after `i` steps
+    let expressions: Vec<_> = std::iter::once(start)
+        .chain(steps.iter().map(|step| &step.expression))
+        .collect();
+    let mut minimized = Vec::new();
+    let mut index = 0;
+    while index < steps.len() {
+        if let Some(later) =
+            (index + 1..=steps.len()).rfind(|&later| expressions[later] == expressions[index])
+        {
+            index = later;
+            continue;
+        }
+
+        let candidates = neighbors(expressions[index], rules);
+        let (next, step) = (index + 1..=steps.len())
+            .rev()
+            .find_map(|later| {
+                let budget = sequence_cost(&steps[index..later], rules);
+                candidates
+                    .iter()
+                    .filter(|candidate| candidate.expression == *expressions[later])
+                    .filter(|candidate| rules[candidate.position.rule_index].cost() <= budget)
+                    .min_by_key(|candidate| rules[candidate.position.rule_index].cost())
+                    .map(|candidate| (later, candidate.clone()))
+            })
+            .unwrap_or_else(|| (index + 1, steps[index].clone()));
+        minimized.push(step);
+        index = next;
+    }
+
+    minimized
+}
+
+fn reconstruct(
+    parents: &HashMap<Expression, Option<(Expression, RewritePosition)>>,
+    mut expression: Expression,
+) -> Vec<RewriteStep> {
+    let mut steps = Vec::new();
+    while let Some((parent, position)) = &parents[&expression] {
+        steps.push(RewriteStep {
+            position: position.clone(),
+            expression,
+        });
+        expression = parent.clone();
+    }
+    steps.reverse();
+    steps
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compact::SinglyCompact;
+    use crate::language::{Language, expression::Expression};
+    use crate::macros::rules;
+    use crate::rewriting::direct::apply_rewrite_at_position_expr;
+    use crate::rewriting::rule::Rule;
+
+    use crate::rewriting::heuristic::Heuristic;
+
+    use super::{
+        Minimization, RewriteStep, a_star_rewrite, bfs_rewrite, bfs_rewrite_limited,
+        minimize_sequence, neighbors, sequence_cost,
+    };
+
+    struct Constant(SinglyCompact<u32>);
+
+    impl Heuristic for Constant {
+        fn lower_bound_dist(&self, _: &Expression) -> SinglyCompact<u32> {
+            self.0
+        }
+    }
+
+    #[test]
+    fn finds_shortest_sequence() {
+        let lang = Language::simple_math();
+        let rules = rules!(lang;
+            "(+ $0 $1)" => "(+ $1 $0)",
+            "(+ $0 0)" => "$0",
+            "(* $0 1)" => "$0",
+            "(* $0 $1)" => "(* $1 $0)",
+        );
+        let start = lang.parse("(+ 0 (* 1 $0))").unwrap();
+        let end = lang.parse("$0").unwrap();
+
+        let steps = bfs_rewrite(&start, &end, &rules, 5).unwrap();
+        assert_eq!(steps.len(), 4);
+        assert_eq!(steps.last().unwrap().expression, end);
+
+        let mut expression = start.clone();
+        for step in &steps {
+            expression = apply_rewrite_at_position_expr(expression, &rules, &step.position);
+            assert_eq!(expression, step.expression);
+        }
+
+        assert!(bfs_rewrite(&start, &end, &rules, 3).is_none());
+        assert!(bfs_rewrite(&start, &start, &rules, 0).unwrap().is_empty());
+
+        assert_eq!(
+            bfs_rewrite_limited(&start, &end, &rules, 5, 1000)
+                .unwrap()
+                .len(),
+            4
+        );
+        assert!(bfs_rewrite_limited(&start, &end, &rules, 5, 3).is_none());
+    }
+
+    /// Steps rewriting `start` into the expressions of `path` one after another.
+    fn steps_through(start: &Expression, path: &[Expression], rules: &[Rule]) -> Vec<RewriteStep> {
+        let mut expression = start;
+        path.iter()
+            .map(|next| {
+                let step = neighbors(expression, rules)
+                    .into_iter()
+                    .find(|step| step.expression == *next)
+                    .unwrap();
+                expression = next;
+                step
+            })
+            .collect()
+    }
+
+    #[test]
+    fn minimizes_sequences() {
+        let lang = Language::simple_math();
+        let rules = rules!(lang;
+            "(+ $0 $1)" => "(+ $1 $0)",
+            "(+ $0 0)" => "$0",
+            "(+ 0 $0)" => "$0",
+        );
+        let parse = |expression: &str| lang.parse(expression).unwrap();
+        let start = parse("(+ 0 (+ 2 0))");
+        // Swaps back and forth, then takes a detour through a swap
+        let path = [
+            parse("(+ (+ 2 0) 0)"),
+            parse("(+ 0 (+ 2 0))"),
+            parse("(+ 0 (+ 0 2))"),
+            parse("(+ 0 2)"),
+            parse("(+ 2 0)"),
+            parse("2"),
+        ];
+        let steps = steps_through(&start, &path, &rules);
+
+        let greedy = minimize_sequence(&start, &steps, &rules, Minimization::Greedy);
+        let greedy_path: Vec<_> = greedy.iter().map(|step| step.expression.clone()).collect();
+        // The outer zero is removed at once, skipping the cycle and the detour
+        assert_eq!(greedy_path, [parse("(+ 2 0)"), parse("2")]);
+
+        let optimal = minimize_sequence(
+            &start,
+            &steps,
+            &rules,
+            Minimization::Optimal { max_visited: 1000 },
+        );
+        assert_eq!(optimal.len(), 2);
+        assert_eq!(optimal.last().unwrap().expression, parse("2"));
+        // Without enough budget the greedy sequence is returned
+        let limited = minimize_sequence(
+            &start,
+            &steps,
+            &rules,
+            Minimization::Optimal { max_visited: 1 },
+        );
+        assert_eq!(limited.len(), greedy.len());
+
+        let mut expression = start.clone();
+        for step in &greedy {
+            expression = apply_rewrite_at_position_expr(expression, &rules, &step.position);
+            assert_eq!(expression, step.expression);
+        }
+        assert!(minimize_sequence(&start, &[], &rules, Minimization::Greedy).is_empty());
+    }
+
+    #[test]
+    fn a_star_agrees_with_bfs() {
+        let lang = Language::simple_math();
+        let rules = rules!(lang;
+            "(+ $0 $1)" => "(+ $1 $0)",
+            "(+ $0 0)" => "$0",
+            "(* $0 1)" => "$0",
+            "(* $0 $1)" => "(* $1 $0)",
+        );
+        let start = lang.parse("(+ 0 (* 1 (+ $0 0)))").unwrap();
+        let end = lang.parse("$0").unwrap();
+        let blind = Constant(SinglyCompact::Finite(0));
+
+        let shortest = bfs_rewrite(&start, &end, &rules, 10).unwrap();
+        let steps = a_star_rewrite(&start, &end, &rules, &blind, 1000).unwrap();
+        assert_eq!(steps.len(), shortest.len());
+        assert_eq!(steps.last().unwrap().expression, end);
+        assert!(a_star_rewrite(&start, &end, &rules, &blind, 2).is_none());
+
+        let unreachable = Constant(SinglyCompact::Infinite);
+        assert!(a_star_rewrite(&start, &end, &rules, &unreachable, 1000).is_none());
+    }
+    #[test]
+    fn weighted_rules() {
+        let lang = Language::simple_math();
+        let rules = vec![
+            Rule::from_strings("(sin $0)", "$0", &lang).with_cost(10),
+            Rule::from_strings("(sin $0)", "(cos $0)", &lang),
+            Rule::from_strings("(cos $0)", "$0", &lang),
+        ];
+        let start = lang.parse("(sin $0)").unwrap();
+        let end = lang.parse("$0").unwrap();
+
+        let cheapest = bfs_rewrite(&start, &end, &rules, 5).unwrap();
+        assert_eq!(cheapest.len(), 2);
+        assert_eq!(sequence_cost(&cheapest, &rules), 2);
+
+        // Every sequence takes at least one step
+        let steps = a_star_rewrite(
+            &start,
+            &end,
+            &rules,
+            &Constant(SinglyCompact::Finite(1)),
+            100,
+        )
+        .unwrap();
+        assert_eq!(sequence_cost(&steps, &rules), 2);
+
+        let unweighted: Vec<_> = rules.iter().map(|rule| rule.clone().with_cost(1)).collect();
+        assert_eq!(bfs_rewrite(&start, &end, &unweighted, 5).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,81 @@
+//! One-call simplification of expressions by equality saturation.
+//!
+//! [`simplify`] builds an e-graph of an expression, saturates it with the rules of a system
+//! and extracts the cheapest equivalent expression, covering the common use case without
+//! choosing an analysis, a matcher, a saturator and an extractor by hand.
+
+use crate::language::expression::VarFreeExpression;
+use crate::rewriting::egraph::{
+    EGraph,
+    class::term_size::TermSize,
+    extraction::{CostTable, Extractor, TableExtractor},
+    matching::bottom_up::BottomUpMatcher,
+    saturation::{SaturationConfig, SaturationReport, Saturator, SimpleSaturator},
+};
+use crate::rewriting::system::TermRewritingSystem;
+
+/// Simplifies `expression` with the rules of `trs`, returning the cheapest equivalent expression
+/// found according to `costs` together with its cost and the report of the saturation.
+///
+/// Rules are applied in the round-robin order until the e-graph saturates or one of `limits`
+/// is hit, so unless the rules terminate, e.g. because none of them grows expressions,
+/// `limits` should bound the saturation. Term bounds of `limits` are respected.
+///
+/// Returns `None` if no expression equivalent to `expression` can be extracted.
+pub fn simplify(
+    expression: &VarFreeExpression,
+    trs: &TermRewritingSystem,
+    costs: &CostTable,
+    limits: &SaturationConfig,
+) -> Option<(VarFreeExpression, usize, SaturationReport)> {
+    let (mut egraph, root) = EGraph::<TermSize>::from_expression_with_id(expression.clone());
+    let report =
+        SimpleSaturator::new(Box::new(BottomUpMatcher)).saturate(&mut egraph, trs.rules(), limits);
+
+    let result = TableExtractor::new(costs, trs.language()).extract_cone(&egraph, root)?;
+    let cost = *result.cost();
+    Some((result.winner().clone(), cost, report))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::language::Language;
+    use crate::macros::rules;
+    use crate::rewriting::egraph::{
+        extraction::CostTable,
+        saturation::{SaturationConfig, SaturationStopReason},
+    };
+    use crate::rewriting::system::TermRewritingSystem;
+
+    use super::simplify;
+
+    #[test]
+    fn simplifies_to_cheapest_expression() {
+        let lang = Language::simple_math();
+        let rules = rules!(lang;
+            "(* $0 1)" => "$0",
+            "(+ $0 $1)" => "(+ $1 $0)",
+            "(+ $0 0)" => "$0",
+        );
+        let trs = TermRewritingSystem::new(lang.clone(), rules);
+        let costs = CostTable {
+            default: 1,
+            ..Default::default()
+        };
+
+        let expression = lang.parse_no_vars("(+ 0 (* 7 1))").unwrap();
+        let (simplified, cost, report) =
+            simplify(&expression, &trs, &costs, &SaturationConfig::default()).unwrap();
+        assert_eq!(simplified, lang.parse_no_vars("7").unwrap());
+        assert_eq!(cost, 1);
+        assert_eq!(report.stop_reason, Some(SaturationStopReason::Saturated));
+
+        let limits = SaturationConfig {
+            max_applications: Some(1),
+            ..Default::default()
+        };
+        let (simplified, cost, _) = simplify(&expression, &trs, &costs, &limits).unwrap();
+        assert_eq!(simplified, lang.parse_no_vars("(+ 0 7)").unwrap());
+        assert_eq!(cost, 3);
+    }
+}
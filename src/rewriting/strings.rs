@@ -2,16 +2,204 @@
 //!
 //! This module implements "induced string rewriting" which converts
 //! expressions and rewrite rules into a string language where all symbols
-//! have arity 0 or 1.
+//! have arity 0 or 1. [`StringRewritingSystem`] bundles the induced language
+//! and rules of a term rewriting system together with their abelianized matrix,
+//! and can run the induced rules on paths represented as unary chains in e-graphs.
 
 use crate::language::{
     Language,
     arities::Arities,
-    expression::{Expression, OwnedPath, VariableId},
+    expression::{AnyExpression, Expression, Literal, OwnedPath, VarFreeExpression, VariableId},
     symbol::{Symbol, SymbolId},
 };
-use crate::rewriting::rule::Rule;
+use crate::rewriting::{
+    egraph::{
+        Analysis, ClassId, DynEGraph, EGraph,
+        matching::Matcher,
+        saturation::{SaturationConfig, SaturationReport, Saturator},
+    },
+    reachability::{ReachabilityResult, terms_reachable_round_robin},
+    rule::Rule,
+    system::TermRewritingSystem,
+};
 use nalgebra::{DMatrix, DVector};
+use std::ops::Sub;
+
+/// The induced string rewriting system of a term rewriting system.
+///
+/// Owns the induced string language, the induced rules of all rules of the system
+/// and the abelianized matrix of the induced rules, together with the original language
+/// and arities needed to convert expressions to paths in the string language.
+#[derive(Debug, Clone)]
+pub struct StringRewritingSystem {
+    language: Language,
+    arities: Arities,
+    string_language: Language,
+    rules: Vec<Rule>,
+    abelian_matrix: DMatrix<i32>,
+}
+
+impl StringRewritingSystem {
+    /// Creates the induced string rewriting system of `trs`, with symbols of arities `arities`.
+    pub fn from_trs(trs: &TermRewritingSystem, arities: &Arities) -> Self {
+        let language = trs.language().clone();
+        let string_language = to_string_language(&language, arities);
+        let rules: Vec<_> = trs
+            .rules()
+            .iter()
+            .flat_map(|rule| rule_to_induced_rules(rule, &language, &string_language, arities))
+            .collect();
+        let abelian_matrix = rules_to_abelian_matrix(&rules, &string_language);
+
+        Self {
+            language,
+            arities: arities.clone(),
+            string_language,
+            rules,
+            abelian_matrix,
+        }
+    }
+
+    /// Returns the language of the original term rewriting system.
+    pub fn language(&self) -> &Language {
+        &self.language
+    }
+
+    /// Returns the arities of symbols of the original language.
+    pub fn arities(&self) -> &Arities {
+        &self.arities
+    }
+
+    /// Returns the induced string language.
+    pub fn string_language(&self) -> &Language {
+        &self.string_language
+    }
+
+    /// Returns the induced rules, in the order of rules of the original system.
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Returns the abelianized matrix of the induced rules, see [`rules_to_abelian_matrix`].
+    pub fn abelian_matrix(&self) -> &DMatrix<i32> {
+        &self.abelian_matrix
+    }
+
+    /// Returns the paths of `expr` in the string language, see [`expression_to_paths`].
+    pub fn expression_to_paths(&self, expr: &Expression) -> Vec<Expression> {
+        expression_to_paths(expr, &self.language, &self.string_language, &self.arities)
+    }
+
+    /// Returns abelianized vectors of paths to variables of `expr`,
+    /// see [`get_path_abelian_vectors_to_variables`].
+    pub fn path_abelian_vectors_to_variables(&self, expr: &Expression) -> Vec<PathAbelianVector> {
+        get_path_abelian_vectors_to_variables(
+            expr,
+            &self.language,
+            &self.string_language,
+            &self.arities,
+        )
+    }
+
+    /// Returns abelianized vectors of paths to variables of `expr` passing through
+    /// the subexpression at `position`, i.e. the paths changed by a rewrite at `position`.
+    pub fn path_abelian_vectors_below(
+        &self,
+        expr: &Expression,
+        position: &OwnedPath,
+    ) -> Vec<PathAbelianVector> {
+        let Some(subexpression) = expr.subexpression(position.as_path()) else {
+            return Vec::new();
+        };
+
+        let var_paths = subexpression
+            .find_all_variables()
+            .into_iter()
+            .map(|(var_id, paths)| {
+                let paths = paths
+                    .into_iter()
+                    .map(|path| OwnedPath(position.0.iter().chain(&path.0).copied().collect()))
+                    .collect();
+                (var_id, paths)
+            });
+        path_abelian_vectors(
+            expr,
+            var_paths,
+            &self.language,
+            &self.string_language,
+            &self.arities,
+        )
+    }
+
+    /// Returns the induced rules as a term rewriting system over the string language.
+    pub fn to_trs(&self) -> TermRewritingSystem {
+        TermRewritingSystem::new(self.string_language.clone(), self.rules.clone())
+    }
+
+    /// Adds unary chains of `paths` (see [`path_to_chain`]) to a new e-graph and saturates it
+    /// with the induced rules using `saturator`.
+    ///
+    /// # Returns
+    ///
+    /// Returns the saturated e-graph, canonical IDs of classes of the paths in their order
+    /// and the saturation report
+    pub fn saturate_paths<A: Analysis>(
+        &self,
+        paths: &[Expression],
+        saturator: &dyn Saturator<A>,
+        config: &SaturationConfig,
+    ) -> (EGraph<A>, Vec<ClassId>, SaturationReport) {
+        let mut egraph = EGraph::<A>::default();
+        let nodes: Vec<_> = paths
+            .iter()
+            .map(|path| egraph.add_expression(path_to_chain(path)))
+            .collect();
+
+        let report = saturator.saturate(&mut egraph, &self.rules, config);
+        let classes = nodes
+            .into_iter()
+            .map(|node_id| egraph.containing_class(node_id))
+            .collect();
+
+        (egraph, classes, report)
+    }
+
+    /// Checks whether paths `from` and `to` reach a common form with the induced rules,
+    /// see [`terms_reachable_round_robin`].
+    ///
+    /// Comparing the result with the abelianized relaxation shows how tight
+    /// the abelian heuristic is.
+    pub fn paths_reachable<A: Analysis>(
+        &self,
+        from: &Expression,
+        to: &Expression,
+        config: &SaturationConfig,
+        matcher: &dyn Matcher,
+    ) -> ReachabilityResult<A> {
+        terms_reachable_round_robin(
+            &self.rules,
+            path_to_chain(from),
+            path_to_chain(to),
+            config,
+            matcher,
+        )
+    }
+}
+
+/// Converts a path in the string language to a unary chain which can be added to an e-graph.
+///
+/// Variables, which end paths to variables, are replaced by string literals
+/// with their names, so that paths ending at the same variable end with the same literal.
+/// Induced rules match such chains at any position, like string rewriting rules.
+pub fn path_to_chain(path: &Expression) -> VarFreeExpression {
+    match path {
+        Expression::Literal(literal) => VarFreeExpression::Literal(literal.clone()),
+        Expression::Variable(variable_id) => {
+            VarFreeExpression::Literal(Literal::Str(Expression::variable_name(*variable_id)))
+        }
+        Expression::Symbol(symbol) => VarFreeExpression::Symbol(symbol.map_children(path_to_chain)),
+    }
+}
 
 /// Converts a language to its induced string language.
 ///
@@ -21,10 +209,14 @@ use nalgebra::{DMatrix, DVector};
 /// - n-arity symbols (n > 1) are converted to n different 1-arity symbols:
 ///   symbol `s` becomes `s_1`, `s_2`, ..., `s_n`
 ///
+/// Symbols with several arities are indexed up to the largest of them, so that
+/// the i-th child of `s` is below `s_i` whatever the number of children of `s`.
+/// If such a symbol also has arity 0, it additionally keeps its name for its leaves.
+///
 /// # Arguments
 ///
 /// * `lang` - The original language to convert
-/// * `arities` - A mapping from symbol IDs to their typical arity (number of children)
+/// * `arities` - A mapping from symbol IDs to their arities (numbers of children)
 ///
 /// # Returns
 ///
@@ -34,7 +226,7 @@ pub fn to_string_language(lang: &Language, arities: &Arities) -> Language {
 
     for symbol_id in 0..lang.symbol_count() {
         let symbol_name = lang.get_symbol(symbol_id);
-        let arity = arities.get_first(symbol_id).unwrap_or(0);
+        let arity = arities.get_max(symbol_id).unwrap_or(0);
 
         match arity {
             0 | 1 => {
@@ -42,6 +234,10 @@ pub fn to_string_language(lang: &Language, arities: &Arities) -> Language {
                 string_lang = string_lang.add_symbol(symbol_name);
             }
             n => {
+                // Leaves of variadic symbols are written with their names
+                if arities.has_arity(symbol_id, 0) {
+                    string_lang = string_lang.add_symbol(symbol_name);
+                }
                 // For n-arity symbols (n > 1), create n different 1-arity symbols
                 for i in 1..=n {
                     let indexed_name = format!("{}_{}", symbol_name, i);
@@ -69,7 +265,9 @@ pub fn to_string_language(lang: &Language, arities: &Arities) -> Language {
 ///
 /// # Returns
 ///
-/// Returns a vector of expressions, each representing a path from root to a leaf
+/// Returns a vector of expressions, each representing a path from root to a leaf.
+/// Leaves which are variadic symbols without a declared arity of 0 are skipped,
+/// as they have no symbol in the string language.
 pub fn expression_to_paths(
     expr: &Expression,
     lang: &Language,
@@ -147,15 +345,18 @@ fn handle_symbol_path(
     current_path: &mut Vec<Expression>,
     paths: &mut Vec<Expression>,
 ) {
-    let arity = arities
-        .get_first(symbol.id)
-        .unwrap_or(symbol.children.len());
+    let arity = arities.get_max(symbol.id).unwrap_or(symbol.children.len());
 
     if symbol.children.is_empty() {
-        // 0-arity symbol is a leaf
+        // 0-arity symbol is a leaf. Variadic symbols without a declared arity of 0 have no
+        // leaf symbol in the string language, so like other paths which cannot be
+        // represented, paths to them are skipped
+        let Some(id) = string_lang.try_get_id(lang.get_symbol(symbol.id)) else {
+            return;
+        };
         let mut path = current_path.clone();
         path.push(Expression::Symbol(Symbol {
-            id: symbol.id,
+            id,
             children: vec![],
         }));
 
@@ -313,9 +514,7 @@ fn path_to_expression(
             return None;
         };
 
-        let arity = arities
-            .get_first(symbol.id)
-            .unwrap_or(symbol.children.len());
+        let arity = arities.get_max(symbol.id).unwrap_or(symbol.children.len());
 
         // Convert symbol to its string language version
         let indexed_symbol_id = to_string_symbol(symbol.id, child_idx, lang, string_lang, arity);
@@ -335,10 +534,116 @@ fn path_to_expression(
     build_path_expression(&path_elements)
 }
 
+/// A sparse abelianized vector, storing only the nonzero counts of symbols.
+///
+/// Paths in induced string languages contain few of their many symbols, so their abelianized
+/// vectors are stored as lists of `(symbol, count)` pairs sorted by symbol IDs.
+/// They are converted to dense vectors only when passed to the ILP solver.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SparseAbelianVector {
+    entries: Vec<(SymbolId, i32)>,
+}
+
+impl SparseAbelianVector {
+    /// Counts the symbols of `expr`.
+    pub fn from_expression(expr: &Expression) -> Self {
+        let mut symbols = Vec::new();
+        collect_symbols_impl(expr, &mut symbols);
+        symbols.sort_unstable();
+
+        let mut entries: Vec<(SymbolId, i32)> = Vec::new();
+        for symbol_id in symbols {
+            match entries.last_mut() {
+                Some((last_id, count)) if *last_id == symbol_id => *count += 1,
+                _ => entries.push((symbol_id, 1)),
+            }
+        }
+
+        Self { entries }
+    }
+
+    /// Creates a sparse vector from a dense one.
+    pub fn from_dense(vector: &DVector<i32>) -> Self {
+        Self {
+            entries: vector
+                .iter()
+                .enumerate()
+                .filter(|(_, count)| **count != 0)
+                .map(|(symbol_id, &count)| (symbol_id, count))
+                .collect(),
+        }
+    }
+
+    /// Converts the vector to a dense one with `dimension` entries.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the vector has a nonzero entry at an index not smaller than `dimension`
+    pub fn to_dense(&self, dimension: usize) -> DVector<i32> {
+        let mut dense = DVector::zeros(dimension);
+        for &(symbol_id, count) in &self.entries {
+            dense[symbol_id] = count;
+        }
+        dense
+    }
+
+    /// Returns the count of symbol `symbol_id`.
+    pub fn get(&self, symbol_id: SymbolId) -> i32 {
+        self.entries
+            .binary_search_by_key(&symbol_id, |&(id, _)| id)
+            .map_or(0, |index| self.entries[index].1)
+    }
+
+    /// Iterates over nonzero entries in the order of symbol IDs.
+    pub fn iter(&self) -> impl Iterator<Item = (SymbolId, i32)> + '_ {
+        self.entries.iter().copied()
+    }
+
+    /// `true` if all entries are zero.
+    pub fn is_zero(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Sub for &SparseAbelianVector {
+    type Output = SparseAbelianVector;
+
+    fn sub(self, other: &SparseAbelianVector) -> SparseAbelianVector {
+        let mut entries = Vec::with_capacity(self.entries.len() + other.entries.len());
+        let (mut left, mut right) = (self.iter().peekable(), other.iter().peekable());
+
+        loop {
+            let entry = match (left.peek(), right.peek()) {
+                (Some(&(left_id, _)), Some(&(right_id, _))) if left_id < right_id => left.next(),
+                (Some(&(left_id, _)), Some(&(right_id, _))) if left_id > right_id => {
+                    right.next().map(|(id, count)| (id, -count))
+                }
+                (Some(_), Some(_)) => {
+                    let (id, left_count) = left.next().unwrap();
+                    let (_, right_count) = right.next().unwrap();
+                    Some((id, left_count - right_count))
+                }
+                (Some(_), None) => left.next(),
+                (None, Some(_)) => right.next().map(|(id, count)| (id, -count)),
+                (None, None) => break,
+            };
+
+            if let Some(entry) = entry
+                && entry.1 != 0
+            {
+                entries.push(entry);
+            }
+        }
+
+        SparseAbelianVector { entries }
+    }
+}
+
 /// Converts an expression to its abelianized vector.
 ///
 /// The abelianized vector has dimension equal to the number of symbols in the language.
 /// Each coordinate represents the count of how many times that symbol appears in the expression.
+/// See [`SparseAbelianVector::from_expression`] for a sparse version.
 ///
 /// # Arguments
 ///
@@ -349,27 +654,21 @@ fn path_to_expression(
 ///
 /// Returns a `DVector` where the i-th entry is the count of symbol i in the expression
 pub fn expression_to_abelian_vector(expr: &Expression, lang: &Language) -> DVector<i32> {
-    let symbol_count = lang.symbol_count();
-    let mut counts = vec![0i32; symbol_count];
-
-    count_symbols_impl(expr, &mut counts);
-
-    DVector::from_vec(counts)
+    SparseAbelianVector::from_expression(expr).to_dense(lang.symbol_count())
 }
 
-/// Helper function to recursively count symbols in an expression
-fn count_symbols_impl(expr: &Expression, counts: &mut [i32]) {
+/// Helper function to recursively collect IDs of all symbols in an expression
+fn collect_symbols_impl(expr: &Expression, symbols: &mut Vec<SymbolId>) {
     match expr {
         Expression::Literal(_) | Expression::Variable(_) => {
             // Literals and variables don't contribute to symbol counts
         }
         Expression::Symbol(symbol) => {
-            // Increment count for this symbol
-            counts[symbol.id] += 1;
+            symbols.push(symbol.id);
 
-            // Recursively count symbols in children
+            // Recursively collect symbols in children
             for child in &symbol.children {
-                count_symbols_impl(child, counts);
+                collect_symbols_impl(child, symbols);
             }
         }
     }
@@ -402,12 +701,12 @@ pub fn rules_to_abelian_matrix(rules: &[Rule], lang: &Language) -> DMatrix<i32>
     let mut matrix_data = vec![0i32; symbol_count * rule_count];
 
     for (rule_idx, rule) in rules.iter().enumerate() {
-        let left_vec = expression_to_abelian_vector(rule.from(), lang);
-        let right_vec = expression_to_abelian_vector(rule.to(), lang);
-        let diff_vec = right_vec - left_vec;
+        let left_vec = SparseAbelianVector::from_expression(rule.from());
+        let right_vec = SparseAbelianVector::from_expression(rule.to());
+        let diff_vec = &right_vec - &left_vec;
 
-        for symbol_idx in 0..symbol_count {
-            matrix_data[rule_idx * symbol_count + symbol_idx] = diff_vec[symbol_idx];
+        for (symbol_idx, count) in diff_vec.iter() {
+            matrix_data[rule_idx * symbol_count + symbol_idx] = count;
         }
     }
 
@@ -416,13 +715,16 @@ pub fn rules_to_abelian_matrix(rules: &[Rule], lang: &Language) -> DMatrix<i32>
 
 /// Represents a stringified abelianized vector for a path from root to a variable.
 ///
-/// Contains the abelianized vector of the path and the variable ID it ends at.
+/// Contains the abelianized vector of the path, the variable ID it ends at
+/// and the position of the variable occurrence.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PathAbelianVector {
     /// The abelianized vector representing symbol counts along the path
-    pub vector: DVector<i32>,
+    pub vector: SparseAbelianVector,
     /// The variable ID at the end of the path
     pub variable_id: VariableId,
+    /// The path in the expression leading to the variable occurrence
+    pub position: OwnedPath,
 }
 
 /// Gets all stringified abelianized vectors for paths from root to variables in an expression.
@@ -447,11 +749,21 @@ pub fn get_path_abelian_vectors_to_variables(
     string_lang: &Language,
     arities: &Arities,
 ) -> Vec<PathAbelianVector> {
-    let mut results = Vec::new();
-    
     // Find all variables and their paths
-    let var_paths = expr.find_all_variables();
-    
+    path_abelian_vectors(expr, expr.find_all_variables(), lang, string_lang, arities)
+}
+
+/// Computes abelianized vectors of `var_paths`, given as paths in `expr` to occurrences
+/// of each variable.
+fn path_abelian_vectors(
+    expr: &Expression,
+    var_paths: impl IntoIterator<Item = (VariableId, Vec<OwnedPath>)>,
+    lang: &Language,
+    string_lang: &Language,
+    arities: &Arities,
+) -> Vec<PathAbelianVector> {
+    let mut results = Vec::new();
+
     for (var_id, paths) in var_paths {
         for path in paths {
             // Build the path expression for this path to the variable
@@ -459,17 +771,20 @@ pub fn get_path_abelian_vectors_to_variables(
             // (e.g., if the path goes through a literal or variable instead of a symbol node).
             // This is an expected condition and safe to skip - such paths cannot be represented
             // in the induced string rewriting system since they don't follow symbol structure.
-            if let Some(path_expr) = path_to_expression(expr, &path, lang, string_lang, arities, var_id) {
+            if let Some(path_expr) =
+                path_to_expression(expr, &path, lang, string_lang, arities, var_id)
+            {
                 // Compute the abelianized vector for this path expression
-                let vector = expression_to_abelian_vector(&path_expr, string_lang);
+                let vector = SparseAbelianVector::from_expression(&path_expr);
                 results.push(PathAbelianVector {
                     vector,
                     variable_id: var_id,
+                    position: path,
                 });
             }
         }
     }
-    
+
     results
 }
 
@@ -477,6 +792,10 @@ pub fn get_path_abelian_vectors_to_variables(
 mod tests {
     use super::*;
     use crate::language::Language;
+    use crate::rewriting::egraph::{
+        matching::top_down::TopDownMatcher, saturation::SimpleSaturator,
+    };
+    use crate::rewriting::reachability::ReachabilityStopReason;
     use std::collections::HashMap;
 
     #[test]
@@ -615,6 +934,70 @@ mod tests {
         assert_eq!(string_lang.symbol_count(), 3);
     }
 
+    #[test]
+    fn test_variadic_symbols() {
+        let lang = Language::default().add_symbol("+").add_symbol("x");
+
+        let mut arities = Arities::new();
+        arities.add(0, 3);
+        arities.add(0, 2);
+        arities.add(1, 0);
+        arities.add(1, 2);
+        assert_eq!(arities.get(0), Some(&[2, 3][..]));
+        assert_eq!(arities.get_max(0), Some(3));
+
+        // x is both a constant and a binary symbol
+        let string_lang = to_string_language(&lang, &arities);
+        let names: Vec<_> = (0..string_lang.symbol_count())
+            .map(|id| string_lang.get_symbol(id))
+            .collect();
+        assert_eq!(names, ["+_1", "+_2", "+_3", "x", "x_1", "x_2"]);
+
+        // Children are indexed by their positions, whatever the number of their siblings
+        let expr = lang.parse("(+ (+ 1 (x)) 2 (x 3 4))").unwrap();
+        let paths: Vec<_> = expression_to_paths(&expr, &lang, &string_lang, &arities)
+            .iter()
+            .map(|path| path.with_language(&string_lang).to_string())
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                "(+_1 (+_1 1))",
+                "(+_1 (+_2 (x)))",
+                "(+_2 2)",
+                "(+_3 (x_1 3))",
+                "(+_3 (x_2 4))"
+            ]
+        );
+
+        let rule = Rule::from_strings("(+ $0 $1 $2)", "(+ $2 $0)", &lang);
+        let induced: Vec<_> = rule_to_induced_rules(&rule, &lang, &string_lang, &arities)
+            .iter()
+            .map(|rule| rule.format_sides(&string_lang))
+            .collect();
+        assert_eq!(induced.len(), 2);
+        assert!(induced.contains(&(String::from("(+_1 $0)"), String::from("(+_2 $0)"))));
+        assert!(induced.contains(&(String::from("(+_3 $2)"), String::from("(+_1 $2)"))));
+    }
+
+    #[test]
+    fn test_undeclared_variadic_leaf() {
+        let lang = Language::default().add_symbol("+").add_symbol("x");
+
+        let mut arities = Arities::new();
+        arities.add(0, 2);
+        arities.add(1, 2);
+
+        // x is only declared as a binary symbol, so the path to the constant (x) is skipped
+        let string_lang = to_string_language(&lang, &arities);
+        let expr = lang.parse("(+ (x) (x 1 2))").unwrap();
+        let paths: Vec<_> = expression_to_paths(&expr, &lang, &string_lang, &arities)
+            .iter()
+            .map(|path| path.with_language(&string_lang).to_string())
+            .collect();
+        assert_eq!(paths, ["(+_2 (x_1 1))", "(+_2 (x_2 2))"]);
+    }
+
     #[test]
     fn test_expression_to_paths_detailed() {
         let lang = Language::default()
@@ -677,6 +1060,76 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_string_rewriting_system_from_trs() {
+        let lang = Language::default().add_symbol("+").add_symbol("*");
+
+        let mut arities_map = HashMap::new();
+        arities_map.insert(0, 2);
+        arities_map.insert(1, 2);
+        let arities = Arities::from(arities_map);
+
+        let rules = vec![
+            Rule::from_strings("(+ $0 $1)", "(* $0 $1)", &lang),
+            Rule::from_strings("(+ $0 $0)", "(* $0 $0)", &lang),
+        ];
+        let trs = TermRewritingSystem::new(lang.clone(), rules);
+        let srs = StringRewritingSystem::from_trs(&trs, &arities);
+
+        let string_lang = to_string_language(&lang, &arities);
+        assert_eq!(srs.string_language(), &string_lang);
+        // 2 induced rules for the first rule and 4 for the second one
+        assert_eq!(srs.rules().len(), 6);
+        assert_eq!(
+            srs.abelian_matrix(),
+            &rules_to_abelian_matrix(srs.rules(), &string_lang)
+        );
+
+        let expr = lang.parse("(+ $0 (* 2 $1))").unwrap();
+        assert_eq!(srs.expression_to_paths(&expr).len(), 3);
+        assert_eq!(srs.path_abelian_vectors_to_variables(&expr).len(), 2);
+    }
+
+    #[test]
+    fn test_string_rewriting_system_runs_induced_rules() {
+        let lang = Language::default().add_symbol("+").add_symbol("sin");
+
+        let mut arities_map = HashMap::new();
+        arities_map.insert(0, 2);
+        arities_map.insert(1, 1);
+        let arities = Arities::from(arities_map);
+
+        let rules = vec![Rule::from_strings("(+ $0 $1)", "(+ $1 $0)", &lang)];
+        let trs = TermRewritingSystem::new(lang.clone(), rules);
+        let srs = StringRewritingSystem::from_trs(&trs, &arities);
+        assert_eq!(srs.to_trs().rules().len(), 2);
+
+        // Paths +_1 sin $0, +_2 sin $0 and +_2 $1
+        let left_paths = srs.expression_to_paths(&lang.parse("(+ (sin $0) $1)").unwrap());
+        let right_paths = srs.expression_to_paths(&lang.parse("(+ 2 (sin $0))").unwrap());
+        let (left, right, other) = (&left_paths[0], &right_paths[1], &left_paths[1]);
+
+        let cfg = SaturationConfig::default();
+        let reachable: ReachabilityResult<()> =
+            srs.paths_reachable(left, right, &cfg, &TopDownMatcher);
+        assert_eq!(reachable.goal, Some(0));
+        let unreachable: ReachabilityResult<()> =
+            srs.paths_reachable(left, other, &cfg, &TopDownMatcher);
+        assert_eq!(
+            unreachable.reason,
+            ReachabilityStopReason::SaturatedNoUnification
+        );
+
+        let saturator = SimpleSaturator::new(Box::new(TopDownMatcher));
+        let (_, classes, _) = srs.saturate_paths::<()>(
+            &[left.clone(), right.clone(), other.clone()],
+            &saturator,
+            &cfg,
+        );
+        assert_eq!(classes[0], classes[1]);
+        assert_ne!(classes[0], classes[2]);
+    }
+
     #[test]
     fn test_expression_to_abelian_vector_simple() {
         let lang = Language::default()
@@ -745,6 +1198,47 @@ mod tests {
         assert_eq!(vec[1], 1); // one *
     }
 
+    #[test]
+    fn test_sparse_abelian_vector_matches_dense() {
+        let lang = Language::default()
+            .add_symbol("+") // id: 0
+            .add_symbol("*") // id: 1
+            .add_symbol("sin"); // id: 2
+
+        // Expression: (sin (+ (sin 1) (sin 2)))
+        // Has: 1 +, 0 *, 3 sin
+        let expr = lang.parse("(sin (+ (sin 1) (sin 2)))").unwrap();
+        let sparse = SparseAbelianVector::from_expression(&expr);
+
+        assert_eq!(sparse.iter().collect::<Vec<_>>(), vec![(0, 1), (2, 3)]);
+        assert_eq!(sparse.get(1), 0);
+        assert_eq!(sparse.get(2), 3);
+        assert_eq!(
+            sparse.to_dense(lang.symbol_count()),
+            expression_to_abelian_vector(&expr, &lang)
+        );
+        assert_eq!(
+            SparseAbelianVector::from_dense(&sparse.to_dense(lang.symbol_count())),
+            sparse
+        );
+    }
+
+    #[test]
+    fn test_sparse_abelian_vector_difference() {
+        let lang = Language::default()
+            .add_symbol("+") // id: 0
+            .add_symbol("*") // id: 1
+            .add_symbol("sin"); // id: 2
+
+        let left = SparseAbelianVector::from_expression(&lang.parse("(+ (sin 1) 2)").unwrap());
+        let right = SparseAbelianVector::from_expression(&lang.parse("(* (sin 1) 2)").unwrap());
+
+        // sin cancels out and is not stored
+        let diff = &left - &right;
+        assert_eq!(diff.iter().collect::<Vec<_>>(), vec![(0, 1), (1, -1)]);
+        assert!((&left - &left).is_zero());
+    }
+
     #[test]
     fn test_rules_to_abelian_matrix_single_rule() {
         let lang = Language::default()
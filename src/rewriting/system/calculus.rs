@@ -55,7 +55,7 @@ mod tests {
         let mut egraph = EGraph::<()>::from_expression(expr);
 
         let saturator = SimpleSaturator::new(Box::new(BottomUpMatcher));
-        let _reason = saturator.saturate(
+        let _report = saturator.saturate(
             &mut egraph,
             trs.rules(),
             &SaturationConfig {
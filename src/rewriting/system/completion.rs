@@ -0,0 +1,644 @@
+//! Critical pairs and Knuth–Bendix completion.
+//!
+//! Completion turns the rules of a system into a convergent rewriting system by
+//! orienting equations with a term ordering and adding critical pairs until all
+//! of them are joinable. Equations which the ordering cannot orient are not a
+//! failure: they are kept aside and only used for ordered rewriting, i.e. an
+//! instance `s = t` is used as `s -> t` when `s` is greater than `t`
+//! (unfailing completion).
+
+use std::collections::{BTreeSet, VecDeque};
+use std::fmt;
+
+use crate::{
+    equation::Equation,
+    language::{
+        expression::{AnyExpression, Expression, OwnedPath, VariableId},
+        symbol::SymbolId,
+    },
+    rewriting::{
+        direct::find_all_rewrite_positions_with_binders, rule::Rule, termination::TermOrdering,
+        unification::UnificationProblem,
+    },
+};
+
+use super::TermRewritingSystem;
+
+/// A critical pair obtained by overlapping the left-hand sides of two rules.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CriticalPair {
+    /// The most general expression rewritten by both rules
+    pub peak: Expression,
+    /// The peak rewritten by the outer rule at the root
+    pub left: Expression,
+    /// The peak rewritten by the inner rule at `position`
+    pub right: Expression,
+    /// Position in the outer left-hand side which the inner left-hand side overlaps
+    pub position: OwnedPath,
+}
+
+impl CriticalPair {
+    /// Checks if both sides of the pair are identical.
+    pub fn is_trivial(&self) -> bool {
+        self.left == self.right
+    }
+}
+
+impl Rule {
+    /// Returns the smallest variable ID greater than all variables used by the rule.
+    fn variable_bound(&self) -> usize {
+        self.from()
+            .max_variable_id()
+            .max(self.to().max_variable_id())
+            .map(|max| max + 1)
+            .unwrap_or(0)
+    }
+
+    /// Computes all critical pairs where `self` is the outer rule and `other` the inner one,
+    /// i.e. `other` rewrites a non-variable subexpression of an instance of `self`'s left-hand side.
+    /// Variables of `other` are renamed apart from the variables of `self` before unification.
+    /// When `self == other`, the trivial overlap at the root is omitted.
+    pub fn critical_pairs(&self, other: &Rule) -> Vec<CriticalPair> {
+        self.critical_pairs_with_binders(other, &BTreeSet::new())
+    }
+
+    /// Same as [`Rule::critical_pairs`], respecting the scopes of variables bound by `binders`,
+    /// see [`Language::binders`](crate::language::Language::binders).
+    ///
+    /// Left-hand sides overlap up to renaming of bound variables, and variables bound around
+    /// the overlapped position cannot be substituted. Pairs in which a bound variable would
+    /// escape the scope of its binder are omitted.
+    pub fn critical_pairs_with_binders(
+        &self,
+        other: &Rule,
+        binders: &BTreeSet<SymbolId>,
+    ) -> Vec<CriticalPair> {
+        let shift = self.variable_bound();
+        let mut inner_from = other.from().clone();
+        let mut inner_to = other.to().clone();
+        inner_from.shift_variables(shift);
+        inner_to.shift_variables(shift);
+
+        // Bound variables of the outer rule are renamed apart from all others,
+        // so that the unifiers can be applied to its left-hand side
+        let mut outer_from = self.from().clone();
+        if !binders.is_empty() {
+            rename_bound_apart(
+                &mut outer_from,
+                binders,
+                &mut (shift + other.variable_bound()),
+            );
+        }
+
+        let same_rule = self == other;
+
+        outer_from
+            .iter_paths()
+            .filter_map(|position| {
+                let subexpression = outer_from.subexpression(position.as_path())?;
+                if matches!(subexpression, Expression::Variable(_))
+                    || (same_rule && position.0.is_empty())
+                {
+                    return None;
+                }
+
+                // With binders the whole left-hand side is unified, which makes the variables
+                // bound around the position rigid
+                let equation = if binders.is_empty() {
+                    Equation::new(subexpression.clone(), inner_from.clone())
+                } else {
+                    Equation::new(
+                        outer_from.clone(),
+                        outer_from
+                            .clone()
+                            .apply_at_path(&position, |_| inner_from.clone()),
+                    )
+                };
+                let substitution = UnificationProblem::from_equation(equation)
+                    .with_binders(binders)
+                    .solve()?;
+
+                let peak = substitution.apply(&outer_from);
+                let left = substitution.apply(self.to());
+                let right = peak
+                    .clone()
+                    .apply_at_path(&position, |_| substitution.apply(&inner_to));
+
+                if !binders.is_empty() {
+                    let free = peak.free_variables(binders);
+                    let escapes =
+                        |side: &Expression| !side.free_variables(binders).is_subset(&free);
+                    if escapes(&left) || escapes(&right) {
+                        return None;
+                    }
+                }
+
+                Some(CriticalPair {
+                    peak,
+                    left,
+                    right,
+                    position,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Renames every variable bound in `expression` to a fresh one, starting from `next_fresh`.
+fn rename_bound_apart(
+    expression: &mut Expression,
+    binders: &BTreeSet<SymbolId>,
+    next_fresh: &mut VariableId,
+) {
+    let bound = expression.bound_variable(binders);
+    let Expression::Symbol(symbol) = expression else {
+        return;
+    };
+
+    if let Some(bound) = bound {
+        let fresh = Expression::Variable(*next_fresh);
+        *next_fresh += 1;
+        for child in &mut symbol.children[1..] {
+            child.substitute_avoiding_capture(bound, &fresh, binders);
+        }
+        symbol.children[0] = fresh;
+    }
+
+    for child in &mut symbol.children {
+        rename_bound_apart(child, binders, next_fresh);
+    }
+}
+
+/// Limits for [`TermRewritingSystem::complete`].
+#[derive(Clone, Debug)]
+pub struct CompletionLimits {
+    /// Maximal number of equations processed
+    pub max_iterations: usize,
+    /// Maximal number of rules and unorientable equations kept at once
+    pub max_rules: usize,
+    /// Maximal number of rewrite steps used to normalize a single expression
+    pub max_rewrite_steps: usize,
+}
+
+impl Default for CompletionLimits {
+    fn default() -> Self {
+        Self {
+            max_iterations: 1000,
+            max_rules: 100,
+            max_rewrite_steps: 1000,
+        }
+    }
+}
+
+/// Result of a successful completion.
+pub struct Completion {
+    /// The completed system
+    pub system: TermRewritingSystem,
+    /// Equations which the ordering could not orient.
+    /// If empty, `system` is convergent. Otherwise `system` together with ordered
+    /// rewriting using these equations is convergent on ground expressions.
+    pub unorientable: Vec<Equation>,
+}
+
+/// Reason why completion did not finish.
+#[derive(Debug)]
+pub enum CompletionError {
+    /// One of the [`CompletionLimits`] was reached before all critical pairs were joined
+    Diverged {
+        /// Rules obtained so far
+        rules: Vec<Rule>,
+        /// Number of equations which were not processed yet
+        pending: usize,
+    },
+}
+
+impl fmt::Display for CompletionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompletionError::Diverged { rules, pending } => write!(
+                f,
+                "Completion diverged with {} rules and {} pending equations",
+                rules.len(),
+                pending
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CompletionError {}
+
+struct Completer<'a, O: TermOrdering + ?Sized> {
+    ordering: &'a O,
+    limits: &'a CompletionLimits,
+    binders: &'a BTreeSet<SymbolId>,
+    rules: Vec<Rule>,
+    unorientable: Vec<Equation>,
+    pending: VecDeque<Equation>,
+}
+
+impl<'a, O: TermOrdering + ?Sized> Completer<'a, O> {
+    fn new(
+        ordering: &'a O,
+        limits: &'a CompletionLimits,
+        binders: &'a BTreeSet<SymbolId>,
+        pending: VecDeque<Equation>,
+    ) -> Self {
+        Self {
+            ordering,
+            limits,
+            binders,
+            rules: Vec::new(),
+            unorientable: Vec::new(),
+            pending,
+        }
+    }
+
+    fn size(&self) -> usize {
+        self.rules.len() + self.unorientable.len()
+    }
+
+    /// Matches `from` at `expression` and instantiates `to` with the match.
+    fn rewrite_root(
+        &self,
+        from: &Expression,
+        to: &Expression,
+        expression: &Expression,
+    ) -> Option<Expression> {
+        if self.binders.is_empty() {
+            let matching = Expression::try_match_expression(from, expression)?;
+            return Some(Expression::instantiate_expression(to, &matching));
+        }
+
+        let matching =
+            Expression::try_match_expression_with_binders(from, expression, self.binders)?;
+        Expression::instantiate_expression_with_binders(to, &matching, self.binders)
+    }
+
+    /// Performs a single rewrite step using the rules or, if they are not applicable,
+    /// a decreasing instance of one of the unorientable equations.
+    fn rewrite_step(&self, expression: &Expression) -> Option<Expression> {
+        for position in expression.iter_paths() {
+            let Some(subexpression) = expression.subexpression(position.as_path()) else {
+                continue;
+            };
+
+            let oriented = self
+                .rules
+                .iter()
+                .map(|rule| (rule.from(), rule.to(), false));
+            let ordered = self.unorientable.iter().flat_map(|equation| {
+                [
+                    (&equation.left, &equation.right, true),
+                    (&equation.right, &equation.left, true),
+                ]
+            });
+
+            for (from, to, needs_check) in oriented.chain(ordered) {
+                let Some(replacement) = self.rewrite_root(from, to, subexpression) else {
+                    continue;
+                };
+
+                if !needs_check || self.ordering.greater(subexpression, &replacement) {
+                    return Some(expression.clone().apply_at_path(&position, |_| replacement));
+                }
+            }
+        }
+
+        None
+    }
+
+    fn normalize(&self, mut expression: Expression) -> Expression {
+        for _ in 0..self.limits.max_rewrite_steps {
+            match self.rewrite_step(&expression) {
+                Some(rewritten) => expression = rewritten,
+                None => break,
+            }
+        }
+
+        expression
+    }
+
+    /// Checks if `equation` is an instance of one of the unorientable equations.
+    fn is_subsumed(&self, equation: &Equation) -> bool {
+        self.unorientable.iter().any(|existing| {
+            [
+                (&existing.left, &existing.right),
+                (&existing.right, &existing.left),
+            ]
+            .into_iter()
+            .any(|(left, right)| {
+                Expression::try_match_expression_with_binders(left, &equation.left, self.binders)
+                    .zip(Expression::try_match_expression_with_binders(
+                        right,
+                        &equation.right,
+                        self.binders,
+                    ))
+                    .and_then(|(left_match, right_match)| left_match.try_merge(&right_match))
+                    .is_some()
+            })
+        })
+    }
+
+    fn process(&mut self, equation: Equation) {
+        let left = self.normalize(equation.left);
+        let right = self.normalize(equation.right);
+
+        if left.alpha_eq_binding(&right, self.binders) {
+            return;
+        }
+
+        if self.ordering.greater(&left, &right) {
+            self.add_rule(Rule::from_expressions(left, right));
+        } else if self.ordering.greater(&right, &left) {
+            self.add_rule(Rule::from_expressions(right, left));
+        } else {
+            let equation = Equation::new(left, right);
+            if !self.is_subsumed(&equation) {
+                self.add_unorientable(equation);
+            }
+        }
+    }
+
+    /// Queues critical pairs between `rule` and all rules and unorientable equations.
+    fn add_critical_pairs(&mut self, rule: &Rule) {
+        let unorientable_rules = self.unorientable.iter().flat_map(|equation| {
+            [
+                Rule::from_expressions(equation.left.clone(), equation.right.clone()),
+                Rule::from_expressions(equation.right.clone(), equation.left.clone()),
+            ]
+        });
+
+        let mut critical_pairs = rule.critical_pairs_with_binders(rule, self.binders);
+        for other in self.rules.iter().cloned().chain(unorientable_rules) {
+            critical_pairs.extend(rule.critical_pairs_with_binders(&other, self.binders));
+            critical_pairs.extend(other.critical_pairs_with_binders(rule, self.binders));
+        }
+
+        self.pending.extend(
+            critical_pairs
+                .into_iter()
+                .filter(|critical_pair| !critical_pair.is_trivial())
+                .map(|critical_pair| Equation::new(critical_pair.left, critical_pair.right)),
+        );
+    }
+
+    /// Moves rules and equations whose left-hand sides are reducible by `rule` back to pending equations.
+    fn collapse(&mut self, rule: &Rule) {
+        let reducible = |expression: &Expression| {
+            !find_all_rewrite_positions_with_binders(
+                expression,
+                std::slice::from_ref(rule),
+                self.binders,
+            )
+            .is_empty()
+        };
+
+        let (collapsed, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.rules)
+            .into_iter()
+            .partition(|existing| reducible(existing.from()));
+        self.rules = kept;
+        self.pending.extend(
+            collapsed
+                .into_iter()
+                .map(|rule| Equation::new(rule.from().clone(), rule.to().clone())),
+        );
+
+        let (collapsed, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.unorientable)
+            .into_iter()
+            .partition(|equation| reducible(&equation.left) || reducible(&equation.right));
+        self.unorientable = kept;
+        self.pending.extend(collapsed);
+    }
+
+    fn add_rule(&mut self, rule: Rule) {
+        self.collapse(&rule);
+        self.add_critical_pairs(&rule);
+        self.rules.push(rule);
+
+        self.rules = self
+            .rules
+            .iter()
+            .map(|rule| {
+                let to = self.normalize(rule.to().clone());
+                Rule::from_expressions(rule.from().clone(), to)
+            })
+            .collect();
+    }
+
+    fn add_unorientable(&mut self, equation: Equation) {
+        let rules = [
+            Rule::from_expressions(equation.left.clone(), equation.right.clone()),
+            Rule::from_expressions(equation.right.clone(), equation.left.clone()),
+        ];
+
+        self.unorientable.push(equation);
+
+        for rule in &rules {
+            self.add_critical_pairs(rule);
+        }
+    }
+}
+
+impl TermRewritingSystem {
+    /// Runs unfailing Knuth–Bendix completion on the rules of the system, using `ordering`
+    /// to orient equations. Rules of `self` are treated as equations, so their orientation
+    /// does not matter.
+    ///
+    /// # Arguments
+    ///
+    /// * `ordering` - The reduction ordering used to orient equations
+    /// * `limits` - Limits after which completion is considered divergent
+    ///
+    /// # Returns
+    ///
+    /// Returns the completed system together with the equations which could not be oriented,
+    /// or [`CompletionError::Diverged`] if a limit was reached.
+    pub fn complete(
+        &self,
+        ordering: &(impl TermOrdering + ?Sized),
+        limits: &CompletionLimits,
+    ) -> Result<Completion, CompletionError> {
+        let pending = self
+            .rules()
+            .iter()
+            .map(|rule| Equation::new(rule.from().clone(), rule.to().clone()))
+            .collect();
+
+        let mut completer = Completer::new(ordering, limits, self.language().binders(), pending);
+        let mut iterations = 0;
+
+        while !completer.pending.is_empty() {
+            if iterations == limits.max_iterations || completer.size() > limits.max_rules {
+                return Err(CompletionError::Diverged {
+                    pending: completer.pending.len(),
+                    rules: completer.rules,
+                });
+            }
+
+            iterations += 1;
+            if let Some(equation) = completer.pending.pop_front() {
+                completer.process(equation);
+            }
+        }
+
+        Ok(Completion {
+            system: TermRewritingSystem::new(self.language().clone(), completer.rules),
+            unorientable: completer.unorientable,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CompletionError, CompletionLimits};
+    use crate::{
+        language::{
+            Language,
+            expression::{Expression, OwnedPath},
+            topology::expression_size,
+        },
+        rewriting::{rule::Rule, system::TermRewritingSystem, termination::TermOrdering},
+    };
+
+    /// Compares expressions by size, requiring that no variable occurs more often on the right.
+    struct SizeOrdering;
+
+    impl TermOrdering for SizeOrdering {
+        fn greater(&self, left: &Expression, right: &Expression) -> bool {
+            let left_variables = left.find_all_variables();
+            let variables_ok = right.find_all_variables().iter().all(|(variable, paths)| {
+                left_variables
+                    .get(variable)
+                    .is_some_and(|left_paths| left_paths.len() >= paths.len())
+            });
+
+            variables_ok && expression_size(left) > expression_size(right)
+        }
+    }
+
+    fn lang() -> Language {
+        Language::default()
+            .add_symbol("a")
+            .add_symbol("b")
+            .add_symbol("f")
+            .add_symbol("g")
+    }
+
+    fn rule(lang: &Language, from: &str, to: &str) -> Rule {
+        Rule::from_strings(from, to, lang)
+    }
+
+    #[test]
+    fn critical_pair_self_overlap() {
+        let lang = lang();
+        let rule = rule(&lang, "(f (f $0))", "(g $0)");
+        let critical_pairs = rule.critical_pairs(&rule);
+
+        assert_eq!(critical_pairs.len(), 1);
+        let critical_pair = &critical_pairs[0];
+        assert_eq!(critical_pair.position, OwnedPath(vec![0]));
+        assert_eq!(critical_pair.peak, lang.parse("(f (f (f $1)))").unwrap());
+        assert_eq!(critical_pair.left, lang.parse("(g (f $1))").unwrap());
+        assert_eq!(critical_pair.right, lang.parse("(f (g $1))").unwrap());
+    }
+
+    #[test]
+    fn critical_pairs_no_overlap() {
+        let lang = lang();
+        let rule_1 = rule(&lang, "(f $0)", "(g $0)");
+        let rule_2 = rule(&lang, "(g (a))", "(b)");
+        assert!(rule_1.critical_pairs(&rule_2).is_empty());
+    }
+
+    #[test]
+    fn completion_respects_binders() {
+        let lang = lang()
+            .add_symbol("lam")
+            .add_symbol("id")
+            .declare_binder("lam");
+        let rules = vec![
+            rule(&lang, "(g (lam $0 $1))", "$1"),
+            rule(&lang, "(lam $0 $0)", "(id)"),
+        ];
+        let trs = TermRewritingSystem::new(lang.clone(), rules);
+
+        // Without binders, the overlap would give `(g (id)) = $2`, where the bound
+        // variable escapes its binder
+        let completion = trs
+            .complete(&SizeOrdering, &CompletionLimits::default())
+            .unwrap();
+
+        assert!(completion.unorientable.is_empty());
+        assert_eq!(completion.system.rules().len(), 2);
+    }
+
+    #[test]
+    fn completion_adds_rule() {
+        let lang = lang();
+        let rules = vec![
+            rule(&lang, "(f (g $0))", "$0"),
+            rule(&lang, "(g (a))", "(b)"),
+        ];
+        let trs = TermRewritingSystem::new(lang.clone(), rules);
+
+        let completion = trs
+            .complete(&SizeOrdering, &CompletionLimits::default())
+            .unwrap();
+
+        assert!(completion.unorientable.is_empty());
+        assert_eq!(completion.system.rules().len(), 3);
+        assert!(
+            completion
+                .system
+                .rules()
+                .contains(&rule(&lang, "(f (b))", "(a)"))
+        );
+    }
+
+    #[test]
+    fn completion_already_confluent() {
+        let lang = lang();
+        let rules = vec![
+            rule(&lang, "(f (g $0))", "$0"),
+            rule(&lang, "(g (f $0))", "$0"),
+        ];
+        let trs = TermRewritingSystem::new(lang, rules.clone());
+
+        let completion = trs
+            .complete(&SizeOrdering, &CompletionLimits::default())
+            .unwrap();
+
+        assert_eq!(completion.system.rules(), &rules);
+    }
+
+    #[test]
+    fn completion_keeps_unorientable() {
+        let lang = lang();
+        let rules = vec![rule(&lang, "(f $0 $1)", "(f $1 $0)")];
+        let trs = TermRewritingSystem::new(lang, rules);
+
+        let completion = trs
+            .complete(&SizeOrdering, &CompletionLimits::default())
+            .unwrap();
+
+        assert!(completion.system.rules().is_empty());
+        assert_eq!(completion.unorientable.len(), 1);
+    }
+
+    #[test]
+    fn completion_diverges() {
+        let lang = lang();
+        let rules = vec![rule(&lang, "(f (g (f $0)))", "(g (f $0))")];
+        let trs = TermRewritingSystem::new(lang, rules);
+
+        let limits = CompletionLimits {
+            max_rules: 10,
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            trs.complete(&SizeOrdering, &limits),
+            Err(CompletionError::Diverged { .. })
+        ));
+    }
+}
@@ -0,0 +1,216 @@
+//! Local confluence checking via critical pairs.
+//!
+//! By the critical pair lemma, a system is locally confluent if and only if all
+//! of its critical pairs are joinable. Joinability of each pair is tested by
+//! saturating a small e-graph containing both sides of the pair, with variables
+//! replaced by fresh constants.
+
+use crate::{
+    language::{
+        expression::{Expression, VarFreeExpression},
+        symbol::Symbol,
+    },
+    rewriting::{
+        egraph::{matching::bottom_up::BottomUpMatcher, saturation::SaturationConfig},
+        reachability::{ReachabilityResult, ReachabilityStopReason, terms_reachable_round_robin},
+    },
+};
+
+use super::{TermRewritingSystem, completion::CriticalPair};
+
+/// A critical pair whose sides were not found to be joinable.
+#[derive(Clone, Debug)]
+pub struct NonJoinablePair {
+    /// Index of the rule applied at the root of the peak
+    pub outer_rule: usize,
+    /// Index of the rule applied below the root of the peak
+    pub inner_rule: usize,
+    /// The critical pair itself
+    pub critical_pair: CriticalPair,
+    /// Why the saturation of the pair stopped.
+    /// [`ReachabilityStopReason::Limit`] means that joinability could not be decided.
+    pub reason: ReachabilityStopReason,
+}
+
+/// Result of [`TermRewritingSystem::is_locally_confluent`].
+#[derive(Clone, Debug, Default)]
+pub struct ConfluenceReport {
+    /// Number of non-trivial critical pairs checked
+    pub critical_pair_count: usize,
+    /// Critical pairs which were not joined
+    pub non_joinable: Vec<NonJoinablePair>,
+}
+
+impl ConfluenceReport {
+    /// Returns `true` if all critical pairs were joined.
+    pub fn is_locally_confluent(&self) -> bool {
+        self.non_joinable.is_empty()
+    }
+}
+
+/// Replaces variables with fresh nullary symbols, starting with ID `first_fresh_symbol`.
+fn ground(expression: &Expression, first_fresh_symbol: usize) -> VarFreeExpression {
+    match expression {
+        Expression::Literal(literal) => VarFreeExpression::Literal(literal.clone()),
+        Expression::Symbol(symbol) => VarFreeExpression::Symbol(Symbol {
+            id: symbol.id,
+            children: symbol
+                .children
+                .iter()
+                .map(|child| ground(child, first_fresh_symbol))
+                .collect(),
+        }),
+        Expression::Variable(variable) => VarFreeExpression::Symbol(Symbol {
+            id: first_fresh_symbol + variable,
+            children: Vec::new(),
+        }),
+    }
+}
+
+impl TermRewritingSystem {
+    /// Returns all critical pairs of the system together with the indices of the outer
+    /// and the inner rule, in that order.
+    pub fn critical_pairs(&self) -> Vec<(usize, usize, CriticalPair)> {
+        let mut critical_pairs = Vec::new();
+
+        for (outer_index, outer) in self.rules().iter().enumerate() {
+            for (inner_index, inner) in self.rules().iter().enumerate() {
+                critical_pairs.extend(
+                    outer
+                        .critical_pairs(inner)
+                        .into_iter()
+                        .map(|critical_pair| (outer_index, inner_index, critical_pair)),
+                );
+            }
+        }
+
+        critical_pairs
+    }
+
+    /// Checks if every critical pair of the system is joinable.
+    /// Both sides of a pair are added to an e-graph which is saturated under `config`
+    /// until they end up in the same class.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Limits for the saturation of each critical pair
+    ///
+    /// # Returns
+    ///
+    /// Returns a report listing the critical pairs which were not joined.
+    pub fn is_locally_confluent(&self, config: &SaturationConfig) -> ConfluenceReport {
+        let first_fresh_symbol = self.language().symbol_count();
+        let mut report = ConfluenceReport::default();
+
+        for (outer_rule, inner_rule, critical_pair) in self.critical_pairs() {
+            if critical_pair.is_trivial() {
+                continue;
+            }
+
+            report.critical_pair_count += 1;
+
+            let result: ReachabilityResult<()> = terms_reachable_round_robin(
+                self.rules(),
+                ground(&critical_pair.left, first_fresh_symbol),
+                ground(&critical_pair.right, first_fresh_symbol),
+                config,
+                &BottomUpMatcher,
+            );
+
+            if !matches!(
+                result.reason,
+                ReachabilityStopReason::ReachedCommonForm { .. }
+            ) {
+                report.non_joinable.push(NonJoinablePair {
+                    outer_rule,
+                    inner_rule,
+                    critical_pair,
+                    reason: result.reason,
+                });
+            }
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::language::Language;
+    use crate::macros::rules;
+    use crate::rewriting::egraph::saturation::SaturationConfig;
+    use crate::rewriting::reachability::ReachabilityStopReason;
+    use crate::rewriting::system::TermRewritingSystem;
+
+    fn config() -> SaturationConfig {
+        SaturationConfig {
+            max_applications: Some(100),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn locally_confluent() {
+        let lang = Language::default().add_symbol("f").add_symbol("g");
+        let rules = rules!(lang;
+            "(f (g $0))" => "$0",
+            "(g (f $0))" => "$0",
+        );
+        let trs = TermRewritingSystem::new(lang, rules);
+
+        let report = trs.is_locally_confluent(&config());
+        assert_eq!(report.critical_pair_count, 0);
+        assert!(report.is_locally_confluent());
+    }
+
+    #[test]
+    fn joinable_pairs() {
+        let lang = Language::default().add_symbol("f").add_symbol("a");
+        let rules = rules!(lang;
+            "(f (f $0))" => "(f $0)",
+        );
+        let trs = TermRewritingSystem::new(lang, rules);
+
+        let report = trs.is_locally_confluent(&config());
+        assert_eq!(report.critical_pair_count, 0);
+
+        let lang = Language::default()
+            .add_symbol("f")
+            .add_symbol("a")
+            .add_symbol("b");
+        let rules = rules!(lang;
+            "(f (a))" => "(b)",
+            "(a)" => "(b)",
+            "(f (b))" => "(b)",
+        );
+        let trs = TermRewritingSystem::new(lang, rules);
+
+        let report = trs.is_locally_confluent(&config());
+        assert_eq!(report.critical_pair_count, 1);
+        assert!(report.is_locally_confluent());
+    }
+
+    #[test]
+    fn not_locally_confluent() {
+        let lang = Language::default()
+            .add_symbol("f")
+            .add_symbol("g")
+            .add_symbol("h")
+            .add_symbol("a");
+        let rules = rules!(lang;
+            "(f (g $0))" => "(h $0)",
+            "(g (a))" => "(a)",
+        );
+        let trs = TermRewritingSystem::new(lang.clone(), rules);
+
+        let report = trs.is_locally_confluent(&config());
+        assert!(!report.is_locally_confluent());
+        assert_eq!(report.non_joinable.len(), 1);
+
+        let pair = &report.non_joinable[0];
+        assert_eq!(pair.outer_rule, 0);
+        assert_eq!(pair.inner_rule, 1);
+        assert_eq!(pair.critical_pair.peak, lang.parse("(f (g (a)))").unwrap());
+        assert_eq!(pair.reason, ReachabilityStopReason::SaturatedNoUnification);
+    }
+}
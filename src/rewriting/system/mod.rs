@@ -4,30 +4,160 @@
 //! definition with a set of rewrite rules to perform symbolic computation through
 //! term rewriting and equality saturation.
 
-use crate::language::expression::AnyExpression;
-use crate::language::{Language, expression::VarFreeExpression};
+use crate::language::{
+    Language,
+    arities::Arities,
+    expression::{AnyExpression, Expression, VarFreeExpression},
+};
+use crate::rewriting::egraph::class::table_local_cost::SymbolCosts;
+use crate::rewriting::egraph::extraction::CostTable;
 use crate::rewriting::egraph::saturation::{SaturationConfig, Saturator, SimpleSaturator};
-use crate::rewriting::egraph::{Analysis, EGraph, matching::bottom_up::BottomUpMatcher};
+use crate::rewriting::egraph::{
+    Analysis, ClassId, DynEGraph, EGraph, matching::bottom_up::BottomUpMatcher,
+};
+use crate::rewriting::guard::Guard;
 use crate::rewriting::rule::Rule;
+use crate::utils::json;
 use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Visitor, ser::SerializeStruct};
 use std::error::Error;
 use std::fmt;
 use std::path::Path;
+use std::sync::Arc;
+use validation::{Diagnostic, ValidationError};
 
 pub mod calculus;
+pub mod completion;
+pub mod confluence;
 pub mod dependency_graph;
+pub mod validation;
 
 // Helper struct for serializing/deserializing rules
 #[derive(Serialize, Deserialize)]
 struct SerializableRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
     from: String,
     to: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    bidirectional: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    guard: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cost: Option<usize>,
+}
+
+// A rule as written in JSON, either as an object or as a string with both sides
+// separated by `=>`, or by `<=>` for a rule together with its reverse
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RuleEntry {
+    Arrow(String),
+    Sides(SerializableRule),
+}
+
+// Rules as written in JSON, either as a list or as a map from rule names to rules
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RuleEntries {
+    List(Vec<RuleEntry>),
+    Named(NamedRuleEntries),
+}
+
+// Named rules in the order in which they appear in JSON
+struct NamedRuleEntries(Vec<(String, RuleEntry)>);
+
+impl<'de> Deserialize<'de> for NamedRuleEntries {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct NamedRuleEntriesVisitor;
+
+        impl<'de> Visitor<'de> for NamedRuleEntriesVisitor {
+            type Value = NamedRuleEntries;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a map from rule names to rules")
+            }
+
+            fn visit_map<V>(self, mut map: V) -> Result<NamedRuleEntries, V::Error>
+            where
+                V: serde::de::MapAccess<'de>,
+            {
+                let mut entries = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(NamedRuleEntries(entries))
+            }
+        }
+
+        deserializer.deserialize_map(NamedRuleEntriesVisitor)
+    }
+}
+
+impl RuleEntries {
+    /// Parses the rules, expanding bidirectional ones into two rules.
+    fn into_rules(self, language: &Language) -> anyhow::Result<Vec<Rule>> {
+        let entries: Vec<(Option<String>, RuleEntry)> = match self {
+            RuleEntries::List(entries) => entries.into_iter().map(|e| (None, e)).collect(),
+            RuleEntries::Named(NamedRuleEntries(entries)) => entries
+                .into_iter()
+                .map(|(name, e)| (Some(name), e))
+                .collect(),
+        };
+
+        let mut rules = Vec::new();
+        for (key, entry) in entries {
+            let rule = match entry {
+                RuleEntry::Sides(rule) => rule,
+                RuleEntry::Arrow(string) => {
+                    let (from, to, bidirectional) =
+                        if let Some((from, to)) = string.split_once("<=>") {
+                            (from, to, true)
+                        } else if let Some((from, to)) = string.split_once("=>") {
+                            (from, to, false)
+                        } else {
+                            anyhow::bail!("Rule {string} has neither => nor <=>");
+                        };
+
+                    SerializableRule {
+                        name: None,
+                        from: String::from(from.trim()),
+                        to: String::from(to.trim()),
+                        bidirectional,
+                        guard: None,
+                        cost: None,
+                    }
+                }
+            };
+
+            let mut parsed = Rule::try_from_strings(&rule.from, &rule.to, language)?;
+            if let Some(guard) = &rule.guard {
+                parsed = parsed.with_guard(Guard::parse(guard)?);
+            }
+            if let Some(name) = key.or(rule.name) {
+                parsed = parsed.with_name(&name);
+            }
+            if let Some(cost) = rule.cost {
+                parsed = parsed.with_cost(cost);
+            }
+
+            let reversed = rule.bidirectional.then(|| parsed.reversed());
+            rules.push(parsed);
+            rules.extend(reversed);
+        }
+
+        Ok(rules)
+    }
 }
 
 // Helper struct for loading rules from separate JSON file
 #[derive(Deserialize)]
 struct RulesFile {
-    rules: Vec<SerializableRule>,
+    rules: RuleEntries,
+    #[serde(default)]
+    costs: Option<CostTable>,
 }
 
 /// A complete term rewriting system.
@@ -35,9 +165,11 @@ struct RulesFile {
 /// Combines a [`Language`] definition with a set of [`Rule`]s to enable
 /// symbolic computation through term rewriting. The system can parse expressions,
 /// apply rewrite rules, and perform equality saturation using e-graphs.
+/// A system may also carry a [`CostTable`] used for extraction of expressions.
 pub struct TermRewritingSystem {
     language: Language,
     rules: Vec<Rule>,
+    costs: Option<CostTable>,
 }
 
 impl TermRewritingSystem {
@@ -48,11 +180,35 @@ impl TermRewritingSystem {
     /// * `language` - The language definition containing all symbols
     /// * `rules` - The set of rewrite rules to apply
     pub fn new(language: Language, rules: Vec<Rule>) -> Self {
-        Self { language, rules }
+        Self {
+            language,
+            rules,
+            costs: None,
+        }
+    }
+
+    /// Sets the costs of the system.
+    pub fn with_costs(mut self, costs: CostTable) -> Self {
+        self.costs = Some(costs);
+        self
     }
 
     /// Load a TermRewritingSystem from a directory containing language.json and trs.json
     ///
+    /// The `rules` of `trs.json` are either a list or a map from rule names to rules.
+    /// A rule is an object with `from` and `to` patterns, an optional `name`,
+    /// an optional `bidirectional` flag, an optional `guard` like `"#1 != 0"`,
+    /// see [`Guard::parse`], and an optional `cost` of its applications, see [`Rule::with_cost`],
+    /// or a string like `"(+ $0 $1) => (+ $1 $0)"`.
+    /// Variables written as `#n` in patterns only match numeric literals.
+    /// Bidirectional rules, also written with `<=>`, are expanded into the rule and its reverse.
+    /// Costs of the system are read from the `costs` section of `trs.json`, which is
+    /// a [`CostTable`], or if it is missing, from an optional `costs.json` file.
+    ///
+    /// The loaded system is checked with [`TermRewritingSystem::validate`]. Errors make
+    /// loading fail, while warnings are ignored, see
+    /// [`TermRewritingSystem::from_directory_with_diagnostics`] to get them.
+    ///
     /// # Arguments
     ///
     /// * `dir_path` - Path to a directory containing `language.json` and `trs.json` files
@@ -61,24 +217,119 @@ impl TermRewritingSystem {
     ///
     /// Returns a `TermRewritingSystem` on success, or an error if files cannot be loaded
     pub fn from_directory<P: AsRef<Path>>(dir_path: P) -> Result<Self, Box<dyn Error>> {
+        Self::from_directory_with_diagnostics(dir_path).map(|(trs, _)| trs)
+    }
+
+    /// Loads a system like [`TermRewritingSystem::from_directory`], also returning
+    /// the warnings of [`TermRewritingSystem::validate`] about it.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir_path` - Path to a directory containing `language.json` and `trs.json` files
+    ///
+    /// # Returns
+    ///
+    /// Returns the system with its warnings on success, or an error if files cannot be loaded
+    /// or the system is invalid
+    pub fn from_directory_with_diagnostics<P: AsRef<Path>>(
+        dir_path: P,
+    ) -> Result<(Self, Vec<Diagnostic>), Box<dyn Error>> {
         let dir_path = dir_path.as_ref();
 
         // Load language
         let lang_path = dir_path.join("language.json");
-        let language: Language = crate::utils::json::load_json(lang_path)?;
+        let language: Language = json::load_json(lang_path)?;
 
         // Load rules
         let rules_path = dir_path.join("trs.json");
-        let rules_file: RulesFile = crate::utils::json::load_json(rules_path)?;
+        let rules_file: RulesFile = json::load_json(rules_path)?;
 
         // Parse rules using the language
-        let rules: Vec<Rule> = rules_file
-            .rules
-            .into_iter()
-            .map(|sr| Rule::from_strings(&sr.from, &sr.to, &language))
-            .collect();
+        let rules = rules_file.rules.into_rules(&language)?;
+
+        let costs_path = dir_path.join("costs.json");
+        let costs = match rules_file.costs {
+            Some(costs) => Some(costs),
+            None if costs_path.exists() => Some(json::load_json(costs_path)?),
+            None => None,
+        };
+
+        let trs = Self {
+            language,
+            rules,
+            costs,
+        };
+        let diagnostics = trs.validate();
+        if diagnostics.iter().any(Diagnostic::is_error) {
+            return Err(ValidationError { diagnostics }.into());
+        }
+
+        Ok((trs, diagnostics))
+    }
+
+    /// Saves the system to `dir_path`, creating it if needed, in the format read by
+    /// [`TermRewritingSystem::from_directory`]: the language to `language.json`, the rules
+    /// to `trs.json`, [`TermRewritingSystem::arities`] to `arities.json` and the costs,
+    /// if the system has any, to `costs.json`. A `costs.json` left in `dir_path` by an earlier
+    /// save is removed from it if the system has no costs, so that loading gives back the system.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir_path` - Path to the directory to save the files to
+    ///
+    /// # Returns
+    ///
+    /// Returns an error if a file cannot be written
+    pub fn save_to_directory<P: AsRef<Path>>(&self, dir_path: P) -> Result<(), Box<dyn Error>> {
+        let dir_path = dir_path.as_ref();
+        std::fs::create_dir_all(dir_path)?;
+
+        json::save_json(&self.language, dir_path.join("language.json"))?;
+        json::save_json(
+            &serde_json::json!({ "rules": self.serializable_rules() }),
+            dir_path.join("trs.json"),
+        )?;
+        json::save_json(&self.arities(), dir_path.join("arities.json"))?;
+
+        let costs_path = dir_path.join("costs.json");
+        match &self.costs {
+            Some(costs) => json::save_json(costs, costs_path)?,
+            None if costs_path.exists() => std::fs::remove_file(costs_path)?,
+            None => {}
+        }
 
-        Ok(Self::new(language, rules))
+        Ok(())
+    }
+
+    /// Combines two systems, e.g. loaded from different directories, into one with the rules
+    /// of both. Languages are merged with [`Language::merge`], so symbols with the same name
+    /// are shared. Symbol costs are combined, preferring those of `self`, whose literal and
+    /// default costs are kept.
+    pub fn merge(&self, other: &TermRewritingSystem) -> TermRewritingSystem {
+        let (language, remap) = self.language.merge(&other.language);
+        let mut rules = self.rules.clone();
+        rules.extend(other.rules.iter().map(|rule| {
+            let mut rule = rule.clone();
+            rule.remap_symbols(&remap);
+            rule
+        }));
+
+        let costs = match (&self.costs, &other.costs) {
+            (Some(own), Some(other_costs)) => {
+                let mut costs = own.clone();
+                for (symbol, &cost) in &other_costs.per_symbol_cost {
+                    costs.per_symbol_cost.entry(symbol.clone()).or_insert(cost);
+                }
+                Some(costs)
+            }
+            (own, other_costs) => own.clone().or_else(|| other_costs.clone()),
+        };
+
+        Self {
+            language,
+            rules,
+            costs,
+        }
     }
 
     /// Returns a reference to the system's language definition.
@@ -91,6 +342,69 @@ impl TermRewritingSystem {
         &self.rules
     }
 
+    /// Returns the costs of the system, if it has any.
+    pub fn costs(&self) -> Option<&CostTable> {
+        self.costs.as_ref()
+    }
+
+    /// Returns the costs of the system, or the sizes of expressions if it has none.
+    pub fn costs_or_sizes(&self) -> CostTable {
+        self.costs.clone().unwrap_or(CostTable {
+            default: 1,
+            ..Default::default()
+        })
+    }
+
+    /// Returns an empty e-graph whose analyses use the costs of the system, if it has any,
+    /// see [`EGraph::with_costs`].
+    pub fn egraph<A: Analysis>(&self) -> EGraph<A> {
+        match &self.costs {
+            Some(costs) => {
+                EGraph::default().with_costs(Arc::new(SymbolCosts::new(costs, &self.language)))
+            }
+            None => EGraph::default(),
+        }
+    }
+
+    /// Returns the arities declared by the language of the system, or if it declares none,
+    /// the numbers of children with which symbols occur in the rules.
+    pub fn arities(&self) -> Arities {
+        if let Some(arities) = self.language.arities() {
+            return arities.clone();
+        }
+
+        let mut arities = Arities::new();
+        for rule in &self.rules {
+            for expression in [rule.from(), rule.to()] {
+                for subexpression in expression.iter_subexpressions() {
+                    if let Expression::Symbol(symbol) = subexpression {
+                        arities.add(symbol.id, symbol.children.len());
+                    }
+                }
+            }
+        }
+        arities
+    }
+
+    /// Rules of the system in the format of `trs.json`.
+    fn serializable_rules(&self) -> Vec<SerializableRule> {
+        self.rules
+            .iter()
+            .map(|rule| {
+                let (from, to) = rule.format_sides(&self.language);
+                let comparisons = &rule.guard().comparisons;
+                SerializableRule {
+                    name: rule.name().map(String::from),
+                    from,
+                    to,
+                    bidirectional: false,
+                    guard: (!comparisons.is_empty()).then(|| rule.guard().to_string()),
+                    cost: (rule.cost() != 1).then(|| rule.cost()),
+                }
+            })
+            .collect()
+    }
+
     /// Build an e-graph from the provided expression and saturate it using the system's rules.
     ///
     /// # Arguments
@@ -101,11 +415,36 @@ impl TermRewritingSystem {
     ///
     /// Returns the saturated e-graph containing all equivalent expressions
     pub fn rewrite<A: Analysis>(&self, expression: VarFreeExpression) -> EGraph<A> {
-        let mut egraph = EGraph::<A>::from_expression(expression);
+        let mut egraph = self.egraph::<A>();
+        egraph.add_expression(expression);
         let saturator = SimpleSaturator::new(Box::new(BottomUpMatcher));
         let _ = saturator.saturate(&mut egraph, &self.rules, &SaturationConfig::default());
         egraph
     }
+
+    /// Like [`TermRewritingSystem::rewrite`], but adds the expression to `assumptions`, an e-graph
+    /// of equalities assumed to hold, e.g. built with [`EGraph::assume_equal`], and saturates
+    /// it together with them, so that the expression is rewritten modulo the assumptions.
+    ///
+    /// # Arguments
+    ///
+    /// * `assumptions` - The background e-graph
+    /// * `expression` - The expression to rewrite
+    ///
+    /// # Returns
+    ///
+    /// Returns the saturated e-graph and the canonical ID of the class of the expression
+    pub fn rewrite_with_assumptions<A: Analysis>(
+        &self,
+        mut assumptions: EGraph<A>,
+        expression: VarFreeExpression,
+    ) -> (EGraph<A>, ClassId) {
+        let class_id = assumptions.add_expression(expression);
+        let saturator = SimpleSaturator::new(Box::new(BottomUpMatcher));
+        let _ = saturator.saturate(&mut assumptions, &self.rules, &SaturationConfig::default());
+        let class_id = assumptions.canonical_class(class_id);
+        (assumptions, class_id)
+    }
 }
 
 impl Serialize for TermRewritingSystem {
@@ -113,18 +452,13 @@ impl Serialize for TermRewritingSystem {
     where
         S: Serializer,
     {
-        let mut state = serializer.serialize_struct("TermRewritingSystem", 2)?;
+        let field_count = if self.costs.is_some() { 3 } else { 2 };
+        let mut state = serializer.serialize_struct("TermRewritingSystem", field_count)?;
         state.serialize_field("language", &self.language)?;
-
-        let serializable_rules: Vec<SerializableRule> = self
-            .rules
-            .iter()
-            .map(|rule| SerializableRule {
-                from: format!("{}", rule.from().with_language(&self.language)),
-                to: format!("{}", rule.to().with_language(&self.language)),
-            })
-            .collect();
-        state.serialize_field("rules", &serializable_rules)?;
+        state.serialize_field("rules", &self.serializable_rules())?;
+        if let Some(costs) = &self.costs {
+            state.serialize_field("costs", costs)?;
+        }
         state.end()
     }
 }
@@ -139,6 +473,7 @@ impl<'de> Deserialize<'de> for TermRewritingSystem {
         enum Field {
             Language,
             Rules,
+            Costs,
         }
 
         struct TermRewritingSystemVisitor;
@@ -155,7 +490,8 @@ impl<'de> Deserialize<'de> for TermRewritingSystem {
                 V: serde::de::MapAccess<'de>,
             {
                 let mut language: Option<Language> = None;
-                let mut serializable_rules: Option<Vec<SerializableRule>> = None;
+                let mut serializable_rules: Option<RuleEntries> = None;
+                let mut costs: Option<CostTable> = None;
 
                 while let Some(key) = map.next_key()? {
                     match key {
@@ -171,6 +507,12 @@ impl<'de> Deserialize<'de> for TermRewritingSystem {
                             }
                             serializable_rules = Some(map.next_value()?);
                         }
+                        Field::Costs => {
+                            if costs.is_some() {
+                                return Err(serde::de::Error::duplicate_field("costs"));
+                            }
+                            costs = Some(map.next_value()?);
+                        }
                     }
                 }
 
@@ -179,18 +521,21 @@ impl<'de> Deserialize<'de> for TermRewritingSystem {
                 let serializable_rules =
                     serializable_rules.ok_or_else(|| serde::de::Error::missing_field("rules"))?;
 
-                let rules: Vec<Rule> = serializable_rules
-                    .into_iter()
-                    .map(|sr| Rule::from_strings(&sr.from, &sr.to, &language))
-                    .collect();
+                let rules = serializable_rules
+                    .into_rules(&language)
+                    .map_err(serde::de::Error::custom)?;
 
-                Ok(TermRewritingSystem::new(language, rules))
+                Ok(TermRewritingSystem {
+                    language,
+                    rules,
+                    costs,
+                })
             }
         }
 
         deserializer.deserialize_struct(
             "TermRewritingSystem",
-            &["language", "rules"],
+            &["language", "rules", "costs"],
             TermRewritingSystemVisitor,
         )
     }
@@ -200,7 +545,7 @@ impl<'de> Deserialize<'de> for TermRewritingSystem {
 mod tests {
     use super::TermRewritingSystem;
     use crate::language::Language;
-    use crate::language::expression::AnyExpression;
+    use crate::language::expression::{AnyExpression, Literal};
     use crate::macros::rules;
     use crate::rewriting::egraph::{DynEGraph, EGraph};
 
@@ -264,4 +609,219 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn rewrite_with_assumptions() {
+        let lang = Language::simple_math().add_symbol("x");
+        let trs = TermRewritingSystem::new(lang.clone(), rules!(lang; "(- $0 $0)" => "0"));
+        let expression = lang.parse_no_vars("(- (x) 2)").unwrap();
+
+        let mut assumptions = EGraph::<()>::default();
+        let x = lang.parse_no_vars("(x)").unwrap();
+        let two = lang.parse_no_vars("2").unwrap();
+        assert!(
+            assumptions
+                .assume_equal(x.clone(), two.clone())
+                .new()
+                .is_some()
+        );
+        assert!(assumptions.assume_equal(x, two).new().is_none());
+
+        let (egraph, class_id) = trs.rewrite_with_assumptions(assumptions, expression.clone());
+        assert!(egraph.class_contains_literal(class_id, &Literal::Int(0)));
+
+        let (egraph, class_id) = trs.rewrite_with_assumptions(EGraph::<()>::default(), expression);
+        assert!(!egraph.class_contains_literal(class_id, &Literal::Int(0)));
+    }
+
+    #[test]
+    fn merge_systems() {
+        let logic_lang = Language::default().add_symbol("and").add_symbol("eq");
+        let logic = TermRewritingSystem::new(
+            logic_lang.clone(),
+            rules!(logic_lang; "(and $0 $0)" => "$0"),
+        );
+        let math_lang = Language::default().add_symbol("eq").add_symbol("+");
+        let math = TermRewritingSystem::new(
+            math_lang.clone(),
+            rules!(math_lang; "(eq (+ $0 0) $1)" => "(eq $0 $1)"),
+        );
+
+        let merged = logic.merge(&math);
+        let lang = merged.language();
+        assert_eq!(lang.symbol_count(), 3);
+        assert_eq!(merged.rules()[0].label(lang), "(and $0 $0) => $0");
+        assert_eq!(
+            merged.rules()[1].label(lang),
+            "(eq (+ $0 0) $1) => (eq $0 $1)"
+        );
+    }
+
+    #[test]
+    fn named_and_bidirectional_rules_macro() {
+        let lang = Language::simple_math();
+        let rules = rules!(lang;
+            "comm-add": "(+ $0 $1)" <=> "(+ $1 $0)",
+            "(* $0 1)" <=> "$0",
+            "double": "(* $0 2)" => "(<< $0 1)",
+        );
+
+        let names: Vec<_> = rules.iter().map(|rule| rule.name()).collect();
+        assert_eq!(
+            names,
+            [
+                Some("comm-add"),
+                Some("comm-add-rev"),
+                None,
+                None,
+                Some("double")
+            ]
+        );
+        assert_eq!(rules[3].label(&lang), "$0 => (* $0 1)");
+    }
+
+    #[test]
+    fn rules_json_formats() {
+        let language = serde_json::to_value(Language::simple_math()).unwrap();
+        let trs = |rules: serde_json::Value| -> TermRewritingSystem {
+            serde_json::from_value(serde_json::json!({ "language": language, "rules": rules }))
+                .unwrap()
+        };
+
+        let listed = trs(serde_json::json!([
+            "(+ $0 0) => $0",
+            { "name": "comm-mul", "from": "(* $0 $1)", "to": "(* $1 $0)", "bidirectional": true },
+            "(sin $0) <=> (cos $0)",
+        ]));
+        let names: Vec<_> = listed.rules().iter().map(|rule| rule.name()).collect();
+        assert_eq!(
+            names,
+            [None, Some("comm-mul"), Some("comm-mul-rev"), None, None]
+        );
+        assert_eq!(listed.rules()[0].label(listed.language()), "(+ $0 0) => $0");
+        assert_eq!(
+            listed.rules()[4].label(listed.language()),
+            "(cos $0) => (sin $0)"
+        );
+
+        // Parsed from a string, since `serde_json::Value` does not preserve the order of keys
+        let named: TermRewritingSystem = serde_json::from_str(&format!(
+            r#"{{
+                "language": {language},
+                "rules": {{
+                    "zero-add": "(+ $0 0) => $0",
+                    "comm-add": "(+ $0 $1) <=> (+ $1 $0)",
+                    "assoc-add": {{ "from": "(+ $0 (+ $1 $2))", "to": "(+ (+ $0 $1) $2)" }}
+                }}
+            }}"#
+        ))
+        .unwrap();
+        let names: Vec<_> = named.rules().iter().map(|rule| rule.name()).collect();
+        assert_eq!(
+            names,
+            [
+                Some("zero-add"),
+                Some("comm-add"),
+                Some("comm-add-rev"),
+                Some("assoc-add")
+            ]
+        );
+
+        let round_trip: TermRewritingSystem =
+            serde_json::from_str(&serde_json::to_string(&named).unwrap()).unwrap();
+        assert_eq!(round_trip.rules(), named.rules());
+
+        let invalid = serde_json::json!({ "language": language, "rules": ["(+ $0 0) -> $0"] });
+        assert!(serde_json::from_value::<TermRewritingSystem>(invalid).is_err());
+
+        let guarded = trs(serde_json::json!([
+            { "from": "(/ #0 #0)", "to": "1", "guard": "#0 != 0" },
+            "(* #0 0) => 0",
+        ]));
+        assert_eq!(
+            guarded.rules()[0].label(guarded.language()),
+            "(/ #0 #0) => 1 if #0 != 0"
+        );
+        assert_eq!(guarded.rules()[1].guard().literal_variables.len(), 1);
+        let round_trip: TermRewritingSystem =
+            serde_json::from_str(&serde_json::to_string(&guarded).unwrap()).unwrap();
+        assert_eq!(round_trip.rules(), guarded.rules());
+
+        let invalid = serde_json::json!({
+            "language": language,
+            "rules": [{ "from": "(/ #0 #0)", "to": "1", "guard": "#0 ! 0" }],
+        });
+        assert!(serde_json::from_value::<TermRewritingSystem>(invalid).is_err());
+    }
+
+    #[test]
+    fn costs() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("jsons/simple-math");
+        let trs = TermRewritingSystem::from_directory(dir).unwrap();
+        let costs = trs.costs().unwrap();
+        assert_eq!(costs.symbol_cost("/"), 8);
+        assert_eq!(costs.literal_cost, 1);
+
+        let language = serde_json::to_value(Language::simple_math()).unwrap();
+        let with_costs: TermRewritingSystem = serde_json::from_value(serde_json::json!({
+            "language": language,
+            "rules": ["(+ $0 0) => $0"],
+            "costs": { "literal_cost": 2, "per_symbol_cost": { "+": 3 } },
+        }))
+        .unwrap();
+        assert_eq!(with_costs.costs().unwrap().symbol_cost("+"), 3);
+
+        let round_trip: TermRewritingSystem =
+            serde_json::from_str(&serde_json::to_string(&with_costs).unwrap()).unwrap();
+        assert_eq!(round_trip.costs(), with_costs.costs());
+        assert!(
+            TermRewritingSystem::new(Language::simple_math(), vec![])
+                .costs()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn save_to_directory_round_trip() {
+        use crate::language::arities::Arities;
+        use crate::rewriting::egraph::extraction::CostTable;
+        use crate::rewriting::{guard::Guard, rule::Rule};
+
+        let lang = Language::simple_math();
+        let rules = rules!(lang;
+            "commute": "(+ $0 $1)" <=> "(+ $1 $0)",
+            "(* $0 1)" => "$0",
+        );
+        let rules = vec![
+            rules[0].clone().with_cost(3),
+            rules[1].clone(),
+            rules[2].clone(),
+            Rule::try_from_strings("(/ $0 #1)", "(* $0 (/ 1 #1))", &lang)
+                .unwrap()
+                .with_guard(Guard::parse("#1 != 0").unwrap()),
+        ];
+        let costs = CostTable {
+            per_symbol_cost: [(String::from("/"), 8)].into_iter().collect(),
+            ..Default::default()
+        };
+        let trs = TermRewritingSystem::new(lang.clone(), rules).with_costs(costs);
+
+        let dir = std::env::temp_dir().join(format!("trs-{}", std::process::id()));
+        trs.save_to_directory(&dir).unwrap();
+        let loaded = TermRewritingSystem::from_directory(&dir).unwrap();
+        assert_eq!(loaded.language(), trs.language());
+        assert_eq!(loaded.rules(), trs.rules());
+        assert_eq!(loaded.costs(), trs.costs());
+
+        let arities: Arities = crate::utils::json::load_json(dir.join("arities.json")).unwrap();
+        assert_eq!(arities, trs.arities());
+        assert_eq!(arities.get(lang.get_id("+")), Some(&[2][..]));
+        assert_eq!(arities.get(lang.get_id("sin")), None);
+
+        let without_costs = TermRewritingSystem::new(lang, trs.rules().clone());
+        without_costs.save_to_directory(&dir).unwrap();
+        let loaded = TermRewritingSystem::from_directory(&dir).unwrap();
+        assert!(loaded.costs().is_none());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 }
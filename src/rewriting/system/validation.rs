@@ -0,0 +1,333 @@
+//! Static checks of term rewriting systems.
+//!
+//! [`TermRewritingSystem::validate`] looks for mistakes which are easy to make when writing
+//! rules by hand, such as typos in variable names or duplicated rules.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+use crate::language::{
+    expression::{Expression, VariableId},
+    symbol::SymbolId,
+};
+use crate::rewriting::rule::Rule;
+
+use super::TermRewritingSystem;
+
+/// How serious a [`Diagnostic`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The system can be used, but probably does not do what was intended
+    Warning,
+    /// The system cannot be used
+    Error,
+}
+
+/// A problem found by [`TermRewritingSystem::validate`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Diagnostic {
+    /// The right-hand side of a rule contains variables which do not occur on its left-hand side
+    UnboundVariables {
+        rule: usize,
+        variables: Vec<VariableId>,
+    },
+    /// A rule contains a symbol ID which is not in the language
+    UnknownSymbol { rule: usize, symbol: SymbolId },
+    /// A rule is the same as an earlier one, up to renaming of variables
+    DuplicateRule { rule: usize, duplicate_of: usize },
+    /// Both sides of a rule are the same
+    TrivialLoop { rule: usize },
+    /// A symbol without declared arities is used with different numbers of children in rules
+    InconsistentArity {
+        symbol: String,
+        /// Numbers of children, each with the index of the first rule in which it occurs
+        arities: BTreeMap<usize, usize>,
+    },
+    /// The costs of the system name a symbol which is not in the language
+    UnknownCostSymbol { symbol: String },
+}
+
+impl Diagnostic {
+    /// Returns how serious the diagnostic is.
+    pub fn severity(&self) -> Severity {
+        match self {
+            Diagnostic::UnboundVariables { .. } | Diagnostic::UnknownSymbol { .. } => {
+                Severity::Error
+            }
+            Diagnostic::DuplicateRule { .. }
+            | Diagnostic::TrivialLoop { .. }
+            | Diagnostic::InconsistentArity { .. }
+            | Diagnostic::UnknownCostSymbol { .. } => Severity::Warning,
+        }
+    }
+
+    /// Returns `true` if the diagnostic makes the system unusable.
+    pub fn is_error(&self) -> bool {
+        self.severity() == Severity::Error
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Diagnostic::UnboundVariables { rule, variables } => {
+                let variables: Vec<_> = variables
+                    .iter()
+                    .map(|id| Expression::variable_name(*id))
+                    .collect();
+                write!(
+                    f,
+                    "Rule {rule} has variables {} on its right-hand side which are not bound by its left-hand side",
+                    variables.join(", ")
+                )
+            }
+            Diagnostic::UnknownSymbol { rule, symbol } => {
+                write!(
+                    f,
+                    "Rule {rule} uses symbol {symbol} which is not in the language"
+                )
+            }
+            Diagnostic::DuplicateRule { rule, duplicate_of } => write!(
+                f,
+                "Rule {rule} is the same as rule {duplicate_of} up to renaming of variables"
+            ),
+            Diagnostic::TrivialLoop { rule } => {
+                write!(f, "Rule {rule} rewrites an expression to itself")
+            }
+            Diagnostic::InconsistentArity { symbol, arities } => {
+                write!(f, "Symbol {symbol} is used with")?;
+                for (index, (arity, rule)) in arities.iter().enumerate() {
+                    let separator = if index == 0 { "" } else { "," };
+                    write!(f, "{separator} {arity} children in rule {rule}")?;
+                }
+                Ok(())
+            }
+            Diagnostic::UnknownCostSymbol { symbol } => {
+                write!(f, "Costs name symbol {symbol} which is not in the language")
+            }
+        }
+    }
+}
+
+/// A system rejected because of diagnostics with [`Severity::Error`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationError {
+    /// All diagnostics of the system, including warnings
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid term rewriting system:")?;
+        for diagnostic in self.diagnostics.iter().filter(|d| d.is_error()) {
+            write!(f, "\n  {diagnostic}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Renames variables of a rule in the order of their first occurrence, left-hand side first.
+fn canonical_rule(rule: &Rule) -> (Expression, Expression) {
+    let mut renaming = HashMap::new();
+    rule.from().extend_canonical_renaming(&mut renaming);
+    rule.to().extend_canonical_renaming(&mut renaming);
+    let (mut from, mut to) = (rule.from().clone(), rule.to().clone());
+    from.rename_variables(&renaming);
+    to.rename_variables(&renaming);
+    (from, to)
+}
+
+/// Calls `f` with the ID and number of children of every symbol occurrence in `expression`.
+fn for_each_symbol(expression: &Expression, f: &mut impl FnMut(SymbolId, usize)) {
+    if let Expression::Symbol(symbol) = expression {
+        f(symbol.id, symbol.children.len());
+        for child in &symbol.children {
+            for_each_symbol(child, f);
+        }
+    }
+}
+
+impl TermRewritingSystem {
+    /// Checks the system for common mistakes.
+    ///
+    /// # Returns
+    ///
+    /// Returns diagnostics ordered by the rules they concern, followed by diagnostics about symbols
+    /// and costs.
+    /// The system is unusable if any of them is an error, see [`Diagnostic::is_error`].
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let language = self.language();
+        let mut diagnostics = Vec::new();
+        let mut canonical_rules = HashMap::new();
+        let mut symbol_arities: BTreeMap<SymbolId, BTreeMap<usize, usize>> = BTreeMap::new();
+
+        for (index, rule) in self.rules().iter().enumerate() {
+            let bound = rule.from().variables();
+            let unbound: BTreeSet<_> = rule
+                .to()
+                .variables()
+                .into_iter()
+                .filter(|variable| !bound.contains(variable))
+                .collect();
+            if !unbound.is_empty() {
+                diagnostics.push(Diagnostic::UnboundVariables {
+                    rule: index,
+                    variables: unbound.into_iter().collect(),
+                });
+            }
+
+            let mut unknown = BTreeSet::new();
+            for side in [rule.from(), rule.to()] {
+                for_each_symbol(side, &mut |symbol, child_count| {
+                    if symbol >= language.symbol_count() {
+                        unknown.insert(symbol);
+                    } else if language.arities().and_then(|a| a.get(symbol)).is_none() {
+                        symbol_arities
+                            .entry(symbol)
+                            .or_default()
+                            .entry(child_count)
+                            .or_insert(index);
+                    }
+                });
+            }
+            diagnostics.extend(unknown.into_iter().map(|symbol| Diagnostic::UnknownSymbol {
+                rule: index,
+                symbol,
+            }));
+
+            if rule.from() == rule.to() {
+                diagnostics.push(Diagnostic::TrivialLoop { rule: index });
+            }
+
+            if let Some(duplicate_of) = canonical_rules.get(&canonical_rule(rule)) {
+                diagnostics.push(Diagnostic::DuplicateRule {
+                    rule: index,
+                    duplicate_of: *duplicate_of,
+                });
+            } else {
+                canonical_rules.insert(canonical_rule(rule), index);
+            }
+        }
+
+        diagnostics.extend(
+            symbol_arities
+                .into_iter()
+                .filter(|(_, arities)| arities.len() > 1)
+                .map(|(symbol, arities)| Diagnostic::InconsistentArity {
+                    symbol: String::from(language.get_symbol(symbol)),
+                    arities,
+                }),
+        );
+
+        if let Some(costs) = self.costs() {
+            diagnostics.extend(costs.unknown_symbols(language).into_iter().map(|symbol| {
+                Diagnostic::UnknownCostSymbol {
+                    symbol: String::from(symbol),
+                }
+            }));
+        }
+
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+
+    use super::{Diagnostic, Severity};
+    use crate::{
+        language::{Language, arities::Arities, expression::Expression, symbol::Symbol},
+        macros::rules,
+        rewriting::{egraph::extraction::CostTable, rule::Rule, system::TermRewritingSystem},
+    };
+
+    #[test]
+    fn valid_system() {
+        let lang = Language::simple_math();
+        let rules = rules!(lang;
+            "(+ $0 0)" => "$0",
+            "(* $0 $1)" => "(* $1 $0)",
+        );
+        assert!(TermRewritingSystem::new(lang, rules).validate().is_empty());
+    }
+
+    #[test]
+    fn all_diagnostics() {
+        let lang = Language::simple_math();
+        let mut rules = rules!(lang;
+            "(+ $0 0)" => "(* $0 $1 $2)",
+            "(sin $0)" => "(sin $0)",
+            "(* $0 $1)" => "(* $1 $0)",
+            "(* $1 $2)" => "(* $2 $1)",
+        );
+        rules.push(Rule::from_expressions(
+            Expression::Symbol(Symbol {
+                id: 100,
+                children: vec![],
+            }),
+            lang.parse("0").unwrap(),
+        ));
+        let trs = TermRewritingSystem::new(lang, rules).with_costs(CostTable {
+            per_symbol_cost: HashMap::from([(String::from("tan"), 2), (String::from("sin"), 2)]),
+            ..Default::default()
+        });
+
+        let diagnostics = trs.validate();
+        assert_eq!(
+            diagnostics,
+            vec![
+                Diagnostic::UnboundVariables {
+                    rule: 0,
+                    variables: vec![1, 2],
+                },
+                Diagnostic::TrivialLoop { rule: 1 },
+                Diagnostic::DuplicateRule {
+                    rule: 3,
+                    duplicate_of: 2,
+                },
+                Diagnostic::UnknownSymbol {
+                    rule: 4,
+                    symbol: 100,
+                },
+                Diagnostic::InconsistentArity {
+                    symbol: String::from("*"),
+                    arities: BTreeMap::from([(3, 0), (2, 2)]),
+                },
+                Diagnostic::UnknownCostSymbol {
+                    symbol: String::from("tan"),
+                },
+            ]
+        );
+
+        let severities: Vec<_> = diagnostics.iter().map(Diagnostic::severity).collect();
+        assert_eq!(
+            severities,
+            [
+                Severity::Error,
+                Severity::Warning,
+                Severity::Warning,
+                Severity::Error,
+                Severity::Warning,
+                Severity::Warning
+            ]
+        );
+        assert_eq!(
+            diagnostics[4].to_string(),
+            "Symbol * is used with 2 children in rule 2, 3 children in rule 0"
+        );
+    }
+
+    #[test]
+    fn declared_arities_are_not_inconsistent() {
+        let mut arities = Arities::new();
+        arities.set(2, vec![2, 3]);
+        let lang = Language::simple_math().with_arities(arities);
+        let rules = rules!(lang;
+            "(* $0 $1 $2)" => "(* $0 (* $1 $2))",
+        );
+        assert!(TermRewritingSystem::new(lang, rules).validate().is_empty());
+    }
+}
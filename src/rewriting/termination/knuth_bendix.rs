@@ -0,0 +1,212 @@
+//! Knuth–Bendix ordering.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
+use super::TermOrdering;
+use crate::language::{
+    Language,
+    expression::{Expression, VariableId},
+    symbol::SymbolId,
+};
+
+/// Weight of expressions in a [`KnuthBendixOrdering`]
+pub type Weight = u32;
+
+/// The Knuth–Bendix ordering (KBO).
+///
+/// Expressions are compared first by their weight, i.e. the sum of weights of all their
+/// symbols, literals and variables, and then by the precedence of their root symbols and
+/// lexicographically by their children. Literals are smaller than all symbols in the
+/// precedence and are ordered by value among themselves.
+///
+/// For the ordering to be well-founded, all weights have to be positive, with the exception
+/// of at most one unary symbol, which then has to be the greatest in the precedence.
+#[derive(Clone, Debug)]
+pub struct KnuthBendixOrdering {
+    symbol_weights: Vec<Weight>,
+    precedence: Vec<usize>,
+    variable_weight: Weight,
+}
+
+impl KnuthBendixOrdering {
+    /// Creates an ordering where all symbols and variables have weight `1`
+    /// and symbols with greater IDs are greater in the precedence.
+    pub fn new(language: &Language) -> Self {
+        let symbol_count = language.symbol_count();
+        Self {
+            symbol_weights: vec![1; symbol_count],
+            precedence: (0..symbol_count).collect(),
+            variable_weight: 1,
+        }
+    }
+
+    /// Sets the weight of `symbol`.
+    pub fn with_weight(mut self, symbol: SymbolId, weight: Weight) -> Self {
+        self.symbol_weights[symbol] = weight;
+        self
+    }
+
+    /// Sets the weight of variables and literals.
+    pub fn with_variable_weight(mut self, weight: Weight) -> Self {
+        self.variable_weight = weight;
+        self
+    }
+
+    /// Sets the precedence of symbols. `symbols` are listed from the smallest to the greatest.
+    /// Symbols not present in `symbols` are smaller than all symbols which are.
+    pub fn with_precedence(mut self, symbols: &[SymbolId]) -> Self {
+        self.precedence = vec![0; self.symbol_weights.len()];
+        for (rank, symbol) in symbols.iter().enumerate() {
+            self.precedence[*symbol] = rank + 1;
+        }
+        self
+    }
+
+    fn weight(&self, expression: &Expression) -> Weight {
+        match expression {
+            Expression::Literal(_) | Expression::Variable(_) => self.variable_weight,
+            Expression::Symbol(symbol) => {
+                self.symbol_weights[symbol.id]
+                    + symbol
+                        .children
+                        .iter()
+                        .map(|child| self.weight(child))
+                        .sum::<Weight>()
+            }
+        }
+    }
+
+    fn variable_counts(expression: &Expression) -> HashMap<VariableId, usize> {
+        expression
+            .find_all_variables()
+            .into_iter()
+            .map(|(variable, paths)| (variable, paths.len()))
+            .collect()
+    }
+
+    /// Compares the heads of two non-variable expressions in the precedence.
+    fn compare_heads(&self, left: &Expression, right: &Expression) -> Ordering {
+        match (left, right) {
+            (Expression::Symbol(left), Expression::Symbol(right)) => (
+                self.precedence[left.id],
+                left.id,
+                left.children.len(),
+            )
+                .cmp(&(self.precedence[right.id], right.id, right.children.len())),
+            (Expression::Symbol(_), _) => Ordering::Greater,
+            (_, Expression::Symbol(_)) => Ordering::Less,
+            (Expression::Literal(left), Expression::Literal(right)) => left.cmp(right),
+            _ => Ordering::Equal,
+        }
+    }
+}
+
+impl TermOrdering for KnuthBendixOrdering {
+    fn greater(&self, left: &Expression, right: &Expression) -> bool {
+        if left == right {
+            return false;
+        }
+
+        match (left, right) {
+            (Expression::Variable(_), _) => return false,
+            (_, Expression::Variable(variable)) => return left.contains_variable(*variable),
+            _ => {}
+        }
+
+        let left_counts = Self::variable_counts(left);
+        let variables_ok = Self::variable_counts(right)
+            .into_iter()
+            .all(|(variable, count)| left_counts.get(&variable).is_some_and(|c| *c >= count));
+
+        if !variables_ok {
+            return false;
+        }
+
+        match self.weight(left).cmp(&self.weight(right)) {
+            Ordering::Greater => true,
+            Ordering::Less => false,
+            Ordering::Equal => match self.compare_heads(left, right) {
+                Ordering::Greater => true,
+                Ordering::Less => false,
+                Ordering::Equal => {
+                    let (Expression::Symbol(left), Expression::Symbol(right)) = (left, right)
+                    else {
+                        return false;
+                    };
+
+                    left.children
+                        .iter()
+                        .zip_eq(right.children.iter())
+                        .find(|(left_child, right_child)| left_child != right_child)
+                        .is_some_and(|(left_child, right_child)| {
+                            self.greater(left_child, right_child)
+                        })
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KnuthBendixOrdering;
+    use crate::language::Language;
+    use crate::rewriting::termination::TermOrdering;
+
+    fn greater(ordering: &KnuthBendixOrdering, lang: &Language, left: &str, right: &str) -> bool {
+        ordering.greater(&lang.parse(left).unwrap(), &lang.parse(right).unwrap())
+    }
+
+    #[test]
+    fn kbo_weight() {
+        let lang = Language::simple_math();
+        let ordering = KnuthBendixOrdering::new(&lang);
+        assert!(greater(&ordering, &lang, "(* $0 1)", "$0"));
+        assert!(greater(&ordering, &lang, "(+ $0 (+ $1 2))", "(+ $0 $1)"));
+        assert!(!greater(&ordering, &lang, "$0", "(* $0 1)"));
+    }
+
+    #[test]
+    fn kbo_variable_condition() {
+        let lang = Language::simple_math();
+        let ordering = KnuthBendixOrdering::new(&lang);
+        assert!(!greater(&ordering, &lang, "(* (* $0 1) 1)", "(+ $1 $0)"));
+        assert!(!greater(&ordering, &lang, "(sin (sin $0))", "(+ $0 $0)"));
+    }
+
+    #[test]
+    fn kbo_precedence() {
+        let lang = Language::simple_math();
+        let ordering =
+            KnuthBendixOrdering::new(&lang).with_precedence(&[lang.get_id("+"), lang.get_id("*")]);
+        assert!(greater(&ordering, &lang, "(* $0 $1)", "(+ $0 $1)"));
+        assert!(!greater(&ordering, &lang, "(+ $0 $1)", "(* $0 $1)"));
+    }
+
+    #[test]
+    fn kbo_lexicographic() {
+        let lang = Language::simple_math();
+        let ordering = KnuthBendixOrdering::new(&lang);
+        assert!(greater(
+            &ordering,
+            &lang,
+            "(* (* $0 $1) $2)",
+            "(* $0 (* $1 $2))"
+        ));
+        assert!(!greater(&ordering, &lang, "(+ $0 $1)", "(+ $1 $0)"));
+        assert!(greater(&ordering, &lang, "(+ 2 3)", "(+ 1 3)"));
+    }
+
+    #[test]
+    fn kbo_zero_weight_unary() {
+        let lang = Language::default().add_symbol("i").add_symbol("f");
+        let ordering = KnuthBendixOrdering::new(&lang)
+            .with_weight(lang.get_id("f"), 0)
+            .with_precedence(&[lang.get_id("i"), lang.get_id("f")]);
+        assert!(greater(&ordering, &lang, "(f (i $0))", "(i (f $0))"));
+        assert!(greater(&ordering, &lang, "(f $0)", "$0"));
+    }
+}
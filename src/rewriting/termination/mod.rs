@@ -0,0 +1,110 @@
+//! Termination checking via reduction orderings.
+//!
+//! A rewriting system terminates if there is a reduction ordering in which the
+//! left-hand side of every rule is greater than its right-hand side. This module
+//! provides the [`TermOrdering`] trait together with two classic families of such
+//! orderings: the Knuth–Bendix ordering and linear polynomial interpretations.
+
+use std::fmt;
+
+use crate::language::expression::Expression;
+use crate::rewriting::system::TermRewritingSystem;
+
+pub mod knuth_bendix;
+pub mod polynomial;
+
+pub use knuth_bendix::KnuthBendixOrdering;
+pub use polynomial::{LinearInterpretation, PolynomialOrdering};
+
+/// A reduction ordering on expressions, used to orient equations into rules.
+/// Variables in compared expressions stand for arbitrary expressions, so the ordering
+/// has to be stable under substitutions for termination proofs and completion to be sound.
+pub trait TermOrdering {
+    /// Returns `true` if `left` is strictly greater than `right`.
+    fn greater(&self, left: &Expression, right: &Expression) -> bool;
+}
+
+/// Rules of a system which are not decreasing in the given ordering.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TerminationError {
+    /// Indices of the non-decreasing rules
+    pub rule_indices: Vec<usize>,
+}
+
+impl fmt::Display for TerminationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Rules {:?} are not decreasing in the term ordering",
+            self.rule_indices
+        )
+    }
+}
+
+impl std::error::Error for TerminationError {}
+
+impl TermRewritingSystem {
+    /// Verifies that the left-hand side of every rule is greater than its right-hand side
+    /// in `ordering`. If so, the system is terminating, e.g. [`crate::rewriting::direct::rewrite`]
+    /// reaches a normal form for every expression.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if all rules are decreasing, or the indices of the rules that are not.
+    pub fn check_termination(&self, ordering: &dyn TermOrdering) -> Result<(), TerminationError> {
+        let rule_indices: Vec<usize> = self
+            .rules()
+            .iter()
+            .enumerate()
+            .filter(|(_, rule)| !ordering.greater(rule.from(), rule.to()))
+            .map(|(index, _)| index)
+            .collect();
+
+        if rule_indices.is_empty() {
+            Ok(())
+        } else {
+            Err(TerminationError { rule_indices })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{KnuthBendixOrdering, TerminationError};
+    use crate::language::Language;
+    use crate::macros::rules;
+    use crate::rewriting::system::TermRewritingSystem;
+
+    #[test]
+    fn check_termination_accepts_decreasing_rules() {
+        let lang = Language::simple_math();
+        let rules = rules!(lang;
+            "(* $0 1)" => "$0",
+            "(/ $0 $0)" => "1",
+            "(/ (* $0 $1) $2)" => "(* $0 (/ $1 $2))",
+        );
+        let ordering = KnuthBendixOrdering::new(&lang).with_precedence(&[0, 1, 2, 3]);
+        let trs = TermRewritingSystem::new(lang, rules);
+
+        assert_eq!(trs.check_termination(&ordering), Ok(()));
+    }
+
+    #[test]
+    fn check_termination_reports_rules() {
+        let lang = Language::simple_math();
+        let rules = rules!(lang;
+            "(* $0 1)" => "$0",
+            "(+ $0 $1)" => "(+ $1 $0)",
+            "$0" => "(+ $0 0)",
+        );
+        let ordering = KnuthBendixOrdering::new(&lang);
+        let trs = TermRewritingSystem::new(lang, rules);
+
+        assert_eq!(
+            trs.check_termination(&ordering),
+            Err(TerminationError {
+                rule_indices: vec![1, 2]
+            })
+        );
+    }
+}
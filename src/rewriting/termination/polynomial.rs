@@ -0,0 +1,193 @@
+//! Linear polynomial interpretations.
+
+use std::collections::HashMap;
+
+use super::TermOrdering;
+use crate::language::{
+    expression::{Expression, VariableId},
+    symbol::SymbolId,
+};
+
+/// Interpretation of a symbol as a linear polynomial `c + a_1 x_1 + ... + a_n x_n`
+/// over natural numbers, where `x_i` is the interpretation of the `i`-th child.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LinearInterpretation {
+    /// The constant term `c`
+    pub constant: u64,
+    /// Coefficients `a_i` of the children. Children without a coefficient get `1`.
+    pub coefficients: Vec<u64>,
+}
+
+impl LinearInterpretation {
+    /// Creates a new interpretation.
+    pub fn new(constant: u64, coefficients: Vec<u64>) -> Self {
+        Self {
+            constant,
+            coefficients,
+        }
+    }
+
+    fn coefficient(&self, child: usize) -> u64 {
+        self.coefficients.get(child).copied().unwrap_or(1)
+    }
+}
+
+impl Default for LinearInterpretation {
+    /// `1 + x_1 + ... + x_n`, i.e. the size of an expression
+    fn default() -> Self {
+        Self::new(1, Vec::new())
+    }
+}
+
+/// A linear polynomial with variables standing for interpretations of expression variables.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct LinearPolynomial {
+    constant: u64,
+    coefficients: HashMap<VariableId, u64>,
+}
+
+impl LinearPolynomial {
+    fn add_scaled(&mut self, other: &LinearPolynomial, factor: u64) {
+        self.constant += factor * other.constant;
+        for (variable, coefficient) in &other.coefficients {
+            *self.coefficients.entry(*variable).or_default() += factor * coefficient;
+        }
+    }
+
+    /// Checks if `self > other` for all assignments of natural numbers to variables.
+    fn absolutely_greater(&self, other: &LinearPolynomial) -> bool {
+        self.constant > other.constant
+            && other.coefficients.iter().all(|(variable, coefficient)| {
+                self.coefficients.get(variable).copied().unwrap_or(0) >= *coefficient
+            })
+    }
+}
+
+/// Ordering induced by interpreting symbols as linear polynomials over natural numbers.
+///
+/// `left > right` holds if the interpretation of `left` is greater than that of `right`
+/// for every assignment of natural numbers to variables. Symbols without an explicit
+/// interpretation use [`LinearInterpretation::default`]. For the ordering to be monotone,
+/// all coefficients have to be positive.
+#[derive(Clone, Debug)]
+pub struct PolynomialOrdering {
+    interpretations: HashMap<SymbolId, LinearInterpretation>,
+    literal_value: u64,
+}
+
+impl Default for PolynomialOrdering {
+    fn default() -> Self {
+        Self {
+            interpretations: HashMap::new(),
+            literal_value: 1,
+        }
+    }
+}
+
+impl PolynomialOrdering {
+    /// Creates an ordering in which every symbol is interpreted by [`LinearInterpretation::default`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the interpretation of `symbol`.
+    pub fn with_interpretation(
+        mut self,
+        symbol: SymbolId,
+        interpretation: LinearInterpretation,
+    ) -> Self {
+        self.interpretations.insert(symbol, interpretation);
+        self
+    }
+
+    /// Sets the value to which all literals are interpreted.
+    pub fn with_literal_value(mut self, value: u64) -> Self {
+        self.literal_value = value;
+        self
+    }
+
+    fn interpret(&self, expression: &Expression) -> LinearPolynomial {
+        match expression {
+            Expression::Literal(_) => LinearPolynomial {
+                constant: self.literal_value,
+                coefficients: HashMap::new(),
+            },
+            Expression::Variable(variable) => LinearPolynomial {
+                constant: 0,
+                coefficients: HashMap::from([(*variable, 1)]),
+            },
+            Expression::Symbol(symbol) => {
+                let default = LinearInterpretation::default();
+                let interpretation = self.interpretations.get(&symbol.id).unwrap_or(&default);
+
+                let mut polynomial = LinearPolynomial {
+                    constant: interpretation.constant,
+                    coefficients: HashMap::new(),
+                };
+
+                for (index, child) in symbol.children.iter().enumerate() {
+                    polynomial
+                        .add_scaled(&self.interpret(child), interpretation.coefficient(index));
+                }
+
+                polynomial
+            }
+        }
+    }
+}
+
+impl TermOrdering for PolynomialOrdering {
+    fn greater(&self, left: &Expression, right: &Expression) -> bool {
+        self.interpret(left)
+            .absolutely_greater(&self.interpret(right))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LinearInterpretation, PolynomialOrdering};
+    use crate::language::Language;
+    use crate::rewriting::termination::TermOrdering;
+
+    fn greater(ordering: &PolynomialOrdering, lang: &Language, left: &str, right: &str) -> bool {
+        ordering.greater(&lang.parse(left).unwrap(), &lang.parse(right).unwrap())
+    }
+
+    #[test]
+    fn polynomial_default_is_size() {
+        let lang = Language::simple_math();
+        let ordering = PolynomialOrdering::new();
+        assert!(greater(&ordering, &lang, "(* $0 1)", "$0"));
+        assert!(!greater(&ordering, &lang, "(+ $0 $1)", "(+ $1 $0)"));
+        assert!(!greater(&ordering, &lang, "(sin $0)", "(+ $0 $0)"));
+    }
+
+    #[test]
+    fn polynomial_distributivity() {
+        // Distributivity duplicates `$0`, so no linear interpretation orients it
+        let lang = Language::simple_math();
+        let ordering = PolynomialOrdering::new()
+            .with_interpretation(lang.get_id("*"), LinearInterpretation::new(1, vec![2, 2]))
+            .with_interpretation(lang.get_id("+"), LinearInterpretation::new(1, vec![1, 1]));
+        assert!(!greater(
+            &ordering,
+            &lang,
+            "(* $0 (+ $1 $2))",
+            "(+ (* $0 $1) (* $0 $2))"
+        ));
+        assert!(greater(
+            &ordering,
+            &lang,
+            "(* (+ $0 $1) $2)",
+            "(+ $0 (+ $1 $2))"
+        ));
+    }
+
+    #[test]
+    fn polynomial_weighted_coefficients() {
+        let lang = Language::simple_math();
+        let ordering = PolynomialOrdering::new()
+            .with_interpretation(lang.get_id("sin"), LinearInterpretation::new(2, vec![3]));
+        assert!(greater(&ordering, &lang, "(sin $0)", "(+ $0 $0)"));
+    }
+}
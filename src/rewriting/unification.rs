@@ -2,13 +2,15 @@
 //!
 //! This module implements unification algorithms that match expressions where
 //! both sides may contain variables. Unification finds substitutions that make
-//! two expressions equal.
+//! two expressions equal. Unification can respect the scopes of variables bound by binder
+//! symbols, see [`UnificationProblem::with_binders`].
 
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
 
 use crate::did::Did;
 use crate::equation::Equation;
 use crate::language::expression::{Expression, VariableId};
+use crate::language::symbol::{Symbol, SymbolId};
 
 /// A substitution mapping variables to expressions.
 ///
@@ -72,6 +74,27 @@ impl Substitution {
     pub fn shift_negative(&mut self, shift: usize) {
         self.map_variables(|variable| variable - shift);
     }
+
+    /// Applies the substitution to `expression`.
+    /// Variables occurring in substituted expressions are substituted as well,
+    /// so substitutions returned by [`UnificationProblem::solve`] are applied in full.
+    pub fn apply(&self, expression: &Expression) -> Expression {
+        match expression {
+            Expression::Variable(variable) => match self.get(*variable) {
+                Some(substitute) => self.apply(substitute),
+                None => expression.clone(),
+            },
+            Expression::Symbol(symbol) => Expression::Symbol(Symbol {
+                id: symbol.id,
+                children: symbol
+                    .children
+                    .iter()
+                    .map(|child| self.apply(child))
+                    .collect(),
+            }),
+            Expression::Literal(_) => expression.clone(),
+        }
+    }
 }
 
 #[derive(Clone, Default, Debug, PartialEq, Eq)]
@@ -86,12 +109,22 @@ impl IndependentVarUnifier {
     /// Returns `None` if the problem has no solution.`
     /// If one of the variables on the left will have a variable on the right assigned to it in the solution,
     /// the name of the variable from the right will be shifted.
-    pub fn unify(mut equation: Equation) -> Option<Self> {
+    pub fn unify(equation: Equation) -> Option<Self> {
+        Self::unify_with_binders(equation, &BTreeSet::new())
+    }
+
+    /// Same as [`Self::unify`], respecting the scopes of variables bound by `binders`,
+    /// see [`UnificationProblem::with_binders`].
+    pub fn unify_with_binders(
+        mut equation: Equation,
+        binders: &BTreeSet<SymbolId>,
+    ) -> Option<Self> {
         let shift = equation.left.max_variable_id().map(|x| x + 1).unwrap_or(0);
         equation.right.shift_variables(shift);
 
         let (left_substitution, mut right_substitution) =
             UnificationProblem::from_equation(equation)
+                .with_binders(binders)
                 .solve()?
                 .split_at(shift);
 
@@ -116,6 +149,11 @@ impl IndependentVarUnifier {
 #[derive(Clone, Debug, Default)]
 pub struct UnificationProblem {
     equations: Vec<Equation>,
+    binders: BTreeSet<SymbolId>,
+    /// Variables bound by binders, renamed apart, which cannot be substituted
+    rigid: HashSet<VariableId>,
+    /// Rigid variables in scope at all occurrences of the other variables
+    scopes: HashMap<VariableId, HashSet<VariableId>>,
 }
 
 impl UnificationProblem {
@@ -124,13 +162,29 @@ impl UnificationProblem {
     }
 
     pub fn from_equation(equation: Equation) -> Self {
+        Self::from_equations(vec![equation])
+    }
+
+    pub fn from_equations(equations: Vec<Equation>) -> Self {
         Self {
-            equations: vec![equation],
+            equations,
+            ..Default::default()
         }
     }
 
-    pub fn from_equations(equations: Vec<Equation>) -> Self {
-        Self { equations }
+    /// Makes the problem respect the scopes of variables bound by `binders`,
+    /// see [`Language::binders`](crate::language::Language::binders).
+    ///
+    /// Bound variables are renamed apart and treated as constants, so that binders unify only
+    /// with binders, whose bound variables are identified, e.g. `(lam $0 $1)` and
+    /// `(lam $2 (f $2))` unify by substituting `(f $0)` for `$1`. Variables bound in the first
+    /// equation keep their names if they are not bound twice, so that the solution can be
+    /// applied to its left-hand side. Solutions in which a bound
+    /// variable would escape the scope of its binder are rejected, e.g. those of
+    /// `(g $1 (lam $0 $1))` and `(g $2 (lam $2 $2))`.
+    pub fn with_binders(mut self, binders: &BTreeSet<SymbolId>) -> Self {
+        self.binders = binders.clone();
+        self
     }
 
     pub fn add_equation(&mut self, equation: Equation) {
@@ -148,10 +202,14 @@ impl UnificationProblem {
     /// pages 74-75.
     pub fn solve(mut self) -> Option<Substitution> {
         let mut substitution = Substitution::default();
+        if !self.binders.is_empty() {
+            self.rename_bound_apart();
+        }
 
         'outer: loop {
             for i in 0..self.equations.len() {
                 if self.delete(i).did_something()
+                    || self.identify_bound(i, &mut substitution).did_something()
                     || self.decompose(i).did_something()
                     || self.eliminate(i, &mut substitution).did_something()
                     || self.orient(i).did_something()
@@ -163,13 +221,144 @@ impl UnificationProblem {
             break;
         }
 
-        if self.equations.is_empty() {
+        if self.equations.is_empty() && !self.escapes(&substitution) {
             Some(substitution)
         } else {
             None
         }
     }
 
+    /// Makes variables bound by binders rigid, renaming them to fresh ones if they are bound
+    /// by several binders or are also free, and finds the rigid variables in scope
+    /// of the other variables.
+    fn rename_bound_apart(&mut self) {
+        let mut next_fresh = self
+            .equations
+            .iter()
+            .flat_map(|equation| [&equation.left, &equation.right])
+            .filter_map(Expression::max_variable_id)
+            .max()
+            .map_or(0, |id| id + 1);
+
+        // Free variables are taken until the bound ones are renamed
+        self.rigid = self
+            .equations
+            .iter()
+            .flat_map(|equation| [&equation.left, &equation.right])
+            .flat_map(|side| side.free_variables(&self.binders))
+            .collect();
+        let mut equations = std::mem::take(&mut self.equations);
+        for equation in &mut equations {
+            for side in [&mut equation.left, &mut equation.right] {
+                self.rename_in_scope(side, &mut Vec::new(), &mut next_fresh);
+            }
+        }
+        self.equations = equations;
+        self.rigid
+            .retain(|variable| !self.scopes.contains_key(variable));
+    }
+
+    fn rename_in_scope(
+        &mut self,
+        expression: &mut Expression,
+        scope: &mut Vec<VariableId>,
+        next_fresh: &mut VariableId,
+    ) {
+        let binding = expression.bound_variable(&self.binders);
+        match expression {
+            Expression::Variable(variable) => {
+                if !scope.contains(variable) {
+                    let in_scope = HashSet::from_iter(scope.iter().copied());
+                    self.scopes
+                        .entry(*variable)
+                        .and_modify(|common| common.retain(|rigid| in_scope.contains(rigid)))
+                        .or_insert(in_scope);
+                }
+            }
+            Expression::Symbol(symbol) => {
+                let Some(bound) = binding else {
+                    for child in &mut symbol.children {
+                        self.rename_in_scope(child, scope, next_fresh);
+                    }
+                    return;
+                };
+
+                let rigid = if self.rigid.insert(bound) {
+                    bound
+                } else {
+                    let fresh = *next_fresh;
+                    *next_fresh += 1;
+                    self.rigid.insert(fresh);
+                    symbol.children[0] = Expression::Variable(fresh);
+                    for child in &mut symbol.children[1..] {
+                        child.substitute_avoiding_capture(
+                            bound,
+                            &Expression::Variable(fresh),
+                            &self.binders,
+                        );
+                    }
+                    fresh
+                };
+                scope.push(rigid);
+                for child in &mut symbol.children[1..] {
+                    self.rename_in_scope(child, scope, next_fresh);
+                }
+                scope.pop();
+            }
+            Expression::Literal(_) => {}
+        }
+    }
+
+    /// If equation number `equation_idx` has the same binder at root on both sides,
+    /// renames the rigid variable bound on the right to the one bound on the left everywhere.
+    fn identify_bound(&mut self, equation_idx: usize, substitution: &mut Substitution) -> Did {
+        let equation = &self.equations[equation_idx];
+        let (Some(left), Some(right)) = (
+            equation.left.bound_variable(&self.binders),
+            equation.right.bound_variable(&self.binders),
+        ) else {
+            return Did::Nothing;
+        };
+        let (Expression::Symbol(left_symbol), Expression::Symbol(right_symbol)) =
+            (&equation.left, &equation.right)
+        else {
+            return Did::Nothing;
+        };
+        if left == right || !left_symbol.same_shape_as(right_symbol) {
+            return Did::Nothing;
+        }
+
+        let renamed = Expression::Variable(left);
+        for equation in &mut self.equations {
+            equation.left.substitute(right, &renamed);
+            equation.right.substitute(right, &renamed);
+        }
+        for substitute in substitution.0.values_mut() {
+            substitute.substitute(right, &renamed);
+        }
+        for scope in self.scopes.values_mut() {
+            if scope.remove(&right) {
+                scope.insert(left);
+            }
+        }
+        Did::Something
+    }
+
+    /// `true` if a rigid variable occurs free in the substitute of a variable occurring
+    /// outside of the scope of its binder.
+    fn escapes(&self, substitution: &Substitution) -> bool {
+        substitution.0.iter().any(|(variable, substitute)| {
+            let scope = self.scopes.get(variable);
+            substitution
+                .apply(substitute)
+                .free_variables(&self.binders)
+                .into_iter()
+                .any(|free| {
+                    self.rigid.contains(&free) && scope.is_none_or(|scope| !scope.contains(&free))
+                })
+        })
+    }
+
     /// If equation number `equation_idx` is trivial, removes it from equations.
     pub fn delete(&mut self, equation_idx: usize) -> Did {
         if self.equations[equation_idx].is_trivial() {
@@ -196,7 +385,9 @@ impl UnificationProblem {
 
     /// Checks if the equation number `equation_idx` is of the form `t = $i` and replaces it with `$i = t`.
     pub fn orient(&mut self, equation_idx: usize) -> Did {
-        if matches!(self.equations[equation_idx].right, Expression::Variable(_)) {
+        if let Expression::Variable(variable) = self.equations[equation_idx].right
+            && !self.rigid.contains(&variable)
+        {
             self.equations[equation_idx].reorient();
             Did::Something
         } else {
@@ -211,6 +402,9 @@ impl UnificationProblem {
         let Expression::Variable(variable_id) = self.equations[equation_idx].left else {
             return Did::Nothing;
         };
+        if self.rigid.contains(&variable_id) {
+            return Did::Nothing;
+        }
 
         // In case of `$i = $i` the `delete` function should get rid of the equation earlier.
         if self.equations[equation_idx]
@@ -271,6 +465,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_substitution_apply_resolves_chains() {
+        let lang = Language::simple_math();
+        let expression_1 = lang.parse("(+ $0 $1)").unwrap();
+        let expression_2 = lang.parse("(+ (sin $1) 2)").unwrap();
+        let substitution =
+            UnificationProblem::from_equation(Equation::new(expression_1.clone(), expression_2))
+                .solve()
+                .unwrap();
+        assert_eq!(
+            substitution.apply(&expression_1),
+            lang.parse("(+ (sin 2) 2)").unwrap()
+        );
+    }
+
     #[test]
     fn test_unify_no_unification() {
         assert!(solve_unification_problem("(* 2 $0)", "(+ 3 $1)").is_none());
@@ -323,4 +532,58 @@ mod tests {
         assert_eq!(*left_sub.get(0).unwrap(), lang.parse("(* $1 2)").unwrap());
         assert!(right_sub.get(0).is_none());
     }
+
+    #[test]
+    fn test_unify_with_binders() {
+        let lang = Language::default()
+            .add_symbol("lam")
+            .add_symbol("f")
+            .add_symbol("g")
+            .declare_binder("lam");
+        let binders = lang.binders();
+        let solve = |left: &str, right: &str| {
+            let equation = Equation::new(lang.parse(left).unwrap(), lang.parse(right).unwrap());
+            UnificationProblem::from_equation(equation)
+                .with_binders(binders)
+                .solve()
+        };
+
+        let substitution = solve("(lam $0 $1)", "(lam $2 (f $2))").unwrap();
+        let body = substitution.apply(&lang.parse("(lam $0 $1)").unwrap());
+        assert!(body.alpha_eq_binding(&lang.parse("(lam $0 (f $0))").unwrap(), binders));
+
+        // Bound variables are not substituted
+        assert!(solve("(lam $0 $0)", "(lam $1 (f 1))").is_none());
+        assert!(solve("(lam $0 (f $0 $0))", "(lam $1 (f $1 $2))").is_some());
+        // $1 would have to refer to the bound variable outside of its binder
+        assert!(solve("(g $1 (lam $0 $1))", "(g $2 (lam $2 $2))").is_none());
+        // Without binders the bound variables are unified as any other ones
+        assert!(
+            UnificationProblem::from_equation(Equation::new(
+                lang.parse("(lam $0 $0)").unwrap(),
+                lang.parse("(lam $1 (f 1))").unwrap()
+            ))
+            .solve()
+            .is_some()
+        );
+        assert!(
+            UnificationProblem::from_equation(Equation::new(
+                lang.parse("(g $1 (lam $0 $1))").unwrap(),
+                lang.parse("(g $2 (lam $2 $2))").unwrap()
+            ))
+            .solve()
+            .is_some()
+        );
+
+        let unifier = IndependentVarUnifier::unify_with_binders(
+            Equation::new(
+                lang.parse("(lam $0 (f $0 $1))").unwrap(),
+                lang.parse("(lam $0 (f $1 $0))").unwrap(),
+            ),
+            binders,
+        )
+        .unwrap();
+        assert!(unifier.left_substitution().get(1).is_some());
+        assert!(unifier.right_substitution().get(1).is_some());
+    }
 }
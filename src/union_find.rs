@@ -1,5 +1,7 @@
 //! A Union Find implementation (also known as Disjoint-Set Union), used to efficiently manage equivalence classes and perform union and find operations on sets.
-use std::cell::Cell;
+use std::{cell::Cell, cmp::Ordering};
+
+use serde::{Deserialize, Serialize};
 
 pub type SetId = usize;
 
@@ -16,10 +18,17 @@ pub type SetId = usize;
 /// - `parents`: A vector of `Cell<SetId>` representing the parent of each element in the disjoint-set structure.
 ///   Each element either points to its parent or, in the case of a root node, points to itself.
 ///   `Cell` is used for interior mutability.
+/// - `ranks`: Upper bounds on the heights of trees of roots, used by [`UnionFind::union_by_rank`].
+///   Missing entries are zero.
+/// - `undo_log`: Unions since the oldest snapshot, if there is one.
+///   Paths are not compressed while it is kept, as compressed paths could skip over
+///   the redirected roots, see [`UnionFind::snapshot`].
+/// - `stats`: Counters of finds, see [`UnionFindStats`], only kept with the `metrics` feature.
 ///
 /// # Derives
 /// - `Clone`: Allows the `UnionFind` structure to be cloned, creating a deep copy of the internal state.
 /// - `Default`: Provides a default empty initialization for the `UnionFind` structure.
+/// - `Serialize`, `Deserialize`: Stores the parent of every element as a plain list.
 ///
 /// # Example
 /// ```
@@ -29,9 +38,45 @@ pub type SetId = usize;
 /// // Example usage would involve initializing the structure with elements,
 /// // performing union and find operations, etc.
 /// ```
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct UnionFind {
     parents: Vec<Cell<SetId>>,
+    #[serde(skip)]
+    ranks: Vec<u8>,
+    #[serde(skip)]
+    undo_log: Option<Vec<UndoEntry>>,
+    #[cfg(feature = "metrics")]
+    #[serde(skip)]
+    stats: Cell<UnionFindStats>,
+}
+
+/// A union recorded for [`UnionFind::rollback`].
+#[derive(Clone, Copy, Debug)]
+struct UndoEntry {
+    // Root whose parent was changed
+    redirected: SetId,
+    // Root whose rank was increased
+    promoted: Option<SetId>,
+}
+
+/// Counters of the work done by a [`UnionFind`], for deciding whether it is a bottleneck.
+/// They are only recorded with the `metrics` feature, see [`crate::metrics`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnionFindStats {
+    /// Number of calls to [`UnionFind::find`]
+    pub finds: usize,
+    /// Number of parents changed by path compression
+    pub path_compressions: usize,
+    /// Length of the longest path from an element to its root traversed by a find
+    pub max_depth: usize,
+}
+
+/// A state of a [`UnionFind`] which it can be rolled back to, see [`UnionFind::snapshot`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnionFindSnapshot {
+    size: usize,
+    unions: usize,
 }
 
 impl UnionFind {
@@ -42,7 +87,10 @@ impl UnionFind {
             vec.push(Cell::new(i));
         }
 
-        Self { parents: vec }
+        Self {
+            parents: vec,
+            ..Default::default()
+        }
     }
 
     pub fn size(&self) -> usize {
@@ -63,7 +111,74 @@ impl UnionFind {
     pub fn union(&mut self, id_1: SetId, id_2: SetId) {
         let id_1 = self.find(id_1);
         let id_2 = self.find(id_2);
-        self.parents[id_1] = Cell::new(id_2);
+        self.redirect(id_1, id_2, None);
+    }
+
+    /// Unites the sets of `id_1` and `id_2`, making the root of the higher tree
+    /// the canonical version of both, which keeps paths logarithmic. Returns the canonical ID.
+    ///
+    /// Ties are resolved like in [`UnionFind::union`], i.e. in favour of `id_2`.
+    pub fn union_by_rank(&mut self, id_1: SetId, id_2: SetId) -> SetId {
+        let id_1 = self.find(id_1);
+        let id_2 = self.find(id_2);
+        if id_1 == id_2 {
+            return id_2;
+        }
+
+        let (child, root) = match self.rank(id_1).cmp(&self.rank(id_2)) {
+            Ordering::Greater => (id_2, id_1),
+            Ordering::Less => (id_1, id_2),
+            Ordering::Equal => {
+                if self.ranks.len() <= id_2 {
+                    self.ranks.resize(id_2 + 1, 0);
+                }
+                self.ranks[id_2] += 1;
+                self.redirect(id_1, id_2, Some(id_2));
+                return id_2;
+            }
+        };
+        self.redirect(child, root, None);
+        root
+    }
+
+    fn rank(&self, id: SetId) -> u8 {
+        self.ranks.get(id).copied().unwrap_or(0)
+    }
+
+    fn redirect(&mut self, redirected: SetId, root: SetId, promoted: Option<SetId>) {
+        self.parents[redirected].set(root);
+        if let Some(undo_log) = &mut self.undo_log {
+            undo_log.push(UndoEntry {
+                redirected,
+                promoted,
+            });
+        }
+    }
+
+    /// Checks if the parents form a forest, i.e. all of them are elements and following them
+    /// from any element reaches a root. This holds unless the parents come from malformed input.
+    pub fn is_forest(&self) -> bool {
+        // Elements known to reach a root
+        let mut rooted = vec![false; self.size()];
+        for start in 0..self.size() {
+            let mut path = Vec::new();
+            let mut id = start;
+            while !rooted[id] {
+                let parent = self.parent(id);
+                if parent >= self.size() || path.len() > self.size() {
+                    return false;
+                }
+                path.push(id);
+                if parent == id {
+                    break;
+                }
+                id = parent;
+            }
+            for id in path {
+                rooted[id] = true;
+            }
+        }
+        true
     }
 
     pub fn find_no_compress(&self, id: SetId) -> SetId {
@@ -76,15 +191,91 @@ impl UnionFind {
     }
 
     pub fn find(&self, id: SetId) -> SetId {
-        let parent = self.parent(id);
-        if parent == id {
-            return id;
+        let mut id = id;
+        #[cfg(feature = "metrics")]
+        let mut depth = 0;
+        loop {
+            let parent = self.parent(id);
+            if parent == id {
+                break;
+            }
+
+            let grandparent = self.parent(parent);
+            if self.undo_log.is_none() && grandparent != parent {
+                self.parents[id].set(grandparent);
+                #[cfg(feature = "metrics")]
+                self.update_stats(|stats| stats.path_compressions += 1);
+            }
+
+            id = parent;
+            #[cfg(feature = "metrics")]
+            {
+                depth += 1;
+            }
         }
 
-        let grandparent = self.parent(parent);
-        self.parents[id].set(grandparent);
+        #[cfg(feature = "metrics")]
+        self.update_stats(|stats| {
+            stats.finds += 1;
+            stats.max_depth = stats.max_depth.max(depth);
+        });
+        id
+    }
+
+    #[cfg(feature = "metrics")]
+    fn update_stats(&self, update: impl FnOnce(&mut UnionFindStats)) {
+        let mut stats = self.stats.get();
+        update(&mut stats);
+        self.stats.set(stats);
+    }
 
-        self.find(parent)
+    /// Returns the counters of work done since the creation of the union-find
+    /// or the last call to [`UnionFind::reset_stats`], which are all zero
+    /// without the `metrics` feature.
+    pub fn stats(&self) -> UnionFindStats {
+        #[cfg(feature = "metrics")]
+        return self.stats.get();
+        #[cfg(not(feature = "metrics"))]
+        UnionFindStats::default()
+    }
+
+    /// Sets all counters returned by [`UnionFind::stats`] to zero.
+    pub fn reset_stats(&self) {
+        #[cfg(feature = "metrics")]
+        self.stats.take();
+    }
+
+    /// Returns the current state, which can be restored with [`UnionFind::rollback`].
+    ///
+    /// Unions are recorded from the first snapshot until [`UnionFind::forget_snapshots`]
+    /// is called, and paths are not compressed in the meantime.
+    pub fn snapshot(&mut self) -> UnionFindSnapshot {
+        UnionFindSnapshot {
+            size: self.size(),
+            unions: self.undo_log.get_or_insert_with(Vec::new).len(),
+        }
+    }
+
+    /// Undoes all unions and removes all sets added since `snapshot` was taken.
+    /// Later snapshots become invalid.
+    pub fn rollback(&mut self, snapshot: UnionFindSnapshot) {
+        let undo_log = self
+            .undo_log
+            .as_mut()
+            .expect("Rolling back a union-find without snapshots");
+        for entry in undo_log.drain(snapshot.unions..).rev() {
+            self.parents[entry.redirected].set(entry.redirected);
+            if let Some(promoted) = entry.promoted {
+                self.ranks[promoted] -= 1;
+            }
+        }
+        self.parents.truncate(snapshot.size);
+        self.ranks.truncate(snapshot.size);
+    }
+
+    /// Stops recording unions, which makes all snapshots invalid.
+    pub fn forget_snapshots(&mut self) {
+        self.undo_log = None;
     }
 }
 
@@ -146,4 +337,74 @@ mod tests {
         uf.find(0);
         assert_eq!(uf.parents[0].get(), 2);
     }
+
+    #[test]
+    fn rollback() {
+        let mut uf = super::UnionFind::with_size(4);
+        uf.union(0, 1);
+        let snapshot = uf.snapshot();
+        uf.union(1, 2);
+        uf.add();
+        uf.union(4, 3);
+        uf.union(2, 3);
+        assert_eq!(uf.find(0), uf.find(4));
+
+        uf.rollback(snapshot);
+        assert_eq!(uf.size(), 4);
+        assert_eq!(uf.find(0), 1);
+        assert_eq!(uf.find(2), 2);
+        assert_eq!(uf.find(3), 3);
+    }
+
+    #[test]
+    fn union_by_rank() {
+        let mut uf = super::UnionFind::with_size(5);
+        assert_eq!(uf.union_by_rank(0, 1), 1);
+        // The single element is attached below the higher tree
+        assert_eq!(uf.union_by_rank(1, 2), 1);
+        assert_eq!(uf.union_by_rank(3, 4), 4);
+        let snapshot = uf.snapshot();
+        assert_eq!(uf.union_by_rank(4, 0), 1);
+        assert_eq!(uf.find(3), 1);
+
+        uf.rollback(snapshot);
+        assert_eq!(uf.find(3), 4);
+        assert_eq!(uf.union_by_rank(1, 4), 4);
+    }
+
+    #[test]
+    fn stats() {
+        let uf = super::UnionFind::with_size(4);
+        uf.parents[0].set(1);
+        uf.parents[1].set(2);
+        uf.parents[2].set(3);
+
+        assert_eq!(uf.find(0), 3);
+        assert_eq!(uf.find(3), 3);
+        let stats = uf.stats();
+        if crate::metrics::ENABLED {
+            assert_eq!(stats.finds, 2);
+            assert_eq!(stats.path_compressions, 2);
+            assert_eq!(stats.max_depth, 3);
+        } else {
+            assert_eq!(stats, super::UnionFindStats::default());
+        }
+        assert_eq!(uf.parents[0].get(), 2);
+
+        uf.reset_stats();
+        assert_eq!(uf.stats(), super::UnionFindStats::default());
+    }
+
+    #[test]
+    fn forests() {
+        let mut uf = super::UnionFind::with_size(4);
+        uf.union(0, 1);
+        uf.union(1, 2);
+        assert!(uf.is_forest());
+
+        uf.parents[2].set(0);
+        assert!(!uf.is_forest());
+        uf.parents[2].set(4);
+        assert!(!uf.is_forest());
+    }
 }
@@ -3,3 +3,6 @@
 //! This module provides utility functions used throughout the codebase.
 
 pub mod json;
+pub mod proof_export;
+pub mod rng;
+pub mod smtlib;
@@ -0,0 +1,284 @@
+//! Human-readable traces of rewrite sequences.
+//!
+//! A sequence of [`RewriteStep`]s, e.g. found by [`a_star_rewrite`] or [`bfs_rewrite`], is
+//! an equational proof that its start equals its end. This module writes such proofs
+//! step by step, so that results of searches and reachability checks can be verified by hand:
+//! every step lists the intermediate expression, the applied rule and the position
+//! it was applied at. In every expression the subexpression rewritten by the next step
+//! is highlighted, written in brackets in text and drawn in a different color in DOT.
+//! Sequences found by heuristic searches can be much longer than needed,
+//! [`minimize_sequence`] shortens them before they are written.
+//!
+//! [`a_star_rewrite`]: crate::rewriting::search::a_star_rewrite
+//! [`bfs_rewrite`]: crate::rewriting::search::bfs_rewrite
+//! [`minimize_sequence`]: crate::rewriting::search::minimize_sequence
+
+use std::{
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use itertools::Itertools;
+
+use crate::{
+    language::{
+        Language,
+        expression::{AnyExpression, Expression, OwnedPath},
+    },
+    rewriting::{rule::Rule, search::RewriteStep},
+};
+
+const HIGHLIGHT_COLOR: &str = "lightsalmon";
+
+/// Format of a trace written by [`proof_trace`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// A chain of equalities, one expression per line followed by the justifying rule
+    #[default]
+    Text,
+    /// A Markdown table with a row per step
+    Markdown,
+}
+
+/// Writes the proof that `start` equals the last expression of `steps`, with rules
+/// of `steps` indexing `rules`.
+///
+/// For example, rewriting `(+ (* 3 1) 0)` into `3` is written in text as
+///
+/// ```text
+///    (+ [(* 3 1)] 0)
+/// =  [(+ 3 0)]    by (* $0 1) => $0 at 0
+/// =  3    by (+ $0 0) => $0 at root
+/// ```
+pub fn proof_trace(
+    start: &Expression,
+    steps: &[RewriteStep],
+    rules: &[Rule],
+    language: &Language,
+    format: TraceFormat,
+) -> String {
+    let mut out = String::new();
+    if format == TraceFormat::Markdown {
+        writeln!(out, "| Step | Expression | Rule | Position |").unwrap();
+        writeln!(out, "|---|---|---|---|").unwrap();
+    }
+
+    for (index, expression) in expressions(start, steps).enumerate() {
+        let marked = format_marked(expression, next_path(steps, index), language);
+        let justification = index.checked_sub(1).map(|previous| {
+            let position = &steps[previous].position;
+            (
+                rules[position.rule_index].label(language),
+                format_path(&position.path),
+            )
+        });
+
+        match (format, justification) {
+            (TraceFormat::Text, None) => writeln!(out, "   {marked}"),
+            (TraceFormat::Text, Some((rule, path))) => {
+                writeln!(out, "=  {marked}    by {rule} at {path}")
+            }
+            (TraceFormat::Markdown, None) => writeln!(out, "| {index} | `{marked}` | | |"),
+            (TraceFormat::Markdown, Some((rule, path))) => writeln!(
+                out,
+                "| {index} | `{marked}` | `{}` | {path} |",
+                rule.replace('|', "\\|")
+            ),
+        }
+        .unwrap();
+    }
+
+    out
+}
+
+/// Returns a DOT drawing of the tree of every expression of the proof, starting with `start`,
+/// with the subexpression rewritten by the next step highlighted and the rule of the step
+/// leading to the expression as the label of the graph.
+pub fn proof_step_dots(
+    start: &Expression,
+    steps: &[RewriteStep],
+    rules: &[Rule],
+    language: &Language,
+) -> Vec<String> {
+    expressions(start, steps)
+        .enumerate()
+        .map(|(index, expression)| {
+            let label = match index.checked_sub(1) {
+                Some(previous) => {
+                    let position = &steps[previous].position;
+                    format!(
+                        "Step {index}: {} at {}",
+                        rules[position.rule_index].label(language),
+                        format_path(&position.path)
+                    )
+                }
+                None => String::from("Start"),
+            };
+            expression_dot(expression, next_path(steps, index), &label, language)
+        })
+        .collect()
+}
+
+/// Saves the drawings of [`proof_step_dots`] to `directory` as `step-000.dot`,
+/// `step-001.dot`, ..., creating it if needed.
+///
+/// # Returns
+///
+/// Returns the paths of the saved files in the order of the steps
+pub fn save_proof_step_dots(
+    start: &Expression,
+    steps: &[RewriteStep],
+    rules: &[Rule],
+    language: &Language,
+    directory: impl AsRef<Path>,
+) -> std::io::Result<Vec<PathBuf>> {
+    let directory = directory.as_ref();
+    fs::create_dir_all(directory)?;
+    proof_step_dots(start, steps, rules, language)
+        .into_iter()
+        .enumerate()
+        .map(|(index, dot)| {
+            let path = directory.join(format!("step-{index:03}.dot"));
+            fs::write(&path, dot)?;
+            Ok(path)
+        })
+        .collect()
+}
+
+fn expressions<'a>(
+    start: &'a Expression,
+    steps: &'a [RewriteStep],
+) -> impl Iterator<Item = &'a Expression> {
+    std::iter::once(start).chain(steps.iter().map(|step| &step.expression))
+}
+
+/// Path rewritten by the step after the expression with index `index`, if there is one.
+fn next_path(steps: &[RewriteStep], index: usize) -> Option<&[usize]> {
+    steps.get(index).map(|step| step.position.path.0.as_slice())
+}
+
+fn format_path(path: &OwnedPath) -> String {
+    match path.0.is_empty() {
+        true => String::from("root"),
+        false => path.0.iter().join("."),
+    }
+}
+
+/// Writes `expression` with the subexpression at `path` in brackets.
+fn format_marked(expression: &Expression, path: Option<&[usize]>, language: &Language) -> String {
+    match (expression, path) {
+        (_, Some([])) => format!("[{}]", expression.with_language(language)),
+        (Expression::Symbol(symbol), Some([head, rest @ ..])) => {
+            let mut string = format!("({}", language.get_symbol(symbol.id));
+            for (index, child) in symbol.children.iter().enumerate() {
+                let child_path = (index == *head).then_some(rest);
+                string.push(' ');
+                string.push_str(&format_marked(child, child_path, language));
+            }
+            string.push(')');
+            string
+        }
+        _ => expression.with_language(language).to_string(),
+    }
+}
+
+fn expression_dot(
+    expression: &Expression,
+    highlighted: Option<&[usize]>,
+    label: &str,
+    language: &Language,
+) -> String {
+    let mut out = String::new();
+    writeln!(out, "digraph step {{").unwrap();
+    writeln!(out, "  label=\"{}\";", escape(label)).unwrap();
+    writeln!(out, "  labelloc=t;").unwrap();
+    writeln!(out, "  node [shape=box];").unwrap();
+    write_expression_vertices(&mut out, expression, highlighted, language, &mut 0);
+    writeln!(out, "}}").unwrap();
+    out
+}
+
+/// Writes the vertex of `expression` with ID `next_id` and the ones of its subexpressions
+/// in preorder, returning the ID of the vertex.
+fn write_expression_vertices(
+    out: &mut String,
+    expression: &Expression,
+    highlighted: Option<&[usize]>,
+    language: &Language,
+    next_id: &mut usize,
+) -> usize {
+    let id = *next_id;
+    *next_id += 1;
+
+    let label = match expression {
+        Expression::Symbol(symbol) => language.get_symbol(symbol.id).to_string(),
+        _ => expression.with_language(language).to_string(),
+    };
+    let fill = match highlighted {
+        Some([]) => format!(", style=filled, fillcolor={HIGHLIGHT_COLOR:?}"),
+        _ => String::new(),
+    };
+    writeln!(out, "  n{id} [label=\"{}\"{fill}];", escape(&label)).unwrap();
+
+    if let Expression::Symbol(symbol) = expression {
+        for (index, child) in symbol.children.iter().enumerate() {
+            let child_highlighted = match highlighted {
+                Some([]) => Some([].as_slice()),
+                Some([head, rest @ ..]) if *head == index => Some(rest),
+                _ => None,
+            };
+            let child_id =
+                write_expression_vertices(out, child, child_highlighted, language, next_id);
+            writeln!(out, "  n{id} -> n{child_id};").unwrap();
+        }
+    }
+
+    id
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{language::Language, macros::rules, rewriting::search::bfs_rewrite};
+
+    use super::{TraceFormat, proof_step_dots, proof_trace};
+
+    #[test]
+    fn writes_traces_of_rewrite_sequences() {
+        let lang = Language::simple_math();
+        let rules = rules!(lang;
+            "(* $0 1)" => "$0",
+            "(+ $0 0)" => "$0",
+        );
+        let start = lang.parse("(+ (* 3 1) 0)").unwrap();
+        let end = lang.parse("3").unwrap();
+        let mut steps = bfs_rewrite(&start, &end, &rules, 2).unwrap();
+        // Both orders of the two rewrites are shortest, fix the one checked below
+        if steps[0].position.rule_index == 1 {
+            steps = bfs_rewrite(&start, &lang.parse("(+ 3 0)").unwrap(), &rules, 1).unwrap();
+            steps.extend(bfs_rewrite(&steps[0].expression, &end, &rules, 1).unwrap());
+        }
+
+        assert_eq!(
+            proof_trace(&start, &steps, &rules, &lang, TraceFormat::Text),
+            "   (+ [(* 3 1)] 0)\n\
+             =  [(+ 3 0)]    by (* $0 1) => $0 at 0\n\
+             =  3    by (+ $0 0) => $0 at root\n"
+        );
+
+        let markdown = proof_trace(&start, &steps, &rules, &lang, TraceFormat::Markdown);
+        assert_eq!(markdown.lines().count(), 5);
+        assert!(markdown.contains("| 1 | `[(+ 3 0)]` | `(* $0 1) => $0` | 0 |"));
+
+        let dots = proof_step_dots(&start, &steps, &rules, &lang);
+        assert_eq!(dots.len(), 3);
+        // `(* 3 1)` and its children are highlighted in the first drawing
+        assert_eq!(dots[0].matches("fillcolor").count(), 3);
+        assert!(dots[1].contains("label=\"Step 1: (* $0 1) => $0 at 0\""));
+        assert_eq!(dots[2].matches("fillcolor").count(), 0);
+    }
+}
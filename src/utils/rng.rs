@@ -0,0 +1,26 @@
+//! Seedable random number generation.
+//!
+//! Randomized functions of the library take an `impl Rng`, so that their results
+//! can be reproduced by passing a generator seeded with a known value.
+
+use rand::{SeedableRng, rngs::StdRng};
+
+/// Returns a random number generator seeded with `seed`, or with a random seed if it is `None`,
+/// together with the seed it was created with.
+///
+/// Reporting the returned seed makes runs with random seeds reproducible.
+///
+/// # Examples
+///
+/// ```
+/// use rand::Rng;
+/// use verbum::utils::rng::seeded_rng;
+///
+/// let (mut rng, seed) = seeded_rng(None);
+/// let (mut repeated, _) = seeded_rng(Some(seed));
+/// assert_eq!(rng.r#gen::<u64>(), repeated.r#gen::<u64>());
+/// ```
+pub fn seeded_rng(seed: Option<u64>) -> (StdRng, u64) {
+    let seed = seed.unwrap_or_else(rand::random);
+    (StdRng::seed_from_u64(seed), seed)
+}
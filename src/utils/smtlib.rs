@@ -0,0 +1,763 @@
+//! SMT-LIB2 import and export of terms.
+//!
+//! This module converts between [`Expression`]s and the term syntax of SMT-LIB2, so that
+//! equivalence problems from SMT benchmarks can be used for reachability benchmarks
+//! and rewriting systems can be checked by SMT solvers.
+//!
+//! Only the term language without sorts is supported: function applications, constants,
+//! numerals, decimals, hexadecimals, binaries, strings, `let` bindings and `!` annotations.
+//! Symbols of the form `$0`, `$1`, ... are variables, matching the s-expression syntax.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+use crate::language::{
+    Language,
+    expression::{Expression, Literal, VarFreeExpression, VariableId},
+    symbol::{Symbol, SymbolId},
+};
+use crate::rewriting::rule::Rule;
+
+/// Errors of SMT-LIB2 import.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SmtError {
+    /// The input is not a well-formed s-expression at the given byte offset
+    Syntax { position: usize },
+    /// A function or constant is neither mapped to a symbol nor bound by `let`
+    UnknownSymbol(String),
+    /// A construct outside of the supported subset of SMT-LIB2
+    Unsupported(String),
+}
+
+impl fmt::Display for SmtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SmtError::Syntax { position } => {
+                write!(f, "SMT-LIB syntax error at position {position}")
+            }
+            SmtError::UnknownSymbol(name) => write!(f, "Unknown SMT-LIB symbol {name}"),
+            SmtError::Unsupported(construct) => {
+                write!(f, "Unsupported SMT-LIB construct {construct}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SmtError {}
+
+/// An SMT-LIB2 s-expression.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum SExpr {
+    Atom(String),
+    Str(String),
+    List(Vec<SExpr>),
+}
+
+impl fmt::Display for SExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SExpr::Atom(atom) => write!(f, "{atom}"),
+            SExpr::Str(string) => write!(f, "\"{}\"", string.replace('"', "\"\"")),
+            SExpr::List(items) => {
+                write!(f, "(")?;
+                for (index, item) in items.iter().enumerate() {
+                    let separator = if index == 0 { "" } else { " " };
+                    write!(f, "{separator}{item}")?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+/// Reads all s-expressions of `input`, skipping comments.
+fn read_sexprs(input: &str) -> Result<Vec<SExpr>, SmtError> {
+    let mut stack: Vec<Vec<SExpr>> = vec![Vec::new()];
+    let mut characters = input.char_indices().peekable();
+
+    while let Some((position, character)) = characters.next() {
+        match character {
+            '(' => stack.push(Vec::new()),
+            ')' => {
+                let list = stack.pop().filter(|_| !stack.is_empty());
+                let Some(list) = list else {
+                    return Err(SmtError::Syntax { position });
+                };
+                stack.last_mut().unwrap().push(SExpr::List(list));
+            }
+            ';' => while characters.next_if(|(_, c)| *c != '\n').is_some() {},
+            '"' => {
+                let mut string = String::new();
+                loop {
+                    match characters.next() {
+                        Some((_, '"')) if characters.next_if(|(_, c)| *c == '"').is_some() => {
+                            string.push('"')
+                        }
+                        Some((_, '"')) => break,
+                        Some((_, c)) => string.push(c),
+                        None => return Err(SmtError::Syntax { position }),
+                    }
+                }
+                stack.last_mut().unwrap().push(SExpr::Str(string));
+            }
+            '|' => {
+                let mut symbol = String::new();
+                loop {
+                    match characters.next() {
+                        Some((_, '|')) => break,
+                        Some((_, c)) => symbol.push(c),
+                        None => return Err(SmtError::Syntax { position }),
+                    }
+                }
+                stack.last_mut().unwrap().push(SExpr::Atom(symbol));
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut atom = String::from(c);
+                while let Some((_, c)) = characters.next_if(|(_, c)| {
+                    !c.is_whitespace() && !matches!(c, '(' | ')' | ';' | '"' | '|')
+                }) {
+                    atom.push(c);
+                }
+                stack.last_mut().unwrap().push(SExpr::Atom(atom));
+            }
+        }
+    }
+
+    if stack.len() != 1 {
+        return Err(SmtError::Syntax {
+            position: input.len(),
+        });
+    }
+    Ok(stack.pop().unwrap())
+}
+
+/// Parses an SMT-LIB2 constant, or returns `None` if `atom` is not one.
+fn parse_constant(atom: &str) -> Option<Result<Literal, SmtError>> {
+    let overflow = || SmtError::Unsupported(format!("constant {atom} out of range"));
+    if let Some(hex) = atom.strip_prefix("#x") {
+        return Some(
+            u64::from_str_radix(hex, 16)
+                .map(Literal::UInt)
+                .map_err(|_| overflow()),
+        );
+    }
+    if let Some(binary) = atom.strip_prefix("#b") {
+        return Some(
+            u64::from_str_radix(binary, 2)
+                .map(Literal::UInt)
+                .map_err(|_| overflow()),
+        );
+    }
+    if !atom.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    Some(if atom.contains('.') {
+        atom.parse()
+            .map(Literal::float)
+            .map_err(|_| SmtError::Unsupported(String::from(atom)))
+    } else {
+        atom.parse().map(Literal::Int).map_err(|_| overflow())
+    })
+}
+
+/// Returns the ID of a variable written as `$n`.
+fn variable_id(name: &str) -> Option<VariableId> {
+    name.strip_prefix('$')?.parse().ok()
+}
+
+/// Mapping of SMT-LIB2 function names to symbols of a language, used for importing terms.
+#[derive(Clone, Debug)]
+pub struct SmtSymbols<'l> {
+    language: &'l Language,
+    functions: HashMap<String, SymbolId>,
+}
+
+impl<'l> SmtSymbols<'l> {
+    /// Creates a mapping in which every symbol of `language` has its own name.
+    pub fn new(language: &'l Language) -> Self {
+        let functions = (0..language.symbol_count())
+            .map(|id| (String::from(language.get_symbol(id)), id))
+            .collect();
+        Self {
+            language,
+            functions,
+        }
+    }
+
+    /// Maps the SMT-LIB2 function `name` to the symbol `symbol` of the language,
+    /// e.g. `bvadd` to `+`.
+    pub fn with_function(mut self, name: &str, symbol: SymbolId) -> Self {
+        self.functions.insert(String::from(name), symbol);
+        self
+    }
+
+    /// Parses a single SMT-LIB2 term.
+    pub fn parse_term(&self, term: &str) -> Result<Expression, SmtError> {
+        let mut sexprs = read_sexprs(term)?;
+        if sexprs.len() != 1 {
+            return Err(SmtError::Syntax {
+                position: term.len(),
+            });
+        }
+        self.term(&sexprs.remove(0), &HashMap::new())
+    }
+
+    /// Parses a single SMT-LIB2 term without variables.
+    pub fn parse_ground_term(&self, term: &str) -> Result<VarFreeExpression, SmtError> {
+        self.parse_term(term)?
+            .without_variables()
+            .ok_or_else(|| SmtError::Unsupported(format!("variables in ground term {term}")))
+    }
+
+    fn symbol(&self, name: &str, children: Vec<Expression>) -> Result<Expression, SmtError> {
+        let id = *self
+            .functions
+            .get(name)
+            .ok_or_else(|| SmtError::UnknownSymbol(String::from(name)))?;
+        if let Some(expected) = self.language.arities().and_then(|arities| arities.get(id))
+            && !expected.contains(&children.len())
+        {
+            return Err(SmtError::Unsupported(format!(
+                "{name} applied to {} arguments",
+                children.len()
+            )));
+        }
+        Ok(Expression::Symbol(Symbol { id, children }))
+    }
+
+    fn term(
+        &self,
+        sexpr: &SExpr,
+        bindings: &HashMap<String, Expression>,
+    ) -> Result<Expression, SmtError> {
+        match sexpr {
+            SExpr::Str(string) => Ok(Expression::Literal(Literal::Str(string.clone()))),
+            SExpr::Atom(atom) => {
+                if let Some(constant) = parse_constant(atom) {
+                    return constant.map(Expression::Literal);
+                }
+                if let Some(bound) = bindings.get(atom) {
+                    return Ok(bound.clone());
+                }
+                if let Some(id) = variable_id(atom) {
+                    return Ok(Expression::Variable(id));
+                }
+                self.symbol(atom, Vec::new())
+            }
+            SExpr::List(items) => match items.as_slice() {
+                [SExpr::Atom(head), bound, body] if head == "let" => {
+                    let SExpr::List(bound) = bound else {
+                        return Err(SmtError::Unsupported(sexpr.to_string()));
+                    };
+                    // Bindings of a single `let` are parallel, so they are all evaluated first
+                    let mut inner = bindings.clone();
+                    for binding in bound {
+                        let SExpr::List(binding) = binding else {
+                            return Err(SmtError::Unsupported(binding.to_string()));
+                        };
+                        let [SExpr::Atom(name), value] = binding.as_slice() else {
+                            return Err(SmtError::Unsupported(sexpr.to_string()));
+                        };
+                        inner.insert(name.clone(), self.term(value, bindings)?);
+                    }
+                    self.term(body, &inner)
+                }
+                [SExpr::Atom(head), term, ..] if head == "!" => self.term(term, bindings),
+                [SExpr::Atom(head), SExpr::Atom(constant)] if head == "-" => {
+                    match parse_constant(constant) {
+                        Some(Ok(Literal::Int(value))) => {
+                            Ok(Expression::Literal(Literal::Int(-value)))
+                        }
+                        Some(Ok(Literal::Float(value))) => {
+                            Ok(Expression::Literal(Literal::float(-value.0)))
+                        }
+                        _ => {
+                            let child = self.term(&items[1], bindings)?;
+                            self.symbol(head, vec![child])
+                        }
+                    }
+                }
+                [SExpr::Atom(head), arguments @ ..]
+                    if !matches!(head.as_str(), "as" | "_" | "forall" | "exists" | "match") =>
+                {
+                    let children = arguments
+                        .iter()
+                        .map(|argument| self.term(argument, bindings))
+                        .collect::<Result<_, _>>()?;
+                    self.symbol(head, children)
+                }
+                _ => Err(SmtError::Unsupported(sexpr.to_string())),
+            },
+        }
+    }
+}
+
+/// Result of importing an SMT-LIB2 script with [`import_script`].
+#[derive(Clone, Debug)]
+pub struct SmtImport {
+    /// The language extended with the functions declared by the script
+    pub language: Language,
+    /// Pairs of expressions asserted to be equal or different, in the order of assertions
+    pub equivalences: Vec<(VarFreeExpression, VarFreeExpression)>,
+}
+
+/// Imports equivalence problems from an SMT-LIB2 script.
+///
+/// Functions and constants declared with `declare-fun` and `declare-const` which are missing
+/// from `language` are added to it. Every assertion has to be an equality or disequality
+/// of two terms, possibly negated, e.g. `(assert (not (= (f x) (g x))))`, and becomes
+/// an equivalence problem. Commands other than declarations and assertions are ignored.
+///
+/// # Arguments
+///
+/// * `script` - The SMT-LIB2 script
+/// * `language` - The language to which SMT-LIB2 functions are mapped by name
+pub fn import_script(script: &str, mut language: Language) -> Result<SmtImport, SmtError> {
+    let commands = read_sexprs(script)?;
+    let mut assertions = Vec::new();
+
+    for command in &commands {
+        let SExpr::List(items) = command else {
+            return Err(SmtError::Unsupported(command.to_string()));
+        };
+        match items.as_slice() {
+            [SExpr::Atom(head), SExpr::Atom(name), ..]
+                if (head == "declare-fun" || head == "declare-const")
+                    && language.try_get_id(name).is_none() =>
+            {
+                language = language.add_symbol(name);
+            }
+            [SExpr::Atom(head), assertion] if head == "assert" => assertions.push(assertion),
+            _ => {}
+        }
+    }
+
+    let symbols = SmtSymbols::new(&language);
+    let empty = HashMap::new();
+    let mut equivalences = Vec::new();
+    for assertion in assertions {
+        let mut assertion = assertion;
+        while let SExpr::List(items) = assertion
+            && let [SExpr::Atom(head), inner] = items.as_slice()
+            && head == "not"
+        {
+            assertion = inner;
+        }
+
+        let SExpr::List(items) = assertion else {
+            return Err(SmtError::Unsupported(assertion.to_string()));
+        };
+        let [SExpr::Atom(head), left, right] = items.as_slice() else {
+            return Err(SmtError::Unsupported(assertion.to_string()));
+        };
+        if head != "=" && head != "distinct" {
+            return Err(SmtError::Unsupported(assertion.to_string()));
+        }
+
+        let ground = |sexpr: &SExpr| {
+            symbols
+                .term(sexpr, &empty)?
+                .without_variables()
+                .ok_or_else(|| SmtError::Unsupported(format!("variables in {sexpr}")))
+        };
+        equivalences.push((ground(left)?, ground(right)?));
+    }
+
+    Ok(SmtImport {
+        language,
+        equivalences,
+    })
+}
+
+/// Returns `true` if `name` can be written as an SMT-LIB2 symbol without quoting.
+fn is_simple_symbol(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "~!@$%^&*_-+=<>.?/".contains(c))
+}
+
+fn symbol_to_smtlib(name: &str) -> String {
+    if is_simple_symbol(name) {
+        String::from(name)
+    } else {
+        format!("|{name}|")
+    }
+}
+
+fn literal_to_smtlib(literal: &Literal) -> String {
+    match literal {
+        Literal::UInt(value) => value.to_string(),
+        Literal::Int(value) if *value < 0 => format!("(- {})", value.unsigned_abs()),
+        Literal::Int(value) => value.to_string(),
+        Literal::Float(value) if value.0 < 0.0 => format!("(- {})", Literal::float(-value.0)),
+        Literal::Float(_) => literal.to_string(),
+        Literal::Rational(numerator, denominator) => {
+            let numerator = literal_to_smtlib(&Literal::Int(*numerator));
+            format!("(/ {numerator} {denominator})")
+        }
+        Literal::Str(string) => format!("\"{}\"", string.replace('"', "\"\"")),
+    }
+}
+
+/// Writes `expression` as an SMT-LIB2 term. Variables are written as `$0`, `$1`, ...
+pub fn term_to_smtlib(expression: &Expression, language: &Language) -> String {
+    match expression {
+        Expression::Literal(literal) => literal_to_smtlib(literal),
+        Expression::Variable(id) => Expression::variable_name(*id),
+        Expression::Symbol(symbol) if symbol.children.is_empty() => {
+            symbol_to_smtlib(language.get_symbol(symbol.id))
+        }
+        Expression::Symbol(symbol) => {
+            let mut term = format!("({}", symbol_to_smtlib(language.get_symbol(symbol.id)));
+            for child in &symbol.children {
+                term.push(' ');
+                term.push_str(&term_to_smtlib(child, language));
+            }
+            term.push(')');
+            term
+        }
+    }
+}
+
+/// Writes `rule` as a universally quantified SMT-LIB2 equation, with all variables of sort `sort`.
+pub fn rule_to_smtlib(rule: &Rule, language: &Language, sort: &str) -> String {
+    let equation = format!(
+        "(= {} {})",
+        term_to_smtlib(rule.from(), language),
+        term_to_smtlib(rule.to(), language)
+    );
+    quantify(equation, rule, sort)
+}
+
+/// Universally quantifies the variables of `rule` in `equation`, with all of them of sort `sort`.
+fn quantify(equation: String, rule: &Rule, sort: &str) -> String {
+    let mut variables = rule.from().variables_vec();
+    variables.extend(rule.to().variables_vec());
+    variables.sort();
+    variables.dedup();
+    if variables.is_empty() {
+        return equation;
+    }
+
+    let bound: Vec<_> = variables
+        .into_iter()
+        .map(|id| format!("({} {sort})", Expression::variable_name(id)))
+        .collect();
+    format!("(forall ({}) {equation})", bound.join(" "))
+}
+
+/// Names of the uninterpreted functions and constants of an [`equivalence_script`].
+///
+/// A symbol occurring with a single number of children keeps its name, while a symbol
+/// occurring with several gets one function per number of children, named `name@arity`.
+/// Literals become constants named `literal0`, `literal1`, ..., since numerals
+/// and strings have sorts of their own in SMT-LIB2.
+struct ScriptNames<'l> {
+    language: &'l Language,
+    functions: BTreeMap<(SymbolId, usize), String>,
+    literals: Vec<(Literal, String)>,
+}
+
+impl<'l> ScriptNames<'l> {
+    fn new<'e>(
+        expressions: impl IntoIterator<Item = &'e Expression>,
+        language: &'l Language,
+    ) -> Self {
+        let mut occurrences = BTreeSet::new();
+        let mut literals = Vec::new();
+        for expression in expressions {
+            Self::collect(expression, &mut occurrences, &mut literals);
+        }
+
+        let mut names = Self {
+            language,
+            functions: BTreeMap::new(),
+            literals: Vec::new(),
+        };
+        for &(id, arity) in &occurrences {
+            let name = language.get_symbol(id);
+            let overloaded = occurrences.range((id, 0)..=(id, usize::MAX)).count() > 1;
+            let name = if overloaded {
+                symbol_to_smtlib(&format!("{name}@{arity}"))
+            } else {
+                symbol_to_smtlib(name)
+            };
+            names.functions.insert((id, arity), name);
+        }
+
+        let mut index = 0;
+        for literal in literals {
+            let name = loop {
+                let name = format!("literal{index}");
+                index += 1;
+                if language.try_get_id(&name).is_none() {
+                    break name;
+                }
+            };
+            names.literals.push((literal, name));
+        }
+        names
+    }
+
+    fn collect(
+        expression: &Expression,
+        occurrences: &mut BTreeSet<(SymbolId, usize)>,
+        literals: &mut Vec<Literal>,
+    ) {
+        match expression {
+            Expression::Symbol(symbol) => {
+                occurrences.insert((symbol.id, symbol.children.len()));
+                for child in &symbol.children {
+                    Self::collect(child, occurrences, literals);
+                }
+            }
+            Expression::Literal(literal) => {
+                if !literals.contains(literal) {
+                    literals.push(literal.clone());
+                }
+            }
+            Expression::Variable(_) => {}
+        }
+    }
+
+    fn term(&self, expression: &Expression) -> String {
+        match expression {
+            Expression::Literal(literal) => self
+                .literals
+                .iter()
+                .find(|(other, _)| other == literal)
+                .map(|(_, name)| name.clone())
+                .unwrap_or_else(|| literal_to_smtlib(literal)),
+            Expression::Variable(id) => Expression::variable_name(*id),
+            Expression::Symbol(symbol) => {
+                let name = match self.functions.get(&(symbol.id, symbol.children.len())) {
+                    Some(name) => name.clone(),
+                    None => symbol_to_smtlib(self.language.get_symbol(symbol.id)),
+                };
+                if symbol.children.is_empty() {
+                    return name;
+                }
+                let mut term = format!("({name}");
+                for child in &symbol.children {
+                    term.push(' ');
+                    term.push_str(&self.term(child));
+                }
+                term.push(')');
+                term
+            }
+        }
+    }
+}
+
+/// Writes an SMT-LIB2 script asserting `rules` and asking whether `left` and `right`
+/// are equal under them, which holds if the script is unsatisfiable.
+///
+/// Every symbol is declared as an uninterpreted function over the uninterpreted sort `sort`,
+/// with the number of children it has in the rules and terms. Symbols occurring with several
+/// numbers of children get one function per number, named `name@arity`. Literals are
+/// declared as constants `literal0`, `literal1`, ... of that sort, since numerals and strings
+/// have sorts of their own in SMT-LIB2.
+pub fn equivalence_script(
+    rules: &[Rule],
+    left: &VarFreeExpression,
+    right: &VarFreeExpression,
+    language: &Language,
+    sort: &str,
+) -> String {
+    let left = left.to_expression();
+    let right = right.to_expression();
+    let names = ScriptNames::new(
+        rules
+            .iter()
+            .flat_map(|rule| [rule.from(), rule.to()])
+            .chain([&left, &right]),
+        language,
+    );
+
+    let mut script = format!("(declare-sort {sort} 0)\n");
+    for (&(_, arity), name) in &names.functions {
+        let arguments = vec![sort; arity].join(" ");
+        script.push_str(&format!("(declare-fun {name} ({arguments}) {sort})\n"));
+    }
+    for (literal, name) in &names.literals {
+        script.push_str(&format!(
+            "(declare-const {name} {sort}) ; {}\n",
+            literal_to_smtlib(literal).replace('\n', " ")
+        ));
+    }
+    for rule in rules {
+        let equation = format!("(= {} {})", names.term(rule.from()), names.term(rule.to()));
+        script.push_str(&format!("(assert {})\n", quantify(equation, rule, sort)));
+    }
+    script.push_str(&format!(
+        "(assert (not (= {} {})))\n(check-sat)\n",
+        names.term(&left),
+        names.term(&right)
+    ));
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SmtError, SmtSymbols, equivalence_script, import_script, rule_to_smtlib};
+    use crate::language::Language;
+    use crate::rewriting::rule::Rule;
+
+    #[test]
+    fn parse_terms() {
+        let lang = Language::simple_math();
+        let symbols = SmtSymbols::new(&lang).with_function("bvmul", lang.get_id("*"));
+
+        let parse = |term| symbols.parse_term(term).unwrap();
+        assert_eq!(
+            parse("(+ $0 (sin 1))"),
+            lang.parse("(+ $0 (sin 1))").unwrap()
+        );
+        assert_eq!(
+            parse("(bvmul #x0F #b11)"),
+            lang.parse("(* 15u 3u)").unwrap()
+        );
+        assert_eq!(
+            parse("(- (- 5) (- 2.5))"),
+            lang.parse("(- -5 -2.5)").unwrap()
+        );
+        assert_eq!(parse("(- $0)"), lang.parse("(- $0)").unwrap());
+        assert_eq!(
+            parse("(cos \"say \"\"hi\"\"\") ; comment"),
+            lang.parse("(cos \"say \\\"hi\\\"\")").unwrap()
+        );
+        assert_eq!(
+            parse("(let ((a 1) (b (sin 2))) (let ((a b) (b a)) (! (* a b) :named t)))"),
+            lang.parse("(* (sin 2) 1)").unwrap()
+        );
+
+        assert_eq!(
+            symbols.parse_term("(f 1)"),
+            Err(SmtError::UnknownSymbol(String::from("f")))
+        );
+        assert!(matches!(
+            symbols.parse_term("((_ extract 1 0) 1)"),
+            Err(SmtError::Unsupported(_))
+        ));
+        assert_eq!(
+            symbols.parse_term("(+ 1"),
+            Err(SmtError::Syntax { position: 4 })
+        );
+        assert!(symbols.parse_ground_term("(+ $0 1)").is_err());
+    }
+
+    #[test]
+    fn import_equivalences() {
+        let script = "
+            (set-logic QF_UF)
+            (declare-sort S 0)
+            (declare-const x S)
+            (declare-fun |f| (S S) S)
+            (declare-fun sin (S) S)
+            (assert (not (= (f x 1) (+ x 0))))
+            (assert (distinct x (sin x)))
+            (check-sat)
+        ";
+        let import = import_script(script, Language::simple_math()).unwrap();
+        let lang = &import.language;
+
+        assert_eq!(
+            lang.symbol_count(),
+            Language::simple_math().symbol_count() + 2
+        );
+        assert_eq!(
+            import.equivalences,
+            vec![
+                (
+                    lang.parse_no_vars("(f (x) 1)").unwrap(),
+                    lang.parse_no_vars("(+ (x) 0)").unwrap()
+                ),
+                (
+                    lang.parse_no_vars("(x)").unwrap(),
+                    lang.parse_no_vars("(sin (x))").unwrap()
+                ),
+            ]
+        );
+
+        assert!(import_script("(assert (< 1 2))", Language::simple_math()).is_err());
+    }
+
+    #[test]
+    fn export_and_reimport() {
+        let lang = Language::simple_math();
+        let rule = Rule::from_strings("(+ $1 (* $0 -3/4))", "(sin \"a\")", &lang);
+        assert_eq!(
+            rule_to_smtlib(&rule, &lang, "Real"),
+            "(forall (($0 Real) ($1 Real)) (= (+ $1 (* $0 (/ (- 3) 4))) (sin \"a\")))"
+        );
+
+        let ground = Rule::from_strings("(- -2 1.5)", "(cos -0.5)", &lang);
+        let exported = rule_to_smtlib(&ground, &lang, "Real");
+        assert_eq!(exported, "(= (- (- 2) 1.5) (cos (- 0.5)))");
+        let import = import_script(&format!("(assert {exported})"), lang.clone()).unwrap();
+        assert_eq!(
+            import.equivalences,
+            vec![(
+                lang.parse_no_vars("(- -2 1.5)").unwrap(),
+                lang.parse_no_vars("(cos -0.5)").unwrap()
+            )]
+        );
+    }
+
+    #[test]
+    fn equivalence_script_declarations() {
+        let lang = Language::simple_math();
+        let rules = vec![Rule::from_strings("(+ $0 $1)", "(+ $1 $0)", &lang)];
+        let script = equivalence_script(
+            &rules,
+            &lang.parse_no_vars("(+ 1 (sin 2))").unwrap(),
+            &lang.parse_no_vars("(+ (sin 2) 1)").unwrap(),
+            &lang,
+            "S",
+        );
+        assert_eq!(
+            script,
+            "(declare-sort S 0)\n\
+             (declare-fun + (S S) S)\n\
+             (declare-fun sin (S) S)\n\
+             (declare-const literal0 S) ; 1\n\
+             (declare-const literal1 S) ; 2\n\
+             (assert (forall (($0 S) ($1 S)) (= (+ $0 $1) (+ $1 $0))))\n\
+             (assert (not (= (+ literal0 (sin literal1)) (+ (sin literal1) literal0))))\n\
+             (check-sat)\n"
+        );
+    }
+
+    #[test]
+    fn equivalence_script_overloads_variadic_symbols() {
+        let lang = Language::simple_math().add_symbol("literal0");
+        let rules = vec![Rule::from_strings(
+            "(+ $0 $1 $2)",
+            "(+ $0 (+ $1 $2))",
+            &lang,
+        )];
+        let script = equivalence_script(
+            &rules,
+            &lang.parse_no_vars("(+ (literal0) 1 -1)").unwrap(),
+            &lang.parse_no_vars("(+ (literal0) 0)").unwrap(),
+            &lang,
+            "S",
+        );
+        assert_eq!(
+            script,
+            "(declare-sort S 0)\n\
+             (declare-fun +@2 (S S) S)\n\
+             (declare-fun +@3 (S S S) S)\n\
+             (declare-fun literal0 () S)\n\
+             (declare-const literal1 S) ; 1\n\
+             (declare-const literal2 S) ; (- 1)\n\
+             (declare-const literal3 S) ; 0\n\
+             (assert (forall (($0 S) ($1 S) ($2 S)) (= (+@3 $0 $1 $2) (+@2 $0 (+@2 $1 $2)))))\n\
+             (assert (not (= (+@3 literal0 literal1 literal2) (+@2 literal0 literal3))))\n\
+             (check-sat)\n"
+        );
+    }
+}